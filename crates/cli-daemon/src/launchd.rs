@@ -0,0 +1,109 @@
+//! launchd job definition for running the daemon on macOS
+//!
+//! launchd takes the place of the systemd unit `scripts/deploy.sh` installs:
+//! `--print-launchd-plist` writes a property list for the running binary to
+//! save under `~/Library/LaunchAgents` (or `/Library/LaunchDaemons` to run it
+//! without a logged-in user).
+
+use std::path::Path;
+
+/// Label launchd knows the job by, also the plist's file name stem
+pub const LAUNCHD_LABEL: &str = "local.av1-super-daemon";
+
+/// launchd starts jobs with a bare PATH; av1an and ffmpeg usually live in
+/// Homebrew's prefix
+const LAUNCHD_PATH: &str = "/opt/homebrew/bin:/usr/local/bin:/usr/bin:/bin:/usr/sbin:/sbin";
+
+/// Escapes text for use inside a plist `<string>`.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Renders the plist that runs `program` with the given config and temp
+/// directory, restarting it if it exits with an error.
+///
+/// Output and errors both go to `log_path`.
+pub fn launchd_plist(
+    program: &Path,
+    config: Option<&Path>,
+    temp_dir: &Path,
+    log_path: &Path,
+) -> String {
+    let mut args = vec![program.display().to_string()];
+    if let Some(config) = config {
+        args.push("--config".to_string());
+        args.push(config.display().to_string());
+    }
+    args.push("--temp-dir".to_string());
+    args.push(temp_dir.display().to_string());
+    let args: String = args
+        .iter()
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(arg)))
+        .collect();
+    let log = xml_escape(&log_path.display().to_string());
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCHD_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+{args}    </array>
+    <key>EnvironmentVariables</key>
+    <dict>
+        <key>PATH</key>
+        <string>{LAUNCHD_PATH}</string>
+    </dict>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ThrottleInterval</key>
+    <integer>5</integer>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launchd_plist_lists_arguments_in_order() {
+        let plist = launchd_plist(
+            Path::new("/opt/homebrew/bin/av1-super-daemon"),
+            Some(Path::new("/Users/me/R&D/config.toml")),
+            Path::new("/tmp/av1-super-daemon"),
+            Path::new("/Users/me/Library/Logs/av1-super-daemon.log"),
+        );
+        let expected: String = [
+            "/opt/homebrew/bin/av1-super-daemon",
+            "--config",
+            "/Users/me/R&amp;D/config.toml",
+            "--temp-dir",
+            "/tmp/av1-super-daemon",
+        ]
+        .iter()
+        .map(|arg| format!("        <string>{}</string>\n", arg))
+        .collect();
+        assert!(plist.contains(&expected), "{}", plist);
+        assert!(plist.contains("<string>local.av1-super-daemon</string>"));
+        assert_eq!(plist.matches("av1-super-daemon.log</string>").count(), 2);
+
+        let without_config =
+            launchd_plist(Path::new("/bin/d"), None, Path::new("/tmp"), Path::new("/tmp/log"));
+        assert!(!without_config.contains("--config"));
+    }
+}
@@ -5,66 +5,470 @@
 //! # Requirements
 //! - 8.1: Parse config.toml for cpu, av1an, and encoder_safety sections
 
-use av1_super_daemon::{Config, Daemon};
-use clap::Parser;
-use std::path::PathBuf;
+use av1_super_daemon::config::{default_config_toml, ConfigError, ConfigProfile};
+use av1_super_daemon::encode::{CRF_RANGE, PRESET_RANGE};
+use av1_super_daemon::skip_marker::write_force_marker;
+use av1_super_daemon::{
+    import_paths, log_info, log_warn, parse_path_list, reset_path, Config, Daemon, JobOverrides,
+};
+use clap::{Parser, Subcommand};
+use launchd::launchd_plist;
+use std::io::Read;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
+mod launchd;
+
 /// AV1 Super Daemon - Automated media encoding with film-grain-tuned AV1
 #[derive(Parser, Debug)]
 #[command(name = "av1-super-daemon")]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to the configuration file (config.toml)
-    #[arg(short, long, default_value = "config.toml")]
-    config: PathBuf,
+    /// Configuration file to use instead of the search path
+    /// (/etc/av1-super-daemon, $XDG_CONFIG_HOME/av1-super-daemon, then the
+    /// current directory, later files overriding earlier ones)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Base directory for temporary chunk files [default:
+    /// /tmp/av1-super-daemon, or `chunks` under paths.temp_output_dir with
+    /// --container]
+    #[arg(short, long)]
+    temp_dir: Option<PathBuf>,
+
+    /// Built-in preset to layer the config over (archive, balanced,
+    /// space-saver, anime); replaces any `profile` set in the config file
+    #[arg(long)]
+    profile: Option<ConfigProfile>,
+
+    /// Print a fully commented config.toml with every default and exit
+    #[arg(long)]
+    print_default_config: bool,
 
-    /// Base directory for temporary chunk files
-    #[arg(short, long, default_value = "/tmp/av1-super-daemon")]
-    temp_dir: PathBuf,
+    /// Print a launchd plist that runs this binary with the given --config
+    /// and --temp-dir, for macOS, and exit
+    #[arg(long)]
+    print_launchd_plist: bool,
+
+    /// Encode into temp without touching the libraries: no skip markers,
+    /// sidecars, or replacements (same as `read_only = true`)
+    #[arg(long)]
+    read_only: bool,
+
+    /// Run as the single process of a container: config comes only from
+    /// `AV1SD_` environment variables, logs are JSON lines on stdout, chunks
+    /// go under paths.temp_output_dir, and the API listens on 0.0.0.0:$PORT
+    /// (7878 if unset)
+    #[arg(long, conflicts_with = "config")]
+    container: bool,
 
     /// Skip startup checks (av1an, ffmpeg version). For testing only.
     #[arg(long, default_value = "false")]
     skip_checks: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// One-off maintenance commands; without one the daemon runs normally
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Clear the skip marker, why sidecars, and finished jobs for a file so it is retried
+    Requeue {
+        /// Media file to retry
+        path: PathBuf,
+        /// Write an `.av1force` marker so the file is encoded even if it is
+        /// already AV1 or fails the size gates
+        #[arg(long)]
+        force: bool,
+    },
+    /// Queue an explicit list of files instead of scanning the libraries
+    ///
+    /// Paths are read one per line; blank lines and `#` comments are ignored.
+    /// The daemon keeps running to encode the imported files.
+    Import {
+        /// File holding the path list; reads stdin if omitted or `-`
+        file: Option<PathBuf>,
+        #[command(flatten)]
+        overrides: OverrideArgs,
+    },
+    /// Queue a single file, optionally with its own encode settings
+    ///
+    /// Like `import` with one path. The daemon keeps running to encode it.
+    Add {
+        /// Media file to encode
+        path: PathBuf,
+        #[command(flatten)]
+        overrides: OverrideArgs,
+    },
+    /// Write a fully commented default config to the `--config` path
+    /// (`./config.toml` if not given)
+    Init {
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Encode settings for the queued jobs only, replacing the configured ones
+#[derive(clap::Args, Debug, Default)]
+struct OverrideArgs {
+    /// SVT-AV1 CRF for these jobs
+    #[arg(long, value_parser = clap::value_parser!(u8).range(crf_range()))]
+    crf: Option<u8>,
+    /// SVT-AV1 preset for these jobs
+    #[arg(long, value_parser = clap::value_parser!(u8).range(preset_range()))]
+    preset: Option<u8>,
+    /// Av1an workers for these jobs instead of the planned count
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    workers: Option<u32>,
+    /// Keep the encodes whatever their size compared to the originals
+    #[arg(long)]
+    skip_size_gate: bool,
+}
+
+/// [`CRF_RANGE`] widened to the integer type clap ranges take
+fn crf_range() -> RangeInclusive<i64> {
+    i64::from(*CRF_RANGE.start())..=i64::from(*CRF_RANGE.end())
+}
+
+/// [`PRESET_RANGE`] widened to the integer type clap ranges take
+fn preset_range() -> RangeInclusive<i64> {
+    i64::from(*PRESET_RANGE.start())..=i64::from(*PRESET_RANGE.end())
+}
+
+impl OverrideArgs {
+    fn to_overrides(&self) -> JobOverrides {
+        JobOverrides {
+            crf: self.crf,
+            preset: self.preset,
+            workers: self.workers,
+            skip_size_gate: self.skip_size_gate,
+        }
+    }
+}
+
+/// Writes the commented default config to `path`, refusing to clobber an
+/// existing file unless `force` is set.
+fn init_config(path: &Path, force: bool) -> ExitCode {
+    if path.exists() && !force {
+        log_warn!("{} already exists; pass --force to overwrite it", path.display());
+        return ExitCode::FAILURE;
+    }
+    match std::fs::write(path, default_config_toml()) {
+        Ok(()) => {
+            log_info!("Wrote default config to {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log_warn!("Failed to write {}: {}", path.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Reads the newline-delimited path list for `import`.
+fn read_path_list(file: Option<&Path>) -> std::io::Result<Vec<PathBuf>> {
+    let text = match file {
+        Some(path) if path != Path::new("-") => std::fs::read_to_string(path)?,
+        _ => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+    Ok(parse_path_list(&text))
+}
+
+/// Loads the `--config` file, or layers every file on the search path.
+///
+/// In container mode no files are read at all, only the defaults and
+/// `AV1SD_` overrides. `--read-only` turns read-only mode on whatever the
+/// config says.
+fn load_config(args: &Args) -> Result<Config, ConfigError> {
+    let mut config = match &args.config {
+        _ if args.container => Config::load_layered(&[], args.profile)?,
+        Some(path) => {
+            log_info!("Config file: {}", path.display());
+            Config::load_with_profile(path, args.profile)?
+        }
+        None => {
+            let (config, files) = Config::discover(args.profile)?;
+            if files.is_empty() {
+                log_info!("No config file found, using defaults");
+            }
+            for file in &files {
+                log_info!("Config file: {}", file.display());
+            }
+            config
+        }
+    };
+    config.read_only |= args.read_only;
+    Ok(config)
+}
+
+/// Resets a file so the next scan cycle re-evaluates it.
+///
+/// Works without a running daemon. To re-probe immediately instead, POST
+/// `{"path": ...}` to `/jobs/requeue` on the running daemon. With `force`,
+/// an `.av1force` marker is left so the gates are bypassed.
+fn requeue(args: &Args, path: &Path, force: bool) -> ExitCode {
+    let config = match load_config(args) {
+        Ok(config) => config,
+        Err(e) => {
+            log_warn!("Failed to load config: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let reset = reset_path(path, &config).and_then(|report| {
+        if force {
+            write_force_marker(path)?;
+        }
+        Ok(report)
+    });
+    match reset {
+        Ok(report) => {
+            log_info!(
+                "Reset {}: marker removed: {}, sidecars removed: {}, jobs reset: {}",
+                path.display(),
+                report.marker_removed,
+                report.sidecars_removed,
+                report.jobs_reset
+            );
+            log_info!("The file will be re-evaluated on the next scan cycle.");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log_warn!("Failed to reset {}: {}", path.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Address the API listens on in container mode, from the `PORT` variable
+fn container_api_addr(port: Option<&str>) -> Result<SocketAddr, String> {
+    let port = match port {
+        Some(port) => port
+            .trim()
+            .parse()
+            .map_err(|_| format!("PORT must be a port number, got {:?}", port))?,
+        None => 7878,
+    };
+    Ok(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)))
+}
+
+/// Base directory for temporary chunk files when `--temp-dir` is not given
+///
+/// A container's /tmp is often a small overlay or tmpfs, so container mode
+/// keeps chunks next to the encode output, on whatever volume holds it.
+fn resolve_temp_dir(args: &Args, config: &Config) -> PathBuf {
+    match &args.temp_dir {
+        Some(dir) => dir.clone(),
+        None if args.container => config.paths.temp_output_dir.join("chunks"),
+        None => default_temp_dir(),
+    }
+}
+
+/// Default base directory for temporary chunk files
+#[cfg(not(windows))]
+fn default_temp_dir() -> PathBuf {
+    PathBuf::from("/tmp/av1-super-daemon")
+}
+
+/// Default base directory for temporary chunk files, under `%TEMP%`
+#[cfg(windows)]
+fn default_temp_dir() -> PathBuf {
+    std::env::temp_dir().join("av1-super-daemon")
+}
+
+/// Resolves when the process receives SIGINT or SIGTERM.
+///
+/// On Windows, Ctrl+Break (sent by service wrappers such as NSSM) and the
+/// console closing stand in for SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(windows)]
+    let terminate = async {
+        match (tokio::signal::windows::ctrl_break(), tokio::signal::windows::ctrl_close()) {
+            (Ok(mut ctrl_break), Ok(mut ctrl_close)) => {
+                tokio::select! {
+                    _ = ctrl_break.recv() => {}
+                    _ = ctrl_close.recv() => {}
+                }
+            }
+            _ => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(any(unix, windows)))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 }
 
 #[tokio::main]
 async fn main() -> ExitCode {
     let args = Args::parse();
+    av1_super_daemon::logging::set_json(args.container);
 
-    println!("AV1 Super Daemon starting...");
-    println!("Config file: {}", args.config.display());
-    println!("Temp directory: {}", args.temp_dir.display());
+    if args.print_default_config {
+        print!("{}", default_config_toml());
+        return ExitCode::SUCCESS;
+    }
 
-    // Initialize the daemon
-    let daemon_result = if args.skip_checks {
-        println!("WARNING: Skipping startup checks (--skip-checks enabled)");
-        Config::load(&args.config)
-            .map(|config| Daemon::new_without_checks(config, args.temp_dir))
-            .map_err(|e| e.into())
+    if args.print_launchd_plist {
+        let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let program = std::env::current_exe()
+            .unwrap_or_else(|_| PathBuf::from("/usr/local/bin/av1-super-daemon"));
+        let log_path = std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join("Library/Logs/av1-super-daemon.log"))
+            .unwrap_or_else(|| PathBuf::from("/tmp/av1-super-daemon.log"));
+        let config = args.config.as_deref().map(absolute);
+        let temp_dir = absolute(&args.temp_dir.clone().unwrap_or_else(default_temp_dir));
+        print!("{}", launchd_plist(&program, config.as_deref(), &temp_dir, &log_path));
+        return ExitCode::SUCCESS;
+    }
+
+    let import_list = match &args.command {
+        Some(Command::Init { force }) => {
+            let path = args.config.as_deref().unwrap_or(Path::new("config.toml"));
+            return init_config(path, *force);
+        }
+        Some(Command::Requeue { path, force }) => return requeue(&args, path, *force),
+        Some(Command::Import { file, overrides }) => match read_path_list(file.as_deref()) {
+            Ok(paths) => Some((paths, overrides.to_overrides())),
+            Err(e) => {
+                log_warn!("Failed to read import list: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        Some(Command::Add { path, overrides }) => Some((vec![path.clone()], overrides.to_overrides())),
+        None => None,
+    };
+
+    log_info!("AV1 Super Daemon starting...");
+
+    let api_addr = if args.container {
+        match container_api_addr(std::env::var("PORT").ok().as_deref()) {
+            Ok(addr) => Some(addr),
+            Err(e) => {
+                log_warn!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
     } else {
-        Daemon::new(&args.config, args.temp_dir).await
+        None
+    };
+
+    // Initialize the daemon
+    let daemon_result = match load_config(&args) {
+        Err(e) => Err(e.into()),
+        Ok(config) => {
+            if args.container {
+                log_info!("Container mode: config from AV1SD_ environment variables only");
+            }
+            let temp_dir = resolve_temp_dir(&args, &config);
+            log_info!("Temp directory: {}", temp_dir.display());
+            if let Some(profile) = config.profile {
+                log_info!("Profile: {}", profile);
+            }
+            if config.read_only {
+                log_info!("READ-ONLY: originals, skip markers, and sidecars will not be touched");
+            }
+            if args.skip_checks {
+                log_warn!("WARNING: Skipping startup checks (--skip-checks enabled)");
+                let mut daemon = Daemon::new_without_checks(config, temp_dir);
+                daemon.lock_instance().map(|()| daemon)
+            } else {
+                Daemon::with_config(config, temp_dir).await
+            }
+        }
     };
 
     match daemon_result {
-        Ok(daemon) => {
-            println!(
+        Ok(mut daemon) => {
+            if let Some(addr) = api_addr {
+                daemon.api_addr = addr;
+            }
+            log_info!(
                 "Daemon initialized with {} workers, {} max concurrent jobs",
                 daemon.concurrency_plan.av1an_workers,
                 daemon.concurrency_plan.max_concurrent_jobs
             );
-            println!("Starting metrics server on http://127.0.0.1:7878/metrics");
+            log_info!("Starting metrics server on http://{}/metrics", daemon.api_addr);
+
+            let run = async {
+                match import_list {
+                    // Import mode: queue the listed files and skip the scanner
+                    Some((paths, overrides)) => {
+                        log_info!("Importing {} paths", paths.len());
+                        let ctx = daemon.pipeline_context();
+                        tokio::spawn(async move {
+                            let entries = import_paths(&ctx, &paths, overrides).await;
+                            for entry in &entries {
+                                match &entry.message {
+                                    Some(message) => log_info!(
+                                        "{}: {} ({})",
+                                        entry.path.display(),
+                                        entry.outcome,
+                                        message
+                                    ),
+                                    None => {
+                                        log_info!("{}: {}", entry.path.display(), entry.outcome)
+                                    }
+                                }
+                            }
+                            let queued = entries.iter().filter(|e| e.job_id.is_some()).count();
+                            log_info!(
+                                "Import complete, queued {} of {} paths",
+                                queued,
+                                entries.len()
+                            );
+                        });
+                        daemon.run_with_server().await
+                    }
+                    // Run the daemon with the metrics server and scanning
+                    None => daemon.run_with_scanning().await,
+                }
+            };
+
+            // Stop running encodes on SIGINT/SIGTERM so no encoder
+            // processes are left behind
+            let result = tokio::select! {
+                result = run => result,
+                _ = shutdown_signal() => {
+                    log_info!("Shutdown requested, stopping running encodes...");
+                    daemon.shutdown().await;
+                    Ok(())
+                }
+            };
 
-            // Run the daemon with the metrics server and scanning
-            if let Err(e) = daemon.run_with_scanning().await {
-                eprintln!("Daemon error: {}", e);
+            if let Err(e) = result {
+                log_warn!("Daemon error: {}", e);
                 return ExitCode::FAILURE;
             }
 
             ExitCode::SUCCESS
         }
         Err(e) => {
-            eprintln!("Failed to initialize daemon: {}", e);
+            log_warn!("Failed to initialize daemon: {}", e);
             ExitCode::FAILURE
         }
     }
@@ -0,0 +1,88 @@
+//! Runs the binary the way a container entrypoint would: `--container`, no
+//! config file, everything from the environment.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Returns a port nothing listens on right now
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+#[test]
+fn test_container_entrypoint_logs_json_and_listens_on_port() {
+    let state = TempDir::new().unwrap();
+    let port = free_port();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_av1-super-daemon"))
+        .args(["--container", "--skip-checks"])
+        .env("PORT", port.to_string())
+        .env("AV1SD_PATHS__JOB_STATE_DIR", state.path().join("jobs"))
+        .env("AV1SD_PATHS__TEMP_OUTPUT_DIR", state.path().join("temp"))
+        .env("AV1SD_PATHS__SKIP_STATS_PATH", state.path().join("skip_stats.json"))
+        .env("AV1SD_SCAN__SCAN_INTERVAL_SECS", "3600")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Collect log lines until the daemon says the server is up
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let mut lines = Vec::new();
+    while let Ok(line) = rx.recv_timeout(Duration::from_secs(10)) {
+        let done = line.contains("Starting metrics server");
+        lines.push(line);
+        if done {
+            break;
+        }
+    }
+
+    // The server task may need a moment after the log line to bind
+    let mut response = String::new();
+    for _ in 0..50 {
+        if let Ok(mut stream) = TcpStream::connect(("127.0.0.1", port)) {
+            stream
+                .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            stream.read_to_string(&mut response).unwrap();
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    child.kill().unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(
+        lines.last().is_some_and(|l| l.contains(&format!("0.0.0.0:{}", port))),
+        "server never started: {:?}\nstderr: {}",
+        lines,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let messages: Vec<String> = lines
+        .iter()
+        .map(|line| {
+            let value: serde_json::Value = serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("not a JSON log line: {:?} ({})", line, e));
+            assert!(value["ts"].is_number());
+            assert!(value["level"].is_string());
+            value["msg"].as_str().unwrap().to_string()
+        })
+        .collect();
+    let temp_dir = state.path().join("temp").join("chunks");
+    let temp_line = format!("Temp directory: {}", temp_dir.display());
+    assert!(messages.contains(&temp_line), "{:?}", messages);
+    assert!(response.starts_with("HTTP/1.1 200"), "unexpected response: {}", response);
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+}
@@ -0,0 +1,226 @@
+//! Generic `AV1SD_` environment variable overrides
+//!
+//! Any config field can be set from the environment by joining its path with
+//! double underscores after the prefix, in any case:
+//! `AV1SD_SCAN__SCAN_INTERVAL_SECS=60` sets `scan.scan_interval_secs`, and
+//! `AV1SD_PROFILE=anime` selects a profile. Values are read as TOML, so
+//! arrays work too (`AV1SD_SCAN__LIBRARY_ROOTS='["/media/tv"]'`); fields
+//! that hold strings take the raw value without quoting, including optional
+//! ones such as `AV1SD_TORRENT__PASSWORD=123456`.
+
+use crate::config::{Config, ConfigError};
+
+/// Prefix shared by all generic override variables
+pub const ENV_PREFIX: &str = "AV1SD_";
+
+/// Separator between path segments in a variable name
+const PATH_SEPARATOR: &str = "__";
+
+/// One override taken from the environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvOverride {
+    /// Variable name as it appeared in the environment
+    pub var: String,
+    /// Lowercased config path, e.g. `["scan", "scan_interval_secs"]`
+    pub path: Vec<String>,
+}
+
+/// Converts a variable name into a config path, or `None` if it lacks the prefix
+pub fn env_var_path(var: &str) -> Option<Vec<String>> {
+    let rest = var.strip_prefix(ENV_PREFIX)?;
+    let path: Vec<String> = rest.split(PATH_SEPARATOR).map(str::to_lowercase).collect();
+    if path.iter().any(|segment| segment.is_empty()) {
+        return None;
+    }
+    Some(path)
+}
+
+/// Writes every `AV1SD_` variable in `vars` into `table`
+///
+/// `table` is the user's config file before profiles are applied, so the
+/// environment beats the file and a profile fills in whatever neither sets.
+///
+/// # Returns
+/// The overrides applied, for [`check_overrides_applied`]
+pub fn apply_prefixed_env<I>(table: &mut toml::Table, vars: I) -> Result<Vec<EnvOverride>, ConfigError>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let defaults = toml::Table::try_from(Config::default())
+        .map_err(|e| ConfigError::Env(format!("failed to serialize defaults: {}", e)))?;
+
+    let mut applied = Vec::new();
+    for (var, raw) in vars {
+        let Some(path) = env_var_path(&var) else {
+            continue;
+        };
+        let existing = lookup(table, &path).or_else(|| lookup(&defaults, &path));
+        let untyped = existing.is_none();
+        let mut value = parse_env_value(&raw, existing);
+        // Fields unset by default, such as `Option<String>` ones, are missing
+        // from the serialized defaults, so their type is unknown; a value
+        // like `123456` stays a string if that is the only way it loads
+        if untyped && !value.is_str() {
+            let raw_value = toml::Value::String(raw.clone());
+            if !loads_with(table, &path, value.clone()) && loads_with(table, &path, raw_value.clone()) {
+                value = raw_value;
+            }
+        }
+        insert(table, &path, value).map_err(|e| ConfigError::Env(format!("{}: {}", var, e)))?;
+        applied.push(EnvOverride { var, path });
+    }
+    Ok(applied)
+}
+
+/// Fails if any override named a path that the loaded config does not have
+///
+/// Config sections ignore unknown keys, so without this a misspelled
+/// variable would be silently dropped.
+pub fn check_overrides_applied(config: &Config, applied: &[EnvOverride]) -> Result<(), ConfigError> {
+    let loaded = toml::Table::try_from(config)
+        .map_err(|e| ConfigError::Env(format!("failed to serialize config: {}", e)))?;
+    match applied.iter().find(|o| lookup(&loaded, &o.path).is_none()) {
+        Some(unknown) => Err(ConfigError::Env(format!(
+            "{} does not match any config field",
+            unknown.var
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Reads `raw` as a TOML value, or as a plain string where that is what the
+/// field holds or the value is not valid TOML
+fn parse_env_value(raw: &str, existing: Option<&toml::Value>) -> toml::Value {
+    if let Some(toml::Value::String(_)) = existing {
+        return toml::Value::String(raw.to_string());
+    }
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+/// Whether the config loads with `value` set at `path` in `table`
+fn loads_with(table: &toml::Table, path: &[String], value: toml::Value) -> bool {
+    let mut table = table.clone();
+    insert(&mut table, path, value).is_ok() && Config::parse_table(table, None).is_ok()
+}
+
+fn lookup<'a>(table: &'a toml::Table, path: &[String]) -> Option<&'a toml::Value> {
+    let (last, parents) = path.split_last()?;
+    let mut current = table;
+    for segment in parents {
+        current = current.get(segment)?.as_table()?;
+    }
+    current.get(last)
+}
+
+fn insert(table: &mut toml::Table, path: &[String], value: toml::Value) -> Result<(), String> {
+    let Some((last, parents)) = path.split_last() else {
+        return Err("empty config path".to_string());
+    };
+    let mut current = table;
+    for segment in parents {
+        current = current
+            .entry(segment.clone())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| format!("{} is not a config section", segment))?;
+    }
+    current.insert(last.clone(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ConfigProfile;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn load(content: &str, env: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let mut table: toml::Table = toml::from_str(content).unwrap();
+        let applied = apply_prefixed_env(&mut table, vars(env))?;
+        let config = Config::parse_table(table, None)?;
+        check_overrides_applied(&config, &applied)?;
+        Ok(config)
+    }
+
+    #[test]
+    fn test_env_var_path() {
+        assert_eq!(
+            env_var_path("AV1SD_SCAN__SCAN_INTERVAL_SECS"),
+            Some(vec!["scan".to_string(), "scan_interval_secs".to_string()])
+        );
+        assert_eq!(env_var_path("AV1SD_PROFILE"), Some(vec!["profile".to_string()]));
+        assert_eq!(env_var_path("SCAN__SCAN_INTERVAL_SECS"), None);
+        assert_eq!(env_var_path("AV1SD_SCAN____X"), None);
+    }
+
+    #[test]
+    fn test_overrides_typed_fields() {
+        let config = load(
+            "[scan]\nscan_interval_secs = 30",
+            &[
+                ("AV1SD_SCAN__SCAN_INTERVAL_SECS", "90"),
+                ("AV1SD_GATES__MAX_SIZE_RATIO", "0.8"),
+                ("AV1SD_GATES__KEEP_ORIGINAL", "true"),
+                ("AV1SD_PATHS__JOB_STATE_DIR", "/srv/jobs"),
+                ("AV1SD_SCAN__LIBRARY_ROOTS", "[\"/media/tv\", \"/media/movies\"]"),
+                ("AV1SD_AV1AN__CRF", "24"),
+                ("AV1SD_OUTPUT__RENAME_TEMPLATE", "123"),
+                ("UNRELATED", "ignored"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(config.scan.scan_interval_secs, 90);
+        assert!((config.gates.max_size_ratio - 0.8).abs() < 0.0001);
+        assert!(config.gates.keep_original);
+        assert_eq!(config.paths.job_state_dir, std::path::PathBuf::from("/srv/jobs"));
+        assert_eq!(config.scan.library_roots.len(), 2);
+        assert_eq!(config.av1an.crf, Some(24));
+        assert_eq!(config.output.rename_template, "123");
+    }
+
+    #[test]
+    fn test_unset_string_fields_keep_raw_value() {
+        let config = load(
+            "",
+            &[
+                ("AV1SD_TORRENT__PASSWORD", "123456"),
+                ("AV1SD_POLITE__PLEX_TOKEN", "true"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.torrent.password.as_deref(), Some("123456"));
+        assert_eq!(config.polite.plex_token.as_deref(), Some("true"));
+    }
+
+    #[test]
+    fn test_profile_from_env_sits_under_file_values() {
+        let config = load("[av1an]\ncrf = 12", &[("AV1SD_PROFILE", "balanced")]).unwrap();
+        assert_eq!(config.profile, Some(ConfigProfile::Balanced));
+        assert_eq!(config.av1an.crf, Some(12));
+        assert_eq!(config.av1an.preset, Some(4));
+    }
+
+    #[test]
+    fn test_unknown_field_is_rejected() {
+        let err = load("", &[("AV1SD_SCAN__SCAN_INTERVAL", "60")]).unwrap_err();
+        assert!(err.to_string().contains("AV1SD_SCAN__SCAN_INTERVAL"));
+    }
+
+    #[test]
+    fn test_wrong_type_is_rejected() {
+        assert!(matches!(
+            load("", &[("AV1SD_SCAN__SCAN_INTERVAL_SECS", "soon")]),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+//! Config file formats
+//!
+//! TOML is the native format; YAML and JSON files are read into the same
+//! table representation so profiles and environment overrides apply to
+//! them unchanged.
+
+use crate::config::ConfigError;
+use std::path::Path;
+
+/// Serialization format of a config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Detects the format from the file extension, defaulting to TOML
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match ext.as_deref() {
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+
+    /// Parses `content` into a config table
+    ///
+    /// Null values in YAML or JSON are dropped, leaving the field at its
+    /// default, since TOML has no null.
+    pub fn parse_table(&self, content: &str) -> Result<toml::Table, ConfigError> {
+        let value: serde_json::Value = match self {
+            ConfigFormat::Toml => return Ok(toml::from_str(content)?),
+            ConfigFormat::Yaml => {
+                // An empty YAML document is null rather than an empty mapping
+                if content.trim().is_empty() {
+                    return Ok(toml::Table::new());
+                }
+                serde_yaml::from_str(content)?
+            }
+            ConfigFormat::Json => serde_json::from_str(content)?,
+        };
+
+        match toml::Value::try_from(strip_nulls(value)) {
+            Ok(toml::Value::Table(table)) => Ok(table),
+            Ok(_) => Err(ConfigError::Format(
+                "config root must be a mapping of sections".to_string(),
+            )),
+            Err(e) => Err(ConfigError::Format(e.to_string())),
+        }
+    }
+}
+
+fn strip_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, strip_nulls(v)))
+                .collect(),
+        ),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(strip_nulls).collect())
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::profile::ConfigProfile;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(ConfigFormat::from_path(Path::new("config.toml")), ConfigFormat::Toml);
+        assert_eq!(ConfigFormat::from_path(Path::new("config.YAML")), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path(Path::new("/etc/av1.yml")), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path(Path::new("config.json")), ConfigFormat::Json);
+        assert_eq!(ConfigFormat::from_path(Path::new("config")), ConfigFormat::Toml);
+    }
+
+    #[test]
+    fn test_yaml_and_json_match_toml() {
+        let toml = "profile = \"anime\"\n[scan]\nlibrary_roots = [\"/media/tv\"]\nscan_interval_secs = 60\n[gates]\nmax_size_ratio = 0.8\n";
+        let yaml = "profile: anime\nscan:\n  library_roots: [/media/tv]\n  scan_interval_secs: 60\ngates:\n  max_size_ratio: 0.8\ncpu:\n  logical_cores: null\n";
+        let json = r#"{"profile": "anime", "scan": {"library_roots": ["/media/tv"], "scan_interval_secs": 60}, "gates": {"max_size_ratio": 0.8}}"#;
+
+        let parse = |format: ConfigFormat, content: &str| {
+            Config::parse_table(format.parse_table(content).unwrap(), None).unwrap()
+        };
+        let from_toml = parse(ConfigFormat::Toml, toml);
+        assert_eq!(parse(ConfigFormat::Yaml, yaml), from_toml);
+        assert_eq!(parse(ConfigFormat::Json, json), from_toml);
+
+        assert_eq!(from_toml.profile, Some(ConfigProfile::Anime));
+        assert_eq!(from_toml.scan.library_roots, vec![PathBuf::from("/media/tv")]);
+    }
+
+    #[test]
+    fn test_empty_yaml_is_default() {
+        let table = ConfigFormat::Yaml.parse_table("").unwrap();
+        assert_eq!(Config::parse_table(table, None).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_non_mapping_root_is_rejected() {
+        assert!(matches!(
+            ConfigFormat::Json.parse_table("[1, 2]"),
+            Err(ConfigError::Format(_))
+        ));
+        assert!(matches!(
+            ConfigFormat::Yaml.parse_table("scan: [unclosed"),
+            Err(ConfigError::Yaml(_))
+        ));
+    }
+}
@@ -1,7 +1,21 @@
 //! Configuration module for AV1 Super Daemon
 //!
-//! Handles loading configuration from TOML files and environment variable overrides.
+//! Handles loading configuration from TOML, YAML, or JSON files, built-in
+//! profiles, and environment variable overrides.
 
 pub mod config;
+pub mod env;
+pub mod format;
+pub mod profile;
+pub mod search;
+pub mod template;
 
 pub use config::*;
+pub use env::{apply_prefixed_env, check_overrides_applied, env_var_path, EnvOverride, ENV_PREFIX};
+pub use format::ConfigFormat;
+pub use profile::*;
+pub use search::{
+    default_search_dirs, existing_config_files, find_config_in, search_dirs, CONFIG_FILE_NAMES,
+    SYSTEM_CONFIG_DIR,
+};
+pub use template::default_config_toml;
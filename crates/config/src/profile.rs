@@ -0,0 +1,163 @@
+//! Named configuration presets shipped in the binary
+//!
+//! A profile is a partial config that sits between the built-in defaults and
+//! the user's config file: anything the file sets wins, anything it leaves
+//! out comes from the profile, and the rest falls back to the defaults.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Built-in configuration preset selected by `profile = "..."` or `--profile`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfigProfile {
+    /// Near-transparent encodes; only replaces files when there is any saving
+    Archive,
+    /// Visually lossless for most content at a sensible size
+    Balanced,
+    /// Aggressive size reduction for libraries that are mostly watched once
+    SpaceSaver,
+    /// Low grain synthesis and tighter CRF for animated libraries
+    Anime,
+}
+
+/// Every profile, in the order they are listed to users
+pub const ALL_PROFILES: &[ConfigProfile] = &[
+    ConfigProfile::Archive,
+    ConfigProfile::Balanced,
+    ConfigProfile::SpaceSaver,
+    ConfigProfile::Anime,
+];
+
+const ARCHIVE_PRESET: &str = r#"
+[av1an]
+crf = 6
+preset = 2
+film_grain = 20
+
+[gates]
+max_size_ratio = 0.98
+"#;
+
+const BALANCED_PRESET: &str = r#"
+[av1an]
+crf = 20
+preset = 4
+film_grain = 12
+
+[gates]
+max_size_ratio = 0.90
+"#;
+
+const SPACE_SAVER_PRESET: &str = r#"
+[av1an]
+crf = 30
+preset = 5
+film_grain = 8
+
+[gates]
+min_bytes = 104857600
+max_size_ratio = 0.75
+"#;
+
+const ANIME_PRESET: &str = r#"
+[av1an]
+crf = 16
+preset = 4
+film_grain = 4
+
+[gates]
+max_size_ratio = 0.90
+"#;
+
+impl ConfigProfile {
+    /// Name used in config files and on the command line
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigProfile::Archive => "archive",
+            ConfigProfile::Balanced => "balanced",
+            ConfigProfile::SpaceSaver => "space-saver",
+            ConfigProfile::Anime => "anime",
+        }
+    }
+
+    /// The preset's settings as a partial config table
+    pub fn preset_table(&self) -> toml::Table {
+        let preset = match self {
+            ConfigProfile::Archive => ARCHIVE_PRESET,
+            ConfigProfile::Balanced => BALANCED_PRESET,
+            ConfigProfile::SpaceSaver => SPACE_SAVER_PRESET,
+            ConfigProfile::Anime => ANIME_PRESET,
+        };
+        toml::from_str(preset).expect("built-in profile presets are valid TOML")
+    }
+}
+
+impl fmt::Display for ConfigProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ConfigProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL_PROFILES
+            .iter()
+            .copied()
+            .find(|profile| profile.as_str() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = ALL_PROFILES.iter().map(|p| p.as_str()).collect();
+                format!("unknown profile '{}', expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+/// Merges `overlay` into `base`, recursing into tables so that only the keys
+/// present in `overlay` replace those in `base`
+pub fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_preset_parses() {
+        for profile in ALL_PROFILES {
+            let table = profile.preset_table();
+            assert!(table.contains_key("av1an"), "{} has no av1an section", profile);
+        }
+    }
+
+    #[test]
+    fn test_profile_names_round_trip() {
+        for profile in ALL_PROFILES {
+            assert_eq!(profile.as_str().parse::<ConfigProfile>().unwrap(), *profile);
+        }
+        assert!("fast".parse::<ConfigProfile>().is_err());
+    }
+
+    #[test]
+    fn test_merge_tables_keeps_unset_keys() {
+        let mut base: toml::Table = toml::from_str("[gates]\nmin_bytes = 1\nmax_size_ratio = 0.5").unwrap();
+        let overlay: toml::Table = toml::from_str("[gates]\nmax_size_ratio = 0.8\n[scan]\nincremental = true").unwrap();
+        merge_tables(&mut base, overlay);
+
+        assert_eq!(base["gates"]["min_bytes"].as_integer(), Some(1));
+        assert_eq!(base["gates"]["max_size_ratio"].as_float(), Some(0.8));
+        assert_eq!(base["scan"]["incremental"].as_bool(), Some(true));
+    }
+}
@@ -0,0 +1,123 @@
+//! Config file search path
+//!
+//! Without an explicit `--config`, the daemon layers every config file it
+//! finds, from most general to most specific:
+//!
+//! 1. `/etc/av1-super-daemon/`
+//! 2. `$XDG_CONFIG_HOME/av1-super-daemon/` (or `~/.config/av1-super-daemon/`)
+//! 3. the current directory
+//!
+//! On Windows the first two are `%ProgramData%\av1-super-daemon\` and
+//! `%APPDATA%\av1-super-daemon\`.
+//!
+//! Each directory contributes its first `config.toml`, `config.yaml`,
+//! `config.yml`, or `config.json`. Later files override earlier ones key by
+//! key, so fleet-wide defaults can live in /etc and per-host tweaks locally.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Directory name used under /etc and the XDG config home
+pub const APP_DIR_NAME: &str = "av1-super-daemon";
+
+/// System-wide config directory
+#[cfg(not(windows))]
+pub const SYSTEM_CONFIG_DIR: &str = "/etc/av1-super-daemon";
+
+/// System-wide config directory
+#[cfg(windows)]
+pub const SYSTEM_CONFIG_DIR: &str = r"C:\ProgramData\av1-super-daemon";
+
+/// File names tried in each directory, in order
+pub const CONFIG_FILE_NAMES: &[&str] = &["config.toml", "config.yaml", "config.yml", "config.json"];
+
+/// Directories searched for config files, most general first
+///
+/// `xdg_config_home` wins over `home`; with neither, the user directory is
+/// left out.
+pub fn search_dirs(xdg_config_home: Option<PathBuf>, home: Option<PathBuf>) -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(SYSTEM_CONFIG_DIR)];
+    let user_config = xdg_config_home
+        .filter(|p| p.is_absolute())
+        .or_else(|| home.map(|h| h.join(".config")));
+    if let Some(user_config) = user_config {
+        dirs.push(user_config.join(APP_DIR_NAME));
+    }
+    dirs.push(PathBuf::from("."));
+    dirs
+}
+
+/// Search directories for the current user's environment
+#[cfg(not(windows))]
+pub fn default_search_dirs() -> Vec<PathBuf> {
+    search_dirs(
+        env::var_os("XDG_CONFIG_HOME").map(PathBuf::from),
+        env::var_os("HOME").map(PathBuf::from),
+    )
+}
+
+/// Search directories for the current user's environment
+///
+/// `%APPDATA%` takes the place of the XDG config home.
+#[cfg(windows)]
+pub fn default_search_dirs() -> Vec<PathBuf> {
+    search_dirs(env::var_os("APPDATA").map(PathBuf::from), None)
+}
+
+/// The first config file present in `dir`, if any
+pub fn find_config_in(dir: &Path) -> Option<PathBuf> {
+    CONFIG_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Config files that exist along `dirs`, in layering order
+pub fn existing_config_files(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    dirs.iter().filter_map(|dir| find_config_in(dir)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_search_dirs_order() {
+        // Absolute on every platform, unlike "/xdg" on Windows
+        let xdg = env::temp_dir().join("xdg");
+        let home = env::temp_dir().join("home").join("me");
+        let dirs = search_dirs(Some(xdg.clone()), Some(home.clone()));
+        assert_eq!(
+            dirs,
+            vec![
+                PathBuf::from(SYSTEM_CONFIG_DIR),
+                xdg.join("av1-super-daemon"),
+                PathBuf::from("."),
+            ]
+        );
+
+        // Relative XDG_CONFIG_HOME is invalid per the spec and ignored
+        let dirs = search_dirs(Some(PathBuf::from("rel")), Some(home.clone()));
+        assert_eq!(dirs[1], home.join(".config").join("av1-super-daemon"));
+
+        assert_eq!(search_dirs(None, None).len(), 2);
+    }
+
+    #[test]
+    fn test_existing_config_files_takes_first_name_per_dir() {
+        let temp = TempDir::new().unwrap();
+        let system = temp.path().join("etc");
+        let user = temp.path().join("user");
+        let empty = temp.path().join("empty");
+        fs::create_dir_all(&system).unwrap();
+        fs::create_dir_all(&user).unwrap();
+        fs::write(system.join("config.yaml"), "").unwrap();
+        fs::write(user.join("config.toml"), "").unwrap();
+        fs::write(user.join("config.json"), "{}").unwrap();
+
+        let files = existing_config_files(&[system.clone(), empty, user.clone()]);
+        assert_eq!(files, vec![system.join("config.yaml"), user.join("config.toml")]);
+    }
+}
@@ -0,0 +1,893 @@
+//! Commented default config.toml generation
+//!
+//! Values come from serializing [`Config::default`], so they always match
+//! the code. Each key also needs an entry in [`FIELD_DOCS`]; the tests fail
+//! when a field is added without one or an entry outlives its field.
+
+use crate::config::Config;
+use std::fmt::Write;
+
+/// Description of one config key for the generated file
+struct FieldDoc {
+    /// Dotted path, e.g. `scan.scan_interval_secs`
+    path: &'static str,
+    /// Comment lines written above the key
+    doc: &'static str,
+    /// Example for keys that are unset by default; written commented out
+    example: Option<&'static str>,
+}
+
+/// Section headers in output order, with their comments
+const SECTION_DOCS: &[(&str, &str)] = &[
+    ("cpu", "CPU budget used to size Av1an workers and concurrent jobs"),
+    ("av1an", "Encoder settings"),
+    ("encoder_safety", "Guards against accidental hardware encoding"),
+    ("paths", "Where the daemon keeps its state and temporary files"),
+    ("scan", "Library scanning"),
+    ("gates", "Which files are encoded, and when an encode replaces the original"),
+    ("output", "Output container and naming of replaced files"),
+    ("thermal", "CPU temperature limit for starting new work"),
+    ("validation", "Checks run on each encode before the size gate"),
+    ("pixel_format", "Pixel format of encodes by source bit depth and chroma subsampling; SVT-AV1 encodes 4:2:0 only"),
+    ("audio", "Which audio tracks encodes keep; by default every track is copied"),
+    ("subtitles", "Which subtitle tracks encodes keep, and extraction of image-based ones (PGS, VobSub) to .sup/.idx sidecars"),
+    ("torrent", "Torrent client asked whether a file is seeding before it is touched"),
+    ("run_as", "User and group encodes run as and replaced files belong to, when the daemon runs as root"),
+    ("telemetry", "OpenTelemetry export of per-job traces and daemon metrics over OTLP/HTTP"),
+    ("kill_switch", "Sentinel file that halts all new work while it exists"),
+    ("quotas", "Per-library limits on concurrent jobs, encode hours, and bytes per day"),
+    ("farm", "Several daemons sharing one library, each claiming the files it encodes"),
+    ("staging", "Verified local copies of sources on slow or remote storage, made before encoding"),
+    ("storage", "How Av1an reads sources, by the kind of storage their library is on"),
+    ("ordering", "Rules that make jobs wait for others to finish first"),
+    ("discovery", "mDNS advertisement of the API so tools on the LAN find the daemon"),
+    ("cgroups", "cgroup v2 CPU and memory limits on encoder processes (Linux only)"),
+    ("polite", "Suspend running encodes while the host is in interactive use"),
+    ("tools", "Paths and extra environment of the external tools the daemon runs"),
+    ("process.env", "Environment tools run with; isolate keeps shell settings from changing encodes"),
+    ("triage", "Bundles of Av1an output, probe, command line and metrics written to job_state_dir/<job id>.triage when a job fails"),
+];
+
+const FIELD_DOCS: &[FieldDoc] = &[
+    FieldDoc {
+        path: "profile",
+        doc: "Built-in preset layered under this file: archive, balanced, space-saver, anime",
+        example: Some("\"balanced\""),
+    },
+    FieldDoc {
+        path: "read_only",
+        doc: "Encode into temp but never write markers or sidecars, or replace originals",
+        example: None,
+    },
+    FieldDoc {
+        path: "cpu.logical_cores",
+        doc: "Logical cores to plan for (auto-detected when unset)",
+        example: Some("16"),
+    },
+    FieldDoc {
+        path: "cpu.target_cpu_utilization",
+        doc: "Fraction of the CPU to use (0.5-1.0)",
+        example: None,
+    },
+    FieldDoc {
+        path: "av1an.workers_per_job",
+        doc: "Av1an workers per job (0 = derive from core count)",
+        example: None,
+    },
+    FieldDoc {
+        path: "av1an.max_concurrent_jobs",
+        doc: "Jobs encoded at once (0 = derive from core count)",
+        example: None,
+    },
+    FieldDoc {
+        path: "av1an.scale_workers",
+        doc: "Give short files fewer workers, running more jobs at once within workers_per_job x max_concurrent_jobs",
+        example: None,
+    },
+    FieldDoc {
+        path: "av1an.small_lane_slots",
+        doc: "Run this many short files beside one long file, splitting the workers between them (0 = no lanes)",
+        example: None,
+    },
+    FieldDoc {
+        path: "av1an.big_lane_min_secs",
+        doc: "Files at least this many seconds long use the long-file lane",
+        example: None,
+    },
+    FieldDoc {
+        path: "av1an.max_encode_secs",
+        doc: "Kill an encode that runs longer than this many seconds (0 = unlimited)",
+        example: None,
+    },
+    FieldDoc {
+        path: "av1an.stall_timeout_secs",
+        doc: "Kill an encode after Av1an prints nothing for this many seconds (0 = disabled)",
+        example: None,
+    },
+    FieldDoc {
+        path: "av1an.crf",
+        doc: "SVT-AV1 CRF, replacing the built-in value (8 for film, 10 for animation)",
+        example: Some("20"),
+    },
+    FieldDoc {
+        path: "av1an.preset",
+        doc: "SVT-AV1 preset, replacing the built-in value (3); lower is slower and better",
+        example: Some("4"),
+    },
+    FieldDoc {
+        path: "av1an.film_grain",
+        doc: "SVT-AV1 film grain synthesis level, replacing the built-in value (20 for film, 4 for animation)",
+        example: Some("12"),
+    },
+    FieldDoc {
+        path: "encoder_safety.disallow_hardware_encoding",
+        doc: "Refuse to start if any hardware encoder flag is configured",
+        example: None,
+    },
+    FieldDoc {
+        path: "paths.job_state_dir",
+        doc: "Directory where job JSON files are persisted",
+        example: None,
+    },
+    FieldDoc {
+        path: "paths.temp_output_dir",
+        doc: "Directory for encoded output before it replaces the original",
+        example: None,
+    },
+    FieldDoc {
+        path: "paths.skip_stats_path",
+        doc: "File holding lifetime skip-reason counters",
+        example: None,
+    },
+    FieldDoc {
+        path: "paths.duplicates_report_path",
+        doc: "JSON report of files with the same content, written each scan when scan.detect_duplicates is on",
+        example: None,
+    },
+    FieldDoc {
+        path: "paths.temp_quota_bytes",
+        doc: "Hold back new jobs while temp usage is above this many bytes (0 = unlimited)",
+        example: None,
+    },
+    FieldDoc {
+        path: "paths.temp_gc_interval_secs",
+        doc: "Seconds between temp usage checks and orphaned chunk cleanup (0 = disabled)",
+        example: None,
+    },
+    FieldDoc {
+        path: "paths.api_socket",
+        doc: "Unix socket the API is also served on, usable only by the daemon's user and group (Unix only)",
+        example: Some("\"/run/av1-daemon/api.sock\""),
+    },
+    FieldDoc {
+        path: "scan.library_roots",
+        doc: "Library directories to scan for video files",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.stability_wait_secs",
+        doc: "Seconds a file's size must stay unchanged before it is processed",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.write_why_sidecars",
+        doc: "Write .why.txt files explaining why a file was skipped",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.write_why_json",
+        doc: "Also write structured .why.json files with reason codes",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.scan_interval_secs",
+        doc: "Seconds between scan cycles",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.batch_seasons",
+        doc: "Queue all episodes of a season together, sharing the first episode's encode settings",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.order",
+        doc: "Queue order: walk, round_robin, newest_first, largest_first, smallest_first",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.incremental",
+        doc: "Reuse cached results for directories whose mtime is unchanged",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.full_rescan_interval_secs",
+        doc: "Seconds between full re-walks when scanning incrementally (0 = always full)",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.video_extensions",
+        doc: "File extensions treated as video, case-insensitive",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.walk_threads",
+        doc: "Threads walking directories on full scans (1 = single-threaded, 0 = one per CPU); raise for network mounts",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.probe_concurrency",
+        doc: "Candidates stability-checked and probed with ffprobe at the same time during a scan",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.max_queue_len",
+        doc: "Pending jobs at which a scan stops queueing and probing until the queue drains (0 = no limit)",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.detect_duplicates",
+        doc: "Hash the size and a few sampled blocks of candidates of equal size and report files found more than once (see paths.duplicates_report_path)",
+        example: None,
+    },
+    FieldDoc {
+        path: "scan.watch",
+        doc: "Scan as soon as a video file under the library roots changes, besides the interval (macOS only, uses FSEvents)",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.min_bytes",
+        doc: "Skip files smaller than this many bytes",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.min_age_days",
+        doc: "Leave files modified fewer than this many days ago for a later scan (0 = no minimum)",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.min_kbps_per_megapixel",
+        doc: "Skip sources whose video bitrate per megapixel is below this for their codec, e.g. { hevc = 950, h264 = 1500 } skips 1080p HEVC under 2 Mbps",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.max_size_ratio",
+        doc: "Only replace the original when output/original size is below this ratio",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.keep_original",
+        doc: "Keep the backup of the original (see output.backup_template) after replacement",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.backup_max_age_days",
+        doc: "Delete kept backups older than this many days (0 = keep forever)",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.backup_max_total_bytes",
+        doc: "Delete the oldest kept backups while together they take more than this many bytes (0 = no cap)",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.remux_av1",
+        doc: "Remux AV1 files in MP4/MOV/TS containers to MKV instead of skipping them",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.reencode_on_settings_change",
+        doc: "Encode files this daemon already encoded again when the encode settings changed since (per the .av1settings sidecar)",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.multi_video",
+        doc: "Files with several video streams (angles, not cover art): main encodes only the largest, longest one; skip leaves them alone",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.drop_attached_pictures",
+        doc: "Leave attached pictures such as cover art out of the encode",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.max_replacements_per_day",
+        doc: "Replace at most this many originals in any 24 hours; later encodes wait for approval (0 = unlimited)",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.replace_window",
+        doc: "Local time of day (HH:MM-HH:MM, may wrap midnight) originals are replaced in; encodes finished outside it wait as ready_to_replace",
+        example: Some("\"03:00-06:00\""),
+    },
+    FieldDoc {
+        path: "gates.require_approval",
+        doc: "Hold every encode that passes the size gate until it is approved or rejected",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.comparison_stills",
+        doc: "Side-by-side stills of original and encode to write for each held encode, under <job_state_dir>/<id>.compare (0 = none)",
+        example: None,
+    },
+    FieldDoc {
+        path: "gates.hardlinks",
+        doc: "Files with more than one hard link: skip, break (replace this path; other links keep the original), or copy (as break, but the original is copied to its backup rather than moved)",
+        example: None,
+    },
+    FieldDoc {
+        path: "output.container",
+        doc: "Container for encoded output: mkv or mp4",
+        example: None,
+    },
+    FieldDoc {
+        path: "output.rename_template",
+        doc: "Name of the replaced file; {stem}, {ext} (output) and {orig_ext} are substituted",
+        example: None,
+    },
+    FieldDoc {
+        path: "output.on_collision",
+        doc: "When the name is taken by another file: fail or suffix",
+        example: None,
+    },
+    FieldDoc {
+        path: "output.checksum_sidecars",
+        doc: "Checksum files (.sfv, .md5, .sha1, .sha256) listing a replaced file: update, remove, or ignore",
+        example: None,
+    },
+    FieldDoc {
+        path: "output.metadata_tags",
+        doc: "Tag MKV outputs with the encoder settings, original codec and size, daemon and date (needs mkvpropedit)",
+        example: None,
+    },
+    FieldDoc {
+        path: "output.backup_template",
+        doc: "File name of the original's backup; {name} (original file name) and {ts} (Unix seconds) are substituted",
+        example: None,
+    },
+    FieldDoc {
+        path: "output.backup_location",
+        doc: "Where backups go: beside (next to the original) or hidden (a .av1backup folder next to it)",
+        example: None,
+    },
+    FieldDoc {
+        path: "thermal.max_cpu_temp_celsius",
+        doc: "Throttle when the hottest CPU sensor reaches this many °C (disabled when unset)",
+        example: Some("85.0"),
+    },
+    FieldDoc {
+        path: "thermal.resume_margin_celsius",
+        doc: "Resume once the CPU is this many °C below the limit",
+        example: None,
+    },
+    FieldDoc {
+        path: "thermal.action",
+        doc: "While too hot: pause (start no new jobs) or reduce_workers",
+        example: None,
+    },
+    FieldDoc {
+        path: "thermal.throttled_workers_per_job",
+        doc: "Workers per job while reduced (0 = half the planned count)",
+        example: None,
+    },
+    FieldDoc {
+        path: "validation.quality_samples",
+        doc: "Frame pairs of original and encode to compare with PSNR/SSIM (0 = none)",
+        example: None,
+    },
+    FieldDoc {
+        path: "validation.min_psnr",
+        doc: "Flag encodes with any sampled frame below this PSNR in dB (no flag when unset)",
+        example: Some("35.0"),
+    },
+    FieldDoc {
+        path: "validation.min_ssim",
+        doc: "Flag encodes with any sampled frame below this SSIM, 0-1 (no flag when unset)",
+        example: Some("0.95"),
+    },
+    FieldDoc {
+        path: "validation.min_black_secs",
+        doc: "Fail encodes that are black for this many seconds where the source is not (no check when unset)",
+        example: Some("2.0"),
+    },
+    FieldDoc {
+        path: "validation.max_block_mean",
+        doc: "Fail encodes whose mean ffmpeg blockdetect score is above this (no check when unset)",
+        example: Some("8.0"),
+    },
+    FieldDoc {
+        path: "validation.max_audio_drift_ms",
+        doc: "Fail encodes whose audio start or duration drifts from the source by more than this (0 = no check)",
+        example: None,
+    },
+    FieldDoc {
+        path: "pixel_format.keep_8bit",
+        doc: "Encode 8-bit sources as 8-bit yuv420p instead of 10-bit yuv420p10le",
+        example: None,
+    },
+    FieldDoc {
+        path: "pixel_format.chroma_422",
+        doc: "What to do with 4:2:2 sources: convert them to 4:2:0, or skip them and keep the original",
+        example: None,
+    },
+    FieldDoc {
+        path: "pixel_format.chroma_444",
+        doc: "What to do with 4:4:4 sources: convert them to 4:2:0, or skip them and keep the original",
+        example: None,
+    },
+    FieldDoc {
+        path: "pixel_format.skip_unsupported",
+        doc: "Skip sources that are not planar YUV or grayscale (RGB, palette, Bayer, ...)",
+        example: None,
+    },
+    FieldDoc {
+        path: "audio.keep_languages",
+        doc: "Audio languages to keep as ISO 639-2 codes, e.g. [\"eng\"] (empty = all); untagged tracks are always kept",
+        example: None,
+    },
+    FieldDoc {
+        path: "audio.keep_original_language",
+        doc: "With keep_languages set, also keep the language of the first tagged track",
+        example: None,
+    },
+    FieldDoc {
+        path: "audio.drop_title_patterns",
+        doc: "Drop tracks whose title matches any of these regular expressions, e.g. [\"(?i)commentary\"]",
+        example: None,
+    },
+    FieldDoc {
+        path: "audio.max_channels",
+        doc: "Downmix tracks with more channels to this many, e.g. 8 for 7.1 (0 = never)",
+        example: None,
+    },
+    FieldDoc {
+        path: "audio.downmix_codec",
+        doc: "ffmpeg encoder for downmixed tracks",
+        example: None,
+    },
+    FieldDoc {
+        path: "audio.downmix_bitrate_kbps",
+        doc: "Bitrate of downmixed tracks in kbps",
+        example: None,
+    },
+    FieldDoc {
+        path: "subtitles.default_action",
+        doc: "What to do with PGS/VobSub tracks: keep them embedded, extract them to sidecars as well, or extract_and_drop them from the encode",
+        example: None,
+    },
+    FieldDoc {
+        path: "subtitles.libraries",
+        doc: "Action per library root, overriding default_action, e.g. { \"/media/movies\" = \"extract_and_drop\" }",
+        example: None,
+    },
+    FieldDoc {
+        path: "subtitles.keep_languages",
+        doc: "Subtitle languages encodes keep, e.g. [\"eng\", \"jpn\"]; empty keeps every track, untagged tracks are always kept",
+        example: None,
+    },
+    FieldDoc {
+        path: "subtitles.keep_forced",
+        doc: "With keep_languages set, also keep forced tracks in other languages",
+        example: None,
+    },
+    FieldDoc {
+        path: "torrent.client",
+        doc: "Torrent client to ask about seeding files: none, qbittorrent, transmission",
+        example: None,
+    },
+    FieldDoc {
+        path: "torrent.url",
+        doc: "Client Web UI or RPC address (default http://localhost:8080 for qBittorrent, http://localhost:9091/transmission/rpc for Transmission)",
+        example: Some("\"http://localhost:8080\""),
+    },
+    FieldDoc {
+        path: "torrent.username",
+        doc: "Client login, if it asks for one",
+        example: Some("\"admin\""),
+    },
+    FieldDoc {
+        path: "torrent.password",
+        doc: "Client password",
+        example: Some("\"adminadmin\""),
+    },
+    FieldDoc {
+        path: "torrent.default_action",
+        doc: "What to do with seeding files: skip them until they stop, pause their torrents around the replacement, or ignore",
+        example: None,
+    },
+    FieldDoc {
+        path: "torrent.libraries",
+        doc: "Action per library root, overriding default_action, e.g. { \"/media/movies\" = \"pause\" }",
+        example: None,
+    },
+    FieldDoc {
+        path: "run_as.uid",
+        doc: "User ID for encoder processes and replaced files (unset = the daemon's own user)",
+        example: Some("1000"),
+    },
+    FieldDoc {
+        path: "run_as.gid",
+        doc: "Group ID for encoder processes and replaced files (defaults to the user's primary group)",
+        example: Some("1000"),
+    },
+    FieldDoc {
+        path: "telemetry.endpoint",
+        doc: "OTLP/HTTP collector base URL; traces go to /v1/traces and metrics to /v1/metrics (no export when unset)",
+        example: Some("\"http://localhost:4318\""),
+    },
+    FieldDoc {
+        path: "telemetry.service_name",
+        doc: "service.name resource attribute on exported spans and metrics",
+        example: None,
+    },
+    FieldDoc {
+        path: "telemetry.metrics_interval_secs",
+        doc: "Seconds between metric exports",
+        example: None,
+    },
+    FieldDoc {
+        path: "telemetry.headers",
+        doc: "Extra HTTP headers sent with each export, e.g. { \"x-api-key\" = \"...\" }",
+        example: None,
+    },
+    FieldDoc {
+        path: "kill_switch.file",
+        doc: "While this file exists no scans run and no new jobs start; create it to halt the daemon, remove it to resume",
+        example: None,
+    },
+    FieldDoc {
+        path: "kill_switch.suspend_running",
+        doc: "Also suspend running encodes (SIGSTOP) until the file is removed; Unix only",
+        example: None,
+    },
+    FieldDoc {
+        path: "quotas.libraries",
+        doc: "Limits per library root (0 = unlimited, daily limits reset at midnight UTC), e.g. { \"/media/anime\" = { max_concurrent_jobs = 1, max_encode_hours_per_day = 6.0, max_bytes_per_day = 200000000000 } }",
+        example: None,
+    },
+    FieldDoc {
+        path: "farm.enabled",
+        doc: "Claim each file with an .av1claim lease next to it before encoding, so daemons sharing the library never encode the same file",
+        example: None,
+    },
+    FieldDoc {
+        path: "farm.node_name",
+        doc: "Name of this daemon in claims (unset = the host name)",
+        example: Some("\"nas-1\""),
+    },
+    FieldDoc {
+        path: "farm.lease_secs",
+        doc: "Seconds a claim holds without renewal; a daemon that stops renewing loses its files to the others after this",
+        example: None,
+    },
+    FieldDoc {
+        path: "staging.dir",
+        doc: "Local scratch folder for staged sources (unset = a staging folder in the temp chunks folder)",
+        example: Some("\"/var/tmp/av1-staging\""),
+    },
+    FieldDoc {
+        path: "staging.libraries",
+        doc: "Library roots whose sources are copied locally (and checksum-verified) before encoding, e.g. { \"/mnt/nas/movies\" = { stage_locally = true } }",
+        example: None,
+    },
+    FieldDoc {
+        path: "storage.default_kind",
+        doc: "Storage of files in no listed library: \"local\" (SSD, Av1an's default chunking), \"hdd\" (lsmash chunking with capped readers) or \"network\" (sequential segment chunking)",
+        example: None,
+    },
+    FieldDoc {
+        path: "storage.libraries",
+        doc: "Storage kind per library root; staged sources always count as local, e.g. { \"/mnt/nas\" = \"network\", \"/media/archive\" = \"hdd\" }",
+        example: None,
+    },
+    FieldDoc {
+        path: "storage.hdd_max_readers",
+        doc: "Av1an workers that may read a source on spinning disks at once (0 = no cap)",
+        example: None,
+    },
+    FieldDoc {
+        path: "ordering.serialize_directories",
+        doc: "Run the jobs of one folder one after another, each waiting for the one queued before it",
+        example: None,
+    },
+    FieldDoc {
+        path: "ordering.extras_after_main",
+        doc: "Start files in extras folders (Extras, Featurettes, Trailers, ...) only after the last job from the folder above has finished",
+        example: None,
+    },
+    FieldDoc {
+        path: "discovery.advertise",
+        doc: "Advertise the API over mDNS as _av1sd._tcp; the API must listen on an address other than loopback",
+        example: None,
+    },
+    FieldDoc {
+        path: "discovery.instance_name",
+        doc: "Name the daemon is advertised under (the host name when unset)",
+        example: Some("\"attic\""),
+    },
+    FieldDoc {
+        path: "cgroups.enabled",
+        doc: "Run each encode in its own cgroup, weighted by its workers; needs root or a delegated cgroup",
+        example: None,
+    },
+    FieldDoc {
+        path: "cgroups.parent",
+        doc: "cgroup the per-encode cgroups go under, capped at the planned threads; must hold no processes itself",
+        example: None,
+    },
+    FieldDoc {
+        path: "cgroups.memory_per_worker_mb",
+        doc: "Memory limit of an encode per Av1an worker in MiB (0 = unlimited)",
+        example: None,
+    },
+    FieldDoc {
+        path: "polite.enabled",
+        doc: "Suspend running encodes while Plex transcodes or other processes keep the CPU busy (Unix only)",
+        example: None,
+    },
+    FieldDoc {
+        path: "polite.plex_url",
+        doc: "Plex Media Server whose active transcodes suspend encodes",
+        example: Some("\"http://127.0.0.1:32400\""),
+    },
+    FieldDoc {
+        path: "polite.plex_token",
+        doc: "X-Plex-Token for the Plex API",
+        example: Some("\"your-plex-token\""),
+    },
+    FieldDoc {
+        path: "polite.suspend_cpu_percent",
+        doc: "Suspend when processes other than the daemon and its encoders use this much CPU",
+        example: Some("50.0"),
+    },
+    FieldDoc {
+        path: "polite.resume_cpu_percent",
+        doc: "CPU use by other processes at or below which the host counts as quiet again",
+        example: None,
+    },
+    FieldDoc {
+        path: "polite.resume_after_secs",
+        doc: "Seconds the host must stay quiet before suspended encodes resume",
+        example: None,
+    },
+    FieldDoc {
+        path: "polite.poll_secs",
+        doc: "Seconds between checks for interactive use",
+        example: None,
+    },
+    FieldDoc {
+        path: "tools.av1an",
+        doc: "Av1an binary to run instead of av1an from PATH",
+        example: Some("\"/opt/av1an/bin/av1an\""),
+    },
+    FieldDoc {
+        path: "tools.ffmpeg",
+        doc: "ffmpeg binary to run instead of ffmpeg from PATH; its directory is put first in Av1an's PATH",
+        example: Some("\"/opt/ffmpeg-8/bin/ffmpeg\""),
+    },
+    FieldDoc {
+        path: "tools.ffprobe",
+        doc: "ffprobe binary to run instead of ffprobe from PATH",
+        example: Some("\"/opt/ffmpeg-8/bin/ffprobe\""),
+    },
+    FieldDoc {
+        path: "tools.env",
+        doc: "Extra environment per tool, e.g. [tools.env.av1an] VAPOURSYNTH_PLUGIN_PATH = \"...\"",
+        example: None,
+    },
+    FieldDoc {
+        path: "process.env.isolate",
+        doc: "Start tools from a clean environment instead of the daemon's, so LD_PRELOAD and the like are not inherited",
+        example: None,
+    },
+    FieldDoc {
+        path: "process.env.path",
+        doc: "PATH of isolated tools",
+        example: None,
+    },
+    FieldDoc {
+        path: "process.env.locale",
+        doc: "LANG and LC_ALL of isolated tools",
+        example: None,
+    },
+    FieldDoc {
+        path: "process.env.pass",
+        doc: "Variables isolated tools keep from the daemon's environment",
+        example: None,
+    },
+    FieldDoc {
+        path: "process.env.vars",
+        doc: "Variables set for every isolated tool, e.g. { SVT_LOG = \"2\" }",
+        example: None,
+    },
+    FieldDoc {
+        path: "triage.enabled",
+        doc: "Write a triage bundle for every failed job and reference it from the job",
+        example: None,
+    },
+    FieldDoc {
+        path: "triage.output_lines",
+        doc: "Lines of Av1an output kept in a bundle, from the end",
+        example: None,
+    },
+    FieldDoc {
+        path: "triage.max_bundles",
+        doc: "Bundles kept before the oldest are removed (0 = no limit)",
+        example: None,
+    },
+];
+
+/// Renders a complete config.toml with every key, its default, and a comment
+///
+/// Keys without a default are included commented out with an example value.
+pub fn default_config_toml() -> String {
+    let defaults = toml::Table::try_from(Config::default())
+        .expect("default config serializes to TOML");
+
+    let mut out = String::new();
+    out.push_str("# AV1 Super Daemon configuration\n");
+    out.push_str("#\n");
+    out.push_str("# Every setting is shown with its default. Any of them can also be set\n");
+    out.push_str("# from the environment as AV1SD_<SECTION>__<KEY>, e.g.\n");
+    out.push_str("# AV1SD_SCAN__SCAN_INTERVAL_SECS=60.\n\n");
+
+    write_fields(&mut out, &defaults, "");
+    for (section, doc) in SECTION_DOCS {
+        let table = lookup(&defaults, section)
+            .and_then(|v| v.as_table())
+            .cloned()
+            .unwrap_or_default();
+        let _ = write!(out, "\n# {}\n[{}]\n", doc, section);
+        write_fields(&mut out, &table, section);
+    }
+    out
+}
+
+/// Writes the documented keys of one section, in [`FIELD_DOCS`] order
+fn write_fields(out: &mut String, table: &toml::Table, section: &str) {
+    for field in FIELD_DOCS.iter().filter(|f| section_of(f.path) == section) {
+        let key = key_of(field.path);
+        let _ = writeln!(out, "# {}", field.doc);
+        match (table.get(key), field.example) {
+            (Some(value), _) => {
+                let _ = writeln!(out, "{} = {}", key, format_value(value));
+            }
+            (None, Some(example)) => {
+                let _ = writeln!(out, "# {} = {}", key, example);
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// Value at a dotted path such as `process.env`
+fn lookup<'a>(table: &'a toml::Table, path: &str) -> Option<&'a toml::Value> {
+    let (first, rest) = path.split_once('.').unwrap_or((path, ""));
+    let value = table.get(first)?;
+    if rest.is_empty() {
+        return Some(value);
+    }
+    lookup(value.as_table()?, rest)
+}
+
+fn section_of(path: &str) -> &str {
+    path.rsplit_once('.').map(|(section, _)| section).unwrap_or("")
+}
+
+fn key_of(path: &str) -> &str {
+    path.rsplit_once('.').map(|(_, key)| key).unwrap_or(path)
+}
+
+/// Formats a value inline, printing floats at f32 precision since that is
+/// how the config stores them
+fn format_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::Float(f) => {
+            let short = (*f as f32).to_string();
+            if short.contains(['.', 'e', 'E']) {
+                short
+            } else {
+                format!("{}.0", short)
+            }
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// Every key present in the serialized defaults, as dotted paths
+    fn default_paths() -> BTreeSet<String> {
+        let defaults = toml::Table::try_from(Config::default()).unwrap();
+        let mut paths = BTreeSet::new();
+        collect_paths(&defaults, "", &mut paths);
+        paths
+    }
+
+    /// Adds the keys of `table` to `paths`, descending into the tables that
+    /// are sections of their own
+    fn collect_paths(table: &toml::Table, prefix: &str, paths: &mut BTreeSet<String>) {
+        for (key, value) in table {
+            let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+            let is_section = SECTION_DOCS.iter().any(|(section, _)| *section == path);
+            match value.as_table() {
+                Some(section) if prefix.is_empty() || is_section => {
+                    collect_paths(section, &path, paths);
+                }
+                _ => {
+                    paths.insert(path);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_default_field_is_documented() {
+        let documented: BTreeSet<String> = FIELD_DOCS.iter().map(|f| f.path.to_string()).collect();
+        let missing: Vec<_> = default_paths().difference(&documented).cloned().collect();
+        assert!(missing.is_empty(), "undocumented config fields: {:?}", missing);
+
+        // Entries without an example describe fields that have a default
+        let stale: Vec<_> = FIELD_DOCS
+            .iter()
+            .filter(|f| f.example.is_none() && !default_paths().contains(f.path))
+            .map(|f| f.path)
+            .collect();
+        assert!(stale.is_empty(), "docs for missing config fields: {:?}", stale);
+    }
+
+    #[test]
+    fn test_every_section_is_listed() {
+        let defaults = toml::Table::try_from(Config::default()).unwrap();
+        for (key, value) in &defaults {
+            if value.is_table() {
+                let nested = format!("{}.", key);
+                assert!(
+                    SECTION_DOCS
+                        .iter()
+                        .any(|(section, _)| section == key || section.starts_with(&nested)),
+                    "section [{}] missing from SECTION_DOCS",
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_config_parses_to_defaults() {
+        let generated = default_config_toml();
+        assert_eq!(Config::parse_toml(&generated).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_examples_name_real_fields() {
+        // Uncommenting every example must set exactly the optional fields
+        let mut uncommented = default_config_toml();
+        for field in FIELD_DOCS {
+            if let Some(example) = field.example {
+                let key = key_of(field.path);
+                uncommented = uncommented.replace(
+                    &format!("# {} = {}", key, example),
+                    &format!("{} = {}", key, example),
+                );
+            }
+        }
+        let config = Config::parse_toml(&uncommented).unwrap();
+        let reloaded = toml::Table::try_from(&config).unwrap();
+        let defaults = default_paths();
+        for field in FIELD_DOCS.iter().filter(|f| f.example.is_some()) {
+            let value = lookup(&reloaded, field.path);
+            assert!(value.is_some(), "example for {} did not set it", field.path);
+            assert!(!defaults.contains(field.path), "{} has both a default and an example", field.path);
+        }
+    }
+
+    #[test]
+    fn test_floats_are_not_widened() {
+        let generated = default_config_toml();
+        assert!(generated.contains("target_cpu_utilization = 0.85\n"));
+        assert!(generated.contains("max_size_ratio = 0.95\n"));
+    }
+}
@@ -0,0 +1,301 @@
+//! Audio track selection for encodes.
+//!
+//! By default every audio track is copied into the encode untouched. The
+//! `[audio]` section narrows that down per track: only the configured
+//! languages (plus the original language) are kept, tracks whose title
+//! matches a drop pattern such as `(?i)commentary` are dropped, and tracks
+//! with more channels than `max_channels` are downmixed. The result is an
+//! [`AudioPlan`] for the source, turned into the ffmpeg mapping arguments
+//! Av1an passes on when it extracts the audio, and checked against the encode
+//! afterwards by counting its audio streams.
+//!
+//! Tracks without a language tag are always kept, and a plan that would drop
+//! every track keeps them all instead, so an encode never loses its audio to
+//! a rule. Remuxes copy every stream and are not affected.
+
+use regex::Regex;
+
+use crate::config::AudioConfig;
+use crate::gates::AudioStream;
+
+/// Audio arguments for a plan that copies every track untouched.
+pub const COPY_ALL_AUDIO_PARAMS: &str = "-c:a copy";
+
+/// Language tag ffmpeg writes for tracks of unknown language.
+const UNDEFINED_LANGUAGE: &str = "und";
+
+/// Per-track rules compiled from the `[audio]` config.
+#[derive(Debug, Clone)]
+pub struct AudioPolicy {
+    keep_languages: Vec<String>,
+    keep_original_language: bool,
+    drop_titles: Vec<Regex>,
+    max_channels: u32,
+    downmix_codec: String,
+    downmix_bitrate_kbps: u32,
+}
+
+impl Default for AudioPolicy {
+    fn default() -> Self {
+        Self::from_config(&AudioConfig::default())
+    }
+}
+
+/// A source audio track kept in the encode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeptTrack {
+    /// Position among the source's audio streams
+    pub source_index: usize,
+    /// Channels to downmix to, or `None` to copy the track as it is
+    pub downmix_to: Option<u32>,
+}
+
+/// The audio tracks an encode of one source keeps, in output order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AudioPlan {
+    /// Audio streams in the source
+    pub source_tracks: usize,
+    /// Kept tracks, in source order
+    pub kept: Vec<KeptTrack>,
+    downmix_codec: String,
+    downmix_bitrate_kbps: u32,
+}
+
+impl AudioPolicy {
+    /// Compiles the rules in `config`
+    ///
+    /// Title patterns that are not valid regular expressions are logged and
+    /// ignored.
+    pub fn from_config(config: &AudioConfig) -> Self {
+        let drop_titles = config
+            .drop_title_patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    log_warn!("Warning: Ignoring audio title pattern {:?}: {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            keep_languages: config.keep_languages.iter().map(|l| l.trim().to_lowercase()).collect(),
+            keep_original_language: config.keep_original_language,
+            drop_titles,
+            max_channels: config.max_channels,
+            downmix_codec: config.downmix_codec.clone(),
+            downmix_bitrate_kbps: config.downmix_bitrate_kbps,
+        }
+    }
+
+    /// Decides which of `tracks` an encode keeps and which are downmixed
+    pub fn plan(&self, tracks: &[AudioStream]) -> AudioPlan {
+        let original = tracks.iter().find_map(language_of);
+        let keeps_language = |track: &AudioStream| match language_of(track) {
+            None => true,
+            Some(_) if self.keep_languages.is_empty() => true,
+            Some(language) => {
+                self.keep_languages.contains(&language)
+                    || (self.keep_original_language && original.as_ref() == Some(&language))
+            }
+        };
+        let dropped_by_title = |track: &AudioStream| {
+            let title = track.title.as_deref().unwrap_or("");
+            !title.is_empty() && self.drop_titles.iter().any(|regex| regex.is_match(title))
+        };
+
+        let mut kept: Vec<usize> = (0..tracks.len())
+            .filter(|&i| keeps_language(&tracks[i]) && !dropped_by_title(&tracks[i]))
+            .collect();
+        if kept.is_empty() {
+            kept = (0..tracks.len()).collect();
+        }
+
+        AudioPlan {
+            source_tracks: tracks.len(),
+            kept: kept
+                .into_iter()
+                .map(|source_index| KeptTrack {
+                    source_index,
+                    downmix_to: (self.max_channels > 0
+                        && tracks[source_index].channels > self.max_channels)
+                        .then_some(self.max_channels),
+                })
+                .collect(),
+            downmix_codec: self.downmix_codec.clone(),
+            downmix_bitrate_kbps: self.downmix_bitrate_kbps,
+        }
+    }
+}
+
+/// Lowercased language tag of `track`, or `None` if it has none
+fn language_of(track: &AudioStream) -> Option<String> {
+    track
+        .language
+        .as_deref()
+        .map(|language| language.trim().to_lowercase())
+        .filter(|language| !language.is_empty() && language != UNDEFINED_LANGUAGE)
+}
+
+impl AudioPlan {
+    /// Returns true if every track is copied untouched
+    pub fn copies_all(&self) -> bool {
+        self.kept.len() == self.source_tracks && self.kept.iter().all(|t| t.downmix_to.is_none())
+    }
+
+    /// Source audio stream positions the encode keeps, in output order
+    pub fn kept_indices(&self) -> Vec<usize> {
+        self.kept.iter().map(|track| track.source_index).collect()
+    }
+
+    /// ffmpeg arguments that drop and convert tracks, for Av1an's
+    /// `--audio-params`
+    ///
+    /// Av1an already maps every stream of the source, so dropped tracks are
+    /// removed with negative maps.
+    pub fn ffmpeg_args(&self) -> String {
+        if self.copies_all() {
+            return COPY_ALL_AUDIO_PARAMS.to_string();
+        }
+        let kept = self.kept_indices();
+        let mut args: Vec<String> = (0..self.source_tracks)
+            .filter(|i| !kept.contains(i))
+            .map(|i| format!("-map -0:a:{}", i))
+            .collect();
+        args.push(COPY_ALL_AUDIO_PARAMS.to_string());
+        for (out_index, track) in self.kept.iter().enumerate() {
+            if let Some(channels) = track.downmix_to {
+                args.push(format!(
+                    "-c:a:{i} {} -b:a:{i} {}k -ac:a:{i} {}",
+                    self.downmix_codec,
+                    self.downmix_bitrate_kbps,
+                    channels,
+                    i = out_index
+                ));
+            }
+        }
+        args.join(" ")
+    }
+
+    /// Checks the audio streams of the encode against the plan
+    ///
+    /// # Returns
+    /// A description of each mismatch; empty if the encode has exactly the
+    /// kept tracks and every downmixed one is within its channel count
+    pub fn check_output(&self, output: &[AudioStream]) -> Vec<String> {
+        if output.len() != self.kept.len() {
+            return vec![format!(
+                "output has {} audio streams, expected {} of the source's {}",
+                output.len(),
+                self.kept.len(),
+                self.source_tracks
+            )];
+        }
+        self.kept
+            .iter()
+            .zip(output)
+            .enumerate()
+            .filter_map(|(i, (track, stream))| match track.downmix_to {
+                Some(channels) if stream.channels > channels => Some(format!(
+                    "audio stream {} has {} channels, expected at most {}",
+                    i, stream.channels, channels
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(language: Option<&str>, title: Option<&str>, channels: u32) -> AudioStream {
+        AudioStream {
+            codec_name: "truehd".to_string(),
+            channels,
+            language: language.map(String::from),
+            title: title.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn policy(keep_languages: &[&str], drop_title_patterns: &[&str], max_channels: u32) -> AudioPolicy {
+        AudioPolicy::from_config(&AudioConfig {
+            keep_languages: keep_languages.iter().map(|l| l.to_string()).collect(),
+            drop_title_patterns: drop_title_patterns.iter().map(|p| p.to_string()).collect(),
+            max_channels,
+            ..AudioConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_default_policy_copies_everything() {
+        let tracks = [track(Some("jpn"), None, 10), track(Some("eng"), Some("Commentary"), 2)];
+        let plan = AudioPolicy::default().plan(&tracks);
+        assert!(plan.copies_all());
+        assert_eq!(plan.ffmpeg_args(), "-c:a copy");
+        assert!(AudioPolicy::default().plan(&[]).copies_all());
+    }
+
+    #[test]
+    fn test_plan_keeps_original_and_listed_languages() {
+        let tracks = [
+            track(Some("jpn"), None, 6),
+            track(Some("ger"), None, 6),
+            track(Some("ENG"), None, 6),
+            track(Some("eng"), Some("Director's Commentary"), 2),
+            track(None, None, 2),
+        ];
+        let plan = policy(&["eng"], &["(?i)commentary"], 0).plan(&tracks);
+        assert_eq!(plan.kept_indices(), vec![0, 2, 4]);
+        assert_eq!(plan.ffmpeg_args(), "-map -0:a:1 -map -0:a:3 -c:a copy");
+
+        let mut config = AudioConfig {
+            keep_languages: vec!["eng".to_string()],
+            ..AudioConfig::default()
+        };
+        config.keep_original_language = false;
+        let plan = AudioPolicy::from_config(&config).plan(&tracks);
+        assert_eq!(plan.kept_indices(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_plan_never_drops_every_track() {
+        let tracks = [track(Some("fre"), Some("Commentary"), 2)];
+        let plan = policy(&["eng"], &["Commentary"], 0).plan(&tracks);
+        assert_eq!(plan.kept_indices(), vec![0]);
+        assert!(plan.copies_all());
+    }
+
+    #[test]
+    fn test_plan_downmixes_above_max_channels() {
+        let tracks = [track(Some("eng"), None, 12), track(Some("eng"), None, 8)];
+        let plan = policy(&[], &[], 8).plan(&tracks);
+        assert!(!plan.copies_all());
+        assert_eq!(
+            plan.ffmpeg_args(),
+            "-c:a copy -c:a:0 libopus -b:a:0 448k -ac:a:0 8"
+        );
+    }
+
+    #[test]
+    fn test_invalid_title_pattern_is_ignored() {
+        let tracks = [track(None, Some("Commentary"), 2), track(None, None, 2)];
+        let plan = policy(&[], &["(", "Commentary"], 0).plan(&tracks);
+        assert_eq!(plan.kept_indices(), vec![1]);
+    }
+
+    #[test]
+    fn test_check_output_counts_streams_and_channels() {
+        let tracks = [track(Some("eng"), None, 12), track(Some("eng"), Some("Commentary"), 2)];
+        let plan = policy(&[], &["Commentary"], 8).plan(&tracks);
+        assert!(plan.check_output(&[track(Some("eng"), None, 8)]).is_empty());
+
+        let problems = plan.check_output(&tracks);
+        assert_eq!(problems, vec!["output has 2 audio streams, expected 1 of the source's 2".to_string()]);
+
+        let problems = plan.check_output(&[track(Some("eng"), None, 12)]);
+        assert_eq!(problems, vec!["audio stream 0 has 12 channels, expected at most 8".to_string()]);
+    }
+}
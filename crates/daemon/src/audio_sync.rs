@@ -0,0 +1,245 @@
+//! Audio sync validation between source and encode.
+//!
+//! Av1an encodes the video in chunks and muxes the untouched audio back in
+//! at the end. When the merge goes wrong the audio ends up shifted or cut
+//! short, and a decode check has no way to notice. This module reads the
+//! stream timing of both files with ffprobe and compares, per audio stream,
+//! its duration and its start offset relative to the first video stream.
+
+use std::io;
+use std::path::Path;
+
+use crate::tools::Tool;
+
+/// Arguments passed to ffprobe ahead of the input path.
+const FFPROBE_TIMING_ARGS: &[&str] = &[
+    "-v",
+    "quiet",
+    "-print_format",
+    "json",
+    "-show_entries",
+    "stream=codec_type,start_time,duration:stream_tags=DURATION",
+];
+
+/// Start time and duration of one stream, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StreamTiming {
+    pub start_time: f64,
+    /// Unset when the container stores no per-stream duration
+    pub duration: Option<f64>,
+}
+
+/// Timing of the streams that matter for sync.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MediaTiming {
+    /// First video stream
+    pub video: Option<StreamTiming>,
+    /// Audio streams in file order
+    pub audio: Vec<StreamTiming>,
+}
+
+impl MediaTiming {
+    /// The same timing with only the audio streams at `indices`, in that
+    /// order; indices past the last stream are skipped.
+    pub fn select_audio(&self, indices: &[usize]) -> MediaTiming {
+        MediaTiming {
+            video: self.video,
+            audio: indices.iter().filter_map(|&i| self.audio.get(i).copied()).collect(),
+        }
+    }
+}
+
+/// Subset of ffprobe's JSON output read here.
+mod ffprobe_json {
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    pub struct Output {
+        #[serde(default)]
+        pub streams: Vec<Stream>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Stream {
+        pub codec_type: Option<String>,
+        pub start_time: Option<String>,
+        pub duration: Option<String>,
+        pub tags: Option<Tags>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Tags {
+        #[serde(rename = "DURATION")]
+        pub duration: Option<String>,
+    }
+}
+
+/// Parses a Matroska `DURATION` tag such as `01:02:03.500000000`.
+pub fn parse_duration_tag(tag: &str) -> Option<f64> {
+    let mut parts = tag.trim().splitn(3, ':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// Parses the output of ffprobe run with the timing arguments.
+pub fn parse_timing(json: &str) -> Result<MediaTiming, serde_json::Error> {
+    let output: ffprobe_json::Output = serde_json::from_str(json)?;
+    let mut timing = MediaTiming::default();
+    for stream in output.streams {
+        // MKV keeps stream durations in tags rather than the stream header
+        let duration = stream
+            .duration
+            .as_deref()
+            .and_then(|d| d.parse().ok())
+            .or_else(|| {
+                let tag = stream.tags.and_then(|t| t.duration)?;
+                parse_duration_tag(&tag)
+            });
+        let start_time = stream
+            .start_time
+            .as_deref()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0.0);
+        let stream_timing = StreamTiming {
+            start_time,
+            duration,
+        };
+        match stream.codec_type.as_deref() {
+            Some("video") if timing.video.is_none() => timing.video = Some(stream_timing),
+            Some("audio") => timing.audio.push(stream_timing),
+            _ => {}
+        }
+    }
+    Ok(timing)
+}
+
+/// Reads the stream timing of `path` with ffprobe.
+pub fn probe_timing(path: &Path) -> io::Result<MediaTiming> {
+    let output = Tool::Ffprobe.command().args(FFPROBE_TIMING_ARGS).arg(path).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "ffprobe exited with status {}",
+            output.status
+        )));
+    }
+    parse_timing(&String::from_utf8_lossy(&output.stdout)).map_err(io::Error::other)
+}
+
+/// Compares the audio timing of `output` against `source`.
+///
+/// # Returns
+/// A description of each audio stream that drifted by more than
+/// `tolerance_secs`; empty if they are all in sync
+pub fn compare_timing(
+    source: &MediaTiming,
+    output: &MediaTiming,
+    tolerance_secs: f64,
+) -> Vec<String> {
+    if source.audio.len() != output.audio.len() {
+        return vec![format!(
+            "output has {} audio streams, source has {}",
+            output.audio.len(),
+            source.audio.len()
+        )];
+    }
+
+    let video_start = |timing: &MediaTiming| timing.video.map_or(0.0, |v| v.start_time);
+    let mut problems = Vec::new();
+    for (i, (src, out)) in source.audio.iter().zip(&output.audio).enumerate() {
+        let src_offset = src.start_time - video_start(source);
+        let out_offset = out.start_time - video_start(output);
+        if (out_offset - src_offset).abs() > tolerance_secs {
+            problems.push(format!(
+                "audio stream {} starts {:+.3}s from the video, source {:+.3}s",
+                i, out_offset, src_offset
+            ));
+        }
+        if let (Some(src_duration), Some(out_duration)) = (src.duration, out.duration) {
+            if (out_duration - src_duration).abs() > tolerance_secs {
+                problems.push(format!(
+                    "audio stream {} lasts {:.3}s, source {:.3}s",
+                    i, out_duration, src_duration
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Probes both files and compares their audio timing.
+///
+/// `kept_audio` lists the source audio streams the encode kept, when the
+/// audio policy dropped some; `None` compares every stream.
+///
+/// # Errors
+/// Returns an error if either file cannot be probed.
+pub fn check_audio_sync(
+    original: &Path,
+    encode: &Path,
+    kept_audio: Option<&[usize]>,
+    tolerance_secs: f64,
+) -> io::Result<Vec<String>> {
+    let mut source = probe_timing(original)?;
+    if let Some(kept) = kept_audio {
+        source = source.select_audio(kept);
+    }
+    let output = probe_timing(encode)?;
+    Ok(compare_timing(&source, &output, tolerance_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timing_reads_mkv_duration_tags() {
+        let json = r#"{
+            "streams": [
+                {"codec_type": "video", "start_time": "0.000000", "tags": {"DURATION": "00:42:10.125000000"}},
+                {"codec_type": "audio", "start_time": "0.021000", "duration": "2530.104000"},
+                {"codec_type": "audio", "start_time": "0.000000"},
+                {"codec_type": "subtitle", "start_time": "0.000000"}
+            ]
+        }"#;
+        let timing = parse_timing(json).unwrap();
+        assert_eq!(timing.video.unwrap().duration, Some(2530.125));
+        assert_eq!(timing.audio.len(), 2);
+        assert_eq!(timing.audio[0].start_time, 0.021);
+        assert_eq!(timing.audio[0].duration, Some(2530.104));
+        assert_eq!(timing.audio[1].duration, None);
+        assert_eq!(parse_duration_tag("bogus"), None);
+    }
+
+    #[test]
+    fn test_compare_timing_reports_drift() {
+        let timing = |video_start: f64, audio: &[(f64, Option<f64>)]| MediaTiming {
+            video: Some(StreamTiming { start_time: video_start, duration: Some(100.0) }),
+            audio: audio
+                .iter()
+                .map(|&(start_time, duration)| StreamTiming { start_time, duration })
+                .collect(),
+        };
+        let source = timing(0.0, &[(0.02, Some(100.0))]);
+
+        // The same offset from the video is fine even if both shifted
+        assert!(compare_timing(&source, &timing(0.5, &[(0.52, Some(100.01))]), 0.1).is_empty());
+        // An unknown duration on one side is not counted as drift
+        assert!(compare_timing(&source, &timing(0.0, &[(0.02, None)]), 0.1).is_empty());
+
+        let shifted = compare_timing(&source, &timing(0.0, &[(0.52, Some(99.5))]), 0.1);
+        assert_eq!(shifted.len(), 2);
+        assert!(shifted[0].contains("starts +0.520s"));
+        assert!(shifted[1].contains("lasts 99.500s"));
+
+        let dropped = compare_timing(&source, &timing(0.0, &[]), 0.1);
+        assert_eq!(dropped, vec!["output has 0 audio streams, source has 1".to_string()]);
+
+        // Tracks dropped on purpose are left out of the source side
+        let two_tracks = timing(0.0, &[(0.02, Some(100.0)), (0.9, Some(50.0))]);
+        let selected = two_tracks.select_audio(&[1]);
+        assert_eq!(selected.audio, vec![StreamTiming { start_time: 0.9, duration: Some(50.0) }]);
+        assert!(compare_timing(&selected, &timing(0.0, &[(0.9, Some(50.0))]), 0.1).is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+//! Retention of kept backups.
+//!
+//! With `keep_original = true` every replacement leaves the original behind
+//! as a backup, and nothing ever removes them. Each persisted job records the
+//! backup it kept (see [`crate::jobs::KeptBackup`]), so retention works from
+//! the job records rather than by guessing backup names in the library: a
+//! pass deletes backups older than `backup_max_age_days`, then the oldest
+//! remaining ones while together they take more than
+//! `backup_max_total_bytes`.
+//!
+//! Backups kept before jobs recorded them are not known to retention and
+//! are left alone.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::jobs::{load_jobs, update_job};
+use crate::metrics::SharedMetrics;
+use crate::replace::remove_empty_backup_dir;
+
+/// Seconds between retention passes.
+pub const RETENTION_INTERVAL_SECS: u64 = 3600;
+
+/// Age and size limits for kept backups; 0 disables a limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_age_days: u32,
+    pub max_total_bytes: u64,
+}
+
+impl RetentionPolicy {
+    /// Returns true if either limit is set.
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_days > 0 || self.max_total_bytes > 0
+    }
+}
+
+/// A kept backup found on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupFile {
+    /// ID of the job that kept it.
+    pub job_id: String,
+    pub path: PathBuf,
+    /// Unix milliseconds when it was kept.
+    pub kept_at: i64,
+    pub size_bytes: u64,
+}
+
+/// Outcome of a single retention pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+    /// Backups deleted.
+    pub removed: u64,
+    pub bytes_freed: u64,
+    /// Backups left on disk.
+    pub kept: u64,
+    pub bytes_kept: u64,
+}
+
+/// Picks the backups `policy` wants deleted at `now_unix_ms`.
+///
+/// Backups past the age limit go first; then the oldest of the rest until
+/// the remainder fits under the size cap.
+///
+/// # Returns
+/// Indices into `backups`, oldest first
+pub fn select_expired(
+    backups: &[BackupFile],
+    policy: RetentionPolicy,
+    now_unix_ms: i64,
+) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..backups.len()).collect();
+    order.sort_by_key(|&i| (backups[i].kept_at, i));
+
+    let max_age_ms = policy.max_age_days as i64 * 86_400_000;
+    let mut remaining: u64 = backups.iter().map(|b| b.size_bytes).sum();
+    let mut expired = Vec::new();
+    for i in order {
+        let too_old = policy.max_age_days > 0 && now_unix_ms - backups[i].kept_at > max_age_ms;
+        let over_cap = policy.max_total_bytes > 0 && remaining > policy.max_total_bytes;
+        if !too_old && !over_cap {
+            break;
+        }
+        remaining -= backups[i].size_bytes;
+        expired.push(i);
+    }
+    expired
+}
+
+/// Kept backups recorded in the jobs under `state_dir`.
+///
+/// Records whose backup is gone, for example deleted by hand, are cleared.
+pub fn find_backups(state_dir: &Path) -> io::Result<Vec<BackupFile>> {
+    let mut backups = Vec::new();
+    for job in load_jobs(state_dir)? {
+        let Some(kept) = job.backup else {
+            continue;
+        };
+        match fs::metadata(&kept.path) {
+            Ok(meta) => backups.push(BackupFile {
+                job_id: job.id,
+                path: kept.path,
+                kept_at: kept.kept_at,
+                size_bytes: meta.len(),
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                update_job(state_dir, &job.id, |managed| managed.backup = None)?;
+            }
+            Err(e) => log_warn!("Warning: Failed to stat backup {:?}: {}", kept.path, e),
+        }
+    }
+    Ok(backups)
+}
+
+/// Deletes the backups `policy` no longer allows and clears their records.
+pub fn apply_retention(
+    state_dir: &Path,
+    policy: RetentionPolicy,
+    now_unix_ms: i64,
+) -> io::Result<RetentionReport> {
+    let backups = find_backups(state_dir)?;
+    let mut report = RetentionReport {
+        kept: backups.len() as u64,
+        bytes_kept: backups.iter().map(|b| b.size_bytes).sum(),
+        ..RetentionReport::default()
+    };
+
+    for i in select_expired(&backups, policy, now_unix_ms) {
+        let backup = &backups[i];
+        if let Err(e) = fs::remove_file(&backup.path) {
+            log_warn!("Warning: Failed to delete backup {:?}: {}", backup.path, e);
+            continue;
+        }
+        remove_empty_backup_dir(&backup.path);
+        update_job(state_dir, &backup.job_id, |managed| managed.backup = None)?;
+        let age_days = (now_unix_ms - backup.kept_at).max(0) / 86_400_000;
+        log_info!(
+            "Deleted backup {:?} ({} bytes, kept {} days ago)",
+            backup.path, backup.size_bytes, age_days
+        );
+        report.removed += 1;
+        report.bytes_freed += backup.size_bytes;
+        report.kept -= 1;
+        report.bytes_kept -= backup.size_bytes;
+    }
+    Ok(report)
+}
+
+/// Runs one retention pass and records it in the backup metrics.
+pub async fn run_backup_retention(
+    metrics: &SharedMetrics,
+    state_dir: &Path,
+    policy: RetentionPolicy,
+    now_unix_ms: i64,
+) -> RetentionReport {
+    let dir = state_dir.to_path_buf();
+    let report = tokio::task::spawn_blocking(move || {
+        apply_retention(&dir, policy, now_unix_ms).unwrap_or_else(|e| {
+            log_warn!("Warning: Backup retention failed: {}", e);
+            RetentionReport::default()
+        })
+    })
+    .await
+    .unwrap_or_default();
+
+    if report.removed > 0 {
+        log_info!(
+            "Backup retention removed {} backups ({} bytes); {} remain ({} bytes)",
+            report.removed, report.bytes_freed, report.kept, report.bytes_kept
+        );
+    }
+
+    let mut m = metrics.write().await;
+    m.backups.kept = report.kept;
+    m.backups.bytes_kept = report.bytes_kept;
+    m.backups.removed += report.removed;
+    m.backups.bytes_freed += report.bytes_freed;
+    m.backups.last_run_unix_ms = now_unix_ms;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::{FormatInfo, ProbeResult};
+    use crate::jobs::{create_job, load_job, save_job, KeptBackup};
+    use crate::metrics::new_shared_metrics;
+    use crate::scan::ScanCandidate;
+    use std::time::UNIX_EPOCH;
+    use tempfile::TempDir;
+
+    const DAY_MS: i64 = 86_400_000;
+
+    fn backup(id: &str, kept_at: i64, size_bytes: u64) -> BackupFile {
+        BackupFile {
+            job_id: id.to_string(),
+            path: PathBuf::from(format!("/media/{}.mkv.orig.1", id)),
+            kept_at,
+            size_bytes,
+        }
+    }
+
+    #[test]
+    fn test_select_expired_by_age_then_size() {
+        let now = 100 * DAY_MS;
+        let backups = [
+            backup("new", now - DAY_MS, 300),
+            backup("old", now - 40 * DAY_MS, 100),
+            backup("mid", now - 10 * DAY_MS, 500),
+        ];
+
+        let policy = RetentionPolicy::default();
+        assert!(!policy.is_enabled());
+        assert!(select_expired(&backups, policy, now).is_empty());
+
+        let by_age = RetentionPolicy {
+            max_age_days: 30,
+            max_total_bytes: 0,
+        };
+        assert_eq!(select_expired(&backups, by_age, now), vec![1]);
+
+        // 900 bytes in all: the two oldest must go to get under 400
+        let by_size = RetentionPolicy {
+            max_age_days: 0,
+            max_total_bytes: 400,
+        };
+        assert_eq!(select_expired(&backups, by_size, now), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_run_backup_retention_deletes_and_clears_records() {
+        let temp = TempDir::new().unwrap();
+        let state_dir = temp.path().join("jobs");
+        let now = 100 * DAY_MS;
+
+        let mut ids = Vec::new();
+        for (name, age_days) in [("old", 60), ("new", 1), ("gone", 90)] {
+            let original = temp.path().join(format!("{}.mkv", name));
+            let candidate = ScanCandidate {
+                path: original.clone(),
+                size_bytes: 1000,
+                modified_time: UNIX_EPOCH,
+                root: temp.path().to_path_buf(),
+            };
+            let probe = ProbeResult {
+                video_streams: vec![],
+                audio_streams: vec![],
+                subtitle_streams: vec![],
+                font_attachments: 0,
+                format: FormatInfo {
+                    duration_secs: 60.0,
+                    size_bytes: 1000,
+                },
+            };
+            let mut job = create_job(&candidate, probe, Default::default(), temp.path());
+            let path = temp.path().join(format!("{}.mkv.orig.1", name));
+            if name != "gone" {
+                fs::write(&path, b"backup").unwrap();
+            }
+            job.backup = Some(KeptBackup {
+                path,
+                kept_at: now - age_days * DAY_MS,
+            });
+            save_job(&job, &state_dir).unwrap();
+            ids.push(job.id);
+        }
+
+        let metrics = new_shared_metrics();
+        let policy = RetentionPolicy {
+            max_age_days: 30,
+            max_total_bytes: 0,
+        };
+        let report = run_backup_retention(&metrics, &state_dir, policy, now).await;
+        assert_eq!(
+            report,
+            RetentionReport {
+                removed: 1,
+                bytes_freed: 6,
+                kept: 1,
+                bytes_kept: 6,
+            }
+        );
+        assert!(!temp.path().join("old.mkv.orig.1").exists());
+        assert!(temp.path().join("new.mkv.orig.1").exists());
+        for (id, kept) in ids.iter().zip([false, true, false]) {
+            let job = load_job(&state_dir, id).unwrap().unwrap();
+            assert_eq!(job.backup.is_some(), kept, "job {}", id);
+        }
+        let m = metrics.read().await;
+        assert_eq!((m.backups.kept, m.backups.removed), (1, 1));
+        assert_eq!(m.backups.last_run_unix_ms, now);
+    }
+}
@@ -0,0 +1,322 @@
+//! Checksum sidecar upkeep after replacement.
+//!
+//! Release folders often carry `.sfv`, `.md5`, `.sha1` or `.sha256` files
+//! listing the checksum of each file next to them. Once a file is replaced
+//! those entries no longer match, and integrity tools report every converted
+//! file as corrupt. This module finds the sidecars in the replaced file's
+//! directory that list it and either rewrites the entry with the new name
+//! and checksum or drops it.
+
+use crate::config::ChecksumSidecarPolicy;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Checksum file formats recognised by extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumKind {
+    /// `name CRC32` lines, `;` comments
+    Sfv,
+    /// `md5sum` output
+    Md5,
+    /// `sha1sum` output
+    Sha1,
+    /// `sha256sum` output
+    Sha256,
+}
+
+impl ChecksumKind {
+    /// Returns the kind of checksum file `path` is, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "sfv" => Some(ChecksumKind::Sfv),
+            "md5" => Some(ChecksumKind::Md5),
+            "sha1" => Some(ChecksumKind::Sha1),
+            "sha256" => Some(ChecksumKind::Sha256),
+            _ => None,
+        }
+    }
+
+    fn comment_prefix(self) -> &'static str {
+        match self {
+            ChecksumKind::Sfv => ";",
+            _ => "#",
+        }
+    }
+}
+
+/// One checksum entry parsed from a sidecar line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry<'a> {
+    name: &'a str,
+    /// Text between hash and name in `*sum` files: `"  "` or `" *"`
+    separator: &'a str,
+}
+
+/// Parses a line of a checksum file; `None` for comments and lines in
+/// another format.
+fn parse_entry(kind: ChecksumKind, line: &str) -> Option<Entry<'_>> {
+    if line.trim().is_empty() || line.trim_start().starts_with(kind.comment_prefix()) {
+        return None;
+    }
+    match kind {
+        ChecksumKind::Sfv => {
+            let line = line.trim_end();
+            let (name, _crc) = line.rsplit_once([' ', '\t'])?;
+            Some(Entry {
+                name: name.trim_end(),
+                separator: " ",
+            })
+        }
+        _ => {
+            let split = line.find(' ')?;
+            let rest = &line[split..];
+            let separator = if rest.starts_with(" *") || rest.starts_with("  ") {
+                &rest[..2]
+            } else {
+                &rest[..1]
+            };
+            Some(Entry {
+                name: &rest[separator.len()..],
+                separator,
+            })
+        }
+    }
+}
+
+/// Returns true if an entry name refers to `file_name` in the same directory.
+fn names_file(entry_name: &str, file_name: &str) -> bool {
+    let name = entry_name.replace('\\', "/");
+    name.strip_prefix("./").unwrap_or(&name) == file_name
+}
+
+/// Rewrites the entries for `old_name` in a checksum file's contents.
+///
+/// With `replacement` set to a new name and checksum the entry is rewritten
+/// in place; with `None` it is dropped. Other lines, including their line
+/// endings, are kept as they are.
+///
+/// # Returns
+/// The new contents, or `None` if no entry names `old_name`
+pub fn rewrite_entries(
+    contents: &str,
+    kind: ChecksumKind,
+    old_name: &str,
+    replacement: Option<(&str, &str)>,
+) -> Option<String> {
+    let mut out = String::with_capacity(contents.len());
+    let mut matched = false;
+    for raw in contents.split_inclusive('\n') {
+        let line = raw.trim_end_matches(['\r', '\n']);
+        let ending = &raw[line.len()..];
+        let Some(entry) = parse_entry(kind, line).filter(|e| names_file(e.name, old_name)) else {
+            out.push_str(raw);
+            continue;
+        };
+        matched = true;
+        if let Some((new_name, checksum)) = replacement {
+            match kind {
+                ChecksumKind::Sfv => out.push_str(&format!("{} {}", new_name, checksum)),
+                _ => out.push_str(&format!("{}{}{}", checksum, entry.separator, new_name)),
+            }
+            out.push_str(ending);
+        }
+    }
+    matched.then_some(out)
+}
+
+/// Returns true if a checksum file's contents list no files.
+fn has_no_entries(contents: &str, kind: ChecksumKind) -> bool {
+    contents.lines().all(|line| parse_entry(kind, line).is_none())
+}
+
+/// Feeds the contents of `file` to `update` a megabyte at a time.
+fn read_chunks(mut file: File, mut update: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        update(&buf[..n]);
+    }
+}
+
+/// Computes the checksum of `path` as written in a `kind` file.
+pub fn checksum_file(path: &Path, kind: ChecksumKind) -> io::Result<String> {
+    fn digest<D: Digest>(file: File) -> io::Result<String> {
+        let mut hasher = D::new();
+        read_chunks(file, |chunk| hasher.update(chunk))?;
+        Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    let file = File::open(path)?;
+    match kind {
+        ChecksumKind::Sfv => {
+            let mut hasher = crc32fast::Hasher::new();
+            read_chunks(file, |chunk| hasher.update(chunk))?;
+            Ok(format!("{:08X}", hasher.finalize()))
+        }
+        ChecksumKind::Md5 => digest::<Md5>(file),
+        ChecksumKind::Sha1 => digest::<Sha1>(file),
+        ChecksumKind::Sha256 => digest::<Sha256>(file),
+    }
+}
+
+/// Updates or removes the entries for a replaced file in the checksum files
+/// next to it.
+///
+/// `original` is the path the sidecars knew the file by; `replacement` is
+/// where the encoded file now lives, in the same directory. Each checksum is
+/// computed at most once, and only if some sidecar lists the file.
+///
+/// # Returns
+/// The sidecars that were rewritten or deleted
+///
+/// # Errors
+/// Returns an error if the directory cannot be listed or a sidecar cannot be
+/// rewritten; sidecars handled before the error keep their changes.
+pub fn update_checksum_sidecars(
+    original: &Path,
+    replacement: &Path,
+    policy: ChecksumSidecarPolicy,
+) -> io::Result<Vec<PathBuf>> {
+    let mut touched = Vec::new();
+    if policy == ChecksumSidecarPolicy::Ignore {
+        return Ok(touched);
+    }
+    let (Some(dir), Some(old_name), Some(new_name)) = (
+        original.parent(),
+        original.file_name().and_then(|n| n.to_str()),
+        replacement.file_name().and_then(|n| n.to_str()),
+    ) else {
+        return Ok(touched);
+    };
+
+    let mut sidecars: Vec<(PathBuf, ChecksumKind)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter_map(|path| ChecksumKind::from_path(&path).map(|kind| (path, kind)))
+        .collect();
+    sidecars.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut checksums: HashMap<ChecksumKind, String> = HashMap::new();
+    for (sidecar, kind) in sidecars {
+        // Sidecars that are not text are none of our business
+        let Ok(contents) = fs::read_to_string(&sidecar) else {
+            continue;
+        };
+        // Cheap check before hashing a file that may be tens of gigabytes
+        if rewrite_entries(&contents, kind, old_name, None).is_none() {
+            continue;
+        }
+        let rewritten = match policy {
+            ChecksumSidecarPolicy::Update => {
+                let checksum = match checksums.get(&kind) {
+                    Some(checksum) => checksum.clone(),
+                    None => {
+                        let checksum = checksum_file(replacement, kind)?;
+                        checksums.insert(kind, checksum.clone());
+                        checksum
+                    }
+                };
+                rewrite_entries(&contents, kind, old_name, Some((new_name, &checksum)))
+            }
+            _ => rewrite_entries(&contents, kind, old_name, None),
+        };
+        let Some(rewritten) = rewritten else {
+            continue;
+        };
+
+        if policy == ChecksumSidecarPolicy::Remove && has_no_entries(&rewritten, kind) {
+            fs::remove_file(&sidecar)?;
+        } else {
+            let mut tmp = sidecar.clone().into_os_string();
+            tmp.push(".part");
+            fs::write(&tmp, rewritten)?;
+            fs::rename(&tmp, &sidecar)?;
+        }
+        touched.push(sidecar);
+    }
+    Ok(touched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rewrite_entries_keeps_other_lines() {
+        let sfv = "; made by hand\r\nfilm.mp4 DEADBEEF\r\nother.mkv 01234567\r\n";
+        let updated =
+            rewrite_entries(sfv, ChecksumKind::Sfv, "film.mp4", Some(("film.mkv", "3610A686")));
+        assert_eq!(
+            updated.as_deref(),
+            Some("; made by hand\r\nfilm.mkv 3610A686\r\nother.mkv 01234567\r\n")
+        );
+
+        let md5 = "0123  ./film.mp4\nabcd *other.mkv\n";
+        let updated = rewrite_entries(md5, ChecksumKind::Md5, "film.mp4", Some(("film.mkv", "ff")));
+        assert_eq!(updated.as_deref(), Some("ff  film.mkv\nabcd *other.mkv\n"));
+        let dropped = rewrite_entries(md5, ChecksumKind::Md5, "other.mkv", None);
+        assert_eq!(dropped.as_deref(), Some("0123  ./film.mp4\n"));
+
+        assert_eq!(rewrite_entries(md5, ChecksumKind::Md5, "missing.mkv", None), None);
+    }
+
+    #[test]
+    fn test_update_checksum_sidecars_rewrites_each_format() {
+        let temp = TempDir::new().unwrap();
+        let original = temp.path().join("film.mp4");
+        let replacement = temp.path().join("film.mkv");
+        fs::write(&replacement, b"hello").unwrap();
+        fs::write(temp.path().join("release.sfv"), "film.mp4 DEADBEEF\n").unwrap();
+        fs::write(temp.path().join("film.md5"), "0000 *film.mp4\n").unwrap();
+        fs::write(temp.path().join("other.sha1"), "0000  other.mkv\n").unwrap();
+
+        let touched =
+            update_checksum_sidecars(&original, &replacement, ChecksumSidecarPolicy::Update)
+                .unwrap();
+        assert_eq!(touched.len(), 2);
+        assert_eq!(
+            fs::read_to_string(temp.path().join("release.sfv")).unwrap(),
+            "film.mkv 3610A686\n"
+        );
+        assert_eq!(
+            fs::read_to_string(temp.path().join("film.md5")).unwrap(),
+            "5d41402abc4b2a76b9719d911017c592 *film.mkv\n"
+        );
+        assert_eq!(
+            fs::read_to_string(temp.path().join("other.sha1")).unwrap(),
+            "0000  other.mkv\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_deletes_sidecars_left_empty() {
+        let temp = TempDir::new().unwrap();
+        let video = temp.path().join("film.mkv");
+        fs::write(&video, b"hello").unwrap();
+        fs::write(temp.path().join("film.sfv"), "; header\nfilm.mkv DEADBEEF\n").unwrap();
+        fs::write(temp.path().join("all.md5"), "00  film.mkv\n11  extras.mkv\n").unwrap();
+
+        let touched =
+            update_checksum_sidecars(&video, &video, ChecksumSidecarPolicy::Remove).unwrap();
+        assert_eq!(touched.len(), 2);
+        assert!(!temp.path().join("film.sfv").exists());
+        assert_eq!(
+            fs::read_to_string(temp.path().join("all.md5")).unwrap(),
+            "11  extras.mkv\n"
+        );
+
+        let touched =
+            update_checksum_sidecars(&video, &video, ChecksumSidecarPolicy::Ignore).unwrap();
+        assert!(touched.is_empty());
+    }
+}
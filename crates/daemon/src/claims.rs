@@ -0,0 +1,441 @@
+//! Leases that keep daemons sharing a library off each other's files.
+//!
+//! With `farm.enabled`, several daemons can scan the same share. Before a
+//! job starts encoding, its daemon claims the file with an `.av1claim` file
+//! next to it, created exclusively so only one daemon wins. The claim is a
+//! lease: the owner renews it while the job runs and removes it when the
+//! job ends. A claim not renewed within `farm.lease_secs` is stale, so the
+//! files of a daemon that crashed or lost the share go to the others. A
+//! daemon whose stale claim was taken over stops its job, and checks its
+//! claim once more just before replacing the file, so two daemons never
+//! both replace it.
+//!
+//! Files are claimed when a job starts, not when it is queued. A busy
+//! daemon's backlog stays unclaimed, and an idle daemon that found the same
+//! files takes whichever it gets to first; the busy one then drops them
+//! when their turn comes. Claims live next to the files rather than in a
+//! job store, so they work even when each daemon mounts the share at a
+//! different path.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::FarmConfig;
+use crate::encode::CancelToken;
+
+/// A daemon's claim on a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claim {
+    /// Name of the daemon holding the claim
+    pub node: String,
+    /// Run of that daemon; a claim left by an earlier run of this daemon
+    /// is stale
+    pub instance: String,
+    pub job_id: String,
+    /// Unix seconds the claim was made
+    pub claimed_at: u64,
+    /// Unix seconds the claim was last renewed
+    pub heartbeat_at: u64,
+}
+
+/// This daemon, as it appears in claims.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FarmNode {
+    pub name: String,
+    pub instance: String,
+    pub lease_secs: u64,
+}
+
+impl FarmNode {
+    /// This daemon's identity for `config`, or `None` when claims are off
+    pub fn from_config(config: &FarmConfig) -> Option<Self> {
+        config.enabled.then(|| Self {
+            name: config.node_name.clone().unwrap_or_else(host_name),
+            instance: instance_id().to_string(),
+            lease_secs: config.lease_secs.max(1),
+        })
+    }
+
+    /// How often a running job renews its claim
+    pub fn heartbeat_interval(&self) -> Duration {
+        Duration::from_secs((self.lease_secs / 3).max(1))
+    }
+
+    fn owns(&self, claim: &Claim) -> bool {
+        claim.node == self.name && claim.instance == self.instance
+    }
+
+    /// Check if `claim` no longer keeps this daemon off the file at `now`
+    pub fn is_stale(&self, claim: &Claim, now: u64) -> bool {
+        now.saturating_sub(claim.heartbeat_at) > self.lease_secs
+            || (claim.node == self.name && claim.instance != self.instance)
+    }
+
+    /// The live claim of another daemon on `video_path`, if there is one
+    pub fn claimed_elsewhere(&self, video_path: &Path, now: u64) -> Option<Claim> {
+        read_claim(video_path).filter(|claim| !self.owns(claim) && !self.is_stale(claim, now))
+    }
+
+    /// Claims `video_path` for `job_id`, replacing a stale claim.
+    ///
+    /// # Returns
+    /// `Ok(None)` once claimed, `Ok(Some(claim))` if another daemon holds a
+    /// live claim
+    ///
+    /// # Errors
+    /// An IO error if the claim file could not be written
+    pub fn try_claim(&self, video_path: &Path, job_id: &str, now: u64) -> io::Result<Option<Claim>> {
+        let path = claim_path(video_path);
+        let claim = Claim {
+            node: self.name.clone(),
+            instance: self.instance.clone(),
+            job_id: job_id.to_string(),
+            claimed_at: now,
+            heartbeat_at: now,
+        };
+        // One retry, after removing a stale claim
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(&to_json(&claim)?)?;
+                    return Ok(None);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e),
+            }
+            match read_claim(video_path) {
+                Some(held) if self.owns(&held) => return Ok(None),
+                Some(held) if !self.is_stale(&held, now) => return Ok(Some(held)),
+                // A claim being written, or one left half-written by a crash
+                None if !unreadable_claim_expired(&path, self.lease_secs) => {
+                    return Ok(Some(unknown_claim(now)))
+                }
+                _ => remove_if_exists(&path)?,
+            }
+        }
+        // Another daemon replaced the stale claim first
+        Ok(Some(read_claim(video_path).unwrap_or_else(|| unknown_claim(now))))
+    }
+
+    /// Renews this daemon's claim on `video_path`.
+    ///
+    /// # Returns
+    /// `Ok(false)` if the claim was lost to another daemon
+    pub fn renew(&self, video_path: &Path, now: u64) -> io::Result<bool> {
+        let Some(mut claim) = read_claim(video_path).filter(|claim| self.owns(claim)) else {
+            return Ok(false);
+        };
+        claim.heartbeat_at = now;
+        // Written aside and renamed, so others never read a partial claim
+        let mut temp = claim_path(video_path).into_os_string();
+        temp.push(format!(".{}", self.instance));
+        let temp = PathBuf::from(temp);
+        std::fs::write(&temp, to_json(&claim)?)?;
+        std::fs::rename(&temp, claim_path(video_path))?;
+        Ok(true)
+    }
+
+    /// Removes this daemon's claim on `video_path`, leaving other claims.
+    pub fn release(&self, video_path: &Path) -> io::Result<()> {
+        match read_claim(video_path) {
+            Some(claim) if self.owns(&claim) => remove_if_exists(&claim_path(video_path)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A claim held for a running job, renewed in the background until it is
+/// dropped, which releases it.
+pub struct ClaimLease {
+    node: FarmNode,
+    video_path: PathBuf,
+    heartbeat: tokio::task::JoinHandle<()>,
+    /// Set once another daemon is found holding the claim
+    lost: Arc<AtomicBool>,
+}
+
+impl ClaimLease {
+    /// Keeps renewing the claim `node` holds on `video_path`, cancelling
+    /// the job through `cancel` if another daemon takes the claim over.
+    pub fn hold(node: FarmNode, video_path: PathBuf, cancel: CancelToken) -> Self {
+        let lost = Arc::new(AtomicBool::new(false));
+        let heartbeat = {
+            let (node, video_path, lost) = (node.clone(), video_path.clone(), lost.clone());
+            tokio::spawn(async move {
+                let mut ticks = tokio::time::interval(node.heartbeat_interval());
+                ticks.tick().await;
+                loop {
+                    ticks.tick().await;
+                    match node.renew(&video_path, unix_now()) {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            log_warn!("Warning: Lost the claim on {:?} to another daemon", video_path);
+                            lost.store(true, Ordering::SeqCst);
+                            cancel.cancel();
+                            return;
+                        }
+                        Err(e) => log_warn!("Warning: Failed to renew the claim on {:?}: {}", video_path, e),
+                    }
+                }
+            })
+        };
+        Self {
+            node,
+            video_path,
+            heartbeat,
+            lost,
+        }
+    }
+
+    /// Returns true once another daemon has been found holding the claim.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
+    /// Renews the claim now, returning whether this daemon still holds it.
+    ///
+    /// Called just before the file is replaced: a share that stalled longer
+    /// than the lease may have let another daemon take the claim since the
+    /// last heartbeat. A claim that cannot be renewed counts as lost.
+    pub fn confirm(&self) -> bool {
+        if self.is_lost() {
+            return false;
+        }
+        let held = match self.node.renew(&self.video_path, unix_now()) {
+            Ok(held) => held,
+            Err(e) => {
+                log_warn!("Warning: Failed to renew the claim on {:?}: {}", self.video_path, e);
+                false
+            }
+        };
+        if !held {
+            self.lost.store(true, Ordering::SeqCst);
+        }
+        held
+    }
+}
+
+impl Drop for ClaimLease {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+        if let Err(e) = self.node.release(&self.video_path) {
+            log_warn!("Warning: Failed to release the claim on {:?}: {}", self.video_path, e);
+        }
+    }
+}
+
+/// Constructs the claim path for a given video file.
+///
+/// For example: `/media/movie.mkv` -> `/media/movie.mkv.av1claim`
+pub fn claim_path(video_path: &Path) -> PathBuf {
+    let mut claim_path = video_path.as_os_str().to_owned();
+    claim_path.push(".av1claim");
+    PathBuf::from(claim_path)
+}
+
+/// Reads the claim on a video file, if there is a readable one.
+pub fn read_claim(video_path: &Path) -> Option<Claim> {
+    let content = std::fs::read_to_string(claim_path(video_path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Unix time in seconds, as claims record it.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Identifies this run of the daemon in its claims.
+fn instance_id() -> &'static str {
+    static INSTANCE: OnceLock<String> = OnceLock::new();
+    INSTANCE.get_or_init(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Name of this machine, or "unknown"
+pub(crate) fn host_name() -> String {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .chain(std::env::var("HOSTNAME").ok())
+        .chain(std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn to_json(claim: &Claim) -> io::Result<Vec<u8>> {
+    serde_json::to_vec_pretty(claim).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn unknown_claim(now: u64) -> Claim {
+    Claim {
+        node: "unknown".to_string(),
+        instance: String::new(),
+        job_id: String::new(),
+        claimed_at: now,
+        heartbeat_at: now,
+    }
+}
+
+/// Whether an unreadable claim file is older than a lease
+fn unreadable_claim_expired(path: &Path, lease_secs: u64) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_none_or(|age| age.as_secs() > lease_secs)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn node(name: &str, instance: &str) -> FarmNode {
+        FarmNode {
+            name: name.to_string(),
+            instance: instance.to_string(),
+            lease_secs: 300,
+        }
+    }
+
+    #[test]
+    fn test_only_one_daemon_claims_a_file() {
+        let temp = TempDir::new().unwrap();
+        let video = temp.path().join("movie.mkv");
+        let (a, b) = (node("a", "1"), node("b", "1"));
+
+        assert_eq!(a.try_claim(&video, "job-a", 1000).unwrap(), None);
+        let held = b.try_claim(&video, "job-b", 1010).unwrap().unwrap();
+        assert_eq!(held.node, "a");
+        assert_eq!(held.job_id, "job-a");
+        assert_eq!(b.claimed_elsewhere(&video, 1010).map(|c| c.node).as_deref(), Some("a"));
+        assert_eq!(a.claimed_elsewhere(&video, 1010), None);
+
+        // Releasing someone else's claim leaves it in place
+        b.release(&video).unwrap();
+        assert!(claim_path(&video).exists());
+        a.release(&video).unwrap();
+        assert!(!claim_path(&video).exists());
+        assert_eq!(b.try_claim(&video, "job-b", 1020).unwrap(), None);
+    }
+
+    #[test]
+    fn test_stale_claims_are_taken_over() {
+        let temp = TempDir::new().unwrap();
+        let video = temp.path().join("movie.mkv");
+        let (a, b) = (node("a", "1"), node("b", "1"));
+
+        a.try_claim(&video, "job-a", 1000).unwrap();
+        assert!(a.renew(&video, 1200).unwrap());
+        // Renewed, so still live after the first lease
+        assert!(b.try_claim(&video, "job-b", 1400).unwrap().is_some());
+        assert_eq!(b.try_claim(&video, "job-b", 1501).unwrap(), None);
+        assert_eq!(read_claim(&video).unwrap().node, "b");
+        assert!(!a.renew(&video, 1510).unwrap(), "a lost its claim");
+
+        // A restarted daemon does not wait out its own old claims
+        let b_restarted = node("b", "2");
+        assert_eq!(b_restarted.try_claim(&video, "job-b2", 1520).unwrap(), None);
+        assert_eq!(read_claim(&video).unwrap().instance, "2");
+    }
+
+    #[test]
+    fn test_fresh_unreadable_claim_counts_as_held() {
+        let temp = TempDir::new().unwrap();
+        let video = temp.path().join("movie.mkv");
+        std::fs::write(claim_path(&video), "").unwrap();
+
+        let held = node("a", "1").try_claim(&video, "job-a", unix_now()).unwrap();
+        assert_eq!(held.map(|c| c.node).as_deref(), Some("unknown"));
+    }
+
+    #[tokio::test]
+    async fn test_lease_is_released_when_dropped() {
+        let temp = TempDir::new().unwrap();
+        let video = temp.path().join("movie.mkv");
+        let a = node("a", "1");
+
+        a.try_claim(&video, "job-a", unix_now()).unwrap();
+        let lease = ClaimLease::hold(a.clone(), video.clone(), CancelToken::new());
+        assert!(claim_path(&video).exists());
+        drop(lease);
+        assert!(!claim_path(&video).exists());
+    }
+
+    #[tokio::test]
+    async fn test_stolen_lease_cancels_the_job() {
+        let temp = TempDir::new().unwrap();
+        let video = temp.path().join("movie.mkv");
+        let a = FarmNode {
+            lease_secs: 1,
+            ..node("a", "1")
+        };
+
+        a.try_claim(&video, "job-a", unix_now()).unwrap();
+        let cancel = CancelToken::new();
+        let lease = ClaimLease::hold(a.clone(), video.clone(), cancel.clone());
+        assert!(lease.confirm());
+
+        // The share stalls past the lease and another daemon takes the file
+        let b = node("b", "1");
+        assert_eq!(b.try_claim(&video, "job-b", unix_now() + 400).unwrap(), None);
+
+        // The next heartbeat notices and stops the job
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !cancel.is_cancelled() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("the job is cancelled");
+        assert!(lease.is_lost());
+        assert!(!lease.confirm(), "the swap is refused");
+
+        drop(lease);
+        assert_eq!(read_claim(&video).unwrap().node, "b");
+    }
+
+    #[tokio::test]
+    async fn test_confirm_catches_a_steal_between_heartbeats() {
+        let temp = TempDir::new().unwrap();
+        let video = temp.path().join("movie.mkv");
+        let a = node("a", "1");
+
+        a.try_claim(&video, "job-a", 1000).unwrap();
+        let lease = ClaimLease::hold(a.clone(), video.clone(), CancelToken::new());
+        node("b", "1").try_claim(&video, "job-b", 2000).unwrap();
+
+        assert!(!lease.confirm());
+        assert!(lease.is_lost());
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert_eq!(FarmNode::from_config(&FarmConfig::default()), None);
+        let config = FarmConfig {
+            enabled: true,
+            node_name: Some("nas-1".to_string()),
+            lease_secs: 90,
+        };
+        let node = FarmNode::from_config(&config).unwrap();
+        assert_eq!(node.name, "nas-1");
+        assert_eq!(node.instance, instance_id());
+        assert_eq!(node.heartbeat_interval(), Duration::from_secs(30));
+    }
+}
@@ -1,15 +1,16 @@
 //! Classifier module for categorizing video source types.
 //!
 //! This module analyzes video files to determine if they are web-sourced
-//! (streaming rips, web downloads) or disc-sourced (Blu-ray, DVD rips)
-//! based on path keywords, bitrate, and resolution heuristics.
+//! (streaming rips, web downloads), disc-sourced (Blu-ray, DVD rips), or
+//! animated content based on path keywords, bitrate, resolution, and
+//! subtitle/attachment heuristics.
 
 use crate::gates::ProbeResult;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 /// Classification of video source type.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum SourceType {
     /// Web-sourced content (streaming rips, web downloads).
     /// Typically lower bitrate relative to resolution.
@@ -17,26 +18,44 @@ pub enum SourceType {
     /// Disc-sourced content (Blu-ray, DVD rips).
     /// Typically higher bitrate relative to resolution.
     DiscLike,
+    /// Animated content (anime, cartoons).
+    /// Flat shading and sharp line art; encoded with a dedicated profile.
+    Animation,
     /// Source type could not be determined.
+    #[default]
     Unknown,
 }
 
-impl Default for SourceType {
-    fn default() -> Self {
-        Self::Unknown
-    }
-}
-
 impl std::fmt::Display for SourceType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SourceType::WebLike => write!(f, "web_like"),
             SourceType::DiscLike => write!(f, "disc_like"),
+            SourceType::Animation => write!(f, "animation"),
             SourceType::Unknown => write!(f, "unknown"),
         }
     }
 }
 
+/// Whether a video is a TV episode or a standalone movie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum MediaKind {
+    /// Episode of a series (SxxExx naming or inside a season folder).
+    Series,
+    /// Standalone movie.
+    #[default]
+    Movie,
+}
+
+impl std::fmt::Display for MediaKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaKind::Series => write!(f, "series"),
+            MediaKind::Movie => write!(f, "movie"),
+        }
+    }
+}
+
 /// Keywords that indicate web-sourced content.
 const WEB_KEYWORDS: &[&str] = &[
     "webrip", "web-rip", "webdl", "web-dl", "web.dl", "web.rip",
@@ -53,6 +72,25 @@ const DISC_KEYWORDS: &[&str] = &[
     "uhd", "ultrahd", "4k.uhd", "hddvd", "hd-dvd",
 ];
 
+/// Keywords that indicate animated content anywhere in the path.
+///
+/// Checked before web/disc keywords since anime releases usually carry
+/// WEB or BD source tags as well.
+const ANIMATION_KEYWORDS: &[&str] = &[
+    "anime", "animation", "animated", "cartoon", "fansub",
+];
+
+/// Anime release groups. Only matched as a release-group token of the file
+/// name (`[Judas] Show - 01.mkv`, `Show.S01E01.1080p-Commie.mkv`), since
+/// several are ordinary words that also appear in titles.
+const ANIMATION_RELEASE_GROUPS: &[&str] = &[
+    "subsplease", "horriblesubs", "erai-raws", "judas", "commie",
+    "coalgirls", "nyaa", "ass",
+];
+
+/// Subtitle codecs typical of fansubbed animation releases.
+const ANIMATION_SUBTITLE_CODECS: &[&str] = &["ass", "ssa"];
+
 /// Bitrate threshold in kbps per megapixel for web vs disc classification.
 /// Content below this threshold (relative to resolution) is considered web-like.
 /// Typical web content: 2-8 Mbps for 1080p (~2 MP) = 1000-4000 kbps/MP
@@ -62,16 +100,27 @@ const BITRATE_THRESHOLD_KBPS_PER_MP: f32 = 6000.0;
 /// Classifies a video source based on path keywords and probe results.
 ///
 /// Classification logic:
-/// 1. Check path for web-related keywords -> WebLike
-/// 2. Check path for disc-related keywords -> DiscLike
-/// 3. Analyze bitrate vs resolution ratio:
+/// 1. Check path for animation keywords or probe for styled subs with
+///    embedded fonts -> Animation
+/// 2. Check path for web-related keywords -> WebLike
+/// 3. Check path for disc-related keywords -> DiscLike
+/// 4. Analyze bitrate vs resolution ratio:
 ///    - Low bitrate relative to resolution -> WebLike
 ///    - High bitrate relative to resolution -> DiscLike
-/// 4. If no determination can be made -> Unknown
+/// 5. If no determination can be made -> Unknown
 pub fn classify_source(path: &Path, probe: &ProbeResult) -> SourceType {
     // Convert path to lowercase string for keyword matching
     let path_str = path.to_string_lossy().to_lowercase();
 
+    // Check for animation keywords in path, anime release groups in the file
+    // name, or animation-style probe hints
+    if contains_any_keyword(&path_str, ANIMATION_KEYWORDS)
+        || has_animation_release_group(path)
+        || looks_like_animation(probe)
+    {
+        return SourceType::Animation;
+    }
+
     // Check for web keywords in path
     if contains_any_keyword(&path_str, WEB_KEYWORDS) {
         return SourceType::WebLike;
@@ -86,11 +135,192 @@ pub fn classify_source(path: &Path, probe: &ProbeResult) -> SourceType {
     classify_by_bitrate_ratio(probe)
 }
 
+/// Classifies a video as a series episode or a movie from its path.
+///
+/// A file is a series episode when its name carries an episode marker
+/// (`S01E02`, `1x02`) or it lives in a season folder (`Season 1`, `S01`,
+/// `Specials`). Everything else is treated as a movie.
+pub fn classify_media_kind(path: &Path) -> MediaKind {
+    let has_episode_marker = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(parse_episode_marker)
+        .is_some();
+
+    if has_episode_marker || season_folder_number(path).is_some() {
+        MediaKind::Series
+    } else {
+        MediaKind::Movie
+    }
+}
+
+/// Returns a key shared by all episodes of the same season of a show.
+///
+/// The key is `<show>/s<season>` where the show name is taken from the
+/// file name before the episode marker, or from the folder above the
+/// season folder. Returns `None` for movies.
+pub fn season_key(path: &Path) -> Option<String> {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+
+    if let Some((prefix, season)) = parse_episode_marker(stem) {
+        let show = normalize_show_name(prefix);
+        let show = if show.is_empty() {
+            show_folder_name(path).unwrap_or_default()
+        } else {
+            show
+        };
+        return Some(format!("{}/s{:02}", show, season));
+    }
+
+    let season = season_folder_number(path)?;
+    Some(format!("{}/s{:02}", show_folder_name(path).unwrap_or_default(), season))
+}
+
+/// Finds an episode marker (`S01E02` or `1x02`) in a file stem.
+///
+/// Returns the text preceding the marker and the season number.
+fn parse_episode_marker(stem: &str) -> Option<(&str, u32)> {
+    let bytes = stem.as_bytes();
+    let lower = stem.to_ascii_lowercase();
+    let lower = lower.as_bytes();
+
+    for start in 0..bytes.len() {
+        // Markers must not be glued to a preceding word or number
+        if start > 0 && bytes[start - 1].is_ascii_alphanumeric() {
+            continue;
+        }
+
+        // SxxExx form
+        if lower[start] == b's' {
+            let season_digits = count_digits(&lower[start + 1..]);
+            let e_idx = start + 1 + season_digits;
+            if (1..=2).contains(&season_digits)
+                && lower.get(e_idx) == Some(&b'e')
+                && (1..=3).contains(&count_digits(&lower[e_idx + 1..]))
+            {
+                let season = stem[start + 1..e_idx].parse().ok()?;
+                return Some((&stem[..start], season));
+            }
+        }
+
+        // NxNN form
+        let season_digits = count_digits(&lower[start..]);
+        let x_idx = start + season_digits;
+        if (1..=2).contains(&season_digits)
+            && lower.get(x_idx) == Some(&b'x')
+            && (2..=3).contains(&count_digits(&lower[x_idx + 1..]))
+        {
+            let season = stem[start..x_idx].parse().ok()?;
+            return Some((&stem[..start], season));
+        }
+    }
+
+    None
+}
+
+/// Counts leading ASCII digits.
+fn count_digits(bytes: &[u8]) -> usize {
+    bytes.iter().take_while(|b| b.is_ascii_digit()).count()
+}
+
+/// Returns the season number if the file's parent folder is a season folder.
+fn season_folder_number(path: &Path) -> Option<u32> {
+    let name = path.parent()?.file_name()?.to_str()?.to_lowercase();
+    let name = name.trim();
+
+    if name == "specials" {
+        return Some(0);
+    }
+
+    let digits = name
+        .strip_prefix("season")
+        .or_else(|| name.strip_prefix('s'))?
+        .trim_start_matches([' ', '.', '_', '-']);
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Returns the normalized show name from the folder layout.
+///
+/// For `Show/Season 1/episode.mkv` this is `Show`; otherwise the parent folder.
+fn show_folder_name(path: &Path) -> Option<String> {
+    let parent = path.parent()?;
+    let show_dir = if season_folder_number(path).is_some() {
+        parent.parent()?
+    } else {
+        parent
+    };
+    Some(normalize_show_name(show_dir.file_name()?.to_str()?))
+}
+
+/// Normalizes a show name so different release namings group together.
+fn normalize_show_name(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Buckets a frame size into a coarse resolution class.
+///
+/// Width is considered as well as height so that scope-cropped releases
+/// (e.g. 3840x1600) land in the class of their source.
+pub fn resolution_class(width: u32, height: u32) -> &'static str {
+    if width >= 3200 || height >= 2000 {
+        "4k"
+    } else if width >= 1600 || height >= 1000 {
+        "1080p"
+    } else if width >= 1200 || height >= 700 {
+        "720p"
+    } else {
+        "sd"
+    }
+}
+
 /// Checks if the path string contains any of the given keywords.
 fn contains_any_keyword(path_str: &str, keywords: &[&str]) -> bool {
     keywords.iter().any(|kw| path_str.contains(kw))
 }
 
+/// Checks whether the file name is tagged by a known anime release group,
+/// either bracketed (`[Group]`) or as the trailing `-Group` suffix of the stem.
+fn has_animation_release_group(path: &Path) -> bool {
+    let Some(stem) = path.file_stem() else {
+        return false;
+    };
+    let stem = stem.to_string_lossy().to_lowercase();
+
+    let mut bracketed = Vec::new();
+    let mut rest = stem.as_str();
+    while let Some(open) = rest.find('[') {
+        let Some(len) = rest[open + 1..].find(']') else {
+            break;
+        };
+        bracketed.push(rest[open + 1..open + 1 + len].trim());
+        rest = &rest[open + 1 + len + 1..];
+    }
+
+    ANIMATION_RELEASE_GROUPS.iter().any(|group| {
+        bracketed.contains(group) || stem.ends_with(&format!("-{group}"))
+    })
+}
+
+/// Checks probe results for animation hints.
+///
+/// Fansubbed animation almost always ships ASS/SSA styled subtitles with the
+/// fonts they use embedded as attachments; live-action releases rarely do.
+fn looks_like_animation(probe: &ProbeResult) -> bool {
+    probe.font_attachments > 0
+        && probe
+            .subtitle_streams
+            .iter()
+            .any(|s| ANIMATION_SUBTITLE_CODECS.contains(&s.codec_name.to_lowercase().as_str()))
+}
+
 /// Classifies source type based on bitrate to resolution ratio.
 fn classify_by_bitrate_ratio(probe: &ProbeResult) -> SourceType {
     // Get the first video stream
@@ -134,7 +364,7 @@ fn classify_by_bitrate_ratio(probe: &ProbeResult) -> SourceType {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::gates::{AudioStream, FormatInfo, VideoStream};
+    use crate::gates::{AudioStream, FormatInfo, SubtitleStream, VideoStream};
     use proptest::prelude::*;
     use std::path::PathBuf;
 
@@ -145,6 +375,10 @@ mod tests {
             width,
             height,
             bitrate_kbps,
+            pix_fmt: None,
+            duration_secs: None,
+            attached_pic: false,
+            ..Default::default()
         }
     }
 
@@ -156,6 +390,8 @@ mod tests {
         ProbeResult {
             video_streams,
             audio_streams,
+            subtitle_streams: vec![],
+            font_attachments: 0,
             format: FormatInfo {
                 duration_secs: 3600.0,
                 size_bytes: 5_000_000_000,
@@ -182,6 +418,10 @@ mod tests {
                 width,
                 height,
                 bitrate_kbps: bitrate,
+                pix_fmt: None,
+                duration_secs: None,
+                attached_pic: false,
+                ..Default::default()
             })
     }
 
@@ -190,6 +430,8 @@ mod tests {
         prop::collection::vec(video_stream_strategy(), 0..3).prop_map(|video_streams| ProbeResult {
             video_streams,
             audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
             format: FormatInfo {
                 duration_secs: 3600.0,
                 size_bytes: 5_000_000_000,
@@ -215,12 +457,15 @@ mod tests {
             // Verify the result is exactly one of the three variants
             let is_valid = matches!(
                 result,
-                SourceType::WebLike | SourceType::DiscLike | SourceType::Unknown
+                SourceType::WebLike
+                    | SourceType::DiscLike
+                    | SourceType::Animation
+                    | SourceType::Unknown
             );
 
             prop_assert!(
                 is_valid,
-                "classify_source must return exactly one of WebLike, DiscLike, or Unknown"
+                "classify_source must return exactly one of WebLike, DiscLike, Animation, or Unknown"
             );
 
             // Verify the result is deterministic (calling again gives same result)
@@ -232,9 +477,13 @@ mod tests {
         }
 
         // Additional property: web keywords always result in WebLike
+        // Note: base_path must not contain animation keywords, which take precedence
         #[test]
         fn prop_web_keywords_classify_as_weblike(
-            base_path in "[a-zA-Z0-9]{1,10}",
+            base_path in "[a-zA-Z0-9]{1,10}".prop_filter(
+                "base_path must not contain animation keywords",
+                |s| !ANIMATION_KEYWORDS.iter().any(|kw| s.to_lowercase().contains(kw))
+            ),
             web_keyword in prop::sample::select(vec![
                 "webrip", "web-dl", "webdl", "amzn", "netflix", "nf", "hulu",
                 "dsnp", "disney", "atvp", "hmax", "hbo", "web"
@@ -261,8 +510,9 @@ mod tests {
                 "base_path must not contain web keywords",
                 |s| {
                     let lower = s.to_lowercase();
-                    // Exclude paths that contain web keywords (which take precedence)
+                    // Exclude paths that contain web or animation keywords (which take precedence)
                     !WEB_KEYWORDS.iter().any(|kw| lower.contains(kw))
+                        && !ANIMATION_KEYWORDS.iter().any(|kw| lower.contains(kw))
                 }
             ),
             disc_keyword in prop::sample::select(vec![
@@ -374,10 +624,138 @@ mod tests {
         assert_eq!(classify_source(&path, &probe), SourceType::WebLike);
     }
 
+    #[test]
+    fn test_classify_animation_keyword_in_path() {
+        // Animation keywords win over web keywords in the same path
+        let path = PathBuf::from("/media/anime/[SubsPlease] Show - 01 (1080p) WEB.mkv");
+        let probe = make_probe_result(
+            vec![make_video_stream("h264", 1920, 1080, Some(3000.0))],
+            vec![],
+        );
+
+        assert_eq!(classify_source(&path, &probe), SourceType::Animation);
+    }
+
+    #[test]
+    fn test_classify_animation_release_group_tokens() {
+        let probe = make_probe_result(
+            vec![make_video_stream("h264", 1920, 1080, Some(3000.0))],
+            vec![],
+        );
+
+        let path = PathBuf::from("/media/shows/Show/[Judas] Show - 01 (1080p).mkv");
+        assert_eq!(classify_source(&path, &probe), SourceType::Animation);
+        let path = PathBuf::from("/media/shows/Show/Show.S01E01.1080p.WEB-Commie.mkv");
+        assert_eq!(classify_source(&path, &probe), SourceType::Animation);
+    }
+
+    #[test]
+    fn test_classify_live_action_and_bdmv_are_not_animation() {
+        // Release-group names that are ordinary words in a title don't count
+        let path = PathBuf::from(
+            "/movies/Judas and the Black Messiah (2021)/Judas and the Black Messiah (2021).mkv",
+        );
+        let probe = make_probe_result(
+            vec![make_video_stream("h264", 1920, 1080, Some(3000.0))],
+            vec![],
+        );
+        assert_eq!(classify_source(&path, &probe), SourceType::WebLike);
+
+        // A Blu-ray folder layout is a disc source, not an animation hint
+        let path = PathBuf::from("/movies/Movie (2020)/BDMV/STREAM/00001.m2ts");
+        let probe = make_probe_result(
+            vec![make_video_stream("h264", 1920, 1080, Some(30000.0))],
+            vec![],
+        );
+        assert_eq!(classify_source(&path, &probe), SourceType::DiscLike);
+    }
+
+    #[test]
+    fn test_classify_animation_by_styled_subs_and_fonts() {
+        let path = PathBuf::from("/media/shows/Show.S01E01.1080p.mkv");
+        let mut probe = make_probe_result(
+            vec![make_video_stream("hevc", 1920, 1080, Some(25000.0))],
+            vec![],
+        );
+        probe.subtitle_streams = vec![SubtitleStream {
+            codec_name: "ass".to_string(),
+            stream_index: None,
+            language: None,
+            forced: false,
+        }];
+        probe.font_attachments = 3;
+
+        assert_eq!(classify_source(&path, &probe), SourceType::Animation);
+
+        // Styled subs without embedded fonts are not enough on their own
+        probe.font_attachments = 0;
+        assert_eq!(classify_source(&path, &probe), SourceType::DiscLike);
+    }
+
+    #[test]
+    fn test_classify_media_kind() {
+        assert_eq!(
+            classify_media_kind(Path::new("/tv/Show/Show.S01E02.1080p.mkv")),
+            MediaKind::Series
+        );
+        assert_eq!(
+            classify_media_kind(Path::new("/tv/Show/show - 2x05 - title.mkv")),
+            MediaKind::Series
+        );
+        assert_eq!(
+            classify_media_kind(Path::new("/tv/Show/Season 3/episode name.mkv")),
+            MediaKind::Series
+        );
+        assert_eq!(
+            classify_media_kind(Path::new("/movies/Movie (2019)/Movie.2019.1080p.mkv")),
+            MediaKind::Movie
+        );
+        // Resolution and codec tokens are not episode markers
+        assert_eq!(
+            classify_media_kind(Path::new("/movies/Movie.1920x1080.x265.mkv")),
+            MediaKind::Movie
+        );
+    }
+
+    #[test]
+    fn test_season_key_groups_episodes() {
+        let e1 = season_key(Path::new("/tv/Show.Name.S02E01.WEB.mkv"));
+        let e2 = season_key(Path::new("/tv/Show Name - S02E02 - Pilot.mkv"));
+        let other_season = season_key(Path::new("/tv/Show.Name.S03E01.mkv"));
+
+        assert_eq!(e1, Some("show name/s02".to_string()));
+        assert_eq!(e1, e2);
+        assert_ne!(e1, other_season);
+    }
+
+    #[test]
+    fn test_season_key_from_folders() {
+        assert_eq!(
+            season_key(Path::new("/tv/The Show/Season 1/01 - Pilot.mkv")),
+            Some("the show/s01".to_string())
+        );
+        assert_eq!(
+            season_key(Path::new("/tv/The Show/Season 1/S01E01.mkv")),
+            Some("the show/s01".to_string())
+        );
+        assert_eq!(season_key(Path::new("/movies/Movie (2019)/Movie.mkv")), None);
+    }
+
+    #[test]
+    fn test_resolution_class() {
+        assert_eq!(resolution_class(3840, 2160), "4k");
+        assert_eq!(resolution_class(3840, 1600), "4k");
+        assert_eq!(resolution_class(1920, 1080), "1080p");
+        assert_eq!(resolution_class(1920, 800), "1080p");
+        assert_eq!(resolution_class(1280, 720), "720p");
+        assert_eq!(resolution_class(720, 480), "sd");
+    }
+
     #[test]
     fn test_source_type_display() {
         assert_eq!(format!("{}", SourceType::WebLike), "web_like");
         assert_eq!(format!("{}", SourceType::DiscLike), "disc_like");
+        assert_eq!(format!("{}", SourceType::Animation), "animation");
         assert_eq!(format!("{}", SourceType::Unknown), "unknown");
     }
 
@@ -0,0 +1,134 @@
+//! Side-by-side comparison stills for encodes awaiting approval.
+//!
+//! Before approving an encode it helps to look at it next to the original.
+//! For each held job a few frames are grabbed at evenly spaced timestamps
+//! from both files and stacked horizontally, original on the left and encode
+//! on the right, at full resolution. The PNGs go into a `{id}.compare`
+//! directory next to the job's JSON in the job state directory and are
+//! removed once the job is approved or rejected.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::tools::Tool;
+
+/// Suffix of the per-job directory holding comparison stills.
+pub const COMPARE_DIR_SUFFIX: &str = ".compare";
+
+/// Directory holding the comparison stills of job `id`.
+pub fn comparison_dir(state_dir: &Path, id: &str) -> PathBuf {
+    state_dir.join(format!("{}{}", id, COMPARE_DIR_SUFFIX))
+}
+
+/// Evenly spaced timestamps in seconds, avoiding the very start and end
+/// where there are usually titles or black frames.
+pub fn still_timestamps(duration_secs: f64, count: u32) -> Vec<f64> {
+    if duration_secs <= 0.0 {
+        return Vec::new();
+    }
+    (1..=count)
+        .map(|i| duration_secs * i as f64 / (count + 1) as f64)
+        .collect()
+}
+
+/// Build an ffmpeg command writing one frame at `at_secs` of `original` and
+/// `encode` side by side into `output`.
+pub fn build_still_command(original: &Path, encode: &Path, at_secs: f64, output: &Path) -> Command {
+    let at = format!("{:.3}", at_secs);
+    let mut cmd = Tool::Ffmpeg.command();
+    cmd.arg("-hide_banner")
+        .arg("-nostdin")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-y")
+        .arg("-ss")
+        .arg(&at)
+        .arg("-i")
+        .arg(original)
+        .arg("-ss")
+        .arg(&at)
+        .arg("-i")
+        .arg(encode)
+        .arg("-filter_complex")
+        .arg("[0:v:0][1:v:0]hstack=inputs=2")
+        .arg("-frames:v")
+        .arg("1")
+        .arg(output);
+    cmd
+}
+
+/// Write `count` comparison stills of `original` and `encode` into `dir`.
+///
+/// # Returns
+/// Paths of the stills written, in timestamp order
+///
+/// # Errors
+/// Returns an error if the directory cannot be created or ffmpeg fails on
+/// any of the stills.
+pub fn write_comparison_stills(
+    original: &Path,
+    encode: &Path,
+    duration_secs: f64,
+    count: u32,
+    dir: &Path,
+) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let mut stills = Vec::new();
+    for (i, at_secs) in still_timestamps(duration_secs, count).into_iter().enumerate() {
+        let still = dir.join(format!("{:02}_{:05.0}s.png", i + 1, at_secs));
+        let output = build_still_command(original, encode, at_secs, &still)
+            .stdin(Stdio::null())
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "ffmpeg failed at {:.1}s: {}",
+                at_secs,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        stills.push(still);
+    }
+    Ok(stills)
+}
+
+/// Removes the comparison stills of job `id`, if any.
+pub fn remove_comparison(state_dir: &Path, id: &str) {
+    let _ = fs::remove_dir_all(comparison_dir(state_dir, id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_still_timestamps_are_spread_out() {
+        assert_eq!(still_timestamps(400.0, 3), vec![100.0, 200.0, 300.0]);
+        assert!(still_timestamps(0.0, 3).is_empty());
+        assert!(still_timestamps(400.0, 0).is_empty());
+    }
+
+    #[test]
+    fn test_build_still_command_stacks_both_inputs() {
+        let cmd = build_still_command(
+            Path::new("/media/a.mkv"),
+            Path::new("/tmp/a.av1.mkv"),
+            12.5,
+            Path::new("/jobs/x.compare/01_00012s.png"),
+        );
+        assert_eq!(cmd.get_program(), "ffmpeg");
+
+        let args: Vec<String> = cmd
+            .get_args()
+            .filter_map(|arg| arg.to_str().map(String::from))
+            .collect();
+        assert!(args.windows(4).any(|w| w == ["-ss", "12.500", "-i", "/media/a.mkv"]));
+        assert!(args.windows(4).any(|w| w == ["-ss", "12.500", "-i", "/tmp/a.av1.mkv"]));
+        assert!(args.windows(2).any(|w| w == ["-frames:v", "1"]));
+        assert_eq!(
+            args.last().map(String::as_str),
+            Some("/jobs/x.compare/01_00012s.png")
+        );
+    }
+}
@@ -61,6 +61,77 @@ impl ConcurrencyPlan {
     }
 }
 
+/// Seconds of content per Av1an worker when workers are scaled to the file
+const SECS_PER_WORKER: f64 = 600.0;
+
+/// Bytes of input per Av1an worker when the duration is unknown
+const BYTES_PER_WORKER: u64 = 1024 * 1024 * 1024;
+
+/// Resources for one job, derived from the plan and the file being encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobPlan {
+    /// Number of Av1an workers for this job
+    pub av1an_workers: u32,
+}
+
+impl ConcurrencyPlan {
+    /// Workers shared by all running jobs: the planned workers per job
+    /// times the planned number of jobs
+    pub fn worker_budget(&self) -> u32 {
+        self.av1an_workers * self.max_concurrent_jobs
+    }
+
+    /// Plan a job for a file of the given duration and size
+    ///
+    /// Av1an gains nothing from more workers than the file has chunks to
+    /// hand out, and every worker pays its own startup cost, so short files
+    /// get one worker per ten minutes of content. The size stands in for
+    /// the duration when that is unknown. Never more than `av1an_workers`.
+    pub fn plan_job(&self, duration_secs: f64, size_bytes: u64) -> JobPlan {
+        let wanted = if duration_secs > 0.0 {
+            (duration_secs / SECS_PER_WORKER).ceil() as u32
+        } else {
+            size_bytes.div_ceil(BYTES_PER_WORKER) as u32
+        };
+        JobPlan {
+            av1an_workers: wanted.clamp(1, self.av1an_workers.max(1)),
+        }
+    }
+}
+
+/// Split of the worker budget between one lane for long files and several
+/// lanes for short ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LanePlan {
+    /// Workers of the single long-file lane
+    pub big_lane_workers: u32,
+    /// Number of short-file lanes
+    pub small_lane_slots: u32,
+    /// Workers of each short-file lane
+    pub small_lane_workers: u32,
+}
+
+impl ConcurrencyPlan {
+    /// Split the worker budget into lanes, or `None` for no lanes
+    ///
+    /// The long-file lane gets half the budget, since a film keeps that many
+    /// workers busy; the short-file lanes share the other half. Every lane
+    /// gets at least one worker, so on a small budget the lanes together
+    /// may use a little more than the budget.
+    pub fn lanes(&self, small_lane_slots: u32) -> Option<LanePlan> {
+        if small_lane_slots == 0 {
+            return None;
+        }
+        let budget = self.worker_budget().max(2);
+        let big_lane_workers = budget.div_ceil(2);
+        Some(LanePlan {
+            big_lane_workers,
+            small_lane_slots,
+            small_lane_workers: ((budget - big_lane_workers) / small_lane_slots).max(1),
+        })
+    }
+}
+
 /// Derive worker count based on core count
 /// - 8 workers for 32+ cores
 /// - 4 workers otherwise
@@ -97,7 +168,7 @@ pub fn derive_plan(cfg: &Config) -> ConcurrencyPlan {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{Av1anConfig, CpuConfig, EncoderSafetyConfig, GatesConfig, PathsConfig, ScanConfig};
+    use crate::config::{AudioConfig, Av1anConfig, CpuConfig, EncoderSafetyConfig, FarmConfig, GatesConfig, OutputConfig, PathsConfig, ScanConfig, KillSwitchConfig, StagingConfig, StorageConfig, OrderingConfig, DiscoveryConfig, CgroupConfig, PoliteConfig, ToolsConfig, ProcessConfig, TriageConfig, PixelFormatConfig, QuotaConfig, RunAsConfig, SubtitleConfig, TelemetryConfig, ThermalConfig, TorrentConfig, ValidationConfig};
     use proptest::prelude::*;
 
     // **Feature: av1-super-daemon, Property 1: Concurrency Plan Derivation**
@@ -115,6 +186,8 @@ mod tests {
             cores in 1u32..256,
         ) {
             let cfg = Config {
+                profile: None,
+                read_only: false,
                 cpu: CpuConfig {
                     logical_cores: Some(cores),
                     target_cpu_utilization: 0.85,
@@ -122,11 +195,33 @@ mod tests {
                 av1an: Av1anConfig {
                     workers_per_job: 0,      // auto-derive
                     max_concurrent_jobs: 0,  // auto-derive
+                    ..Default::default()
                 },
                 encoder_safety: EncoderSafetyConfig::default(),
                 paths: PathsConfig::default(),
                 scan: ScanConfig::default(),
                 gates: GatesConfig::default(),
+                output: OutputConfig::default(),
+                thermal: ThermalConfig::default(),
+                validation: ValidationConfig::default(),
+                audio: AudioConfig::default(),
+                pixel_format: PixelFormatConfig::default(),
+                subtitles: SubtitleConfig::default(),
+                torrent: TorrentConfig::default(),
+                run_as: RunAsConfig::default(),
+                telemetry: TelemetryConfig::default(),
+                kill_switch: KillSwitchConfig::default(),
+                quotas: QuotaConfig::default(),
+                farm: FarmConfig::default(),
+                staging: StagingConfig::default(),
+                storage: StorageConfig::default(),
+                ordering: OrderingConfig::default(),
+                discovery: DiscoveryConfig::default(),
+                cgroups: CgroupConfig::default(),
+                polite: PoliteConfig::default(),
+                tools: ToolsConfig::default(),
+                process: ProcessConfig::default(),
+                triage: TriageConfig::default(),
             };
 
             let plan = derive_plan(&cfg);
@@ -167,6 +262,8 @@ mod tests {
             explicit_jobs in 1u32..16,
         ) {
             let cfg = Config {
+                profile: None,
+                read_only: false,
                 cpu: CpuConfig {
                     logical_cores: Some(cores),
                     target_cpu_utilization: 0.85,
@@ -174,11 +271,33 @@ mod tests {
                 av1an: Av1anConfig {
                     workers_per_job: explicit_workers,
                     max_concurrent_jobs: explicit_jobs,
+                    ..Default::default()
                 },
                 encoder_safety: EncoderSafetyConfig::default(),
                 paths: PathsConfig::default(),
                 scan: ScanConfig::default(),
                 gates: GatesConfig::default(),
+                output: OutputConfig::default(),
+                thermal: ThermalConfig::default(),
+                validation: ValidationConfig::default(),
+                audio: AudioConfig::default(),
+                pixel_format: PixelFormatConfig::default(),
+                subtitles: SubtitleConfig::default(),
+                torrent: TorrentConfig::default(),
+                run_as: RunAsConfig::default(),
+                telemetry: TelemetryConfig::default(),
+                kill_switch: KillSwitchConfig::default(),
+                quotas: QuotaConfig::default(),
+                farm: FarmConfig::default(),
+                staging: StagingConfig::default(),
+                storage: StorageConfig::default(),
+                ordering: OrderingConfig::default(),
+                discovery: DiscoveryConfig::default(),
+                cgroups: CgroupConfig::default(),
+                polite: PoliteConfig::default(),
+                tools: ToolsConfig::default(),
+                process: ProcessConfig::default(),
+                triage: TriageConfig::default(),
             };
 
             let plan = derive_plan(&cfg);
@@ -211,6 +330,8 @@ mod tests {
             raw_utilization in -1.0f32..3.0,
         ) {
             let cfg = Config {
+                profile: None,
+                read_only: false,
                 cpu: CpuConfig {
                     logical_cores: Some(cores),
                     target_cpu_utilization: raw_utilization,
@@ -220,6 +341,27 @@ mod tests {
                 paths: PathsConfig::default(),
                 scan: ScanConfig::default(),
                 gates: GatesConfig::default(),
+                output: OutputConfig::default(),
+                thermal: ThermalConfig::default(),
+                validation: ValidationConfig::default(),
+                audio: AudioConfig::default(),
+                pixel_format: PixelFormatConfig::default(),
+                subtitles: SubtitleConfig::default(),
+                torrent: TorrentConfig::default(),
+                run_as: RunAsConfig::default(),
+                telemetry: TelemetryConfig::default(),
+                kill_switch: KillSwitchConfig::default(),
+                quotas: QuotaConfig::default(),
+                farm: FarmConfig::default(),
+                staging: StagingConfig::default(),
+                storage: StorageConfig::default(),
+                ordering: OrderingConfig::default(),
+                discovery: DiscoveryConfig::default(),
+                cgroups: CgroupConfig::default(),
+                polite: PoliteConfig::default(),
+                tools: ToolsConfig::default(),
+                process: ProcessConfig::default(),
+                triage: TriageConfig::default(),
             };
 
             let plan = derive_plan(&cfg);
@@ -245,4 +387,68 @@ mod tests {
             );
         }
     }
+
+    // A job never gets more workers than planned, never none, and a longer
+    // file never gets fewer workers than a shorter one
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(100))]
+
+        #[test]
+        fn prop_job_plan_within_plan(
+            av1an_workers in 1u32..32,
+            duration_secs in 0.0f64..20_000.0,
+            extra_secs in 0.0f64..20_000.0,
+            size_bytes in 0u64..100_000_000_000,
+        ) {
+            let plan = ConcurrencyPlan {
+                total_cores: 32,
+                target_threads: 27,
+                av1an_workers,
+                max_concurrent_jobs: 2,
+            };
+
+            let job = plan.plan_job(duration_secs, size_bytes);
+            prop_assert!(job.av1an_workers >= 1 && job.av1an_workers <= av1an_workers);
+
+            let longer = plan.plan_job(duration_secs + extra_secs, size_bytes);
+            prop_assert!(longer.av1an_workers >= job.av1an_workers);
+        }
+    }
+
+    #[test]
+    fn test_job_plan_scales_with_duration() {
+        let plan = ConcurrencyPlan {
+            total_cores: 32,
+            target_threads: 27,
+            av1an_workers: 8,
+            max_concurrent_jobs: 1,
+        };
+        assert_eq!(plan.worker_budget(), 8);
+
+        // 22 minute episode, two hour film, and unknown duration
+        assert_eq!(plan.plan_job(22.0 * 60.0, 200_000_000).av1an_workers, 3);
+        assert_eq!(plan.plan_job(2.0 * 3600.0, 20_000_000_000).av1an_workers, 8);
+        assert_eq!(plan.plan_job(0.0, 200_000_000).av1an_workers, 1);
+    }
+
+    #[test]
+    fn test_lanes_split_worker_budget() {
+        let plan = ConcurrencyPlan {
+            total_cores: 64,
+            target_threads: 54,
+            av1an_workers: 8,
+            max_concurrent_jobs: 2,
+        };
+        assert_eq!(plan.lanes(0), None);
+        assert_eq!(
+            plan.lanes(2),
+            Some(LanePlan {
+                big_lane_workers: 8,
+                small_lane_slots: 2,
+                small_lane_workers: 4,
+            })
+        );
+        // More short lanes than spare workers still get one each
+        assert_eq!(plan.lanes(16).unwrap().small_lane_workers, 1);
+    }
 }
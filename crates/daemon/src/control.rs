@@ -0,0 +1,129 @@
+//! Global daemon controls set through the API.
+//!
+//! Pausing the queue holds every job that has not started yet once it gets
+//! its slot, so the queue keeps its order; jobs already encoding run to the
+//! end. Requesting a scan wakes the scan loop early instead of waiting out
+//! `scan.scan_interval_secs`. Both are kept in memory only: a restarted
+//! daemon runs with the queue resumed.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Notify;
+
+/// Pause and scan requests shared by the API, the executor and the scan loop
+#[derive(Debug, Default)]
+pub struct DaemonControl {
+    paused: AtomicBool,
+    scan_pending: AtomicBool,
+    resumed: Notify,
+    scan: Notify,
+}
+
+impl DaemonControl {
+    /// Creates controls with the queue running and no scan requested.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Holds jobs that have not started yet.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Lets held jobs start again.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.resumed.notify_waiters();
+    }
+
+    /// Returns true while the queue is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Waits until the queue is not paused.
+    ///
+    /// # Returns
+    /// True if the call had to wait
+    pub async fn wait_until_resumed(&self) -> bool {
+        let mut waited = false;
+        loop {
+            let resumed = self.resumed.notified();
+            tokio::pin!(resumed);
+            resumed.as_mut().enable();
+            if !self.is_paused() {
+                return waited;
+            }
+            waited = true;
+            resumed.await;
+        }
+    }
+
+    /// Asks the scan loop to start a cycle now. Requests made while one is
+    /// pending are merged into it.
+    pub fn request_scan(&self) {
+        self.scan_pending.store(true, Ordering::SeqCst);
+        self.scan.notify_one();
+    }
+
+    /// Returns true if a requested scan has not started yet.
+    pub fn scan_pending(&self) -> bool {
+        self.scan_pending.load(Ordering::SeqCst)
+    }
+
+    /// Waits for a scan request.
+    pub async fn scan_requested(&self) {
+        self.scan.notified().await;
+    }
+
+    /// Marks the requested scan as started.
+    pub fn scan_started(&self) {
+        self.scan_pending.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_paused_queue_waits_for_resume() {
+        let control = Arc::new(DaemonControl::new());
+        assert!(!control.wait_until_resumed().await);
+
+        control.pause();
+        let waiting = {
+            let control = control.clone();
+            tokio::spawn(async move { control.wait_until_resumed().await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        control.resume();
+        let waited = tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("waiting job should resume")
+            .unwrap();
+        assert!(waited);
+    }
+
+    #[tokio::test]
+    async fn test_scan_request_is_kept_until_taken() {
+        let control = DaemonControl::new();
+        control.request_scan();
+        control.request_scan();
+        assert!(control.scan_pending());
+
+        // Made before anyone waited, the request is still seen
+        tokio::time::timeout(Duration::from_secs(1), control.scan_requested())
+            .await
+            .expect("scan request should be seen");
+        control.scan_started();
+        assert!(!control.scan_pending());
+        assert!(tokio::time::timeout(Duration::from_millis(20), control.scan_requested())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Library coverage: how much of each library root is already AV1.
+//!
+//! Every video file under a root falls in one of three buckets:
+//!
+//! - **converted**: its `.av1skip` marker says `already_av1`. Files the daemon
+//!   encoded land here too, since the next scan finds them already AV1.
+//! - **skipped**: any other marker (size gate, probe failure, too small, or a
+//!   marker without a code).
+//! - **remaining**: no marker yet, including files queued or encoding.
+//!
+//! Skipped files are left out of the completion percentage because the
+//! daemon will never convert them.
+
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::metrics::{JobMetrics, LibraryCoverage};
+use crate::scan::{has_skip_marker, has_video_extension};
+use crate::skip_marker::{read_skip_marker_code, SkipCode};
+
+/// Stage name of jobs whose sizes give a real savings ratio.
+const COMPLETED_STAGE: &str = "completed";
+
+/// Counts converted, skipped, and remaining files under each root.
+///
+/// `savings_ratio` is the fraction of a remaining file's size an encode is
+/// expected to save; see [`expected_savings_ratio`].
+pub fn measure_coverage<S: AsRef<str>>(
+    roots: &[PathBuf],
+    extensions: &[S],
+    savings_ratio: f64,
+) -> Vec<LibraryCoverage> {
+    roots
+        .iter()
+        .map(|root| measure_root(root, extensions, savings_ratio))
+        .collect()
+}
+
+fn measure_root<S: AsRef<str>>(root: &Path, extensions: &[S], savings_ratio: f64) -> LibraryCoverage {
+    let mut coverage = LibraryCoverage {
+        root: root.display().to_string(),
+        ..Default::default()
+    };
+
+    // Same hidden-directory rule as the scanner
+    let walker = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        !(entry.file_type().is_dir()
+            && entry.depth() > 0
+            && entry.file_name().to_str().is_some_and(|n| n.starts_with('.')))
+    });
+
+    for entry in walker.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !entry.file_type().is_file() || !has_video_extension(path, extensions) {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+
+        if !has_skip_marker(path) {
+            coverage.remaining_files += 1;
+            coverage.remaining_bytes += size;
+        } else if read_skip_marker_code(path) == Some(SkipCode::AlreadyAv1) {
+            coverage.converted_files += 1;
+            coverage.converted_bytes += size;
+        } else {
+            coverage.skipped_files += 1;
+        }
+    }
+
+    let eligible = coverage.converted_files + coverage.remaining_files;
+    coverage.percent_converted = if eligible > 0 {
+        (coverage.converted_files as f64 / eligible as f64 * 100.0) as f32
+    } else {
+        100.0
+    };
+    coverage.est_remaining_savings_bytes = (coverage.remaining_bytes as f64 * savings_ratio) as u64;
+    coverage
+}
+
+/// Average fraction of the original size saved by completed encodes.
+///
+/// With no completed encodes yet, falls back to `1 - max_size_ratio`: the
+/// least an accepted encode can save.
+pub fn expected_savings_ratio(jobs: &[JobMetrics], max_size_ratio: f32) -> f64 {
+    let ratios: Vec<f64> = jobs
+        .iter()
+        .filter(|j| j.stage == COMPLETED_STAGE && j.size_in_bytes_before > 0 && j.size_in_bytes_after > 0)
+        .map(|j| 1.0 - j.size_in_bytes_after as f64 / j.size_in_bytes_before as f64)
+        .collect();
+    if ratios.is_empty() {
+        return (1.0 - max_size_ratio as f64).clamp(0.0, 1.0);
+    }
+    (ratios.iter().sum::<f64>() / ratios.len() as f64).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skip_marker::{write_skip_marker, write_skip_marker_with_code};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn video(dir: &Path, name: &str, bytes: usize) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, vec![0u8; bytes]).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_measure_coverage_buckets() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("Movies");
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+
+        let av1 = video(&root, "a.mkv", 100);
+        write_skip_marker_with_code(&av1, SkipCode::AlreadyAv1).unwrap();
+        let rejected = video(&root, "b.mkv", 100);
+        write_skip_marker_with_code(&rejected, SkipCode::SizeGateRejected).unwrap();
+        let legacy = video(&root, "c.mkv", 100);
+        write_skip_marker(&legacy).unwrap();
+        video(&root, "d.mp4", 1000);
+        video(&root, "e.mkv", 3000);
+        video(&root, "notes.txt", 10);
+        video(&root.join(".hidden"), "f.mkv", 10);
+
+        let coverage = measure_coverage(std::slice::from_ref(&root), &[".mkv", ".mp4"], 0.5);
+        let movies = &coverage[0];
+        assert_eq!(movies.root, root.display().to_string());
+        assert_eq!(movies.converted_files, 1);
+        assert_eq!(movies.skipped_files, 2);
+        assert_eq!(movies.remaining_files, 2);
+        assert_eq!(movies.remaining_bytes, 4000);
+        assert_eq!(movies.est_remaining_savings_bytes, 2000);
+        assert!((movies.percent_converted - 33.33).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_empty_root_is_complete() {
+        let temp = TempDir::new().unwrap();
+        let coverage = measure_coverage(&[temp.path().to_path_buf()], &[".mkv"], 0.5);
+        assert_eq!(coverage[0].percent_converted, 100.0);
+    }
+
+    #[test]
+    fn test_expected_savings_ratio() {
+        let job = |stage: &str, before: u64, after: u64| JobMetrics {
+            id: String::new(),
+            input_path: String::new(),
+            stage: stage.to_string(),
+            progress: 0.0,
+            fps: 0.0,
+            bitrate_kbps: 0.0,
+            crf: 8,
+            encoder: "svt-av1".to_string(),
+            workers: 4,
+            est_remaining_secs: 0.0,
+            frames_encoded: 0,
+            total_frames: 0,
+            size_in_bytes_before: before,
+            size_in_bytes_after: after,
+            vmaf: None,
+            psnr: None,
+            ssim: None,
+            tags: vec![],
+            energy_joules: 0.0,
+            quality_flag: None,
+        };
+        assert!((expected_savings_ratio(&[], 0.95) - 0.05).abs() < 1e-6);
+
+        let jobs = vec![
+            job("completed", 1000, 400),
+            job("completed", 1000, 600),
+            job("encoding", 1000, 0),
+        ];
+        assert!((expected_savings_ratio(&jobs, 0.95) - 0.5).abs() < 1e-9);
+    }
+}
@@ -2,26 +2,42 @@
 //!
 //! Provides the daemon entry point, startup sequence, and main processing loop.
 
-use crate::classify::classify_source;
-use crate::config::{Config, ConfigError};
+use crate::backup_retention::{run_backup_retention, RetentionPolicy, RETENTION_INTERVAL_SECS};
+use crate::config::{Config, ConfigError, ThermalAction};
 use crate::concurrency::{derive_plan, ConcurrencyPlan};
-use crate::gates::{check_gates, probe_file, GateResult, GatesConfig as DaemonGatesConfig};
-use crate::job_executor::{Job, JobError, JobExecutor};
-use crate::jobs::{create_job, job_exists_for_path, load_jobs, save_job};
-use crate::metrics::{collect_system_metrics, new_shared_metrics, SharedMetrics};
-use crate::metrics_server::run_metrics_server;
-use crate::scan::scan_libraries;
-use crate::skip_marker::{write_skip_marker, write_why_sidecar};
-use crate::stability::{check_stability, StabilityResult};
-use crate::startup::{run_startup_checks, StartupError};
+use crate::encode::terminate_all_groups;
+use crate::instance_lock::{InstanceLock, InstanceLockError};
+use crate::dependencies::DependencyRules;
+use crate::energy::{attribute_energy, EnergyMeter, POWERCAP_ROOT};
+use crate::job_executor::{Job, JobError, JobExecutor, JobExecutorConfig};
+use crate::jobs::update_job;
+use crate::journal::{recover_interrupted_jobs, RecoveryAction};
+use crate::discovery::advertise;
+use crate::kill_switch::{kill_switch_engaged, KillSwitch, KILL_SWITCH_POLL_SECS};
+use crate::metrics::{ControlMetrics, MetricsSnapshot, SharedMetrics};
+use crate::system_stats::{SystemSampler, WatchedPath, ROLE_LIBRARY, ROLE_TEMP};
+use crate::metrics_server::{run_api_server, ApiState, DEFAULT_API_ADDR};
+#[cfg(unix)]
+use crate::metrics_server::run_unix_api_server;
+use crate::pipeline::{scan_and_queue, PipelineContext};
+use crate::polite::run_polite_mode;
+use crate::replace_window::REPLACE_WINDOW_POLL_SECS;
+use crate::scan_cache::ScanCache;
+use crate::skip_stats::{persist_skip_stats, SkipStats};
+use crate::temp_gc::run_temp_gc;
+use crate::startup::{run_startup_checks, StartupReport};
+use crate::tools::configure_tools;
+use crate::thermal::ThermalGovernor;
+use crate::watch::watch_libraries;
 use std::fs;
 use std::io;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 use tokio::sync::mpsc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
 /// Error type for daemon operations
 #[derive(Debug, Error)]
@@ -30,9 +46,13 @@ pub enum DaemonError {
     #[error("Configuration error: {0}")]
     Config(#[from] ConfigError),
 
-    /// Startup check failed
-    #[error("Startup check failed: {0}")]
-    Startup(#[from] StartupError),
+    /// One or more startup checks failed
+    #[error("{0}")]
+    Startup(#[from] StartupReport),
+
+    /// Another daemon holds the job state directory
+    #[error("{0}")]
+    InstanceLock(#[from] InstanceLockError),
 
     /// Job execution error
     #[error("Job execution error: {0}")]
@@ -47,6 +67,55 @@ pub enum DaemonError {
     Io(#[from] io::Error),
 }
 
+/// Feeds a temperature reading to the governor and applies state changes:
+/// reports them in the metrics and, for `reduce_workers`, caps the workers
+/// of encodes started while throttled.
+fn apply_thermal_policy(
+    governor: &mut ThermalGovernor,
+    temp: Option<f32>,
+    executor: &JobExecutor,
+    snapshot: &mut MetricsSnapshot,
+) {
+    let was_throttled = governor.is_throttled();
+    let throttled = governor.update(temp);
+
+    snapshot.thermal.limit_celsius = Some(governor.limit_celsius());
+    snapshot.thermal.action = match governor.action() {
+        ThermalAction::Pause => "pause",
+        ThermalAction::ReduceWorkers => "reduce_workers",
+    }
+    .to_string();
+    snapshot.thermal.throttled = throttled;
+    if throttled == was_throttled {
+        return;
+    }
+
+    if throttled {
+        snapshot.thermal.throttle_events += 1;
+        log_warn!(
+            "Warning: CPU at {:.0}°C reached the {:.0}°C limit, throttling",
+            temp.unwrap_or_default(),
+            governor.limit_celsius()
+        );
+    }
+    if governor.action() == ThermalAction::ReduceWorkers {
+        let planned = executor.concurrency_plan().av1an_workers;
+        executor.set_worker_cap(throttled.then(|| governor.reduced_workers(planned)));
+    }
+}
+
+/// Creates the shared metrics, seeded with the persisted skip counters.
+fn init_shared_metrics(config: &Config) -> SharedMetrics {
+    let skip_stats = SkipStats::load(&config.paths.skip_stats_path).unwrap_or_else(|e| {
+        log_warn!("Warning: Failed to load skip stats: {}", e);
+        SkipStats::default()
+    });
+    Arc::new(RwLock::new(MetricsSnapshot {
+        skip_totals: skip_stats.counts,
+        ..Default::default()
+    }))
+}
+
 /// Creates required directories for daemon operation.
 ///
 /// Creates the job_state_dir and temp_output_dir if they don't exist.
@@ -81,9 +150,23 @@ pub struct Daemon {
     /// Job executor for processing encoding jobs
     pub executor: Arc<JobExecutor>,
     /// Job queue sender
-    job_tx: mpsc::Sender<Job>,
+    ///
+    /// The queue is unbounded so a scan of a large library never blocks on
+    /// it. Every queued job is persisted as pending first, so the queue
+    /// itself holds nothing that a restart would lose; `scan.max_queue_len`
+    /// keeps it from growing without limit.
+    job_tx: mpsc::UnboundedSender<Job>,
     /// Job queue receiver (wrapped for async access)
-    job_rx: Arc<RwLock<mpsc::Receiver<Job>>>,
+    job_rx: Arc<RwLock<mpsc::UnboundedReceiver<Job>>>,
+    /// Directory cache shared by scan cycles when scanning incrementally
+    scan_cache: Arc<Mutex<ScanCache>>,
+    /// Address the metrics and job API listen on
+    ///
+    /// [`DEFAULT_API_ADDR`] unless changed before the server starts, as
+    /// container mode does to listen on `$PORT` on every interface.
+    pub api_addr: SocketAddr,
+    /// Lock on `job_state_dir` keeping a second daemon off the same state
+    instance_lock: Option<InstanceLock>,
 }
 
 impl Daemon {
@@ -93,7 +176,8 @@ impl Daemon {
     /// 1. Load config from file
     /// 2. Apply environment overrides
     /// 3. Run startup checks (software-only, av1an, ffmpeg)
-    /// 4. Create required directories (job_state_dir, temp_output_dir)
+    /// 4. Create required directories (job_state_dir, temp_output_dir) and
+    ///    lock job_state_dir against a second daemon
     /// 5. Derive concurrency plan
     /// 6. Initialize shared metrics
     ///
@@ -117,27 +201,31 @@ impl Daemon {
         // Step 1 & 2: Load config from file and apply environment overrides
         let config = Config::load(config_path)?;
 
-        // Step 3: Run startup checks in order: software-only, av1an, ffmpeg
+        // Step 3: Run startup checks in order: software-only, av1an, ffmpeg,
+        // against the tool binaries the config names
+        configure_tools(&config);
         run_startup_checks(&config)?;
 
-        // Step 4: Create required directories
+        // Step 4: Create required directories and take the state lock
         create_required_directories(&config)?;
+        let instance_lock = InstanceLock::acquire(&config.paths.job_state_dir)?;
 
         // Step 5: Derive concurrency plan from configuration
         let concurrency_plan = derive_plan(&config);
 
         // Step 6: Initialize shared metrics
-        let metrics = new_shared_metrics();
+        let metrics = init_shared_metrics(&config);
 
         // Create job executor
-        let executor = Arc::new(JobExecutor::new(
+        let executor = Arc::new(JobExecutor::with_config(
             concurrency_plan.clone(),
             metrics.clone(),
             temp_base_dir,
+            JobExecutorConfig::from_config(&config),
         ));
 
         // Create job queue channel
-        let (job_tx, job_rx) = mpsc::channel(100);
+        let (job_tx, job_rx) = mpsc::unbounded_channel();
 
         Ok(Self {
             config,
@@ -146,6 +234,9 @@ impl Daemon {
             executor,
             job_tx,
             job_rx: Arc::new(RwLock::new(job_rx)),
+            scan_cache: Arc::new(Mutex::new(ScanCache::new())),
+            api_addr: DEFAULT_API_ADDR,
+            instance_lock: Some(instance_lock),
         })
     }
 
@@ -153,27 +244,30 @@ impl Daemon {
     ///
     /// Useful for testing or when configuration is already loaded.
     pub async fn with_config(config: Config, temp_base_dir: PathBuf) -> Result<Self, DaemonError> {
-        // Run startup checks
+        // Run startup checks against the configured tool binaries
+        configure_tools(&config);
         run_startup_checks(&config)?;
 
-        // Create required directories
+        // Create required directories and take the state lock
         create_required_directories(&config)?;
+        let instance_lock = InstanceLock::acquire(&config.paths.job_state_dir)?;
 
         // Derive concurrency plan
         let concurrency_plan = derive_plan(&config);
 
         // Initialize shared metrics
-        let metrics = new_shared_metrics();
+        let metrics = init_shared_metrics(&config);
 
         // Create job executor
-        let executor = Arc::new(JobExecutor::new(
+        let executor = Arc::new(JobExecutor::with_config(
             concurrency_plan.clone(),
             metrics.clone(),
             temp_base_dir,
+            JobExecutorConfig::from_config(&config),
         ));
 
         // Create job queue channel
-        let (job_tx, job_rx) = mpsc::channel(100);
+        let (job_tx, job_rx) = mpsc::unbounded_channel();
 
         Ok(Self {
             config,
@@ -182,21 +276,27 @@ impl Daemon {
             executor,
             job_tx,
             job_rx: Arc::new(RwLock::new(job_rx)),
+            scan_cache: Arc::new(Mutex::new(ScanCache::new())),
+            api_addr: DEFAULT_API_ADDR,
+            instance_lock: Some(instance_lock),
         })
     }
 
     /// Initialize the daemon without running startup checks
     ///
     /// Useful for testing when external tools (av1an, ffmpeg) are not available.
+    /// The state directory is not locked; see [`Daemon::lock_instance`].
     pub fn new_without_checks(config: Config, temp_base_dir: PathBuf) -> Self {
+        configure_tools(&config);
         let concurrency_plan = derive_plan(&config);
-        let metrics = new_shared_metrics();
-        let executor = Arc::new(JobExecutor::new(
+        let metrics = init_shared_metrics(&config);
+        let executor = Arc::new(JobExecutor::with_config(
             concurrency_plan.clone(),
             metrics.clone(),
             temp_base_dir,
+            JobExecutorConfig::from_config(&config),
         ));
-        let (job_tx, job_rx) = mpsc::channel(100);
+        let (job_tx, job_rx) = mpsc::unbounded_channel();
 
         Self {
             config,
@@ -205,19 +305,32 @@ impl Daemon {
             executor,
             job_tx,
             job_rx: Arc::new(RwLock::new(job_rx)),
+            scan_cache: Arc::new(Mutex::new(ScanCache::new())),
+            api_addr: DEFAULT_API_ADDR,
+            instance_lock: None,
         }
     }
 
+    /// Locks `job_state_dir` if this daemon does not hold it yet
+    ///
+    /// # Returns
+    /// * `Err(DaemonError::InstanceLock)` if another daemon is using it
+    pub fn lock_instance(&mut self) -> Result<(), DaemonError> {
+        if self.instance_lock.is_none() {
+            self.instance_lock = Some(InstanceLock::acquire(&self.config.paths.job_state_dir)?);
+        }
+        Ok(())
+    }
+
     /// Submit a job to the queue
     pub async fn submit_job(&self, job: Job) -> Result<(), DaemonError> {
         self.job_tx
             .send(job)
-            .await
             .map_err(|e| DaemonError::Server(format!("Failed to submit job: {}", e)))
     }
 
     /// Get a clone of the job sender for external job submission
-    pub fn job_sender(&self) -> mpsc::Sender<Job> {
+    pub fn job_sender(&self) -> mpsc::UnboundedSender<Job> {
         self.job_tx.clone()
     }
 
@@ -226,40 +339,132 @@ impl Daemon {
         self.metrics.clone()
     }
 
+    /// Get a pipeline context for evaluating and queueing candidates
+    pub fn pipeline_context(&self) -> PipelineContext {
+        PipelineContext {
+            config: self.config.clone(),
+            metrics: self.metrics.clone(),
+            job_tx: self.job_tx.clone(),
+        }
+    }
+
     /// Start the metrics HTTP server
     ///
     /// Spawns the HTTP server as a background task.
     ///
     /// # Requirements
-    /// - 7.1: Start HTTP server on 127.0.0.1:7878
+    /// - 7.1: Start HTTP server on 127.0.0.1:7878, or wherever `api_addr` points
     pub fn start_metrics_server(&self) -> tokio::task::JoinHandle<()> {
-        let metrics = self.metrics.clone();
+        let state = ApiState {
+            metrics: self.metrics.clone(),
+            job_state_dir: self.config.paths.job_state_dir.clone(),
+            pipeline: Some(self.pipeline_context()),
+            executor: Some(self.executor.clone()),
+        };
+        let addr = self.api_addr;
         tokio::spawn(async move {
-            if let Err(e) = run_metrics_server(metrics).await {
-                eprintln!("Metrics server error: {}", e);
+            if let Err(e) = run_api_server(state, addr).await {
+                log_warn!("Metrics server error: {}", e);
             }
         })
     }
 
+    /// Serve the API on `paths.api_socket` as well, if one is set
+    ///
+    /// Unix only; elsewhere a configured socket is reported and ignored.
+    pub fn start_socket_server(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let path = self.config.paths.api_socket.clone()?;
+        #[cfg(unix)]
+        {
+            let state = ApiState {
+                metrics: self.metrics.clone(),
+                job_state_dir: self.config.paths.job_state_dir.clone(),
+                pipeline: Some(self.pipeline_context()),
+                executor: Some(self.executor.clone()),
+            };
+            Some(tokio::spawn(async move {
+                if let Err(e) = run_unix_api_server(state, &path).await {
+                    log_warn!("Warning: API socket {} failed: {}", path.display(), e);
+                }
+            }))
+        }
+        #[cfg(not(unix))]
+        {
+            log_warn!("Warning: paths.api_socket {} ignored; unix sockets are not supported here", path.display());
+            None
+        }
+    }
+
     /// Start the metrics update task
     ///
-    /// Periodically updates system metrics in the shared state.
+    /// Periodically updates system metrics in the shared state, including
+    /// IO and free space for the filesystems holding the library roots and
+    /// temp directories.
     pub fn start_metrics_updater(&self) -> tokio::task::JoinHandle<()> {
         let metrics = self.metrics.clone();
+        let mut watched: Vec<WatchedPath> = self
+            .config
+            .scan
+            .library_roots
+            .iter()
+            .map(|root| WatchedPath::new(ROLE_LIBRARY, root))
+            .collect();
+        watched.push(WatchedPath::new(ROLE_TEMP, &self.config.paths.temp_output_dir));
+        watched.push(WatchedPath::new(ROLE_TEMP, self.executor.temp_base_dir()));
+        let mut sampler = SystemSampler::new(watched);
+        let mut governor = ThermalGovernor::from_config(&self.config.thermal);
+        let mut energy = EnergyMeter::new(Path::new(POWERCAP_ROOT));
+        let executor = self.executor.clone();
+
         tokio::spawn(async move {
             loop {
                 // Collect and update system metrics
-                let system_metrics = collect_system_metrics();
+                let system_metrics = sampler.sample();
+                let temp = system_metrics.cpu_temp_celsius;
+                let joules = energy.sample();
                 {
                     let mut snapshot = metrics.write().await;
                     snapshot.system = system_metrics;
                     snapshot.timestamp_unix_ms = chrono_timestamp_ms();
+                    let now = snapshot.timestamp_unix_ms;
+                    snapshot.record_history(now);
+                    snapshot.energy.available = joules.is_some();
+                    if let Some(joules) = joules {
+                        attribute_energy(&mut snapshot, joules);
+                    }
+                    if let Some(ref mut governor) = governor {
+                        apply_thermal_policy(governor, temp, &executor, &mut snapshot);
+                    }
+                    let control = executor.control();
+                    snapshot.control = ControlMetrics {
+                        queue_paused: control.is_paused(),
+                        scan_requested: control.scan_pending(),
+                    };
                 }
                 tokio::time::sleep(Duration::from_millis(500)).await;
             }
         })
     }
 
+    /// Start exporting daemon metrics to the telemetry collector
+    ///
+    /// Does nothing unless `telemetry.endpoint` is set. Job traces are
+    /// exported by the executor as each job finishes.
+    pub fn start_telemetry_export(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let telemetry = self.executor.telemetry()?;
+        let metrics = self.metrics.clone();
+        let interval = Duration::from_secs(self.config.telemetry.metrics_interval_secs.max(1));
+        let started = SystemTime::now();
+
+        Some(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let snapshot = metrics.read().await.clone();
+                telemetry.export_metrics(&snapshot, started).await;
+            }
+        }))
+    }
+
     /// Run the daemon main loop
     ///
     /// Processes jobs from the queue and updates metrics on completion.
@@ -269,6 +474,7 @@ impl Daemon {
     /// - 5.3: Mark job as failed and halt processing on encoding failure
     /// - 5.4: Replace original file after validation passes
     pub async fn run(&self) -> Result<(), DaemonError> {
+        let mut ordering = DependencyRules::new(self.config.ordering);
         loop {
             // Get next job from queue
             let job = {
@@ -278,6 +484,10 @@ impl Daemon {
 
             match job {
                 Some(job) => {
+                    if ordering.enabled() {
+                        self.record_dependencies(&job, &mut ordering);
+                    }
+
                     // Update queue length in metrics
                     {
                         let mut metrics = self.metrics.write().await;
@@ -287,6 +497,7 @@ impl Daemon {
                     // Execute the job
                     let executor = self.executor.clone();
                     let metrics = self.metrics.clone();
+                    let skip_stats_path = self.config.paths.skip_stats_path.clone();
 
                     // Spawn job execution as a separate task
                     tokio::spawn(async move {
@@ -300,8 +511,18 @@ impl Daemon {
                                     m.total_bytes_encoded += metadata.len();
                                 }
                             }
+                            Err(JobError::Cancelled) => log_info!("Job cancelled"),
+                            // Logged by the executor; the other daemon has the file
+                            Err(JobError::ClaimedElsewhere(_)) => {}
                             Err(e) => {
-                                eprintln!("Job execution failed: {}", e);
+                                log_warn!("Job execution failed: {}", e);
+                                if matches!(e, JobError::SizeGateRejected { .. }) {
+                                    if let Err(e) =
+                                        persist_skip_stats(&metrics, &skip_stats_path).await
+                                    {
+                                        log_warn!("Warning: Failed to save skip stats: {}", e);
+                                    }
+                                }
                             }
                         }
                     });
@@ -316,11 +537,137 @@ impl Daemon {
         Ok(())
     }
 
+    /// Adds the dependencies the `[ordering]` rules give `job` to its
+    /// persisted record, where the executor reads them.
+    fn record_dependencies(&self, job: &Job, ordering: &mut DependencyRules) {
+        let depends_on = ordering.dependencies_for(&job.id, &job.input_path);
+        if depends_on.is_empty() {
+            return;
+        }
+        let result = update_job(&self.config.paths.job_state_dir, &job.id, |managed| {
+            for dep in depends_on {
+                if !managed.depends_on.contains(&dep) {
+                    managed.depends_on.push(dep);
+                }
+            }
+        });
+        if let Err(e) = result {
+            log_warn!("Warning: Failed to record dependencies of job {}: {}", job.id, e);
+        }
+    }
+
+    /// Start the temp garbage collection task
+    ///
+    /// Periodically removes chunk directories left behind by jobs that are no
+    /// longer active and refreshes temp usage metrics. Does nothing when
+    /// `temp_gc_interval_secs` is 0.
+    pub fn start_temp_gc(&self) -> tokio::task::JoinHandle<()> {
+        let metrics = self.metrics.clone();
+        let temp_base_dir = self.executor.temp_base_dir().to_path_buf();
+        let temp_output_dir = self.config.paths.temp_output_dir.clone();
+        let quota = self.config.paths.temp_quota_bytes;
+        let interval = self.config.paths.temp_gc_interval_secs;
+
+        tokio::spawn(async move {
+            if interval == 0 {
+                return;
+            }
+            loop {
+                run_temp_gc(&metrics, &temp_base_dir, &temp_output_dir, quota).await;
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        })
+    }
+
+    /// Start the kill switch watch task
+    ///
+    /// Checks the sentinel file every [`KILL_SWITCH_POLL_SECS`], reports it in
+    /// the metrics, and with `kill_switch.suspend_running` suspends running
+    /// encodes while it exists.
+    pub fn start_kill_switch_watch(&self) -> tokio::task::JoinHandle<()> {
+        let metrics = self.metrics.clone();
+        let mut switch = KillSwitch::new(&self.config.kill_switch);
+
+        tokio::spawn(async move {
+            loop {
+                {
+                    let mut snapshot = metrics.write().await;
+                    switch.poll(&mut snapshot);
+                }
+                tokio::time::sleep(Duration::from_secs(KILL_SWITCH_POLL_SECS)).await;
+            }
+        })
+    }
+
+    /// Start polite mode
+    ///
+    /// Does nothing unless `polite.enabled` is set. Otherwise suspends
+    /// running encodes, and holds new ones, while Plex transcodes or other
+    /// processes keep the CPU busy.
+    pub fn start_polite_mode(&self) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.polite.enabled {
+            return None;
+        }
+        let config = self.config.polite.clone();
+        let metrics = self.metrics.clone();
+        Some(tokio::spawn(run_polite_mode(config, metrics)))
+    }
+
+    /// Start the backup retention task
+    ///
+    /// Every [`RETENTION_INTERVAL_SECS`] deletes kept backups past
+    /// `backup_max_age_days`, then the oldest while all of them take more
+    /// than `backup_max_total_bytes`. Does nothing when neither limit is set.
+    pub fn start_backup_retention(&self) -> tokio::task::JoinHandle<()> {
+        let metrics = self.metrics.clone();
+        let state_dir = self.config.paths.job_state_dir.clone();
+        let policy = RetentionPolicy {
+            max_age_days: self.config.gates.backup_max_age_days,
+            max_total_bytes: self.config.gates.backup_max_total_bytes,
+        };
+
+        tokio::spawn(async move {
+            if !policy.is_enabled() {
+                return;
+            }
+            loop {
+                run_backup_retention(&metrics, &state_dir, policy, chrono_timestamp_ms()).await;
+                tokio::time::sleep(Duration::from_secs(RETENTION_INTERVAL_SECS)).await;
+            }
+        })
+    }
+
+    /// Start the replace window task
+    ///
+    /// Every [`REPLACE_WINDOW_POLL_SECS`] replaces the originals of jobs left
+    /// ready to replace, if `gates.replace_window` is open and the kill
+    /// switch is not engaged. Does nothing without a window.
+    pub fn start_replace_window(&self) -> tokio::task::JoinHandle<()> {
+        let executor = self.executor.clone();
+        let window = self.config.gates.replace_window;
+        let kill_switch_file = self.config.kill_switch.file.clone();
+
+        tokio::spawn(async move {
+            let Some(window) = window else {
+                return;
+            };
+            loop {
+                if !kill_switch_engaged(&kill_switch_file) {
+                    let replaced = executor.replace_ready().await;
+                    if replaced > 0 {
+                        log_info!("Replaced {} originals in the replace window {}", replaced, window);
+                    }
+                }
+                tokio::time::sleep(Duration::from_secs(REPLACE_WINDOW_POLL_SECS)).await;
+            }
+        })
+    }
+
     /// Run a single scan cycle to discover and queue new encoding jobs.
     ///
     /// This method implements the scan cycle:
     /// 1. Load existing jobs to avoid duplicates
-    /// 2. Scan all library_roots for video files
+    /// 2. Scan all library_roots for video files and order the candidates
     /// 3. For each candidate: stability check, probe, gates, classify, create job
     /// 4. Queue jobs for execution
     ///
@@ -331,129 +678,67 @@ impl Daemon {
     /// - 14.3: Load existing jobs to avoid duplicate work
     /// - 15.1-15.5: Classify source files
     pub async fn run_scan_cycle(&self) -> Result<usize, DaemonError> {
-        let mut jobs_queued = 0;
-
-        // Step 1: Load existing jobs to avoid duplicates (Requirement 14.3)
-        let existing_jobs = load_jobs(&self.config.paths.job_state_dir).unwrap_or_else(|e| {
-            eprintln!("Warning: Failed to load existing jobs: {}", e);
-            Vec::new()
-        });
-
-        // Step 2: Scan all library_roots (Requirement 11.1)
-        let candidates = scan_libraries(&self.config.scan.library_roots);
-
-        // Create gates config from daemon config
-        let gates_config = DaemonGatesConfig {
-            min_bytes: self.config.gates.min_bytes,
-            max_size_ratio: self.config.gates.max_size_ratio,
-            keep_original: self.config.gates.keep_original,
-        };
-
-        // Step 3: Process each candidate
-        for candidate in candidates {
-            // Skip if job already exists for this path (Requirement 14.3)
-            if job_exists_for_path(&existing_jobs, &candidate.path) {
-                continue;
-            }
-
-            // Step 3a: Stability check (Requirements 12.1-12.4)
-            let stability_result = match check_stability(
-                &candidate.path,
-                candidate.size_bytes,
-                self.config.scan.stability_wait_secs,
-            )
-            .await
-            {
-                Ok(result) => result,
-                Err(e) => {
-                    eprintln!(
-                        "Warning: Stability check failed for {:?}: {}",
-                        candidate.path, e
-                    );
-                    continue;
-                }
-            };
+        let mut cache = self.scan_cache.lock().await;
+        Ok(scan_and_queue(&self.pipeline_context(), &mut cache).await)
+    }
 
-            // Skip unstable files (Requirement 12.3)
-            if let StabilityResult::Unstable { .. } = stability_result {
-                continue;
-            }
+    /// Start the recovery task for jobs interrupted by the previous run
+    ///
+    /// Reads each unfinished job's journal once at startup. Jobs stopped
+    /// before replacement are queued again with their saved priorities. Jobs
+    /// stopped mid-replacement are repaired from their swap marker when
+    /// possible, finishing what follows a replacement, and otherwise marked
+    /// failed and reported so their files can be checked by hand.
+    pub fn start_recovery(&self) -> tokio::task::JoinHandle<()> {
+        let state_dir = self.config.paths.job_state_dir.clone();
+        let job_tx = self.job_tx.clone();
+        let metrics = self.metrics.clone();
+        let executor = self.executor.clone();
 
-            // Step 3b: Probe file (Requirement 13.1)
-            let probe_result = match probe_file(&candidate.path) {
-                Ok(result) => result,
+        tokio::spawn(async move {
+            let recovered = match recover_interrupted_jobs(&state_dir) {
+                Ok(recovered) => recovered,
                 Err(e) => {
-                    // Create skip marker on probe failure (Requirement 13.2)
-                    let reason = format!("ffprobe failed: {}", e);
-                    let _ = write_skip_marker(&candidate.path);
-                    let _ = write_why_sidecar(
-                        &candidate.path,
-                        &reason,
-                        self.config.scan.write_why_sidecars,
-                    );
-                    continue;
+                    log_warn!("Warning: Failed to recover interrupted jobs: {}", e);
+                    return;
                 }
             };
-
-            // Step 3c: Check gates (Requirements 13.3-13.6)
-            let gate_result = check_gates(&probe_result, candidate.size_bytes, &gates_config);
-
-            match gate_result {
-                GateResult::Skip { reason } => {
-                    // Create skip markers (Requirements 13.3, 13.4, 13.5)
-                    let _ = write_skip_marker(&candidate.path);
-                    let _ = write_why_sidecar(
-                        &candidate.path,
-                        &reason,
-                        self.config.scan.write_why_sidecars,
-                    );
-                    continue;
-                }
-                GateResult::Pass(probe) => {
-                    // Step 3d: Classify source (Requirements 15.1-15.4)
-                    let source_type = classify_source(&candidate.path, &probe);
-
-                    // Step 3e: Create job (Requirement 14.1)
-                    let managed_job = create_job(
-                        &candidate,
-                        probe.clone(),
-                        source_type,
-                        &self.config.paths.temp_output_dir,
-                    );
-
-                    // Save job to state directory (Requirement 14.2)
-                    if let Err(e) = save_job(&managed_job, &self.config.paths.job_state_dir) {
-                        eprintln!("Warning: Failed to save job state: {}", e);
-                    }
-
-                    // Step 4: Queue job for execution
-                    let executor_job = Job::new(
-                        managed_job.id.clone(),
-                        managed_job.input_path.clone(),
-                        managed_job.output_path.clone(),
-                    );
-
-                    // Set the original file size for size gate comparison
-                    let mut job_with_size = executor_job;
-                    job_with_size.size_in_bytes_before = candidate.size_bytes;
-
-                    if let Err(e) = self.submit_job(job_with_size).await {
-                        eprintln!("Warning: Failed to queue job: {}", e);
+            // Resumed jobs keep the place in the queue they were moved to
+            executor.set_queue_priorities(
+                recovered
+                    .iter()
+                    .filter(|(_, action)| *action == RecoveryAction::Resume)
+                    .map(|(managed_job, _)| (managed_job.id.as_str(), managed_job.priority)),
+            );
+            for (managed_job, action) in recovered {
+                match action {
+                    RecoveryAction::Resume => {}
+                    RecoveryAction::Finished(intent) => {
+                        executor
+                            .finish_repaired_swap(&Job::from_managed(&managed_job, 0), &intent)
+                            .await;
+                        log_info!(
+                            "Finished interrupted replacement of {:?} for job {}",
+                            managed_job.input_path, managed_job.id
+                        );
                         continue;
                     }
-
-                    // Update queue length in metrics
-                    {
-                        let mut metrics = self.metrics.write().await;
-                        metrics.queue_len += 1;
+                    RecoveryAction::Fail => {
+                        log_warn!(
+                            "Job {} was interrupted while replacing {:?}; check the file and its backup",
+                            managed_job.id, managed_job.input_path
+                        );
+                        continue;
                     }
-
-                    jobs_queued += 1;
                 }
+                let size = fs::metadata(&managed_job.input_path).map(|m| m.len()).unwrap_or(0);
+                if job_tx.send(Job::from_managed(&managed_job, size)).is_err() {
+                    return;
+                }
+                log_info!("Resumed job {}: {:?}", managed_job.id, managed_job.input_path);
+                metrics.write().await.queue_len += 1;
             }
-        }
-
-        Ok(jobs_queued)
+        })
     }
 
     /// Start the scan cycle task
@@ -463,129 +748,70 @@ impl Daemon {
     /// # Requirements
     /// - 11.1: Recursively walk each configured library_root directory
     pub fn start_scan_cycle(&self) -> tokio::task::JoinHandle<()> {
-        let config = self.config.clone();
-        let job_tx = self.job_tx.clone();
-        let metrics = self.metrics.clone();
-        let job_state_dir = self.config.paths.job_state_dir.clone();
-        let temp_output_dir = self.config.paths.temp_output_dir.clone();
+        let ctx = self.pipeline_context();
+        let scan_cache = self.scan_cache.clone();
+        let control = self.executor.control();
 
         tokio::spawn(async move {
             loop {
-                println!("Starting scan cycle...");
-                
-                // Load existing jobs
-                let existing_jobs = load_jobs(&job_state_dir).unwrap_or_else(|e| {
-                    eprintln!("Warning: Failed to load existing jobs: {}", e);
-                    Vec::new()
-                });
-                println!("Loaded {} existing jobs", existing_jobs.len());
-
-                // Scan libraries
-                println!("Scanning {} library roots: {:?}", config.scan.library_roots.len(), config.scan.library_roots);
-                let candidates = scan_libraries(&config.scan.library_roots);
-                println!("Found {} video candidates", candidates.len());
-
-                // Create gates config
-                let gates_config = DaemonGatesConfig {
-                    min_bytes: config.gates.min_bytes,
-                    max_size_ratio: config.gates.max_size_ratio,
-                    keep_original: config.gates.keep_original,
+                if kill_switch_engaged(&ctx.config.kill_switch.file) {
+                    log_info!(
+                        "Kill switch {:?} present, skipping scan cycle",
+                        ctx.config.kill_switch.file
+                    );
+                    tokio::time::sleep(Duration::from_secs(KILL_SWITCH_POLL_SECS)).await;
+                    continue;
+                }
+                log_info!("Starting scan cycle...");
+                control.scan_started();
+                let queued = {
+                    let mut cache = scan_cache.lock().await;
+                    scan_and_queue(&ctx, &mut cache).await
                 };
 
-                // Process candidates
-                for candidate in candidates {
-                    // Skip if job already exists
-                    if job_exists_for_path(&existing_jobs, &candidate.path) {
-                        continue;
-                    }
-
-                    // Stability check
-                    let stability_result = match check_stability(
-                        &candidate.path,
-                        candidate.size_bytes,
-                        config.scan.stability_wait_secs,
-                    )
-                    .await
-                    {
-                        Ok(result) => result,
-                        Err(_) => continue,
-                    };
-
-                    if let StabilityResult::Unstable { .. } = stability_result {
-                        continue;
-                    }
-
-                    // Probe file
-                    let probe_result = match probe_file(&candidate.path) {
-                        Ok(result) => result,
-                        Err(e) => {
-                            let reason = format!("ffprobe failed: {}", e);
-                            let _ = write_skip_marker(&candidate.path);
-                            let _ = write_why_sidecar(
-                                &candidate.path,
-                                &reason,
-                                config.scan.write_why_sidecars,
-                            );
-                            continue;
-                        }
-                    };
-
-                    // Check gates
-                    let gate_result =
-                        check_gates(&probe_result, candidate.size_bytes, &gates_config);
-
-                    match gate_result {
-                        GateResult::Skip { reason } => {
-                            let _ = write_skip_marker(&candidate.path);
-                            let _ = write_why_sidecar(
-                                &candidate.path,
-                                &reason,
-                                config.scan.write_why_sidecars,
-                            );
-                            continue;
-                        }
-                        GateResult::Pass(probe) => {
-                            // Classify source
-                            let source_type = classify_source(&candidate.path, &probe);
-
-                            // Create job
-                            let managed_job = create_job(
-                                &candidate,
-                                probe,
-                                source_type,
-                                &temp_output_dir,
-                            );
-
-                            // Save job state
-                            if let Err(e) = save_job(&managed_job, &job_state_dir) {
-                                eprintln!("Warning: Failed to save job state: {}", e);
-                            }
-
-                            // Create executor job
-                            let mut executor_job = Job::new(
-                                managed_job.id.clone(),
-                                managed_job.input_path.clone(),
-                                managed_job.output_path.clone(),
-                            );
-                            executor_job.size_in_bytes_before = candidate.size_bytes;
-
-                            // Queue job
-                            if job_tx.send(executor_job).await.is_ok() {
-                                println!("Queued job {} for encoding: {:?}", managed_job.id, managed_job.input_path);
-                                let mut m = metrics.write().await;
-                                m.queue_len += 1;
-                            }
-                        }
-                    }
+                log_info!(
+                    "Scan cycle complete, queued {} jobs. Waiting {} seconds before next scan.",
+                    queued, ctx.config.scan.scan_interval_secs
+                );
+                // Wait before next scan cycle, or until one is requested
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(ctx.config.scan.scan_interval_secs)) => {}
+                    _ = control.scan_requested() => log_info!("Scan requested"),
                 }
-
-                println!("Scan cycle complete. Waiting {} seconds before next scan.", config.scan.scan_interval_secs);
-                // Wait before next scan cycle
-                tokio::time::sleep(Duration::from_secs(config.scan.scan_interval_secs)).await;
             }
         })
     }
 
+    /// Request scans on library changes, if `scan.watch` is set
+    ///
+    /// Without a watcher the scan interval alone finds new files.
+    pub fn start_library_watch(&self) {
+        if !self.config.scan.watch {
+            return;
+        }
+        match watch_libraries(
+            &self.config.scan.library_roots,
+            &self.config.scan.video_extensions,
+            self.executor.control(),
+        ) {
+            Ok(()) => log_info!("Watching {} library root(s) for changes", self.config.scan.library_roots.len()),
+            Err(e) => log_warn!("Cannot watch libraries, relying on the scan interval: {}", e),
+        }
+    }
+
+    /// Stop all running encodes before the daemon exits
+    ///
+    /// Terminates every encoder process group so no av1an, ffmpeg, or
+    /// SvtAv1EncApp processes are orphaned.
+    pub async fn shutdown(&self) {
+        let stopped = tokio::task::spawn_blocking(terminate_all_groups)
+            .await
+            .unwrap_or(0);
+        if stopped > 0 {
+            log_info!("Stopped {} running encode(s)", stopped);
+        }
+    }
+
     /// Run the daemon with all background tasks
     ///
     /// Starts the metrics server, metrics updater, and main processing loop.
@@ -593,9 +819,36 @@ impl Daemon {
         // Start metrics server
         let _server_handle = self.start_metrics_server();
 
+        // Also serve the API on the local socket, if configured
+        let _socket_handle = self.start_socket_server();
+
         // Start metrics updater
         let _updater_handle = self.start_metrics_updater();
 
+        // Start temp garbage collection
+        let _gc_handle = self.start_temp_gc();
+
+        // Delete kept backups past their retention limits
+        let _retention_handle = self.start_backup_retention();
+
+        // Halt new work while the kill switch file exists
+        let _kill_switch_handle = self.start_kill_switch_watch();
+
+        // Replace finished encodes once the replace window opens
+        let _replace_window_handle = self.start_replace_window();
+
+        // Requeue jobs interrupted by the previous run
+        let _recovery_handle = self.start_recovery();
+
+        // Export metrics to the OpenTelemetry collector, if configured
+        let _telemetry_handle = self.start_telemetry_export();
+
+        // Yield to interactive use of the host, if configured
+        let _polite_handle = self.start_polite_mode();
+
+        // Announce the API on the LAN, if configured
+        let _advertisement = advertise(&self.config.discovery, self.api_addr);
+
         // Run main loop
         self.run().await
     }
@@ -607,12 +860,42 @@ impl Daemon {
         // Start metrics server
         let _server_handle = self.start_metrics_server();
 
+        // Also serve the API on the local socket, if configured
+        let _socket_handle = self.start_socket_server();
+
         // Start metrics updater
         let _updater_handle = self.start_metrics_updater();
 
+        // Start temp garbage collection
+        let _gc_handle = self.start_temp_gc();
+
+        // Delete kept backups past their retention limits
+        let _retention_handle = self.start_backup_retention();
+
+        // Halt new work while the kill switch file exists
+        let _kill_switch_handle = self.start_kill_switch_watch();
+
+        // Replace finished encodes once the replace window opens
+        let _replace_window_handle = self.start_replace_window();
+
+        // Requeue jobs interrupted by the previous run
+        let _recovery_handle = self.start_recovery();
+
+        // Export metrics to the OpenTelemetry collector, if configured
+        let _telemetry_handle = self.start_telemetry_export();
+
+        // Yield to interactive use of the host, if configured
+        let _polite_handle = self.start_polite_mode();
+
+        // Announce the API on the LAN, if configured
+        let _advertisement = advertise(&self.config.discovery, self.api_addr);
+
         // Start scan cycle
         let _scan_handle = self.start_scan_cycle();
 
+        // Scan early when the libraries change, if configured
+        self.start_library_watch();
+
         // Run main loop
         self.run().await
     }
@@ -629,11 +912,13 @@ fn chrono_timestamp_ms() -> i64 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{Av1anConfig, CpuConfig, EncoderSafetyConfig, GatesConfig, PathsConfig, ScanConfig};
+    use crate::config::{AudioConfig, Av1anConfig, CpuConfig, EncoderSafetyConfig, FarmConfig, GatesConfig, OutputConfig, PathsConfig, ScanConfig, KillSwitchConfig, StagingConfig, StorageConfig, OrderingConfig, DiscoveryConfig, CgroupConfig, PoliteConfig, ToolsConfig, ProcessConfig, TriageConfig, PixelFormatConfig, QuotaConfig, RunAsConfig, SubtitleConfig, TelemetryConfig, ThermalConfig, TorrentConfig, ValidationConfig};
     use tempfile::TempDir;
 
     fn create_test_config() -> Config {
         Config {
+            profile: None,
+            read_only: false,
             cpu: CpuConfig {
                 logical_cores: Some(32),
                 target_cpu_utilization: 0.85,
@@ -641,6 +926,7 @@ mod tests {
             av1an: Av1anConfig {
                 workers_per_job: 8,
                 max_concurrent_jobs: 1,
+                ..Default::default()
             },
             encoder_safety: EncoderSafetyConfig {
                 disallow_hardware_encoding: true,
@@ -648,11 +934,34 @@ mod tests {
             paths: PathsConfig::default(),
             scan: ScanConfig::default(),
             gates: GatesConfig::default(),
+            output: OutputConfig::default(),
+            thermal: ThermalConfig::default(),
+            validation: ValidationConfig::default(),
+            audio: AudioConfig::default(),
+            pixel_format: PixelFormatConfig::default(),
+            subtitles: SubtitleConfig::default(),
+            torrent: TorrentConfig::default(),
+            run_as: RunAsConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            kill_switch: KillSwitchConfig::default(),
+            quotas: QuotaConfig::default(),
+            farm: FarmConfig::default(),
+            staging: StagingConfig::default(),
+            storage: StorageConfig::default(),
+            ordering: OrderingConfig::default(),
+            discovery: DiscoveryConfig::default(),
+            cgroups: CgroupConfig::default(),
+            polite: PoliteConfig::default(),
+            tools: ToolsConfig::default(),
+            process: ProcessConfig::default(),
+            triage: TriageConfig::default(),
         }
     }
 
     fn create_test_config_with_paths(job_state_dir: PathBuf, temp_output_dir: PathBuf) -> Config {
         Config {
+            profile: None,
+            read_only: false,
             cpu: CpuConfig {
                 logical_cores: Some(32),
                 target_cpu_utilization: 0.85,
@@ -660,6 +969,7 @@ mod tests {
             av1an: Av1anConfig {
                 workers_per_job: 8,
                 max_concurrent_jobs: 1,
+                ..Default::default()
             },
             encoder_safety: EncoderSafetyConfig {
                 disallow_hardware_encoding: true,
@@ -667,9 +977,31 @@ mod tests {
             paths: PathsConfig {
                 job_state_dir,
                 temp_output_dir,
+                ..Default::default()
             },
             scan: ScanConfig::default(),
             gates: GatesConfig::default(),
+            output: OutputConfig::default(),
+            thermal: ThermalConfig::default(),
+            validation: ValidationConfig::default(),
+            audio: AudioConfig::default(),
+            pixel_format: PixelFormatConfig::default(),
+            subtitles: SubtitleConfig::default(),
+            torrent: TorrentConfig::default(),
+            run_as: RunAsConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            kill_switch: KillSwitchConfig::default(),
+            quotas: QuotaConfig::default(),
+            farm: FarmConfig::default(),
+            staging: StagingConfig::default(),
+            storage: StorageConfig::default(),
+            ordering: OrderingConfig::default(),
+            discovery: DiscoveryConfig::default(),
+            cgroups: CgroupConfig::default(),
+            polite: PoliteConfig::default(),
+            tools: ToolsConfig::default(),
+            process: ProcessConfig::default(),
+            triage: TriageConfig::default(),
         }
     }
 
@@ -686,6 +1018,8 @@ mod tests {
     #[tokio::test]
     async fn test_daemon_derives_concurrency_plan() {
         let config = Config {
+            profile: None,
+            read_only: false,
             cpu: CpuConfig {
                 logical_cores: Some(48),
                 target_cpu_utilization: 0.9,
@@ -693,11 +1027,33 @@ mod tests {
             av1an: Av1anConfig {
                 workers_per_job: 0, // auto-derive
                 max_concurrent_jobs: 0, // auto-derive
+                ..Default::default()
             },
             encoder_safety: EncoderSafetyConfig::default(),
             paths: PathsConfig::default(),
             scan: ScanConfig::default(),
             gates: GatesConfig::default(),
+            output: OutputConfig::default(),
+            thermal: ThermalConfig::default(),
+            validation: ValidationConfig::default(),
+            audio: AudioConfig::default(),
+            pixel_format: PixelFormatConfig::default(),
+            subtitles: SubtitleConfig::default(),
+            torrent: TorrentConfig::default(),
+            run_as: RunAsConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            kill_switch: KillSwitchConfig::default(),
+            quotas: QuotaConfig::default(),
+            farm: FarmConfig::default(),
+            staging: StagingConfig::default(),
+            storage: StorageConfig::default(),
+            ordering: OrderingConfig::default(),
+            discovery: DiscoveryConfig::default(),
+            cgroups: CgroupConfig::default(),
+            polite: PoliteConfig::default(),
+            tools: ToolsConfig::default(),
+            process: ProcessConfig::default(),
+            triage: TriageConfig::default(),
         };
 
         let daemon = Daemon::new_without_checks(config, PathBuf::from("/tmp"));
@@ -729,6 +1085,23 @@ mod tests {
         assert_eq!(metrics.queue_len, 0);
     }
 
+    // Submitting never waits on the run loop, however many jobs are queued
+    #[tokio::test]
+    async fn test_daemon_queue_is_unbounded() {
+        let config = create_test_config();
+        let daemon = Daemon::new_without_checks(config, PathBuf::from("/tmp"));
+
+        for i in 0..1000 {
+            let job = Job::new(
+                format!("test-job-{:04}", i),
+                PathBuf::from(format!("/input/video{}.mkv", i)),
+                PathBuf::from(format!("/output/video{}.mkv", i)),
+            );
+            daemon.submit_job(job).await.unwrap();
+        }
+        assert_eq!(daemon.job_rx.read().await.len(), 1000);
+    }
+
     #[tokio::test]
     async fn test_daemon_metrics_initialized() {
         let config = create_test_config();
@@ -741,6 +1114,38 @@ mod tests {
         assert_eq!(metrics.failed_jobs, 0);
     }
 
+    #[test]
+    fn test_thermal_policy_caps_workers_while_throttled() {
+        let plan = ConcurrencyPlan {
+            total_cores: 32,
+            target_threads: 27,
+            av1an_workers: 8,
+            max_concurrent_jobs: 3,
+        };
+        let executor = JobExecutor::new(plan, crate::metrics::new_shared_metrics(), PathBuf::from("/tmp"));
+        let mut governor = ThermalGovernor::from_config(&ThermalConfig {
+            max_cpu_temp_celsius: Some(80.0),
+            action: ThermalAction::ReduceWorkers,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut snapshot = MetricsSnapshot::default();
+
+        apply_thermal_policy(&mut governor, Some(70.0), &executor, &mut snapshot);
+        assert!(!snapshot.thermal.throttled);
+        assert_eq!(executor.capped_workers(), None);
+
+        apply_thermal_policy(&mut governor, Some(81.0), &executor, &mut snapshot);
+        assert!(snapshot.thermal.throttled);
+        assert_eq!(snapshot.thermal.throttle_events, 1);
+        assert_eq!(snapshot.thermal.action, "reduce_workers");
+        assert_eq!(executor.capped_workers(), Some(4));
+
+        apply_thermal_policy(&mut governor, Some(74.0), &executor, &mut snapshot);
+        assert!(!snapshot.thermal.throttled);
+        assert_eq!(executor.capped_workers(), None);
+    }
+
     #[test]
     fn test_chrono_timestamp_ms() {
         let ts = chrono_timestamp_ms();
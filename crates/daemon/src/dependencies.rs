@@ -0,0 +1,269 @@
+//! Ordering constraints between jobs.
+//!
+//! A job may list jobs it depends on in `depends_on`; the executor holds it
+//! back until every one of them has finished, whatever their outcome. Jobs
+//! get dependencies in two ways:
+//!
+//! - `[ordering]` rules applied by the dispatcher as jobs are handed to the
+//!   executor: `serialize_directories` makes each job wait for the one
+//!   queued before it from the same folder, and `extras_after_main` makes a
+//!   file in an extras folder (`Extras`, `Featurettes`, ...) wait for the
+//!   last job queued from the folder above it.
+//! - `POST /jobs/depend`, which adds dependencies to a queued job.
+//!
+//! Dependencies are read from the persisted job each time it is checked, so
+//! ones added while it waits count too. A dependency whose record is gone
+//! counts as finished.
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::OrderingConfig;
+use crate::jobs::{load_job, update_job, Job};
+
+/// Folder names media servers use for a movie's or show's extras, compared
+/// case-insensitively
+pub const EXTRAS_DIRS: &[&str] = &[
+    "extras",
+    "featurettes",
+    "behind the scenes",
+    "deleted scenes",
+    "interviews",
+    "scenes",
+    "shorts",
+    "trailers",
+    "other",
+];
+
+/// Returns true if `path` is in an extras folder.
+pub fn is_extra(path: &Path) -> bool {
+    path.parent()
+        .and_then(|dir| dir.file_name())
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| EXTRAS_DIRS.iter().any(|extras| name.eq_ignore_ascii_case(extras)))
+}
+
+/// Dependencies the `[ordering]` rules give jobs, in the order the
+/// dispatcher hands them out.
+#[derive(Debug, Default)]
+pub struct DependencyRules {
+    config: OrderingConfig,
+    /// Last job handed out from each folder
+    last_in_dir: HashMap<PathBuf, String>,
+}
+
+impl DependencyRules {
+    /// Creates rules with no jobs seen yet.
+    pub fn new(config: OrderingConfig) -> Self {
+        Self {
+            config,
+            last_in_dir: HashMap::new(),
+        }
+    }
+
+    /// Returns true if any rule is on.
+    pub fn enabled(&self) -> bool {
+        self.config.serialize_directories || self.config.extras_after_main
+    }
+
+    /// Jobs that job `id` for `path` has to wait for, given the jobs handed
+    /// out before it.
+    pub fn dependencies_for(&mut self, id: &str, path: &Path) -> Vec<String> {
+        let Some(dir) = path.parent() else {
+            return Vec::new();
+        };
+        let mut depends_on = Vec::new();
+        if self.config.serialize_directories {
+            depends_on.extend(self.last_in_dir.get(dir).cloned());
+        }
+        if self.config.extras_after_main && is_extra(path) {
+            depends_on.extend(dir.parent().and_then(|main| self.last_in_dir.get(main)).cloned());
+        }
+        if self.enabled() {
+            self.last_in_dir.insert(dir.to_path_buf(), id.to_string());
+        }
+        depends_on.retain(|dep| dep != id);
+        depends_on.dedup();
+        depends_on
+    }
+}
+
+/// The dependencies of the persisted job `id` that have not finished yet.
+pub fn unfinished_dependencies(state_dir: &Path, id: &str) -> io::Result<Vec<String>> {
+    let Some(job) = load_job(state_dir, id)? else {
+        return Ok(Vec::new());
+    };
+    let mut unfinished = Vec::new();
+    for dep in job.depends_on {
+        if load_job(state_dir, &dep)?.is_some_and(|dep| dep.is_active()) {
+            unfinished.push(dep);
+        }
+    }
+    Ok(unfinished)
+}
+
+/// Adds `after` to the dependencies of the queued job `id`.
+///
+/// # Returns
+/// The updated job; a `NotFound` error naming a job that does not exist, or
+/// an `InvalidInput` error if the job is not queued or would end up waiting
+/// on itself
+pub fn add_dependencies(state_dir: &Path, id: &str, after: &[String]) -> io::Result<Job> {
+    let job = load_job(state_dir, id)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no job with id {}", id)))?;
+    if !job.is_queued() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a queued job", id),
+        ));
+    }
+    for dep in after {
+        if load_job(state_dir, dep)?.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no job with id {}", dep),
+            ));
+        }
+        if dep == id || depends_on(state_dir, dep, id)? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} already waits for {}", dep, id),
+            ));
+        }
+    }
+
+    let updated = update_job(state_dir, id, |job| {
+        for dep in after {
+            if !job.depends_on.contains(dep) {
+                job.depends_on.push(dep.clone());
+            }
+        }
+    })?;
+    updated.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no job with id {}", id)))
+}
+
+/// Returns true if job `id` waits for `target`, directly or through other
+/// jobs.
+fn depends_on(state_dir: &Path, id: &str, target: &str) -> io::Result<bool> {
+    let mut seen = HashSet::new();
+    let mut stack = vec![id.to_string()];
+    while let Some(next) = stack.pop() {
+        if !seen.insert(next.clone()) {
+            continue;
+        }
+        let Some(job) = load_job(state_dir, &next)? else {
+            continue;
+        };
+        if job.depends_on.iter().any(|dep| dep == target) {
+            return Ok(true);
+        }
+        stack.extend(job.depends_on);
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::SourceType;
+    use crate::gates::{FormatInfo, ProbeResult};
+    use crate::jobs::{create_job, save_job, JobStatus};
+    use crate::scan::ScanCandidate;
+    use std::time::SystemTime;
+    use tempfile::TempDir;
+
+    fn job(state_dir: &Path, path: &str) -> Job {
+        let candidate = ScanCandidate {
+            path: PathBuf::from(path),
+            size_bytes: 1,
+            modified_time: SystemTime::now(),
+            root: PathBuf::from("/media"),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 60.0,
+                size_bytes: 1,
+            },
+        };
+        let job = create_job(&candidate, probe, SourceType::Unknown, Path::new("/tmp"));
+        save_job(&job, state_dir).unwrap();
+        job
+    }
+
+    #[test]
+    fn test_is_extra() {
+        assert!(is_extra(Path::new("/media/Heat (1995)/Featurettes/making of.mkv")));
+        assert!(is_extra(Path::new("/media/Heat (1995)/extras/a.mkv")));
+        assert!(!is_extra(Path::new("/media/Heat (1995)/Heat (1995).mkv")));
+    }
+
+    #[test]
+    fn test_rules() {
+        let mut rules = DependencyRules::new(OrderingConfig::default());
+        assert!(!rules.enabled());
+        assert!(rules.dependencies_for("a", Path::new("/m/Heat/Heat.mkv")).is_empty());
+        assert!(rules.dependencies_for("b", Path::new("/m/Heat/Heat 2.mkv")).is_empty());
+
+        let mut rules = DependencyRules::new(OrderingConfig {
+            serialize_directories: true,
+            extras_after_main: true,
+        });
+        assert!(rules.dependencies_for("a", Path::new("/m/Heat/Heat.mkv")).is_empty());
+        assert_eq!(rules.dependencies_for("b", Path::new("/m/Heat/Extras/x.mkv")), ["a"]);
+        assert_eq!(rules.dependencies_for("c", Path::new("/m/Heat/Extras/y.mkv")), ["b", "a"]);
+        assert!(rules.dependencies_for("d", Path::new("/m/Ronin/Ronin.mkv")).is_empty());
+
+        let mut rules = DependencyRules::new(OrderingConfig {
+            serialize_directories: false,
+            extras_after_main: true,
+        });
+        rules.dependencies_for("a", Path::new("/m/Heat/Heat.mkv"));
+        assert_eq!(rules.dependencies_for("b", Path::new("/m/Heat/Extras/x.mkv")), ["a"]);
+        assert!(rules.dependencies_for("c", Path::new("/m/Heat/Heat 2.mkv")).is_empty());
+    }
+
+    #[test]
+    fn test_unfinished_dependencies() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let movie = job(dir, "/media/Heat/Heat.mkv");
+        let extra = job(dir, "/media/Heat/Extras/x.mkv");
+        add_dependencies(dir, &extra.id, std::slice::from_ref(&movie.id)).unwrap();
+        assert_eq!(unfinished_dependencies(dir, &extra.id).unwrap(), [movie.id.as_str()]);
+
+        update_job(dir, &movie.id, |job| job.status = JobStatus::Failed).unwrap();
+        assert!(unfinished_dependencies(dir, &extra.id).unwrap().is_empty());
+        assert!(unfinished_dependencies(dir, "missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_add_dependencies_rejects_cycles_and_unknown_jobs() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let a = job(dir, "/media/a.mkv");
+        let b = job(dir, "/media/b.mkv");
+        let c = job(dir, "/media/c.mkv");
+
+        let updated = add_dependencies(dir, &b.id, std::slice::from_ref(&a.id)).unwrap();
+        assert_eq!(updated.depends_on, [a.id.as_str()]);
+        // Adding it again changes nothing
+        assert_eq!(add_dependencies(dir, &b.id, std::slice::from_ref(&a.id)).unwrap().depends_on.len(), 1);
+        add_dependencies(dir, &c.id, std::slice::from_ref(&b.id)).unwrap();
+
+        let err = add_dependencies(dir, &a.id, std::slice::from_ref(&c.id)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = add_dependencies(dir, &a.id, std::slice::from_ref(&a.id)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = add_dependencies(dir, &a.id, &["missing".to_string()]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        update_job(dir, &a.id, |job| job.status = JobStatus::Running).unwrap();
+        let err = add_dependencies(dir, &a.id, std::slice::from_ref(&b.id)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
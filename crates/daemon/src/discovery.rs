@@ -0,0 +1,126 @@
+//! mDNS advertisement of the API.
+//!
+//! With `discovery.advertise`, the daemon announces its API on the LAN as
+//! `_av1sd._tcp`, so the dashboard (`atop --discover`) and other tools can
+//! find it without being told a host and port. The TXT record carries the
+//! daemon version and the metrics path.
+//!
+//! An API listening on loopback is unreachable from the LAN and is not
+//! advertised. One listening on the unspecified address is advertised on
+//! every interface, following address changes.
+
+use std::net::SocketAddr;
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+
+use crate::claims::host_name;
+use crate::config::DiscoveryConfig;
+use crate::tool_versions::DAEMON_VERSION;
+
+/// Service type the API is advertised as
+pub const SERVICE_TYPE: &str = "_av1sd._tcp.local.";
+
+/// A running advertisement, withdrawn when dropped.
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Advertisement {
+    /// Full mDNS name of the advertised instance.
+    pub fn fullname(&self) -> &str {
+        &self.fullname
+    }
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        let _ = self.daemon.unregister(&self.fullname);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// The service record for an API at `addr` on the machine `host`.
+pub fn service_info(
+    config: &DiscoveryConfig,
+    addr: SocketAddr,
+    host: &str,
+) -> Result<ServiceInfo, mdns_sd::Error> {
+    let instance = config.instance_name.as_deref().unwrap_or(host);
+    let host_name = format!("{}.local.", host.trim_end_matches(".local"));
+    let properties = [("version", DAEMON_VERSION), ("path", "/metrics")];
+    if addr.ip().is_unspecified() {
+        ServiceInfo::new(SERVICE_TYPE, instance, &host_name, "", addr.port(), &properties[..])
+            .map(ServiceInfo::enable_addr_auto)
+    } else {
+        ServiceInfo::new(SERVICE_TYPE, instance, &host_name, addr.ip(), addr.port(), &properties[..])
+    }
+}
+
+/// Advertises the API at `addr` if `config` asks for it.
+///
+/// # Returns
+/// `None` if advertising is off, the API is on loopback, or mDNS could not
+/// be started; the last two are logged
+pub fn advertise(config: &DiscoveryConfig, addr: SocketAddr) -> Option<Advertisement> {
+    if !config.advertise {
+        return None;
+    }
+    if addr.ip().is_loopback() {
+        log_warn!(
+            "Warning: discovery.advertise is set but the API listens on loopback ({}); not advertising",
+            addr
+        );
+        return None;
+    }
+    let started = ServiceDaemon::new().and_then(|daemon| {
+        let info = service_info(config, addr, &host_name())?;
+        let fullname = info.get_fullname().to_string();
+        daemon.register(info)?;
+        Ok(Advertisement { daemon, fullname })
+    });
+    match started {
+        Ok(advertisement) => {
+            log_info!("Advertising the API over mDNS as {}", advertisement.fullname);
+            Some(advertisement)
+        }
+        Err(e) => {
+            log_warn!("Warning: Failed to advertise the API over mDNS: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_info() {
+        let config = DiscoveryConfig {
+            advertise: true,
+            instance_name: Some("attic".to_string()),
+        };
+        let info = service_info(&config, "192.168.1.20:7878".parse().unwrap(), "nas").unwrap();
+        assert_eq!(info.get_fullname(), "attic._av1sd._tcp.local.");
+        assert_eq!(info.get_hostname(), "nas.local.");
+        assert_eq!(info.get_port(), 7878);
+        assert_eq!(info.get_property_val_str("path"), Some("/metrics"));
+        assert!(!info.is_addr_auto());
+
+        let config = DiscoveryConfig::default();
+        let info = service_info(&config, "0.0.0.0:7878".parse().unwrap(), "nas").unwrap();
+        assert_eq!(info.get_fullname(), "nas._av1sd._tcp.local.");
+        assert!(info.is_addr_auto());
+    }
+
+    #[test]
+    fn test_loopback_is_not_advertised() {
+        let config = DiscoveryConfig {
+            advertise: true,
+            instance_name: None,
+        };
+        assert!(advertise(&config, "127.0.0.1:7878".parse().unwrap()).is_none());
+        assert!(advertise(&DiscoveryConfig::default(), "0.0.0.0:7878".parse().unwrap()).is_none());
+    }
+}
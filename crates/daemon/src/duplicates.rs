@@ -0,0 +1,238 @@
+//! Duplicate content detection across the libraries.
+//!
+//! The same movie sitting in two folders gets encoded twice. With
+//! `scan.detect_duplicates`, every scan compares its candidates by a quick
+//! content signature and writes the files found more than once to a JSON
+//! report, so they can be cleaned up before any time is spent on them.
+//!
+//! Hashing whole files would read the entire library on every scan, so the
+//! signature covers the size and a handful of blocks sampled across the
+//! file. Only candidates sharing a size with another candidate are read at
+//! all. Hard links and other paths to the same physical file are not
+//! duplicates; the scanner already reports each physical file once.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::scan::ScanCandidate;
+
+/// Blocks hashed per file
+const SAMPLE_BLOCKS: u64 = 5;
+
+/// Size of each sampled block
+const BLOCK_BYTES: u64 = 64 * 1024;
+
+/// Files that share one content signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    /// Content signature, see [`content_signature`]
+    pub signature: String,
+    /// Size of each file in bytes
+    pub size_bytes: u64,
+    /// The files, sorted
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Bytes taken by every copy but one
+    pub fn redundant_bytes(&self) -> u64 {
+        self.size_bytes * (self.paths.len() as u64).saturating_sub(1)
+    }
+}
+
+/// Duplicates found by one scan
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    /// When the scan finished, in Unix milliseconds
+    pub generated_unix_ms: i64,
+    /// Largest files first
+    pub groups: Vec<DuplicateGroup>,
+}
+
+impl DuplicateReport {
+    /// Bytes taken by every copy but one, over all groups
+    pub fn redundant_bytes(&self) -> u64 {
+        self.groups.iter().map(DuplicateGroup::redundant_bytes).sum()
+    }
+
+    /// Loads a report from `path`, returning an empty one if the file does
+    /// not exist.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the report to `path` via a temporary file.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)
+    }
+}
+
+/// Signature of the file at `path` of `size` bytes: a SHA-256 over the size
+/// and [`SAMPLE_BLOCKS`] blocks spread evenly from its start to its end,
+/// as hex. Files too small to sample are hashed whole.
+pub fn content_signature(path: &Path, size: u64) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    hasher.update(size.to_le_bytes());
+    let mut buf = vec![0u8; BLOCK_BYTES as usize];
+
+    if size <= SAMPLE_BLOCKS * BLOCK_BYTES {
+        io::copy(&mut file, &mut HashWriter(&mut hasher))?;
+    } else {
+        let last = size - BLOCK_BYTES;
+        for i in 0..SAMPLE_BLOCKS {
+            file.seek(SeekFrom::Start(last * i / (SAMPLE_BLOCKS - 1)))?;
+            file.read_exact(&mut buf)?;
+            hasher.update(&buf);
+        }
+    }
+    Ok(hasher.finalize()[..16].iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+struct HashWriter<'a>(&'a mut Sha256);
+
+impl io::Write for HashWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Groups candidates with the same content signature.
+///
+/// Only candidates sharing a size are signed; files that cannot be read
+/// are left out with a warning.
+pub fn find_duplicates(candidates: &[ScanCandidate]) -> Vec<DuplicateGroup> {
+    let mut by_size: BTreeMap<u64, Vec<&Path>> = BTreeMap::new();
+    for candidate in candidates {
+        by_size.entry(candidate.size_bytes).or_default().push(&candidate.path);
+    }
+
+    let mut groups = Vec::new();
+    for (size, paths) in by_size.into_iter().rev() {
+        if paths.len() < 2 {
+            continue;
+        }
+        let mut by_signature: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for path in paths {
+            match content_signature(path, size) {
+                Ok(signature) => by_signature.entry(signature).or_default().push(path.to_path_buf()),
+                Err(e) => log_warn!("Warning: Failed to sign {:?} for duplicate detection: {}", path, e),
+            }
+        }
+        for (signature, mut paths) in by_signature {
+            if paths.len() > 1 {
+                paths.sort();
+                groups.push(DuplicateGroup {
+                    signature,
+                    size_bytes: size,
+                    paths,
+                });
+            }
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+    use tempfile::TempDir;
+
+    fn candidate(path: &Path) -> ScanCandidate {
+        ScanCandidate {
+            path: path.to_path_buf(),
+            size_bytes: fs::metadata(path).unwrap().len(),
+            modified_time: SystemTime::now(),
+            root: path.parent().unwrap().to_path_buf(),
+        }
+    }
+
+    fn write(dir: &Path, name: &str, content: &[u8]) -> ScanCandidate {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        candidate(&path)
+    }
+
+    #[test]
+    fn test_signature_samples_large_files() {
+        let temp = TempDir::new().unwrap();
+        let size = (SAMPLE_BLOCKS * BLOCK_BYTES * 4) as usize;
+        let a = write(temp.path(), "a.mkv", &vec![1u8; size]);
+
+        // A change between the sampled blocks goes unseen
+        let mut content = vec![1u8; size];
+        content[(BLOCK_BYTES + 10) as usize] = 2;
+        let b = write(temp.path(), "b.mkv", &content);
+        // The last block is always sampled
+        content[size - 1] = 2;
+        let c = write(temp.path(), "c.mkv", &content);
+
+        let signature = |c: &ScanCandidate| content_signature(&c.path, c.size_bytes).unwrap();
+        assert_eq!(signature(&a), signature(&b));
+        assert_ne!(signature(&a), signature(&c));
+        assert_eq!(signature(&a).len(), 32);
+    }
+
+    #[test]
+    fn test_find_duplicates_groups_same_content() {
+        let temp = TempDir::new().unwrap();
+        let candidates = vec![
+            write(temp.path(), "movies/Heat (1995).mkv", b"heat"),
+            write(temp.path(), "downloads/heat.mkv", b"heat"),
+            write(temp.path(), "movies/Ronin (1998).mkv", b"ronn"),
+            write(temp.path(), "movies/Alien (1979).mkv", b"alien"),
+        ];
+
+        let groups = find_duplicates(&candidates);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].size_bytes, 4);
+        assert_eq!(
+            groups[0].paths,
+            [temp.path().join("downloads/heat.mkv"), temp.path().join("movies/Heat (1995).mkv")]
+        );
+        assert_eq!(groups[0].redundant_bytes(), 4);
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state").join("duplicates.json");
+        assert_eq!(DuplicateReport::load(&path).unwrap(), DuplicateReport::default());
+
+        let report = DuplicateReport {
+            generated_unix_ms: 1,
+            groups: vec![DuplicateGroup {
+                signature: "ab".to_string(),
+                size_bytes: 10,
+                paths: vec![PathBuf::from("/a.mkv"), PathBuf::from("/b.mkv"), PathBuf::from("/c.mkv")],
+            }],
+        };
+        report.save(&path).unwrap();
+        assert_eq!(DuplicateReport::load(&path).unwrap(), report);
+        assert_eq!(report.redundant_bytes(), 20);
+    }
+}
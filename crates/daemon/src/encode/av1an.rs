@@ -3,15 +3,122 @@
 //! Provides functionality to build and execute Av1an encoding commands
 //! with fixed film-grain-tuned settings.
 
+use super::cancel::CancelToken;
+use super::cgroup::EncoderCgroup;
+use super::output_tail::{LineSplitter, OutputTail};
+use super::process_group::{groups_suspended, EncoderProcess};
+use super::run_as::RunAs;
+use super::svt_params::SvtParams;
+use crate::audio_policy::COPY_ALL_AUDIO_PARAMS;
+use crate::config::{Av1anConfig, StorageKind};
+use crate::classify::SourceType;
+use crate::pixel_format::DEFAULT_PIX_FORMAT;
+use crate::startup::detect_hardware_flag;
+use crate::tools::Tool;
 use crate::ConcurrencyPlan;
+use std::io::{self, Read, Write};
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
-/// Fixed SVT-AV1 parameters for film-grain tuning
-/// Includes CRF, preset, and film-grain settings for the encoder
-/// tune: 0=VQ, 1=PSNR, 2=SSIM (no tune 3 in newer SVT-AV1)
-const SVT_PARAMS: &str = "--crf 8 --preset 3 --film-grain 20 --enable-qm 1 --qm-min 1 --qm-max 15 --keyint 240 --lookahead 40";
+/// Encode profile selecting the SVT-AV1 parameter set for a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncodeProfile {
+    /// Film-grain-tuned settings for live action content
+    #[default]
+    Film,
+    /// Settings tuned for animated content
+    Animation,
+}
+
+impl EncodeProfile {
+    /// Select the encode profile for a classified source
+    pub fn for_source(source_type: SourceType) -> Self {
+        match source_type {
+            SourceType::Animation => EncodeProfile::Animation,
+            _ => EncodeProfile::Film,
+        }
+    }
+
+    /// SVT-AV1 parameters passed to Av1an via `--video-params`
+    pub fn svt_params(&self) -> SvtParams {
+        match self {
+            EncodeProfile::Film => SvtParams::film(),
+            EncodeProfile::Animation => SvtParams::animation(),
+        }
+    }
+}
+
+/// User overrides for individual SVT-AV1 settings of an encode profile
+///
+/// Unset fields keep the profile's value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SvtOverrides {
+    /// Replaces `--crf`
+    pub crf: Option<u8>,
+    /// Replaces `--preset`
+    pub preset: Option<u8>,
+    /// Replaces `--film-grain`
+    pub film_grain: Option<u8>,
+}
+
+impl SvtOverrides {
+    /// The overrides set in the `[av1an]` config section
+    pub fn from_config(av1an: &Av1anConfig) -> Self {
+        Self {
+            crf: av1an.crf,
+            preset: av1an.preset,
+            film_grain: av1an.film_grain,
+        }
+    }
+
+    /// Applies the overrides to a profile's SVT-AV1 parameters
+    pub fn apply(&self, params: SvtParams) -> SvtParams {
+        SvtParams {
+            crf: self.crf.unwrap_or(params.crf),
+            preset: self.preset.unwrap_or(params.preset),
+            film_grain: self.film_grain.unwrap_or(params.film_grain),
+            ..params
+        }
+    }
+}
+
+/// How Av1an splits the source into chunks (`--chunk-method`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkMethod {
+    /// Index the source once, then every worker seeks into it
+    Lsmash,
+    /// Split the source into segment files in one sequential pass
+    Segment,
+}
+
+impl ChunkMethod {
+    /// Chunk method for a source on `storage`, or `None` to let Av1an pick
+    ///
+    /// Workers reading an indexed source each seek into it, which thrashes
+    /// spinning disks; those keep lsmash with fewer workers reading (see
+    /// `storage.hdd_max_readers`). On a network share the many small reads
+    /// are slow, so the source is read once, sequentially, into segments
+    /// in the temp folder.
+    pub fn for_storage(storage: StorageKind) -> Option<Self> {
+        match storage {
+            StorageKind::Local => None,
+            StorageKind::Hdd => Some(ChunkMethod::Lsmash),
+            StorageKind::Network => Some(ChunkMethod::Segment),
+        }
+    }
+
+    /// The value Av1an takes for `--chunk-method`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChunkMethod::Lsmash => "lsmash",
+            ChunkMethod::Segment => "segment",
+        }
+    }
+}
 
 /// Error type for encoding operations
 #[derive(Debug, Error)]
@@ -20,15 +127,85 @@ pub enum EncodeError {
     #[error("Av1an failed with exit code: {0}")]
     Av1anFailed(i32),
 
-    /// Av1an process was terminated by signal
-    #[error("Av1an process was terminated by signal")]
-    Av1anTerminated,
+    /// Av1an process was terminated by a signal, its number if known
+    #[error("Av1an process was terminated by {}", signal_description(*.0))]
+    Av1anTerminated(Option<i32>),
+
+    /// Av1an ran longer than the configured wall-clock limit and was killed
+    #[error("Av1an exceeded the maximum encode time of {}s", .0.as_secs())]
+    TimedOut(Duration),
+
+    /// Av1an printed no progress output for too long and was killed
+    #[error("Av1an stalled: no progress output for {}s", .0.as_secs())]
+    Stalled(Duration),
+
+    /// The encode was cancelled and its process group killed
+    #[error("Encode cancelled")]
+    Cancelled,
+
+    /// The encode settings are out of range or ask for hardware encoding
+    #[error("Invalid encode parameters: {0}")]
+    InvalidParams(String),
+
+    /// ffmpeg failed while remuxing an AV1 source into MKV
+    #[error("Remux failed: {0}")]
+    RemuxFailed(String),
+
+    /// mkvpropedit failed while writing tags into the output
+    #[error("Tagging failed: {0}")]
+    TaggingFailed(String),
 
     /// IO error during encoding
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
 
+fn signal_description(signal: Option<i32>) -> String {
+    match signal {
+        Some(signal) => format!("signal {}", signal),
+        None => "a signal".to_string(),
+    }
+}
+
+/// Signal that ended a process, on platforms that have them
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+/// How often a supervised encode is checked against its limits
+const SUPERVISE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Limits enforced while an Av1an process runs
+///
+/// A wedged encode otherwise holds its concurrency slot forever.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodeLimits {
+    /// Kill the encode once it has run this long
+    pub max_duration: Option<Duration>,
+    /// Kill the encode if Av1an writes nothing to stdout or stderr for this long
+    pub stall_timeout: Option<Duration>,
+}
+
+impl EncodeLimits {
+    /// Build limits from config values in seconds, where 0 disables a limit
+    pub fn from_secs(max_encode_secs: u64, stall_timeout_secs: u64) -> Self {
+        let limit = |secs| (secs > 0).then(|| Duration::from_secs(secs));
+        Self {
+            max_duration: limit(max_encode_secs),
+            stall_timeout: limit(stall_timeout_secs),
+        }
+    }
+}
+
 /// Parameters for an Av1an encoding job
 ///
 /// Contains all necessary information to execute an encoding job.
@@ -42,6 +219,22 @@ pub struct Av1anEncodeParams {
     pub temp_chunks_dir: PathBuf,
     /// Concurrency settings for the encoding job
     pub concurrency: ConcurrencyPlan,
+    /// Encode profile selecting the SVT-AV1 parameter set
+    pub profile: EncodeProfile,
+    /// Per-setting overrides layered over the profile's parameters
+    pub svt_overrides: SvtOverrides,
+    /// User and group Av1an runs as, if not the daemon's own
+    pub run_as: Option<RunAs>,
+    /// cgroup the Av1an process tree runs in, if `[cgroups]` is enabled
+    pub cgroup: Option<Arc<EncoderCgroup>>,
+    /// Keeps the end of Av1an's output for a triage bundle, if set
+    pub output_tail: Option<Arc<OutputTail>>,
+    /// ffmpeg arguments for the audio tracks, from the job's audio plan
+    pub audio_params: String,
+    /// Pixel format of the encode, from the source's and `[pixel_format]`
+    pub pix_format: String,
+    /// Chunk method for the source's storage; Av1an's default when `None`
+    pub chunk_method: Option<ChunkMethod>,
 }
 
 impl Av1anEncodeParams {
@@ -57,7 +250,41 @@ impl Av1anEncodeParams {
             output_path,
             temp_chunks_dir,
             concurrency,
+            profile: EncodeProfile::default(),
+            svt_overrides: SvtOverrides::default(),
+            run_as: None,
+            cgroup: None,
+            output_tail: None,
+            audio_params: COPY_ALL_AUDIO_PARAMS.to_string(),
+            pix_format: DEFAULT_PIX_FORMAT.to_string(),
+            chunk_method: None,
+        }
+    }
+
+    /// SVT-AV1 parameters of the encode: the profile's, with the overrides
+    pub fn video_params(&self) -> SvtParams {
+        self.svt_overrides.apply(self.profile.svt_params())
+    }
+
+    /// Checks the encoder settings before Av1an is started
+    ///
+    /// # Errors
+    /// [`EncodeError::InvalidParams`] if an SVT-AV1 setting is out of range
+    /// or the pixel format or audio arguments name a hardware encoder
+    pub fn validate(&self) -> Result<(), EncodeError> {
+        self.video_params()
+            .validate()
+            .map_err(|e| EncodeError::InvalidParams(e.to_string()))?;
+        // Paths are left out; a file may well be called "Barracuda.mkv"
+        for arg in [&self.pix_format, &self.audio_params] {
+            if let Some(flag) = detect_hardware_flag(arg) {
+                return Err(EncodeError::InvalidParams(format!(
+                    "hardware encoder flag {:?} in {:?}",
+                    flag, arg
+                )));
+            }
         }
+        Ok(())
     }
 }
 
@@ -66,10 +293,13 @@ impl Av1anEncodeParams {
 ///
 /// Creates a Command configured with:
 /// - Input and output paths
-/// - SVT-AV1 encoder with film-grain tuning
-/// - Fixed quality settings (CRF 8, preset 3, yuv420p10le)
+/// - SVT-AV1 encoder with parameters from the encode profile
+/// - Pixel format from the parameters (yuv420p10le unless the source and
+///   `[pixel_format]` ask for another)
 /// - Worker count from concurrency plan
+/// - Chunk method, when the source's storage calls for one
 /// - Temporary directory for chunks
+/// - The `run_as` identity and the encode's cgroup, when set
 ///
 /// # Arguments
 /// * `params` - Encoding parameters including paths and concurrency settings
@@ -77,7 +307,7 @@ impl Av1anEncodeParams {
 /// # Returns
 /// A configured Command ready for execution
 pub fn build_av1an_command(params: &Av1anEncodeParams) -> Command {
-    let mut cmd = Command::new("av1an");
+    let mut cmd = Tool::Av1an.command();
 
     // Input and output paths (Requirements 10.1, 10.2)
     cmd.arg("-i").arg(&params.input_path);
@@ -87,25 +317,58 @@ pub fn build_av1an_command(params: &Av1anEncodeParams) -> Command {
     cmd.arg("--encoder").arg("svt-av1");
 
     // Pixel format (Requirements 2.2, 10.4)
-    cmd.arg("--pix-format").arg("yuv420p10le");
+    cmd.arg("--pix-format").arg(&params.pix_format);
 
     // Video encoder parameters including CRF, preset, and film-grain tuning
     // (Requirements 2.3, 2.4, 2.5, 10.5, 10.6, 10.7)
-    cmd.arg("--video-params").arg(SVT_PARAMS);
+    cmd.arg("--video-params").arg(params.video_params().to_string());
 
-    // Audio handling - copy all audio streams (Requirements 2.7, 10.9)
-    cmd.arg("--audio-params").arg("-c:a copy");
+    // Audio handling - copy all audio streams unless the audio policy drops
+    // or downmixes some (Requirements 2.7, 10.9)
+    cmd.arg("--audio-params").arg(&params.audio_params);
 
     // Worker count from concurrency plan (Requirements 10.10)
     cmd.arg("--workers")
         .arg(params.concurrency.av1an_workers.to_string());
 
+    if let Some(method) = params.chunk_method {
+        cmd.arg("--chunk-method").arg(method.as_str());
+    }
+
     // Temporary chunks directory (Requirements 10.11)
     cmd.arg("--temp").arg(&params.temp_chunks_dir);
 
+    if let Some(run_as) = params.run_as {
+        run_as.apply(&mut cmd);
+    }
+    if let Some(cgroup) = &params.cgroup {
+        cgroup.apply(&mut cmd);
+    }
+
     cmd
 }
 
+/// The command line `cmd` runs, with arguments quoted for a POSIX shell
+/// where needed, for job records and logs
+pub fn command_line(cmd: &Command) -> String {
+    let quote = |arg: &std::ffi::OsStr| {
+        let arg = arg.to_string_lossy();
+        let plain = !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c));
+        if plain {
+            arg.into_owned()
+        } else {
+            format!("'{}'", arg.replace('\'', "'\\''"))
+        }
+    };
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(quote)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
 /// Execute an Av1an encoding job
 ///
@@ -120,24 +383,161 @@ pub fn build_av1an_command(params: &Av1anEncodeParams) -> Command {
 ///
 /// # Errors
 /// Returns an error if:
+/// - The encode parameters fail [`Av1anEncodeParams::validate`]
 /// - The Av1an process fails to start (IO error)
 /// - The Av1an process exits with non-zero status
 /// - The Av1an process is terminated by a signal
 pub fn run_av1an(params: &Av1anEncodeParams) -> Result<(), EncodeError> {
-    let mut cmd = build_av1an_command(params);
+    run_av1an_with_limits(params, &EncodeLimits::default())
+}
 
-    let status = cmd.status()?;
+/// Execute an Av1an encoding job, killing it if it exceeds `limits`
+///
+/// Av1an's output is forwarded to the daemon's own stdout and stderr; any
+/// output counts as progress for stall detection.
+///
+/// # Errors
+/// In addition to the errors of [`run_av1an`], returns
+/// [`EncodeError::TimedOut`] or [`EncodeError::Stalled`] when a limit is hit.
+pub fn run_av1an_with_limits(
+    params: &Av1anEncodeParams,
+    limits: &EncodeLimits,
+) -> Result<(), EncodeError> {
+    run_av1an_cancellable(params, limits, &CancelToken::new())
+}
+
+/// Execute an Av1an encoding job that can also be stopped through `cancel`
+///
+/// # Errors
+/// In addition to the errors of [`run_av1an_with_limits`], returns
+/// [`EncodeError::Cancelled`] once `cancel` is triggered.
+pub fn run_av1an_cancellable(
+    params: &Av1anEncodeParams,
+    limits: &EncodeLimits,
+    cancel: &CancelToken,
+) -> Result<(), EncodeError> {
+    params.validate()?;
+    supervise(build_av1an_command(params), limits, cancel, params.output_tail.clone())
+}
+
+/// Runs `cmd` to completion while enforcing `limits` and watching `cancel`,
+/// recording the end of its output in `tail` if given
+///
+/// The command runs in its own process group, so stopping it also stops the
+/// ffmpeg and encoder processes it spawned.
+fn supervise(
+    mut cmd: Command,
+    limits: &EncodeLimits,
+    cancel: &CancelToken,
+    tail: Option<Arc<OutputTail>>,
+) -> Result<(), EncodeError> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut process = EncoderProcess::spawn(&mut cmd)?;
+    let child = process.child_mut();
+
+    let mut started = Instant::now();
+    let mut last_tick = started;
+    let last_output = Arc::new(Mutex::new(started));
+    let mut forwarders = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        forwarders.push(forward_output(stdout, io::stdout(), last_output.clone(), tail.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forwarders.push(forward_output(stderr, io::stderr(), last_output.clone(), tail));
+    }
+
+    let status = loop {
+        if let Some(status) = process.child_mut().try_wait()? {
+            break status;
+        }
+
+        if cancel.is_cancelled() {
+            process.terminate();
+            return Err(EncodeError::Cancelled);
+        }
+
+        // Time suspended by the kill switch or polite mode counts against
+        // neither limit
+        let now = Instant::now();
+        if groups_suspended() {
+            let paused = now - last_tick;
+            started += paused;
+            if let Ok(mut last) = last_output.lock() {
+                *last += paused;
+            }
+        }
+        last_tick = now;
+
+        if let Some(max) = limits.max_duration {
+            if started.elapsed() >= max {
+                process.terminate();
+                return Err(EncodeError::TimedOut(max));
+            }
+        }
+        if let Some(stall) = limits.stall_timeout {
+            let idle = last_output.lock().map(|t| t.elapsed()).unwrap_or_default();
+            if idle >= stall {
+                process.terminate();
+                return Err(EncodeError::Stalled(stall));
+            }
+        }
+
+        thread::sleep(SUPERVISE_POLL_INTERVAL);
+    };
+
+    // Stop any stragglers still holding the output pipes open before
+    // waiting for the forwarders to drain
+    drop(process);
+    for forwarder in forwarders {
+        let _ = forwarder.join();
+    }
 
     if status.success() {
         Ok(())
     } else {
         match status.code() {
             Some(code) => Err(EncodeError::Av1anFailed(code)),
-            None => Err(EncodeError::Av1anTerminated),
+            None => Err(EncodeError::Av1anTerminated(exit_signal(&status))),
         }
     }
 }
 
+/// Copies a child's output stream to `writer`, recording when output was last
+/// seen and, if given, its last lines in `tail`
+fn forward_output<R, W>(
+    mut reader: R,
+    mut writer: W,
+    last_output: Arc<Mutex<Instant>>,
+    tail: Option<Arc<OutputTail>>,
+) -> thread::JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        let mut lines = LineSplitter::default();
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if let Ok(mut t) = last_output.lock() {
+                        *t = Instant::now();
+                    }
+                    if let Some(tail) = &tail {
+                        lines.feed(&buf[..n], tail);
+                    }
+                    let _ = writer.write_all(&buf[..n]);
+                    let _ = writer.flush();
+                }
+            }
+        }
+        if let Some(tail) = &tail {
+            lines.finish(tail);
+        }
+    })
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -158,11 +558,6 @@ mod tests {
             .any(|pair| pair[0] == flag && pair[1] == value)
     }
 
-    /// Helper to check if args contain a standalone flag
-    fn has_flag(args: &[String], flag: &str) -> bool {
-        args.iter().any(|arg| arg == flag)
-    }
-
     // Strategy for generating valid path-like strings
     fn path_strategy() -> impl Strategy<Value = String> {
         prop::string::string_regex("[a-zA-Z0-9_/.-]{1,50}")
@@ -235,38 +630,22 @@ mod tests {
                 args
             );
 
-            // Verify CRF (Requirements 2.3, 10.5)
-            prop_assert!(
-                has_flag_with_value(&args, "--crf", "8"),
-                "Command should contain --crf 8, args: {:?}",
-                args
-            );
-
-            // Verify preset (Requirements 2.4, 10.6)
-            prop_assert!(
-                has_flag_with_value(&args, "--preset", "3"),
-                "Command should contain --preset 3, args: {:?}",
-                args
-            );
-
-            // Verify SVT params (Requirements 2.5, 10.7)
+            // Verify SVT params with CRF, preset, and film-grain tuning
+            // (Requirements 2.3, 2.4, 2.5, 10.5, 10.6, 10.7)
+            let film = SvtParams::film().to_string();
             prop_assert!(
-                has_flag_with_value(&args, "--svt-params", SVT_PARAMS),
-                "Command should contain --svt-params with film-grain tuning, args: {:?}",
-                args
-            );
-
-            // Verify target quality (Requirements 2.6, 10.8)
-            prop_assert!(
-                has_flag_with_value(&args, "--target-quality", "1"),
-                "Command should contain --target-quality 1, args: {:?}",
+                has_flag_with_value(&args, "--video-params", &film),
+                "Command should contain --video-params with film-grain tuning, args: {:?}",
                 args
             );
+            prop_assert!(film.contains("--crf 8"));
+            prop_assert!(film.contains("--preset 3"));
+            prop_assert!(film.contains("--film-grain 20"));
 
             // Verify audio copy (Requirements 2.7, 10.9)
             prop_assert!(
-                has_flag(&args, "--audio-copy"),
-                "Command should contain --audio-copy, args: {:?}",
+                has_flag_with_value(&args, "--audio-params", "-c:a copy"),
+                "Command should contain --audio-params -c:a copy, args: {:?}",
                 args
             );
 
@@ -285,4 +664,211 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_encode_profile_for_source() {
+        assert_eq!(EncodeProfile::for_source(SourceType::Animation), EncodeProfile::Animation);
+        assert_eq!(EncodeProfile::for_source(SourceType::DiscLike), EncodeProfile::Film);
+        assert_eq!(EncodeProfile::for_source(SourceType::WebLike), EncodeProfile::Film);
+        assert_eq!(EncodeProfile::for_source(SourceType::Unknown), EncodeProfile::Film);
+    }
+
+    #[test]
+    fn test_animation_profile_video_params() {
+        let concurrency = ConcurrencyPlan {
+            total_cores: 32,
+            target_threads: 28,
+            av1an_workers: 8,
+            max_concurrent_jobs: 1,
+        };
+        let mut params = Av1anEncodeParams::new(
+            PathBuf::from("/media/anime/show.mkv"),
+            PathBuf::from("/tmp/out.mkv"),
+            PathBuf::from("/tmp/chunks"),
+            concurrency,
+        );
+        params.profile = EncodeProfile::Animation;
+
+        let args = get_command_args(&build_av1an_command(&params));
+        assert!(has_flag_with_value(&args, "--video-params", &SvtParams::animation().to_string()));
+        assert!(!has_flag_with_value(&args, "--video-params", &SvtParams::film().to_string()));
+    }
+
+    #[test]
+    fn test_chunk_method_follows_storage() {
+        assert_eq!(ChunkMethod::for_storage(StorageKind::Local), None);
+        assert_eq!(ChunkMethod::for_storage(StorageKind::Hdd), Some(ChunkMethod::Lsmash));
+        assert_eq!(ChunkMethod::for_storage(StorageKind::Network), Some(ChunkMethod::Segment));
+
+        let concurrency = ConcurrencyPlan {
+            total_cores: 8,
+            target_threads: 8,
+            av1an_workers: 2,
+            max_concurrent_jobs: 1,
+        };
+        let mut params = Av1anEncodeParams::new(
+            PathBuf::from("/mnt/nas/movie.mkv"),
+            PathBuf::from("/tmp/out.mkv"),
+            PathBuf::from("/tmp/chunks"),
+            concurrency,
+        );
+        let args = get_command_args(&build_av1an_command(&params));
+        assert!(!args.iter().any(|arg| arg == "--chunk-method"));
+
+        params.chunk_method = ChunkMethod::for_storage(StorageKind::Network);
+        let args = get_command_args(&build_av1an_command(&params));
+        assert!(has_flag_with_value(&args, "--chunk-method", "segment"));
+    }
+
+    #[test]
+    fn test_svt_overrides_replace_fields() {
+        assert_eq!(SvtOverrides::default().apply(SvtParams::film()), SvtParams::film());
+
+        let overrides = SvtOverrides {
+            crf: Some(24),
+            preset: None,
+            film_grain: Some(0),
+        };
+        let params = overrides.apply(SvtParams::film());
+        assert_eq!(params.crf, 24);
+        assert_eq!(params.preset, 3);
+        assert_eq!(params.film_grain, 0);
+        assert_eq!(params.keyint, SvtParams::film().keyint);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_params() {
+        let concurrency = ConcurrencyPlan {
+            total_cores: 8,
+            target_threads: 8,
+            av1an_workers: 2,
+            max_concurrent_jobs: 1,
+        };
+        let mut params = Av1anEncodeParams::new(
+            PathBuf::from("/media/Barracuda.mkv"),
+            PathBuf::from("/tmp/out.mkv"),
+            PathBuf::from("/tmp/chunks"),
+            concurrency,
+        );
+        assert!(params.validate().is_ok());
+
+        params.svt_overrides.preset = Some(14);
+        assert!(matches!(params.validate(), Err(EncodeError::InvalidParams(_))));
+        // Checked before Av1an is ever started
+        assert!(matches!(
+            run_av1an(&params),
+            Err(EncodeError::InvalidParams(_))
+        ));
+
+        params.svt_overrides.preset = None;
+        params.audio_params = "-c:a copy -hwaccel cuda".to_string();
+        assert!(matches!(params.validate(), Err(EncodeError::InvalidParams(_))));
+    }
+
+    #[test]
+    fn test_command_line_quotes_args() {
+        let video_params = SvtParams::film().to_args()[..2].join(" ");
+        let mut cmd = Command::new("av1an");
+        cmd.arg("-i")
+            .arg("/media/Movie (2020).mkv")
+            .arg("--video-params")
+            .arg(video_params)
+            .arg("it's");
+        assert_eq!(
+            command_line(&cmd),
+            "av1an -i '/media/Movie (2020).mkv' --video-params '--crf 8' 'it'\\''s'"
+        );
+    }
+
+    #[cfg(unix)]
+    fn sh(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+
+    #[test]
+    fn test_encode_limits_from_secs() {
+        assert_eq!(EncodeLimits::from_secs(0, 0), EncodeLimits::default());
+        let limits = EncodeLimits::from_secs(3600, 60);
+        assert_eq!(limits.max_duration, Some(Duration::from_secs(3600)));
+        assert_eq!(limits.stall_timeout, Some(Duration::from_secs(60)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_supervise_passes_through_exit_status() {
+        let limits = EncodeLimits::from_secs(60, 60);
+        assert!(supervise(sh("echo progress >&2"), &limits, &CancelToken::new(), None).is_ok());
+        let tail = Arc::new(OutputTail::new(2));
+        assert!(matches!(
+            supervise(
+                sh("echo one; echo two >&2; echo 'Error: chunk 4 failed' >&2; exit 3"),
+                &limits,
+                &CancelToken::new(),
+                Some(tail.clone())
+            ),
+            Err(EncodeError::Av1anFailed(3))
+        ));
+        assert_eq!(tail.lines().last().map(String::as_str), Some("Error: chunk 4 failed"));
+        assert_eq!(tail.lines().len(), 2);
+
+        // Killed the way the OOM killer does it
+        assert!(matches!(
+            supervise(sh("kill -9 $$"), &limits, &CancelToken::new(), None),
+            Err(EncodeError::Av1anTerminated(Some(9)))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_supervise_kills_stalled_process() {
+        let limits = EncodeLimits {
+            max_duration: None,
+            stall_timeout: Some(Duration::from_millis(300)),
+        };
+        let started = Instant::now();
+        let result = supervise(sh("echo starting >&2; exec sleep 30"), &limits, &CancelToken::new(), None);
+        assert!(matches!(result, Err(EncodeError::Stalled(_))));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_supervise_enforces_wall_clock_limit() {
+        // Keeps printing, so only the wall-clock limit can stop it
+        let limits = EncodeLimits {
+            max_duration: Some(Duration::from_millis(500)),
+            stall_timeout: Some(Duration::from_secs(30)),
+        };
+        let result = supervise(
+            sh("while true; do echo frame >&2; sleep 0.05; done"),
+            &limits,
+            &CancelToken::new(),
+            None,
+        );
+        assert!(matches!(result, Err(EncodeError::TimedOut(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_supervise_stops_on_cancel() {
+        let cancel = CancelToken::new();
+        let trigger = cancel.clone();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            trigger.cancel();
+        });
+
+        let started = Instant::now();
+        let result = supervise(
+            sh("while true; do echo frame >&2; sleep 0.05; done"),
+            &EncodeLimits::default(),
+            &cancel,
+            None,
+        );
+        canceller.join().unwrap();
+        assert!(matches!(result, Err(EncodeError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
 }
@@ -0,0 +1,32 @@
+//! Cancellation tokens for running encodes
+//!
+//! Encodes run on blocking threads that poll their process several times a
+//! second, so a shared flag is all the signalling they need. Cancelling a
+//! token makes the supervisor terminate the encoder's process group on its
+//! next poll.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared flag asking an encode to stop
+///
+/// Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates a token that has not been cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every holder of this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true once [`cancel`](Self::cancel) has been called on any clone
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,187 @@
+//! cgroup v2 limits for encoder processes
+//!
+//! Worker counts only keep encodes from asking for more CPU than planned;
+//! nothing stops an Av1an process tree from taking more, or from using
+//! enough memory to push the rest of the host into swap. With
+//! `[cgroups]` enabled every encode runs in a cgroup of its own under the
+//! configured parent:
+//! - the parent's `cpu.max` caps all encodes together at the planned
+//!   number of threads
+//! - each encode's `cpu.weight` is proportional to its workers, so encodes
+//!   share that CPU the way the plan splits it
+//! - each encode's `memory.max` is `memory_per_worker_mb` per worker, so a
+//!   runaway encode is killed instead of the host running out of memory
+//!
+//! The encoder moves itself into its cgroup between fork and exec, so
+//! every process it starts is limited from the start.
+
+use crate::config::CgroupConfig;
+use crate::ConcurrencyPlan;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// `cpu.weight` per Av1an worker; 100 is the weight of a cgroup that
+/// sets none
+const CPU_WEIGHT_PER_WORKER: u32 = 100;
+
+/// Largest `cpu.weight` the kernel accepts
+const MAX_CPU_WEIGHT: u32 = 10_000;
+
+/// Period of the parent's `cpu.max`, in microseconds
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// The cgroup of one encode, removed again when dropped
+#[derive(Debug)]
+pub struct EncoderCgroup {
+    path: PathBuf,
+    /// `cgroup.procs`, opened while the daemon still has the rights to
+    /// move processes into the cgroup
+    procs: File,
+}
+
+impl EncoderCgroup {
+    /// Creates the cgroup `name` under `config.parent` for an encode with
+    /// `plan.av1an_workers` workers
+    ///
+    /// The parent is created if needed, gets the cpu and memory
+    /// controllers enabled for its children, and is capped at
+    /// `plan.target_threads` CPUs.
+    pub fn create(config: &CgroupConfig, plan: &ConcurrencyPlan, name: &str) -> io::Result<Self> {
+        if !cfg!(target_os = "linux") {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "cgroups need Linux"));
+        }
+
+        let parent = &config.parent;
+        fs::create_dir_all(parent)?;
+        fs::write(parent.join("cgroup.subtree_control"), "+cpu +memory")?;
+        fs::write(parent.join("cpu.max"), cpu_max(plan.target_threads))?;
+
+        let path = parent.join(name);
+        match fs::create_dir(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+        let procs = configure(&path, config, plan.av1an_workers).inspect_err(|_| {
+            let _ = fs::remove_dir(&path);
+        })?;
+        Ok(Self { path, procs })
+    }
+
+    /// Directory of the cgroup
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Makes `cmd` move itself into this cgroup when it is spawned, before
+    /// it runs anything
+    pub fn apply(&self, cmd: &mut Command) {
+        #[cfg(unix)]
+        {
+            use std::os::fd::AsRawFd;
+            use std::os::unix::process::CommandExt;
+
+            let fd = self.procs.as_raw_fd();
+            // SAFETY: the closure only calls write(2), which is safe between
+            // fork and exec; writing "0" to cgroup.procs moves the writer.
+            // `self` outlives the spawn, so the descriptor is still open.
+            unsafe {
+                cmd.pre_exec(move || {
+                    if libc::write(fd, b"0".as_ptr().cast(), 1) < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = cmd;
+    }
+}
+
+impl Drop for EncoderCgroup {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir(&self.path) {
+            log_warn!("Warning: Failed to remove cgroup {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// Sets the limits of the encode cgroup at `path` and opens its
+/// `cgroup.procs`
+fn configure(path: &Path, config: &CgroupConfig, workers: u32) -> io::Result<File> {
+    fs::write(path.join("cpu.weight"), cpu_weight(workers).to_string())?;
+    fs::write(path.join("memory.max"), memory_max(config.memory_per_worker_mb, workers))?;
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path.join("cgroup.procs"))
+}
+
+/// `cpu.weight` of an encode with `workers` workers
+fn cpu_weight(workers: u32) -> u32 {
+    workers.max(1).saturating_mul(CPU_WEIGHT_PER_WORKER).min(MAX_CPU_WEIGHT)
+}
+
+/// `cpu.max` allowing `threads` CPUs, or no limit for 0
+fn cpu_max(threads: u32) -> String {
+    if threads == 0 {
+        return format!("max {}", CPU_PERIOD_US);
+    }
+    format!("{} {}", threads as u64 * CPU_PERIOD_US, CPU_PERIOD_US)
+}
+
+/// `memory.max` for `workers` workers at `per_worker_mb` MiB each, or no
+/// limit for 0
+fn memory_max(per_worker_mb: u64, workers: u32) -> String {
+    if per_worker_mb == 0 {
+        return "max".to_string();
+    }
+    (per_worker_mb * workers.max(1) as u64 * 1024 * 1024).to_string()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_follow_the_plan() {
+        // A plain directory stands in for the cgroup filesystem
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = CgroupConfig {
+            enabled: true,
+            parent: temp_dir.path().join("av1-super-daemon"),
+            memory_per_worker_mb: 2048,
+        };
+        let plan = ConcurrencyPlan {
+            total_cores: 32,
+            target_threads: 27,
+            av1an_workers: 4,
+            max_concurrent_jobs: 2,
+        };
+
+        let cgroup = EncoderCgroup::create(&config, &plan, "job-1").unwrap();
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(config.parent.join("cgroup.subtree_control")), "+cpu +memory");
+        assert_eq!(read(config.parent.join("cpu.max")), "2700000 100000");
+        assert_eq!(read(cgroup.path().join("cpu.weight")), "400");
+        assert_eq!(read(cgroup.path().join("memory.max")), (8u64 << 30).to_string());
+
+        let mut cmd = Command::new("true");
+        cgroup.apply(&mut cmd);
+        assert!(cmd.status().unwrap().success());
+        assert_eq!(read(cgroup.path().join("cgroup.procs")), "0");
+    }
+
+    #[test]
+    fn test_limit_values() {
+        assert_eq!(cpu_weight(0), 100);
+        assert_eq!(cpu_weight(500), MAX_CPU_WEIGHT);
+        assert_eq!(cpu_max(0), "max 100000");
+        assert_eq!(memory_max(0, 8), "max");
+        assert_eq!(memory_max(1, 2), "2097152");
+    }
+}
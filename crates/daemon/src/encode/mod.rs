@@ -1,5 +1,29 @@
 //! Encoding modules for AV1 Super Daemon
 
 pub mod av1an;
+pub mod cancel;
+pub mod cgroup;
+pub mod output_tail;
+pub mod process_group;
+pub mod remux;
+pub mod run_as;
+pub mod svt_params;
+pub mod tags;
 
-pub use av1an::{build_av1an_command, run_av1an, Av1anEncodeParams, EncodeError};
+pub use av1an::{
+    build_av1an_command, command_line, run_av1an, run_av1an_cancellable, run_av1an_with_limits,
+    Av1anEncodeParams, ChunkMethod, EncodeError, EncodeLimits, EncodeProfile, SvtOverrides,
+};
+pub use cancel::CancelToken;
+pub use cgroup::EncoderCgroup;
+pub use output_tail::{LineSplitter, OutputTail};
+pub use process_group::{
+    active_group_count, can_suspend_groups, groups_suspended, resume_all_groups, suspend_all_groups,
+    terminate_all_groups, EncoderProcess, SuspendReason,
+};
+pub use remux::{
+    build_remux_command, is_remux_container, run_remux, run_remux_as, REMUX_SOURCE_EXTENSIONS,
+};
+pub use run_as::RunAs;
+pub use svt_params::{SvtParamError, SvtParams, CRF_RANGE, PRESET_RANGE};
+pub use tags::{build_tag_command, is_taggable, write_mkv_tags, ProcessingTags, ENCODED_BY};
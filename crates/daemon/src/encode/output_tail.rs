@@ -0,0 +1,109 @@
+//! The last lines an encoder printed, kept for failure triage
+//!
+//! Av1an's output is forwarded to the daemon's own stdout and stderr as it
+//! arrives, so by the time a job fails it is gone unless the daemon's log is
+//! kept. An [`OutputTail`] remembers the end of it. Progress bars redraw
+//! their line with `\r`, so carriage returns end a line too; otherwise a
+//! single progress line would push everything else out.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Longest line kept, in bytes; the rest of a longer line is dropped
+const MAX_LINE_BYTES: usize = 4096;
+
+/// Ring buffer of the last lines written by an encoder's output streams
+#[derive(Debug)]
+pub struct OutputTail {
+    capacity: usize,
+    lines: Mutex<VecDeque<String>>,
+}
+
+impl OutputTail {
+    /// Keeps the last `capacity` lines; 0 keeps nothing
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+        }
+    }
+
+    /// Records a complete line, dropping the oldest if the tail is full
+    pub fn push_line(&self, line: &[u8]) {
+        if self.capacity == 0 {
+            return;
+        }
+        let line = String::from_utf8_lossy(&line[..line.len().min(MAX_LINE_BYTES)]);
+        let line = line.trim_end();
+        if line.is_empty() {
+            return;
+        }
+        let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line.to_string());
+    }
+
+    /// The kept lines, oldest first
+    pub fn lines(&self) -> Vec<String> {
+        let lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        lines.iter().cloned().collect()
+    }
+}
+
+/// Splits one output stream into lines for an [`OutputTail`]
+///
+/// Each stream needs its own splitter, so a line cut across two reads of
+/// stdout is not joined with stderr's.
+#[derive(Debug, Default)]
+pub struct LineSplitter {
+    partial: Vec<u8>,
+}
+
+impl LineSplitter {
+    /// Feeds a chunk of output, pushing every line it completes to `tail`
+    pub fn feed(&mut self, chunk: &[u8], tail: &OutputTail) {
+        for &byte in chunk {
+            if byte == b'\n' || byte == b'\r' {
+                tail.push_line(&self.partial);
+                self.partial.clear();
+            } else if self.partial.len() < MAX_LINE_BYTES {
+                self.partial.push(byte);
+            }
+        }
+    }
+
+    /// Pushes whatever is left once the stream has ended
+    pub fn finish(&mut self, tail: &OutputTail) {
+        tail.push_line(&self.partial);
+        self.partial.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_only_the_last_lines() {
+        let tail = OutputTail::new(3);
+        let mut splitter = LineSplitter::default();
+        splitter.feed(b"one\ntwo\nthr", &tail);
+        splitter.feed(b"ee\nfour\n\nfive", &tail);
+        splitter.finish(&tail);
+        assert_eq!(tail.lines(), ["three", "four", "five"]);
+    }
+
+    #[test]
+    fn test_progress_redraws_end_lines() {
+        let tail = OutputTail::new(10);
+        let mut splitter = LineSplitter::default();
+        splitter.feed(b"encoding\r 10%\r 20%\r\nError: chunk 4 failed\n", &tail);
+        assert_eq!(tail.lines(), ["encoding", " 10%", " 20%", "Error: chunk 4 failed"]);
+
+        let nothing = OutputTail::new(0);
+        splitter.feed(b"lost\n", &nothing);
+        assert!(nothing.lines().is_empty());
+    }
+}
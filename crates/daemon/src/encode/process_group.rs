@@ -0,0 +1,436 @@
+//! Process group management for encoder subprocesses
+//!
+//! Av1an fans out into ffmpeg and SvtAv1EncApp processes. Killing only the
+//! av1an process leaves those behind, chewing CPU with nobody to collect
+//! their output. Each encode is therefore started as the leader of its own
+//! process group, and the whole group is signalled when the encode is
+//! stopped or the daemon shuts down.
+//!
+//! Windows has no signals to send to a group. There each encode is put in a
+//! job object, which the processes it starts join as well: stopping sends
+//! Ctrl+Break to its console process group, then terminates the job.
+//!
+//! On Unix every group can also be suspended and resumed at once, for the
+//! kill switch and polite mode. Groups started while suspended are stopped
+//! right away, and stay stopped until every reason to suspend is gone.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a group gets to exit after SIGTERM before it is sent SIGKILL
+pub const TERMINATE_GRACE: Duration = Duration::from_secs(5);
+
+/// Poll interval while waiting for a group to exit
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Process group IDs of every encoder currently running
+static ACTIVE_GROUPS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+/// Reasons encoder groups are currently suspended, one bit per
+/// [`SuspendReason`]
+static SUSPENDED: AtomicU8 = AtomicU8::new(0);
+
+/// What suspended the encoder groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuspendReason {
+    /// The kill switch file exists
+    KillSwitch,
+    /// Polite mode yields the host to interactive use
+    Polite,
+}
+
+impl SuspendReason {
+    fn bit(self) -> u8 {
+        match self {
+            SuspendReason::KillSwitch => 1,
+            SuspendReason::Polite => 2,
+        }
+    }
+}
+
+/// An encoder process running as the leader of its own process group
+///
+/// Dropping it terminates whatever is left of the group, so descendants
+/// never outlive the encode that started them.
+pub struct EncoderProcess {
+    child: Child,
+    pgid: u32,
+}
+
+impl EncoderProcess {
+    /// Spawns `cmd` as the leader of a new process group
+    pub fn spawn(cmd: &mut Command) -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            use windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP;
+            cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+        }
+
+        let child = cmd.spawn()?;
+        let pgid = child.id();
+        #[cfg(windows)]
+        windows_job::attach(pgid, &child);
+        if let Ok(mut groups) = ACTIVE_GROUPS.lock() {
+            groups.insert(pgid);
+            if groups_suspended() {
+                signal_group(pgid, Signal::Stop);
+            }
+        }
+        Ok(Self { child, pgid })
+    }
+
+    /// The underlying child process (the group leader)
+    pub fn child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Process group ID, equal to the leader's PID
+    pub fn pgid(&self) -> u32 {
+        self.pgid
+    }
+
+    /// Terminates every process in the group and reaps the leader
+    ///
+    /// Sends SIGTERM, waits up to [`TERMINATE_GRACE`] for the group to
+    /// exit, then sends SIGKILL.
+    pub fn terminate(&mut self) {
+        signal_group(self.pgid, Signal::Term);
+        // A stopped group only acts on SIGTERM once it runs again
+        signal_group(self.pgid, Signal::Cont);
+
+        let deadline = Instant::now() + TERMINATE_GRACE;
+        while Instant::now() < deadline {
+            // Reap the leader so it does not keep the group alive as a zombie
+            let _ = self.child.try_wait();
+            if !group_alive(self.pgid) {
+                break;
+            }
+            thread::sleep(EXIT_POLL_INTERVAL);
+        }
+
+        signal_group(self.pgid, Signal::Kill);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for EncoderProcess {
+    fn drop(&mut self) {
+        // The leader may have exited normally while children linger
+        if matches!(self.child.try_wait(), Ok(None)) || group_alive(self.pgid) {
+            self.terminate();
+        }
+        if let Ok(mut groups) = ACTIVE_GROUPS.lock() {
+            groups.remove(&self.pgid);
+        }
+        #[cfg(windows)]
+        windows_job::release(self.pgid);
+    }
+}
+
+/// Number of encoder process groups currently running
+pub fn active_group_count() -> usize {
+    ACTIVE_GROUPS.lock().map(|g| g.len()).unwrap_or(0)
+}
+
+/// Terminates every running encoder process group
+///
+/// Called on daemon shutdown. Groups get SIGTERM, then SIGKILL if any are
+/// still around after [`TERMINATE_GRACE`].
+///
+/// # Returns
+/// The number of groups signalled
+pub fn terminate_all_groups() -> usize {
+    let groups: Vec<u32> = match ACTIVE_GROUPS.lock() {
+        Ok(groups) => groups.iter().copied().collect(),
+        Err(_) => return 0,
+    };
+
+    for &pgid in &groups {
+        signal_group(pgid, Signal::Term);
+        signal_group(pgid, Signal::Cont);
+    }
+
+    let deadline = Instant::now() + TERMINATE_GRACE;
+    while Instant::now() < deadline && groups.iter().any(|&pgid| group_alive(pgid)) {
+        thread::sleep(EXIT_POLL_INTERVAL);
+    }
+
+    for &pgid in &groups {
+        signal_group(pgid, Signal::Kill);
+    }
+
+    groups.len()
+}
+
+/// Returns true if groups can be suspended on this platform
+pub fn can_suspend_groups() -> bool {
+    cfg!(unix)
+}
+
+/// Returns true while encoder groups are suspended, for any reason
+pub fn groups_suspended() -> bool {
+    SUSPENDED.load(Ordering::Relaxed) != 0
+}
+
+/// Suspends every running encoder process group with SIGSTOP
+///
+/// Groups spawned afterwards start suspended too, until
+/// [`resume_all_groups`] is called for `reason` and every other reason.
+/// Does nothing where [`can_suspend_groups`] is false.
+///
+/// # Returns
+/// The number of groups suspended
+pub fn suspend_all_groups(reason: SuspendReason) -> usize {
+    if !can_suspend_groups() {
+        return 0;
+    }
+    let Ok(groups) = ACTIVE_GROUPS.lock() else {
+        return 0;
+    };
+    SUSPENDED.fetch_or(reason.bit(), Ordering::Relaxed);
+    for &pgid in groups.iter() {
+        signal_group(pgid, Signal::Stop);
+    }
+    groups.len()
+}
+
+/// Drops `reason` for suspending, and resumes the groups with SIGCONT if
+/// no other reason is left
+///
+/// # Returns
+/// The number of groups resumed
+pub fn resume_all_groups(reason: SuspendReason) -> usize {
+    let Ok(groups) = ACTIVE_GROUPS.lock() else {
+        return 0;
+    };
+    let before = SUSPENDED.fetch_and(!reason.bit(), Ordering::Relaxed);
+    if before != reason.bit() {
+        // Either `reason` was not suspending them, or another one still is
+        return 0;
+    }
+    for &pgid in groups.iter() {
+        signal_group(pgid, Signal::Cont);
+    }
+    groups.len()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Signal {
+    Term,
+    Kill,
+    Stop,
+    Cont,
+}
+
+#[cfg(unix)]
+fn signal_group(pgid: u32, signal: Signal) {
+    let signal = match signal {
+        Signal::Term => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+        Signal::Stop => libc::SIGSTOP,
+        Signal::Cont => libc::SIGCONT,
+    };
+    // SAFETY: killpg only sends a signal; an invalid or exited group yields ESRCH
+    unsafe {
+        libc::killpg(pgid as libc::pid_t, signal);
+    }
+}
+
+#[cfg(windows)]
+fn signal_group(pgid: u32, signal: Signal) {
+    match signal {
+        // Console programs shut down cleanly on Ctrl+Break; this does nothing
+        // when the daemon has no console, as under a service wrapper
+        Signal::Term => {
+            use windows_sys::Win32::System::Console::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+            // SAFETY: only sends a console event; an unknown group is an error
+            unsafe {
+                GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pgid);
+            }
+        }
+        Signal::Kill => windows_job::terminate(pgid),
+        // Suspending is Unix only; see can_suspend_groups
+        Signal::Stop | Signal::Cont => {}
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn signal_group(_pgid: u32, _signal: Signal) {}
+
+#[cfg(unix)]
+fn group_alive(pgid: u32) -> bool {
+    // SAFETY: signal 0 performs the permission and existence check only
+    unsafe { libc::killpg(pgid as libc::pid_t, 0) == 0 }
+}
+
+#[cfg(windows)]
+fn group_alive(pgid: u32) -> bool {
+    windows_job::active(pgid)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn group_alive(_pgid: u32) -> bool {
+    false
+}
+
+/// Job objects standing in for process groups on Windows
+#[cfg(windows)]
+mod windows_job {
+    use std::collections::BTreeMap;
+    use std::ffi::c_void;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use std::sync::Mutex;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectBasicAccountingInformation,
+        JobObjectExtendedLimitInformation, QueryInformationJobObject, SetInformationJobObject,
+        TerminateJobObject, JOBOBJECT_BASIC_ACCOUNTING_INFORMATION,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    /// Job handle of each encoder by the leader's PID, kept as an integer so
+    /// the map can live in a static
+    static JOBS: Mutex<BTreeMap<u32, usize>> = Mutex::new(BTreeMap::new());
+
+    /// Puts `child` in a new job that is killed when its handle is closed
+    ///
+    /// Processes the child starts before it joins escape the job; Av1an
+    /// spends far longer than that reading its input before it starts any.
+    pub fn attach(pid: u32, child: &Child) {
+        // SAFETY: the job handle is checked before use and closed on failure;
+        // the limit struct is plain data for which all zeroes is valid
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return;
+            }
+            let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            let attached = SetInformationJobObject(
+                job,
+                JobObjectExtendedLimitInformation,
+                &limits as *const _ as *const c_void,
+                std::mem::size_of_val(&limits) as u32,
+            ) != 0
+                && AssignProcessToJobObject(job, child.as_raw_handle() as HANDLE) != 0;
+            if !attached {
+                CloseHandle(job);
+                return;
+            }
+            if let Ok(mut jobs) = JOBS.lock() {
+                jobs.insert(pid, job as usize);
+            }
+        }
+    }
+
+    fn with_job<T>(pid: u32, f: impl FnOnce(HANDLE) -> T) -> Option<T> {
+        let jobs = JOBS.lock().ok()?;
+        jobs.get(&pid).map(|&job| f(job as HANDLE))
+    }
+
+    /// Kills every process in the job
+    pub fn terminate(pid: u32) {
+        // SAFETY: the handle stays open while it is in the map
+        with_job(pid, |job| unsafe { TerminateJobObject(job, 1) });
+    }
+
+    /// Returns true while any process in the job is running
+    pub fn active(pid: u32) -> bool {
+        with_job(pid, |job| {
+            // SAFETY: the handle stays open while it is in the map, and the
+            // buffer matches the information class asked for
+            unsafe {
+                let mut info: JOBOBJECT_BASIC_ACCOUNTING_INFORMATION = std::mem::zeroed();
+                QueryInformationJobObject(
+                    job,
+                    JobObjectBasicAccountingInformation,
+                    &mut info as *mut _ as *mut c_void,
+                    std::mem::size_of_val(&info) as u32,
+                    std::ptr::null_mut(),
+                ) != 0
+                    && info.ActiveProcesses > 0
+            }
+        })
+        .unwrap_or(false)
+    }
+
+    /// Closes the job handle, killing anything still in the job
+    pub fn release(pid: u32) {
+        let job = JOBS.lock().ok().and_then(|mut jobs| jobs.remove(&pid));
+        if let Some(job) = job {
+            // SAFETY: the handle was removed from the map, so nothing else uses it
+            unsafe {
+                CloseHandle(job as HANDLE);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+
+    /// Spawns a shell whose background child sleeps in the same group
+    fn spawn_with_descendant() -> (EncoderProcess, u32) {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("sleep 30 & echo $!; wait")
+            .stdout(Stdio::piped());
+        let mut process = EncoderProcess::spawn(&mut cmd).unwrap();
+
+        let mut line = String::new();
+        let stdout = process.child_mut().stdout.take().unwrap();
+        io::BufRead::read_line(&mut io::BufReader::new(stdout), &mut line).unwrap();
+        (process, line.trim().parse().unwrap())
+    }
+
+    fn process_alive(pid: u32) -> bool {
+        // A zombie has already exited and only awaits reaping
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => !stat.contains(") Z "),
+            Err(_) => false,
+        }
+    }
+
+    #[test]
+    fn test_terminate_kills_descendants() {
+        let (mut process, descendant) = spawn_with_descendant();
+        assert!(process_alive(descendant));
+
+        process.terminate();
+        // The descendant was reparented; give init a moment to reap it
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while process_alive(descendant) && Instant::now() < deadline {
+            thread::sleep(EXIT_POLL_INTERVAL);
+        }
+        assert!(!process_alive(descendant));
+    }
+
+    #[test]
+    fn test_drop_kills_descendants() {
+        let (process, descendant) = spawn_with_descendant();
+        let pgid = process.pgid();
+        drop(process);
+
+        assert!(!ACTIVE_GROUPS.lock().unwrap().contains(&pgid));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while process_alive(descendant) && Instant::now() < deadline {
+            thread::sleep(EXIT_POLL_INTERVAL);
+        }
+        assert!(!process_alive(descendant));
+    }
+}
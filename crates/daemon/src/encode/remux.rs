@@ -0,0 +1,119 @@
+//! Remux module for AV1 Super Daemon
+//!
+//! Files that are already AV1 but sit in MP4, MOV, or transport stream
+//! containers don't need re-encoding, only a new container. This module
+//! copies every stream into Matroska with ffmpeg, which takes seconds
+//! rather than hours and leaves the video bit-for-bit untouched.
+
+use super::av1an::EncodeError;
+use super::process_group::EncoderProcess;
+use super::run_as::RunAs;
+use crate::tools::Tool;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Container extensions worth remuxing to MKV when the video is already AV1
+pub const REMUX_SOURCE_EXTENSIONS: &[&str] = &["mp4", "m4v", "mov", "ts", "m2ts"];
+
+/// Returns true if `path` has a container extension that should be remuxed
+pub fn is_remux_container(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            REMUX_SOURCE_EXTENSIONS
+                .iter()
+                .any(|candidate| ext.eq_ignore_ascii_case(candidate))
+        })
+        .unwrap_or(false)
+}
+
+/// Build an ffmpeg command that copies all streams from `input` into MKV
+///
+/// Every stream is mapped, including attachments and data tracks, and the
+/// output format is forced to Matroska regardless of the output extension.
+pub fn build_remux_command(input: &Path, output: &Path) -> Command {
+    let mut cmd = Tool::Ffmpeg.command();
+    cmd.arg("-hide_banner")
+        .arg("-nostdin")
+        .arg("-y")
+        .arg("-i")
+        .arg(input)
+        .arg("-map")
+        .arg("0")
+        .arg("-c")
+        .arg("copy")
+        .arg("-f")
+        .arg("matroska")
+        .arg(output);
+    cmd
+}
+
+/// Remux `input` into an MKV at `output` without re-encoding
+///
+/// ffmpeg runs in its own process group like the encoder, so daemon shutdown
+/// stops it as well.
+///
+/// # Errors
+/// Returns [`EncodeError::RemuxFailed`] if ffmpeg exits with a non-zero
+/// status, or an IO error if it could not be started.
+pub fn run_remux(input: &Path, output: &Path) -> Result<(), EncodeError> {
+    run_remux_as(input, output, None)
+}
+
+/// Remux `input` into an MKV at `output`, with ffmpeg running as `run_as`
+///
+/// # Errors
+/// Same as [`run_remux`].
+pub fn run_remux_as(
+    input: &Path,
+    output: &Path,
+    run_as: Option<RunAs>,
+) -> Result<(), EncodeError> {
+    let mut cmd = build_remux_command(input, output);
+    cmd.stdin(Stdio::null());
+    if let Some(run_as) = run_as {
+        run_as.apply(&mut cmd);
+    }
+    let mut process = EncoderProcess::spawn(&mut cmd)?;
+    let status = process.child_mut().wait()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(EncodeError::RemuxFailed(match status.code() {
+            Some(code) => format!("exit code {}", code),
+            None => "terminated by signal".to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_remux_container() {
+        assert!(is_remux_container(Path::new("/media/show/ep01.mp4")));
+        assert!(is_remux_container(Path::new("/media/show/ep01.M2TS")));
+        assert!(is_remux_container(Path::new("/media/clip.mov")));
+        assert!(!is_remux_container(Path::new("/media/movie.mkv")));
+        assert!(!is_remux_container(Path::new("/media/movie.webm")));
+        assert!(!is_remux_container(Path::new("/media/movie")));
+    }
+
+    #[test]
+    fn test_build_remux_command_copies_all_streams() {
+        let cmd = build_remux_command(Path::new("/in/a.mp4"), Path::new("/tmp/out.mkv"));
+        assert_eq!(cmd.get_program(), "ffmpeg");
+
+        let args: Vec<String> = cmd
+            .get_args()
+            .filter_map(|arg| arg.to_str().map(String::from))
+            .collect();
+        assert!(args.windows(2).any(|w| w == ["-i", "/in/a.mp4"]));
+        assert!(args.windows(2).any(|w| w == ["-map", "0"]));
+        assert!(args.windows(2).any(|w| w == ["-c", "copy"]));
+        assert!(args.windows(2).any(|w| w == ["-f", "matroska"]));
+        assert_eq!(args.last().map(String::as_str), Some("/tmp/out.mkv"));
+    }
+}
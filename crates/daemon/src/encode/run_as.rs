@@ -0,0 +1,145 @@
+//! Privilege drop for encoder processes
+//!
+//! Under systemd the daemon often runs as root so it can read and replace
+//! files owned by several users. Encodes do not need that: Av1an and ffmpeg
+//! are started as the configured user and group instead, and whatever the
+//! daemon writes on their behalf is handed over to the same identity, so a
+//! replaced file ends up owned by the media user rather than root.
+
+use crate::config::RunAsConfig;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Where the primary group of a user is looked up
+const PASSWD_PATH: &str = "/etc/passwd";
+
+/// User and group that encodes run as and their output belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunAs {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl RunAs {
+    /// Identity configured in `[run_as]`, or `None` when no uid is set
+    ///
+    /// Without a gid the user's primary group from `/etc/passwd` is used,
+    /// falling back to a group with the same number as the uid.
+    pub fn from_config(config: &RunAsConfig) -> Option<Self> {
+        let uid = config.uid?;
+        let gid = config.gid.unwrap_or_else(|| {
+            fs::read_to_string(PASSWD_PATH)
+                .ok()
+                .and_then(|passwd| primary_gid(&passwd, uid))
+                .unwrap_or(uid)
+        });
+        Some(Self { uid, gid })
+    }
+
+    /// Like [`RunAs::from_config`], but only while the daemon runs as root
+    ///
+    /// Only root may switch to another user, so anything else keeps its own
+    /// identity and gets a warning instead of failing every encode.
+    pub fn for_daemon(config: &RunAsConfig) -> Option<Self> {
+        let run_as = Self::from_config(config)?;
+        if !is_root() {
+            log_warn!(
+                "Warning: run_as.uid is set but the daemon is not running as root; \
+                 encodes keep the daemon's own user"
+            );
+            return None;
+        }
+        Some(run_as)
+    }
+
+    /// Makes `cmd` switch to this user and group when it is spawned
+    ///
+    /// Supplementary groups are dropped along with root's.
+    pub fn apply(&self, cmd: &mut Command) {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.uid(self.uid).gid(self.gid);
+        }
+        #[cfg(not(unix))]
+        let _ = cmd;
+    }
+
+    /// Hands `path` over to this user and group
+    pub fn chown(&self, path: &Path) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::chown(path, Some(self.uid), Some(self.gid))
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = path;
+            Ok(())
+        }
+    }
+}
+
+/// Returns true if the daemon runs with root's effective user ID
+fn is_root() -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: geteuid has no preconditions and cannot fail
+        unsafe { libc::geteuid() == 0 }
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Looks up the primary group of `uid` in the contents of `/etc/passwd`
+pub fn primary_gid(passwd: &str, uid: u32) -> Option<u32> {
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':').skip(2);
+        let line_uid: u32 = fields.next()?.parse().ok()?;
+        let gid = fields.next()?.parse().ok()?;
+        (line_uid == uid).then_some(gid)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primary_gid_reads_passwd() {
+        let passwd = "root:x:0:0:root:/root:/bin/bash\n\
+                      # comment\n\
+                      media:x:1001:100:Media:/srv/media:/usr/sbin/nologin\n";
+        assert_eq!(primary_gid(passwd, 1001), Some(100));
+        assert_eq!(primary_gid(passwd, 0), Some(0));
+        assert_eq!(primary_gid(passwd, 4242), None);
+    }
+
+    #[test]
+    fn test_from_config_needs_uid() {
+        assert_eq!(RunAs::from_config(&RunAsConfig::default()), None);
+        let config = RunAsConfig {
+            uid: Some(1001),
+            gid: Some(2002),
+        };
+        assert_eq!(RunAs::from_config(&config), Some(RunAs { uid: 1001, gid: 2002 }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_sets_process_identity() {
+        // Switching to our own identity is allowed for any user
+        let run_as = RunAs {
+            uid: unsafe { libc::getuid() },
+            gid: unsafe { libc::getgid() },
+        };
+        let mut cmd = Command::new("id");
+        run_as.apply(&mut cmd);
+        let output = cmd.arg("-u").output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), run_as.uid.to_string());
+    }
+}
@@ -0,0 +1,176 @@
+//! Typed SVT-AV1 parameters passed to Av1an via `--video-params`
+//!
+//! Each encode profile is a [`SvtParams`] value rather than a parameter
+//! string, so overrides replace fields instead of editing text, and the
+//! values are range-checked before an encode starts.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use thiserror::Error;
+
+/// `--crf` values SVT-AV1 accepts
+pub const CRF_RANGE: RangeInclusive<u8> = 1..=63;
+
+/// `--preset` values SVT-AV1 accepts
+pub const PRESET_RANGE: RangeInclusive<u8> = 0..=13;
+
+/// A setting outside the range SVT-AV1 accepts
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{flag} must be {min}-{max}, got {value}")]
+pub struct SvtParamError {
+    pub flag: &'static str,
+    pub value: u32,
+    pub min: u32,
+    pub max: u32,
+}
+
+/// SVT-AV1 settings of an encode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SvtParams {
+    /// `--crf`, see [`CRF_RANGE`]
+    pub crf: u8,
+    /// `--preset`, see [`PRESET_RANGE`]
+    pub preset: u8,
+    /// `--film-grain` synthesis strength, 0-50
+    pub film_grain: u8,
+    /// `--film-grain-denoise`, left to SVT-AV1 when unset
+    pub film_grain_denoise: Option<bool>,
+    /// `--tune`: 0=VQ, 1=PSNR, 2=SSIM (no tune 3 in newer SVT-AV1); left
+    /// to SVT-AV1 when unset
+    pub tune: Option<u8>,
+    /// `--enable-qm`
+    pub enable_qm: bool,
+    /// `--qm-min`, 0-15
+    pub qm_min: u8,
+    /// `--qm-max`, 0-15 and at least `qm_min`
+    pub qm_max: u8,
+    /// `--keyint` in frames
+    pub keyint: u32,
+    /// `--lookahead` in frames, 0-120
+    pub lookahead: u32,
+}
+
+impl SvtParams {
+    /// Film-grain-tuned settings for live action content
+    pub const fn film() -> Self {
+        Self {
+            crf: 8,
+            preset: 3,
+            film_grain: 20,
+            film_grain_denoise: None,
+            tune: None,
+            enable_qm: true,
+            qm_min: 1,
+            qm_max: 15,
+            keyint: 240,
+            lookahead: 40,
+        }
+    }
+
+    /// Settings for animated content
+    ///
+    /// Flat shading and line art gain nothing from synthesized grain, so
+    /// grain synthesis is kept minimal without denoising, and VQ tuning
+    /// preserves edges.
+    pub const fn animation() -> Self {
+        Self {
+            crf: 10,
+            film_grain: 4,
+            film_grain_denoise: Some(false),
+            tune: Some(0),
+            qm_min: 0,
+            ..Self::film()
+        }
+    }
+
+    /// Checks every setting against the range SVT-AV1 accepts
+    pub fn validate(&self) -> Result<(), SvtParamError> {
+        let ranges = [
+            ("--crf", self.crf as u32, *CRF_RANGE.start() as u32, *CRF_RANGE.end() as u32),
+            (
+                "--preset",
+                self.preset as u32,
+                *PRESET_RANGE.start() as u32,
+                *PRESET_RANGE.end() as u32,
+            ),
+            ("--film-grain", self.film_grain as u32, 0, 50),
+            ("--tune", self.tune.unwrap_or(0) as u32, 0, 2),
+            ("--qm-min", self.qm_min as u32, 0, 15),
+            ("--qm-max", self.qm_max as u32, self.qm_min as u32, 15),
+            ("--lookahead", self.lookahead, 0, 120),
+        ];
+        for (flag, value, min, max) in ranges {
+            if !(min..=max).contains(&value) {
+                return Err(SvtParamError { flag, value, min, max });
+            }
+        }
+        Ok(())
+    }
+
+    /// The settings as SVT-AV1 arguments
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![
+            ("--crf", self.crf.to_string()),
+            ("--preset", self.preset.to_string()),
+            ("--film-grain", self.film_grain.to_string()),
+        ];
+        if let Some(denoise) = self.film_grain_denoise {
+            args.push(("--film-grain-denoise", u8::from(denoise).to_string()));
+        }
+        if let Some(tune) = self.tune {
+            args.push(("--tune", tune.to_string()));
+        }
+        args.extend([
+            ("--enable-qm", u8::from(self.enable_qm).to_string()),
+            ("--qm-min", self.qm_min.to_string()),
+            ("--qm-max", self.qm_max.to_string()),
+            ("--keyint", self.keyint.to_string()),
+            ("--lookahead", self.lookahead.to_string()),
+        ]);
+        args.into_iter()
+            .flat_map(|(flag, value)| [flag.to_string(), value])
+            .collect()
+    }
+}
+
+impl fmt::Display for SvtParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_args().join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_render_in_flag_order() {
+        assert_eq!(
+            SvtParams::film().to_string(),
+            "--crf 8 --preset 3 --film-grain 20 --enable-qm 1 --qm-min 1 --qm-max 15 --keyint 240 --lookahead 40"
+        );
+        assert_eq!(
+            SvtParams::animation().to_string(),
+            "--crf 10 --preset 3 --film-grain 4 --film-grain-denoise 0 --tune 0 --enable-qm 1 --qm-min 0 --qm-max 15 --keyint 240 --lookahead 40"
+        );
+    }
+
+    #[test]
+    fn test_validate_ranges() {
+        assert!(SvtParams::film().validate().is_ok());
+        assert!(SvtParams::animation().validate().is_ok());
+
+        let params = SvtParams { crf: 64, ..SvtParams::film() };
+        let err = params.validate().unwrap_err();
+        assert_eq!(err.to_string(), "--crf must be 1-63, got 64");
+        let params = SvtParams { crf: 0, ..SvtParams::film() };
+        assert_eq!(params.validate().unwrap_err().flag, "--crf");
+
+        let params = SvtParams { preset: 14, ..SvtParams::film() };
+        assert_eq!(params.validate().unwrap_err().flag, "--preset");
+
+        let params = SvtParams { qm_min: 10, qm_max: 8, ..SvtParams::film() };
+        assert_eq!(params.validate().unwrap_err().to_string(), "--qm-max must be 10-15, got 8");
+    }
+}
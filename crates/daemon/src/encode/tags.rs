@@ -0,0 +1,238 @@
+//! Matroska tags describing how a file was processed
+//!
+//! Job records live in the state directory and can be lost or pruned. With
+//! `output.metadata_tags`, the daemon also writes global tags into each MKV
+//! it produces: the encoder settings, the original codec and size, the
+//! daemon that did it and when. Tools like `mediainfo` then show how a file
+//! came to be without any record of the job.
+//!
+//! The tags are written with `mkvpropedit`, which edits the file in place
+//! without rewriting the streams.
+
+use super::av1an::EncodeError;
+use crate::tools::Tool;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Name the daemon signs its tags with
+pub const ENCODED_BY: &str = concat!("av1-super-daemon ", env!("CARGO_PKG_VERSION"));
+
+/// What went into a processed file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessingTags {
+    /// SVT-AV1 parameters, or `None` for a remux that kept the video
+    pub encoder_settings: Option<String>,
+    /// Settings fingerprint, see [`crate::encode_settings`]
+    pub settings_fingerprint: Option<String>,
+    /// Video codec of the original, e.g. "hevc"
+    pub original_codec: Option<String>,
+    /// Size of the original in bytes
+    pub original_size: u64,
+    /// When processing finished, in Unix seconds
+    pub processed_at: u64,
+}
+
+impl ProcessingTags {
+    /// The tags as Matroska simple tag name/value pairs
+    ///
+    /// `ENCODER_SETTINGS`, `ENCODED_BY` and `DATE_ENCODED` are official
+    /// Matroska tag names; the others are the daemon's own.
+    pub fn pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(settings) = &self.encoder_settings {
+            pairs.push(("ENCODER_SETTINGS", settings.clone()));
+        }
+        if let Some(fingerprint) = &self.settings_fingerprint {
+            pairs.push(("AV1_SETTINGS_FINGERPRINT", fingerprint.clone()));
+        }
+        if let Some(codec) = &self.original_codec {
+            pairs.push(("ORIGINAL_CODEC", codec.clone()));
+        }
+        pairs.push(("ORIGINAL_SIZE", self.original_size.to_string()));
+        pairs.push(("ENCODED_BY", ENCODED_BY.to_string()));
+        pairs.push(("DATE_ENCODED", utc_timestamp(self.processed_at)));
+        pairs
+    }
+
+    /// The tags as a Matroska tags XML document applying to the whole file
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Tags>\n  <Tag>\n    <Targets />\n",
+        );
+        for (name, value) in self.pairs() {
+            let _ = writeln!(
+                xml,
+                "    <Simple>\n      <Name>{}</Name>\n      <String>{}</String>\n    </Simple>",
+                name,
+                escape_xml(&value)
+            );
+        }
+        xml.push_str("  </Tag>\n</Tags>\n");
+        xml
+    }
+}
+
+/// Returns true if `path` is a container `mkvpropedit` can tag
+pub fn is_taggable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mkv"))
+}
+
+/// Build the `mkvpropedit` command that sets the global tags of `path` from
+/// the XML file `tags_xml`
+pub fn build_tag_command(path: &Path, tags_xml: &Path) -> Command {
+    let mut global = std::ffi::OsString::from("global:");
+    global.push(tags_xml);
+    let mut cmd = Tool::Mkvpropedit.command();
+    cmd.arg(path).arg("--tags").arg(global);
+    cmd
+}
+
+/// Write `tags` into the MKV at `path`, replacing its global tags
+///
+/// # Errors
+/// Returns [`EncodeError::TaggingFailed`] if `mkvpropedit` exits with a
+/// non-zero status, or an IO error if it could not be started.
+pub fn write_mkv_tags(path: &Path, tags: &ProcessingTags) -> Result<(), EncodeError> {
+    let xml_path = tags_xml_path(path);
+    std::fs::write(&xml_path, tags.to_xml())?;
+    let output = build_tag_command(path, &xml_path)
+        .stdin(Stdio::null())
+        .output();
+    let _ = std::fs::remove_file(&xml_path);
+
+    let output = output?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let detail = stdout.lines().rev().find(|line| !line.trim().is_empty());
+        Err(EncodeError::TaggingFailed(match (output.status.code(), detail) {
+            (Some(code), Some(line)) => format!("exit code {}: {}", code, line.trim()),
+            (Some(code), None) => format!("exit code {}", code),
+            (None, _) => "terminated by signal".to_string(),
+        }))
+    }
+}
+
+fn tags_xml_path(path: &Path) -> PathBuf {
+    let mut xml_path = path.as_os_str().to_owned();
+    xml_path.push(".tags.xml");
+    PathBuf::from(xml_path)
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `YYYY-MM-DD HH:MM:SS` in UTC, the form Matroska date tags use
+fn utc_timestamp(unix_secs: u64) -> String {
+    let days = (unix_secs / 86_400) as i64;
+    let secs = unix_secs % 86_400;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_tags() -> ProcessingTags {
+        ProcessingTags {
+            encoder_settings: Some("--crf 8 --preset 3".to_string()),
+            settings_fingerprint: Some("0123456789abcdef".to_string()),
+            original_codec: Some("hevc".to_string()),
+            original_size: 4_000_000_000,
+            processed_at: 1_760_572_800,
+        }
+    }
+
+    #[test]
+    fn test_utc_timestamp() {
+        assert_eq!(utc_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(utc_timestamp(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(utc_timestamp(1_760_621_445), "2025-10-16 13:30:45");
+    }
+
+    #[test]
+    fn test_pairs_skip_unknown_values() {
+        let tags = sample_tags();
+        let names: Vec<&str> = tags.pairs().iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            [
+                "ENCODER_SETTINGS",
+                "AV1_SETTINGS_FINGERPRINT",
+                "ORIGINAL_CODEC",
+                "ORIGINAL_SIZE",
+                "ENCODED_BY",
+                "DATE_ENCODED"
+            ]
+        );
+
+        let remux = ProcessingTags {
+            encoder_settings: None,
+            settings_fingerprint: None,
+            ..tags
+        };
+        let names: Vec<&str> = remux.pairs().iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["ORIGINAL_CODEC", "ORIGINAL_SIZE", "ENCODED_BY", "DATE_ENCODED"]);
+    }
+
+    #[test]
+    fn test_to_xml_escapes_values() {
+        let tags = ProcessingTags {
+            original_codec: Some("a<b>&\"c\"".to_string()),
+            ..sample_tags()
+        };
+        let xml = tags.to_xml();
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<Targets />"));
+        assert!(xml.contains("<Name>ORIGINAL_CODEC</Name>\n      <String>a&lt;b&gt;&amp;&quot;c&quot;</String>"));
+        assert!(xml.contains("<String>4000000000</String>"));
+        assert!(xml.contains("<String>2025-10-16 00:00:00</String>"));
+    }
+
+    #[test]
+    fn test_build_tag_command() {
+        let cmd = build_tag_command(Path::new("/tmp/out.mkv"), Path::new("/tmp/out.mkv.tags.xml"));
+        assert_eq!(cmd.get_program(), "mkvpropedit");
+        let args: Vec<String> = cmd
+            .get_args()
+            .filter_map(|arg| arg.to_str().map(String::from))
+            .collect();
+        assert_eq!(args, ["/tmp/out.mkv", "--tags", "global:/tmp/out.mkv.tags.xml"]);
+        assert!(is_taggable(Path::new("/tmp/out.MKV")));
+        assert!(!is_taggable(Path::new("/tmp/out.mp4")));
+    }
+}
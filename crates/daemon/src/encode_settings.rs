@@ -0,0 +1,144 @@
+//! Settings fingerprint recorded next to encoded files.
+//!
+//! Files the daemon encoded are AV1, so later scans skip them like any
+//! other AV1 file. When the encode settings change in a way that matters
+//! (say, new grain handling), those files would keep the old settings
+//! forever. After each replacement the daemon writes an `.av1settings`
+//! sidecar holding a fingerprint of the SVT-AV1 parameters the file was
+//! encoded with; with `gates.reencode_on_settings_change`, an AV1 file
+//! whose fingerprint no longer matches the current settings is encoded
+//! again instead of skipped.
+//!
+//! Only parameters that change the picture count: the fingerprint covers
+//! the `--video-params` SVT-AV1 receives, not worker counts or audio
+//! handling. Files encoded with per-job overrides keep a sidecar for the
+//! record but are never re-encoded by the policy, since their settings
+//! were chosen on purpose.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::classify::SourceType;
+use crate::encode::{EncodeProfile, SvtOverrides, SvtParams};
+use crate::tool_versions::DAEMON_VERSION;
+
+/// Settings an encode ran with, as kept in the `.av1settings` sidecar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncodeSettings {
+    /// Fingerprint of `video_params`
+    pub fingerprint: String,
+    /// The SVT-AV1 parameters, as passed to Av1an
+    pub video_params: String,
+    /// Output pixel format
+    pub pix_format: String,
+    /// Classification that picked the encode profile
+    pub source_type: SourceType,
+    /// Whether per-job overrides changed the parameters
+    #[serde(default)]
+    pub custom: bool,
+    /// Version of the daemon that ran the encode
+    pub daemon_version: String,
+}
+
+impl EncodeSettings {
+    /// Settings of an encode using `params`.
+    pub fn new(params: &SvtParams, pix_format: &str, source_type: SourceType, custom: bool) -> Self {
+        Self {
+            fingerprint: settings_fingerprint(params),
+            video_params: params.to_string(),
+            pix_format: pix_format.to_string(),
+            source_type,
+            custom,
+            daemon_version: DAEMON_VERSION.to_string(),
+        }
+    }
+
+    /// Check if a file encoded with these settings would be encoded
+    /// differently now, given the configured `overrides`.
+    pub fn is_outdated(&self, overrides: &SvtOverrides) -> bool {
+        let current = overrides.apply(EncodeProfile::for_source(self.source_type).svt_params());
+        !self.custom && self.fingerprint != settings_fingerprint(&current)
+    }
+}
+
+/// Short hash of the SVT-AV1 parameters, stable across daemon versions as
+/// long as the parameters render the same.
+pub fn settings_fingerprint(params: &SvtParams) -> String {
+    let digest = Sha256::digest(params.to_string().as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Constructs the settings sidecar path for a given video file.
+///
+/// For example: `/media/movie.mkv` -> `/media/movie.mkv.av1settings`
+pub fn settings_sidecar_path(video_path: &Path) -> PathBuf {
+    let mut sidecar_path = video_path.as_os_str().to_owned();
+    sidecar_path.push(".av1settings");
+    PathBuf::from(sidecar_path)
+}
+
+/// Writes the `.av1settings` sidecar for an encoded file.
+pub fn write_settings_sidecar(video_path: &Path, settings: &EncodeSettings) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    std::fs::write(settings_sidecar_path(video_path), json)
+}
+
+/// Reads the `.av1settings` sidecar of a video file.
+///
+/// Returns `None` if there is none or it cannot be parsed, as for files
+/// this daemon did not encode.
+pub fn read_settings_sidecar(video_path: &Path) -> Option<EncodeSettings> {
+    let content = std::fs::read_to_string(settings_sidecar_path(video_path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_fingerprint_follows_material_settings() {
+        let film = SvtParams::film();
+        assert_eq!(settings_fingerprint(&film), settings_fingerprint(&SvtParams::film()));
+        assert_eq!(settings_fingerprint(&film).len(), 16);
+        assert_ne!(settings_fingerprint(&film), settings_fingerprint(&SvtParams::animation()));
+
+        let grain = SvtParams { film_grain: 12, ..film };
+        assert_ne!(settings_fingerprint(&film), settings_fingerprint(&grain));
+    }
+
+    #[test]
+    fn test_is_outdated() {
+        let settings = EncodeSettings::new(&SvtParams::film(), "yuv420p10le", SourceType::DiscLike, false);
+        assert!(!settings.is_outdated(&SvtOverrides::default()));
+
+        let new_grain = SvtOverrides {
+            film_grain: Some(10),
+            ..SvtOverrides::default()
+        };
+        assert!(settings.is_outdated(&new_grain));
+        // The profile comes from the recorded classification
+        let animation = EncodeSettings::new(&SvtParams::animation(), "yuv420p10le", SourceType::Animation, false);
+        assert!(!animation.is_outdated(&SvtOverrides::default()));
+
+        let custom = EncodeSettings { custom: true, ..settings };
+        assert!(!custom.is_outdated(&new_grain));
+    }
+
+    #[test]
+    fn test_sidecar_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let video = temp.path().join("movie.mkv");
+        assert_eq!(read_settings_sidecar(&video), None);
+
+        let settings = EncodeSettings::new(&SvtParams::film(), "yuv420p10le", SourceType::WebLike, false);
+        write_settings_sidecar(&video, &settings).unwrap();
+        assert!(temp.path().join("movie.mkv.av1settings").exists());
+        assert_eq!(read_settings_sidecar(&video), Some(settings));
+    }
+}
@@ -0,0 +1,237 @@
+//! CPU package energy sampling via RAPL.
+//!
+//! Intel CPUs, and AMD Zen CPUs on Linux 5.8+, expose cumulative package
+//! energy counters under `/sys/class/powercap/intel-rapl:N/energy_uj`. The
+//! metrics updater samples them every tick and splits each interval's energy
+//! evenly across the jobs encoding at the time, which is a fair estimate
+//! when concurrent encodes have similar settings. Energy used while nothing
+//! encodes is not attributed to any job.
+//!
+//! Reading `energy_uj` needs root on kernels 5.10+; without access, or on
+//! machines without RAPL, energy is simply reported as unavailable.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::metrics::MetricsSnapshot;
+
+/// Default location of the powercap sysfs tree.
+pub const POWERCAP_ROOT: &str = "/sys/class/powercap";
+
+/// Prefix of RAPL zone directories (used by both Intel and AMD drivers).
+const RAPL_ZONE_PREFIX: &str = "intel-rapl:";
+
+/// Stage name of jobs that are drawing encode power.
+const ENCODING_STAGE: &str = "encoding";
+
+/// Joules in one kilowatt-hour.
+pub const JOULES_PER_KWH: f64 = 3_600_000.0;
+
+/// Converts joules to kilowatt-hours.
+pub fn joules_to_kwh(joules: f64) -> f64 {
+    joules / JOULES_PER_KWH
+}
+
+/// One RAPL package zone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaplZone {
+    /// Path of the zone's `energy_uj` counter.
+    pub energy_path: PathBuf,
+    /// Value at which the counter wraps back to zero.
+    pub max_energy_range_uj: u64,
+}
+
+/// Finds the package-level RAPL zones under `root`.
+///
+/// Subzones (`intel-rapl:0:0` for cores, DRAM, ...) are skipped because
+/// their energy is already included in the package counter.
+pub fn discover_rapl_zones(root: &Path) -> Vec<RaplZone> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut zones: Vec<RaplZone> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix(RAPL_ZONE_PREFIX)
+                .is_some_and(|index| !index.is_empty() && !index.contains(':'))
+        })
+        .filter_map(|e| {
+            let dir = e.path();
+            let max_energy_range_uj = read_u64(&dir.join("max_energy_range_uj"))?;
+            Some(RaplZone {
+                energy_path: dir.join("energy_uj"),
+                max_energy_range_uj,
+            })
+        })
+        .collect();
+    zones.sort_by(|a, b| a.energy_path.cmp(&b.energy_path));
+    zones
+}
+
+/// Microjoules consumed between two counter readings, allowing for one wrap.
+pub fn counter_delta_uj(previous: u64, current: u64, max_range_uj: u64) -> u64 {
+    if current >= previous {
+        current - previous
+    } else {
+        max_range_uj.saturating_sub(previous) + current
+    }
+}
+
+/// Stateful reader returning the energy used since the previous sample.
+#[derive(Debug, Clone, Default)]
+pub struct EnergyMeter {
+    zones: Vec<RaplZone>,
+    last_uj: Vec<Option<u64>>,
+}
+
+impl EnergyMeter {
+    /// Creates a meter over the RAPL zones found under `root`.
+    pub fn new(root: &Path) -> Self {
+        Self::with_zones(discover_rapl_zones(root))
+    }
+
+    pub fn with_zones(zones: Vec<RaplZone>) -> Self {
+        let last_uj = vec![None; zones.len()];
+        Self { zones, last_uj }
+    }
+
+    /// True when at least one zone was found.
+    pub fn is_available(&self) -> bool {
+        !self.zones.is_empty()
+    }
+
+    /// Joules used by all packages since the previous call.
+    ///
+    /// Returns `None` when no counter could be read; the first successful
+    /// reading of a zone only establishes its baseline.
+    pub fn sample(&mut self) -> Option<f64> {
+        let mut total_uj = 0u64;
+        let mut any_read = false;
+        for (zone, last) in self.zones.iter().zip(self.last_uj.iter_mut()) {
+            let Some(current) = read_u64(&zone.energy_path) else {
+                continue;
+            };
+            any_read = true;
+            if let Some(previous) = last.replace(current) {
+                total_uj += counter_delta_uj(previous, current, zone.max_energy_range_uj);
+            }
+        }
+        any_read.then(|| total_uj as f64 / 1_000_000.0)
+    }
+}
+
+/// Splits `joules` evenly across the jobs currently encoding.
+///
+/// Energy measured while no job encodes is dropped.
+pub fn attribute_energy(snapshot: &mut MetricsSnapshot, joules: f64) {
+    let encoding = snapshot.jobs.iter().filter(|j| j.stage == ENCODING_STAGE).count();
+    if encoding == 0 {
+        return;
+    }
+    let share = joules / encoding as f64;
+    for job in snapshot.jobs.iter_mut().filter(|j| j.stage == ENCODING_STAGE) {
+        job.energy_joules += share;
+    }
+    snapshot.energy.encode_joules += joules;
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_zone(root: &Path, name: &str, energy_uj: u64, max_uj: u64) {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("energy_uj"), format!("{}\n", energy_uj)).unwrap();
+        fs::write(dir.join("max_energy_range_uj"), format!("{}\n", max_uj)).unwrap();
+    }
+
+    #[test]
+    fn test_discover_skips_subzones() {
+        let temp = TempDir::new().unwrap();
+        write_zone(temp.path(), "intel-rapl:0", 0, 100);
+        write_zone(temp.path(), "intel-rapl:0:0", 0, 100);
+        write_zone(temp.path(), "intel-rapl:1", 0, 100);
+        fs::create_dir_all(temp.path().join("intel-rapl")).unwrap();
+
+        let zones = discover_rapl_zones(temp.path());
+        assert_eq!(zones.len(), 2);
+        assert!(zones[0].energy_path.ends_with("intel-rapl:0/energy_uj"));
+        assert!(discover_rapl_zones(&temp.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_counter_delta_wraps() {
+        assert_eq!(counter_delta_uj(100, 250, 1_000), 150);
+        assert_eq!(counter_delta_uj(900, 50, 1_000), 150);
+    }
+
+    #[test]
+    fn test_meter_sums_zones_after_baseline() {
+        let temp = TempDir::new().unwrap();
+        write_zone(temp.path(), "intel-rapl:0", 1_000_000, 10_000_000);
+        write_zone(temp.path(), "intel-rapl:1", 9_000_000, 10_000_000);
+        let mut meter = EnergyMeter::new(temp.path());
+        assert!(meter.is_available());
+        assert_eq!(meter.sample(), Some(0.0));
+
+        write_zone(temp.path(), "intel-rapl:0", 3_000_000, 10_000_000);
+        write_zone(temp.path(), "intel-rapl:1", 500_000, 10_000_000);
+        assert_eq!(meter.sample(), Some(3.5));
+
+        assert_eq!(EnergyMeter::new(&temp.path().join("missing")).sample(), None);
+    }
+
+    #[test]
+    fn test_attribute_energy_splits_across_encoding_jobs() {
+        let job = |id: &str, stage: &str| crate::metrics::JobMetrics {
+            id: id.to_string(),
+            input_path: String::new(),
+            stage: stage.to_string(),
+            progress: 0.0,
+            fps: 0.0,
+            bitrate_kbps: 0.0,
+            crf: 8,
+            encoder: "svt-av1".to_string(),
+            workers: 4,
+            est_remaining_secs: 0.0,
+            frames_encoded: 0,
+            total_frames: 0,
+            size_in_bytes_before: 0,
+            size_in_bytes_after: 0,
+            vmaf: None,
+            psnr: None,
+            ssim: None,
+            tags: vec![],
+            energy_joules: 0.0,
+            quality_flag: None,
+        };
+        let mut snapshot = MetricsSnapshot {
+            jobs: vec![job("a", "encoding"), job("b", "encoding"), job("c", "completed")],
+            ..Default::default()
+        };
+
+        attribute_energy(&mut snapshot, 30.0);
+        assert_eq!(snapshot.jobs[0].energy_joules, 15.0);
+        assert_eq!(snapshot.jobs[1].energy_joules, 15.0);
+        assert_eq!(snapshot.jobs[2].energy_joules, 0.0);
+        assert_eq!(snapshot.energy.encode_joules, 30.0);
+
+        // Idle energy is not counted
+        snapshot.jobs.truncate(0);
+        attribute_energy(&mut snapshot, 30.0);
+        assert_eq!(snapshot.energy.encode_joules, 30.0);
+    }
+
+    #[test]
+    fn test_joules_to_kwh() {
+        assert!((joules_to_kwh(7_200_000.0) - 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,206 @@
+//! Classification of encode failures.
+//!
+//! An exit code says little about why an encode failed: Av1an exits with 1
+//! whether the disk filled up or the source has a broken stream. The cause
+//! is read from the end of Av1an's output, where ffmpeg and the encoder
+//! report their errors, and from how the process ended. A process killed
+//! with SIGKILL was almost always killed by the kernel's or a cgroup's OOM
+//! killer, since the daemon itself stops encodes with SIGTERM first.
+//!
+//! The [`FailureKind`] is kept on the job record and counted in the
+//! metrics, so a disk-full failure can be told apart from a corrupt source.
+
+use std::fmt;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::encode::EncodeError;
+
+/// SIGKILL, the signal the OOM killer sends
+const SIGKILL: i32 = 9;
+
+/// Exit code of a shell whose child was killed with SIGKILL
+const SIGKILL_EXIT_CODE: i32 = 128 + SIGKILL;
+
+/// Output fragments naming each cause, matched case-insensitively and
+/// checked in order, so a full disk wins over the decode errors it causes
+const PATTERNS: &[(FailureKind, &[&str])] = &[
+    (
+        FailureKind::DiskFull,
+        &["no space left on device", "disk quota exceeded", "enospc", "disk full"],
+    ),
+    (
+        FailureKind::OutOfMemory,
+        &[
+            "out of memory",
+            "cannot allocate memory",
+            "memory allocation failed",
+            "memory allocation of",
+            "sigkill",
+        ],
+    ),
+    (
+        FailureKind::UnsupportedStream,
+        &[
+            "unsupported codec",
+            "not currently supported",
+            "could not find codec parameters",
+            "unknown codec",
+            "no decoder for",
+            "decoder not found",
+        ],
+    ),
+    (
+        FailureKind::DecoderError,
+        &[
+            "invalid data found when processing input",
+            "error while decoding",
+            "error splitting the input into nal units",
+            "invalid nal unit",
+            "missing reference picture",
+            "corrupt",
+            "truncated",
+        ],
+    ),
+];
+
+/// Broad cause of a failed encode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// The temp or output filesystem ran out of space
+    DiskFull,
+    /// The encoder was killed for using too much memory
+    OutOfMemory,
+    /// The source has a stream ffmpeg cannot decode or Av1an cannot handle
+    UnsupportedStream,
+    /// Decoding the source failed part way, usually a damaged file
+    DecoderError,
+    /// The encode ran past `max_encode_secs`
+    TimedOut,
+    /// The encode printed nothing for `stall_timeout_secs`
+    Stalled,
+    /// Anything not recognised
+    Other,
+}
+
+impl FailureKind {
+    /// Name used in the job record and metrics
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureKind::DiskFull => "disk_full",
+            FailureKind::OutOfMemory => "out_of_memory",
+            FailureKind::UnsupportedStream => "unsupported_stream",
+            FailureKind::DecoderError => "decoder_error",
+            FailureKind::TimedOut => "timed_out",
+            FailureKind::Stalled => "stalled",
+            FailureKind::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Classify the failed encode that returned `error` after printing
+/// `output`, the last lines of its output
+pub fn classify_failure(error: &EncodeError, output: &[String]) -> FailureKind {
+    match error {
+        EncodeError::TimedOut(_) => FailureKind::TimedOut,
+        EncodeError::Stalled(_) => FailureKind::Stalled,
+        EncodeError::Av1anTerminated(Some(SIGKILL)) | EncodeError::Av1anFailed(SIGKILL_EXIT_CODE) => {
+            FailureKind::OutOfMemory
+        }
+        EncodeError::Io(e) => classify_io_error(e),
+        EncodeError::RemuxFailed(detail) | EncodeError::TaggingFailed(detail) => {
+            classify_output(std::slice::from_ref(detail)).unwrap_or(FailureKind::Other)
+        }
+        _ => classify_output(output).unwrap_or(FailureKind::Other),
+    }
+}
+
+/// Cause named in `output`, if any
+pub fn classify_output(output: &[String]) -> Option<FailureKind> {
+    let lines: Vec<String> = output.iter().map(|line| line.to_lowercase()).collect();
+    PATTERNS.iter().find_map(|(kind, fragments)| {
+        let found = lines
+            .iter()
+            .any(|line| fragments.iter().any(|fragment| line.contains(fragment)));
+        found.then_some(*kind)
+    })
+}
+
+fn classify_io_error(e: &io::Error) -> FailureKind {
+    match e.kind() {
+        io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => FailureKind::DiskFull,
+        io::ErrorKind::OutOfMemory => FailureKind::OutOfMemory,
+        _ => FailureKind::Other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_output_names_the_cause() {
+        let disk = lines(
+            "[h264 @ 0x55d] error while decoding MB 12 4\n\
+             Error: failed to write chunk 0007.ivf: No space left on device (os error 28)",
+        );
+        assert_eq!(classify_failure(&EncodeError::Av1anFailed(1), &disk), FailureKind::DiskFull);
+
+        let corrupt = lines("[h264 @ 0x55d] Invalid NAL unit size (1234 > 512).\nError splitting the input into NAL units.");
+        assert_eq!(classify_failure(&EncodeError::Av1anFailed(1), &corrupt), FailureKind::DecoderError);
+
+        let unsupported = lines("[matroska @ 0x7f] Could not find codec parameters for stream 2 (Video: none)");
+        assert_eq!(
+            classify_failure(&EncodeError::Av1anFailed(1), &unsupported),
+            FailureKind::UnsupportedStream
+        );
+
+        let oom = lines("thread 'main' panicked: encoder crashed: signal: 9 (SIGKILL)");
+        assert_eq!(classify_failure(&EncodeError::Av1anFailed(101), &oom), FailureKind::OutOfMemory);
+
+        assert_eq!(
+            classify_failure(&EncodeError::Av1anFailed(1), &lines("Error: something else")),
+            FailureKind::Other
+        );
+    }
+
+    #[test]
+    fn test_how_the_process_ended_names_the_cause() {
+        let none = Vec::new();
+        assert_eq!(classify_failure(&EncodeError::Av1anTerminated(Some(9)), &none), FailureKind::OutOfMemory);
+        assert_eq!(classify_failure(&EncodeError::Av1anFailed(137), &none), FailureKind::OutOfMemory);
+        assert_eq!(classify_failure(&EncodeError::Av1anTerminated(Some(11)), &none), FailureKind::Other);
+        assert_eq!(
+            classify_failure(&EncodeError::TimedOut(Duration::from_secs(60)), &none),
+            FailureKind::TimedOut
+        );
+        assert_eq!(
+            classify_failure(&EncodeError::Stalled(Duration::from_secs(60)), &none),
+            FailureKind::Stalled
+        );
+        let full = io::Error::from(io::ErrorKind::StorageFull);
+        assert_eq!(classify_failure(&EncodeError::Io(full), &none), FailureKind::DiskFull);
+    }
+
+    #[test]
+    fn test_failure_kind_names() {
+        assert_eq!(
+            serde_json::to_string(&FailureKind::UnsupportedStream).unwrap(),
+            "\"unsupported_stream\""
+        );
+        assert_eq!(FailureKind::OutOfMemory.to_string(), "out_of_memory");
+    }
+}
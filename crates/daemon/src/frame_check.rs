@@ -0,0 +1,237 @@
+//! Black and corrupt frame detection on encoded output.
+//!
+//! A decode check passes as long as every frame decodes, but a chunk that
+//! Av1an encoded to solid black or to a smear of blocks decodes just fine.
+//! This module decodes the whole output once through ffmpeg's `blackdetect`
+//! and `blockdetect` filters. A black run in the output only counts as a
+//! problem when the same stretch of the source is not black as well, so
+//! fades and night scenes pass. Blockiness is compared against an absolute
+//! limit on the mean `blockdetect` score.
+
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::tools::Tool;
+
+/// Luma below which a pixel counts as black, as a fraction of the range.
+const BLACK_PIXEL_THRESHOLD: f64 = 0.10;
+
+/// Fraction of an output black run that must also be black in the source.
+const SOURCE_BLACK_COVERAGE: f64 = 0.5;
+
+/// A stretch of black frames reported by `blackdetect`, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlackInterval {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl BlackInterval {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// The `blackdetect` filter for runs of at least `min_black_secs`.
+fn blackdetect_filter(min_black_secs: f64) -> String {
+    format!("blackdetect=d={}:pix_th={:.2}", min_black_secs, BLACK_PIXEL_THRESHOLD)
+}
+
+/// Build an ffmpeg command that decodes all of `encode` through the enabled
+/// detection filters, printing their findings to stderr.
+pub fn build_output_scan_command(
+    encode: &Path,
+    min_black_secs: Option<f64>,
+    detect_blocks: bool,
+) -> Command {
+    let mut filters = Vec::new();
+    if let Some(secs) = min_black_secs {
+        filters.push(blackdetect_filter(secs));
+    }
+    if detect_blocks {
+        filters.push("blockdetect".to_string());
+    }
+
+    let mut cmd = Tool::Ffmpeg.command();
+    cmd.arg("-hide_banner")
+        .arg("-nostdin")
+        .arg("-i")
+        .arg(encode)
+        .arg("-map")
+        .arg("0:v:0")
+        .arg("-vf")
+        .arg(filters.join(","))
+        .arg("-f")
+        .arg("null")
+        .arg("-");
+    cmd
+}
+
+/// Build an ffmpeg command running `blackdetect` over `interval` of `original`.
+pub fn build_source_black_command(
+    original: &Path,
+    interval: BlackInterval,
+    min_black_secs: f64,
+) -> Command {
+    let mut cmd = Tool::Ffmpeg.command();
+    cmd.arg("-hide_banner")
+        .arg("-nostdin")
+        .arg("-ss")
+        .arg(format!("{:.3}", interval.start))
+        .arg("-t")
+        .arg(format!("{:.3}", interval.duration()))
+        .arg("-i")
+        .arg(original)
+        .arg("-map")
+        .arg("0:v:0")
+        .arg("-vf")
+        .arg(blackdetect_filter(min_black_secs))
+        .arg("-f")
+        .arg("null")
+        .arg("-");
+    cmd
+}
+
+/// Black runs from `blackdetect` lines such as
+/// `black_start:12.5 black_end:15 black_duration:2.5`.
+pub fn parse_black_intervals(stderr: &str) -> Vec<BlackInterval> {
+    let value = |line: &str, key: &str| -> Option<f64> {
+        let rest = &line[line.find(key)? + key.len()..];
+        rest.split_whitespace().next()?.parse().ok()
+    };
+    stderr
+        .lines()
+        .filter_map(|line| {
+            Some(BlackInterval {
+                start: value(line, "black_start:")?,
+                end: value(line, "black_end:")?,
+            })
+        })
+        .collect()
+}
+
+/// Mean blockiness from the `blockdetect` summary line `block mean: 3.21`.
+pub fn parse_block_mean(stderr: &str) -> Option<f32> {
+    let line = stderr.lines().find(|line| line.contains("block mean:"))?;
+    let rest = &line[line.find("block mean:")? + "block mean:".len()..];
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// Run `cmd` to completion and return its stderr.
+fn run_for_stderr(mut cmd: Command) -> io::Result<String> {
+    let output = cmd.stdin(Stdio::null()).output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    if output.status.success() {
+        Ok(stderr)
+    } else {
+        Err(io::Error::other(format!(
+            "ffmpeg failed: {}",
+            stderr.lines().last().unwrap_or("").trim()
+        )))
+    }
+}
+
+/// Decode `encode` looking for black runs the source does not have and for
+/// heavy blocking.
+///
+/// Black runs are checked when `min_black_secs` is set and blockiness when
+/// `max_block_mean` is.
+///
+/// # Returns
+/// A description of each problem found; empty if the output looks fine
+///
+/// # Errors
+/// Returns an error if ffmpeg cannot be run or fails to decode a file.
+pub fn find_frame_problems(
+    original: &Path,
+    encode: &Path,
+    min_black_secs: Option<f64>,
+    max_block_mean: Option<f32>,
+) -> io::Result<Vec<String>> {
+    let mut problems = Vec::new();
+    if min_black_secs.is_none() && max_block_mean.is_none() {
+        return Ok(problems);
+    }
+
+    let stderr = run_for_stderr(build_output_scan_command(
+        encode,
+        min_black_secs,
+        max_block_mean.is_some(),
+    ))?;
+
+    if let Some(min_secs) = min_black_secs {
+        for interval in parse_black_intervals(&stderr) {
+            let source = run_for_stderr(build_source_black_command(original, interval, min_secs))?;
+            let source_black: f64 = parse_black_intervals(&source)
+                .iter()
+                .map(BlackInterval::duration)
+                .sum();
+            if source_black < interval.duration() * SOURCE_BLACK_COVERAGE {
+                problems.push(format!(
+                    "output is black from {:.1}s to {:.1}s where the source is not",
+                    interval.start, interval.end
+                ));
+            }
+        }
+    }
+
+    if let Some(limit) = max_block_mean {
+        match parse_block_mean(&stderr) {
+            Some(mean) if mean > limit => {
+                problems.push(format!("blockiness {:.2} above {:.2}", mean, limit));
+            }
+            Some(_) => {}
+            None => log_warn!("Warning: ffmpeg printed no blockdetect summary for {:?}", encode),
+        }
+    }
+
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_detector_output() {
+        let stderr = "\
+[blackdetect @ 0x5601] black_start:0 black_end:1.5 black_duration:1.5
+frame= 2400 fps=310 q=-0.0 size=N/A time=00:01:40.00 bitrate=N/A speed=12.9x
+[blackdetect @ 0x5601] black_start:61.244 black_end:64.08 black_duration:2.836
+[Parsed_blockdetect_1 @ 0x5602] block mean: 3.21
+";
+        assert_eq!(
+            parse_black_intervals(stderr),
+            vec![
+                BlackInterval { start: 0.0, end: 1.5 },
+                BlackInterval { start: 61.244, end: 64.08 },
+            ]
+        );
+        assert_eq!(parse_block_mean(stderr), Some(3.21));
+        assert!(parse_black_intervals("no detections").is_empty());
+        assert_eq!(parse_block_mean("no detections"), None);
+    }
+
+    #[test]
+    fn test_output_scan_runs_only_enabled_filters() {
+        let args = |cmd: Command| -> Vec<String> {
+            cmd.get_args()
+                .filter_map(|arg| arg.to_str().map(String::from))
+                .collect()
+        };
+
+        let both = args(build_output_scan_command(Path::new("/tmp/out.mkv"), Some(2.0), true));
+        assert!(both
+            .windows(2)
+            .any(|w| w == ["-vf", "blackdetect=d=2:pix_th=0.10,blockdetect"]));
+
+        let blocks = args(build_output_scan_command(Path::new("/tmp/out.mkv"), None, true));
+        assert!(blocks.windows(2).any(|w| w == ["-vf", "blockdetect"]));
+
+        let window = BlackInterval { start: 61.25, end: 64.0 };
+        let source = args(build_source_black_command(Path::new("/media/a.mkv"), window, 2.0));
+        assert!(source.windows(2).any(|w| w == ["-ss", "61.250"]));
+        assert!(source.windows(2).any(|w| w == ["-t", "2.750"]));
+    }
+}
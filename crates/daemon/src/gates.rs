@@ -1,14 +1,20 @@
 //! Gates module for validating video files before encoding.
 //!
 //! This module provides functionality to probe video files using ffprobe
-//! and check various gates (no video streams, minimum size, already AV1)
-//! to determine if a file should proceed to encoding.
+//! and check various gates (no or several video streams, minimum size,
+//! already AV1, pixel format, low bitrate) to determine if a file should
+//! proceed to encoding.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::Path;
-use std::process::Command;
 use thiserror::Error;
 
+use crate::config::{MultiVideoAction, PixelFormatConfig};
+use crate::pixel_format::pixel_format_skip_reason;
+use crate::skip_marker::{SkipCode, SkipReason};
+use crate::tools::Tool;
+
 /// Error type for probe operations.
 #[derive(Debug, Error)]
 pub enum ProbeError {
@@ -26,7 +32,7 @@ pub enum ProbeError {
 }
 
 /// Information about a video stream from ffprobe.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct VideoStream {
     /// Codec name (e.g., "hevc", "h264", "av1").
     pub codec_name: String,
@@ -36,15 +42,132 @@ pub struct VideoStream {
     pub height: u32,
     /// Bitrate in kbps (if available).
     pub bitrate_kbps: Option<f32>,
+    /// Pixel format (e.g., "yuv420p10le", "yuv422p"), if ffprobe reported it.
+    #[serde(default)]
+    pub pix_fmt: Option<String>,
+    /// Stream duration in seconds, if ffprobe reported one.
+    #[serde(default)]
+    pub duration_secs: Option<f64>,
+    /// Whether the stream is an attached picture such as cover art.
+    #[serde(default)]
+    pub attached_pic: bool,
+    /// Average frames per second, if ffprobe reported a usable rate.
+    #[serde(default)]
+    pub frame_rate: Option<f64>,
+    /// Codec profile (e.g., "Main 10", "High").
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Codec level as ffprobe reports it (e.g., 150 for HEVC level 5).
+    #[serde(default)]
+    pub level: Option<i32>,
+    /// Transfer characteristics (e.g., "smpte2084" for PQ).
+    #[serde(default)]
+    pub color_transfer: Option<String>,
+    /// Color primaries (e.g., "bt2020").
+    #[serde(default)]
+    pub color_primaries: Option<String>,
+    /// Matrix coefficients (e.g., "bt2020nc").
+    #[serde(default)]
+    pub color_space: Option<String>,
+    /// HDR metadata carried as stream side data.
+    #[serde(default)]
+    pub hdr: HdrSideData,
+    /// Language tag, if the track has one.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// All stream tags, keys lowercased.
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl VideoStream {
+    /// Whether the stream is HDR: PQ or HLG transfer, or Dolby Vision.
+    pub fn is_hdr(&self) -> bool {
+        matches!(
+            self.color_transfer.as_deref(),
+            Some("smpte2084" | "arib-std-b67")
+        ) || self.hdr.dolby_vision_profile.is_some()
+    }
+}
+
+/// HDR metadata a video stream carries as side data.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct HdrSideData {
+    /// Mastering display color volume (SMPTE ST 2086).
+    #[serde(default)]
+    pub mastering_display: Option<MasteringDisplay>,
+    /// Content light level (CTA-861.3).
+    #[serde(default)]
+    pub content_light: Option<ContentLightLevel>,
+    /// Dolby Vision profile from the DOVI configuration record.
+    #[serde(default)]
+    pub dolby_vision_profile: Option<u8>,
+}
+
+/// Mastering display the content was graded on, as CIE 1931 xy
+/// chromaticities and luminance in cd/m².
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct MasteringDisplay {
+    pub red: (f64, f64),
+    pub green: (f64, f64),
+    pub blue: (f64, f64),
+    pub white_point: (f64, f64),
+    pub min_luminance: f64,
+    pub max_luminance: f64,
+}
+
+/// Brightest pixel (MaxCLL) and brightest frame average (MaxFALL) in cd/m².
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ContentLightLevel {
+    pub max_content: u32,
+    pub max_average: u32,
 }
 
 /// Information about an audio stream from ffprobe.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct AudioStream {
     /// Codec name (e.g., "aac", "truehd", "dts").
     pub codec_name: String,
     /// Number of audio channels.
     pub channels: u32,
+    /// Language tag (e.g., "eng", "jpn"), if the track has one.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Track title (e.g., "Director's Commentary"), if the track has one.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Codec profile (e.g., "DTS-HD MA", "LC").
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Channel layout (e.g., "5.1(side)").
+    #[serde(default)]
+    pub channel_layout: Option<String>,
+    /// Sample rate in Hz.
+    #[serde(default)]
+    pub sample_rate: Option<u32>,
+    /// Whether the track is flagged as the default track.
+    #[serde(default)]
+    pub default: bool,
+    /// All stream tags, keys lowercased.
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+/// Information about a subtitle stream from ffprobe.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SubtitleStream {
+    /// Codec name (e.g., "subrip", "ass", "hdmv_pgs_subtitle").
+    pub codec_name: String,
+    /// Index of the stream among all streams of the file.
+    #[serde(default)]
+    pub stream_index: Option<u32>,
+    /// Language tag (e.g., "eng"), if the track has one.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Whether the track is flagged as forced, i.e. only translates
+    /// foreign-language dialogue.
+    #[serde(default)]
+    pub forced: bool,
 }
 
 /// Format information from ffprobe.
@@ -64,10 +187,50 @@ pub struct ProbeResult {
     pub video_streams: Vec<VideoStream>,
     /// Audio streams found in the file.
     pub audio_streams: Vec<AudioStream>,
+    /// Subtitle streams found in the file.
+    #[serde(default)]
+    pub subtitle_streams: Vec<SubtitleStream>,
+    /// Number of embedded font attachments (typical of ASS-subbed releases).
+    #[serde(default)]
+    pub font_attachments: u32,
     /// Format information.
     pub format: FormatInfo,
 }
 
+impl ProbeResult {
+    /// Position among `video_streams` of the main video stream: the largest
+    /// picture that is not an attached picture, the longest among equals,
+    /// the first among those.
+    pub fn main_video_index(&self) -> Option<usize> {
+        let rank = |video: &VideoStream| {
+            (
+                video.width as u64 * video.height as u64,
+                video.duration_secs.unwrap_or(0.0),
+            )
+        };
+        let mut main: Option<usize> = None;
+        for (i, video) in self.video_streams.iter().enumerate() {
+            if video.attached_pic {
+                continue;
+            }
+            if main.is_none_or(|m| rank(video) > rank(&self.video_streams[m])) {
+                main = Some(i);
+            }
+        }
+        main
+    }
+
+    /// Positions among `video_streams` of attached pictures.
+    pub fn attached_picture_indices(&self) -> Vec<usize> {
+        self.video_streams
+            .iter()
+            .enumerate()
+            .filter(|(_, video)| video.attached_pic)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
 /// Configuration for gate checks.
 #[derive(Debug, Clone)]
 pub struct GatesConfig {
@@ -77,6 +240,13 @@ pub struct GatesConfig {
     pub max_size_ratio: f32,
     /// Whether to keep original file after replacement.
     pub keep_original: bool,
+    /// Minimum video kbps per megapixel by codec name; empty disables the
+    /// bitrate gate.
+    pub min_kbps_per_megapixel: BTreeMap<String, f32>,
+    /// Which bit depths and chroma subsamplings are encoded.
+    pub pixel_format: PixelFormatConfig,
+    /// What to do with files that have several video streams.
+    pub multi_video: MultiVideoAction,
 }
 
 impl Default for GatesConfig {
@@ -85,6 +255,9 @@ impl Default for GatesConfig {
             min_bytes: 1048576, // 1 MB
             max_size_ratio: 0.95,
             keep_original: false,
+            min_kbps_per_megapixel: BTreeMap::new(),
+            pixel_format: PixelFormatConfig::default(),
+            multi_video: MultiVideoAction::default(),
         }
     }
 }
@@ -95,54 +268,214 @@ pub enum GateResult {
     /// File passed all gates and can proceed to encoding.
     Pass(ProbeResult),
     /// File should be skipped with the given reason.
-    Skip { reason: String },
+    Skip { reason: SkipReason },
 }
 
 /// Raw ffprobe JSON structures for parsing.
+///
+/// ffprobe builds and muxers differ in how they write the same field: a
+/// number as a string or the other way round, a missing value as `"N/A"`.
+/// Every field is read leniently, so a value of an unexpected shape is
+/// dropped instead of failing the whole probe.
 mod ffprobe_json {
-    use serde::Deserialize;
+    use serde::{Deserialize, Deserializer};
+    use serde_json::{Map, Value};
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
 
     #[derive(Debug, Deserialize)]
     pub struct FfprobeOutput {
-        pub streams: Option<Vec<Stream>>,
+        #[serde(default, deserialize_with = "lenient_list")]
+        pub streams: Vec<Stream>,
+        #[serde(default, deserialize_with = "lenient_object")]
         pub format: Option<Format>,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
     pub struct Stream {
+        #[serde(deserialize_with = "lenient")]
+        pub index: Option<u32>,
+        #[serde(deserialize_with = "lenient_string")]
         pub codec_type: Option<String>,
+        #[serde(deserialize_with = "lenient_string")]
         pub codec_name: Option<String>,
+        #[serde(deserialize_with = "lenient_string")]
+        pub profile: Option<String>,
+        #[serde(deserialize_with = "lenient")]
+        pub level: Option<i32>,
+        #[serde(deserialize_with = "lenient")]
         pub width: Option<u32>,
+        #[serde(deserialize_with = "lenient")]
         pub height: Option<u32>,
-        pub bit_rate: Option<String>,
+        #[serde(deserialize_with = "lenient")]
+        pub bit_rate: Option<f64>,
+        #[serde(deserialize_with = "lenient_string")]
+        pub pix_fmt: Option<String>,
+        #[serde(deserialize_with = "lenient_string")]
+        pub avg_frame_rate: Option<String>,
+        #[serde(deserialize_with = "lenient_string")]
+        pub r_frame_rate: Option<String>,
+        #[serde(deserialize_with = "lenient_string")]
+        pub color_transfer: Option<String>,
+        #[serde(deserialize_with = "lenient_string")]
+        pub color_primaries: Option<String>,
+        #[serde(deserialize_with = "lenient_string")]
+        pub color_space: Option<String>,
+        #[serde(deserialize_with = "lenient")]
+        pub duration: Option<f64>,
+        #[serde(deserialize_with = "lenient")]
         pub channels: Option<u32>,
+        #[serde(deserialize_with = "lenient_string")]
+        pub channel_layout: Option<String>,
+        #[serde(deserialize_with = "lenient")]
+        pub sample_rate: Option<u32>,
+        #[serde(deserialize_with = "lenient_tags")]
+        pub tags: BTreeMap<String, String>,
+        #[serde(deserialize_with = "lenient_tags")]
+        pub disposition: BTreeMap<String, String>,
+        #[serde(deserialize_with = "lenient_list")]
+        pub side_data_list: Vec<Map<String, Value>>,
     }
 
-    #[derive(Debug, Deserialize)]
+    impl Stream {
+        /// Whether the disposition flag `name` is set.
+        pub fn disposition(&self, name: &str) -> bool {
+            self.disposition.get(name).is_some_and(|v| v == "1")
+        }
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
     pub struct Format {
-        pub duration: Option<String>,
-        pub size: Option<String>,
+        #[serde(deserialize_with = "lenient")]
+        pub duration: Option<f64>,
+        #[serde(deserialize_with = "lenient")]
+        pub size: Option<u64>,
+    }
+
+    /// Parses a number, or a `"num/den"` rational, as a float.
+    pub fn rational(value: &Value) -> Option<f64> {
+        match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => parse_rational(s),
+            _ => None,
+        }
+    }
+
+    /// Parses `"num/den"`, or a plain number, as a float.
+    pub fn parse_rational(s: &str) -> Option<f64> {
+        let parsed = match s.split_once('/') {
+            Some((num, den)) => {
+                let den: f64 = den.trim().parse().ok()?;
+                (den != 0.0).then_some(num.trim().parse::<f64>().ok()? / den)
+            }
+            None => s.trim().parse().ok(),
+        };
+        parsed.filter(|v| v.is_finite())
+    }
+
+    fn scalar_string(value: Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    /// A number or numeric string; anything else is `None`.
+    fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: FromStr,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Ok(scalar_string(value).and_then(|s| s.trim().parse().ok()))
+    }
+
+    /// A string, or a number written as one; anything else is `None`.
+    fn lenient_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(scalar_string(Value::deserialize(deserializer)?))
+    }
+
+    /// An object of scalars, keys lowercased; other values are dropped.
+    fn lenient_tags<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let Value::Object(map) = Value::deserialize(deserializer)? else {
+            return Ok(BTreeMap::new());
+        };
+        Ok(map
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_lowercase(), scalar_string(value)?)))
+            .collect())
+    }
+
+    /// A list whose entries that do not have the expected shape are dropped.
+    fn lenient_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: serde::de::DeserializeOwned,
+    {
+        let Value::Array(items) = Value::deserialize(deserializer)? else {
+            return Ok(Vec::new());
+        };
+        Ok(items
+            .into_iter()
+            .filter_map(|item| serde_json::from_value(item).ok())
+            .collect())
+    }
+
+    /// An object of the expected shape, or `None`.
+    fn lenient_object<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: serde::de::DeserializeOwned,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Ok(value.is_object().then(|| serde_json::from_value(value).ok()).flatten())
     }
 }
 
 
+/// Arguments passed to ffprobe ahead of the input path.
+const FFPROBE_ARGS: &[&str] = &[
+    "-v",
+    "quiet",
+    "-print_format",
+    "json",
+    "-show_streams",
+    "-show_format",
+];
+
 /// Probes a video file using ffprobe to collect stream and format metadata.
 ///
 /// Runs `ffprobe -v quiet -print_format json -show_streams -show_format <path>`
 /// and parses the JSON output.
 pub fn probe_file(path: &Path) -> Result<ProbeResult, ProbeError> {
-    let output = Command::new("ffprobe")
-        .args([
-            "-v",
-            "quiet",
-            "-print_format",
-            "json",
-            "-show_streams",
-            "-show_format",
-        ])
+    let output = Tool::Ffprobe.command().args(FFPROBE_ARGS).arg(path).output()?;
+    probe_result_from_output(output)
+}
+
+/// Same as [`probe_file`], without blocking the async runtime.
+///
+/// ffprobe is killed if the returned future is dropped before it exits.
+pub async fn probe_file_async(path: &Path) -> Result<ProbeResult, ProbeError> {
+    let output = Tool::Ffprobe.async_command()
+        .args(FFPROBE_ARGS)
         .arg(path)
-        .output()?;
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    probe_result_from_output(output)
+}
 
+fn probe_result_from_output(output: std::process::Output) -> Result<ProbeResult, ProbeError> {
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(ProbeError::FfprobeFailed(format!(
@@ -157,109 +490,275 @@ pub fn probe_file(path: &Path) -> Result<ProbeResult, ProbeError> {
 }
 
 /// Parses ffprobe JSON output into a ProbeResult.
+///
+/// Fields that are missing or have an unexpected shape are left unset, so
+/// output from older or newer ffprobe builds still parses.
 pub fn parse_ffprobe_output(json_str: &str) -> Result<ProbeResult, ProbeError> {
     let ffprobe: ffprobe_json::FfprobeOutput =
         serde_json::from_str(json_str).map_err(|e| ProbeError::ParseError(e.to_string()))?;
 
-    let streams = ffprobe.streams.unwrap_or_default();
     let format = ffprobe.format.ok_or_else(|| {
         ProbeError::ParseError("Missing format information in ffprobe output".to_string())
     })?;
 
     let mut video_streams = Vec::new();
     let mut audio_streams = Vec::new();
+    let mut subtitle_streams = Vec::new();
+    let mut font_attachments = 0;
 
-    for stream in streams {
+    for stream in ffprobe.streams {
         let codec_type = stream.codec_type.as_deref().unwrap_or("");
         let codec_name = stream.codec_name.clone().unwrap_or_default();
+        let language = stream.tags.get("language").cloned();
 
         match codec_type {
             "video" => {
-                let bitrate_kbps = stream
-                    .bit_rate
-                    .as_ref()
-                    .and_then(|br| br.parse::<f64>().ok())
-                    .map(|bps| (bps / 1000.0) as f32);
-
                 video_streams.push(VideoStream {
                     codec_name,
                     width: stream.width.unwrap_or(0),
                     height: stream.height.unwrap_or(0),
-                    bitrate_kbps,
+                    bitrate_kbps: stream.bit_rate.map(|bps| (bps / 1000.0) as f32),
+                    pix_fmt: stream.pix_fmt.clone(),
+                    duration_secs: stream.duration,
+                    attached_pic: stream.disposition("attached_pic"),
+                    frame_rate: frame_rate(&stream),
+                    profile: stream.profile.clone(),
+                    // ffprobe reports an unknown level as -99
+                    level: stream.level.filter(|level| *level >= 0),
+                    color_transfer: stream.color_transfer.clone(),
+                    color_primaries: stream.color_primaries.clone(),
+                    color_space: stream.color_space.clone(),
+                    hdr: hdr_side_data(&stream.side_data_list),
+                    language,
+                    tags: stream.tags,
                 });
             }
             "audio" => {
                 audio_streams.push(AudioStream {
                     codec_name,
                     channels: stream.channels.unwrap_or(0),
+                    language,
+                    title: stream.tags.get("title").cloned(),
+                    profile: stream.profile.clone(),
+                    channel_layout: stream.channel_layout.clone(),
+                    sample_rate: stream.sample_rate,
+                    default: stream.disposition("default"),
+                    tags: stream.tags,
+                });
+            }
+            "subtitle" => {
+                subtitle_streams.push(SubtitleStream {
+                    codec_name,
+                    stream_index: stream.index,
+                    language,
+                    forced: stream.disposition("forced"),
                 });
             }
+            "attachment" => {
+                let mimetype = stream.tags.get("mimetype").map(String::as_str).unwrap_or("");
+                if is_font_attachment(&codec_name, mimetype) {
+                    font_attachments += 1;
+                }
+            }
             _ => {}
         }
     }
 
-    let duration_secs = format
-        .duration
-        .as_ref()
-        .and_then(|d| d.parse::<f64>().ok())
-        .unwrap_or(0.0);
-
-    let size_bytes = format
-        .size
-        .as_ref()
-        .and_then(|s| s.parse::<u64>().ok())
-        .unwrap_or(0);
-
     Ok(ProbeResult {
         video_streams,
         audio_streams,
+        subtitle_streams,
+        font_attachments,
         format: FormatInfo {
-            duration_secs,
-            size_bytes,
+            duration_secs: format.duration.unwrap_or(0.0),
+            size_bytes: format.size.unwrap_or(0),
         },
     })
 }
 
+/// Average frame rate of a stream, falling back to the base rate; ffprobe
+/// writes `0/0` when it does not know either.
+fn frame_rate(stream: &ffprobe_json::Stream) -> Option<f64> {
+    [&stream.avg_frame_rate, &stream.r_frame_rate]
+        .into_iter()
+        .flatten()
+        .filter_map(|rate| ffprobe_json::parse_rational(rate))
+        .find(|rate| *rate > 0.0)
+}
+
+type SideData = serde_json::Map<String, serde_json::Value>;
+
+/// Collects the HDR entries of a stream's `side_data_list`.
+fn hdr_side_data(side_data: &[SideData]) -> HdrSideData {
+    let mut hdr = HdrSideData::default();
+    for entry in side_data {
+        match entry.get("side_data_type").and_then(|t| t.as_str()) {
+            Some("Mastering display metadata") => hdr.mastering_display = mastering_display(entry),
+            Some("Content light level metadata") => hdr.content_light = content_light(entry),
+            Some("DOVI configuration record") => {
+                hdr.dolby_vision_profile = side_data_number(entry, "dv_profile").map(|p| p as u8);
+            }
+            _ => {}
+        }
+    }
+    hdr
+}
+
+fn side_data_number(entry: &SideData, key: &str) -> Option<f64> {
+    entry.get(key).and_then(ffprobe_json::rational)
+}
+
+fn mastering_display(entry: &SideData) -> Option<MasteringDisplay> {
+    let point = |x: &str, y: &str| Some((side_data_number(entry, x)?, side_data_number(entry, y)?));
+    Some(MasteringDisplay {
+        red: point("red_x", "red_y")?,
+        green: point("green_x", "green_y")?,
+        blue: point("blue_x", "blue_y")?,
+        white_point: point("white_point_x", "white_point_y")?,
+        min_luminance: side_data_number(entry, "min_luminance")?,
+        max_luminance: side_data_number(entry, "max_luminance")?,
+    })
+}
+
+fn content_light(entry: &SideData) -> Option<ContentLightLevel> {
+    Some(ContentLightLevel {
+        max_content: side_data_number(entry, "max_content")? as u32,
+        max_average: side_data_number(entry, "max_average")? as u32,
+    })
+}
+
+/// Checks whether an attachment stream is an embedded font.
+fn is_font_attachment(codec_name: &str, mimetype: &str) -> bool {
+    matches!(codec_name, "ttf" | "otf")
+        || mimetype.contains("font")
+        || mimetype.contains("truetype")
+        || mimetype.contains("opentype")
+}
+
 
 /// Checks if a file passes all gates for encoding.
 ///
 /// Gates checked:
-/// 1. No video streams -> skip with "no video streams"
-/// 2. File size < min_bytes -> skip with "below minimum size"
-/// 3. First video stream is AV1 -> skip with "already AV1"
+/// 1. No video streams other than attached pictures -> skip with "no video
+///    streams"
+/// 2. Several video streams with `multi_video = "skip"`, or a main stream
+///    that is not the first -> skip with the stream count
+/// 3. File size < min_bytes -> skip with "below minimum size"
+/// 4. First video stream is AV1 -> skip with "already AV1"
+/// 5. Pixel format not encoded under `[pixel_format]` -> skip with the format
+/// 6. Video bitrate per megapixel below the threshold for its codec -> skip
+///    with "bitrate already low"
 ///
 /// Returns `GateResult::Pass` with the probe result if all gates pass.
 pub fn check_gates(probe: &ProbeResult, file_size: u64, cfg: &GatesConfig) -> GateResult {
-    // Gate 1: Check for no video streams
-    if probe.video_streams.is_empty() {
+    // Gate 1: Check for no video streams; cover art alone doesn't count
+    let Some(main_video) = probe.main_video_index() else {
         return GateResult::Skip {
-            reason: "no video streams".to_string(),
+            reason: SkipReason::new(SkipCode::NoVideoStreams, "no video streams"),
         };
+    };
+
+    // Gate 2: Check how many video streams there are besides cover art
+    if let Some(reason) = multi_video_reason(probe, main_video, cfg.multi_video) {
+        return GateResult::Skip { reason };
     }
 
-    // Gate 2: Check minimum file size
+    // Gate 3: Check minimum file size
     if file_size < cfg.min_bytes {
         return GateResult::Skip {
-            reason: format!(
-                "below minimum size ({} bytes < {} bytes)",
-                file_size, cfg.min_bytes
-            ),
+            reason: SkipReason::new(
+                SkipCode::BelowMinSize,
+                format!(
+                    "below minimum size ({} bytes < {} bytes)",
+                    file_size, cfg.min_bytes
+                ),
+            )
+            .with_threshold("file_size", file_size as f64)
+            .with_threshold("min_bytes", cfg.min_bytes as f64),
         };
     }
 
-    // Gate 3: Check if first video stream is already AV1
+    // Gate 4: Check if first video stream is already AV1
     if let Some(first_video) = probe.video_streams.first() {
         if first_video.codec_name.to_lowercase().contains("av1") {
             return GateResult::Skip {
-                reason: "already AV1".to_string(),
+                reason: SkipReason::new(SkipCode::AlreadyAv1, "already AV1"),
             };
         }
     }
 
+    // Gate 5: Check the pixel format can and should be encoded
+    let pix_fmt = probe.video_streams.first().and_then(|v| v.pix_fmt.as_deref());
+    if let Some(reason) = pixel_format_skip_reason(pix_fmt, &cfg.pixel_format) {
+        return GateResult::Skip { reason };
+    }
+
+    // Gate 6: Check the bitrate isn't already too low to gain from AV1
+    if let Some(reason) = low_bitrate_reason(probe, cfg) {
+        return GateResult::Skip { reason };
+    }
+
     // All gates passed
     GateResult::Pass(probe.clone())
 }
 
+/// Skip reason for a file with several video streams, not counting attached
+/// pictures, that `action` does not encode.
+///
+/// The encoder only reads the first video stream, so with
+/// [`MultiVideoAction::Main`] the main stream has to be that one.
+fn multi_video_reason(probe: &ProbeResult, main_video: usize, action: MultiVideoAction) -> Option<SkipReason> {
+    let count = probe.video_streams.iter().filter(|v| !v.attached_pic).count();
+    let reason = match action {
+        MultiVideoAction::Skip if count > 1 => format!("{} video streams", count),
+        _ if main_video != 0 => format!(
+            "main video stream is video stream {} of {}; only the first is encoded",
+            main_video,
+            probe.video_streams.len()
+        ),
+        _ => return None,
+    };
+    Some(
+        SkipReason::new(SkipCode::MultipleVideoStreams, reason)
+            .with_threshold("video_streams", count as f64),
+    )
+}
+
+/// Skip reason if the first video stream's bitrate per megapixel is below
+/// the threshold for its codec.
+///
+/// Uses the stream bitrate when ffprobe reports one, otherwise the overall
+/// bitrate of the file, which includes the audio and so errs towards
+/// encoding.
+fn low_bitrate_reason(probe: &ProbeResult, cfg: &GatesConfig) -> Option<SkipReason> {
+    let video = probe.video_streams.first()?;
+    let codec = video.codec_name.to_lowercase();
+    let min_kbps_per_mp = *cfg.min_kbps_per_megapixel.get(&codec)?;
+    let megapixels = video.width as f64 * video.height as f64 / 1_000_000.0;
+    let kbps = match video.bitrate_kbps {
+        Some(kbps) if kbps > 0.0 => kbps as f64,
+        _ if probe.format.duration_secs > 0.0 => {
+            probe.format.size_bytes as f64 * 8.0 / probe.format.duration_secs / 1000.0
+        }
+        _ => return None,
+    };
+    if megapixels <= 0.0 {
+        return None;
+    }
+    let kbps_per_mp = kbps / megapixels;
+    (kbps_per_mp < min_kbps_per_mp as f64).then(|| {
+        SkipReason::new(
+            SkipCode::LowBitrate,
+            format!(
+                "bitrate already low ({:.0} kbps at {}x{} {} is {:.0} kbps/MP < {} kbps/MP)",
+                kbps, video.width, video.height, codec, kbps_per_mp, min_kbps_per_mp
+            ),
+        )
+        .with_threshold("kbps_per_megapixel", kbps_per_mp)
+        .with_threshold("min_kbps_per_megapixel", min_kbps_per_mp as f64)
+    })
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -273,6 +772,10 @@ mod tests {
             width,
             height,
             bitrate_kbps: Some(5000.0),
+            pix_fmt: None,
+            duration_secs: None,
+            attached_pic: false,
+            ..Default::default()
         }
     }
 
@@ -281,6 +784,9 @@ mod tests {
         AudioStream {
             codec_name: codec.to_string(),
             channels,
+            language: None,
+            title: None,
+            ..Default::default()
         }
     }
 
@@ -289,6 +795,8 @@ mod tests {
         ProbeResult {
             video_streams,
             audio_streams,
+            subtitle_streams: vec![],
+            font_attachments: 0,
             format: FormatInfo {
                 duration_secs: 3600.0,
                 size_bytes: 5_000_000_000,
@@ -318,6 +826,8 @@ mod tests {
             let probe = ProbeResult {
                 video_streams: vec![], // No video streams
                 audio_streams,
+                subtitle_streams: vec![],
+                font_attachments: 0,
                 format: FormatInfo {
                     duration_secs: 3600.0,
                     size_bytes: file_size,
@@ -328,6 +838,9 @@ mod tests {
                 min_bytes,
                 max_size_ratio: 0.95,
                 keep_original: false,
+                min_kbps_per_megapixel: BTreeMap::new(),
+                pixel_format: PixelFormatConfig::default(),
+                multi_video: MultiVideoAction::default(),
             };
 
             let result = check_gates(&probe, file_size, &cfg);
@@ -336,7 +849,7 @@ mod tests {
             match result {
                 GateResult::Skip { reason } => {
                     prop_assert!(
-                        reason.contains("no video streams"),
+                        reason.message.contains("no video streams"),
                         "Skip reason should contain 'no video streams', got: {}",
                         reason
                     );
@@ -380,6 +893,9 @@ mod tests {
                 min_bytes,
                 max_size_ratio: 0.95,
                 keep_original: false,
+                min_kbps_per_megapixel: BTreeMap::new(),
+                pixel_format: PixelFormatConfig::default(),
+                multi_video: MultiVideoAction::default(),
             };
 
             let result = check_gates(&probe, file_size, &cfg);
@@ -387,7 +903,7 @@ mod tests {
             match result {
                 GateResult::Skip { reason } => {
                     prop_assert!(
-                        reason.contains("below minimum size"),
+                        reason.message.contains("below minimum size"),
                         "Skip reason should contain 'below minimum size', got: {}",
                         reason
                     );
@@ -435,6 +951,9 @@ mod tests {
                 min_bytes,
                 max_size_ratio: 0.95,
                 keep_original: false,
+                min_kbps_per_megapixel: BTreeMap::new(),
+                pixel_format: PixelFormatConfig::default(),
+                multi_video: MultiVideoAction::default(),
             };
 
             let result = check_gates(&probe, file_size, &cfg);
@@ -442,7 +961,7 @@ mod tests {
             match result {
                 GateResult::Skip { reason } => {
                     prop_assert!(
-                        reason.contains("already AV1"),
+                        reason.message.contains("already AV1"),
                         "Skip reason should contain 'already AV1', got: {}",
                         reason
                     );
@@ -493,6 +1012,8 @@ mod tests {
             let probe = ProbeResult {
                 video_streams: vec![make_video_stream(&codec, 1920, 1080)],
                 audio_streams,
+                subtitle_streams: vec![],
+                font_attachments: 0,
                 format: FormatInfo {
                     duration_secs: 3600.0,
                     size_bytes: file_size,
@@ -503,6 +1024,9 @@ mod tests {
                 min_bytes,
                 max_size_ratio: 0.95,
                 keep_original: false,
+                min_kbps_per_megapixel: BTreeMap::new(),
+                pixel_format: PixelFormatConfig::default(),
+                multi_video: MultiVideoAction::default(),
             };
 
             let result = check_gates(&probe, file_size, &cfg);
@@ -547,7 +1071,8 @@ mod tests {
                 {
                     "codec_type": "audio",
                     "codec_name": "aac",
-                    "channels": 6
+                    "channels": 6,
+                    "tags": { "language": "eng", "title": "Commentary" }
                 }
             ],
             "format": {
@@ -567,6 +1092,8 @@ mod tests {
         assert_eq!(result.audio_streams.len(), 1);
         assert_eq!(result.audio_streams[0].codec_name, "aac");
         assert_eq!(result.audio_streams[0].channels, 6);
+        assert_eq!(result.audio_streams[0].language.as_deref(), Some("eng"));
+        assert_eq!(result.audio_streams[0].title.as_deref(), Some("Commentary"));
 
         assert!((result.format.duration_secs - 7200.5).abs() < 0.001);
         assert_eq!(result.format.size_bytes, 22548578304);
@@ -587,6 +1114,13 @@ mod tests {
         assert!(result.audio_streams.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_probe_file_async_fails_on_unreadable_file() {
+        // Either ffprobe is missing or it rejects the path; both are errors
+        let result = probe_file_async(Path::new("/nonexistent/av1-probe-test.mkv")).await;
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_parse_ffprobe_output_missing_optional_fields() {
         let json = r#"{
@@ -609,6 +1143,145 @@ mod tests {
         assert!(result.video_streams[0].bitrate_kbps.is_none());
     }
 
+    #[test]
+    fn test_parse_ffprobe_output_hdr_and_stream_details() {
+        let json = r#"{
+            "streams": [
+                {
+                    "index": 0,
+                    "codec_type": "video",
+                    "codec_name": "hevc",
+                    "profile": "Main 10",
+                    "level": 153,
+                    "width": 3840,
+                    "height": 2160,
+                    "avg_frame_rate": "24000/1001",
+                    "color_transfer": "smpte2084",
+                    "color_primaries": "bt2020",
+                    "color_space": "bt2020nc",
+                    "tags": { "LANGUAGE": "und", "BPS-eng": "45000000" },
+                    "side_data_list": [
+                        {
+                            "side_data_type": "Mastering display metadata",
+                            "red_x": "34000/50000", "red_y": "16000/50000",
+                            "green_x": "13250/50000", "green_y": "34500/50000",
+                            "blue_x": "7500/50000", "blue_y": "3000/50000",
+                            "white_point_x": "15635/50000", "white_point_y": "16450/50000",
+                            "min_luminance": "50/10000", "max_luminance": "40000000/10000"
+                        },
+                        { "side_data_type": "Content light level metadata", "max_content": 1000, "max_average": 400 },
+                        { "side_data_type": "DOVI configuration record", "dv_profile": 8 }
+                    ]
+                },
+                {
+                    "codec_type": "audio",
+                    "codec_name": "dts",
+                    "profile": "DTS-HD MA",
+                    "channels": 8,
+                    "channel_layout": "7.1",
+                    "sample_rate": "48000",
+                    "disposition": { "default": 1 },
+                    "tags": { "language": "eng" }
+                }
+            ],
+            "format": { "duration": "5400.0", "size": "60000000000" }
+        }"#;
+
+        let result = parse_ffprobe_output(json).expect("Should parse HDR stream");
+        let video = &result.video_streams[0];
+        assert!((video.frame_rate.unwrap() - 23.976).abs() < 0.001);
+        assert_eq!(video.profile.as_deref(), Some("Main 10"));
+        assert_eq!(video.level, Some(153));
+        assert_eq!(video.color_primaries.as_deref(), Some("bt2020"));
+        assert_eq!(video.language.as_deref(), Some("und"));
+        assert_eq!(video.tags.get("bps-eng").map(String::as_str), Some("45000000"));
+        assert!(video.is_hdr());
+
+        let mastering = video.hdr.mastering_display.expect("mastering display");
+        assert_eq!(mastering.red, (0.68, 0.32));
+        assert_eq!(mastering.white_point, (0.3127, 0.329));
+        assert_eq!(mastering.min_luminance, 0.005);
+        assert_eq!(mastering.max_luminance, 4000.0);
+        assert_eq!(
+            video.hdr.content_light,
+            Some(ContentLightLevel { max_content: 1000, max_average: 400 })
+        );
+        assert_eq!(video.hdr.dolby_vision_profile, Some(8));
+
+        let audio = &result.audio_streams[0];
+        assert_eq!(audio.profile.as_deref(), Some("DTS-HD MA"));
+        assert_eq!(audio.channel_layout.as_deref(), Some("7.1"));
+        assert_eq!(audio.sample_rate, Some(48000));
+        assert!(audio.default);
+    }
+
+    #[test]
+    fn test_parse_ffprobe_output_tolerates_unexpected_shapes() {
+        // Numbers as strings and the other way round, "N/A", unknown level,
+        // a malformed tags value, and a stream that is not an object
+        let json = r#"{
+            "streams": [
+                {
+                    "codec_type": "video",
+                    "codec_name": "h264",
+                    "width": "1920",
+                    "height": 1080,
+                    "bit_rate": 8000000,
+                    "duration": "N/A",
+                    "level": -99,
+                    "avg_frame_rate": "0/0",
+                    "r_frame_rate": "25/1",
+                    "tags": ["unexpected"],
+                    "side_data_list": { "unexpected": true }
+                },
+                "garbage",
+                { "codec_type": "audio", "codec_name": "aac", "channels": "2", "sample_rate": null }
+            ],
+            "format": { "duration": 60, "size": "N/A" },
+            "unknown_section": {}
+        }"#;
+
+        let result = parse_ffprobe_output(json).expect("Should tolerate unexpected shapes");
+        let video = &result.video_streams[0];
+        assert_eq!((video.width, video.height), (1920, 1080));
+        assert!((video.bitrate_kbps.unwrap() - 8000.0).abs() < 0.1);
+        assert_eq!(video.duration_secs, None);
+        assert_eq!(video.level, None);
+        assert_eq!(video.frame_rate, Some(25.0));
+        assert!(video.tags.is_empty());
+        assert!(!video.is_hdr());
+
+        assert_eq!(result.audio_streams[0].channels, 2);
+        assert_eq!(result.audio_streams[0].sample_rate, None);
+        assert_eq!(result.format.duration_secs, 60.0);
+        assert_eq!(result.format.size_bytes, 0);
+
+        // Without a format section the probe is still unusable
+        assert!(parse_ffprobe_output(r#"{"streams": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_ffprobe_output_subtitles_and_fonts() {
+        let json = r#"{
+            "streams": [
+                { "codec_type": "video", "codec_name": "hevc" },
+                { "index": 1, "codec_type": "subtitle", "codec_name": "ass", "tags": { "language": "eng" }, "disposition": { "forced": 1 } },
+                { "codec_type": "attachment", "codec_name": "ttf" },
+                { "codec_type": "attachment", "tags": { "mimetype": "application/x-truetype-font" } },
+                { "codec_type": "attachment", "tags": { "mimetype": "image/jpeg" } }
+            ],
+            "format": { "duration": "1420.0", "size": "350000000" }
+        }"#;
+
+        let result = parse_ffprobe_output(json).expect("Should parse JSON with attachments");
+        assert_eq!(result.subtitle_streams.len(), 1);
+        assert_eq!(result.subtitle_streams[0].codec_name, "ass");
+        assert_eq!(result.subtitle_streams[0].stream_index, Some(1));
+        assert_eq!(result.subtitle_streams[0].language.as_deref(), Some("eng"));
+        assert!(result.subtitle_streams[0].forced);
+        assert_eq!(result.font_attachments, 2);
+    }
+
     #[test]
     fn test_check_gates_no_video_streams() {
         let probe = make_probe_result(vec![], vec![make_audio_stream("aac", 2)]);
@@ -617,7 +1290,7 @@ mod tests {
         let result = check_gates(&probe, 10_000_000, &cfg);
         match result {
             GateResult::Skip { reason } => {
-                assert!(reason.contains("no video streams"));
+                assert!(reason.message.contains("no video streams"));
             }
             _ => panic!("Expected Skip result"),
         }
@@ -637,7 +1310,9 @@ mod tests {
         let result = check_gates(&probe, 5_000_000, &cfg);
         match result {
             GateResult::Skip { reason } => {
-                assert!(reason.contains("below minimum size"));
+                assert!(reason.message.contains("below minimum size"));
+                assert_eq!(reason.code, SkipCode::BelowMinSize);
+                assert_eq!(reason.thresholds.get("min_bytes"), Some(&10_000_000.0));
             }
             _ => panic!("Expected Skip result"),
         }
@@ -657,7 +1332,7 @@ mod tests {
         let result = check_gates(&probe, 10_000_000, &cfg);
         match result {
             GateResult::Skip { reason } => {
-                assert!(reason.contains("already AV1"));
+                assert!(reason.message.contains("already AV1"));
             }
             _ => panic!("Expected Skip result"),
         }
@@ -682,4 +1357,206 @@ mod tests {
             _ => panic!("Expected Pass result"),
         }
     }
+
+    #[test]
+    fn test_check_gates_low_bitrate() {
+        let mut probe = make_probe_result(
+            vec![make_video_stream("hevc", 1920, 1080)],
+            vec![make_audio_stream("aac", 2)],
+        );
+        let cfg = GatesConfig {
+            min_bytes: 1_000,
+            min_kbps_per_megapixel: BTreeMap::from([("hevc".to_string(), 965.0)]),
+            ..Default::default()
+        };
+
+        // 5000 kbps at 1080p is about 2411 kbps/MP
+        assert!(matches!(check_gates(&probe, 10_000_000, &cfg), GateResult::Pass(_)));
+
+        probe.video_streams[0].bitrate_kbps = Some(1800.0);
+        match check_gates(&probe, 10_000_000, &cfg) {
+            GateResult::Skip { reason } => {
+                assert_eq!(reason.code, SkipCode::LowBitrate);
+                assert!(reason.message.contains("868 kbps/MP < 965 kbps/MP"), "{}", reason.message);
+            }
+            _ => panic!("Expected Skip result"),
+        }
+
+        // Without a stream bitrate the overall bitrate is used: 5 GB over an
+        // hour is about 11111 kbps
+        probe.video_streams[0].bitrate_kbps = None;
+        assert!(matches!(check_gates(&probe, 10_000_000, &cfg), GateResult::Pass(_)));
+
+        // Codecs missing from the table are not checked
+        probe.video_streams[0] = make_video_stream("h264", 1920, 1080);
+        probe.video_streams[0].bitrate_kbps = Some(100.0);
+        assert!(matches!(check_gates(&probe, 10_000_000, &cfg), GateResult::Pass(_)));
+    }
+
+    #[test]
+    fn test_check_gates_multiple_video_streams() {
+        let json = r#"{
+            "streams": [
+                { "codec_type": "video", "codec_name": "hevc", "width": 1920, "height": 1080, "duration": "5400.0" },
+                { "codec_type": "video", "codec_name": "mjpeg", "width": 600, "height": 900,
+                  "disposition": { "attached_pic": 1 } }
+            ],
+            "format": { "duration": "5400.0", "size": "4000000000" }
+        }"#;
+        let mut probe = parse_ffprobe_output(json).unwrap();
+        assert!(probe.video_streams[1].attached_pic);
+        assert_eq!(probe.main_video_index(), Some(0));
+        assert_eq!(probe.attached_picture_indices(), vec![1]);
+
+        // Cover art is not a second video stream
+        let mut cfg = GatesConfig {
+            min_bytes: 1_000,
+            multi_video: MultiVideoAction::Skip,
+            ..Default::default()
+        };
+        assert!(matches!(check_gates(&probe, 10_000_000, &cfg), GateResult::Pass(_)));
+
+        // An angle track is
+        probe.video_streams[1] = make_video_stream("hevc", 1920, 1080);
+        match check_gates(&probe, 10_000_000, &cfg) {
+            GateResult::Skip { reason } => {
+                assert_eq!(reason.code, SkipCode::MultipleVideoStreams);
+                assert_eq!(reason.message, "2 video streams");
+            }
+            _ => panic!("Expected Skip result"),
+        }
+        cfg.multi_video = MultiVideoAction::Main;
+        assert!(matches!(check_gates(&probe, 10_000_000, &cfg), GateResult::Pass(_)));
+
+        // A larger stream after the first can't be encoded on its own
+        probe.video_streams[1] = make_video_stream("hevc", 3840, 2160);
+        assert_eq!(probe.main_video_index(), Some(1));
+        match check_gates(&probe, 10_000_000, &cfg) {
+            GateResult::Skip { reason } => assert_eq!(reason.code, SkipCode::MultipleVideoStreams),
+            _ => panic!("Expected Skip result"),
+        }
+
+        // Cover art alone is no video at all
+        probe.video_streams = vec![VideoStream {
+            attached_pic: true,
+            ..make_video_stream("png", 600, 600)
+        }];
+        match check_gates(&probe, 10_000_000, &cfg) {
+            GateResult::Skip { reason } => assert_eq!(reason.code, SkipCode::NoVideoStreams),
+            _ => panic!("Expected Skip result"),
+        }
+    }
+
+    /// Representative ffprobe output for each of the less common containers,
+    /// as (container, ffprobe JSON, expected first video codec).
+    fn container_samples() -> Vec<(&'static str, &'static str, &'static str)> {
+        vec![
+            (
+                "webm",
+                r#"{
+                    "streams": [
+                        { "index": 0, "codec_type": "video", "codec_name": "vp9", "width": 1920, "height": 1080 },
+                        { "index": 1, "codec_type": "audio", "codec_name": "opus", "channels": 2 }
+                    ],
+                    "format": { "format_name": "matroska,webm", "duration": "600.0", "size": "150000000" }
+                }"#,
+                "vp9",
+            ),
+            (
+                "wmv",
+                r#"{
+                    "streams": [
+                        { "index": 0, "codec_type": "audio", "codec_name": "wmav2", "channels": 2 },
+                        { "index": 1, "codec_type": "video", "codec_name": "wmv3", "width": 1280, "height": 720, "bit_rate": "4000000" }
+                    ],
+                    "format": { "format_name": "asf", "duration": "1800.0", "size": "900000000" }
+                }"#,
+                "wmv3",
+            ),
+            (
+                "mpg",
+                r#"{
+                    "streams": [
+                        { "index": 0, "codec_type": "video", "codec_name": "mpeg2video", "width": 720, "height": 480 },
+                        { "index": 1, "codec_type": "audio", "codec_name": "mp2", "channels": 2 }
+                    ],
+                    "format": { "format_name": "mpeg", "duration": "3600.0", "size": "2000000000" }
+                }"#,
+                "mpeg2video",
+            ),
+            (
+                "mpeg",
+                r#"{
+                    "streams": [
+                        { "index": 0, "codec_type": "video", "codec_name": "mpeg1video", "width": 352, "height": 240, "bit_rate": "1150000" }
+                    ],
+                    "format": { "format_name": "mpeg", "duration": "5400.0", "size": "800000000" }
+                }"#,
+                "mpeg1video",
+            ),
+            (
+                "vob",
+                r#"{
+                    "streams": [
+                        { "index": 0, "codec_type": "data", "codec_name": "dvd_nav_packet" },
+                        { "index": 1, "codec_type": "video", "codec_name": "mpeg2video", "width": 720, "height": 576 },
+                        { "index": 2, "codec_type": "audio", "codec_name": "ac3", "channels": 6 },
+                        { "index": 3, "codec_type": "subtitle", "codec_name": "dvd_subtitle" }
+                    ],
+                    "format": { "format_name": "mpeg", "size": "1073709056" }
+                }"#,
+                "mpeg2video",
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_gates_pass_for_each_container() {
+        let cfg = GatesConfig {
+            min_bytes: 1_000,
+            ..Default::default()
+        };
+
+        for (container, json, codec) in container_samples() {
+            let probe = parse_ffprobe_output(json)
+                .unwrap_or_else(|e| panic!("{} probe should parse: {}", container, e));
+            assert_eq!(probe.video_streams.len(), 1, "{}: video streams", container);
+            assert_eq!(probe.video_streams[0].codec_name, codec, "{}: codec", container);
+
+            match check_gates(&probe, probe.format.size_bytes, &cfg) {
+                GateResult::Pass(_) => {}
+                GateResult::Skip { reason } => {
+                    panic!("{} should pass the gates, skipped: {}", container, reason)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_vob_without_duration_parses() {
+        let (_, json, _) = container_samples()
+            .into_iter()
+            .find(|(container, _, _)| *container == "vob")
+            .unwrap();
+        let probe = parse_ffprobe_output(json).unwrap();
+        assert_eq!(probe.format.duration_secs, 0.0);
+        assert_eq!(probe.audio_streams[0].channels, 6);
+        assert_eq!(probe.subtitle_streams[0].codec_name, "dvd_subtitle");
+    }
+
+    #[test]
+    fn test_av1_webm_is_already_av1() {
+        let json = r#"{
+            "streams": [
+                { "codec_type": "video", "codec_name": "av1", "width": 3840, "height": 2160 },
+                { "codec_type": "audio", "codec_name": "opus", "channels": 2 }
+            ],
+            "format": { "format_name": "matroska,webm", "duration": "600.0", "size": "300000000" }
+        }"#;
+        let probe = parse_ffprobe_output(json).unwrap();
+        match check_gates(&probe, probe.format.size_bytes, &GatesConfig::default()) {
+            GateResult::Skip { reason } => assert_eq!(reason.code, SkipCode::AlreadyAv1),
+            GateResult::Pass(_) => panic!("AV1 WebM should be skipped"),
+        }
+    }
 }
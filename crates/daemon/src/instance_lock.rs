@@ -0,0 +1,119 @@
+//! Single-instance lock on the job state directory.
+//!
+//! A daemon holds an exclusive lock on `daemon.lock` in `job_state_dir` for
+//! as long as it runs, so a second instance started against the same state
+//! fails at startup instead of claiming and encoding the same files. The
+//! lock is released by the OS when the process exits, even on a crash; the
+//! file itself is left in place and only records the holder's PID for the
+//! error message.
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+/// Name of the lock file inside `job_state_dir`.
+pub const LOCK_FILE: &str = "daemon.lock";
+
+/// Error returned when the state directory cannot be locked
+#[derive(Debug, Error)]
+pub enum InstanceLockError {
+    #[error(
+        "another daemon ({}) is already using {}; stop it or point paths.job_state_dir elsewhere",
+        holder_description(*pid),
+        path.display()
+    )]
+    AlreadyRunning { path: PathBuf, pid: Option<u32> },
+
+    #[error("Cannot lock {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+}
+
+fn holder_description(pid: Option<u32>) -> String {
+    match pid {
+        Some(pid) => format!("pid {}", pid),
+        None => "pid unknown".to_string(),
+    }
+}
+
+/// Exclusive lock on a job state directory, held until dropped
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Locks `job_state_dir`, creating it if needed, and records this
+    /// process's PID in the lock file.
+    ///
+    /// # Returns
+    /// * `Err(InstanceLockError::AlreadyRunning)` if another process holds it
+    pub fn acquire(job_state_dir: &Path) -> Result<Self, InstanceLockError> {
+        let path = job_state_dir.join(LOCK_FILE);
+        let io_error = |source| InstanceLockError::Io { path: path.clone(), source };
+
+        std::fs::create_dir_all(job_state_dir).map_err(io_error)?;
+        // Not truncated on open: until the lock is ours the PID belongs to
+        // the current holder
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(io_error)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut contents = String::new();
+                let pid = file
+                    .read_to_string(&mut contents)
+                    .ok()
+                    .and_then(|_| contents.trim().parse().ok());
+                return Err(InstanceLockError::AlreadyRunning { path, pid });
+            }
+            Err(TryLockError::Error(e)) => return Err(io_error(e)),
+        }
+
+        file.set_len(0)
+            .and_then(|()| file.seek(SeekFrom::Start(0)))
+            .and_then(|_| writeln!(file, "{}", std::process::id()))
+            .map_err(io_error)?;
+        Ok(Self { _file: file, path })
+    }
+
+    /// Path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_instance_is_refused() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let state_dir = temp_dir.path().join("jobs");
+
+        let lock = InstanceLock::acquire(&state_dir).unwrap();
+        let recorded = std::fs::read_to_string(lock.path()).unwrap();
+        assert_eq!(recorded.trim(), std::process::id().to_string());
+
+        match InstanceLock::acquire(&state_dir) {
+            Err(InstanceLockError::AlreadyRunning { pid, .. }) => {
+                assert_eq!(pid, Some(std::process::id()));
+            }
+            other => panic!("expected AlreadyRunning, got {:?}", other),
+        }
+
+        // Released with the holder, though the file stays behind
+        drop(lock);
+        assert!(state_dir.join(LOCK_FILE).exists());
+        InstanceLock::acquire(&state_dir).unwrap();
+    }
+}
@@ -2,16 +2,64 @@
 //!
 //! Manages the execution of encoding jobs with concurrency limiting via semaphore.
 
-use crate::encode::{run_av1an, Av1anEncodeParams, EncodeError};
+use crate::classify::SourceType;
+use crate::config::{
+    BackupLocation, CgroupConfig, ChecksumSidecarPolicy, CollisionPolicy, Config, HardlinkPolicy,
+    ImageSubtitleAction, PixelFormatConfig, QuotaConfig, SeedAction, StagingConfig, StorageConfig, StorageKind, SubtitleConfig, TelemetryConfig,
+    ThermalAction, TimeWindow, TorrentConfig, TriageConfig, ValidationConfig,
+};
+use crate::encode::{
+    build_av1an_command, command_line, is_taggable, run_av1an_cancellable, run_remux_as,
+    write_mkv_tags, Av1anEncodeParams, CancelToken, ChunkMethod, EncodeError, EncoderCgroup,
+    EncodeLimits, EncodeProfile, OutputTail, ProcessingTags, RunAs, SvtOverrides, SvtParams,
+};
+use crate::jobs::{
+    load_job, load_jobs, update_job, Job as ManagedJob, JobKind, JobOverrides, JobStage, JobStatus,
+    KeptBackup,
+};
+use crate::journal::{append_entry, JournalEntry};
+use crate::kill_switch::kill_switch_engaged;
 use crate::metrics::{JobMetrics, SharedMetrics};
-use crate::replace::{atomic_replace, ReplaceError};
+use crate::replace::{
+    render_backup_path, replace_with_backup_verified, resolve_output_path, ReplaceError, SwapIntent,
+};
+use crate::scan::hard_link_count;
+use crate::compare::{comparison_dir, remove_comparison, write_comparison_stills};
+use crate::checksums::update_checksum_sidecars;
+use crate::audio_policy::{AudioPlan, AudioPolicy};
+use crate::audio_sync::check_audio_sync;
+use crate::failure::{classify_failure, FailureKind};
+use crate::frame_check::find_frame_problems;
+use crate::gates::{probe_file_async, AudioStream, SubtitleStream};
+use crate::pixel_format::output_pix_format;
+use crate::quality::sample_quality;
+use crate::claims::{read_claim, unix_now, Claim, ClaimLease, FarmNode};
+use crate::quotas::LibraryQuotas;
+use crate::dependencies::unfinished_dependencies;
+use crate::staging::{stage_source, verify_source, StagedSource};
+use crate::queue_order::{Lane, WaitLine};
+use crate::replace_window::replace_window_open;
+use crate::replacement_budget::ReplacementBudget;
 use crate::size_gate::{check_size_gate, SizeGateResult};
-use crate::skip_marker::{write_skip_marker, write_why_sidecar};
-use crate::ConcurrencyPlan;
-use std::path::PathBuf;
-use std::sync::Arc;
+use crate::skip_marker::{write_skip_marker_with_code, write_why_json, write_why_sidecar, SkipCode, SkipReason};
+use crate::skip_stats::record_skip;
+use crate::subtitles::{drop_subtitle_args, dropped_by_language, extract_image_subtitles, rename_sidecars};
+use crate::control::DaemonControl;
+use crate::telemetry::{SpanTimes, Telemetry};
+use crate::temp_gc::{measure_temp_usage, over_quota};
+use crate::timings::record_stage_time;
+use crate::encode_settings::{settings_sidecar_path, write_settings_sidecar, EncodeSettings};
+use crate::tool_versions::ToolVersions;
+use crate::torrent::{seeding_hashes, TorrentClient, TorrentError};
+use crate::triage::{prune_bundles, write_bundle};
+use crate::{ConcurrencyPlan, LanePlan};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
-use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::sync::{Notify, OnceCell, OwnedSemaphorePermit, Semaphore};
 
 /// Error type for job execution operations
 #[derive(Debug, Error)]
@@ -43,6 +91,26 @@ pub enum JobError {
     /// Failed to write skip marker
     #[error("Failed to write skip marker: {0}")]
     SkipMarkerFailed(std::io::Error),
+
+    /// The job was cancelled before it finished encoding
+    #[error("Job cancelled")]
+    Cancelled,
+
+    /// Approval was asked for a job that is not held for it
+    #[error("Job {0} is not awaiting approval")]
+    NotAwaitingApproval(String),
+
+    /// Failed to read the persisted job
+    #[error("Failed to read job store: {0}")]
+    JobStore(std::io::Error),
+
+    /// The source could not be copied to local scratch
+    #[error("Failed to stage source locally: {0}")]
+    Staging(std::io::Error),
+
+    /// Another daemon sharing the library claimed the file first
+    #[error("File claimed by {0}")]
+    ClaimedElsewhere(String),
 }
 
 /// Job state representing the current stage in the pipeline
@@ -60,10 +128,16 @@ pub enum JobState {
     Replacing,
     /// Job completed successfully
     Completed,
+    /// Encode passed every gate but is held until approved
+    AwaitingApproval,
+    /// Encode passed every gate and waits for the replace window
+    ReadyToReplace,
     /// Job was skipped (e.g., size gate rejection)
     Skipped(String),
     /// Job failed
     Failed(String),
+    /// Job was cancelled on request
+    Cancelled,
 }
 
 impl JobState {
@@ -76,8 +150,51 @@ impl JobState {
             JobState::SizeGating => "size_gating",
             JobState::Replacing => "replacing",
             JobState::Completed => "completed",
+            JobState::AwaitingApproval => "awaiting_approval",
+            JobState::ReadyToReplace => "ready_to_replace",
             JobState::Skipped(_) => "skipped",
             JobState::Failed(_) => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+
+    /// Stage of the persisted job, or `None` for outcomes that leave it at
+    /// the stage where the job stopped
+    pub fn stage(&self) -> Option<JobStage> {
+        match self {
+            JobState::Queued => Some(JobStage::Queued),
+            JobState::Encoding => Some(JobStage::Encoding),
+            JobState::Validating => Some(JobStage::Validating),
+            JobState::SizeGating => Some(JobStage::SizeGating),
+            JobState::Replacing => Some(JobStage::Replacing),
+            JobState::Completed => Some(JobStage::Complete),
+            JobState::AwaitingApproval => Some(JobStage::AwaitingApproval),
+            JobState::ReadyToReplace => Some(JobStage::ReadyToReplace),
+            JobState::Skipped(_) | JobState::Failed(_) | JobState::Cancelled => None,
+        }
+    }
+
+    /// Status of the persisted job
+    pub fn status(&self) -> JobStatus {
+        match self {
+            JobState::Queued | JobState::AwaitingApproval | JobState::ReadyToReplace => {
+                JobStatus::Pending
+            }
+            JobState::Encoding | JobState::Validating | JobState::SizeGating | JobState::Replacing => {
+                JobStatus::Running
+            }
+            JobState::Completed => JobStatus::Success,
+            JobState::Skipped(_) => JobStatus::Skipped,
+            JobState::Failed(_) => JobStatus::Failed,
+            JobState::Cancelled => JobStatus::Cancelled,
+        }
+    }
+
+    /// Why the job stopped, for skipped and failed jobs
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            JobState::Skipped(reason) | JobState::Failed(reason) => Some(reason),
+            _ => None,
         }
     }
 }
@@ -98,6 +215,48 @@ pub struct Job {
     pub total_frames: u64,
     /// Original file size in bytes
     pub size_in_bytes_before: u64,
+    /// Duration of the input in seconds (0 if unknown)
+    pub duration_secs: f64,
+    /// Classified source type, used to select the encode profile
+    pub source_type: SourceType,
+    /// Tags copied from the managed job, reported in metrics for filtering
+    pub tags: Vec<String>,
+    /// Full encode, or stream copy of an already-AV1 source into MKV
+    pub kind: JobKind,
+    /// Av1an workers for this run when not the planned count, set when the
+    /// job starts while the CPU is thermally throttled or asks for its own
+    pub worker_limit: Option<u32>,
+    /// When the scan probed the input, for the job's trace; `None` when the
+    /// probe came from the cache or the job was not created by a scan
+    pub probe_times: Option<SpanTimes>,
+    /// Audio streams of the input, which the audio policy picks from
+    pub audio_streams: Vec<AudioStream>,
+    /// Subtitle streams of the input, whose image-based tracks may be
+    /// extracted to sidecars
+    pub subtitle_streams: Vec<SubtitleStream>,
+    /// Forced by an `.av1force` marker; the size gate does not apply
+    pub forced: bool,
+    /// Settings given with the submission that replace the configured ones
+    pub overrides: JobOverrides,
+    /// Pixel format of the input's first video stream, if probed
+    pub pix_fmt: Option<String>,
+    /// Positions among the input's video streams of attached pictures
+    pub attached_pictures: Vec<usize>,
+    /// Settings of the encode, once its parameters are built
+    pub settings: Option<EncodeSettings>,
+    /// Codec of the input's first video stream, if probed
+    pub source_codec: Option<String>,
+    /// Local copy of the input the encode reads instead, when its library
+    /// is staged
+    pub staged_input: Option<PathBuf>,
+    /// SHA-256 of the input when it was staged, checked again before the
+    /// input is replaced
+    pub staged_digest: Option<String>,
+    /// End of Av1an's output, for classifying a failure and the triage
+    /// bundle, once the encode starts
+    pub output_tail: Option<Arc<OutputTail>>,
+    /// Cause of the failure, once an encode or remux has failed
+    pub failure: Option<FailureKind>,
 }
 
 impl Job {
@@ -110,11 +269,88 @@ impl Job {
             state: JobState::Queued,
             total_frames: 0,
             size_in_bytes_before: 0,
+            duration_secs: 0.0,
+            source_type: SourceType::default(),
+            tags: Vec::new(),
+            kind: JobKind::Encode,
+            worker_limit: None,
+            probe_times: None,
+            audio_streams: Vec::new(),
+            subtitle_streams: Vec::new(),
+            forced: false,
+            overrides: JobOverrides::default(),
+            pix_fmt: None,
+            attached_pictures: Vec::new(),
+            settings: None,
+            source_codec: None,
+            staged_input: None,
+            staged_digest: None,
+            output_tail: None,
+            failure: None,
+        }
+    }
+
+    /// Path the encode and its checks read the input from
+    pub fn source_path(&self) -> &Path {
+        self.staged_input.as_deref().unwrap_or(&self.input_path)
+    }
+
+    /// Create the executor's view of a persisted job
+    ///
+    /// `size_in_bytes_before` is the size seen at scan time, which the size
+    /// gate compares the encode against.
+    pub fn from_managed(managed: &ManagedJob, size_in_bytes_before: u64) -> Self {
+        let mut job = Self::new(
+            managed.id.clone(),
+            managed.input_path.clone(),
+            managed.output_path.clone(),
+        );
+        job.size_in_bytes_before = size_in_bytes_before;
+        job.duration_secs = managed.probe_result.format.duration_secs;
+        job.source_type = managed.source_type;
+        job.tags = managed.tags.clone();
+        job.kind = managed.kind;
+        job.audio_streams = managed.probe_result.audio_streams.clone();
+        job.subtitle_streams = managed.probe_result.subtitle_streams.clone();
+        job.forced = managed.forced;
+        job.overrides = managed.overrides;
+        job.pix_fmt = managed
+            .probe_result
+            .video_streams
+            .first()
+            .and_then(|video| video.pix_fmt.clone());
+        job.attached_pictures = managed.probe_result.attached_picture_indices();
+        job.settings = managed.encode_settings.clone();
+        job.source_codec = managed
+            .probe_result
+            .video_streams
+            .first()
+            .map(|video| video.codec_name.clone());
+        job
+    }
+
+    /// SVT-AV1 overrides of this job's encode: its own CRF and preset over
+    /// the `configured` ones
+    pub fn svt_overrides(&self, configured: SvtOverrides) -> SvtOverrides {
+        SvtOverrides {
+            crf: self.overrides.crf.or(configured.crf),
+            preset: self.overrides.preset.or(configured.preset),
+            ..configured
         }
     }
 
+    /// SVT-AV1 parameters this job encodes with: the profile for its source
+    /// with [`Job::svt_overrides`] applied
+    pub fn svt_params(&self, configured: SvtOverrides) -> SvtParams {
+        self.svt_overrides(configured)
+            .apply(EncodeProfile::for_source(self.source_type).svt_params())
+    }
+
     /// Create JobMetrics from current job state
-    pub fn to_metrics(&self, workers: u32) -> JobMetrics {
+    ///
+    /// `svt_params` are the settings the encode runs with (see
+    /// [`Job::svt_params`]).
+    pub fn to_metrics(&self, workers: u32, svt_params: SvtParams) -> JobMetrics {
         JobMetrics {
             id: self.id.clone(),
             input_path: self.input_path.to_string_lossy().to_string(),
@@ -122,8 +358,11 @@ impl Job {
             progress: 0.0,
             fps: 0.0,
             bitrate_kbps: 0.0,
-            crf: 8,
-            encoder: "svt-av1".to_string(),
+            crf: svt_params.crf,
+            encoder: match self.kind {
+                JobKind::Encode => "svt-av1".to_string(),
+                JobKind::Remux => "copy".to_string(),
+            },
             workers,
             est_remaining_secs: 0.0,
             frames_encoded: 0,
@@ -133,6 +372,9 @@ impl Job {
             vmaf: None,
             psnr: None,
             ssim: None,
+            tags: self.tags.clone(),
+            energy_joules: 0.0,
+            quality_flag: None,
         }
     }
 }
@@ -146,6 +388,140 @@ pub struct JobExecutorConfig {
     pub keep_original: bool,
     /// Whether to write .why.txt sidecar files explaining skips
     pub write_why_sidecars: bool,
+    /// Whether to write structured .why.json sidecar files
+    pub write_why_json: bool,
+    /// Wall-clock and stall limits applied to each Av1an run
+    pub encode_limits: EncodeLimits,
+    /// CRF, preset, and film grain overrides from the config or its profile
+    pub svt_overrides: SvtOverrides,
+    /// File name template for the replaced file (see `OutputConfig`)
+    pub rename_template: String,
+    /// What to do when the rendered name is taken
+    pub on_collision: CollisionPolicy,
+    /// How originals with other hard links are replaced
+    pub hardlinks: HardlinkPolicy,
+    /// File name template for the original's backup (see `OutputConfig`)
+    pub backup_template: String,
+    /// Folder the backup is written to
+    pub backup_location: BackupLocation,
+    /// Torrent client whose seeding torrents are paused around replacements
+    pub torrent: TorrentConfig,
+    /// What happens to checksum files listing a replaced original
+    pub checksum_sidecars: ChecksumSidecarPolicy,
+    /// Whether MKV outputs are tagged with how they were made
+    pub metadata_tags: bool,
+    /// User and group encodes run as and replaced files are handed to
+    pub run_as: Option<RunAs>,
+    /// Collector that job traces are exported to
+    pub telemetry: TelemetryConfig,
+    /// Directory of persisted job JSON files kept in step with each state
+    /// change; `None` leaves persisted jobs alone
+    pub job_state_dir: Option<PathBuf>,
+    /// Encode and gate as usual but never replace originals or write markers
+    pub read_only: bool,
+    /// Replacements allowed per rolling day before encodes are held for
+    /// approval; 0 means unlimited
+    pub max_replacements_per_day: u32,
+    /// Hold every encode that passes the size gate for approval
+    pub require_approval: bool,
+    /// Local time of day originals are replaced in; `None` means any time
+    pub replace_window: Option<TimeWindow>,
+    /// Side-by-side stills written for each held encode
+    pub comparison_stills: u32,
+    /// Checks run on the output before the size gate
+    pub validation: ValidationConfig,
+    /// Limits on the jobs of each library
+    pub quotas: QuotaConfig,
+    /// This daemon's identity in file claims, when daemons share libraries
+    pub farm: Option<FarmNode>,
+    /// Libraries whose sources are copied to local scratch first
+    pub staging: StagingConfig,
+    /// Storage each library is on, which sets how Av1an reads sources
+    pub storage: StorageConfig,
+    /// Pixel format of encodes by source bit depth
+    pub pixel_format: PixelFormatConfig,
+    /// Which audio tracks encodes keep
+    pub audio: AudioPolicy,
+    /// Which libraries extract image-based subtitles to sidecars
+    pub subtitles: SubtitleConfig,
+    /// Leave attached pictures such as cover art out of encodes
+    pub drop_attached_pictures: bool,
+    /// Give short files fewer workers; permits then count workers instead
+    /// of jobs
+    pub scale_workers: bool,
+    /// Slots for short files beside one for long files (0 = no lanes)
+    pub small_lane_slots: u32,
+    /// Files at least this long in seconds use the long-file lane
+    pub big_lane_min_secs: u64,
+    /// cgroup limits on each encode's process tree
+    pub cgroups: CgroupConfig,
+    /// Triage bundles written for failed jobs
+    pub triage: TriageConfig,
+    /// Sentinel file that holds jobs at their slot while it exists
+    pub kill_switch_file: Option<PathBuf>,
+    /// Hold jobs at their slot while the CPU is over its temperature limit
+    pub thermal_pause: bool,
+    /// Hold jobs at their slot while polite mode yields the host
+    pub polite: bool,
+    /// Combined size limit for the temp output and chunk directories; jobs
+    /// wait at their slot while usage is above it (0 = unlimited)
+    pub temp_quota_bytes: u64,
+    /// Directory of temporary encode outputs, counted towards the quota
+    /// with the chunk directories
+    pub temp_output_dir: Option<PathBuf>,
+}
+
+impl JobExecutorConfig {
+    /// Build the executor configuration from the daemon configuration
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_size_ratio: config.gates.max_size_ratio,
+            keep_original: config.gates.keep_original,
+            write_why_sidecars: config.scan.write_why_sidecars,
+            write_why_json: config.scan.write_why_json,
+            encode_limits: EncodeLimits::from_secs(
+                config.av1an.max_encode_secs,
+                config.av1an.stall_timeout_secs,
+            ),
+            svt_overrides: SvtOverrides::from_config(&config.av1an),
+            rename_template: config.output.rename_template.clone(),
+            on_collision: config.output.on_collision,
+            hardlinks: config.gates.hardlinks,
+            backup_template: config.output.backup_template.clone(),
+            backup_location: config.output.backup_location,
+            torrent: config.torrent.clone(),
+            checksum_sidecars: config.output.checksum_sidecars,
+            metadata_tags: config.output.metadata_tags,
+            run_as: RunAs::for_daemon(&config.run_as),
+            telemetry: config.telemetry.clone(),
+            job_state_dir: Some(config.paths.job_state_dir.clone()),
+            read_only: config.read_only,
+            max_replacements_per_day: config.gates.max_replacements_per_day,
+            require_approval: config.gates.require_approval,
+            replace_window: config.gates.replace_window,
+            comparison_stills: config.gates.comparison_stills,
+            validation: config.validation.clone(),
+            quotas: config.quotas.clone(),
+            farm: FarmNode::from_config(&config.farm),
+            staging: config.staging.clone(),
+            storage: config.storage.clone(),
+            pixel_format: config.pixel_format.clone(),
+            audio: AudioPolicy::from_config(&config.audio),
+            subtitles: config.subtitles.clone(),
+            drop_attached_pictures: config.gates.drop_attached_pictures,
+            scale_workers: config.av1an.scale_workers,
+            small_lane_slots: config.av1an.small_lane_slots,
+            big_lane_min_secs: config.av1an.big_lane_min_secs,
+            cgroups: config.cgroups.clone(),
+            triage: config.triage.clone(),
+            kill_switch_file: Some(config.kill_switch.file.clone()),
+            thermal_pause: config.thermal.max_cpu_temp_celsius.is_some()
+                && config.thermal.action == ThermalAction::Pause,
+            polite: config.polite.enabled,
+            temp_quota_bytes: config.paths.temp_quota_bytes,
+            temp_output_dir: Some(config.paths.temp_output_dir.clone()),
+        }
+    }
 }
 
 impl Default for JobExecutorConfig {
@@ -154,6 +530,44 @@ impl Default for JobExecutorConfig {
             max_size_ratio: 0.95,
             keep_original: false,
             write_why_sidecars: true,
+            write_why_json: false,
+            encode_limits: EncodeLimits::default(),
+            svt_overrides: SvtOverrides::default(),
+            rename_template: "{stem}.{ext}".to_string(),
+            on_collision: CollisionPolicy::default(),
+            hardlinks: HardlinkPolicy::default(),
+            backup_template: "{name}.orig.{ts}".to_string(),
+            backup_location: BackupLocation::default(),
+            torrent: TorrentConfig::default(),
+            checksum_sidecars: ChecksumSidecarPolicy::default(),
+            metadata_tags: false,
+            run_as: None,
+            telemetry: TelemetryConfig::default(),
+            job_state_dir: None,
+            read_only: false,
+            max_replacements_per_day: 0,
+            require_approval: false,
+            replace_window: None,
+            comparison_stills: 0,
+            validation: ValidationConfig::default(),
+            quotas: QuotaConfig::default(),
+            farm: None,
+            staging: StagingConfig::default(),
+            storage: StorageConfig::default(),
+            pixel_format: PixelFormatConfig::default(),
+            audio: AudioPolicy::default(),
+            subtitles: SubtitleConfig::default(),
+            drop_attached_pictures: false,
+            scale_workers: false,
+            small_lane_slots: 0,
+            big_lane_min_secs: 3600,
+            cgroups: CgroupConfig::default(),
+            triage: TriageConfig::default(),
+            kill_switch_file: None,
+            thermal_pause: false,
+            polite: false,
+            temp_quota_bytes: 0,
+            temp_output_dir: None,
         }
     }
 }
@@ -173,6 +587,76 @@ pub struct JobExecutor {
     temp_base_dir: PathBuf,
     /// Configuration for the pipeline
     config: JobExecutorConfig,
+    /// Workers per job for newly started encodes while thermally
+    /// throttled (0 = use the plan)
+    worker_cap: AtomicU32,
+    /// Cancellation tokens of jobs inside `execute`, plus queued jobs
+    /// cancelled before they got there
+    cancels: Mutex<HashMap<String, CancelToken>>,
+    /// Replacements left in the rolling day before encodes are held
+    replacement_budget: ReplacementBudget,
+    /// Separate slots for long and short files, used instead of `semaphore`
+    /// when configured
+    lanes: Option<Lanes>,
+    /// Exporter for job traces, when a collector is configured
+    telemetry: Option<Arc<Telemetry>>,
+    /// Jobs waiting for a slot, in the order they get one
+    wait_line: WaitLine,
+    /// What each library with a quota has used
+    quotas: LibraryQuotas,
+    /// Tool versions recorded on encode jobs, detected at the first encode
+    tool_versions: OnceCell<ToolVersions>,
+    /// Notified whenever a job finishes, for jobs waiting on dependencies
+    job_finished: Notify,
+    /// Queue pause and scan requests from the API
+    control: Arc<DaemonControl>,
+}
+
+/// How often a job waiting on dependencies checks them when no job of this
+/// executor finished, for dependencies cancelled before they got here
+const DEPENDENCY_RECHECK: Duration = Duration::from_secs(30);
+
+/// How often a job held at its slot checks whether it was cancelled and
+/// whether it still has to wait
+const PAUSE_RECHECK: Duration = Duration::from_secs(1);
+
+/// How often a job held by the temp quota measures temp usage again
+const TEMP_QUOTA_RECHECK: Duration = Duration::from_secs(5);
+
+/// Lines of Av1an output kept for classifying a failure, even when triage
+/// bundles keep fewer
+const FAILURE_OUTPUT_LINES: usize = 50;
+
+/// One slot for a long file and a few for short ones, each lane with its
+/// own share of the workers
+struct Lanes {
+    plan: LanePlan,
+    big: Arc<Semaphore>,
+    small: Arc<Semaphore>,
+}
+
+impl Lanes {
+    fn new(plan: LanePlan) -> Self {
+        Self {
+            plan,
+            big: Arc::new(Semaphore::new(1)),
+            small: Arc::new(Semaphore::new(plan.small_lane_slots as usize)),
+        }
+    }
+}
+
+/// Removes a job's cancellation token when `execute` returns
+struct CancelRegistration<'a> {
+    cancels: &'a Mutex<HashMap<String, CancelToken>>,
+    id: String,
+}
+
+impl Drop for CancelRegistration<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut cancels) = self.cancels.lock() {
+            cancels.remove(&self.id);
+        }
+    }
 }
 
 impl JobExecutor {
@@ -190,6 +674,16 @@ impl JobExecutor {
             metrics,
             temp_base_dir,
             config: JobExecutorConfig::default(),
+            worker_cap: AtomicU32::new(0),
+            cancels: Mutex::new(HashMap::new()),
+            replacement_budget: ReplacementBudget::default(),
+            lanes: None,
+            telemetry: None,
+            wait_line: WaitLine::new(),
+            quotas: LibraryQuotas::default(),
+            tool_versions: OnceCell::new(),
+            job_finished: Notify::new(),
+            control: Arc::new(DaemonControl::new()),
         }
     }
 
@@ -206,17 +700,65 @@ impl JobExecutor {
         temp_base_dir: PathBuf,
         config: JobExecutorConfig,
     ) -> Self {
-        let permits = plan.max_concurrent_jobs as usize;
+        let permits = if config.scale_workers {
+            plan.worker_budget() as usize
+        } else {
+            plan.max_concurrent_jobs as usize
+        };
+        let history = config
+            .job_state_dir
+            .as_deref()
+            .filter(|_| config.max_replacements_per_day > 0)
+            .and_then(|dir| load_jobs(dir).ok())
+            .unwrap_or_default();
+        let replacement_budget = ReplacementBudget::with_history(
+            config.max_replacements_per_day,
+            &history,
+            current_timestamp_ms(),
+        );
+        let lanes = plan.lanes(config.small_lane_slots).map(Lanes::new);
+        let telemetry = Telemetry::from_config(&config.telemetry).map(Arc::new);
+        let quotas = LibraryQuotas::new(config.quotas.clone());
         Self {
             semaphore: Arc::new(Semaphore::new(permits)),
             concurrency_plan: plan,
             metrics,
             temp_base_dir,
             config,
+            worker_cap: AtomicU32::new(0),
+            cancels: Mutex::new(HashMap::new()),
+            replacement_budget,
+            lanes,
+            telemetry,
+            wait_line: WaitLine::new(),
+            quotas,
+            tool_versions: OnceCell::new(),
+            job_finished: Notify::new(),
+            control: Arc::new(DaemonControl::new()),
+        }
+    }
+
+    /// Exporter shared with the daemon's metric export, if configured
+    pub fn telemetry(&self) -> Option<Arc<Telemetry>> {
+        self.telemetry.clone()
+    }
+
+    /// Controls shared with the API and the scan loop
+    pub fn control(&self) -> Arc<DaemonControl> {
+        self.control.clone()
+    }
+
+    /// Number of jobs that run at once: both lanes' slots when lanes are
+    /// configured, otherwise the planned concurrent jobs
+    pub fn job_slots(&self) -> usize {
+        match self.lanes {
+            Some(ref lanes) => 1 + lanes.plan.small_lane_slots as usize,
+            None => self.concurrency_plan.max_concurrent_jobs as usize,
         }
     }
 
-    /// Get the number of available permits (slots for concurrent jobs)
+    /// Get the number of available permits (slots for concurrent jobs, or
+    /// workers when workers are scaled to each file)
     pub fn available_permits(&self) -> usize {
         self.semaphore.available_permits()
     }
@@ -226,6 +768,200 @@ impl JobExecutor {
         &self.concurrency_plan
     }
 
+    /// Get the base directory holding per-job chunk directories
+    pub fn temp_base_dir(&self) -> &Path {
+        &self.temp_base_dir
+    }
+
+    /// Limit the Av1an workers of encodes started from now on
+    ///
+    /// `None` restores the planned worker count. Running encodes keep the
+    /// workers they started with.
+    pub fn set_worker_cap(&self, workers: Option<u32>) {
+        self.worker_cap.store(workers.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Workers a newly started encode gets, or `None` for the planned count
+    pub(crate) fn capped_workers(&self) -> Option<u32> {
+        match self.worker_cap.load(Ordering::Relaxed) {
+            0 => None,
+            cap => Some(cap.min(self.concurrency_plan.av1an_workers)),
+        }
+    }
+
+    /// Cancel a job
+    ///
+    /// A running encode has its Av1an process group killed and its temp
+    /// files removed; a job that has not started yet is dropped as soon as
+    /// it reaches the executor. Jobs already past encoding finish normally.
+    ///
+    /// # Returns
+    /// True if the job was inside the executor, false if it is still queued
+    pub fn cancel(&self, job_id: &str) -> bool {
+        let Ok(mut cancels) = self.cancels.lock() else {
+            return false;
+        };
+        let running = cancels.contains_key(job_id);
+        cancels.entry(job_id.to_string()).or_default().cancel();
+        running
+    }
+
+    /// Moves jobs in the queue: higher priorities start first.
+    ///
+    /// Applies to jobs still waiting for a slot as well as to jobs that have
+    /// not reached the executor yet.
+    pub fn set_queue_priorities<'a>(&self, priorities: impl IntoIterator<Item = (&'a str, i64)>) {
+        self.wait_line.set_priorities(priorities);
+    }
+
+    /// Token for `job_id`, created unless the job was cancelled while queued
+    fn register_cancel(&self, job_id: &str) -> (CancelToken, CancelRegistration<'_>) {
+        let token = self
+            .cancels
+            .lock()
+            .map(|mut cancels| cancels.entry(job_id.to_string()).or_default().clone())
+            .unwrap_or_default();
+        let registration = CancelRegistration {
+            cancels: &self.cancels,
+            id: job_id.to_string(),
+        };
+        (token, registration)
+    }
+
+    /// Replace the original of a job held for approval
+    ///
+    /// Approved replacements go ahead whatever the daily limit, but count
+    /// towards it.
+    ///
+    /// # Returns
+    /// Where the encoded file ended up
+    pub async fn approve(&self, job_id: &str) -> Result<PathBuf, JobError> {
+        let job = self.load_held(job_id)?;
+        self.remove_comparison(&job);
+        self.replace_held(job).await
+    }
+
+    /// Replace the originals of jobs waiting for the replace window
+    ///
+    /// Does nothing while the window is closed. Jobs go oldest first until
+    /// the window closes; once the daily limit is reached the rest are held
+    /// for approval instead.
+    ///
+    /// # Returns
+    /// The number of originals replaced
+    pub async fn replace_ready(&self) -> usize {
+        let Some(state_dir) = self.config.job_state_dir.as_deref() else {
+            return 0;
+        };
+        if !self.window_open() {
+            return 0;
+        }
+        let mut ready: Vec<ManagedJob> = match load_jobs(state_dir) {
+            Ok(jobs) => jobs
+                .into_iter()
+                .filter(|job| job.stage == JobStage::ReadyToReplace && job.is_active())
+                .collect(),
+            Err(e) => {
+                log_warn!("Warning: Failed to load jobs waiting for the replace window: {}", e);
+                return 0;
+            }
+        };
+        ready.sort_by_key(|job| job.updated_at);
+
+        let mut replaced = 0;
+        for managed in ready {
+            if !self.window_open() {
+                break;
+            }
+            let size_before = std::fs::metadata(&managed.input_path)
+                .map(|m| m.len())
+                .unwrap_or(0);
+            let job = Job::from_managed(&managed, size_before);
+            if !self.may_replace() {
+                let output_bytes = std::fs::metadata(&job.output_path)
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+                let why = "Daily replacement limit reached";
+                self.hold_for_approval(job, output_bytes, why).await;
+                continue;
+            }
+            match self.replace_held(job).await {
+                Ok(target) => {
+                    log_info!("Replaced {:?} in the replace window", target);
+                    replaced += 1;
+                }
+                Err(e) => log_warn!("Warning: Job {} failed to replace: {}", managed.id, e),
+            }
+        }
+        replaced
+    }
+
+    /// Replace the original of a job whose encode was kept in temp
+    async fn replace_held(&self, mut job: Job) -> Result<PathBuf, JobError> {
+        let output_bytes = std::fs::metadata(&job.output_path)
+            .map(|m| m.len())
+            .unwrap_or(0);
+        if output_bytes == 0 {
+            let error_msg = "Held output is missing or empty".to_string();
+            job.state = JobState::Failed(error_msg.clone());
+            self.record_state(&job).await;
+            self.increment_failed_jobs().await;
+            return Err(JobError::Validation(error_msg));
+        }
+
+        job.state = JobState::Replacing;
+        self.record_state(&job).await;
+        match self.replace_original(&job, output_bytes).await {
+            Ok(target) => {
+                job.state = JobState::Completed;
+                self.record_state(&job).await;
+                self.increment_completed_jobs().await;
+                self.set_job_size_after(&job.id, output_bytes).await;
+                let _ = std::fs::remove_file(&job.output_path);
+                Ok(target)
+            }
+            Err(replace_err) => {
+                job.state = JobState::Failed(replace_err.to_string());
+                self.record_state(&job).await;
+                self.increment_failed_jobs().await;
+                Err(JobError::Replacement(replace_err))
+            }
+        }
+    }
+
+    /// Discard the encode of a job held for approval
+    ///
+    /// The original was never touched while the job was held, so it stays as
+    /// it is. The file gets a skip marker like a size gate rejection, so the
+    /// scanner does not encode it again until it is requeued.
+    pub async fn reject(&self, job_id: &str) -> Result<(), JobError> {
+        let mut job = self.load_held(job_id)?;
+        let _ = std::fs::remove_file(&job.output_path);
+        self.remove_comparison(&job);
+
+        let skip_reason = SkipReason::new(SkipCode::RejectedOnReview, "Encode rejected on review");
+        job.state = JobState::Skipped(skip_reason.message.clone());
+        self.record_state(&job).await;
+        self.increment_skipped_jobs().await;
+        record_skip(&self.metrics, skip_reason.code).await;
+        self.write_skip_files(&job, &skip_reason)
+    }
+
+    /// Load the persisted job `job_id`, provided it is awaiting approval
+    fn load_held(&self, job_id: &str) -> Result<Job, JobError> {
+        let not_held = || JobError::NotAwaitingApproval(job_id.to_string());
+        let state_dir = self.config.job_state_dir.as_deref().ok_or_else(not_held)?;
+        let managed = load_job(state_dir, job_id)
+            .map_err(JobError::JobStore)?
+            .filter(|job| job.stage == JobStage::AwaitingApproval && job.is_active())
+            .ok_or_else(not_held)?;
+
+        let size_before = std::fs::metadata(&managed.input_path)
+            .map(|m| m.len())
+            .unwrap_or(0);
+        Ok(Job::from_managed(&managed, size_before))
+    }
+
     /// Acquire a permit for job execution
     ///
     /// This will wait until a permit is available if all slots are in use.
@@ -244,6 +980,83 @@ impl JobExecutor {
         self.semaphore.clone().try_acquire_owned().ok()
     }
 
+    /// Acquire permits for `workers` Av1an workers
+    ///
+    /// Only meaningful when workers are scaled, where each permit stands for
+    /// one worker of the shared budget.
+    async fn acquire_workers(&self, workers: u32) -> OwnedSemaphorePermit {
+        let budget = self.concurrency_plan.worker_budget().max(1);
+        self.semaphore
+            .clone()
+            .acquire_many_owned(workers.clamp(1, budget))
+            .await
+            .expect("semaphore should not be closed")
+    }
+
+    /// Wait for a slot for `job`
+    ///
+    /// With lanes the job waits for a slot in the lane its length puts it
+    /// in; a file of unknown length counts as long. Otherwise it takes a job
+    /// permit, or one permit per worker when workers are scaled.
+    ///
+    /// # Returns
+    /// The permit, and the workers of the lane if the job is in one
+    async fn acquire_slot(
+        &self,
+        job: &Job,
+        scaled_workers: Option<u32>,
+    ) -> (OwnedSemaphorePermit, Option<u32>) {
+        // Only the job at the front of the line asks for a slot, so the
+        // semaphores hand slots out in queue order
+        let lane = match self.lanes {
+            Some(_) if self.is_long(job) => Lane::Big,
+            Some(_) => Lane::Small,
+            None => Lane::Shared,
+        };
+        let place = self.wait_line.join(&job.id, lane);
+        self.wait_line.wait_turn(&place).await;
+
+        if let Some(ref lanes) = self.lanes {
+            let (lane, workers) = if lane == Lane::Big {
+                (&lanes.big, lanes.plan.big_lane_workers)
+            } else {
+                (&lanes.small, lanes.plan.small_lane_workers)
+            };
+            let permit = lane
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("semaphore should not be closed");
+            return (permit, Some(workers));
+        }
+
+        let permit = match scaled_workers {
+            Some(workers) => self.acquire_workers(workers).await,
+            None => self.acquire_permit().await,
+        };
+        (permit, None)
+    }
+
+    /// Whether `job` belongs in the long-file lane
+    ///
+    /// Remuxes are quick whatever the length of the file.
+    fn is_long(&self, job: &Job) -> bool {
+        job.kind == JobKind::Encode
+            && (job.duration_secs <= 0.0
+                || job.duration_secs >= self.config.big_lane_min_secs as f64)
+    }
+
+    /// Workers planned for `job` when workers are scaled to each file
+    fn scaled_workers(&self, job: &Job) -> Option<u32> {
+        if !self.config.scale_workers || job.kind != JobKind::Encode {
+            return None;
+        }
+        let plan = self
+            .concurrency_plan
+            .plan_job(job.duration_secs, job.size_in_bytes_before);
+        Some(plan.av1an_workers)
+    }
+
 
     /// Execute a job through the encoding pipeline
     ///
@@ -264,33 +1077,141 @@ impl JobExecutor {
     /// * `Ok(Job)` - Job completed successfully with updated state
     /// * `Err(JobError)` - Job failed with error details
     pub async fn execute(&self, mut job: Job) -> Result<Job, JobError> {
-        // Acquire permit to respect max_concurrent_jobs limit (Requirement 5.5)
-        let _permit = self.acquire_permit().await;
+        let (cancel, _registration) = self.register_cancel(&job.id);
+
+        // Jobs it depends on finish first, whatever their outcome
+        self.wait_for_dependencies(&job, &cancel).await;
+        if cancel.is_cancelled() {
+            return self.finish_cancelled(job, None).await;
+        }
+
+        // A library over its quota waits here, before lining up for a slot,
+        // so jobs of other libraries go first
+        let _quota = self
+            .quotas
+            .admit(&job.id, &job.input_path, job.size_in_bytes_before)
+            .await;
+
+        // Acquire permit to respect max_concurrent_jobs limit (Requirement 5.5);
+        // with scaled workers the job takes one permit per worker instead, and
+        // with lanes a slot in the lane for its length
+        let scaled_workers = self.scaled_workers(&job);
+        let (_permit, lane_workers) = self.acquire_slot(&job, scaled_workers).await;
+
+        // A paused queue, the kill switch, a hot CPU, a busy host or full temp
+        // directories hold the job with its slot, so it stays first and the
+        // jobs behind it wait too
+        self.wait_for_dispatch(&job, &cancel).await;
+        if cancel.is_cancelled() {
+            return self.finish_cancelled(job, None).await;
+        }
+        // Another daemon sharing the library may have started on the file
+        let claim = match self.claim(&job, &cancel) {
+            Ok(claim) => claim,
+            Err(holder) => return self.finish_claimed_elsewhere(job, holder).await,
+        };
 
         // Update job state to encoding
         job.state = JobState::Encoding;
-        self.update_job_metrics(&job).await;
+        if job.kind == JobKind::Encode {
+            // Workers asked for with the job replace the planned count, but
+            // not the thermal cap or the readers its storage allows
+            let planned = match job.overrides.workers {
+                Some(workers) => [Some(workers), None],
+                None => [lane_workers, scaled_workers],
+            };
+            job.worker_limit = planned
+                .into_iter()
+                .chain([self.capped_workers(), self.reader_cap(&job)])
+                .flatten()
+                .min();
+        }
+        self.record_state(&job).await;
+
+        // Sources on slow or remote storage are read from a local copy
+        let staged = match self.stage(&job).await {
+            Ok(staged) => staged,
+            Err(e) => {
+                job.state = JobState::Failed(format!("Failed to stage source locally: {}", e));
+                self.record_state(&job).await;
+                self.increment_failed_jobs().await;
+                return Err(JobError::Staging(e));
+            }
+        };
+        job.staged_input = staged.as_ref().map(|staged| staged.path().to_path_buf());
+        job.staged_digest = staged.as_ref().map(|staged| staged.digest().to_string());
+
+        if job.kind == JobKind::Remux {
+            return self.execute_remux(job, claim.as_ref()).await;
+        }
 
         // Create temp chunks directory (Requirement 5.1)
         let temp_chunks_dir = self.temp_base_dir.join(format!("chunks_{}", job.id));
-        std::fs::create_dir_all(&temp_chunks_dir).map_err(JobError::TempDirCreation)?;
+        if let Err(e) = std::fs::create_dir_all(&temp_chunks_dir) {
+            job.state = JobState::Failed(format!("Failed to create temp directory: {}", e));
+            self.record_state(&job).await;
+            self.increment_failed_jobs().await;
+            return Err(JobError::TempDirCreation(e));
+        }
+        self.hand_over_dirs(&job, Some(&temp_chunks_dir));
 
         // Build encoding parameters
-        let params = Av1anEncodeParams::new(
-            job.input_path.clone(),
+        let mut plan = self.concurrency_plan.clone();
+        if let Some(workers) = job.worker_limit {
+            plan.av1an_workers = workers;
+        }
+        let mut params = Av1anEncodeParams::new(
+            job.source_path().to_path_buf(),
             job.output_path.clone(),
             temp_chunks_dir.clone(),
-            self.concurrency_plan.clone(),
+            plan,
         );
-
-        // Run Av1an encoding (Requirements 5.2, 5.3)
-        let encode_result = tokio::task::spawn_blocking(move || run_av1an(&params)).await;
+        params.profile = EncodeProfile::for_source(job.source_type);
+        params.svt_overrides = job.svt_overrides(self.config.svt_overrides);
+        params.run_as = self.config.run_as;
+        params.cgroup = self.encoder_cgroup(&job, &params.concurrency);
+        let tail_lines = self.config.triage.output_lines.max(FAILURE_OUTPUT_LINES);
+        let tail = Arc::new(OutputTail::new(tail_lines));
+        job.output_tail = Some(tail.clone());
+        params.output_tail = Some(tail);
+        params.chunk_method = ChunkMethod::for_storage(self.source_storage(&job));
+        params.pix_format =
+            output_pix_format(job.pix_fmt.as_deref(), &self.config.pixel_format).to_string();
+        let audio_plan = self.config.audio.plan(&job.audio_streams);
+        let mut stream_args = Vec::new();
+        if self.config.drop_attached_pictures && !job.attached_pictures.is_empty() {
+            stream_args.push(drop_video_args(&job.attached_pictures));
+        }
+        let dropped_subtitles = self.extract_subtitles(&job).await;
+        if !dropped_subtitles.is_empty() {
+            stream_args.push(drop_subtitle_args(&dropped_subtitles));
+        }
+        stream_args.push(audio_plan.ffmpeg_args());
+        params.audio_params = stream_args.join(" ");
+        let custom = job.overrides.crf.is_some() || job.overrides.preset.is_some();
+        job.settings = Some(EncodeSettings::new(
+            &params.video_params(),
+            &params.pix_format,
+            job.source_type,
+            custom,
+        ));
+        self.record_encode_command(&job, &params).await;
+
+        // Run Av1an encoding (Requirements 5.2, 5.3), killing it if it
+        // runs too long or stops making progress
+        let limits = self.config.encode_limits;
+        let encode_cancel = cancel.clone();
+        let encode_result = tokio::task::spawn_blocking(move || {
+            run_av1an_cancellable(&params, &limits, &encode_cancel)
+        })
+        .await;
 
         match encode_result {
             Ok(Ok(())) => {
                 // Encoding succeeded, proceed to validation (Requirement 5.2)
                 job.state = JobState::Validating;
-                self.update_job_metrics(&job).await;
+                self.record_state(&job).await;
+                self.tag_output(&job).await;
 
                 // Validate the output file exists and has content
                 let output_metadata = match std::fs::metadata(&job.output_path) {
@@ -298,7 +1219,7 @@ impl JobExecutor {
                     Err(e) => {
                         let error_msg = format!("Output file not found: {}", e);
                         job.state = JobState::Failed(error_msg.clone());
-                        self.update_job_metrics(&job).await;
+                        self.record_state(&job).await;
                         self.increment_failed_jobs().await;
                         let _ = std::fs::remove_dir_all(&temp_chunks_dir);
                         return Err(JobError::Validation(error_msg));
@@ -309,7 +1230,30 @@ impl JobExecutor {
                 if output_bytes == 0 {
                     let error_msg = "Output file is empty".to_string();
                     job.state = JobState::Failed(error_msg.clone());
-                    self.update_job_metrics(&job).await;
+                    self.record_state(&job).await;
+                    self.increment_failed_jobs().await;
+                    let _ = std::fs::remove_dir_all(&temp_chunks_dir);
+                    let _ = std::fs::remove_file(&job.output_path);
+                    return Err(JobError::Validation(error_msg));
+                }
+
+                // Score sampled frames against the original; low scores
+                // only flag the encode
+                self.check_quality(&job).await;
+
+                // Black or blocky stretches the source does not have, audio
+                // tracks other than the audio plan's, or audio out of sync
+                // with the source, fail the encode
+                let problem = match self.check_frames(&job).await {
+                    Some(problem) => Some(problem),
+                    None => match self.check_audio_tracks(&job, &audio_plan).await {
+                        Some(problem) => Some(problem),
+                        None => self.check_audio_sync(&job, &audio_plan).await,
+                    },
+                };
+                if let Some(error_msg) = problem {
+                    job.state = JobState::Failed(error_msg.clone());
+                    self.record_state(&job).await;
                     self.increment_failed_jobs().await;
                     let _ = std::fs::remove_dir_all(&temp_chunks_dir);
                     let _ = std::fs::remove_file(&job.output_path);
@@ -318,30 +1262,52 @@ impl JobExecutor {
 
                 // Size gate check (Requirements 16.1, 16.2, 16.3, 16.4)
                 job.state = JobState::SizeGating;
-                self.update_job_metrics(&job).await;
+                self.record_state(&job).await;
 
-                let size_gate_result = check_size_gate(
-                    job.size_in_bytes_before,
-                    output_bytes,
-                    self.config.max_size_ratio,
-                );
+                let size_gate_result = if job.forced || job.overrides.skip_size_gate {
+                    SizeGateResult::Accept
+                } else {
+                    check_size_gate(
+                        job.size_in_bytes_before,
+                        output_bytes,
+                        self.config.max_size_ratio,
+                    )
+                };
 
                 match size_gate_result {
                     SizeGateResult::Accept => {
+                        if !self.needs_review() && !self.window_open() {
+                            let _ = std::fs::remove_dir_all(&temp_chunks_dir);
+                            return Ok(self.wait_for_window(job, output_bytes).await);
+                        }
+                        let why = if self.needs_review() {
+                            Some("Approval required")
+                        } else if !self.may_replace() {
+                            Some("Daily replacement limit reached")
+                        } else {
+                            None
+                        };
+                        if let Some(why) = why {
+                            let _ = std::fs::remove_dir_all(&temp_chunks_dir);
+                            return Ok(self.hold_for_approval(job, output_bytes, why).await);
+                        }
+
+                        // A share that stalled past the lease may have let
+                        // another daemon take the file over
+                        if claim.as_ref().is_some_and(|claim| !claim.confirm()) {
+                            return self.finish_claim_lost(job, Some(&temp_chunks_dir)).await;
+                        }
+
                         // Size gate passed, proceed to replacement
                         job.state = JobState::Replacing;
-                        self.update_job_metrics(&job).await;
+                        self.record_state(&job).await;
 
                         // Atomic file replacement (Requirements 17.1-17.6)
-                        match atomic_replace(
-                            &job.input_path,
-                            &job.output_path,
-                            self.config.keep_original,
-                        ) {
-                            Ok(()) => {
+                        match self.replace_original(&job, output_bytes).await {
+                            Ok(_) => {
                                 // Mark as completed (Requirement 5.4)
                                 job.state = JobState::Completed;
-                                self.update_job_metrics(&job).await;
+                                self.record_state(&job).await;
                                 self.increment_completed_jobs().await;
 
                                 // Update size_in_bytes_after for metrics
@@ -357,7 +1323,7 @@ impl JobExecutor {
                                 // Replacement failed (Requirement 17.6)
                                 let error_msg = replace_err.to_string();
                                 job.state = JobState::Failed(error_msg);
-                                self.update_job_metrics(&job).await;
+                                self.record_state(&job).await;
                                 self.increment_failed_jobs().await;
 
                                 // Preserve temp files for manual inspection
@@ -373,35 +1339,32 @@ impl JobExecutor {
                         ratio,
                     } => {
                         // Size gate rejected (Requirement 16.3)
-                        let skip_reason = format!(
-                            "Size gate rejected: output {} bytes ({:.1}%) >= original {} bytes * {:.2}",
-                            output_bytes,
-                            ratio * 100.0,
-                            original_bytes,
-                            self.config.max_size_ratio
-                        );
-
-                        job.state = JobState::Skipped(skip_reason.clone());
-                        self.update_job_metrics(&job).await;
+                        let skip_reason = SkipReason::new(
+                            SkipCode::SizeGateRejected,
+                            format!(
+                                "Size gate rejected: output {} bytes ({:.1}%) >= original {} bytes * {:.2}",
+                                output_bytes,
+                                ratio * 100.0,
+                                original_bytes,
+                                self.config.max_size_ratio
+                            ),
+                        )
+                        .with_threshold("original_bytes", original_bytes as f64)
+                        .with_threshold("output_bytes", output_bytes as f64)
+                        .with_threshold("max_size_ratio", self.config.max_size_ratio as f64);
+
+                        job.state = JobState::Skipped(skip_reason.message.clone());
+                        self.record_state(&job).await;
                         self.increment_skipped_jobs().await;
+                        record_skip(&self.metrics, skip_reason.code).await;
 
-                        // Delete temp output (Requirement 16.3)
+                        // Delete temp output (Requirement 16.3) and the
+                        // temp directory before the skip files, which may fail
                         let _ = std::fs::remove_file(&job.output_path);
-
-                        // Create skip markers (Requirements 18.1, 18.2)
-                        write_skip_marker(&job.input_path)
-                            .map_err(JobError::SkipMarkerFailed)?;
-                        
-                        // Write why sidecar if enabled
-                        let _ = write_why_sidecar(
-                            &job.input_path,
-                            &skip_reason,
-                            self.config.write_why_sidecars,
-                        );
-
-                        // Clean up temp directory
                         let _ = std::fs::remove_dir_all(&temp_chunks_dir);
 
+                        self.write_skip_files(&job, &skip_reason)?;
+
                         Err(JobError::SizeGateRejected {
                             original_bytes,
                             output_bytes,
@@ -410,14 +1373,23 @@ impl JobExecutor {
                     }
                 }
             }
+            Ok(Err(EncodeError::Cancelled)) if claim.as_ref().is_some_and(ClaimLease::is_lost) => {
+                self.finish_claim_lost(job, Some(&temp_chunks_dir)).await
+            }
+            Ok(Err(EncodeError::Cancelled)) => {
+                self.finish_cancelled(job, Some(&temp_chunks_dir)).await
+            }
             Ok(Err(encode_err)) => {
-                // Encoding failed (Requirement 5.3)
+                // Encoding failed, timed out, or stalled (Requirement 5.3)
+                let output = job.output_tail.as_ref().map(|tail| tail.lines()).unwrap_or_default();
+                self.record_failure_kind(&mut job, classify_failure(&encode_err, &output)).await;
                 job.state = JobState::Failed(encode_err.to_string());
-                self.update_job_metrics(&job).await;
+                self.record_state(&job).await;
                 self.increment_failed_jobs().await;
 
-                // Clean up temp directory
+                // Clean up temp directory and any partial output
                 let _ = std::fs::remove_dir_all(&temp_chunks_dir);
+                let _ = std::fs::remove_file(&job.output_path);
 
                 Err(JobError::Encode(encode_err))
             }
@@ -425,7 +1397,7 @@ impl JobExecutor {
                 // Task panicked
                 let error_msg = format!("Encoding task panicked: {}", join_err);
                 job.state = JobState::Failed(error_msg.clone());
-                self.update_job_metrics(&job).await;
+                self.record_state(&job).await;
                 self.increment_failed_jobs().await;
 
                 // Clean up temp directory
@@ -436,72 +1408,1107 @@ impl JobExecutor {
         }
     }
 
-    /// Update job metrics in shared state
-    async fn update_job_metrics(&self, job: &Job) {
-        let mut metrics = self.metrics.write().await;
-        let job_metrics = job.to_metrics(self.concurrency_plan.av1an_workers);
-
-        // Find and update existing job metrics, or add new one
-        if let Some(existing) = metrics.jobs.iter_mut().find(|j| j.id == job.id) {
-            *existing = job_metrics;
-        } else {
-            metrics.jobs.push(job_metrics);
+    /// Record a cancelled job and remove its partial output and chunks
+    async fn finish_cancelled(
+        &self,
+        mut job: Job,
+        temp_chunks_dir: Option<&Path>,
+    ) -> Result<Job, JobError> {
+        job.state = JobState::Cancelled;
+        self.record_state(&job).await;
+        self.metrics.write().await.cancelled_jobs += 1;
+
+        if let Some(dir) = temp_chunks_dir {
+            let _ = std::fs::remove_dir_all(dir);
         }
+        let _ = std::fs::remove_file(&job.output_path);
 
-        // Update running jobs count
-        metrics.running_jobs = metrics
-            .jobs
-            .iter()
-            .filter(|j| j.stage == "encoding" || j.stage == "validating" || j.stage == "replacing")
-            .count();
+        Err(JobError::Cancelled)
     }
 
-    /// Increment completed jobs counter
-    async fn increment_completed_jobs(&self) {
-        let mut metrics = self.metrics.write().await;
-        metrics.completed_jobs += 1;
+    /// Claim the input of `job` for this daemon when daemons share libraries
+    ///
+    /// A claim that cannot be written is logged and the job goes ahead
+    /// unclaimed; read-only mode writes no claims. Losing the claim to
+    /// another daemon later cancels the job through `cancel`.
+    ///
+    /// # Errors
+    /// The live claim of the daemon that holds the file
+    fn claim(&self, job: &Job, cancel: &CancelToken) -> Result<Option<ClaimLease>, Claim> {
+        let Some(node) = &self.config.farm else {
+            return Ok(None);
+        };
+        if self.config.read_only {
+            return Ok(None);
+        }
+        match node.try_claim(&job.input_path, &job.id, unix_now()) {
+            Ok(None) => Ok(Some(ClaimLease::hold(
+                node.clone(),
+                job.input_path.clone(),
+                cancel.clone(),
+            ))),
+            Ok(Some(holder)) => Err(holder),
+            Err(e) => {
+                log_warn!("Warning: Failed to claim {:?}, encoding it unclaimed: {}", job.input_path, e);
+                Ok(None)
+            }
+        }
     }
 
-    /// Increment failed jobs counter
-    async fn increment_failed_jobs(&self) {
-        let mut metrics = self.metrics.write().await;
-        metrics.failed_jobs += 1;
+    /// Waits until no job that `job` depends on is pending or running, or
+    /// until `job` is cancelled
+    async fn wait_for_dependencies(&self, job: &Job, cancel: &CancelToken) {
+        let Some(state_dir) = self.config.job_state_dir.as_deref() else {
+            return;
+        };
+        let mut waiting = false;
+        loop {
+            let finished = self.job_finished.notified();
+            tokio::pin!(finished);
+            finished.as_mut().enable();
+            let unfinished = unfinished_dependencies(state_dir, &job.id).unwrap_or_else(|e| {
+                log_warn!("Warning: Failed to check dependencies of job {}: {}", job.id, e);
+                Vec::new()
+            });
+            if unfinished.is_empty() || cancel.is_cancelled() {
+                return;
+            }
+            if !waiting {
+                log_info!("Job {} waits for {} to finish", job.id, unfinished.join(", "));
+                waiting = true;
+            }
+            let _ = tokio::time::timeout(DEPENDENCY_RECHECK, finished).await;
+        }
     }
 
-    /// Increment skipped jobs counter (for size gate rejections)
-    async fn increment_skipped_jobs(&self) {
-        let mut metrics = self.metrics.write().await;
-        // Skipped jobs are counted as failed in the aggregate metrics
-        metrics.failed_jobs += 1;
+    /// Holds a job that has its slot until nothing holds back new work in
+    /// one pass over the holds, or until the job is cancelled
+    async fn wait_for_dispatch(&self, job: &Job, cancel: &CancelToken) {
+        loop {
+            let mut held = self.wait_for_resume(job, cancel).await;
+            held |= self.wait_for_kill_switch(job, cancel).await;
+            held |= self.wait_for_thermal_headroom(job, cancel).await;
+            held |= self.wait_for_quiet_host(job, cancel).await;
+            held |= self.wait_for_temp_quota(job, cancel).await;
+            if !held || cancel.is_cancelled() {
+                return;
+            }
+        }
     }
 
-    /// Update the size_in_bytes_after for a completed job
-    async fn update_job_size_after(&self, job_id: &str, size_bytes: u64) {
-        let mut metrics = self.metrics.write().await;
-        if let Some(job_metrics) = metrics.jobs.iter_mut().find(|j| j.id == job_id) {
-            job_metrics.size_in_bytes_after = size_bytes;
+    /// Waits while the queue is paused
+    ///
+    /// # Returns
+    /// True if the job had to wait
+    async fn wait_for_resume(&self, job: &Job, cancel: &CancelToken) -> bool {
+        if !self.control.is_paused() {
+            return false;
         }
-        metrics.total_bytes_encoded += size_bytes;
+        log_info!("Job {} waits for the queue to be resumed", job.id);
+        while self.control.is_paused() && !cancel.is_cancelled() {
+            let _ = tokio::time::timeout(PAUSE_RECHECK, self.control.wait_until_resumed()).await;
+        }
+        true
     }
-}
 
+    /// Waits while the kill switch file exists
+    ///
+    /// # Returns
+    /// True if the job had to wait
+    async fn wait_for_kill_switch(&self, job: &Job, cancel: &CancelToken) -> bool {
+        let Some(file) = self.config.kill_switch_file.as_deref() else {
+            return false;
+        };
+        if !kill_switch_engaged(file) {
+            return false;
+        }
+        log_info!("Job {} waits for the kill switch {:?} to be removed", job.id, file);
+        while kill_switch_engaged(file) && !cancel.is_cancelled() {
+            tokio::time::sleep(PAUSE_RECHECK).await;
+        }
+        true
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::metrics::new_shared_metrics;
-    use std::time::Duration;
+    /// Waits while the CPU is over its temperature limit, if the thermal
+    /// action is `pause`
+    ///
+    /// # Returns
+    /// True if the job had to wait
+    async fn wait_for_thermal_headroom(&self, job: &Job, cancel: &CancelToken) -> bool {
+        if !self.config.thermal_pause || !self.metrics.read().await.thermal.throttled {
+            return false;
+        }
+        log_info!("Job {} waits for the CPU to cool down", job.id);
+        while self.metrics.read().await.thermal.throttled && !cancel.is_cancelled() {
+            tokio::time::sleep(PAUSE_RECHECK).await;
+        }
+        true
+    }
 
-    fn create_test_plan(max_concurrent_jobs: u32) -> ConcurrencyPlan {
-        ConcurrencyPlan {
-            total_cores: 32,
-            target_threads: 28,
-            av1an_workers: 8,
-            max_concurrent_jobs,
+    /// Waits while polite mode yields the host to interactive use
+    ///
+    /// # Returns
+    /// True if the job had to wait
+    async fn wait_for_quiet_host(&self, job: &Job, cancel: &CancelToken) -> bool {
+        if !self.config.polite || !self.metrics.read().await.polite.yielding {
+            return false;
         }
+        log_info!("Job {} waits for the host to be quiet", job.id);
+        while self.metrics.read().await.polite.yielding && !cancel.is_cancelled() {
+            tokio::time::sleep(PAUSE_RECHECK).await;
+        }
+        true
     }
 
-    fn create_test_job(id: &str) -> Job {
+    /// Waits while temp usage is over the configured quota
+    ///
+    /// Running jobs keep going and are expected to free space as they finish
+    /// or as orphaned chunk directories are collected.
+    ///
+    /// # Returns
+    /// True if the job had to wait
+    async fn wait_for_temp_quota(&self, job: &Job, cancel: &CancelToken) -> bool {
+        let quota = self.config.temp_quota_bytes;
+        if quota == 0 {
+            return false;
+        }
+        let output_dir = self
+            .config
+            .temp_output_dir
+            .as_deref()
+            .unwrap_or(&self.temp_base_dir);
+
+        let mut deferred = false;
+        loop {
+            let usage = measure_temp_usage(&self.temp_base_dir, output_dir).await;
+            {
+                let mut m = self.metrics.write().await;
+                m.temp.bytes_used = usage;
+                m.temp.quota_bytes = quota;
+                m.temp.jobs_deferred = over_quota(usage, quota);
+            }
+            if !over_quota(usage, quota) || cancel.is_cancelled() {
+                return deferred;
+            }
+            if !deferred {
+                log_info!(
+                    "Temp usage {} bytes exceeds quota of {} bytes, job {} waits",
+                    usage, quota, job.id
+                );
+                deferred = true;
+            }
+            let recheck = tokio::time::Instant::now() + TEMP_QUOTA_RECHECK;
+            while tokio::time::Instant::now() < recheck && !cancel.is_cancelled() {
+                tokio::time::sleep(PAUSE_RECHECK).await;
+            }
+        }
+    }
+
+    /// cgroup for the encode of `job` with the workers of `plan`, if
+    /// `[cgroups]` is enabled
+    ///
+    /// Without one the encode still runs, limited only by its workers.
+    fn encoder_cgroup(&self, job: &Job, plan: &ConcurrencyPlan) -> Option<Arc<EncoderCgroup>> {
+        if !self.config.cgroups.enabled {
+            return None;
+        }
+        match EncoderCgroup::create(&self.config.cgroups, plan, &format!("job-{}", job.id)) {
+            Ok(cgroup) => Some(Arc::new(cgroup)),
+            Err(e) => {
+                log_warn!(
+                    "Warning: Job {} runs without a cgroup; cannot create it under {:?}: {}",
+                    job.id,
+                    self.config.cgroups.parent,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Storage Av1an reads the source of `job` from; a staged copy is local
+    fn source_storage(&self, job: &Job) -> StorageKind {
+        if self.config.staging.stages(&job.input_path) {
+            StorageKind::Local
+        } else {
+            self.config.storage.kind_for(&job.input_path)
+        }
+    }
+
+    /// Workers that may read the source of `job` at once, if its storage
+    /// limits them
+    fn reader_cap(&self, job: &Job) -> Option<u32> {
+        let max_readers = self.config.storage.hdd_max_readers;
+        (self.source_storage(job) == StorageKind::Hdd && max_readers > 0).then_some(max_readers)
+    }
+
+    /// Copy the input of `job` to local scratch if its library is staged
+    async fn stage(&self, job: &Job) -> std::io::Result<Option<StagedSource>> {
+        if !self.config.staging.stages(&job.input_path) {
+            return Ok(None);
+        }
+        let dir = self
+            .config
+            .staging
+            .dir
+            .clone()
+            .unwrap_or_else(|| self.temp_base_dir.join("staging"))
+            .join(&job.id);
+        let source = job.input_path.clone();
+        log_info!("Staging {:?} in {:?}", source, dir);
+        let staged = tokio::task::spawn_blocking(move || stage_source(&source, &dir))
+            .await
+            .map_err(std::io::Error::other)??;
+        Ok(Some(staged))
+    }
+
+    /// Drop a job whose file another daemon is working on
+    ///
+    /// No skip marker is written: the file is looked at again once the other
+    /// daemon is done with it.
+    async fn finish_claimed_elsewhere(&self, mut job: Job, holder: Claim) -> Result<Job, JobError> {
+        log_info!(
+            "Job {} dropped: {:?} is claimed by {} (job {})",
+            job.id, job.input_path, holder.node, holder.job_id
+        );
+        job.state = JobState::Skipped(format!("Claimed by {}", holder.node));
+        self.record_state(&job).await;
+        Err(JobError::ClaimedElsewhere(holder.node))
+    }
+
+    /// Drop a job whose claim another daemon took over while it ran
+    ///
+    /// The original is left to that daemon, which is encoding it now, and
+    /// this job's output and chunks are removed.
+    async fn finish_claim_lost(
+        &self,
+        mut job: Job,
+        temp_chunks_dir: Option<&Path>,
+    ) -> Result<Job, JobError> {
+        let holder = read_claim(&job.input_path)
+            .map_or_else(|| "another daemon".to_string(), |claim| claim.node);
+        log_warn!(
+            "Warning: Job {} lost its claim on {:?} to {}; discarding its output",
+            job.id, job.input_path, holder
+        );
+        job.state = JobState::Skipped(format!("Claim lost to {}", holder));
+        self.record_state(&job).await;
+
+        if let Some(dir) = temp_chunks_dir {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+        let _ = std::fs::remove_file(&job.output_path);
+        Err(JobError::ClaimedElsewhere(holder))
+    }
+
+    /// Final location of the job's output, from the rename template and the
+    /// extension of the temp output
+    fn output_target(&self, job: &Job) -> Result<PathBuf, ReplaceError> {
+        let ext = job
+            .output_path
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "mkv".to_string());
+        resolve_output_path(
+            &job.input_path,
+            &self.config.rename_template,
+            &ext,
+            self.config.on_collision,
+        )
+    }
+
+    /// Put the job's output in place of its original
+    ///
+    /// A replacement that succeeds counts against the daily budget. In
+    /// read-only mode the original is left alone and the replacement that
+    /// would have happened is only logged; the output is then cleaned up
+    /// like any other completed job's.
+    ///
+    /// # Returns
+    /// Where the encoded file ended up
+    async fn replace_original(&self, job: &Job, output_bytes: u64) -> Result<PathBuf, ReplaceError> {
+        let target = self.output_target(job)?;
+        if self.config.read_only {
+            log_info!(
+                "Read-only: would replace {:?} ({} bytes) with {:?} ({} bytes)",
+                job.input_path, job.size_in_bytes_before, target, output_bytes
+            );
+            return Ok(target);
+        }
+
+        // The encode read a staged copy, so the original must still be
+        // what was copied
+        if let Some(digest) = job.staged_digest.clone() {
+            let original = job.input_path.clone();
+            tokio::task::spawn_blocking(move || verify_source(&original, &digest))
+                .await
+                .map_err(std::io::Error::other)
+                .and_then(|result| result)
+                .map_err(ReplaceError::SourceChanged)?;
+        }
+
+        // Torrents seeding the original must not serve it while it changes
+        let paused = self.pause_seeding(&job.input_path).await?;
+
+        let links = hard_link_count(&job.input_path);
+        if links > 1 {
+            log_info!(
+                "{:?} has {} hard links; the others keep the original",
+                job.input_path, links
+            );
+        }
+        let keep = self.config.keep_original;
+        let copy = links > 1 && self.config.hardlinks == HardlinkPolicy::Copy;
+        let replaced = render_backup_path(
+            &job.input_path,
+            &self.config.backup_template,
+            self.config.backup_location,
+            (current_timestamp_ms() / 1000) as u64,
+        )
+        .and_then(|backup| {
+            // A staged job's output is checked once more on its way back
+            let verify = job.staged_input.is_some();
+            replace_with_backup_verified(
+                &job.input_path,
+                &job.output_path,
+                &target,
+                &backup,
+                keep,
+                copy,
+                verify,
+            )?;
+            Ok(backup)
+        });
+        if let Some((client, hashes)) = paused {
+            // The recheck tells the client the data changed before it seeds again
+            let resumed = match client.recheck(&hashes).await {
+                Ok(()) => client.resume(&hashes).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = resumed {
+                log_warn!(
+                    "Warning: Failed to recheck and resume torrents {:?}: {}",
+                    hashes, e
+                );
+            }
+        }
+        let backup = replaced?;
+        self.replacement_budget.record(current_timestamp_ms());
+        self.finish_replacement(job, &target, keep.then_some(backup)).await;
+        Ok(target)
+    }
+
+    /// Run the steps that follow a replacement for `job`, whose swap was
+    /// interrupted by a restart and then finished from its marker
+    pub async fn finish_repaired_swap(&self, job: &Job, intent: &SwapIntent) {
+        let kept_backup = intent.keep_original.then(|| intent.backup.clone());
+        self.finish_replacement(job, &intent.target, kept_backup).await;
+    }
+
+    /// Steps that follow putting the encode of `job` at `target`: hand it to
+    /// `run_as`, move subtitle sidecars and the settings record over,
+    /// remember a kept backup and update checksum files
+    ///
+    /// The encode is already in place, so each failure is only logged.
+    async fn finish_replacement(&self, job: &Job, target: &Path, kept_backup: Option<PathBuf>) {
+        if let Some(run_as) = self.config.run_as {
+            // A copied file is created by the daemon, so it would belong to root
+            if let Err(e) = run_as.chown(target) {
+                log_warn!("Warning: Failed to hand {:?} to uid {}: {}", target, run_as.uid, e);
+            }
+        }
+        if let Err(e) = rename_sidecars(&job.input_path, target, &job.subtitle_streams) {
+            log_warn!("Warning: Failed to rename subtitle sidecars of {:?}: {}", job.input_path, e);
+        }
+        self.write_settings(job, target);
+        if let Some(backup) = kept_backup {
+            self.record_kept_backup(job, backup);
+        }
+
+        if self.config.checksum_sidecars != ChecksumSidecarPolicy::Ignore {
+            let (original, replacement) = (job.input_path.clone(), target.to_path_buf());
+            let policy = self.config.checksum_sidecars;
+            let updated = tokio::task::spawn_blocking(move || {
+                update_checksum_sidecars(&original, &replacement, policy)
+            })
+            .await
+            .map_err(std::io::Error::other)
+            .and_then(|result| result);
+            match updated {
+                Ok(sidecars) if !sidecars.is_empty() => {
+                    log_info!("Updated checksum files {:?} for {:?}", sidecars, target)
+                }
+                Ok(_) => {}
+                Err(e) => log_warn!(
+                    "Warning: Failed to update checksum files next to {:?}: {}",
+                    target, e
+                ),
+            }
+        }
+    }
+
+    /// Extract the input's image-based subtitles to sidecars as its library
+    /// asks
+    ///
+    /// Nothing is written in read-only mode, nor for tracks in languages
+    /// the encode does not keep.
+    ///
+    /// # Returns
+    /// Positions of the subtitle streams to leave out of the encode: those
+    /// in languages not kept, and with `extract_and_drop` the tracks whose
+    /// sidecar was written
+    async fn extract_subtitles(&self, job: &Job) -> Vec<usize> {
+        let mut dropped = dropped_by_language(&self.config.subtitles, &job.subtitle_streams);
+        if !dropped.is_empty() {
+            log_info!(
+                "Leaving {} subtitle track(s) in other languages out of job {}",
+                dropped.len(),
+                job.id
+            );
+        }
+        let action = self.config.subtitles.action_for(&job.input_path);
+        if action == ImageSubtitleAction::Keep || self.config.read_only {
+            return dropped;
+        }
+        let input = job.input_path.clone();
+        let subtitles = job.subtitle_streams.clone();
+        let skip = dropped.clone();
+        let written =
+            tokio::task::spawn_blocking(move || extract_image_subtitles(&input, &subtitles, &skip))
+                .await
+                .unwrap_or_default();
+        for sidecar in &written {
+            log_info!("Extracted subtitle track {} to {:?}", sidecar.track, sidecar.path);
+            if let Some(run_as) = self.config.run_as {
+                for file in sidecar.files() {
+                    if let Err(e) = run_as.chown(&file) {
+                        log_warn!("Warning: Failed to hand {:?} to uid {}: {}", file, run_as.uid, e);
+                    }
+                }
+            }
+        }
+        if action == ImageSubtitleAction::ExtractAndDrop {
+            dropped.extend(written.iter().map(|sidecar| sidecar.track));
+            dropped.sort_unstable();
+        }
+        dropped
+    }
+
+    /// Hand the directories an encode writes into to the `run_as` user
+    ///
+    /// The daemon creates them, so without this an encoder that dropped its
+    /// privileges could not write its chunks or output.
+    fn hand_over_dirs(&self, job: &Job, temp_chunks_dir: Option<&Path>) {
+        let Some(run_as) = self.config.run_as else {
+            return;
+        };
+        let dirs = temp_chunks_dir.into_iter().chain(job.output_path.parent());
+        for dir in dirs {
+            if let Err(e) = run_as.chown(dir) {
+                log_warn!("Warning: Failed to hand {:?} to uid {}: {}", dir, run_as.uid, e);
+            }
+        }
+    }
+
+    /// Pause the torrents seeding `path` if its library is set to `pause`
+    ///
+    /// # Returns
+    /// The client and the hashes paused, or `None` if nothing was paused
+    ///
+    /// # Errors
+    /// Fails if the client cannot be asked or refuses, so the replacement
+    /// does not go ahead under a seeding torrent.
+    async fn pause_seeding(
+        &self,
+        path: &Path,
+    ) -> Result<Option<(TorrentClient, Vec<String>)>, ReplaceError> {
+        if self.config.torrent.action_for(path) != SeedAction::Pause {
+            return Ok(None);
+        }
+        let Some(client) = TorrentClient::from_config(&self.config.torrent) else {
+            return Ok(None);
+        };
+        let failed = |e: TorrentError| ReplaceError::SeedingPauseFailed(e.to_string());
+        let torrents = client.torrents().await.map_err(failed)?;
+        let hashes = seeding_hashes(&torrents, path);
+        if hashes.is_empty() {
+            return Ok(None);
+        }
+        client.pause(&hashes).await.map_err(failed)?;
+        log_info!("Paused {} torrents seeding {:?}", hashes.len(), path);
+        Ok(Some((client, hashes)))
+    }
+
+    /// Write the skip marker and why sidecars for a skipped job
+    ///
+    /// Nothing is written in read-only mode.
+    fn write_skip_files(&self, job: &Job, skip_reason: &SkipReason) -> Result<(), JobError> {
+        if self.config.read_only {
+            return Ok(());
+        }
+
+        // Create skip markers (Requirements 18.1, 18.2)
+        write_skip_marker_with_code(&job.input_path, skip_reason.code)
+            .map_err(JobError::SkipMarkerFailed)?;
+
+        // Write why sidecars if enabled
+        let _ = write_why_sidecar(
+            &job.input_path,
+            &skip_reason.message,
+            self.config.write_why_sidecars,
+        );
+        let _ = write_why_json(&job.input_path, skip_reason, self.config.write_why_json);
+        Ok(())
+    }
+
+    /// Whether originals may be replaced now, given the replace window
+    ///
+    /// Read-only replacements are only logged, so they need no window.
+    fn window_open(&self) -> bool {
+        self.config.read_only || replace_window_open(self.config.replace_window, current_timestamp_ms())
+    }
+
+    /// Keep a finished encode in temp until the replace window opens
+    async fn wait_for_window(&self, mut job: Job, output_bytes: u64) -> Job {
+        job.state = JobState::ReadyToReplace;
+        self.record_state(&job).await;
+        self.set_job_size_after(&job.id, output_bytes).await;
+        log_info!(
+            "Replace window closed, job {} waits to replace {:?}",
+            job.id, job.input_path
+        );
+        job
+    }
+
+    /// Whether encodes that pass the size gate wait for review
+    ///
+    /// Read-only mode never replaces anything, so it never holds jobs either.
+    fn needs_review(&self) -> bool {
+        self.config.require_approval && !self.config.read_only
+    }
+
+    /// Whether the daily budget has a replacement left
+    ///
+    /// Only replacements that succeed are counted, by `replace_original`.
+    /// Read-only replacements are only logged and cost nothing.
+    fn may_replace(&self) -> bool {
+        self.config.read_only || self.replacement_budget.has_room(current_timestamp_ms())
+    }
+
+    /// Keep a finished encode in temp until it is approved or rejected
+    async fn hold_for_approval(&self, mut job: Job, output_bytes: u64, why: &str) -> Job {
+        job.state = JobState::AwaitingApproval;
+        self.record_state(&job).await;
+        self.set_job_size_after(&job.id, output_bytes).await;
+        log_info!("{}, job {} awaits approval: {:?}", why, job.id, job.input_path);
+        if job.kind == JobKind::Encode {
+            self.write_comparison(&job).await;
+        }
+        job
+    }
+
+    /// Compare sampled frames of the output with the original and put the
+    /// scores in the job's metrics
+    ///
+    /// Failures to score are only logged.
+    async fn check_quality(&self, job: &Job) {
+        let validation = &self.config.validation;
+        if validation.quality_samples == 0 || job.duration_secs <= 0.0 {
+            return;
+        }
+
+        let original = job.source_path().to_path_buf();
+        let encode = job.output_path.clone();
+        let duration_secs = job.duration_secs;
+        let samples = validation.quality_samples;
+        let result = tokio::task::spawn_blocking(move || {
+            sample_quality(&original, &encode, duration_secs, samples)
+        })
+        .await;
+        let scores = match result {
+            Ok(Ok(Some(scores))) => scores,
+            Ok(Ok(None)) => return,
+            Ok(Err(e)) => {
+                log_warn!("Warning: Failed to score quality of job {}: {}", job.id, e);
+                return;
+            }
+            Err(e) => {
+                log_warn!("Warning: Quality task for job {} panicked: {}", job.id, e);
+                return;
+            }
+        };
+
+        let flag = scores.flag(validation.min_psnr, validation.min_ssim);
+        if let Some(ref reason) = flag {
+            log_warn!("Warning: Job {} flagged: {} ({:?})", job.id, reason, job.input_path);
+        }
+        let mut metrics = self.metrics.write().await;
+        if let Some(job_metrics) = metrics.jobs.iter_mut().find(|j| j.id == job.id) {
+            job_metrics.psnr = Some(scores.psnr);
+            job_metrics.ssim = Some(scores.ssim);
+            job_metrics.quality_flag = flag;
+        }
+    }
+
+    /// Decode the output looking for black and blocky stretches
+    ///
+    /// # Returns
+    /// Why the output should be failed, if it should. A check that cannot
+    /// run is logged and passes.
+    async fn check_frames(&self, job: &Job) -> Option<String> {
+        let validation = &self.config.validation;
+        let min_black_secs = validation.min_black_secs;
+        let max_block_mean = validation.max_block_mean;
+        if min_black_secs.is_none() && max_block_mean.is_none() {
+            return None;
+        }
+
+        let original = job.source_path().to_path_buf();
+        let encode = job.output_path.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            find_frame_problems(&original, &encode, min_black_secs, max_block_mean)
+        })
+        .await;
+        match result {
+            Ok(Ok(problems)) if problems.is_empty() => None,
+            Ok(Ok(problems)) => Some(format!("Frame check failed: {}", problems.join("; "))),
+            Ok(Err(e)) => {
+                log_warn!("Warning: Failed to check frames of job {}: {}", job.id, e);
+                None
+            }
+            Err(e) => {
+                log_warn!("Warning: Frame check for job {} panicked: {}", job.id, e);
+                None
+            }
+        }
+    }
+
+    /// Compare the audio timing of the output with the source
+    ///
+    /// # Returns
+    /// Why the output should be failed, if it should. A check that cannot
+    /// run is logged and passes.
+    async fn check_audio_sync(&self, job: &Job, audio_plan: &AudioPlan) -> Option<String> {
+        let max_drift_ms = self.config.validation.max_audio_drift_ms;
+        if max_drift_ms == 0 {
+            return None;
+        }
+
+        let original = job.source_path().to_path_buf();
+        let encode = job.output_path.clone();
+        let kept_audio = (!audio_plan.copies_all()).then(|| audio_plan.kept_indices());
+        let tolerance_secs = max_drift_ms as f64 / 1000.0;
+        let result = tokio::task::spawn_blocking(move || {
+            check_audio_sync(&original, &encode, kept_audio.as_deref(), tolerance_secs)
+        })
+        .await;
+        match result {
+            Ok(Ok(problems)) if problems.is_empty() => None,
+            Ok(Ok(problems)) => Some(format!("Audio out of sync: {}", problems.join("; "))),
+            Ok(Err(e)) => {
+                log_warn!("Warning: Failed to check audio sync of job {}: {}", job.id, e);
+                None
+            }
+            Err(e) => {
+                log_warn!("Warning: Audio sync check for job {} panicked: {}", job.id, e);
+                None
+            }
+        }
+    }
+
+    /// Count the audio streams of the output against the audio plan
+    ///
+    /// Only runs when the plan drops or downmixes tracks.
+    ///
+    /// # Returns
+    /// Why the output should be failed, if it should. A probe that fails is
+    /// logged and passes.
+    async fn check_audio_tracks(&self, job: &Job, audio_plan: &AudioPlan) -> Option<String> {
+        if audio_plan.copies_all() {
+            return None;
+        }
+        match probe_file_async(&job.output_path).await {
+            Ok(probe) => {
+                let problems = audio_plan.check_output(&probe.audio_streams);
+                (!problems.is_empty())
+                    .then(|| format!("Audio tracks do not match policy: {}", problems.join("; ")))
+            }
+            Err(e) => {
+                log_warn!("Warning: Failed to probe audio tracks of job {}: {}", job.id, e);
+                None
+            }
+        }
+    }
+
+    /// Write side-by-side stills of a held encode for review
+    ///
+    /// Failures are only logged; the job stays held either way.
+    async fn write_comparison(&self, job: &Job) {
+        let Some(state_dir) = self.config.job_state_dir.as_deref() else {
+            return;
+        };
+        if self.config.comparison_stills == 0 || job.duration_secs <= 0.0 {
+            return;
+        }
+
+        let dir = comparison_dir(state_dir, &job.id);
+        let original = job.source_path().to_path_buf();
+        let encode = job.output_path.clone();
+        let duration_secs = job.duration_secs;
+        let count = self.config.comparison_stills;
+        let stills_dir = dir.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            write_comparison_stills(&original, &encode, duration_secs, count, &stills_dir)
+        })
+        .await;
+        match result {
+            Ok(Ok(stills)) => log_info!("Wrote {} comparison stills to {:?}", stills.len(), dir),
+            Ok(Err(e)) => {
+                log_warn!("Warning: Failed to write comparison stills for {}: {}", job.id, e)
+            }
+            Err(e) => log_warn!("Warning: Comparison task for {} panicked: {}", job.id, e),
+        }
+    }
+
+    /// Remove a reviewed job's comparison stills
+    fn remove_comparison(&self, job: &Job) {
+        if let Some(state_dir) = self.config.job_state_dir.as_deref() {
+            remove_comparison(state_dir, &job.id);
+        }
+    }
+
+    /// Remux an already-AV1 source into MKV and put it next to the original
+    ///
+    /// The result replaces the original under the name given by the rename
+    /// template. There is no size gate: a stream copy is the same size as
+    /// its source give or take container overhead. The file is only replaced
+    /// while `claim`, if any, is still held.
+    async fn execute_remux(&self, mut job: Job, claim: Option<&ClaimLease>) -> Result<Job, JobError> {
+        self.hand_over_dirs(&job, None);
+        let input = job.source_path().to_path_buf();
+        let output = job.output_path.clone();
+        let run_as = self.config.run_as;
+        let remux_result =
+            tokio::task::spawn_blocking(move || run_remux_as(&input, &output, run_as))
+                .await
+                .unwrap_or_else(|join_err| {
+                    Err(EncodeError::RemuxFailed(format!("remux task panicked: {}", join_err)))
+                });
+
+        if let Err(remux_err) = remux_result {
+            self.record_failure_kind(&mut job, classify_failure(&remux_err, &[])).await;
+            job.state = JobState::Failed(remux_err.to_string());
+            self.record_state(&job).await;
+            self.increment_failed_jobs().await;
+            let _ = std::fs::remove_file(&job.output_path);
+            return Err(JobError::Encode(remux_err));
+        }
+
+        job.state = JobState::Validating;
+        self.record_state(&job).await;
+        self.tag_output(&job).await;
+
+        let output_bytes = std::fs::metadata(&job.output_path).map(|m| m.len()).unwrap_or(0);
+        if output_bytes == 0 {
+            let error_msg = "Remuxed output is missing or empty".to_string();
+            job.state = JobState::Failed(error_msg.clone());
+            self.record_state(&job).await;
+            self.increment_failed_jobs().await;
+            let _ = std::fs::remove_file(&job.output_path);
+            return Err(JobError::Validation(error_msg));
+        }
+
+        if !self.window_open() {
+            return Ok(self.wait_for_window(job, output_bytes).await);
+        }
+        if !self.may_replace() {
+            let why = "Daily replacement limit reached";
+            return Ok(self.hold_for_approval(job, output_bytes, why).await);
+        }
+        if claim.is_some_and(|claim| !claim.confirm()) {
+            return self.finish_claim_lost(job, None).await;
+        }
+
+        job.state = JobState::Replacing;
+        self.record_state(&job).await;
+
+        if let Err(replace_err) = self.replace_original(&job, output_bytes).await {
+            job.state = JobState::Failed(replace_err.to_string());
+            self.record_state(&job).await;
+            self.increment_failed_jobs().await;
+            // Preserve the remuxed output for manual inspection
+            return Err(JobError::Replacement(replace_err));
+        }
+
+        job.state = JobState::Completed;
+        self.record_state(&job).await;
+        self.increment_completed_jobs().await;
+        self.update_job_size_after(&job.id, output_bytes).await;
+        let _ = std::fs::remove_file(&job.output_path);
+
+        Ok(job)
+    }
+
+    /// Record a state change in the metrics, the job's trace, and the
+    /// persisted job
+    ///
+    /// The transition is appended to the job's journal before the job file
+    /// is rewritten. Jobs without a JSON file, such as those submitted
+    /// directly to the executor, are only tracked in metrics. A trace that
+    /// ends with this state is exported in the background.
+    async fn record_state(&self, job: &Job) {
+        self.update_job_metrics(job).await;
+
+        if let Some(telemetry) = &self.telemetry {
+            if let Some(spans) = telemetry.record(job) {
+                let telemetry = telemetry.clone();
+                tokio::spawn(async move { telemetry.export_spans(spans).await });
+            }
+        }
+
+        if let Some(state_dir) = &self.config.job_state_dir {
+            let result = update_job(state_dir, &job.id, |managed| {
+                record_stage_time(managed, current_timestamp_ms());
+                if let Some(stage) = job.state.stage() {
+                    managed.stage = stage;
+                }
+                managed.status = job.state.status();
+                if let Some(reason) = job.state.reason() {
+                    managed.error_reason = Some(reason.to_string());
+                }
+                if matches!(job.state, JobState::Failed(_)) {
+                    managed.failure_kind = job.failure;
+                }
+                let entry = JournalEntry::now(
+                    managed.stage,
+                    managed.status,
+                    job.state.reason().map(str::to_string),
+                );
+                if let Err(e) = append_entry(state_dir, &job.id, &entry) {
+                    log_warn!("Warning: Failed to journal job {}: {}", job.id, e);
+                }
+            });
+            match result {
+                Ok(Some(managed)) if matches!(job.state, JobState::Failed(_)) => {
+                    self.write_triage_bundle(state_dir, job, &managed).await;
+                }
+                Ok(_) => {}
+                Err(e) => log_warn!("Warning: Failed to persist state of job {}: {}", job.id, e),
+            }
+        }
+        if !matches!(job.state.status(), JobStatus::Pending | JobStatus::Running) {
+            self.job_finished.notify_waiters();
+        }
+    }
+
+    /// Collect what is needed to look into the failure of `job` into a
+    /// triage bundle and point its record at it
+    ///
+    /// Failures are only logged; the job has failed either way.
+    async fn write_triage_bundle(&self, state_dir: &Path, job: &Job, managed: &ManagedJob) {
+        if !self.config.triage.enabled {
+            return;
+        }
+        let mut output = job.output_tail.as_ref().map(|tail| tail.lines()).unwrap_or_default();
+        output.drain(..output.len().saturating_sub(self.config.triage.output_lines));
+        let metrics = self.metrics.read().await.clone();
+        match write_bundle(state_dir, managed, &output, &metrics) {
+            Ok(dir) => {
+                log_info!("Wrote triage bundle of job {} to {:?}", job.id, dir);
+                if let Err(e) = update_job(state_dir, &job.id, |managed| managed.triage_bundle = Some(dir)) {
+                    log_warn!("Warning: Failed to record triage bundle of job {}: {}", job.id, e);
+                }
+            }
+            Err(e) => log_warn!("Warning: Failed to write triage bundle of job {}: {}", job.id, e),
+        }
+        if let Err(e) = prune_bundles(state_dir, self.config.triage.max_bundles) {
+            log_warn!("Warning: Failed to prune triage bundles: {}", e);
+        }
+    }
+
+    /// Remember the backup kept for `job` so retention can remove it later
+    fn record_kept_backup(&self, job: &Job, backup: PathBuf) {
+        let Some(state_dir) = &self.config.job_state_dir else {
+            return;
+        };
+        let kept = KeptBackup {
+            path: backup,
+            kept_at: current_timestamp_ms(),
+        };
+        if let Err(e) = update_job(state_dir, &job.id, |managed| managed.backup = Some(kept)) {
+            log_warn!("Warning: Failed to record backup of job {}: {}", job.id, e);
+        }
+    }
+
+    /// Tag the MKV output of `job` with its settings and original, if enabled
+    ///
+    /// The tags only describe the file, so a failure is logged and the job
+    /// carries on.
+    async fn tag_output(&self, job: &Job) {
+        if !self.config.metadata_tags || !is_taggable(&job.output_path) || !job.output_path.is_file() {
+            return;
+        }
+        let tags = ProcessingTags {
+            encoder_settings: job.settings.as_ref().map(|s| s.video_params.clone()),
+            settings_fingerprint: job.settings.as_ref().map(|s| s.fingerprint.clone()),
+            original_codec: job.source_codec.clone(),
+            original_size: job.size_in_bytes_before,
+            processed_at: (current_timestamp_ms() / 1000) as u64,
+        };
+        let output = job.output_path.clone();
+        let tagged = tokio::task::spawn_blocking(move || write_mkv_tags(&output, &tags))
+            .await
+            .unwrap_or_else(|join_err| {
+                Err(EncodeError::TaggingFailed(format!("tagging task panicked: {}", join_err)))
+            });
+        if let Err(e) = tagged {
+            log_warn!("Warning: Failed to tag {:?}: {}", job.output_path, e);
+        }
+    }
+
+    /// Record the settings `job` was encoded with next to its replacement,
+    /// dropping the record of an original that was renamed away
+    fn write_settings(&self, job: &Job, target: &Path) {
+        if target != job.input_path {
+            let _ = std::fs::remove_file(settings_sidecar_path(&job.input_path));
+        }
+        let Some(settings) = &job.settings else {
+            return;
+        };
+        if let Err(e) = write_settings_sidecar(target, settings) {
+            log_warn!("Warning: Failed to write settings sidecar for {:?}: {}", target, e);
+        }
+    }
+
+    /// Keep the exact Av1an command line of `job` and the tool versions in
+    /// its record, so the encode can be reproduced
+    async fn record_encode_command(&self, job: &Job, params: &Av1anEncodeParams) {
+        let Some(state_dir) = &self.config.job_state_dir else {
+            return;
+        };
+        let command = command_line(&build_av1an_command(params));
+        let versions = self
+            .tool_versions
+            .get_or_init(|| async {
+                tokio::task::spawn_blocking(ToolVersions::detect)
+                    .await
+                    .expect("tool version detection should not panic")
+            })
+            .await
+            .clone();
+        let result = update_job(state_dir, &job.id, |managed| {
+            managed.encode_command = Some(command);
+            managed.tool_versions = Some(versions);
+            managed.encode_settings = job.settings.clone();
+        });
+        if let Err(e) = result {
+            log_warn!("Warning: Failed to record encode command of job {}: {}", job.id, e);
+        }
+    }
+
+    /// Update job metrics in shared state
+    async fn update_job_metrics(&self, job: &Job) {
+        let mut metrics = self.metrics.write().await;
+        let mut job_metrics = job.to_metrics(
+            job.worker_limit.unwrap_or(self.concurrency_plan.av1an_workers),
+            job.svt_params(self.config.svt_overrides),
+        );
+
+        // Find and update existing job metrics, or add new one
+        if let Some(existing) = metrics.jobs.iter_mut().find(|j| j.id == job.id) {
+            // Energy is accumulated by the metrics updater, not the executor
+            job_metrics.energy_joules = existing.energy_joules;
+            *existing = job_metrics;
+        } else {
+            metrics.jobs.push(job_metrics);
+        }
+
+        // Update running jobs count
+        metrics.running_jobs = metrics
+            .jobs
+            .iter()
+            .filter(|j| j.stage == "encoding" || j.stage == "validating" || j.stage == "replacing")
+            .count();
+    }
+
+    /// Increment completed jobs counter
+    async fn increment_completed_jobs(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.completed_jobs += 1;
+    }
+
+    /// Keep the cause of `job`'s failure and count it in the metrics
+    async fn record_failure_kind(&self, job: &mut Job, kind: FailureKind) {
+        log_info!("Classified failure of job {} as {}", job.id, kind);
+        job.failure = Some(kind);
+        let mut metrics = self.metrics.write().await;
+        *metrics.failures_by_kind.entry(kind.as_str().to_string()).or_insert(0) += 1;
+    }
+
+    /// Increment failed jobs counter
+    async fn increment_failed_jobs(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.failed_jobs += 1;
+    }
+
+    /// Increment skipped jobs counter (for size gate rejections)
+    async fn increment_skipped_jobs(&self) {
+        let mut metrics = self.metrics.write().await;
+        // Skipped jobs are counted as failed in the aggregate metrics
+        metrics.failed_jobs += 1;
+    }
+
+    /// Update the size_in_bytes_after for a completed job
+    async fn update_job_size_after(&self, job_id: &str, size_bytes: u64) {
+        self.set_job_size_after(job_id, size_bytes).await;
+        self.metrics.write().await.total_bytes_encoded += size_bytes;
+    }
+
+    /// Record a job's output size without counting it as encoded again
+    async fn set_job_size_after(&self, job_id: &str, size_bytes: u64) {
+        let mut metrics = self.metrics.write().await;
+        if let Some(job_metrics) = metrics.jobs.iter_mut().find(|j| j.id == job_id) {
+            job_metrics.size_in_bytes_after = size_bytes;
+        }
+    }
+}
+
+/// ffmpeg arguments leaving the video streams at `positions` out of the
+/// encode
+///
+/// Passed along with the audio parameters: Av1an encodes the first video
+/// stream itself and hands the rest of the input to ffmpeg.
+fn drop_video_args(positions: &[usize]) -> String {
+    positions
+        .iter()
+        .map(|i| format!("-map -0:v:{}", i))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn current_timestamp_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::new_shared_metrics;
+    use std::time::Duration;
+
+    fn create_test_plan(max_concurrent_jobs: u32) -> ConcurrencyPlan {
+        ConcurrencyPlan {
+            total_cores: 32,
+            target_threads: 28,
+            av1an_workers: 8,
+            max_concurrent_jobs,
+        }
+    }
+
+    fn create_test_job(id: &str) -> Job {
         Job::new(
             id.to_string(),
             PathBuf::from("/tmp/input.mkv"),
@@ -555,6 +2562,76 @@ mod tests {
         assert_eq!(executor.available_permits(), 0);
     }
 
+    // With scaled workers, short files share the worker budget of the plan
+    #[tokio::test]
+    async fn test_scaled_workers_share_budget() {
+        let config = JobExecutorConfig {
+            scale_workers: true,
+            ..Default::default()
+        };
+        let executor = JobExecutor::with_config(
+            create_test_plan(2),
+            new_shared_metrics(),
+            PathBuf::from("/tmp"),
+            config,
+        );
+        assert_eq!(executor.available_permits(), 16);
+
+        // Three 20 minute episodes take two workers each
+        let mut episode = Job::new("ep".to_string(), PathBuf::from("/a"), PathBuf::from("/b"));
+        episode.duration_secs = 20.0 * 60.0;
+        assert_eq!(executor.scaled_workers(&episode), Some(2));
+        let _permits = [
+            executor.acquire_workers(2).await,
+            executor.acquire_workers(2).await,
+            executor.acquire_workers(2).await,
+        ];
+        assert_eq!(executor.available_permits(), 10);
+
+        episode.kind = JobKind::Remux;
+        assert_eq!(executor.scaled_workers(&episode), None);
+    }
+
+    // A film in the long-file lane does not hold up episodes in the short ones
+    #[tokio::test]
+    async fn test_lanes_run_short_files_beside_long_one() {
+        let config = JobExecutorConfig {
+            small_lane_slots: 2,
+            ..Default::default()
+        };
+        let executor = JobExecutor::with_config(
+            create_test_plan(2),
+            new_shared_metrics(),
+            PathBuf::from("/tmp"),
+            config,
+        );
+
+        let mut film = Job::new("film".to_string(), PathBuf::from("/a"), PathBuf::from("/b"));
+        film.duration_secs = 2.0 * 3600.0;
+        let mut episode = Job::new("ep".to_string(), PathBuf::from("/c"), PathBuf::from("/d"));
+        episode.duration_secs = 20.0 * 60.0;
+        assert!(executor.is_long(&film));
+        assert!(!executor.is_long(&episode));
+
+        let (_film_permit, film_workers) = executor.acquire_slot(&film, None).await;
+        assert_eq!(film_workers, Some(8));
+        let (_first, ep_workers) = executor.acquire_slot(&episode, None).await;
+        let (_second, _) = executor.acquire_slot(&episode, None).await;
+        assert_eq!(ep_workers, Some(4));
+
+        // Both short-file lanes are busy and the long-file lane is taken
+        let lanes = executor.lanes.as_ref().unwrap();
+        assert_eq!(lanes.small.available_permits(), 0);
+        assert_eq!(lanes.big.available_permits(), 0);
+
+        // Remuxes always count as short, files of unknown length as long
+        episode.kind = JobKind::Remux;
+        episode.duration_secs = 3.0 * 3600.0;
+        assert!(!executor.is_long(&episode));
+        film.duration_secs = 0.0;
+        assert!(executor.is_long(&film));
+    }
+
     // Test job state transitions
     // **Validates: Requirements 5.1, 5.2, 5.3, 5.4, 16.3**
     #[test]
@@ -565,8 +2642,11 @@ mod tests {
         assert_eq!(JobState::SizeGating.as_str(), "size_gating");
         assert_eq!(JobState::Replacing.as_str(), "replacing");
         assert_eq!(JobState::Completed.as_str(), "completed");
+        assert_eq!(JobState::AwaitingApproval.as_str(), "awaiting_approval");
+        assert_eq!(JobState::ReadyToReplace.as_str(), "ready_to_replace");
         assert_eq!(JobState::Skipped("reason".to_string()).as_str(), "skipped");
         assert_eq!(JobState::Failed("error".to_string()).as_str(), "failed");
+        assert_eq!(JobState::Cancelled.as_str(), "cancelled");
     }
 
     // Test job creation and initial state
@@ -588,7 +2668,7 @@ mod tests {
         job.total_frames = 120000;
         job.size_in_bytes_before = 5368709120;
 
-        let metrics = job.to_metrics(8);
+        let metrics = job.to_metrics(8, job.svt_params(SvtOverrides::default()));
 
         assert_eq!(metrics.id, "test-002");
         assert_eq!(metrics.stage, "encoding");
@@ -599,6 +2679,27 @@ mod tests {
         assert_eq!(metrics.crf, 8);
     }
 
+    #[test]
+    fn test_job_svt_params_follow_profile_and_overrides() {
+        let mut job = create_test_job("test-003");
+        job.source_type = SourceType::Animation;
+        assert_eq!(job.svt_params(SvtOverrides::default()).crf, 10);
+
+        let configured = SvtOverrides {
+            crf: Some(20),
+            preset: Some(5),
+            film_grain: None,
+        };
+        assert_eq!(job.svt_params(configured).crf, 20);
+
+        // The job's own CRF wins over the configured one
+        job.overrides.crf = Some(30);
+        let params = job.svt_params(configured);
+        assert_eq!(params.crf, 30);
+        assert_eq!(params.preset, 5);
+        assert_eq!(job.to_metrics(4, params).crf, 30);
+    }
+
     // Test that metrics are updated during job execution
     // **Validates: Requirements 5.5**
     #[tokio::test]
@@ -619,6 +2720,791 @@ mod tests {
         assert_eq!(snapshot.jobs[0].stage, "queued");
     }
 
+    // A failed remux leaves the original alone and reports the copy "encoder"
+    #[tokio::test]
+    async fn test_remux_failure_keeps_original() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let input = temp.path().join("clip.mp4");
+        std::fs::write(&input, b"not really a video").unwrap();
+
+        let metrics = new_shared_metrics();
+        let executor = JobExecutor::new(create_test_plan(1), metrics.clone(), temp.path().to_path_buf());
+        let mut job = Job::new("remux-test".to_string(), input.clone(), temp.path().join("out.mkv"));
+        job.kind = JobKind::Remux;
+
+        assert!(executor.execute(job).await.is_err());
+        assert_eq!(std::fs::read(&input).unwrap(), b"not really a video");
+        assert!(!temp.path().join("clip.mkv").exists());
+
+        let snapshot = metrics.read().await;
+        assert_eq!(snapshot.jobs[0].stage, "failed");
+        assert_eq!(snapshot.jobs[0].encoder, "copy");
+        assert_eq!(snapshot.failed_jobs, 1);
+    }
+
+    // A file another daemon claimed is left to it, and claims of this daemon
+    // are released when the job ends
+    #[tokio::test]
+    async fn test_claimed_file_is_left_to_other_daemon() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let input = temp.path().join("clip.mp4");
+        std::fs::write(&input, b"not really a video").unwrap();
+        let other = FarmNode {
+            name: "other".to_string(),
+            instance: "1".to_string(),
+            lease_secs: 300,
+        };
+        assert_eq!(other.try_claim(&input, "theirs", unix_now()).unwrap(), None);
+
+        let me = FarmNode {
+            name: "me".to_string(),
+            ..other.clone()
+        };
+        let config = JobExecutorConfig {
+            farm: Some(me),
+            ..Default::default()
+        };
+        let executor = JobExecutor::with_config(
+            create_test_plan(1),
+            new_shared_metrics(),
+            temp.path().to_path_buf(),
+            config,
+        );
+        let mut job = Job::new("claimed".to_string(), input.clone(), temp.path().join("out.mkv"));
+        job.kind = JobKind::Remux;
+        let result = executor.execute(job.clone()).await;
+        assert!(matches!(result, Err(JobError::ClaimedElsewhere(node)) if node == "other"));
+        assert_eq!(crate::claims::read_claim(&input).unwrap().node, "other");
+
+        other.release(&input).unwrap();
+        job.id = "mine".to_string();
+        let result = executor.execute(job).await;
+        assert!(!matches!(result, Err(JobError::ClaimedElsewhere(_))));
+        assert!(!crate::claims::claim_path(&input).exists());
+    }
+
+    #[test]
+    fn test_hdd_sources_cap_readers() {
+        let mut storage = StorageConfig::default();
+        storage.libraries.insert(PathBuf::from("/media/archive"), StorageKind::Hdd);
+        storage.libraries.insert(PathBuf::from("/mnt/nas"), StorageKind::Network);
+        let mut staging = StagingConfig::default();
+        staging.libraries.insert(
+            PathBuf::from("/media/archive/staged"),
+            crate::config::LibraryStaging { stage_locally: true },
+        );
+        let config = JobExecutorConfig {
+            storage,
+            staging,
+            ..Default::default()
+        };
+        let executor = JobExecutor::with_config(
+            create_test_plan(1),
+            new_shared_metrics(),
+            PathBuf::from("/tmp"),
+            config,
+        );
+        let job = |path: &str| Job::new("j".to_string(), PathBuf::from(path), PathBuf::from("/tmp/out.mkv"));
+
+        assert_eq!(executor.reader_cap(&job("/media/archive/a.mkv")), Some(2));
+        assert_eq!(executor.reader_cap(&job("/mnt/nas/a.mkv")), None);
+        assert_eq!(executor.source_storage(&job("/mnt/nas/a.mkv")), StorageKind::Network);
+        // The staged copy is read from local scratch
+        assert_eq!(executor.source_storage(&job("/media/archive/staged/a.mkv")), StorageKind::Local);
+        assert_eq!(executor.reader_cap(&job("/media/archive/staged/a.mkv")), None);
+    }
+
+    #[tokio::test]
+    async fn test_job_waits_for_its_dependencies() {
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, load_job, save_job};
+        use crate::scan::ScanCandidate;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let state_dir = temp.path().join("jobs");
+        let managed = |name: &str| {
+            let input = temp.path().join(name);
+            std::fs::write(&input, b"not really a video").unwrap();
+            let candidate = ScanCandidate {
+                path: input,
+                size_bytes: 18,
+                modified_time: std::time::SystemTime::now(),
+                root: temp.path().to_path_buf(),
+            };
+            let probe = ProbeResult {
+                video_streams: vec![],
+                audio_streams: vec![],
+                subtitle_streams: vec![],
+                font_attachments: 0,
+                format: FormatInfo {
+                    duration_secs: 1.0,
+                    size_bytes: 18,
+                },
+            };
+            let mut managed = create_job(&candidate, probe, SourceType::default(), temp.path());
+            managed.kind = JobKind::Remux;
+            managed
+        };
+        let first = managed("movie.mp4");
+        let mut second = managed("extra.mp4");
+        second.depends_on = vec![first.id.clone()];
+        save_job(&first, &state_dir).unwrap();
+        save_job(&second, &state_dir).unwrap();
+
+        let config = JobExecutorConfig {
+            job_state_dir: Some(state_dir.clone()),
+            ..Default::default()
+        };
+        let executor = Arc::new(JobExecutor::with_config(
+            create_test_plan(2),
+            new_shared_metrics(),
+            temp.path().to_path_buf(),
+            config,
+        ));
+        let waiting = {
+            let executor = executor.clone();
+            let job = Job::from_managed(&second, 18);
+            tokio::spawn(async move { executor.execute(job).await })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+        assert!(load_job(&state_dir, &second.id).unwrap().unwrap().is_queued());
+
+        // However the first job ends, the second goes ahead
+        assert!(executor.execute(Job::from_managed(&first, 18)).await.is_err());
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), waiting)
+            .await
+            .expect("dependent job should start once its dependency finished")
+            .unwrap();
+        assert!(result.is_err());
+        assert_eq!(load_job(&state_dir, &second.id).unwrap().unwrap().status, JobStatus::Failed);
+    }
+
+    // A paused queue holds jobs that have not started until it is resumed
+    #[tokio::test]
+    async fn test_paused_queue_holds_jobs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let input = temp.path().join("clip.mp4");
+        std::fs::write(&input, b"not really a video").unwrap();
+
+        let executor = Arc::new(JobExecutor::new(
+            create_test_plan(1),
+            new_shared_metrics(),
+            temp.path().to_path_buf(),
+        ));
+        executor.control().pause();
+        let waiting = {
+            let executor = executor.clone();
+            let mut job = Job::new("held".to_string(), input, temp.path().join("out.mkv"));
+            job.kind = JobKind::Remux;
+            tokio::spawn(async move { executor.execute(job).await })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        executor.control().resume();
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), waiting)
+            .await
+            .expect("held job should start once the queue is resumed")
+            .unwrap();
+        assert!(result.is_err());
+    }
+
+    // The kill switch holds jobs already waiting for a slot when it appears
+    #[tokio::test]
+    async fn test_kill_switch_holds_jobs_waiting_for_a_slot() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let input = temp.path().join("clip.mp4");
+        std::fs::write(&input, b"not really a video").unwrap();
+        let stop = temp.path().join("STOP");
+
+        let config = JobExecutorConfig {
+            kill_switch_file: Some(stop.clone()),
+            ..Default::default()
+        };
+        let executor = Arc::new(JobExecutor::with_config(
+            create_test_plan(1),
+            new_shared_metrics(),
+            temp.path().to_path_buf(),
+            config,
+        ));
+        let slot = executor.acquire_permit().await;
+        let waiting: Vec<_> = (0..3)
+            .map(|i| {
+                let executor = executor.clone();
+                let mut job = Job::new(
+                    format!("queued-{}", i),
+                    input.clone(),
+                    temp.path().join(format!("out-{}.mkv", i)),
+                );
+                job.kind = JobKind::Remux;
+                tokio::spawn(async move { executor.execute(job).await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        std::fs::write(&stop, b"").unwrap();
+        drop(slot);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(waiting.iter().all(|job| !job.is_finished()));
+
+        std::fs::remove_file(&stop).unwrap();
+        for job in waiting {
+            let result = tokio::time::timeout(Duration::from_secs(10), job)
+                .await
+                .expect("held jobs should start once the kill switch is removed")
+                .unwrap();
+            assert!(result.is_err());
+        }
+    }
+
+    // A CPU over its limit holds jobs at their slot when the action is pause
+    #[tokio::test]
+    async fn test_thermal_pause_holds_jobs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let input = temp.path().join("clip.mp4");
+        std::fs::write(&input, b"not really a video").unwrap();
+
+        let metrics = new_shared_metrics();
+        metrics.write().await.thermal.throttled = true;
+        let config = JobExecutorConfig {
+            thermal_pause: true,
+            ..Default::default()
+        };
+        let executor = Arc::new(JobExecutor::with_config(
+            create_test_plan(1),
+            metrics.clone(),
+            temp.path().to_path_buf(),
+            config,
+        ));
+        let waiting = {
+            let executor = executor.clone();
+            let mut job = Job::new("hot".to_string(), input, temp.path().join("out.mkv"));
+            job.kind = JobKind::Remux;
+            tokio::spawn(async move { executor.execute(job).await })
+        };
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiting.is_finished());
+
+        metrics.write().await.thermal.throttled = false;
+        let result = tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .expect("held job should start once the CPU cooled down")
+            .unwrap();
+        assert!(result.is_err());
+    }
+
+    // Polite mode holds jobs at their slot while it yields the host
+    #[tokio::test]
+    async fn test_polite_mode_holds_jobs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let input = temp.path().join("clip.mp4");
+        std::fs::write(&input, b"not really a video").unwrap();
+
+        let metrics = new_shared_metrics();
+        metrics.write().await.polite.yielding = true;
+        let config = JobExecutorConfig {
+            polite: true,
+            ..Default::default()
+        };
+        let executor = Arc::new(JobExecutor::with_config(
+            create_test_plan(1),
+            metrics.clone(),
+            temp.path().to_path_buf(),
+            config,
+        ));
+        let waiting = {
+            let executor = executor.clone();
+            let mut job = Job::new("busy".to_string(), input, temp.path().join("out.mkv"));
+            job.kind = JobKind::Remux;
+            tokio::spawn(async move { executor.execute(job).await })
+        };
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiting.is_finished());
+
+        metrics.write().await.polite.yielding = false;
+        let result = tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .expect("held job should start once the host is quiet")
+            .unwrap();
+        assert!(result.is_err());
+    }
+
+    // Temp usage over the quota holds jobs at their slot until space is freed
+    #[tokio::test]
+    async fn test_temp_quota_holds_jobs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let input = temp.path().join("clip.mp4");
+        std::fs::write(&input, b"not really a video").unwrap();
+        let chunks = temp.path().join("chunks");
+        std::fs::create_dir_all(chunks.join("chunks_other")).unwrap();
+        let leftover = chunks.join("chunks_other").join("0001.ivf");
+        std::fs::write(&leftover, vec![0u8; 100]).unwrap();
+
+        let metrics = new_shared_metrics();
+        let config = JobExecutorConfig {
+            temp_quota_bytes: 10,
+            ..Default::default()
+        };
+        let executor = Arc::new(JobExecutor::with_config(
+            create_test_plan(1),
+            metrics.clone(),
+            chunks,
+            config,
+        ));
+        let waiting = {
+            let executor = executor.clone();
+            let mut job = Job::new("deferred".to_string(), input, temp.path().join("out.mkv"));
+            job.kind = JobKind::Remux;
+            tokio::spawn(async move { executor.execute(job).await })
+        };
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiting.is_finished());
+        assert!(metrics.read().await.temp.jobs_deferred);
+
+        std::fs::remove_file(&leftover).unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(10), waiting)
+            .await
+            .expect("held job should start once temp usage is under the quota")
+            .unwrap();
+        assert!(result.is_err());
+        assert!(!metrics.read().await.temp.jobs_deferred);
+    }
+
+    // The persisted job follows the executor and keeps the stage it failed at
+    #[tokio::test]
+    async fn test_failed_job_is_persisted() {
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, load_jobs, save_job};
+        use crate::scan::ScanCandidate;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let state_dir = temp.path().join("jobs");
+        let input = temp.path().join("clip.mp4");
+        std::fs::write(&input, b"not really a video").unwrap();
+
+        let candidate = ScanCandidate {
+            path: input.clone(),
+            size_bytes: 18,
+            modified_time: std::time::SystemTime::now(),
+            root: temp.path().to_path_buf(),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 1.0,
+                size_bytes: 18,
+            },
+        };
+        let mut managed = create_job(&candidate, probe, SourceType::default(), temp.path());
+        managed.kind = JobKind::Remux;
+        save_job(&managed, &state_dir).unwrap();
+
+        let config = JobExecutorConfig {
+            job_state_dir: Some(state_dir.clone()),
+            ..Default::default()
+        };
+        let executor = JobExecutor::with_config(
+            create_test_plan(1),
+            new_shared_metrics(),
+            temp.path().to_path_buf(),
+            config,
+        );
+        let job = Job::from_managed(&managed, candidate.size_bytes);
+        assert!(executor.execute(job).await.is_err());
+
+        let persisted = load_jobs(&state_dir).unwrap().remove(0);
+        let journal = crate::journal::read_journal(&state_dir, &persisted.id).unwrap();
+        assert_eq!(journal.first().unwrap().stage, JobStage::Encoding);
+        assert_eq!(journal.last().unwrap().status, JobStatus::Failed);
+        assert_eq!(persisted.status, JobStatus::Failed);
+        assert_eq!(persisted.stage, JobStage::Encoding);
+        assert!(persisted.error_reason.is_some());
+        assert!(persisted.updated_at >= managed.updated_at);
+        assert!(persisted.failure_kind.is_some(), "a failed remux is classified");
+
+        // The failure left a triage bundle the record points at
+        let bundle = persisted.triage_bundle.expect("triage bundle recorded");
+        assert_eq!(bundle, crate::triage::triage_dir(&state_dir, &persisted.id));
+        assert!(bundle.join("job.json").is_file());
+        assert!(bundle.join("metrics.json").is_file());
+        assert!(bundle.join("journal.jsonl").is_file());
+    }
+
+    // A job that cannot get its chunks directory is recorded as failed, not
+    // left running
+    #[tokio::test]
+    async fn test_temp_dir_failure_is_persisted() {
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, load_job, save_job};
+        use crate::scan::ScanCandidate;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let state_dir = temp.path().join("jobs");
+        let input = temp.path().join("clip.mkv");
+        std::fs::write(&input, b"not really a video").unwrap();
+        // A file where the chunks directories would go
+        let temp_base = temp.path().join("chunks");
+        std::fs::write(&temp_base, b"").unwrap();
+
+        let candidate = ScanCandidate {
+            path: input.clone(),
+            size_bytes: 18,
+            modified_time: std::time::SystemTime::now(),
+            root: temp.path().to_path_buf(),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 1.0,
+                size_bytes: 18,
+            },
+        };
+        let managed = create_job(&candidate, probe, SourceType::default(), temp.path());
+        save_job(&managed, &state_dir).unwrap();
+
+        let config = JobExecutorConfig {
+            job_state_dir: Some(state_dir.clone()),
+            ..Default::default()
+        };
+        let executor =
+            JobExecutor::with_config(create_test_plan(1), new_shared_metrics(), temp_base, config);
+        let job = Job::from_managed(&managed, candidate.size_bytes);
+        assert!(matches!(
+            executor.execute(job).await,
+            Err(JobError::TempDirCreation(_))
+        ));
+
+        let persisted = load_job(&state_dir, &managed.id).unwrap().unwrap();
+        assert_eq!(persisted.status, JobStatus::Failed);
+        assert!(persisted.error_reason.is_some());
+        assert_eq!(executor.metrics.read().await.failed_jobs, 1);
+    }
+
+    // A replacement that fails leaves the daily limit untouched
+    #[tokio::test]
+    async fn test_failed_replacement_costs_nothing() {
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, save_job};
+        use crate::scan::ScanCandidate;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let state_dir = temp.path().join("jobs");
+        let input = temp.path().join("clip.mkv");
+
+        let candidate = ScanCandidate {
+            path: input.clone(),
+            size_bytes: 25,
+            modified_time: std::time::SystemTime::now(),
+            root: temp.path().to_path_buf(),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 1.0,
+                size_bytes: 25,
+            },
+        };
+        // The original is gone by the time the job is approved
+        let mut managed = create_job(&candidate, probe, SourceType::default(), temp.path());
+        managed.stage = JobStage::AwaitingApproval;
+        managed.status = JobStatus::Pending;
+        std::fs::write(&managed.output_path, b"the encode").unwrap();
+        save_job(&managed, &state_dir).unwrap();
+
+        let config = JobExecutorConfig {
+            job_state_dir: Some(state_dir.clone()),
+            max_replacements_per_day: 1,
+            ..Default::default()
+        };
+        let executor = JobExecutor::with_config(
+            create_test_plan(1),
+            new_shared_metrics(),
+            temp.path().to_path_buf(),
+            config,
+        );
+
+        assert!(matches!(
+            executor.approve(&managed.id).await,
+            Err(JobError::Replacement(_))
+        ));
+        assert!(executor.may_replace());
+    }
+
+    // Approving a held job replaces its original and counts against the limit
+    #[tokio::test]
+    async fn test_approve_held_job() {
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, load_job, save_job};
+        use crate::scan::ScanCandidate;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let state_dir = temp.path().join("jobs");
+        let input = temp.path().join("clip.mkv");
+        std::fs::write(&input, b"the original, larger file").unwrap();
+
+        let candidate = ScanCandidate {
+            path: input.clone(),
+            size_bytes: 25,
+            modified_time: std::time::SystemTime::now(),
+            root: temp.path().to_path_buf(),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 1.0,
+                size_bytes: 25,
+            },
+        };
+        let mut managed = create_job(&candidate, probe, SourceType::default(), temp.path());
+        managed.stage = JobStage::AwaitingApproval;
+        managed.status = JobStatus::Pending;
+        std::fs::write(&managed.output_path, b"the encode").unwrap();
+        save_job(&managed, &state_dir).unwrap();
+
+        let config = JobExecutorConfig {
+            job_state_dir: Some(state_dir.clone()),
+            max_replacements_per_day: 1,
+            ..Default::default()
+        };
+        let executor = JobExecutor::with_config(
+            create_test_plan(1),
+            new_shared_metrics(),
+            temp.path().to_path_buf(),
+            config,
+        );
+
+        let target = executor.approve(&managed.id).await.unwrap();
+        assert_eq!(target, input);
+        assert_eq!(std::fs::read(&input).unwrap(), b"the encode");
+        assert!(!managed.output_path.exists());
+
+        let persisted = load_job(&state_dir, &managed.id).unwrap().unwrap();
+        assert_eq!(persisted.stage, JobStage::Complete);
+        assert_eq!(persisted.status, JobStatus::Success);
+
+        // The approval used up the day's only replacement
+        assert!(!executor.may_replace());
+        assert!(matches!(
+            executor.approve(&managed.id).await,
+            Err(JobError::NotAwaitingApproval(_))
+        ));
+    }
+
+    // A swap finished after a restart gets the same follow-up as any other
+    // replacement
+    #[tokio::test]
+    async fn test_repaired_swap_finishes_replacement() {
+        use crate::encode_settings::read_settings_sidecar;
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, load_job, save_job};
+        use crate::replace::SwapPhase;
+        use crate::scan::ScanCandidate;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let state_dir = temp.path().join("jobs");
+        let input = temp.path().join("clip.mkv");
+        std::fs::write(&input, b"hello").unwrap();
+        std::fs::write(temp.path().join("release.sfv"), "clip.mkv DEADBEEF\n").unwrap();
+
+        let candidate = ScanCandidate {
+            path: input.clone(),
+            size_bytes: 5,
+            modified_time: std::time::SystemTime::now(),
+            root: temp.path().to_path_buf(),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 1.0,
+                size_bytes: 5,
+            },
+        };
+        let mut managed = create_job(&candidate, probe, SourceType::default(), temp.path());
+        let profile = EncodeProfile::for_source(SourceType::default());
+        managed.encode_settings = Some(EncodeSettings::new(
+            &profile.svt_params(),
+            "yuv420p10le",
+            SourceType::default(),
+            false,
+        ));
+        save_job(&managed, &state_dir).unwrap();
+
+        let config = JobExecutorConfig {
+            job_state_dir: Some(state_dir.clone()),
+            ..Default::default()
+        };
+        let executor = JobExecutor::with_config(
+            create_test_plan(1),
+            new_shared_metrics(),
+            temp.path().to_path_buf(),
+            config,
+        );
+        let intent = SwapIntent {
+            original: input.clone(),
+            encoded: managed.output_path.clone(),
+            target: input.clone(),
+            backup: temp.path().join("clip.mkv.orig.1"),
+            keep_original: true,
+            phase: SwapPhase::Cleanup,
+        };
+
+        executor
+            .finish_repaired_swap(&Job::from_managed(&managed, 0), &intent)
+            .await;
+
+        assert_eq!(read_settings_sidecar(&input), managed.encode_settings);
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("release.sfv")).unwrap(),
+            "clip.mkv 3610A686\n"
+        );
+        let persisted = load_job(&state_dir, &managed.id).unwrap().unwrap();
+        assert_eq!(persisted.backup.map(|kept| kept.path), Some(intent.backup));
+    }
+
+    // Jobs ready to replace stay put until the replace window opens
+    #[tokio::test]
+    async fn test_replace_ready_waits_for_window() {
+        use crate::config::TimeWindow;
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, load_job, save_job};
+        use crate::replace_window::local_minute_of_day;
+        use crate::scan::ScanCandidate;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let state_dir = temp.path().join("jobs");
+        let input = temp.path().join("clip.mkv");
+        std::fs::write(&input, b"the original, larger file").unwrap();
+
+        let candidate = ScanCandidate {
+            path: input.clone(),
+            size_bytes: 25,
+            modified_time: std::time::SystemTime::now(),
+            root: temp.path().to_path_buf(),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 1.0,
+                size_bytes: 25,
+            },
+        };
+        let mut managed = create_job(&candidate, probe, SourceType::default(), temp.path());
+        managed.stage = JobStage::ReadyToReplace;
+        managed.status = JobStatus::Pending;
+        std::fs::write(&managed.output_path, b"the encode").unwrap();
+        save_job(&managed, &state_dir).unwrap();
+
+        let minute = local_minute_of_day(current_timestamp_ms());
+        let window_from = |offset: u16| TimeWindow {
+            start_minute: (minute + offset) % 1440,
+            end_minute: (minute + offset + 60) % 1440,
+        };
+        let executor_with = |window: TimeWindow| {
+            let config = JobExecutorConfig {
+                job_state_dir: Some(state_dir.clone()),
+                replace_window: Some(window),
+                ..Default::default()
+            };
+            JobExecutor::with_config(
+                create_test_plan(1),
+                new_shared_metrics(),
+                temp.path().to_path_buf(),
+                config,
+            )
+        };
+
+        assert_eq!(executor_with(window_from(120)).replace_ready().await, 0);
+        assert_eq!(std::fs::read(&input).unwrap(), b"the original, larger file");
+        let persisted = load_job(&state_dir, &managed.id).unwrap().unwrap();
+        assert_eq!(persisted.stage, JobStage::ReadyToReplace);
+
+        assert_eq!(executor_with(window_from(0)).replace_ready().await, 1);
+        assert_eq!(std::fs::read(&input).unwrap(), b"the encode");
+        assert!(!managed.output_path.exists());
+        let persisted = load_job(&state_dir, &managed.id).unwrap().unwrap();
+        assert_eq!(persisted.stage, JobStage::Complete);
+        assert_eq!(persisted.status, JobStatus::Success);
+    }
+
+    // Rejecting a held job keeps the original and marks it skipped
+    #[tokio::test]
+    async fn test_reject_held_job() {
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, load_job, save_job};
+        use crate::scan::ScanCandidate;
+        use crate::skip_marker::read_skip_marker_code;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let state_dir = temp.path().join("jobs");
+        let input = temp.path().join("clip.mkv");
+        std::fs::write(&input, b"the original").unwrap();
+
+        let candidate = ScanCandidate {
+            path: input.clone(),
+            size_bytes: 12,
+            modified_time: std::time::SystemTime::now(),
+            root: temp.path().to_path_buf(),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 1.0,
+                size_bytes: 12,
+            },
+        };
+        let mut managed = create_job(&candidate, probe, SourceType::default(), temp.path());
+        managed.stage = JobStage::AwaitingApproval;
+        managed.status = JobStatus::Pending;
+        std::fs::write(&managed.output_path, b"the encode").unwrap();
+        save_job(&managed, &state_dir).unwrap();
+
+        let config = JobExecutorConfig {
+            job_state_dir: Some(state_dir.clone()),
+            require_approval: true,
+            ..Default::default()
+        };
+        let executor = JobExecutor::with_config(
+            create_test_plan(1),
+            new_shared_metrics(),
+            temp.path().to_path_buf(),
+            config,
+        );
+
+        executor.reject(&managed.id).await.unwrap();
+        assert_eq!(std::fs::read(&input).unwrap(), b"the original");
+        assert!(!managed.output_path.exists());
+        assert_eq!(read_skip_marker_code(&input), Some(SkipCode::RejectedOnReview));
+
+        let persisted = load_job(&state_dir, &managed.id).unwrap().unwrap();
+        assert_eq!(persisted.status, JobStatus::Skipped);
+        assert!(matches!(
+            executor.reject(&managed.id).await,
+            Err(JobError::NotAwaitingApproval(_))
+        ));
+    }
+
     // Test JobExecutorConfig defaults
     #[test]
     fn test_job_executor_config_defaults() {
@@ -626,6 +3512,7 @@ mod tests {
         assert!((config.max_size_ratio - 0.95).abs() < 0.001);
         assert!(!config.keep_original);
         assert!(config.write_why_sidecars);
+        assert!(!config.write_why_json);
     }
 
     // Test JobExecutor with custom config
@@ -637,6 +3524,44 @@ mod tests {
             max_size_ratio: 0.80,
             keep_original: true,
             write_why_sidecars: false,
+            write_why_json: true,
+            encode_limits: EncodeLimits::from_secs(7200, 600),
+            svt_overrides: SvtOverrides::default(),
+            rename_template: "{stem} AV1.{ext}".to_string(),
+            on_collision: CollisionPolicy::Suffix,
+            hardlinks: HardlinkPolicy::default(),
+            backup_template: "{name}.bak".to_string(),
+            backup_location: BackupLocation::Hidden,
+            torrent: TorrentConfig::default(),
+            checksum_sidecars: ChecksumSidecarPolicy::default(),
+            metadata_tags: false,
+            run_as: None,
+            telemetry: TelemetryConfig::default(),
+            job_state_dir: None,
+            read_only: false,
+            max_replacements_per_day: 0,
+            require_approval: false,
+            replace_window: None,
+            comparison_stills: 0,
+            validation: ValidationConfig::default(),
+            quotas: QuotaConfig::default(),
+            farm: None,
+            staging: StagingConfig::default(),
+            storage: StorageConfig::default(),
+            pixel_format: PixelFormatConfig::default(),
+            audio: AudioPolicy::default(),
+            subtitles: SubtitleConfig::default(),
+            drop_attached_pictures: false,
+            scale_workers: false,
+            small_lane_slots: 0,
+            big_lane_min_secs: 3600,
+            cgroups: CgroupConfig::default(),
+            triage: TriageConfig::default(),
+            kill_switch_file: None,
+            thermal_pause: false,
+            polite: false,
+            temp_quota_bytes: 0,
+            temp_output_dir: None,
         };
         let executor = JobExecutor::with_config(
             plan,
@@ -651,6 +3576,24 @@ mod tests {
         assert!(!executor.config.write_why_sidecars);
     }
 
+    #[test]
+    fn test_executor_config_from_config() {
+        let mut config = Config::default();
+        config.gates.max_size_ratio = 0.9;
+        config.scan.write_why_json = true;
+        config.av1an.max_encode_secs = 3600;
+        config.av1an.stall_timeout_secs = 0;
+
+        let executor_config = JobExecutorConfig::from_config(&config);
+        assert!((executor_config.max_size_ratio - 0.9).abs() < 0.001);
+        assert!(executor_config.write_why_json);
+        assert_eq!(
+            executor_config.encode_limits.max_duration,
+            Some(std::time::Duration::from_secs(3600))
+        );
+        assert_eq!(executor_config.encode_limits.stall_timeout, None);
+    }
+
     // Test concurrent permit acquisition with async tasks
     // **Validates: Requirements 5.5**
     #[tokio::test]
@@ -690,4 +3633,26 @@ mod tests {
         let elapsed = start.elapsed();
         assert!(elapsed >= Duration::from_millis(50));
     }
+
+    #[tokio::test]
+    async fn test_job_cancelled_while_queued_never_encodes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let metrics = new_shared_metrics();
+        let executor = JobExecutor::new(create_test_plan(1), metrics.clone(), temp_dir.path().to_path_buf());
+
+        let mut job = create_test_job("queued-cancel");
+        job.output_path = temp_dir.path().join("out.mkv");
+        assert!(!executor.cancel(&job.id), "job has not reached the executor yet");
+
+        let result = executor.execute(job).await;
+        assert!(matches!(result, Err(JobError::Cancelled)));
+        assert!(!temp_dir.path().join("chunks_queued-cancel").exists());
+
+        let snapshot = metrics.read().await;
+        assert_eq!(snapshot.cancelled_jobs, 1);
+        assert_eq!(snapshot.failed_jobs, 0);
+        assert_eq!(snapshot.jobs[0].stage, "cancelled");
+        drop(snapshot);
+        assert!(executor.cancels.lock().unwrap().is_empty());
+    }
 }
@@ -3,10 +3,16 @@
 //! This module provides functionality to create, save, load, and query jobs.
 //! Jobs are persisted as JSON files in a configured state directory.
 
-use crate::classify::SourceType;
+use crate::classify::{classify_media_kind, resolution_class, season_key, MediaKind, SourceType};
 use crate::gates::ProbeResult;
+use crate::journal::journal_path;
 use crate::scan::ScanCandidate;
+use crate::encode::{CRF_RANGE, PRESET_RANGE};
+use crate::encode_settings::EncodeSettings;
+use crate::failure::FailureKind;
+use crate::tool_versions::ToolVersions;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -14,10 +20,11 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 /// Stage of a job in the encoding pipeline.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum JobStage {
     /// Job is waiting in queue.
+    #[default]
     Queued,
     /// Job is currently encoding.
     Encoding,
@@ -29,12 +36,12 @@ pub enum JobStage {
     Replacing,
     /// Job has completed successfully.
     Complete,
-}
-
-impl Default for JobStage {
-    fn default() -> Self {
-        Self::Queued
-    }
+    /// Encode passed every gate and waits for approval before it replaces
+    /// the original.
+    AwaitingApproval,
+    /// Encode passed every gate and waits for the replace window to open
+    /// before it replaces the original.
+    ReadyToReplace,
 }
 
 impl std::fmt::Display for JobStage {
@@ -46,16 +53,19 @@ impl std::fmt::Display for JobStage {
             JobStage::SizeGating => write!(f, "size_gating"),
             JobStage::Replacing => write!(f, "replacing"),
             JobStage::Complete => write!(f, "complete"),
+            JobStage::AwaitingApproval => write!(f, "awaiting_approval"),
+            JobStage::ReadyToReplace => write!(f, "ready_to_replace"),
         }
     }
 }
 
 
 /// Status of a job.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum JobStatus {
     /// Job is pending execution.
+    #[default]
     Pending,
     /// Job is currently running.
     Running,
@@ -65,12 +75,8 @@ pub enum JobStatus {
     Failed,
     /// Job was skipped (e.g., size gate rejection).
     Skipped,
-}
-
-impl Default for JobStatus {
-    fn default() -> Self {
-        Self::Pending
-    }
+    /// Job was cancelled on request.
+    Cancelled,
 }
 
 impl std::fmt::Display for JobStatus {
@@ -81,10 +87,22 @@ impl std::fmt::Display for JobStatus {
             JobStatus::Success => write!(f, "success"),
             JobStatus::Failed => write!(f, "failed"),
             JobStatus::Skipped => write!(f, "skipped"),
+            JobStatus::Cancelled => write!(f, "cancelled"),
         }
     }
 }
 
+/// What a job does to its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    /// Full Av1an encode to AV1.
+    #[default]
+    Encode,
+    /// Stream copy of an already-AV1 source into MKV.
+    Remux,
+}
+
 /// Represents an encoding job with full metadata.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Job {
@@ -100,14 +118,136 @@ pub struct Job {
     pub status: JobStatus,
     /// Classification of the source (web-like, disc-like, unknown).
     pub source_type: SourceType,
+    /// Whether the input is a series episode or a movie.
+    #[serde(default)]
+    pub media_kind: MediaKind,
+    /// Batch key shared by all episodes of the same season.
+    #[serde(default)]
+    pub batch_key: Option<String>,
+    /// Free-form lowercase labels used for filtering (library, source, resolution, ...).
+    #[serde(default)]
+    pub tags: Vec<String>,
     /// Probe result from ffprobe.
     pub probe_result: ProbeResult,
+    /// Whether the job encodes or only remuxes.
+    #[serde(default)]
+    pub kind: JobKind,
+    /// Queue priority set by reordering; higher starts first (see
+    /// [`crate::queue_order`]).
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i64,
+    /// Whether an `.av1force` marker sent the file past the gates.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub forced: bool,
+    /// Settings given with the submission that replace the configured ones.
+    #[serde(default, skip_serializing_if = "JobOverrides::is_empty")]
+    pub overrides: JobOverrides,
     /// Unix timestamp (milliseconds) when job was created.
     pub created_at: i64,
     /// Unix timestamp (milliseconds) when job was last updated.
     pub updated_at: i64,
     /// Error reason if job failed or was skipped.
     pub error_reason: Option<String>,
+    /// Wall-clock seconds spent in each pipeline stage so far, keyed by
+    /// stage name (see [`crate::timings`]).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub stage_secs: BTreeMap<String, f64>,
+    /// Backup of the original kept after replacement, until backup retention
+    /// removes it (see [`crate::backup_retention`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup: Option<KeptBackup>,
+    /// Av1an command line of the latest encode, exactly as it was run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encode_command: Option<String>,
+    /// Daemon and tool versions the latest encode ran with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_versions: Option<ToolVersions>,
+    /// Settings of the latest encode, written to the `.av1settings` sidecar
+    /// once the original is replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encode_settings: Option<EncodeSettings>,
+    /// Triage bundle written when the job last failed (see
+    /// [`crate::triage`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub triage_bundle: Option<PathBuf>,
+    /// Cause of the latest failure, when it was an encode that failed (see
+    /// [`crate::failure`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_kind: Option<FailureKind>,
+    /// Operator notes on the job, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<JobNote>,
+    /// IDs of jobs that must finish before this one starts (see
+    /// [`crate::dependencies`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+}
+
+/// A backup of a replaced original that was kept.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeptBackup {
+    /// Where the backup is.
+    pub path: PathBuf,
+    /// Unix timestamp (milliseconds) when the original was replaced.
+    pub kept_at: i64,
+}
+
+/// Encode settings for one job that replace the configured ones, for
+/// one-off experiments.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JobOverrides {
+    /// Replaces the SVT-AV1 `--crf`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crf: Option<u8>,
+    /// Replaces the SVT-AV1 `--preset`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<u8>,
+    /// Av1an workers for the encode, instead of the planned count.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workers: Option<u32>,
+    /// Keep the encode whatever its size compared to the original.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip_size_gate: bool,
+}
+
+impl JobOverrides {
+    /// Check if nothing is overridden.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Checks the values against what SVT-AV1 and Av1an accept.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(crf) = self.crf.filter(|crf| !CRF_RANGE.contains(crf)) {
+            return Err(format!(
+                "crf must be {}-{}, got {}",
+                CRF_RANGE.start(),
+                CRF_RANGE.end(),
+                crf
+            ));
+        }
+        if let Some(preset) = self.preset.filter(|preset| !PRESET_RANGE.contains(preset)) {
+            return Err(format!(
+                "preset must be {}-{}, got {}",
+                PRESET_RANGE.start(),
+                PRESET_RANGE.end(),
+                preset
+            ));
+        }
+        if self.workers == Some(0) {
+            return Err("workers must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A free-text note an operator attached to a job, e.g. why an encode was
+/// rejected.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JobNote {
+    pub text: String,
+    /// Unix timestamp (milliseconds) when the note was added.
+    pub added_at: i64,
 }
 
 impl Job {
@@ -142,11 +282,11 @@ impl Job {
         self.touch();
     }
 
-    /// Check if the job is in a terminal state (success, failed, or skipped).
+    /// Check if the job is in a terminal state (success, failed, skipped, or cancelled).
     pub fn is_terminal(&self) -> bool {
         matches!(
             self.status,
-            JobStatus::Success | JobStatus::Failed | JobStatus::Skipped
+            JobStatus::Success | JobStatus::Failed | JobStatus::Skipped | JobStatus::Cancelled
         )
     }
 
@@ -154,8 +294,102 @@ impl Job {
     pub fn is_active(&self) -> bool {
         matches!(self.status, JobStatus::Pending | JobStatus::Running)
     }
+
+    /// Check if the job is waiting in the queue to start.
+    pub fn is_queued(&self) -> bool {
+        self.status == JobStatus::Pending && self.stage == JobStage::Queued
+    }
+
+    /// Check if the job carries the given tag (case-insensitive).
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim().to_lowercase();
+        self.tags.contains(&tag)
+    }
+
+    /// Add a tag, normalized to lowercase. Duplicates and blank tags are ignored.
+    pub fn add_tag(&mut self, tag: &str) {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !self.has_tag(&tag) {
+            self.tags.push(tag);
+        }
+    }
+
+    /// Add a note, trimmed. Blank notes are ignored.
+    ///
+    /// # Returns
+    /// True if the note was added
+    pub fn add_note(&mut self, text: &str) -> bool {
+        let text = text.trim();
+        if text.is_empty() {
+            return false;
+        }
+        self.notes.push(JobNote {
+            text: text.to_string(),
+            added_at: current_timestamp_ms(),
+        });
+        true
+    }
 }
 
+/// Criteria for selecting jobs by tag and status.
+///
+/// A job matches when it carries every listed tag and, if a status is given,
+/// is in that status. An empty filter matches everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobFilter {
+    pub tags: Vec<String>,
+    pub status: Option<JobStatus>,
+    pub stage: Option<JobStage>,
+    /// SVT-AV1 version, or a prefix of it, the job was encoded with
+    pub encoder_version: Option<String>,
+}
+
+impl JobFilter {
+    /// Returns true if the job satisfies the filter.
+    pub fn matches(&self, job: &Job) -> bool {
+        self.status.is_none_or(|status| job.status == status)
+            && self.stage.is_none_or(|stage| job.stage == stage)
+            && self.encoder_version.as_deref().is_none_or(|version| {
+                job.tool_versions
+                    .as_ref()
+                    .is_some_and(|tools| tools.has_encoder_version(version))
+            })
+            && self.tags.iter().all(|tag| job.has_tag(tag))
+    }
+}
+
+/// Builds the tags attached to every new job.
+///
+/// Includes `library:<name>` for the library root, the source class (`web`,
+/// `disc`, `animation`), the media kind (`series`, `movie`), and the
+/// resolution class of the first video stream (`4k`, `1080p`, `720p`, `sd`).
+pub fn auto_tags(candidate: &ScanCandidate, source_type: SourceType, probe: &ProbeResult) -> Vec<String> {
+    let mut tags = Vec::new();
+
+    if let Some(name) = candidate.root.file_name().and_then(|n| n.to_str()) {
+        tags.push(format!("library:{}", name.to_lowercase()));
+    }
+
+    match source_type {
+        SourceType::WebLike => tags.push("web".to_string()),
+        SourceType::DiscLike => tags.push("disc".to_string()),
+        SourceType::Animation => tags.push("animation".to_string()),
+        SourceType::Unknown => {}
+    }
+
+    tags.push(classify_media_kind(&candidate.path).to_string());
+
+    if let Some(video) = probe.video_streams.first() {
+        tags.push(resolution_class(video.width, video.height).to_string());
+    }
+
+    tags
+}
+
+
+fn is_zero(value: &i64) -> bool {
+    *value == 0
+}
 
 /// Get current timestamp in milliseconds since Unix epoch.
 fn current_timestamp_ms() -> i64 {
@@ -168,6 +402,8 @@ fn current_timestamp_ms() -> i64 {
 /// Creates a new job from a scan candidate, probe result, and source type.
 ///
 /// Generates a UUID for the job id, sets initial stage to Queued and status to Pending.
+/// The media kind and season batch key are derived from the candidate path, and
+/// the job is tagged via [`auto_tags`].
 ///
 /// # Arguments
 /// * `candidate` - The scan candidate containing input path and file info
@@ -186,6 +422,7 @@ pub fn create_job(
     // Generate output path in temp directory
     let output_filename = format!("{}.mkv", id);
     let output_path = temp_output_dir.join(output_filename);
+    let tags = auto_tags(candidate, source_type, &probe_result);
 
     Job {
         id,
@@ -194,10 +431,26 @@ pub fn create_job(
         stage: JobStage::Queued,
         status: JobStatus::Pending,
         source_type,
+        media_kind: classify_media_kind(&candidate.path),
+        batch_key: season_key(&candidate.path),
+        tags,
         probe_result,
+        kind: JobKind::Encode,
+        priority: 0,
+        forced: false,
+        overrides: JobOverrides::default(),
         created_at: now,
         updated_at: now,
         error_reason: None,
+        stage_secs: BTreeMap::new(),
+        backup: None,
+        encode_command: None,
+        tool_versions: None,
+        encode_settings: None,
+        triage_bundle: None,
+        failure_kind: None,
+        notes: Vec::new(),
+        depends_on: Vec::new(),
     }
 }
 
@@ -245,7 +498,7 @@ pub fn load_jobs(state_dir: &Path) -> Result<Vec<Job>, io::Error> {
             Ok(job) => jobs.push(job),
             Err(e) => {
                 // Log warning but continue loading other jobs
-                eprintln!("Warning: Failed to load job from {:?}: {}", path, e);
+                log_warn!("Warning: Failed to load job from {:?}: {}", path, e);
             }
         }
     }
@@ -261,17 +514,150 @@ fn load_job_from_file(path: &Path) -> Result<Job, io::Error> {
 
 /// Checks if a job already exists for the given input path.
 ///
-/// Returns true if any pending or running job exists for the path.
+/// Returns true if any pending or running job exists for the path. Failed
+/// and cancelled jobs also count, so the scanner never retries them on its
+/// own; requeueing the path clears them.
 ///
 /// # Arguments
 /// * `jobs` - List of existing jobs to check
 /// * `path` - Input path to check for
 pub fn job_exists_for_path(jobs: &[Job], path: &Path) -> bool {
     jobs.iter().any(|job| {
-        job.input_path == path && job.is_active()
+        job.input_path == path
+            && (job.is_active() || matches!(job.status, JobStatus::Failed | JobStatus::Cancelled))
+    })
+}
+
+/// Path of the JSON file for job `id`, or `None` if `id` is not a valid job ID.
+fn job_file_path(state_dir: &Path, id: &str) -> Option<PathBuf> {
+    // IDs are UUIDs; anything else cannot name a job file
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    Some(state_dir.join(format!("{}.json", id)))
+}
+
+/// Loads the persisted job with `id`.
+///
+/// # Returns
+/// The job, or `None` if no job has that ID
+pub fn load_job(state_dir: &Path, id: &str) -> Result<Option<Job>, io::Error> {
+    match job_file_path(state_dir, id).filter(|p| p.exists()) {
+        Some(path) => load_job_from_file(&path).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Loads job `id`, applies `update` to it, and saves it back.
+///
+/// # Returns
+/// The updated job, or `None` if no job has that ID
+pub fn update_job<F>(state_dir: &Path, id: &str, update: F) -> Result<Option<Job>, io::Error>
+where
+    F: FnOnce(&mut Job),
+{
+    let Some(path) = job_file_path(state_dir, id).filter(|p| p.exists()) else {
+        return Ok(None);
+    };
+    let mut job = load_job_from_file(&path)?;
+    update(&mut job);
+    job.touch();
+    save_job(&job, state_dir)?;
+    Ok(Some(job))
+}
+
+/// Attaches the note `text` to the persisted job with `id`.
+///
+/// # Returns
+/// The updated job, or `None` if no job has that ID
+pub fn add_job_note(state_dir: &Path, id: &str, text: &str) -> Result<Option<Job>, io::Error> {
+    update_job(state_dir, id, |job| {
+        job.add_note(text);
     })
 }
 
+/// Order in which queued jobs start: highest priority first, then oldest.
+pub fn queue_order(a: &Job, b: &Job) -> std::cmp::Ordering {
+    b.priority
+        .cmp(&a.priority)
+        .then_with(|| a.created_at.cmp(&b.created_at))
+        .then_with(|| a.id.cmp(&b.id))
+}
+
+/// Moves the queued jobs in `ids` to the front of the queue, in that order.
+///
+/// The listed jobs get priorities above every other queued job; the rest
+/// keep their place behind them.
+///
+/// # Returns
+/// The queued jobs in their new order, or an `InvalidInput` error naming an
+/// ID that is not a queued job
+pub fn reorder_queue(state_dir: &Path, ids: &[String]) -> Result<Vec<Job>, io::Error> {
+    let mut queued: Vec<Job> = load_jobs(state_dir)?
+        .into_iter()
+        .filter(Job::is_queued)
+        .collect();
+    if let Some(id) = ids.iter().find(|id| !queued.iter().any(|job| &job.id == *id)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a queued job", id),
+        ));
+    }
+
+    let top = queued.iter().map(|job| job.priority).max().unwrap_or(0).max(0);
+    for (rank, id) in ids.iter().enumerate() {
+        let priority = top + (ids.len() - rank) as i64;
+        if let Some(job) = queued.iter_mut().find(|job| &job.id == id) {
+            job.priority = priority;
+            job.touch();
+            save_job(job, state_dir)?;
+        }
+    }
+    queued.sort_by(queue_order);
+    Ok(queued)
+}
+
+/// Marks the persisted job with `id` as cancelled.
+///
+/// Jobs that are no longer pending or running are left unchanged.
+///
+/// # Returns
+/// The status the job had before the call, or `None` if no job has that ID
+pub fn cancel_job(state_dir: &Path, id: &str) -> Result<Option<JobStatus>, io::Error> {
+    let mut previous = None;
+    update_job(state_dir, id, |job| {
+        previous = Some(job.status);
+        if job.is_active() {
+            job.status = JobStatus::Cancelled;
+            job.error_reason = Some("cancelled on request".to_string());
+        }
+    })?;
+    Ok(previous)
+}
+
+/// Deletes the persisted records of finished jobs for the given input path.
+///
+/// Succeeded, failed, and skipped jobs are removed so the path can be
+/// processed again, along with their journals; pending and running jobs are
+/// left in place.
+///
+/// # Returns
+/// The number of job files removed
+pub fn remove_terminal_jobs_for_path(state_dir: &Path, path: &Path) -> Result<usize, io::Error> {
+    let mut removed = 0;
+    for job in load_jobs(state_dir)? {
+        if job.input_path == path && job.is_terminal() {
+            fs::remove_file(state_dir.join(format!("{}.json", job.id)))?;
+            match fs::remove_file(journal_path(state_dir, &job.id)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -287,6 +673,10 @@ mod tests {
             width,
             height,
             bitrate_kbps: Some(5000.0),
+            pix_fmt: None,
+            duration_secs: None,
+            attached_pic: false,
+            ..Default::default()
         }
     }
 
@@ -295,6 +685,9 @@ mod tests {
         AudioStream {
             codec_name: codec.to_string(),
             channels,
+            language: None,
+            title: None,
+            ..Default::default()
         }
     }
 
@@ -303,6 +696,8 @@ mod tests {
         ProbeResult {
             video_streams: vec![make_video_stream("hevc", 1920, 1080)],
             audio_streams: vec![make_audio_stream("aac", 6)],
+            subtitle_streams: vec![],
+            font_attachments: 0,
             format: FormatInfo {
                 duration_secs: 7200.0,
                 size_bytes: 22548578304,
@@ -316,6 +711,7 @@ mod tests {
             path: PathBuf::from(path),
             size_bytes: 5_000_000_000,
             modified_time: SystemTime::now(),
+            root: PathBuf::from("/media"),
         }
     }
 
@@ -324,6 +720,7 @@ mod tests {
         prop_oneof![
             Just(SourceType::WebLike),
             Just(SourceType::DiscLike),
+            Just(SourceType::Animation),
             Just(SourceType::Unknown),
         ]
     }
@@ -337,6 +734,8 @@ mod tests {
             Just(JobStage::SizeGating),
             Just(JobStage::Replacing),
             Just(JobStage::Complete),
+            Just(JobStage::AwaitingApproval),
+            Just(JobStage::ReadyToReplace),
         ]
     }
 
@@ -364,6 +763,10 @@ mod tests {
                 width,
                 height,
                 bitrate_kbps: bitrate,
+                pix_fmt: None,
+                duration_secs: None,
+                attached_pic: false,
+                ..Default::default()
             })
     }
 
@@ -372,6 +775,9 @@ mod tests {
         ("[a-z0-9]{2,10}", 1u32..16).prop_map(|(codec, channels)| AudioStream {
             codec_name: codec,
             channels,
+            language: None,
+            title: None,
+            ..Default::default()
         })
     }
 
@@ -386,6 +792,8 @@ mod tests {
             .prop_map(|(video_streams, audio_streams, duration, size)| ProbeResult {
                 video_streams,
                 audio_streams,
+                subtitle_streams: vec![],
+                font_attachments: 0,
                 format: FormatInfo {
                     duration_secs: duration,
                     size_bytes: size,
@@ -416,10 +824,26 @@ mod tests {
                         stage,
                         status,
                         source_type,
+                        media_kind: MediaKind::Movie,
+                        batch_key: None,
+                        tags: vec!["library:movies".to_string(), "4k".to_string()],
                         probe_result: probe,
+                        kind: JobKind::Encode,
+                        priority: 0,
+                        forced: false,
+                        overrides: JobOverrides::default(),
                         created_at: created,
                         updated_at: updated,
                         error_reason: error,
+                        stage_secs: BTreeMap::new(),
+                        backup: None,
+                        encode_command: None,
+                        tool_versions: None,
+                        encode_settings: None,
+                        triage_bundle: None,
+                        failure_kind: None,
+                        notes: Vec::new(),
+        depends_on: Vec::new(),
                     }
                 },
             )
@@ -485,6 +909,11 @@ mod tests {
         assert_eq!(format!("{}", JobStage::SizeGating), "size_gating");
         assert_eq!(format!("{}", JobStage::Replacing), "replacing");
         assert_eq!(format!("{}", JobStage::Complete), "complete");
+        assert_eq!(
+            format!("{}", JobStage::AwaitingApproval),
+            "awaiting_approval"
+        );
+        assert_eq!(format!("{}", JobStage::ReadyToReplace), "ready_to_replace");
     }
 
     #[test]
@@ -528,12 +957,92 @@ mod tests {
         assert!(job.created_at > 0);
         assert_eq!(job.created_at, job.updated_at);
         assert!(job.error_reason.is_none());
+        assert_eq!(job.media_kind, MediaKind::Movie);
+        assert!(job.batch_key.is_none());
 
         // Check probe result is stored
         assert_eq!(job.probe_result.video_streams.len(), 1);
         assert_eq!(job.probe_result.video_streams[0].codec_name, "hevc");
     }
 
+    #[test]
+    fn test_create_job_series_batch_key() {
+        let candidate = make_scan_candidate("/media/tv/Show/Season 2/Show.S02E03.mkv");
+        let job = create_job(
+            &candidate,
+            make_probe_result(),
+            SourceType::WebLike,
+            Path::new("/tmp/av1-daemon"),
+        );
+
+        assert_eq!(job.media_kind, MediaKind::Series);
+        assert_eq!(job.batch_key, Some("show/s02".to_string()));
+    }
+
+    #[test]
+    fn test_create_job_auto_tags() {
+        let candidate = make_scan_candidate("/media/movies/Film.2020.BluRay.mkv");
+        let job = create_job(
+            &candidate,
+            make_probe_result(),
+            SourceType::DiscLike,
+            Path::new("/tmp/av1-daemon"),
+        );
+
+        assert!(job.has_tag("library:media"));
+        assert!(job.has_tag("disc"));
+        assert!(job.has_tag("movie"));
+        assert!(job.has_tag("1080p"));
+        assert!(job.has_tag("DISC"));
+    }
+
+    #[test]
+    fn test_job_filter_by_tags_and_status() {
+        let candidate = make_scan_candidate("/media/movies/film.mkv");
+        let mut job = create_job(
+            &candidate,
+            make_probe_result(),
+            SourceType::DiscLike,
+            Path::new("/tmp/av1-daemon"),
+        );
+        job.add_tag("4K");
+        job.add_tag("4k");
+        assert_eq!(job.tags.iter().filter(|t| *t == "4k").count(), 1);
+
+        let failed_4k_disc = JobFilter {
+            tags: vec!["4k".to_string(), "disc".to_string()],
+            status: Some(JobStatus::Failed),
+            stage: None,
+            encoder_version: None,
+        };
+        assert!(!failed_4k_disc.matches(&job));
+
+        job.fail("encode error");
+        assert!(failed_4k_disc.matches(&job));
+        assert!(JobFilter::default().matches(&job));
+
+        let web = JobFilter {
+            tags: vec!["web".to_string()],
+            status: None,
+            stage: None,
+            encoder_version: None,
+        };
+        assert!(!web.matches(&job));
+
+        let svt_2_1 = JobFilter {
+            encoder_version: Some("2.1".to_string()),
+            ..Default::default()
+        };
+        assert!(!svt_2_1.matches(&job));
+        job.tool_versions = Some(ToolVersions {
+            daemon: "0.1.0".to_string(),
+            av1an: None,
+            ffmpeg: None,
+            svt_av1: Some("2.1.2".to_string()),
+        });
+        assert!(svt_2_1.matches(&job));
+    }
+
     #[test]
     fn test_job_touch() {
         let candidate = make_scan_candidate("/media/movies/film.mkv");
@@ -739,6 +1248,11 @@ mod tests {
         // Should NOT find job for film2 (completed, not active)
         assert!(!job_exists_for_path(&jobs, Path::new("/media/movies/film2.mkv")));
 
+        // A failed job is not retried until it is requeued
+        job2.set_status(JobStatus::Failed);
+        assert!(job_exists_for_path(std::slice::from_ref(&job2), Path::new("/media/movies/film2.mkv")));
+        job2.set_status(JobStatus::Success);
+
         // Should NOT find job for unknown path
         assert!(!job_exists_for_path(&jobs, Path::new("/media/movies/film3.mkv")));
 
@@ -748,6 +1262,109 @@ mod tests {
         assert!(job_exists_for_path(&jobs, Path::new("/media/movies/film1.mkv")));
     }
 
+    #[test]
+    fn test_cancel_job_marks_active_jobs_only() {
+        let temp = TempDir::new().unwrap();
+        let probe = make_probe_result();
+        let temp_dir = PathBuf::from("/tmp/av1-daemon");
+
+        let pending = create_job(&make_scan_candidate("/media/a.mkv"), probe.clone(), SourceType::Unknown, &temp_dir);
+        let mut failed = create_job(&make_scan_candidate("/media/b.mkv"), probe, SourceType::Unknown, &temp_dir);
+        failed.fail("boom");
+        save_job(&pending, temp.path()).unwrap();
+        save_job(&failed, temp.path()).unwrap();
+
+        assert_eq!(cancel_job(temp.path(), &pending.id).unwrap(), Some(JobStatus::Pending));
+        assert_eq!(cancel_job(temp.path(), &pending.id).unwrap(), Some(JobStatus::Cancelled));
+        assert_eq!(cancel_job(temp.path(), &failed.id).unwrap(), Some(JobStatus::Failed));
+        assert!(cancel_job(temp.path(), "missing").unwrap().is_none());
+        assert!(cancel_job(temp.path(), "../escape").unwrap().is_none());
+
+        // Cancelled and failed jobs are terminal but keep the scanner away
+        let jobs = load_jobs(temp.path()).unwrap();
+        assert!(jobs.iter().all(|j| j.is_terminal()));
+        assert!(job_exists_for_path(&jobs, Path::new("/media/a.mkv")));
+        assert!(job_exists_for_path(&jobs, Path::new("/media/b.mkv")));
+    }
+
+    #[test]
+    fn test_job_overrides_validate_and_round_trip() {
+        assert!(JobOverrides::default().is_empty());
+        assert!(JobOverrides::default().validate().is_ok());
+
+        let overrides = JobOverrides {
+            crf: Some(30),
+            preset: Some(4),
+            workers: Some(2),
+            skip_size_gate: true,
+        };
+        assert!(overrides.validate().is_ok());
+        let json = serde_json::to_value(overrides).unwrap();
+        assert_eq!(json, serde_json::json!({ "crf": 30, "preset": 4, "workers": 2, "skip_size_gate": true }));
+        assert_eq!(serde_json::from_value::<JobOverrides>(json).unwrap(), overrides);
+
+        let bad = |overrides: JobOverrides| overrides.validate().unwrap_err();
+        assert_eq!(bad(JobOverrides { crf: Some(64), ..Default::default() }), "crf must be 1-63, got 64");
+        assert_eq!(bad(JobOverrides { preset: Some(14), ..Default::default() }), "preset must be 0-13, got 14");
+        assert_eq!(bad(JobOverrides { workers: Some(0), ..Default::default() }), "workers must be at least 1");
+    }
+
+    #[test]
+    fn test_add_job_note_persists_notes() {
+        let temp = TempDir::new().unwrap();
+        let job = create_job(
+            &make_scan_candidate("/media/a.mkv"),
+            make_probe_result(),
+            SourceType::Unknown,
+            Path::new("/tmp/av1-daemon"),
+        );
+        save_job(&job, temp.path()).unwrap();
+
+        add_job_note(temp.path(), &job.id, "  VMAF looked soft on dark scenes ").unwrap();
+        add_job_note(temp.path(), &job.id, "   ").unwrap();
+        add_job_note(temp.path(), &job.id, "rejected").unwrap();
+        assert!(add_job_note(temp.path(), "missing", "note").unwrap().is_none());
+
+        let loaded = load_job(temp.path(), &job.id).unwrap().unwrap();
+        let texts: Vec<_> = loaded.notes.iter().map(|n| n.text.as_str()).collect();
+        assert_eq!(texts, vec!["VMAF looked soft on dark scenes", "rejected"]);
+        assert!(loaded.notes[0].added_at <= loaded.notes[1].added_at);
+    }
+
+    #[test]
+    fn test_reorder_queue_moves_jobs_to_front() {
+        let temp = TempDir::new().unwrap();
+        let temp_dir = PathBuf::from("/tmp/av1-daemon");
+        let mut ids = Vec::new();
+        for (i, name) in ["a", "b", "c", "d"].iter().enumerate() {
+            let mut job = create_job(
+                &make_scan_candidate(&format!("/media/{}.mkv", name)),
+                make_probe_result(),
+                SourceType::Unknown,
+                &temp_dir,
+            );
+            job.created_at = i as i64;
+            save_job(&job, temp.path()).unwrap();
+            ids.push(job.id);
+        }
+        let order = |jobs: &[Job]| jobs.iter().map(|j| j.id.clone()).collect::<Vec<_>>();
+
+        let queue = reorder_queue(temp.path(), &[ids[3].clone()]).unwrap();
+        assert_eq!(order(&queue), vec![ids[3].clone(), ids[0].clone(), ids[1].clone(), ids[2].clone()]);
+
+        // A later bump goes ahead of the earlier one
+        let queue = reorder_queue(temp.path(), &[ids[2].clone(), ids[1].clone()]).unwrap();
+        assert_eq!(order(&queue), vec![ids[2].clone(), ids[1].clone(), ids[3].clone(), ids[0].clone()]);
+
+        // Priorities are persisted
+        let mut jobs = load_jobs(temp.path()).unwrap();
+        jobs.sort_by(queue_order);
+        assert_eq!(order(&jobs), order(&queue));
+
+        let err = reorder_queue(temp.path(), &["missing".to_string()]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
     #[test]
     fn test_save_job_creates_directory() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,352 @@
+//! Write-ahead stage journal for persisted jobs.
+//!
+//! The job JSON only holds the latest stage, and is rewritten in place on
+//! every transition. The journal sits next to it as `{id}.journal.jsonl` and
+//! only ever grows: each transition is appended and synced to disk before the
+//! job file is updated, so after a crash the last journal line says where the
+//! job really was, even if the job file itself is stale.
+//!
+//! On startup, [`recover_interrupted_jobs`] uses the journal to decide what
+//! to do with jobs that were still pending or running. Anything interrupted
+//! before replacement began left the original untouched and is resumed by
+//! encoding again. A job interrupted while replacing is repaired from its
+//! `.av1swap` marker (see [`crate::replace::repair_swap`]): finished if the
+//! encoded file made it, resumed if the original was put back. Only when
+//! there is no marker to go on is the job failed and left for the operator.
+//!
+//! The `.jsonl` extension keeps [`crate::jobs::load_jobs`] from reading
+//! journals as jobs.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::jobs::{load_jobs, update_job, Job, JobStage, JobStatus};
+use crate::replace::{repair_swap, SwapIntent, SwapRepair};
+
+/// Suffix of journal files inside `job_state_dir`.
+pub const JOURNAL_SUFFIX: &str = ".journal.jsonl";
+
+/// One recorded stage transition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// When the transition happened, in Unix milliseconds.
+    pub at_unix_ms: i64,
+    pub stage: JobStage,
+    pub status: JobStatus,
+    /// Failure or skip reason, or a note from recovery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl JournalEntry {
+    /// Creates an entry stamped with the current time.
+    pub fn now(stage: JobStage, status: JobStatus, detail: Option<String>) -> Self {
+        Self {
+            at_unix_ms: current_timestamp_ms(),
+            stage,
+            status,
+            detail,
+        }
+    }
+}
+
+/// Path of the journal for job `id`.
+pub fn journal_path(state_dir: &Path, id: &str) -> PathBuf {
+    state_dir.join(format!("{}{}", id, JOURNAL_SUFFIX))
+}
+
+/// Appends `entry` to the journal of job `id` and syncs it to disk.
+pub fn append_entry(state_dir: &Path, id: &str, entry: &JournalEntry) -> io::Result<()> {
+    fs::create_dir_all(state_dir)?;
+    let mut line =
+        serde_json::to_string(entry).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal_path(state_dir, id))?;
+    file.write_all(line.as_bytes())?;
+    file.sync_data()
+}
+
+/// Reads the journal of job `id`, oldest entry first.
+///
+/// A missing journal reads as empty. Lines that fail to parse, such as one
+/// torn by a crash mid-append, are dropped.
+pub fn read_journal(state_dir: &Path, id: &str) -> io::Result<Vec<JournalEntry>> {
+    let content = match fs::read_to_string(journal_path(state_dir, id)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// What to do with a job that was interrupted by a restart.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecoveryAction {
+    /// The original is untouched, or was put back; encode it again.
+    Resume,
+    /// An interrupted replacement was completed from its swap marker; the
+    /// steps that follow a replacement are still to run for its target.
+    Finished(SwapIntent),
+    /// Replacement had begun and could not be repaired; fail the job so the
+    /// original and its backup can be checked before anything else happens
+    /// to them.
+    Fail,
+}
+
+/// Decides how to recover `job` from its journal.
+///
+/// Returns `None` for jobs that finished or are held for approval or the
+/// replace window, and [`RecoveryAction::Fail`] for jobs stopped while
+/// replacing, before any swap marker is looked at. The journal wins over
+/// the job file because it is written first.
+pub fn recovery_action(job: &Job, entries: &[JournalEntry]) -> Option<RecoveryAction> {
+    let (stage, status) = entries
+        .last()
+        .map(|entry| (entry.stage, entry.status))
+        .unwrap_or((job.stage, job.status));
+    if !matches!(status, JobStatus::Pending | JobStatus::Running) {
+        return None;
+    }
+    match stage {
+        JobStage::AwaitingApproval | JobStage::ReadyToReplace => None,
+        JobStage::Replacing | JobStage::Complete => Some(RecoveryAction::Fail),
+        _ => Some(RecoveryAction::Resume),
+    }
+}
+
+/// Brings jobs left pending or running by the previous run back to a state
+/// the daemon can act on.
+///
+/// Resumed jobs are reset to queued and returned for resubmission, repaired
+/// replacements are marked complete, and jobs that cannot be repaired are
+/// marked failed. Jobs whose journal shows they finished have their job
+/// file brought up to date. Every change is journaled.
+///
+/// # Returns
+/// Each interrupted job after recovery, with the action taken
+pub fn recover_interrupted_jobs(state_dir: &Path) -> io::Result<Vec<(Job, RecoveryAction)>> {
+    let mut recovered = Vec::new();
+    for job in load_jobs(state_dir)?.into_iter().filter(|job| job.is_active()) {
+        let entries = read_journal(state_dir, &job.id)?;
+        let mut action = recovery_action(&job, &entries);
+        let mut detail = "resumed after restart".to_string();
+        if action == Some(RecoveryAction::Fail) {
+            match repair_swap(&job.input_path) {
+                Ok(Some(SwapRepair::Finished(intent))) => action = Some(RecoveryAction::Finished(intent)),
+                Ok(Some(SwapRepair::RolledBack)) => {
+                    action = Some(RecoveryAction::Resume);
+                    detail = "original restored after interrupted replacement".to_string();
+                }
+                Ok(None) => {
+                    detail = "interrupted during replacement; check the original and its backup".to_string()
+                }
+                Err(e) => detail = format!("failed to repair interrupted replacement: {}", e),
+            }
+        }
+
+        let entry = match action {
+            Some(RecoveryAction::Resume) => {
+                JournalEntry::now(JobStage::Queued, JobStatus::Pending, Some(detail))
+            }
+            Some(RecoveryAction::Finished(_)) => JournalEntry::now(
+                JobStage::Complete,
+                JobStatus::Success,
+                Some("replacement finished after restart".to_string()),
+            ),
+            Some(RecoveryAction::Fail) => {
+                let stage = entries.last().map_or(job.stage, |entry| entry.stage);
+                JournalEntry::now(stage, JobStatus::Failed, Some(detail))
+            }
+            // The job finished but the crash beat the job file update
+            None => match entries.last() {
+                Some(last) => last.clone(),
+                None => continue,
+            },
+        };
+
+        if action.is_some() {
+            append_entry(state_dir, &job.id, &entry)?;
+        }
+        let updated = update_job(state_dir, &job.id, |job| {
+            job.stage = entry.stage;
+            job.status = entry.status;
+            if entry.status == JobStatus::Failed {
+                job.error_reason = entry.detail.clone();
+            }
+        })?;
+        if let (Some(action), Some(job)) = (action, updated) {
+            recovered.push((job, action));
+        }
+    }
+    Ok(recovered)
+}
+
+fn current_timestamp_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::{FormatInfo, ProbeResult};
+    use crate::jobs::{create_job, save_job};
+    use crate::scan::ScanCandidate;
+    use tempfile::TempDir;
+
+    fn persisted_job(state_dir: &Path, input: &Path) -> Job {
+        let candidate = ScanCandidate {
+            path: input.to_path_buf(),
+            size_bytes: 1000,
+            modified_time: UNIX_EPOCH,
+            root: PathBuf::from("/media"),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 60.0,
+                size_bytes: 1000,
+            },
+        };
+        let job = create_job(&candidate, probe, Default::default(), Path::new("/tmp"));
+        save_job(&job, state_dir).unwrap();
+        job
+    }
+
+    fn record(state_dir: &Path, job: &Job, stage: JobStage, status: JobStatus) {
+        append_entry(state_dir, &job.id, &JournalEntry::now(stage, status, None)).unwrap();
+    }
+
+    #[test]
+    fn test_append_and_read_skips_torn_lines() {
+        let temp = TempDir::new().unwrap();
+        record(temp.path(), &persisted_job(temp.path(), Path::new("/media/a.mkv")), JobStage::Encoding, JobStatus::Running);
+        let job = load_jobs(temp.path()).unwrap().remove(0);
+        record(temp.path(), &job, JobStage::Validating, JobStatus::Running);
+
+        let mut file = OpenOptions::new().append(true).open(journal_path(temp.path(), &job.id)).unwrap();
+        file.write_all(b"{\"at_unix_ms\":1,\"sta").unwrap();
+
+        let entries = read_journal(temp.path(), &job.id).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].stage, JobStage::Validating);
+        assert!(read_journal(temp.path(), "missing").unwrap().is_empty());
+        // Journals are not read back as jobs
+        assert_eq!(load_jobs(temp.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_recovery_action_follows_last_entry() {
+        let temp = TempDir::new().unwrap();
+        let job = persisted_job(temp.path(), Path::new("/media/a.mkv"));
+        let entry = |stage, status| JournalEntry::now(stage, status, None);
+
+        assert_eq!(recovery_action(&job, &[]), Some(RecoveryAction::Resume));
+        assert_eq!(
+            recovery_action(&job, &[entry(JobStage::Encoding, JobStatus::Running)]),
+            Some(RecoveryAction::Resume)
+        );
+        assert_eq!(
+            recovery_action(&job, &[entry(JobStage::Replacing, JobStatus::Running)]),
+            Some(RecoveryAction::Fail)
+        );
+        assert_eq!(
+            recovery_action(&job, &[entry(JobStage::Complete, JobStatus::Success)]),
+            None
+        );
+        assert_eq!(
+            recovery_action(
+                &job,
+                &[entry(JobStage::AwaitingApproval, JobStatus::Pending)]
+            ),
+            None
+        );
+        assert_eq!(
+            recovery_action(&job, &[entry(JobStage::ReadyToReplace, JobStatus::Pending)]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_recover_interrupted_jobs() {
+        let temp = TempDir::new().unwrap();
+        let state_dir = temp.path();
+        let encoding = persisted_job(state_dir, Path::new("/media/encoding.mkv"));
+        record(state_dir, &encoding, JobStage::Encoding, JobStatus::Running);
+        let replacing = persisted_job(state_dir, Path::new("/media/replacing.mkv"));
+        record(state_dir, &replacing, JobStage::Replacing, JobStatus::Running);
+        let finished = persisted_job(state_dir, Path::new("/media/finished.mkv"));
+        record(state_dir, &finished, JobStage::Complete, JobStatus::Success);
+
+        let recovered = recover_interrupted_jobs(state_dir).unwrap();
+        assert_eq!(recovered.len(), 2);
+
+        let jobs = load_jobs(state_dir).unwrap();
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert_eq!(find(&encoding.id).status, JobStatus::Pending);
+        assert_eq!(find(&encoding.id).stage, JobStage::Queued);
+        assert_eq!(find(&replacing.id).status, JobStatus::Failed);
+        assert_eq!(find(&replacing.id).stage, JobStage::Replacing);
+        assert_eq!(find(&finished.id).status, JobStatus::Success);
+
+        let journal = read_journal(state_dir, &encoding.id).unwrap();
+        assert_eq!(journal.last().unwrap().detail.as_deref(), Some("resumed after restart"));
+
+        // Only the resumed job, which never ran again, is picked up next time
+        let again = recover_interrupted_jobs(state_dir).unwrap();
+        assert_eq!(again.len(), 1);
+        assert_eq!(again[0].1, RecoveryAction::Resume);
+    }
+
+    #[test]
+    fn test_recover_repairs_interrupted_replacement() {
+        let temp = TempDir::new().unwrap();
+        let state_dir = temp.path().join("jobs");
+        let original = temp.path().join("film.mkv");
+        let encoded = temp.path().join("encoded.mkv");
+        fs::write(&original, b"original").unwrap();
+        fs::write(&encoded, b"encoded").unwrap();
+
+        // Crash between the backup and the copy: the marker is left behind
+        let job = persisted_job(&state_dir, &original);
+        record(&state_dir, &job, JobStage::Replacing, JobStatus::Running);
+        let backup = temp.path().join("film.mkv.orig.1");
+        fs::rename(&original, &backup).unwrap();
+        let marker = crate::replace::SwapIntent {
+            original: original.clone(),
+            encoded: encoded.clone(),
+            target: original.clone(),
+            backup: backup.clone(),
+            keep_original: false,
+            phase: crate::replace::SwapPhase::Copying,
+        };
+        fs::write(
+            crate::replace::swap_marker_path(&original),
+            serde_json::to_string(&marker).unwrap(),
+        )
+        .unwrap();
+
+        let recovered = recover_interrupted_jobs(&state_dir).unwrap();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].1, RecoveryAction::Finished(marker));
+        assert_eq!(recovered[0].0.status, JobStatus::Success);
+        assert_eq!(recovered[0].0.stage, JobStage::Complete);
+        assert_eq!(fs::read(&original).unwrap(), b"encoded");
+        assert!(!backup.exists());
+    }
+}
@@ -0,0 +1,111 @@
+//! Global kill switch.
+//!
+//! While the configured sentinel file (`kill_switch.file`) exists, the
+//! daemon starts no scans and no jobs, queued jobs waiting at their slot
+//! until it is removed; running jobs finish
+//! normally unless `kill_switch.suspend_running` is set, in which case their
+//! encoder process groups are stopped until the file is removed. An
+//! operator halts everything with `touch`, without access to the service
+//! manager on the daemon host.
+
+use std::path::{Path, PathBuf};
+
+use crate::config::KillSwitchConfig;
+use crate::encode::{can_suspend_groups, resume_all_groups, suspend_all_groups, SuspendReason};
+use crate::metrics::MetricsSnapshot;
+
+/// Seconds between checks of the sentinel file while waiting on it.
+pub const KILL_SWITCH_POLL_SECS: u64 = 5;
+
+/// Returns true while the sentinel file at `file` exists.
+pub fn kill_switch_engaged(file: &Path) -> bool {
+    file.exists()
+}
+
+/// Tracks the sentinel file and suspends or resumes running encodes when it
+/// appears or goes away.
+#[derive(Debug, Clone)]
+pub struct KillSwitch {
+    file: PathBuf,
+    suspend_running: bool,
+    engaged: bool,
+}
+
+impl KillSwitch {
+    pub fn new(config: &KillSwitchConfig) -> Self {
+        Self {
+            file: config.file.clone(),
+            suspend_running: config.suspend_running,
+            engaged: false,
+        }
+    }
+
+    /// Path of the sentinel file.
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// Checks the sentinel file and records its state in `snapshot`.
+    ///
+    /// # Returns
+    /// True if the switch was engaged or released by this check
+    pub fn poll(&mut self, snapshot: &mut MetricsSnapshot) -> bool {
+        let engaged = kill_switch_engaged(&self.file);
+        snapshot.kill_switch.engaged = engaged;
+        if engaged == self.engaged {
+            return false;
+        }
+        self.engaged = engaged;
+
+        if engaged {
+            log_info!("Kill switch {:?} engaged, halting new work", self.file);
+            if self.suspend_running && !can_suspend_groups() {
+                log_warn!("Warning: Suspending running encodes is not supported on this platform");
+            } else if self.suspend_running {
+                let suspended = suspend_all_groups(SuspendReason::KillSwitch);
+                snapshot.kill_switch.suspended_encodes = suspended as u64;
+                log_info!("Suspended {} running encode(s)", suspended);
+            }
+        } else {
+            log_info!("Kill switch {:?} released, resuming work", self.file);
+            if self.suspend_running {
+                let resumed = resume_all_groups(SuspendReason::KillSwitch);
+                snapshot.kill_switch.suspended_encodes = 0;
+                if resumed > 0 {
+                    log_info!("Resumed {} suspended encode(s)", resumed);
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_poll_tracks_sentinel_file() {
+        let temp = TempDir::new().unwrap();
+        let config = KillSwitchConfig {
+            file: temp.path().join("STOP"),
+            suspend_running: false,
+        };
+        let mut switch = KillSwitch::new(&config);
+        let mut snapshot = MetricsSnapshot::default();
+
+        assert!(!switch.poll(&mut snapshot));
+        assert!(!snapshot.kill_switch.engaged);
+
+        fs::write(switch.file(), b"").unwrap();
+        assert!(switch.poll(&mut snapshot));
+        assert!(snapshot.kill_switch.engaged);
+        assert!(!switch.poll(&mut snapshot), "no change while the file stays");
+
+        fs::remove_file(switch.file()).unwrap();
+        assert!(switch.poll(&mut snapshot));
+        assert!(!snapshot.kill_switch.engaged);
+    }
+}
@@ -2,51 +2,195 @@
 //!
 //! Background service that manages the encoding pipeline, job queue, and metrics collection.
 
+// Declared first so the logging macros are in scope in every module
+#[macro_use]
+pub mod logging;
+
+pub mod audio_policy;
+pub mod audio_sync;
+pub mod backup_retention;
+pub mod checksums;
+pub mod claims;
 pub mod classify;
+pub mod compare;
 pub mod concurrency;
+pub mod control;
+pub mod coverage;
 pub mod daemon;
+pub mod dependencies;
+pub mod discovery;
+pub mod duplicates;
 pub mod encode;
+pub mod encode_settings;
+pub mod energy;
+pub mod failure;
+pub mod frame_check;
 pub mod gates;
+pub mod instance_lock;
 pub mod job_executor;
 pub mod jobs;
+pub mod journal;
+pub mod kill_switch;
 pub mod metrics;
 pub mod metrics_server;
+pub mod pipeline;
+pub mod pixel_format;
+pub mod polite;
+pub mod probe_cache;
+pub mod quality;
+pub mod queue_order;
+pub mod quotas;
 pub mod replace;
+pub mod replace_window;
+pub mod replacement_budget;
 pub mod scan;
+pub mod scan_cache;
+pub mod schedule;
 pub mod size_gate;
 pub mod skip_marker;
+pub mod skip_stats;
 pub mod stability;
+pub mod staging;
 pub mod startup;
+pub mod subtitles;
+pub mod system_stats;
+pub mod telemetry;
+pub mod temp_gc;
+pub mod thermal;
+pub mod timings;
+pub mod tool_versions;
+pub mod tools;
+pub mod torrent;
+pub mod triage;
+pub mod watch;
 
 pub use av1_super_daemon_config as config;
 pub use av1_super_daemon_config::Config;
-pub use concurrency::{derive_plan, ConcurrencyPlan};
+pub use concurrency::{derive_plan, ConcurrencyPlan, JobPlan, LanePlan};
 pub use daemon::{Daemon, DaemonError};
-pub use encode::{build_av1an_command, run_av1an, Av1anEncodeParams, EncodeError};
+pub use encode::{
+    active_group_count, build_av1an_command, build_remux_command, command_line, can_suspend_groups,
+    groups_suspended, is_remux_container, resume_all_groups, run_av1an, run_av1an_cancellable,
+    run_av1an_with_limits, run_remux, run_remux_as, suspend_all_groups, terminate_all_groups,
+    Av1anEncodeParams, CancelToken, EncodeError, EncoderCgroup, LineSplitter, OutputTail, SuspendReason,
+    EncodeLimits, EncodeProfile, EncoderProcess, RunAs, SvtOverrides, SvtParamError, SvtParams,
+    REMUX_SOURCE_EXTENSIONS,
+};
+pub use encode::{build_tag_command, is_taggable, write_mkv_tags, ProcessingTags, ENCODED_BY};
 pub use job_executor::{Job, JobError, JobExecutor, JobExecutorConfig, JobState};
 pub use metrics::{
-    collect_system_metrics, new_shared_metrics, JobMetrics, MetricsSnapshot, SharedMetrics,
-    SystemMetrics,
+    collect_system_metrics, new_shared_metrics, BackupMetrics, DiskMetrics, JobMetrics,
+    ControlMetrics, KillSwitchMetrics, MetricsSnapshot, PoliteMetrics, ScanMetrics,
+    EnergyMetrics, LibraryCoverage, SharedMetrics, SystemMetrics, TempMetrics, ThermalMetrics, ThroughputHistory,
+    ThroughputSample, HISTORY_CAPACITY, HISTORY_SAMPLE_INTERVAL_SECS,
+};
+pub use metrics_server::{
+    create_api_router, create_metrics_router, run_api_server, run_metrics_server, ApiState, ApproveRequest,
+    DEFAULT_API_ADDR,
+    ApproveResponse, CancelRequest, CancelResponse,
+    ControlResponse, EnergyStatsResponse, HistoryQuery, HistoryResponse, JobEnergy, JobsQuery, RejectResponse, ReorderRequest, ReorderResponse, SubmitRequest, RequeueRequest, RequeueResponse, ServerError, SkipStatsResponse,
+    JobView, TimingStatsResponse,
+};
+#[cfg(unix)]
+pub use metrics_server::run_unix_api_server;
+pub use pipeline::{
+    check_import_path, import_paths, parse_path_list, process_candidate, requeue_path, reset_path, scan_and_queue,
+    CandidateOutcome, ImportEntry, PipelineContext, ResetReport,
 };
-pub use metrics_server::{create_metrics_router, run_metrics_server, ServerError};
 pub use scan::{
-    has_skip_marker, is_video_file, scan_libraries, skip_marker_path, ScanCandidate,
-    VIDEO_EXTENSIONS,
+    candidate_for_path, force_marker_path, group_by_season, hard_link_count, has_force_marker, has_skip_marker, has_video_extension, in_library_roots, is_marked_skipped, is_video_file, order_candidates, scan_libraries,
+    scan_libraries_parallel, scan_libraries_with_count, skip_marker_path, ScanCandidate, VIDEO_EXTENSIONS,
+};
+pub use journal::{
+    append_entry, read_journal, recover_interrupted_jobs, recovery_action, JournalEntry, RecoveryAction,
+    JOURNAL_SUFFIX,
+};
+pub use instance_lock::{InstanceLock, InstanceLockError, LOCK_FILE};
+pub use kill_switch::{kill_switch_engaged, KillSwitch, KILL_SWITCH_POLL_SECS};
+pub use polite::{parse_transcode_count, run_polite_mode, tree_cpu_usage, PoliteGovernor, UsageSignals};
+pub use probe_cache::{ProbeCache, PROBE_CACHE_FILE};
+pub use quotas::{LibraryQuotas, LibraryUsage, QuotaPermit};
+pub use schedule::{schedule_jobs, JobSchedule, ProcessingRates};
+pub use scan_cache::{scan_libraries_incremental, IncrementalScanStats, ScanCache};
+pub use skip_stats::{persist_skip_stats, record_skip, SkipStats};
+pub use temp_gc::{
+    chunk_dir_job_id, collect_garbage, dir_size, run_temp_gc, temp_usage, GcReport,
+};
+pub use system_stats::{
+    io_rates, mount_index_for, parse_diskstats, IoCounters, SystemSampler, WatchedPath,
+    ROLE_LIBRARY, ROLE_TEMP,
+};
+pub use claims::{claim_path, read_claim, Claim, ClaimLease, FarmNode};
+pub use checksums::{checksum_file, rewrite_entries, update_checksum_sidecars, ChecksumKind};
+pub use control::DaemonControl;
+pub use coverage::{expected_savings_ratio, measure_coverage};
+pub use dependencies::{add_dependencies, is_extra, unfinished_dependencies, DependencyRules, EXTRAS_DIRS};
+pub use discovery::{advertise, service_info, Advertisement, SERVICE_TYPE};
+pub use duplicates::{content_signature, find_duplicates, DuplicateGroup, DuplicateReport};
+pub use encode_settings::{
+    read_settings_sidecar, settings_fingerprint, settings_sidecar_path, write_settings_sidecar,
+    EncodeSettings,
+};
+pub use failure::{classify_failure, classify_output, FailureKind};
+pub use energy::{
+    attribute_energy, counter_delta_uj, discover_rapl_zones, joules_to_kwh, EnergyMeter, RaplZone,
+    POWERCAP_ROOT,
 };
+pub use telemetry::{metrics_payload, traces_payload, unix_nanos, Span, SpanTimes, Telemetry};
+pub use thermal::{cpu_temperature, ThermalGovernor};
+pub use timings::{record_stage_time, stage_timing_stats, StageTimingStats};
+pub use tool_versions::{ToolVersions, DAEMON_VERSION};
+pub use triage::{prune_bundles, triage_dir, write_bundle, TRIAGE_DIR_SUFFIX};
+pub use tools::{configure_tools, env_in, isolated_env, program_in, Tool, LOADER_VARS};
+pub use watch::{watch_libraries, WATCH_LATENCY_SECS};
+pub use staging::{copy_verified, sha256_file, stage_source, StagedSource};
 pub use stability::{check_stability, compare_sizes, StabilityResult};
 pub use startup::{
-    assert_software_only, check_args_for_hardware_flags, check_av1an_available,
-    check_ffmpeg_version_8_or_newer, detect_hardware_flag, parse_ffmpeg_version,
-    run_startup_checks, StartupError,
+    assert_software_only, check_args_for_hardware_flags, check_av1an_available, check_directory_permissions,
+    check_clock_skew, check_ffmpeg_version_8_or_newer, configured_encoder_params, detect_hardware_flag, measure_clock_skew,
+    parse_ffmpeg_version, run_startup_checks, StartupError, StartupReport, MAX_CLOCK_SKEW_SECS,
 };
 pub use gates::{
-    check_gates, parse_ffprobe_output, probe_file, AudioStream, FormatInfo, GateResult,
-    GatesConfig, ProbeError, ProbeResult, VideoStream,
+    check_gates, parse_ffprobe_output, probe_file, probe_file_async, AudioStream, ContentLightLevel, FormatInfo,
+    GateResult, GatesConfig, HdrSideData, MasteringDisplay, ProbeError, ProbeResult, SubtitleStream, VideoStream,
+};
+pub use classify::{
+    classify_media_kind, classify_source, resolution_class, season_key, MediaKind, SourceType,
 };
-pub use classify::{classify_source, SourceType};
 pub use jobs::{
-    create_job, job_exists_for_path, load_jobs, save_job, Job as ManagedJob, JobStage, JobStatus,
+    auto_tags, cancel_job, create_job, job_exists_for_path, load_job, load_jobs, queue_order, remove_terminal_jobs_for_path, reorder_queue, save_job,
+    Job as ManagedJob, JobFilter, JobKind, JobOverrides, JobStage, JobStatus, KeptBackup,
 };
 pub use size_gate::{check_size_gate, SizeGateResult};
-pub use skip_marker::{why_sidecar_path, write_skip_marker, write_why_sidecar};
-pub use replace::{atomic_replace, backup_path, ReplaceError};
+pub use skip_marker::{
+    read_skip_marker_code, remove_skip_marker, remove_why_sidecars, why_json_path, why_sidecar_path,
+    write_skip_marker, write_skip_marker_with_code, write_why_json, write_why_sidecar,
+    SkipCode, SkipReason, WhySidecar,
+};
+pub use replace::{
+    atomic_copy_replace_to, atomic_replace, atomic_replace_to, backup_path, render_backup_path,
+    render_output_name, repair_swap, replace_with_backup, replace_with_backup_verified, resolve_output_path, swap_marker_path,
+    ReplaceError, SwapIntent, SwapPhase, SwapRepair, BACKUP_DIR, SWAP_MARKER_SUFFIX,
+};
+pub use audio_policy::{AudioPlan, AudioPolicy, KeptTrack, COPY_ALL_AUDIO_PARAMS};
+pub use audio_sync::{
+    check_audio_sync, compare_timing, parse_duration_tag, parse_timing, probe_timing, MediaTiming,
+    StreamTiming,
+};
+pub use frame_check::{
+    build_output_scan_command, build_source_black_command, find_frame_problems, parse_black_intervals,
+    parse_block_mean, BlackInterval,
+};
+pub use quality::{
+    build_quality_command, parse_psnr, parse_ssim, sample_quality, QualityScores,
+};
+pub use replace_window::{local_minute_of_day, replace_window_open, REPLACE_WINDOW_POLL_SECS};
+pub use replacement_budget::{ReplacementBudget, DAY_MS};
+pub use torrent::{
+    parse_qbittorrent_torrents, parse_transmission_torrents, seeding_hashes, Torrent, TorrentClient,
+    TorrentError, QBITTORRENT_DEFAULT_URL, TRANSMISSION_DEFAULT_URL,
+};
+pub use compare::{
+    build_still_command, comparison_dir, remove_comparison, still_timestamps,
+    write_comparison_stills, COMPARE_DIR_SUFFIX,
+};
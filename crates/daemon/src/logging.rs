@@ -0,0 +1,112 @@
+//! Log output for the daemon
+//!
+//! By default messages are printed as plain lines, progress to stdout and
+//! warnings to stderr. Container mode switches every message to one JSON
+//! object per line on stdout, which log collectors parse without a custom
+//! format:
+//!
+//! ```text
+//! {"ts":1700000000.123,"level":"warn","msg":"Failed to load probe cache: ..."}
+//! ```
+//!
+//! Use [`log_info!`](crate::log_info) and [`log_warn!`](crate::log_warn)
+//! wherever `println!` and `eprintln!` would otherwise be used.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Whether messages are written as JSON lines
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Severity of a log message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warn,
+}
+
+impl Level {
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warn => "warn",
+        }
+    }
+}
+
+/// Switches all further messages to JSON lines on stdout, or back to text
+pub fn set_json(enabled: bool) {
+    JSON.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns true if messages are written as JSON lines
+pub fn json_enabled() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Formats one message as a JSON log line, without the trailing newline
+///
+/// A leading `Warning: ` is dropped from warnings since the level already
+/// says so.
+pub fn json_line(level: Level, ts: f64, msg: &str) -> String {
+    let msg = match level {
+        Level::Warn => msg.strip_prefix("Warning: ").unwrap_or(msg),
+        Level::Info => msg,
+    };
+    // Millisecond precision is plenty and keeps the number short
+    let ts = (ts * 1000.0).round() / 1000.0;
+    serde_json::json!({ "ts": ts, "level": level.as_str(), "msg": msg }).to_string()
+}
+
+/// Writes one message; called by the logging macros
+#[doc(hidden)]
+pub fn write(level: Level, args: fmt::Arguments<'_>) {
+    if json_enabled() {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+        println!("{}", json_line(level, ts, &args.to_string()));
+    } else {
+        match level {
+            Level::Info => println!("{}", args),
+            Level::Warn => eprintln!("{}", args),
+        }
+    }
+}
+
+/// Logs a progress message; `println!` in text mode
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        $crate::logging::write($crate::logging::Level::Info, format_args!($($arg)*))
+    };
+}
+
+/// Logs a warning or error; `eprintln!` in text mode
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::logging::write($crate::logging::Level::Warn, format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_line_escapes_and_drops_warning_prefix() {
+        let line = json_line(Level::Warn, 1700000000.12345, "Warning: bad \"path\"\n");
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "warn");
+        assert_eq!(value["msg"], "bad \"path\"\n");
+        assert_eq!(value["ts"], 1700000000.123);
+        assert!(!line.contains('\n'));
+
+        let line = json_line(Level::Info, 0.0, "Warning: kept for info");
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["msg"], "Warning: kept for info");
+    }
+}
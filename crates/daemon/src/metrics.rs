@@ -4,6 +4,7 @@
 //! with JSON serialization support.
 
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -27,20 +28,220 @@ pub struct JobMetrics {
     pub vmaf: Option<f32>,
     pub psnr: Option<f32>,
     pub ssim: Option<f32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// CPU package energy attributed to this job so far, from RAPL
+    #[serde(default)]
+    pub energy_joules: f64,
+    /// Why the sampled quality checks flagged the encode, if they did
+    #[serde(default)]
+    pub quality_flag: Option<String>,
 }
 
 /// System-level metrics for resource monitoring
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct SystemMetrics {
     pub cpu_usage_percent: f32,
     pub mem_usage_percent: f32,
     pub load_avg_1: f32,
     pub load_avg_5: f32,
     pub load_avg_15: f32,
+    /// Usage of each logical core, in core order
+    #[serde(default)]
+    pub per_core_usage_percent: Vec<f32>,
+    /// Filesystems holding the library roots and temp directories
+    #[serde(default)]
+    pub disks: Vec<DiskMetrics>,
+    /// Hottest CPU sensor, when the platform exposes one
+    #[serde(default)]
+    pub cpu_temp_celsius: Option<f32>,
+}
+
+/// Throughput and space of one filesystem the daemon reads from or writes to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct DiskMetrics {
+    pub mount_point: String,
+    /// What the daemon keeps there: "library", "temp", or both
+    pub roles: Vec<String>,
+    /// Unset until two samples exist, or when the device has no IO counters
+    pub read_bytes_per_sec: Option<u64>,
+    pub write_bytes_per_sec: Option<u64>,
+    pub free_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Library scanner progress and statistics for the most recent scan cycle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ScanMetrics {
+    /// True while a scan cycle is running
+    pub in_progress: bool,
+    pub last_scan_started_unix_ms: i64,
+    /// Zero until the first scan cycle has finished
+    pub last_scan_finished_unix_ms: i64,
+    pub last_scan_duration_ms: u64,
+    pub files_walked: u64,
+    pub candidates_found: u64,
+    pub jobs_queued: u64,
+    /// Candidates not queued during the last cycle, keyed by reason
+    pub skips_by_reason: BTreeMap<String, u64>,
+}
+
+/// Temp directory usage and garbage collection totals
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct TempMetrics {
+    /// Bytes under the temp output and chunk directories at the last check
+    pub bytes_used: u64,
+    /// Configured quota, 0 when unlimited
+    pub quota_bytes: u64,
+    /// True while new jobs are held back because usage is over quota
+    pub jobs_deferred: bool,
+    /// Orphaned chunk directories removed since startup
+    pub gc_dirs_removed: u64,
+    pub gc_bytes_freed: u64,
+}
+
+/// Thermal throttle state
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ThermalMetrics {
+    /// Configured limit, unset when no thermal policy is active
+    pub limit_celsius: Option<f32>,
+    /// True while new jobs are paused or started with fewer workers
+    pub throttled: bool,
+    /// "pause" or "reduce_workers"
+    pub action: String,
+    /// Times the limit was crossed since startup
+    pub throttle_events: u64,
+}
+
+/// Kept backups of replaced originals and what retention removed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct BackupMetrics {
+    /// Backups still on disk at the last retention pass
+    pub kept: u64,
+    pub bytes_kept: u64,
+    /// Backups deleted by retention since startup
+    pub removed: u64,
+    pub bytes_freed: u64,
+    /// Unix milliseconds of the last retention pass, 0 before the first
+    pub last_run_unix_ms: i64,
+}
+
+/// State of the kill switch sentinel file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct KillSwitchMetrics {
+    /// True while the sentinel file exists and no new work starts
+    pub engaged: bool,
+    /// Encodes suspended by the kill switch
+    pub suspended_encodes: u64,
+}
+
+/// Global controls set through the API
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ControlMetrics {
+    /// True while jobs that have not started are held
+    pub queue_paused: bool,
+    /// True from a scan request until the scan cycle starts
+    pub scan_requested: bool,
+}
+
+/// Polite mode state: whether encodes yield to interactive use
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct PoliteMetrics {
+    /// True when polite mode is configured
+    pub enabled: bool,
+    /// True while running encodes are suspended for interactive use
+    pub yielding: bool,
+    /// Active Plex transcodes at the last check, if Plex is watched and
+    /// answered
+    pub plex_transcodes: Option<u32>,
+    /// CPU percent used by processes other than the daemon and its
+    /// encoders at the last check
+    pub other_cpu_percent: Option<f32>,
+    /// Times encodes were suspended since startup
+    pub yield_events: u64,
+}
+
+/// CPU package energy used by encodes since startup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct EnergyMetrics {
+    /// False when the machine has no readable RAPL counters
+    pub available: bool,
+    /// Energy measured while at least one job was encoding
+    pub encode_joules: f64,
+}
+
+/// Conversion progress of one library root, refreshed every scan cycle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct LibraryCoverage {
+    pub root: String,
+    /// Files already AV1, whether encoded by the daemon or not
+    pub converted_files: u64,
+    pub converted_bytes: u64,
+    /// Files the daemon will not convert (size gate, probe failure, ...)
+    pub skipped_files: u64,
+    /// Files not yet converted or skipped, including queued ones
+    pub remaining_files: u64,
+    pub remaining_bytes: u64,
+    /// Converted share of converted plus remaining files
+    pub percent_converted: f32,
+    /// Bytes expected to be saved by encoding the remaining files
+    pub est_remaining_savings_bytes: u64,
+}
+
+/// Seconds between throughput history samples
+pub const HISTORY_SAMPLE_INTERVAL_SECS: u64 = 30;
+
+/// Samples kept in the throughput history (24 hours at the default interval)
+pub const HISTORY_CAPACITY: usize = 2880;
+
+/// Encode totals at one point in time
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct ThroughputSample {
+    pub timestamp_unix_ms: i64,
+    pub total_bytes_encoded: u64,
+    pub completed_jobs: u64,
+}
+
+/// Fixed-size ring buffer of throughput samples, oldest first
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ThroughputHistory {
+    samples: VecDeque<ThroughputSample>,
+}
+
+impl ThroughputHistory {
+    /// Appends a sample, dropping the oldest once [`HISTORY_CAPACITY`] is reached
+    pub fn push(&mut self, sample: ThroughputSample) {
+        if self.samples.len() >= HISTORY_CAPACITY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Samples taken at or after `since_unix_ms`, oldest first
+    pub fn since(&self, since_unix_ms: i64) -> Vec<ThroughputSample> {
+        self.samples
+            .iter()
+            .filter(|s| s.timestamp_unix_ms >= since_unix_ms)
+            .copied()
+            .collect()
+    }
+
+    /// Timestamp of the newest sample
+    pub fn last_timestamp_ms(&self) -> Option<i64> {
+        self.samples.back().map(|s| s.timestamp_unix_ms)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
 }
 
 /// Complete metrics snapshot including jobs, system, and aggregate stats
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct MetricsSnapshot {
     pub timestamp_unix_ms: i64,
     pub jobs: Vec<JobMetrics>,
@@ -49,39 +250,61 @@ pub struct MetricsSnapshot {
     pub running_jobs: usize,
     pub completed_jobs: u64,
     pub failed_jobs: u64,
+    /// Jobs stopped on request, counted apart from failures
+    #[serde(default)]
+    pub cancelled_jobs: u64,
     pub total_bytes_encoded: u64,
+    #[serde(default)]
+    pub scan: ScanMetrics,
+    /// Lifetime count of skipped files by reason code, persisted across restarts
+    #[serde(default)]
+    pub skip_totals: BTreeMap<String, u64>,
+    /// Failed encodes since start by failure kind (see [`crate::failure`])
+    #[serde(default)]
+    pub failures_by_kind: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub temp: TempMetrics,
+    #[serde(default)]
+    pub thermal: ThermalMetrics,
+    #[serde(default)]
+    pub energy: EnergyMetrics,
+    #[serde(default)]
+    pub backups: BackupMetrics,
+    #[serde(default)]
+    pub kill_switch: KillSwitchMetrics,
+    #[serde(default)]
+    pub control: ControlMetrics,
+    #[serde(default)]
+    pub polite: PoliteMetrics,
+    /// Per library root, from the last scan cycle
+    #[serde(default)]
+    pub coverage: Vec<LibraryCoverage>,
+    /// Served separately at /metrics/history to keep /metrics small
+    #[serde(skip)]
+    pub history: ThroughputHistory,
 }
 
-
-/// Shared metrics state for concurrent access across daemon components
-pub type SharedMetrics = Arc<RwLock<MetricsSnapshot>>;
-
-impl Default for SystemMetrics {
-    fn default() -> Self {
-        Self {
-            cpu_usage_percent: 0.0,
-            mem_usage_percent: 0.0,
-            load_avg_1: 0.0,
-            load_avg_5: 0.0,
-            load_avg_15: 0.0,
+impl MetricsSnapshot {
+    /// Records a throughput sample when at least
+    /// [`HISTORY_SAMPLE_INTERVAL_SECS`] have passed since the last one
+    pub fn record_history(&mut self, now_unix_ms: i64) {
+        let due = match self.history.last_timestamp_ms() {
+            Some(last) => now_unix_ms - last >= HISTORY_SAMPLE_INTERVAL_SECS as i64 * 1000,
+            None => true,
+        };
+        if due {
+            self.history.push(ThroughputSample {
+                timestamp_unix_ms: now_unix_ms,
+                total_bytes_encoded: self.total_bytes_encoded,
+                completed_jobs: self.completed_jobs,
+            });
         }
     }
 }
 
-impl Default for MetricsSnapshot {
-    fn default() -> Self {
-        Self {
-            timestamp_unix_ms: 0,
-            jobs: Vec::new(),
-            system: SystemMetrics::default(),
-            queue_len: 0,
-            running_jobs: 0,
-            completed_jobs: 0,
-            failed_jobs: 0,
-            total_bytes_encoded: 0,
-        }
-    }
-}
+
+/// Shared metrics state for concurrent access across daemon components
+pub type SharedMetrics = Arc<RwLock<MetricsSnapshot>>;
 
 /// Creates a new SharedMetrics instance with default values
 pub fn new_shared_metrics() -> SharedMetrics {
@@ -89,31 +312,12 @@ pub fn new_shared_metrics() -> SharedMetrics {
 }
 
 /// Collects current system metrics using sysinfo
+///
+/// One-shot, without disks: CPU usage and disk throughput are measured
+/// between samples, so long-running callers should keep a
+/// [`SystemSampler`](crate::system_stats::SystemSampler) instead.
 pub fn collect_system_metrics() -> SystemMetrics {
-    use sysinfo::System;
-
-    let mut sys = System::new();
-    sys.refresh_cpu_usage();
-    sys.refresh_memory();
-
-    let cpu_usage = sys.global_cpu_usage();
-    let total_memory = sys.total_memory();
-    let used_memory = sys.used_memory();
-    let mem_usage = if total_memory > 0 {
-        (used_memory as f64 / total_memory as f64 * 100.0) as f32
-    } else {
-        0.0
-    };
-
-    let load_avg = System::load_average();
-
-    SystemMetrics {
-        cpu_usage_percent: cpu_usage,
-        mem_usage_percent: mem_usage,
-        load_avg_1: load_avg.one as f32,
-        load_avg_5: load_avg.five as f32,
-        load_avg_15: load_avg.fifteen as f32,
-    }
+    crate::system_stats::SystemSampler::new(Vec::new()).sample()
 }
 
 #[cfg(test)]
@@ -139,6 +343,8 @@ mod tests {
             load_5 in 0.0f32..100.0,
             load_15 in 0.0f32..100.0,
             job_count in 0usize..5,
+            files_walked in any::<u64>(),
+            skipped in 0u64..1000,
         ) {
             let jobs: Vec<JobMetrics> = (0..job_count).map(|i| JobMetrics {
                 id: format!("job-{}", i),
@@ -158,6 +364,9 @@ mod tests {
                 vmaf: Some(95.5),
                 psnr: Some(45.2),
                 ssim: Some(0.98),
+                tags: vec!["4k".to_string(), "disc".to_string()],
+                energy_joules: skipped as f64 * 1000.0,
+                quality_flag: None,
             }).collect();
 
             let snapshot = MetricsSnapshot {
@@ -169,12 +378,85 @@ mod tests {
                     load_avg_1: load_1,
                     load_avg_5: load_5,
                     load_avg_15: load_15,
+                    per_core_usage_percent: vec![cpu_usage, mem_usage],
+                    disks: vec![DiskMetrics {
+                        mount_point: "/media".to_string(),
+                        roles: vec!["library".to_string(), "temp".to_string()],
+                        read_bytes_per_sec: Some(total_bytes_encoded),
+                        write_bytes_per_sec: None,
+                        free_bytes: files_walked,
+                        total_bytes: total_bytes_encoded,
+                    }],
+                    cpu_temp_celsius: Some(load_1),
                 },
                 queue_len,
                 running_jobs,
                 completed_jobs,
                 failed_jobs,
+                cancelled_jobs: skipped,
                 total_bytes_encoded,
+                scan: ScanMetrics {
+                    in_progress: false,
+                    last_scan_started_unix_ms: timestamp,
+                    last_scan_finished_unix_ms: timestamp,
+                    last_scan_duration_ms: 1500,
+                    files_walked,
+                    candidates_found: 10,
+                    jobs_queued: 2,
+                    skips_by_reason: BTreeMap::from([("unstable".to_string(), skipped)]),
+                },
+                skip_totals: BTreeMap::from([("already_av1".to_string(), skipped)]),
+                failures_by_kind: BTreeMap::from([("disk_full".to_string(), skipped)]),
+                temp: TempMetrics {
+                    bytes_used: total_bytes_encoded,
+                    quota_bytes: 0,
+                    jobs_deferred: false,
+                    gc_dirs_removed: skipped,
+                    gc_bytes_freed: files_walked,
+                },
+                thermal: ThermalMetrics {
+                    limit_celsius: Some(85.0),
+                    throttled: skipped % 2 == 0,
+                    action: "pause".to_string(),
+                    throttle_events: skipped,
+                },
+                energy: EnergyMetrics {
+                    available: true,
+                    encode_joules: (skipped * 3600) as f64,
+                },
+                backups: BackupMetrics {
+                    kept: skipped,
+                    bytes_kept: total_bytes_encoded,
+                    removed: 3,
+                    bytes_freed: files_walked,
+                    last_run_unix_ms: 1_700_000_000_000,
+                },
+                kill_switch: KillSwitchMetrics {
+                    engaged: skipped % 2 == 1,
+                    suspended_encodes: skipped,
+                },
+                control: ControlMetrics {
+                    queue_paused: skipped % 2 == 0,
+                    scan_requested: skipped % 3 == 0,
+                },
+                polite: PoliteMetrics {
+                    enabled: true,
+                    yielding: skipped % 2 == 1,
+                    plex_transcodes: Some(skipped as u32),
+                    other_cpu_percent: Some(cpu_usage),
+                    yield_events: skipped,
+                },
+                coverage: vec![LibraryCoverage {
+                    root: "/media/movies".to_string(),
+                    converted_files: skipped,
+                    converted_bytes: files_walked,
+                    skipped_files: 3,
+                    remaining_files: skipped,
+                    remaining_bytes: total_bytes_encoded,
+                    percent_converted: cpu_usage,
+                    est_remaining_savings_bytes: files_walked,
+                }],
+                history: ThroughputHistory::default(),
             };
 
             // Serialize to JSON
@@ -188,4 +470,37 @@ mod tests {
             prop_assert_eq!(snapshot, deserialized);
         }
     }
+
+    #[test]
+    fn test_history_samples_at_interval_and_wraps() {
+        let mut snapshot = MetricsSnapshot::default();
+        let step = HISTORY_SAMPLE_INTERVAL_SECS as i64 * 1000;
+
+        snapshot.record_history(0);
+        snapshot.total_bytes_encoded = 100;
+        snapshot.record_history(step - 1);
+        assert_eq!(snapshot.history.len(), 1);
+        snapshot.record_history(step);
+        assert_eq!(snapshot.history.since(1)[0].total_bytes_encoded, 100);
+
+        for i in 2..=HISTORY_CAPACITY as i64 + 5 {
+            snapshot.record_history(i * step);
+        }
+        assert_eq!(snapshot.history.len(), HISTORY_CAPACITY);
+        assert_eq!(snapshot.history.since(0)[0].timestamp_unix_ms, 6 * step);
+
+        // History is not part of the /metrics payload
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(!json.contains("history"));
+    }
+
+    #[test]
+    fn test_snapshot_without_scan_section_deserializes() {
+        let json = r#"{"timestamp_unix_ms":1,"jobs":[],"system":{"cpu_usage_percent":0.0,
+            "mem_usage_percent":0.0,"load_avg_1":0.0,"load_avg_5":0.0,"load_avg_15":0.0},
+            "queue_len":0,"running_jobs":0,"completed_jobs":0,"failed_jobs":0,
+            "total_bytes_encoded":0}"#;
+        let snapshot: MetricsSnapshot = serde_json::from_str(json).unwrap();
+        assert_eq!(snapshot.scan, ScanMetrics::default());
+    }
 }
@@ -1,12 +1,36 @@
 //! Metrics HTTP Server for AV1 Super Daemon
 //!
-//! Exposes metrics via HTTP endpoint for TUI dashboard and monitoring tools.
+//! Exposes metrics via HTTP endpoint for TUI dashboard and monitoring tools,
+//! plus a small read API over the persisted job state.
 
-use axum::{extract::State, routing::get, Json, Router};
-use std::net::SocketAddr;
+use axum::{
+    extract::{FromRef, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
-use crate::metrics::{MetricsSnapshot, SharedMetrics};
+use crate::config::Config;
+use crate::dependencies::add_dependencies;
+use crate::energy::joules_to_kwh;
+use crate::job_executor::{JobError, JobExecutor};
+use crate::jobs::{
+    add_job_note, cancel_job, load_job, load_jobs, reorder_queue, Job, JobOverrides, JobFilter, JobStage, JobStatus};
+use crate::metrics::{
+    BackupMetrics, MetricsSnapshot, SharedMetrics, ThroughputSample, HISTORY_SAMPLE_INTERVAL_SECS,
+};
+use crate::pipeline::{
+    check_import_path, import_paths, parse_path_list, requeue_path, ImportEntry, PipelineContext, ResetReport,
+};
+use crate::schedule::schedule_jobs;
+use crate::timings::{stage_timing_stats, StageTimingStats};
 
 /// Errors that can occur when running the metrics server
 #[derive(Debug, Error)]
@@ -22,33 +46,812 @@ async fn get_metrics(State(metrics): State<SharedMetrics>) -> Json<MetricsSnapsh
     Json(snapshot)
 }
 
+/// Query parameters for GET /metrics/history
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryQuery {
+    /// Only return samples taken at or after this Unix time in milliseconds
+    pub since_unix_ms: Option<i64>,
+}
+
+/// Response body for GET /metrics/history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryResponse {
+    /// Seconds between samples
+    pub interval_secs: u64,
+    /// Samples, oldest first
+    pub samples: Vec<ThroughputSample>,
+}
+
+/// Handler for GET /metrics/history endpoint
+/// Returns the daemon's throughput history so charts survive client restarts
+async fn get_history(
+    State(metrics): State<SharedMetrics>,
+    Query(query): Query<HistoryQuery>,
+) -> Json<HistoryResponse> {
+    let samples = metrics
+        .read()
+        .await
+        .history
+        .since(query.since_unix_ms.unwrap_or(i64::MIN));
+    Json(HistoryResponse {
+        interval_secs: HISTORY_SAMPLE_INTERVAL_SECS,
+        samples,
+    })
+}
+
+/// Response body for GET /stats/skips
+#[derive(Debug, Clone, Serialize)]
+pub struct SkipStatsResponse {
+    /// Lifetime skip counts keyed by reason code
+    pub counts: BTreeMap<String, u64>,
+    /// Sum of all counts
+    pub total: u64,
+}
+
+/// Handler for GET /stats/skips endpoint
+/// Returns lifetime skip counts by reason, including those from before the last restart
+async fn get_skip_stats(State(metrics): State<SharedMetrics>) -> Json<SkipStatsResponse> {
+    let counts = metrics.read().await.skip_totals.clone();
+    let total = counts.values().sum();
+    Json(SkipStatsResponse { counts, total })
+}
+
+/// Energy used by one job, for GET /stats/energy
+#[derive(Debug, Clone, Serialize)]
+pub struct JobEnergy {
+    pub id: String,
+    pub input_path: String,
+    pub stage: String,
+    pub kwh: f64,
+    /// Bytes saved by the encode, 0 until it has been replaced
+    pub bytes_saved: u64,
+}
+
+/// Response body for GET /stats/energy
+#[derive(Debug, Clone, Serialize)]
+pub struct EnergyStatsResponse {
+    /// False when the machine has no readable RAPL counters
+    pub available: bool,
+    /// Energy used by encodes since the daemon started
+    pub total_kwh: f64,
+    pub jobs: Vec<JobEnergy>,
+}
+
+/// Handler for GET /stats/energy endpoint
+/// Returns per-job and total encode energy since startup
+async fn get_energy_stats(State(metrics): State<SharedMetrics>) -> Json<EnergyStatsResponse> {
+    let snapshot = metrics.read().await;
+    let jobs = snapshot
+        .jobs
+        .iter()
+        .map(|job| JobEnergy {
+            id: job.id.clone(),
+            input_path: job.input_path.clone(),
+            stage: job.stage.clone(),
+            kwh: joules_to_kwh(job.energy_joules),
+            bytes_saved: if job.size_in_bytes_after > 0 {
+                job.size_in_bytes_before.saturating_sub(job.size_in_bytes_after)
+            } else {
+                0
+            },
+        })
+        .collect();
+    Json(EnergyStatsResponse {
+        available: snapshot.energy.available,
+        total_kwh: joules_to_kwh(snapshot.energy.encode_joules),
+        jobs,
+    })
+}
+
+/// Handler for GET /stats/backups endpoint
+/// Returns kept backups and what retention has removed since startup
+async fn get_backup_stats(State(metrics): State<SharedMetrics>) -> Json<BackupMetrics> {
+    Json(metrics.read().await.backups.clone())
+}
+
 /// Creates the axum Router with metrics endpoint
 pub fn create_metrics_router(metrics: SharedMetrics) -> Router {
     Router::new()
         .route("/metrics", get(get_metrics))
+        .route("/metrics/history", get(get_history))
+        .route("/stats/skips", get(get_skip_stats))
+        .route("/stats/energy", get(get_energy_stats))
+        .route("/stats/backups", get(get_backup_stats))
         .with_state(metrics)
 }
 
-/// Runs the metrics HTTP server on 127.0.0.1:7878
+/// Shared state for the full daemon HTTP API
+#[derive(Clone)]
+pub struct ApiState {
+    /// Live metrics snapshot
+    pub metrics: SharedMetrics,
+    /// Directory holding persisted job JSON files
+    pub job_state_dir: PathBuf,
+    /// Pipeline used to re-evaluate files; write endpoints answer 503 without it
+    pub pipeline: Option<PipelineContext>,
+    /// Executor running the encodes; cancellation and approval answer 503
+    /// without it
+    pub executor: Option<Arc<JobExecutor>>,
+}
+
+impl FromRef<ApiState> for SharedMetrics {
+    fn from_ref(state: &ApiState) -> Self {
+        state.metrics.clone()
+    }
+}
+
+/// Query parameters for GET /jobs
+#[derive(Debug, Default, Deserialize)]
+pub struct JobsQuery {
+    /// Comma-separated tags; a job must carry all of them
+    pub tags: Option<String>,
+    /// Only return jobs in this status
+    pub status: Option<JobStatus>,
+    /// Only return jobs at this stage
+    pub stage: Option<JobStage>,
+    /// Only return jobs encoded with this SVT-AV1 version, or a version
+    /// starting with it (`2.1` matches 2.1.0 and 2.1.2)
+    pub encoder_version: Option<String>,
+}
+
+impl JobsQuery {
+    fn to_filter(&self) -> JobFilter {
+        JobFilter {
+            tags: self
+                .tags
+                .as_deref()
+                .unwrap_or("")
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect(),
+            status: self.status,
+            stage: self.stage,
+            encoder_version: self.encoder_version.clone(),
+        }
+    }
+}
+
+/// A persisted job in GET /jobs, with estimated start and finish times for
+/// running and queued jobs
+#[derive(Debug, Clone, Serialize)]
+pub struct JobView {
+    #[serde(flatten)]
+    pub job: Job,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub est_start_unix_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub est_finish_unix_ms: Option<i64>,
+}
+
+/// Handler for GET /jobs endpoint
+/// Returns persisted jobs, optionally filtered by `?tags=4k,disc&status=failed`,
+/// `?stage=awaiting_approval`, or `?encoder_version=2.1`
+async fn list_jobs(
+    State(state): State<ApiState>,
+    Query(query): Query<JobsQuery>,
+) -> Result<Json<Vec<JobView>>, (StatusCode, String)> {
+    let jobs = load_jobs(&state.job_state_dir)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    // Estimates depend on every job ahead in the queue, so they are worked
+    // out before filtering
+    let slots = state.executor.as_ref().map_or(1, |executor| executor.job_slots());
+    let mut schedule = schedule_jobs(&jobs, slots, current_timestamp_ms());
+    let filter = query.to_filter();
+    let mut jobs: Vec<JobView> = jobs
+        .into_iter()
+        .filter(|job| filter.matches(job))
+        .map(|job| {
+            let estimate = schedule.remove(&job.id);
+            JobView {
+                job,
+                est_start_unix_ms: estimate.map(|e| e.est_start_unix_ms),
+                est_finish_unix_ms: estimate.map(|e| e.est_finish_unix_ms),
+            }
+        })
+        .collect();
+    jobs.sort_by_key(|view| view.job.created_at);
+    Ok(Json(jobs))
+}
+
+/// Response body for GET /stats/timings
+#[derive(Debug, Clone, Serialize)]
+pub struct TimingStatsResponse {
+    /// Persisted jobs matching the query
+    pub jobs: usize,
+    /// Wall-clock time per stage, keyed by stage name
+    pub stages: BTreeMap<String, StageTimingStats>,
+}
+
+/// Handler for GET /stats/timings endpoint
+/// Returns per-stage time percentiles over persisted jobs, filtered like GET /jobs
+async fn get_timing_stats(
+    State(state): State<ApiState>,
+    Query(query): Query<JobsQuery>,
+) -> Result<Json<TimingStatsResponse>, (StatusCode, String)> {
+    let jobs = load_jobs(&state.job_state_dir)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let filter = query.to_filter();
+    let jobs: Vec<Job> = jobs.into_iter().filter(|job| filter.matches(job)).collect();
+    Ok(Json(TimingStatsResponse {
+        jobs: jobs.len(),
+        stages: stage_timing_stats(&jobs),
+    }))
+}
+
+/// Request body for POST /jobs/requeue
+#[derive(Debug, Deserialize)]
+pub struct RequeueRequest {
+    /// Media file to retry
+    pub path: PathBuf,
+    /// Encode the file even if it is already AV1 or fails the size gates
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Response body for POST /jobs/requeue
+#[derive(Debug, Clone, Serialize)]
+pub struct RequeueResponse {
+    /// What was cleared before re-evaluating the file
+    #[serde(flatten)]
+    pub reset: ResetReport,
+    /// Outcome label: `queued`, a skip code, `unstable`, ...
+    pub outcome: String,
+    /// ID of the new job when the file was queued
+    pub job_id: Option<String>,
+    /// Human-readable detail for skips and errors
+    pub message: Option<String>,
+}
+
+/// Handler for POST /jobs/requeue endpoint
+/// Clears the skip marker, why sidecars, and terminal jobs for a path, then
+/// immediately re-runs probe and gates on it; with `"force": true` the gates
+/// are bypassed as with an `.av1force` marker
+async fn requeue_job(
+    State(state): State<ApiState>,
+    Json(request): Json<RequeueRequest>,
+) -> Result<Json<RequeueResponse>, (StatusCode, String)> {
+    let Some(pipeline) = state.pipeline.as_ref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "requeue is not available on this server".to_string(),
+        ));
+    };
+
+    let (reset, outcome) = requeue_path(pipeline, &request.path, request.force)
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => (StatusCode::NOT_FOUND, e.to_string()),
+            std::io::ErrorKind::InvalidInput => (StatusCode::BAD_REQUEST, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    let (outcome, job_id, message) = outcome.into_parts();
+    Ok(Json(RequeueResponse {
+        reset,
+        outcome,
+        job_id,
+        message,
+    }))
+}
+
+/// Request body for POST /jobs/cancel
+#[derive(Debug, Deserialize)]
+pub struct CancelRequest {
+    /// ID of the job to stop
+    pub id: String,
+}
+
+/// Response body for POST /jobs/cancel
+#[derive(Debug, Clone, Serialize)]
+pub struct CancelResponse {
+    pub id: String,
+    /// True if the job had reached the executor, false if it was still queued
+    pub was_running: bool,
+}
+
+/// Handler for POST /jobs/cancel endpoint
+/// Marks the job cancelled and kills its Av1an process group if it is encoding
+async fn cancel_job_request(
+    State(state): State<ApiState>,
+    Json(request): Json<CancelRequest>,
+) -> Result<Json<CancelResponse>, (StatusCode, String)> {
+    let Some(executor) = state.executor.as_ref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "cancel is not available on this server".to_string(),
+        ));
+    };
+
+    let previous = cancel_job(&state.job_state_dir, &request.id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("no job with id {}", request.id)))?;
+    if !matches!(previous, JobStatus::Pending | JobStatus::Running) {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("job {} is already {}", request.id, previous),
+        ));
+    }
+
+    let was_running = executor.cancel(&request.id);
+    Ok(Json(CancelResponse {
+        id: request.id,
+        was_running,
+    }))
+}
+
+/// Request body for POST /jobs/approve and POST /jobs/reject
+#[derive(Debug, Deserialize)]
+pub struct ApproveRequest {
+    /// ID of the held job
+    pub id: String,
+    /// Note recorded on the job with the decision
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Response body for POST /jobs/approve
+#[derive(Debug, Clone, Serialize)]
+pub struct ApproveResponse {
+    pub id: String,
+    /// Where the encoded file now lives
+    pub path: PathBuf,
+}
+
+/// Response body for POST /jobs/reject
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectResponse {
+    pub id: String,
+    /// The original, left in place
+    pub path: PathBuf,
+}
+
+/// Returns the executor if job `id` is held for approval, or the status to
+/// answer with otherwise
+fn held_job_executor<'a>(
+    state: &'a ApiState,
+    id: &str,
+    action: &str,
+) -> Result<(&'a JobExecutor, Job), (StatusCode, String)> {
+    let Some(executor) = state.executor.as_deref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("{} is not available on this server", action),
+        ));
+    };
+
+    let job = load_job(&state.job_state_dir, id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("no job with id {}", id)))?;
+    if job.stage != JobStage::AwaitingApproval || !job.is_active() {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("job {} is {} ({}), not awaiting approval", id, job.stage, job.status),
+        ));
+    }
+    Ok((executor, job))
+}
+
+/// Records the note sent with an approve or reject on job `id`
+fn record_review_note(state: &ApiState, id: &str, note: Option<&str>) -> Result<(), (StatusCode, String)> {
+    if let Some(note) = note {
+        add_job_note(&state.job_state_dir, id, note)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Maps an approve or reject failure to a response status
+fn review_error(e: JobError) -> (StatusCode, String) {
+    match e {
+        JobError::NotAwaitingApproval(_) => (StatusCode::CONFLICT, e.to_string()),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Handler for POST /jobs/approve endpoint
+/// Replaces the original of a job held for approval with its encode
+async fn approve_job_request(
+    State(state): State<ApiState>,
+    Json(request): Json<ApproveRequest>,
+) -> Result<Json<ApproveResponse>, (StatusCode, String)> {
+    let (executor, _) = held_job_executor(&state, &request.id, "approve")?;
+    record_review_note(&state, &request.id, request.note.as_deref())?;
+    let path = executor.approve(&request.id).await.map_err(review_error)?;
+    Ok(Json(ApproveResponse { id: request.id, path }))
+}
+
+/// Handler for POST /jobs/reject endpoint
+/// Discards the encode of a job held for approval and keeps the original
+async fn reject_job_request(
+    State(state): State<ApiState>,
+    Json(request): Json<ApproveRequest>,
+) -> Result<Json<RejectResponse>, (StatusCode, String)> {
+    let (executor, job) = held_job_executor(&state, &request.id, "reject")?;
+    record_review_note(&state, &request.id, request.note.as_deref())?;
+    executor.reject(&request.id).await.map_err(review_error)?;
+    Ok(Json(RejectResponse {
+        id: request.id,
+        path: job.input_path,
+    }))
+}
+
+/// Request body for POST /jobs/note
+#[derive(Debug, Deserialize)]
+pub struct NoteRequest {
+    /// ID of the job to annotate
+    pub id: String,
+    pub text: String,
+}
+
+/// Handler for POST /jobs/note endpoint
+/// Attaches a free-text operator note to a job of any status
+async fn add_note_request(
+    State(state): State<ApiState>,
+    Json(request): Json<NoteRequest>,
+) -> Result<Json<Job>, (StatusCode, String)> {
+    if request.text.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "note is empty".to_string()));
+    }
+    add_job_note(&state.job_state_dir, &request.id, &request.text)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("no job with id {}", request.id)))
+}
+
+/// Request body for POST /jobs/depend
+#[derive(Debug, Deserialize)]
+pub struct DependRequest {
+    /// ID of the queued job that waits
+    pub id: String,
+    /// IDs of the jobs that finish first
+    pub after: Vec<String>,
+}
+
+/// Handler for POST /jobs/depend endpoint
+/// Makes a queued job wait until other jobs have finished
+async fn add_dependencies_request(
+    State(state): State<ApiState>,
+    Json(request): Json<DependRequest>,
+) -> Result<Json<Job>, (StatusCode, String)> {
+    if request.after.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "no jobs to wait for".to_string()));
+    }
+    add_dependencies(&state.job_state_dir, &request.id, &request.after)
+        .map(Json)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => (StatusCode::NOT_FOUND, e.to_string()),
+            std::io::ErrorKind::InvalidInput => (StatusCode::CONFLICT, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Request body for POST /queue/reorder
+///
+/// Either `ids`, the jobs to put at the front in that order, or `top`, a
+/// single job to move to the front.
+#[derive(Debug, Default, Deserialize)]
+pub struct ReorderRequest {
+    #[serde(default)]
+    pub ids: Vec<String>,
+    #[serde(default)]
+    pub top: Option<String>,
+}
+
+/// Response body for POST /queue/reorder
+#[derive(Debug, Clone, Serialize)]
+pub struct ReorderResponse {
+    /// IDs of the queued jobs in the order they start
+    pub order: Vec<String>,
+}
+
+/// Handler for POST /queue/reorder endpoint
+/// Moves queued jobs to the front of the queue
+async fn reorder_queue_request(
+    State(state): State<ApiState>,
+    Json(request): Json<ReorderRequest>,
+) -> Result<Json<ReorderResponse>, (StatusCode, String)> {
+    let mut ids = request.ids;
+    ids.extend(request.top);
+    if ids.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "no jobs to move".to_string()));
+    }
+
+    let queue = reorder_queue(&state.job_state_dir, &ids).map_err(|e| match e.kind() {
+        std::io::ErrorKind::InvalidInput => (StatusCode::CONFLICT, e.to_string()),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+    if let Some(executor) = state.executor.as_ref() {
+        executor.set_queue_priorities(queue.iter().map(|job| (job.id.as_str(), job.priority)));
+    }
+    Ok(Json(ReorderResponse {
+        order: queue.into_iter().map(|job| job.id).collect(),
+    }))
+}
+
+/// Handler for GET /config endpoint
+/// The configuration the daemon runs with, after profiles and environment
+/// overrides, with secrets redacted
+async fn get_config(
+    State(state): State<ApiState>,
+) -> Result<Json<Config>, (StatusCode, String)> {
+    let Some(pipeline) = state.pipeline.as_ref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "the configuration is not available on this server".to_string(),
+        ));
+    };
+    Ok(Json(pipeline.config.redacted()))
+}
+
+/// Response body for the /control endpoints
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlResponse {
+    /// True while jobs that have not started are held
+    pub queue_paused: bool,
+    /// True from a scan request until the scan cycle starts
+    pub scan_requested: bool,
+}
+
+/// What `POST /control/...` asks of the daemon
+#[derive(Debug, Clone, Copy)]
+enum ControlAction {
+    Pause,
+    Resume,
+    Scan,
+}
+
+/// Applies `action` to the executor's controls, if any, and reports them
+fn control_request(
+    state: &ApiState,
+    action: Option<ControlAction>,
+) -> Result<Json<ControlResponse>, (StatusCode, String)> {
+    let Some(executor) = state.executor.as_ref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "controls are not available on this server".to_string(),
+        ));
+    };
+    let control = executor.control();
+    match action {
+        Some(ControlAction::Pause) => {
+            control.pause();
+            log_info!("Queue paused");
+        }
+        Some(ControlAction::Resume) => {
+            control.resume();
+            log_info!("Queue resumed");
+        }
+        Some(ControlAction::Scan) => control.request_scan(),
+        None => {}
+    }
+    Ok(Json(ControlResponse {
+        queue_paused: control.is_paused(),
+        scan_requested: control.scan_pending(),
+    }))
+}
+
+/// Handler for GET /control endpoint
+async fn get_control(
+    State(state): State<ApiState>,
+) -> Result<Json<ControlResponse>, (StatusCode, String)> {
+    control_request(&state, None)
+}
+
+/// Handler for POST /control/pause endpoint
+/// Holds queued jobs once they get a slot; running jobs carry on
+async fn pause_queue(
+    State(state): State<ApiState>,
+) -> Result<Json<ControlResponse>, (StatusCode, String)> {
+    control_request(&state, Some(ControlAction::Pause))
+}
+
+/// Handler for POST /control/resume endpoint
+async fn resume_queue(
+    State(state): State<ApiState>,
+) -> Result<Json<ControlResponse>, (StatusCode, String)> {
+    control_request(&state, Some(ControlAction::Resume))
+}
+
+/// Handler for POST /control/scan endpoint
+/// Starts a scan cycle now instead of at the end of the scan interval
+async fn request_scan(
+    State(state): State<ApiState>,
+) -> Result<Json<ControlResponse>, (StatusCode, String)> {
+    control_request(&state, Some(ControlAction::Scan))
+}
+
+/// Handler for POST /jobs/import endpoint
+/// Takes a newline-delimited list of paths as the request body and runs each
+/// through the pipeline without scanning
+async fn import_jobs(
+    State(state): State<ApiState>,
+    body: String,
+) -> Result<Json<Vec<ImportEntry>>, (StatusCode, String)> {
+    let Some(pipeline) = state.pipeline.as_ref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "import is not available on this server".to_string(),
+        ));
+    };
+
+    let paths = parse_path_list(&body);
+    Ok(Json(import_paths(pipeline, &paths, JobOverrides::default()).await))
+}
+
+/// Request body for POST /jobs
+#[derive(Debug, Deserialize)]
+pub struct SubmitRequest {
+    /// Media file to encode
+    pub path: PathBuf,
+    /// Settings for this job only: `crf`, `preset`, `workers`,
+    /// `skip_size_gate`
+    #[serde(flatten)]
+    pub overrides: JobOverrides,
+}
+
+/// Handler for POST /jobs endpoint
+/// Runs one file through the pipeline like an import, with per-job overrides
+/// of the encode settings recorded on its job
+async fn submit_job(
+    State(state): State<ApiState>,
+    Json(request): Json<SubmitRequest>,
+) -> Result<Json<ImportEntry>, (StatusCode, String)> {
+    let Some(pipeline) = state.pipeline.as_ref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "submit is not available on this server".to_string(),
+        ));
+    };
+    request
+        .overrides
+        .validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    check_import_path(&request.path, &pipeline.config)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let entries = import_paths(pipeline, &[request.path], request.overrides).await;
+    entries.into_iter().next().map(Json).ok_or_else(|| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "import returned no result".to_string(),
+        )
+    })
+}
+
+/// Creates the full API router: metrics endpoints plus job queries
+pub fn create_api_router(state: ApiState) -> Router {
+    Router::new()
+        .route("/jobs", get(list_jobs).post(submit_job))
+        .route("/stats/timings", get(get_timing_stats))
+        .route("/jobs/requeue", post(requeue_job))
+        .route("/jobs/import", post(import_jobs))
+        .route("/jobs/cancel", post(cancel_job_request))
+        .route("/jobs/approve", post(approve_job_request))
+        .route("/jobs/reject", post(reject_job_request))
+        .route("/jobs/note", post(add_note_request))
+        .route("/jobs/depend", post(add_dependencies_request))
+        .route("/queue/reorder", post(reorder_queue_request))
+        .route("/config", get(get_config))
+        .route("/control", get(get_control))
+        .route("/control/pause", post(pause_queue))
+        .route("/control/resume", post(resume_queue))
+        .route("/control/scan", post(request_scan))
+        .with_state(state.clone())
+        .merge(create_metrics_router(state.metrics))
+}
+
+/// Address the API listens on unless told otherwise
+pub const DEFAULT_API_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 7878);
+
+/// Runs the full API server on `addr`, normally [`DEFAULT_API_ADDR`]
+///
+/// Serves the same metrics endpoints as [`run_metrics_server`] together
+/// with the job API.
+pub async fn run_api_server(state: ApiState, addr: SocketAddr) -> Result<(), ServerError> {
+    let app = create_api_router(state);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app)
+        .await
+        .map_err(ServerError::BindError)?;
+
+    Ok(())
+}
+
+/// Runs the full API on the unix socket at `path`
+///
+/// A socket file left behind by an earlier run is replaced; the new one is
+/// made accessible to the daemon's user and group only, so local tools in
+/// that group can use the API without any network exposure. It is created
+/// in a directory only the daemon can enter and moved into place once
+/// narrowed, since the API has no authentication of its own.
+///
+/// # Returns
+/// * `Err(ServerError)` if the socket cannot be created; otherwise runs
+///   until the task is dropped
+#[cfg(unix)]
+pub async fn run_unix_api_server(state: ApiState, path: &std::path::Path) -> Result<(), ServerError> {
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+
+    // Bound with whatever mode the umask allows, the socket must not be
+    // reachable until it is narrowed
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let staging = path.with_file_name(format!(".{}.{}", file_name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join("api.sock");
+    let bound = tokio::net::UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o660))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_dir_all(&staging);
+    let listener = bound?;
+
+    let app = create_api_router(state);
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            // Errors such as running out of file descriptors pass; back off
+            // so the loop does not spin while they last
+            Err(e) => {
+                log_warn!("Warning: API socket accept failed: {}", e);
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let service = hyper_util::service::TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let io = hyper_util::rt::TokioIo::new(stream);
+            if let Err(e) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(io, service)
+                .await
+            {
+                log_warn!("Warning: API socket connection failed: {}", e);
+            }
+        });
+    }
+}
+
+/// Runs the metrics HTTP server on `addr`, normally [`DEFAULT_API_ADDR`]
 ///
 /// # Arguments
 /// * `metrics` - Shared metrics state to serve
+/// * `addr` - Address to listen on
 ///
 /// # Returns
 /// * `Ok(())` if server shuts down gracefully
 /// * `Err(ServerError)` if server fails to start
-pub async fn run_metrics_server(metrics: SharedMetrics) -> Result<(), ServerError> {
+pub async fn run_metrics_server(
+    metrics: SharedMetrics,
+    addr: SocketAddr,
+) -> Result<(), ServerError> {
     let app = create_metrics_router(metrics);
-    let addr = SocketAddr::from(([127, 0, 0, 1], 7878));
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, app)
         .await
-        .map_err(|e| ServerError::BindError(e))?;
+        .map_err(ServerError::BindError)?;
 
     Ok(())
 }
 
+fn current_timestamp_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,6 +878,7 @@ mod tests {
                 load_avg_1: 27.5,
                 load_avg_5: 26.8,
                 load_avg_15: 25.2,
+                ..Default::default()
             };
             snapshot.jobs.push(JobMetrics {
                 id: "job-001".to_string(),
@@ -94,6 +898,9 @@ mod tests {
                 vmaf: None,
                 psnr: None,
                 ssim: None,
+                tags: vec![],
+                energy_joules: 0.0,
+                quality_flag: None,
             });
         }
 
@@ -184,6 +991,7 @@ mod tests {
                 load_avg_1: 27.5,
                 load_avg_5: 26.8,
                 load_avg_15: 25.2,
+                ..Default::default()
             };
         }
 
@@ -219,4 +1027,915 @@ mod tests {
         assert!(json_str.contains("failed_jobs"));
         assert!(json_str.contains("total_bytes_encoded"));
     }
+
+    #[tokio::test]
+    async fn test_get_skip_stats() {
+        let metrics = new_shared_metrics();
+        {
+            let mut snapshot = metrics.write().await;
+            snapshot.skip_totals.insert("already_av1".to_string(), 120);
+            snapshot.skip_totals.insert("size_gate_rejected".to_string(), 7);
+        }
+
+        let app = create_metrics_router(metrics);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/stats/skips")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["total"], 127);
+        assert_eq!(json["counts"]["already_av1"], 120);
+        assert_eq!(json["counts"]["size_gate_rejected"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_get_history_filters_by_since() {
+        let metrics = new_shared_metrics();
+        {
+            let mut snapshot = metrics.write().await;
+            for (i, bytes) in [0u64, 500, 1500].iter().enumerate() {
+                snapshot.total_bytes_encoded = *bytes;
+                snapshot.record_history(i as i64 * 60_000);
+            }
+        }
+
+        let app = create_metrics_router(metrics);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/metrics/history?since_unix_ms=60000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let history: HistoryResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(history.interval_secs, HISTORY_SAMPLE_INTERVAL_SECS);
+        assert_eq!(history.samples.len(), 2);
+        assert_eq!(history.samples[1].total_bytes_encoded, 1500);
+    }
+
+    #[tokio::test]
+    async fn test_get_energy_stats() {
+        let metrics = new_shared_metrics();
+        {
+            let mut snapshot = metrics.write().await;
+            snapshot.energy.available = true;
+            snapshot.energy.encode_joules = 7_200_000.0;
+            snapshot.jobs.push(JobMetrics {
+                id: "job-001".to_string(),
+                input_path: "/media/video.mkv".to_string(),
+                stage: "completed".to_string(),
+                progress: 1.0,
+                fps: 0.0,
+                bitrate_kbps: 0.0,
+                crf: 8,
+                encoder: "svt-av1".to_string(),
+                workers: 8,
+                est_remaining_secs: 0.0,
+                frames_encoded: 0,
+                total_frames: 0,
+                size_in_bytes_before: 5000,
+                size_in_bytes_after: 2000,
+                vmaf: None,
+                psnr: None,
+                ssim: None,
+                tags: vec![],
+                energy_joules: 3_600_000.0,
+                quality_flag: None,
+            });
+        }
+
+        let app = create_metrics_router(metrics);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/stats/energy")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["available"], true);
+        assert_eq!(json["total_kwh"], 2.0);
+        assert_eq!(json["jobs"][0]["kwh"], 1.0);
+        assert_eq!(json["jobs"][0]["bytes_saved"], 3000);
+    }
+
+    #[tokio::test]
+    async fn test_backup_stats_endpoint() {
+        let metrics = new_shared_metrics();
+        {
+            let mut m = metrics.write().await;
+            m.backups.kept = 2;
+            m.backups.bytes_kept = 5000;
+            m.backups.removed = 1;
+        }
+
+        let app = create_metrics_router(metrics);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/stats/backups")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["kept"], 2);
+        assert_eq!(json["bytes_kept"], 5000);
+        assert_eq!(json["removed"], 1);
+        assert_eq!(json["bytes_freed"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_list_jobs_filters_by_tags_and_status() {
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, save_job};
+        use crate::scan::ScanCandidate;
+        use crate::classify::SourceType;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 60.0,
+                size_bytes: 1000,
+            },
+        };
+        let candidate = |path: &str| ScanCandidate {
+            path: PathBuf::from(path),
+            size_bytes: 1000,
+            modified_time: std::time::SystemTime::now(),
+            root: PathBuf::from("/media/movies"),
+        };
+
+        let mut failed_disc = create_job(&candidate("/media/movies/a.mkv"), probe.clone(), SourceType::DiscLike, temp_dir.path());
+        failed_disc.add_tag("4k");
+        failed_disc.fail("encode error");
+        let pending_disc = create_job(&candidate("/media/movies/b.mkv"), probe.clone(), SourceType::DiscLike, temp_dir.path());
+        let failed_web = create_job(&candidate("/media/movies/c.mkv"), probe, SourceType::WebLike, temp_dir.path());
+        for job in [&failed_disc, &pending_disc, &failed_web] {
+            save_job(job, temp_dir.path()).unwrap();
+        }
+
+        let app = create_api_router(ApiState {
+            metrics: new_shared_metrics(),
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: None,
+        });
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/jobs?tags=4k,disc&status=failed")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let jobs: Vec<Job> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, failed_disc.id);
+
+        // Queued jobs carry estimated start and finish times, failed ones do not
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/jobs").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        for job in json.as_array().unwrap() {
+            let queued = job["status"] == "pending";
+            assert_eq!(job["est_start_unix_ms"].is_i64(), queued);
+            assert_eq!(job["est_finish_unix_ms"].is_i64(), queued);
+        }
+
+        // Metrics endpoints remain available on the API router
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_get_timing_stats() {
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, save_job};
+        use crate::scan::ScanCandidate;
+        use crate::classify::SourceType;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 60.0,
+                size_bytes: 1000,
+            },
+        };
+        for (i, encode_secs) in [100.0, 300.0, 200.0].into_iter().enumerate() {
+            let candidate = ScanCandidate {
+                path: PathBuf::from(format!("/media/movies/{}.mkv", i)),
+                size_bytes: 1000,
+                modified_time: std::time::SystemTime::now(),
+                root: PathBuf::from("/media/movies"),
+            };
+            let mut job = create_job(&candidate, probe.clone(), SourceType::WebLike, temp_dir.path());
+            job.stage_secs.insert("encoding".to_string(), encode_secs);
+            job.stage_secs.insert("replacing".to_string(), 30.0);
+            save_job(&job, temp_dir.path()).unwrap();
+        }
+
+        let app = create_api_router(ApiState {
+            metrics: new_shared_metrics(),
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: None,
+        });
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/stats/timings")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["jobs"], 3);
+        assert_eq!(json["stages"]["encoding"]["p50_secs"], 200.0);
+        assert_eq!(json["stages"]["encoding"]["p95_secs"], 300.0);
+        assert_eq!(json["stages"]["replacing"]["count"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_requeue_without_pipeline_is_unavailable() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let app = create_api_router(ApiState {
+            metrics: new_shared_metrics(),
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: None,
+        });
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/jobs/requeue")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"path": "/media/movies/a.mkv"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_requeue_missing_file_is_not_found() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = crate::config::Config::default();
+        config.paths.job_state_dir = temp_dir.path().join("jobs");
+        config.scan.library_roots = vec![temp_dir.path().to_path_buf()];
+        let (job_tx, _job_rx) = tokio::sync::mpsc::unbounded_channel();
+        let metrics = new_shared_metrics();
+        let app = create_api_router(ApiState {
+            metrics: metrics.clone(),
+            job_state_dir: config.paths.job_state_dir.clone(),
+            pipeline: Some(PipelineContext {
+                config,
+                metrics,
+                job_tx,
+            }),
+            executor: None,
+        });
+
+        let body = serde_json::json!({ "path": temp_dir.path().join("gone.mkv") }).to_string();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/jobs/requeue")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_submit_refuses_paths_outside_libraries() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let library = temp_dir.path().join("media");
+        std::fs::create_dir_all(&library).unwrap();
+        let outside = temp_dir.path().join("film.mkv");
+        std::fs::write(&outside, b"not a video").unwrap();
+        let mut config = crate::config::Config::default();
+        config.paths.job_state_dir = temp_dir.path().join("jobs");
+        config.scan.library_roots = vec![library.clone()];
+        let (job_tx, _job_rx) = tokio::sync::mpsc::unbounded_channel();
+        let metrics = new_shared_metrics();
+        let app = create_api_router(ApiState {
+            metrics: metrics.clone(),
+            job_state_dir: config.paths.job_state_dir.clone(),
+            pipeline: Some(PipelineContext {
+                config,
+                metrics,
+                job_tx,
+            }),
+            executor: None,
+        });
+        let submit = |path: &std::path::Path| {
+            Request::builder()
+                .method("POST")
+                .uri("/jobs")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::json!({ "path": path }).to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(submit(&outside)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app.clone().oneshot(submit(&library.join("notes.txt"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(!crate::scan::has_skip_marker(&outside));
+
+        // Paths inside a library still get an entry, here for a missing file
+        let response = app.oneshot(submit(&library.join("gone.mkv"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_config_is_redacted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = crate::config::Config::default();
+        config.av1an.workers_per_job = 3;
+        config.torrent.password = Some("hunter2".to_string());
+        let (job_tx, _job_rx) = tokio::sync::mpsc::unbounded_channel();
+        let metrics = new_shared_metrics();
+        let app = create_api_router(ApiState {
+            metrics: metrics.clone(),
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: Some(PipelineContext {
+                config,
+                metrics,
+                job_tx,
+            }),
+            executor: None,
+        });
+
+        let response = app
+            .oneshot(Request::builder().uri("/config").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["av1an"]["workers_per_job"], 3);
+        assert_eq!(json["torrent"]["password"], crate::config::REDACTED);
+        assert!(!String::from_utf8_lossy(&body).contains("hunter2"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_api_over_unix_socket() {
+        use std::os::unix::fs::PermissionsExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("run").join("api.sock");
+        let state = ApiState {
+            metrics: new_shared_metrics(),
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: None,
+        };
+        let server = {
+            let path = path.clone();
+            tokio::spawn(async move { run_unix_api_server(state, &path).await })
+        };
+        let mut stream = loop {
+            match tokio::net::UnixStream::connect(&path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+        // The private directory it was created in is gone
+        let entries: Vec<_> = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, ["api.sock"]);
+
+        stream
+            .write_all(b"GET /jobs HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("[]"), "{}", response);
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_unreadable_paths() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = crate::config::Config::default();
+        config.paths.job_state_dir = temp_dir.path().join("jobs");
+        let (job_tx, _job_rx) = tokio::sync::mpsc::unbounded_channel();
+        let metrics = new_shared_metrics();
+        let app = create_api_router(ApiState {
+            metrics: metrics.clone(),
+            job_state_dir: config.paths.job_state_dir.clone(),
+            pipeline: Some(PipelineContext {
+                config,
+                metrics,
+                job_tx,
+            }),
+            executor: None,
+        });
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/jobs/import")
+                    .body(Body::from("/nonexistent/a.mkv\n\n/nonexistent/b.mkv\n"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let entries = json.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["path"], "/nonexistent/a.mkv");
+        assert_eq!(entries[0]["outcome"], "error");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_queued_job() {
+        use crate::classify::SourceType;
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, save_job};
+        use crate::scan::ScanCandidate;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 60.0,
+                size_bytes: 1000,
+            },
+        };
+        let candidate = ScanCandidate {
+            path: PathBuf::from("/media/movies/a.mkv"),
+            size_bytes: 1000,
+            modified_time: std::time::SystemTime::UNIX_EPOCH,
+            root: PathBuf::from("/media/movies"),
+        };
+        let job = create_job(&candidate, probe, SourceType::Unknown, temp_dir.path());
+        save_job(&job, temp_dir.path()).unwrap();
+
+        let metrics = new_shared_metrics();
+        let executor = Arc::new(JobExecutor::new(
+            crate::concurrency::derive_plan(&crate::config::Config::default()),
+            metrics.clone(),
+            temp_dir.path().join("chunks"),
+        ));
+        let app = create_api_router(ApiState {
+            metrics,
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: Some(executor),
+        });
+
+        let cancel = |id: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/jobs/cancel")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::json!({ "id": id }).to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(cancel(&job.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["was_running"], false);
+        assert_eq!(load_jobs(temp_dir.path()).unwrap()[0].status, JobStatus::Cancelled);
+
+        let response = app.clone().oneshot(cancel(&job.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = app.oneshot(cancel("0000-missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_review_refuses_jobs_not_held() {
+        use crate::classify::SourceType;
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, save_job};
+        use crate::scan::ScanCandidate;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 60.0,
+                size_bytes: 1000,
+            },
+        };
+        let candidate = ScanCandidate {
+            path: PathBuf::from("/media/movies/a.mkv"),
+            size_bytes: 1000,
+            modified_time: std::time::SystemTime::UNIX_EPOCH,
+            root: PathBuf::from("/media/movies"),
+        };
+        let job = create_job(&candidate, probe, SourceType::Unknown, temp_dir.path());
+        save_job(&job, temp_dir.path()).unwrap();
+
+        let review = |action: &str, id: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/jobs/{}", action))
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::json!({ "id": id }).to_string()))
+                .unwrap()
+        };
+
+        let metrics = new_shared_metrics();
+        let state = ApiState {
+            metrics: metrics.clone(),
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: None,
+        };
+        let response = create_api_router(state.clone())
+            .oneshot(review("approve", &job.id))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let executor = Arc::new(JobExecutor::new(
+            crate::concurrency::derive_plan(&crate::config::Config::default()),
+            metrics,
+            temp_dir.path().join("chunks"),
+        ));
+        let app = create_api_router(ApiState {
+            executor: Some(executor),
+            ..state
+        });
+
+        // Still queued, so there is nothing to review yet
+        let response = app.clone().oneshot(review("approve", &job.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = app.clone().oneshot(review("reject", &job.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = app.oneshot(review("reject", "0000-missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_add_note_to_job() {
+        use crate::classify::SourceType;
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, save_job};
+        use crate::scan::ScanCandidate;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 60.0,
+                size_bytes: 1000,
+            },
+        };
+        let candidate = ScanCandidate {
+            path: PathBuf::from("/media/movies/a.mkv"),
+            size_bytes: 1000,
+            modified_time: std::time::SystemTime::UNIX_EPOCH,
+            root: PathBuf::from("/media/movies"),
+        };
+        let job = create_job(&candidate, probe, SourceType::Unknown, temp_dir.path());
+        save_job(&job, temp_dir.path()).unwrap();
+
+        let app = create_api_router(ApiState {
+            metrics: new_shared_metrics(),
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: None,
+        });
+        let note = |id: &str, text: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/jobs/note")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::json!({ "id": id, "text": text }).to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(note(&job.id, "VMAF looked soft on dark scenes, rejected"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["notes"][0]["text"], "VMAF looked soft on dark scenes, rejected");
+
+        let response = app.clone().oneshot(note(&job.id, "  ")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app.clone().oneshot(note("0000-missing", "hi")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Notes show up in the job list
+        let response = app
+            .oneshot(Request::builder().uri("/jobs").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["notes"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_add_dependencies() {
+        use crate::classify::SourceType;
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, save_job};
+        use crate::scan::ScanCandidate;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let job = |path: &str| {
+            let probe = ProbeResult {
+                video_streams: vec![],
+                audio_streams: vec![],
+                subtitle_streams: vec![],
+                font_attachments: 0,
+                format: FormatInfo {
+                    duration_secs: 60.0,
+                    size_bytes: 1000,
+                },
+            };
+            let candidate = ScanCandidate {
+                path: PathBuf::from(path),
+                size_bytes: 1000,
+                modified_time: std::time::SystemTime::UNIX_EPOCH,
+                root: PathBuf::from("/media/movies"),
+            };
+            let job = create_job(&candidate, probe, SourceType::Unknown, temp_dir.path());
+            save_job(&job, temp_dir.path()).unwrap();
+            job
+        };
+        let movie = job("/media/movies/Heat/Heat.mkv");
+        let extra = job("/media/movies/Heat/Extras/making of.mkv");
+
+        let app = create_api_router(ApiState {
+            metrics: new_shared_metrics(),
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: None,
+        });
+        let depend = |id: &str, after: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/jobs/depend")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::json!({ "id": id, "after": [after] }).to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(depend(&extra.id, &movie.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["depends_on"][0], movie.id.as_str());
+
+        // The movie waiting for its extra would never start
+        let response = app.clone().oneshot(depend(&movie.id, &extra.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = app.oneshot(depend(&movie.id, "0000-missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_control_endpoints() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let post = |uri: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let read = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        // Without an executor there is nothing to control
+        let app = create_api_router(ApiState {
+            metrics: new_shared_metrics(),
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: None,
+        });
+        let response = app.oneshot(post("/control/pause")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let metrics = new_shared_metrics();
+        let executor = Arc::new(JobExecutor::new(
+            crate::concurrency::derive_plan(&crate::config::Config::default()),
+            metrics.clone(),
+            temp_dir.path().join("chunks"),
+        ));
+        let app = create_api_router(ApiState {
+            metrics,
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: Some(executor.clone()),
+        });
+
+        let json = read(app.clone().oneshot(post("/control/pause")).await.unwrap()).await;
+        assert_eq!(json["queue_paused"], true);
+        assert!(executor.control().is_paused());
+
+        let json = read(app.clone().oneshot(post("/control/scan")).await.unwrap()).await;
+        assert_eq!(json["scan_requested"], true);
+
+        let json = read(app.clone().oneshot(post("/control/resume")).await.unwrap()).await;
+        assert_eq!(json["queue_paused"], false);
+        assert_eq!(json["scan_requested"], true);
+
+        let request = Request::builder().uri("/control").body(Body::empty()).unwrap();
+        let json = read(app.oneshot(request).await.unwrap()).await;
+        assert_eq!(json["queue_paused"], false);
+    }
+
+    #[tokio::test]
+    async fn test_reorder_queue() {
+        use crate::classify::SourceType;
+        use crate::gates::{FormatInfo, ProbeResult};
+        use crate::jobs::{create_job, save_job};
+        use crate::scan::ScanCandidate;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 60.0,
+                size_bytes: 1000,
+            },
+        };
+        let mut ids = Vec::new();
+        for (i, name) in ["a", "b", "c"].iter().enumerate() {
+            let candidate = ScanCandidate {
+                path: PathBuf::from(format!("/media/movies/{}.mkv", name)),
+                size_bytes: 1000,
+                modified_time: std::time::SystemTime::UNIX_EPOCH,
+                root: PathBuf::from("/media/movies"),
+            };
+            let mut job = create_job(&candidate, probe.clone(), SourceType::Unknown, temp_dir.path());
+            job.created_at = i as i64;
+            save_job(&job, temp_dir.path()).unwrap();
+            ids.push(job.id);
+        }
+
+        let app = create_api_router(ApiState {
+            metrics: new_shared_metrics(),
+            job_state_dir: temp_dir.path().to_path_buf(),
+            pipeline: None,
+            executor: None,
+        });
+        let reorder = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/queue/reorder")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(reorder(serde_json::json!({ "top": ids[2] })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["order"], serde_json::json!([ids[2], ids[0], ids[1]]));
+
+        let response = app
+            .clone()
+            .oneshot(reorder(serde_json::json!({ "ids": [ids[1], ids[0]] })))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["order"], serde_json::json!([ids[1], ids[0], ids[2]]));
+
+        let response = app
+            .clone()
+            .oneshot(reorder(serde_json::json!({ "top": "0000-missing" })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = app.oneshot(reorder(serde_json::json!({}))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
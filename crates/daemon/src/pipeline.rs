@@ -0,0 +1,1499 @@
+//! Candidate pipeline for AV1 Super Daemon
+//!
+//! Takes a discovered file through stability, probe, gates, and
+//! classification, then creates, persists, and queues its job. The periodic
+//! scan cycle and on-demand requeues share this path so every file is held
+//! to the same checks however it entered the daemon.
+
+use crate::claims::{unix_now, FarmNode};
+use crate::classify::{classify_source, season_key, SourceType};
+use crate::config::{Config, HardlinkPolicy, SeedAction};
+use crate::coverage::{expected_savings_ratio, measure_coverage};
+use crate::duplicates::{find_duplicates, DuplicateReport};
+use crate::encode::{is_remux_container, SvtOverrides};
+use crate::encode_settings::{read_settings_sidecar, settings_sidecar_path};
+use crate::gates::{
+    check_gates, probe_file_async, GateResult, GatesConfig as DaemonGatesConfig, ProbeError,
+    ProbeResult,
+};
+use crate::job_executor::Job;
+use crate::jobs::{
+    create_job, job_exists_for_path, load_jobs, remove_terminal_jobs_for_path, save_job,
+    Job as ManagedJob, JobKind, JobOverrides,
+};
+use crate::metrics::SharedMetrics;
+use crate::probe_cache::ProbeCache;
+use crate::replace::resolve_output_path;
+use crate::scan::{
+    candidate_for_path, group_by_season, hard_link_count, has_force_marker, has_video_extension,
+    in_library_roots, order_candidates,
+    scan_libraries_parallel, ScanCandidate,
+};
+use crate::scan_cache::{scan_libraries_incremental, ScanCache};
+use crate::skip_marker::{
+    remove_force_marker, remove_skip_marker, remove_why_sidecars, write_force_marker,
+    write_skip_marker_with_code, write_why_json, write_why_sidecar, SkipCode, SkipReason,
+};
+use crate::skip_stats::{persist_skip_stats, record_skip};
+use crate::stability::{check_stability, StabilityResult};
+use crate::telemetry::SpanTimes;
+use crate::torrent::{seeding_hashes, Torrent, TorrentClient, TorrentError};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Everything needed to evaluate a candidate and hand its job to the executor
+#[derive(Clone)]
+pub struct PipelineContext {
+    /// Daemon configuration
+    pub config: Config,
+    /// Shared metrics state
+    pub metrics: SharedMetrics,
+    /// Sender side of the job queue
+    pub job_tx: mpsc::UnboundedSender<Job>,
+}
+
+/// What happened to a single candidate
+#[derive(Debug, Clone, PartialEq)]
+pub enum CandidateOutcome {
+    /// A job was created and queued
+    Queued { job_id: String },
+    /// A pending or running job already exists for the path
+    ExistingJob,
+    /// The stability check could not be performed
+    StabilityError(String),
+    /// The file is still changing and will be retried later
+    Unstable,
+    /// The file was skipped and marked with `.av1skip`
+    Skipped(SkipReason),
+    /// The job was persisted but could not be queued
+    QueueFailed(String),
+    /// A torrent is seeding the file, or the torrent client could not be
+    /// asked; the file is looked at again next scan
+    Seeding,
+    /// The file was modified more recently than `gates.min_age_days` ago;
+    /// it is looked at again next scan
+    TooNew,
+    /// Another daemon sharing the library claimed the file; it is looked at
+    /// again next scan
+    ClaimedElsewhere,
+}
+
+impl CandidateOutcome {
+    /// Short label used for per-cycle skip counts and API responses
+    pub fn label(&self) -> &'static str {
+        match self {
+            CandidateOutcome::Queued { .. } => "queued",
+            CandidateOutcome::ExistingJob => "existing_job",
+            CandidateOutcome::StabilityError(_) => "stability_error",
+            CandidateOutcome::Unstable => "unstable",
+            CandidateOutcome::Skipped(reason) => reason.code.as_str(),
+            CandidateOutcome::QueueFailed(_) => "queue_failed",
+            CandidateOutcome::Seeding => "seeding",
+            CandidateOutcome::TooNew => "too_new",
+            CandidateOutcome::ClaimedElsewhere => "claimed_elsewhere",
+        }
+    }
+
+    /// Splits the outcome into its label, new job ID, and detail message
+    pub fn into_parts(self) -> (String, Option<String>, Option<String>) {
+        let label = self.label().to_string();
+        match self {
+            CandidateOutcome::Queued { job_id } => (label, Some(job_id), None),
+            CandidateOutcome::Skipped(reason) => (label, None, Some(reason.message)),
+            CandidateOutcome::StabilityError(e) | CandidateOutcome::QueueFailed(e) => {
+                (label, None, Some(e))
+            }
+            CandidateOutcome::ExistingJob
+            | CandidateOutcome::Unstable
+            | CandidateOutcome::Seeding
+            | CandidateOutcome::TooNew
+            | CandidateOutcome::ClaimedElsewhere => (label, None, None),
+        }
+    }
+}
+
+/// Encode settings shared by the episodes of each season when
+/// `scan.batch_seasons` is on.
+///
+/// The first episode of a season queued sets the source class (and with it
+/// the encode profile) and the CRF and preset overrides; later episodes of
+/// the same season reuse them instead of being classified on their own, since
+/// episodes of one show compress almost identically.
+#[derive(Debug, Default)]
+struct SeasonBatches {
+    leaders: HashMap<String, (SourceType, JobOverrides)>,
+}
+
+impl SeasonBatches {
+    /// Seeds the batches from already persisted jobs, the earliest job of
+    /// each season leading it
+    fn from_jobs(jobs: &[ManagedJob]) -> Self {
+        let mut batches = Self::default();
+        let mut jobs: Vec<&ManagedJob> = jobs.iter().filter(|job| job.batch_key.is_some()).collect();
+        jobs.sort_by_key(|job| job.created_at);
+        for job in jobs {
+            if let Some(key) = &job.batch_key {
+                batches
+                    .leaders
+                    .entry(key.clone())
+                    .or_insert((job.source_type, job.overrides));
+            }
+        }
+        batches
+    }
+
+    /// Source class and overrides for an episode of the season `key`
+    ///
+    /// The episode's own CRF and preset overrides win over the season's.
+    /// Without a leader yet, the episode becomes one.
+    fn settings_for(
+        &mut self,
+        key: &str,
+        source_type: SourceType,
+        overrides: JobOverrides,
+    ) -> (SourceType, JobOverrides) {
+        let (leader_type, leader_overrides) = *self
+            .leaders
+            .entry(key.to_string())
+            .or_insert((source_type, overrides));
+        let mut shared = overrides;
+        shared.crf = overrides.crf.or(leader_overrides.crf);
+        shared.preset = overrides.preset.or(leader_overrides.preset);
+        (leader_type, shared)
+    }
+}
+
+/// Runs one candidate through stability, probe, gates, and classification,
+/// queueing a job if it passes.
+///
+/// Skips are recorded in the lifetime skip counters but not persisted;
+/// callers decide when to flush them.
+///
+/// # Requirements
+/// - 12.1-12.4: Verify file stability before processing
+/// - 13.1-13.6: Gate files based on probe results
+/// - 14.1-14.3: Create and persist jobs, avoiding duplicates
+/// - 15.1-15.5: Classify source files
+pub async fn process_candidate(
+    ctx: &PipelineContext,
+    candidate: &ScanCandidate,
+    existing_jobs: &[ManagedJob],
+) -> CandidateOutcome {
+    process_candidate_with(ctx, candidate, existing_jobs, JobOverrides::default(), false).await
+}
+
+/// [`process_candidate`], with `overrides` recorded on the job it queues.
+///
+/// `force` bypasses the gates as an `.av1force` marker would.
+async fn process_candidate_with(
+    ctx: &PipelineContext,
+    candidate: &ScanCandidate,
+    existing_jobs: &[ManagedJob],
+    overrides: JobOverrides,
+    force: bool,
+) -> CandidateOutcome {
+    // Skip if job already exists for this path (Requirement 14.3)
+    if has_job(&ctx.config, existing_jobs, &candidate.path) {
+        return CandidateOutcome::ExistingJob;
+    }
+    if too_new(&ctx.config, candidate, SystemTime::now()) {
+        return CandidateOutcome::TooNew;
+    }
+    let farm = FarmNode::from_config(&ctx.config.farm);
+    if claimed_elsewhere(farm.as_ref(), &candidate.path) {
+        return CandidateOutcome::ClaimedElsewhere;
+    }
+    if ctx.config.torrent.action_for(&candidate.path) == SeedAction::Skip {
+        let torrents = fetch_torrents(&ctx.config).await;
+        if held_for_seeding(&ctx.config, torrents.as_ref(), &candidate.path) {
+            return CandidateOutcome::Seeding;
+        }
+    }
+
+    match inspect_candidate(candidate, ctx.config.scan.stability_wait_secs, None).await {
+        Inspection::Settled(outcome) => outcome,
+        Inspection::Probed {
+            result,
+            probe_times,
+        } => {
+            let mut batches = ctx
+                .config
+                .scan
+                .batch_seasons
+                .then(|| SeasonBatches::from_jobs(existing_jobs));
+            finish_candidate(
+                ctx,
+                candidate,
+                result,
+                probe_times,
+                overrides,
+                batches.as_mut(),
+                force,
+            )
+            .await
+        }
+    }
+}
+
+/// Result of the stability check and probe, before any gate is applied
+enum Inspection {
+    /// The candidate was settled before probing
+    Settled(CandidateOutcome),
+    /// The file was probed; `probe_times` is when ffprobe ran, `None` when
+    /// the result came from the probe cache
+    Probed {
+        result: Result<ProbeResult, ProbeError>,
+        probe_times: Option<SpanTimes>,
+    },
+}
+
+/// Checks that a candidate is stable, then probes it unless `cached` holds
+/// a probe of the unchanged file.
+///
+/// Touches nothing but the file itself, so the scan cycle runs several of
+/// these at once.
+async fn inspect_candidate(
+    candidate: &ScanCandidate,
+    stability_wait_secs: u64,
+    cached: Option<ProbeResult>,
+) -> Inspection {
+    // Stability check (Requirements 12.1-12.4)
+    let stability_result =
+        match check_stability(&candidate.path, candidate.size_bytes, stability_wait_secs).await {
+            Ok(result) => result,
+            Err(e) => {
+                log_warn!(
+                    "Warning: Stability check failed for {:?}: {}",
+                    candidate.path, e
+                );
+                return Inspection::Settled(CandidateOutcome::StabilityError(e.to_string()));
+            }
+        };
+
+    // Skip unstable files (Requirement 12.3)
+    if let StabilityResult::Unstable { .. } = stability_result {
+        return Inspection::Settled(CandidateOutcome::Unstable);
+    }
+
+    // Probe file (Requirement 13.1)
+    match cached {
+        Some(probe) => Inspection::Probed {
+            result: Ok(probe),
+            probe_times: None,
+        },
+        None => {
+            let started = SystemTime::now();
+            let result = probe_file_async(&candidate.path).await;
+            Inspection::Probed {
+                result,
+                probe_times: Some(SpanTimes::since(started)),
+            }
+        }
+    }
+}
+
+/// Applies gates and classification to a probed candidate and queues its
+/// job, carrying `overrides`.
+///
+/// With `batches`, an episode takes its season's shared settings. `force`
+/// bypasses the gates even without an `.av1force` marker.
+async fn finish_candidate(
+    ctx: &PipelineContext,
+    candidate: &ScanCandidate,
+    probe_result: Result<ProbeResult, ProbeError>,
+    probe_times: Option<SpanTimes>,
+    overrides: JobOverrides,
+    batches: Option<&mut SeasonBatches>,
+    force: bool,
+) -> CandidateOutcome {
+    let config = &ctx.config;
+
+    let links = hard_link_count(&candidate.path);
+    if links > 1 && config.gates.hardlinks == HardlinkPolicy::Skip {
+        let reason = SkipReason::new(
+            SkipCode::Hardlinked,
+            format!("file has {} hard links", links),
+        )
+        .with_threshold("hard_links", links as f64);
+        return skip(ctx, &candidate.path, reason).await;
+    }
+
+    // A probe failure creates a skip marker (Requirement 13.2)
+    let probe_result = match probe_result {
+        Ok(result) => result,
+        Err(e) => {
+            let reason = SkipReason::new(SkipCode::ProbeFailed, format!("ffprobe failed: {}", e));
+            return skip(ctx, &candidate.path, reason).await;
+        }
+    };
+
+    // Check gates (Requirements 13.3-13.6)
+    let gates_config = DaemonGatesConfig {
+        min_bytes: config.gates.min_bytes,
+        max_size_ratio: config.gates.max_size_ratio,
+        keep_original: config.gates.keep_original,
+        min_kbps_per_megapixel: config.gates.min_kbps_per_megapixel.clone(),
+        pixel_format: config.pixel_format.clone(),
+        multi_video: config.gates.multi_video,
+    };
+    let forced = force || has_force_marker(&candidate.path);
+    let (probe, kind) = match check_gates(&probe_result, candidate.size_bytes, &gates_config) {
+        GateResult::Pass(probe) => (probe, JobKind::Encode),
+        // A force marker overrides every gate but a missing video stream
+        GateResult::Skip { reason } if forced && reason.code != SkipCode::NoVideoStreams => {
+            log_info!(
+                "Forcing an encode of {:?} past the {} gate: {}",
+                candidate.path,
+                reason.code.gate(),
+                reason.message
+            );
+            (probe_result, JobKind::Encode)
+        }
+        GateResult::Skip { reason } if settings_outdated(config, &candidate.path, &reason) => {
+            log_info!(
+                "Re-encoding {:?}: it was encoded with settings that differ from the current ones",
+                candidate.path
+            );
+            (probe_result, JobKind::Encode)
+        }
+        // Without a marker, a later settings change still gets the file encoded
+        GateResult::Skip { reason } if tracks_settings(config, &candidate.path, &reason) => {
+            record_skip(&ctx.metrics, reason.code).await;
+            return CandidateOutcome::Skipped(reason);
+        }
+        // Already-AV1 files in legacy containers only need a new container
+        GateResult::Skip { reason } if should_remux(config, &candidate.path, &reason) => {
+            (probe_result, JobKind::Remux)
+        }
+        GateResult::Skip { reason } => return skip(ctx, &candidate.path, reason).await,
+    };
+
+    // Classify source (Requirements 15.1-15.4)
+    let mut source_type = classify_source(&candidate.path, &probe);
+    let mut overrides = overrides;
+    if let (Some(batches), Some(key)) = (batches, season_key(&candidate.path)) {
+        (source_type, overrides) = batches.settings_for(&key, source_type, overrides);
+    }
+
+    // Create and persist the job (Requirements 14.1, 14.2)
+    let mut managed_job = create_job(candidate, probe, source_type, &config.paths.temp_output_dir);
+    managed_job.kind = kind;
+    managed_job.forced = forced;
+    managed_job.overrides = overrides;
+    // Remuxing exists to get AV1 into Matroska, whatever the encode container
+    if kind == JobKind::Encode {
+        managed_job
+            .output_path
+            .set_extension(config.output.container.extension());
+    }
+    if let Err(e) = save_job(&managed_job, &config.paths.job_state_dir) {
+        log_warn!("Warning: Failed to save job state: {}", e);
+    }
+
+    // Queue job for execution, carrying the original file size for the size gate
+    let mut executor_job = Job::from_managed(&managed_job, candidate.size_bytes);
+    executor_job.probe_times = probe_times;
+
+    if let Err(e) = ctx.job_tx.send(executor_job) {
+        log_warn!("Warning: Failed to queue job: {}", e);
+        return CandidateOutcome::QueueFailed(e.to_string());
+    }
+    log_info!("Queued job {} for encoding: {:?}", managed_job.id, managed_job.input_path);
+    // The marker asks for one encode, not one per scan
+    if forced && !config.read_only {
+        if let Err(e) = remove_force_marker(&candidate.path) {
+            log_warn!("Warning: Failed to remove force marker for {:?}: {}", candidate.path, e);
+        }
+    }
+
+    ctx.metrics.write().await.queue_len += 1;
+    CandidateOutcome::Queued {
+        job_id: managed_job.id,
+    }
+}
+
+/// Returns true if an already-AV1 file should be remuxed to MKV instead of skipped.
+///
+/// Requires `gates.remux_av1`, a container listed in
+/// [`REMUX_SOURCE_EXTENSIONS`](crate::encode::REMUX_SOURCE_EXTENSIONS), and
+/// an output name that resolves without an unhandled collision.
+fn should_remux(config: &Config, path: &Path, reason: &SkipReason) -> bool {
+    config.gates.remux_av1
+        && reason.code == SkipCode::AlreadyAv1
+        && is_remux_container(path)
+        && resolve_output_path(
+            path,
+            &config.output.rename_template,
+            "mkv",
+            config.output.on_collision,
+        )
+        .is_ok()
+}
+
+/// Returns true if an already-AV1 file encoded by this daemon should be
+/// encoded again because its recorded settings fingerprint no longer matches
+/// the current settings.
+///
+/// Requires `gates.reencode_on_settings_change` and an `.av1settings`
+/// sidecar next to the file.
+fn settings_outdated(config: &Config, path: &Path, reason: &SkipReason) -> bool {
+    tracks_settings(config, path, reason)
+        && read_settings_sidecar(path)
+            .is_some_and(|settings| settings.is_outdated(&SvtOverrides::from_config(&config.av1an)))
+}
+
+/// Returns true if an already-AV1 file is left unmarked so a later settings
+/// change can still get it encoded again.
+fn tracks_settings(config: &Config, path: &Path, reason: &SkipReason) -> bool {
+    config.gates.reencode_on_settings_change
+        && reason.code == SkipCode::AlreadyAv1
+        && settings_sidecar_path(path).exists()
+}
+
+/// Returns true if another daemon sharing the library holds a live claim
+/// on `path`.
+fn claimed_elsewhere(farm: Option<&FarmNode>, path: &Path) -> bool {
+    farm.is_some_and(|node| node.claimed_elsewhere(path, unix_now()).is_some())
+}
+
+/// Marks a file as skipped and counts the reason.
+async fn skip(ctx: &PipelineContext, path: &Path, reason: SkipReason) -> CandidateOutcome {
+    mark_skipped(path, &reason, &ctx.config);
+    record_skip(&ctx.metrics, reason.code).await;
+    CandidateOutcome::Skipped(reason)
+}
+
+/// Asks the torrent client for its torrents, if any library skips seeding
+/// files.
+///
+/// # Returns
+/// `None` when there is nothing to ask, otherwise the client's answer
+async fn fetch_torrents(config: &Config) -> Option<Result<Vec<Torrent>, TorrentError>> {
+    let torrent = &config.torrent;
+    let skips = torrent.default_action == SeedAction::Skip
+        || torrent.libraries.values().any(|action| *action == SeedAction::Skip);
+    if !skips {
+        return None;
+    }
+    let result = TorrentClient::from_config(torrent)?.torrents().await;
+    if let Err(ref e) = result {
+        log_warn!(
+            "Warning: Could not ask the torrent client what is seeding; \
+             leaving files it may be seeding for later: {}",
+            e
+        );
+    }
+    Some(result)
+}
+
+/// Returns true if `path` is left alone because a torrent may be seeding it.
+///
+/// When the client could not be asked, every file in a library set to
+/// `skip` is held back.
+fn held_for_seeding(
+    config: &Config,
+    torrents: Option<&Result<Vec<Torrent>, TorrentError>>,
+    path: &Path,
+) -> bool {
+    if config.torrent.action_for(path) != SeedAction::Skip {
+        return false;
+    }
+    match torrents {
+        Some(Ok(torrents)) => !seeding_hashes(torrents, path).is_empty(),
+        Some(Err(_)) => true,
+        None => false,
+    }
+}
+
+/// Returns true if `candidate` was modified fewer than `gates.min_age_days`
+/// before `now`.
+fn too_new(config: &Config, candidate: &ScanCandidate, now: SystemTime) -> bool {
+    if config.gates.min_age_days == 0 {
+        return false;
+    }
+    let min_age = Duration::from_secs(u64::from(config.gates.min_age_days) * 86_400);
+    // A modification time in the future counts as brand new
+    !now
+        .duration_since(candidate.modified_time)
+        .is_ok_and(|age| age >= min_age)
+}
+
+/// Returns true if `path` should not get another job.
+///
+/// In read-only mode nothing marks a file as done, so any job on record for
+/// the path counts, finished or not.
+fn has_job(config: &Config, jobs: &[ManagedJob], path: &Path) -> bool {
+    job_exists_for_path(jobs, path) || (config.read_only && jobs.iter().any(|job| job.input_path == path))
+}
+
+/// Writes the skip marker and whichever why sidecars are enabled.
+///
+/// Does nothing in read-only mode.
+fn mark_skipped(path: &Path, reason: &SkipReason, config: &Config) {
+    if config.read_only {
+        return;
+    }
+    let _ = write_skip_marker_with_code(path, reason.code);
+    let _ = write_why_sidecar(path, &reason.message, config.scan.write_why_sidecars);
+    let _ = write_why_json(path, reason, config.scan.write_why_json);
+}
+
+/// Jobs that can still be queued before the queue reaches `scan.max_queue_len`.
+async fn queue_room(ctx: &PipelineContext) -> usize {
+    match ctx.config.scan.max_queue_len {
+        0 => usize::MAX,
+        max => max.saturating_sub(ctx.metrics.read().await.queue_len),
+    }
+}
+
+/// Scan the libraries once and queue a job for every candidate that passes.
+///
+/// The scan cache is only consulted when `scan.incremental` is enabled.
+/// While the queue holds `scan.max_queue_len` jobs, candidates are neither
+/// probed nor queued; they are counted as `queue_full` and picked up by a
+/// later cycle. Updates scan progress metrics as it goes and returns the
+/// number of jobs queued.
+///
+/// # Requirements
+/// - 11.1: Recursively walk each configured library_root directory
+/// - 14.3: Load existing jobs to avoid duplicate work
+pub async fn scan_and_queue(ctx: &PipelineContext, scan_cache: &mut ScanCache) -> usize {
+    let config = &ctx.config;
+    let metrics = &ctx.metrics;
+
+    let started = Instant::now();
+    {
+        let mut m = metrics.write().await;
+        m.scan.in_progress = true;
+        m.scan.last_scan_started_unix_ms = timestamp_ms();
+    }
+
+    // Load existing jobs to avoid duplicates (Requirement 14.3)
+    let existing_jobs = load_jobs(&config.paths.job_state_dir).unwrap_or_else(|e| {
+        log_warn!("Warning: Failed to load existing jobs: {}", e);
+        Vec::new()
+    });
+
+    // Scan all library_roots (Requirement 11.1), then order the candidates
+    // so one large library cannot starve the others
+    let (candidates, files_walked) = if config.scan.incremental {
+        let (candidates, stats) = scan_libraries_incremental(
+            &config.scan.library_roots,
+            &config.scan.video_extensions,
+            scan_cache,
+            Duration::from_secs(config.scan.full_rescan_interval_secs),
+        );
+        log_info!(
+            "Incremental scan: {} directories read, {} from cache{}",
+            stats.dirs_read,
+            stats.dirs_cached,
+            if stats.full_rescan { " (full rescan)" } else { "" }
+        );
+        (candidates, stats.files_walked)
+    } else {
+        let roots = config.scan.library_roots.clone();
+        let extensions = config.scan.video_extensions.clone();
+        let threads = config.scan.walk_threads;
+        // The walk blocks on filesystem calls, so keep it off the async workers
+        tokio::task::spawn_blocking(move || scan_libraries_parallel(&roots, &extensions, threads))
+            .await
+            .unwrap_or_default()
+    };
+    log_info!(
+        "Found {} video candidates in {} library roots",
+        candidates.len(),
+        config.scan.library_roots.len()
+    );
+    {
+        let mut m = metrics.write().await;
+        m.scan.files_walked = files_walked;
+        m.scan.candidates_found = candidates.len() as u64;
+    }
+
+    if config.scan.detect_duplicates {
+        report_duplicates(config, &candidates).await;
+    }
+
+    let mut candidates = order_candidates(candidates, config.scan.order);
+    let mut batches = None;
+    if config.scan.batch_seasons {
+        candidates = group_by_season(candidates);
+        batches = Some(SeasonBatches::from_jobs(&existing_jobs));
+    }
+
+    let mut probe_cache = ProbeCache::load(&config.paths.job_state_dir).unwrap_or_else(|e| {
+        log_warn!("Warning: Failed to load probe cache: {}", e);
+        ProbeCache::new()
+    });
+    let seen: HashSet<PathBuf> = candidates.iter().map(|c| c.path.clone()).collect();
+    // Asked once per cycle rather than once per file
+    let torrents = fetch_torrents(config).await;
+    let now = SystemTime::now();
+    let farm = FarmNode::from_config(&config.farm);
+
+    let mut jobs_queued = 0;
+    let mut terminal_skips = 0;
+    let mut skips: BTreeMap<String, u64> = BTreeMap::new();
+    let mut count = |outcome: CandidateOutcome| match outcome {
+        CandidateOutcome::Queued { .. } => jobs_queued += 1,
+        outcome => {
+            if matches!(outcome, CandidateOutcome::Skipped(_)) {
+                terminal_skips += 1;
+            }
+            *skips.entry(outcome.label().to_string()).or_insert(0) += 1;
+        }
+    };
+
+    // Stability waits and probes run up to `probe_concurrency` at a time;
+    // results are finished in candidate order so queue order is unchanged
+    let limit = config.scan.probe_concurrency.max(1);
+    let mut in_flight: VecDeque<(ScanCandidate, JoinHandle<Inspection>)> = VecDeque::new();
+    let mut candidates = candidates.into_iter();
+
+    loop {
+        // Once the queue is full, no new candidates are probed; whatever is
+        // left waits for a later cycle
+        let room = queue_room(ctx).await;
+        while in_flight.len() < limit && in_flight.len() < room {
+            let Some(candidate) = candidates.next() else {
+                break;
+            };
+            // Skip if job already exists for this path (Requirement 14.3)
+            if has_job(config, &existing_jobs, &candidate.path) {
+                count(CandidateOutcome::ExistingJob);
+                continue;
+            }
+            if too_new(config, &candidate, now) {
+                count(CandidateOutcome::TooNew);
+                continue;
+            }
+            if claimed_elsewhere(farm.as_ref(), &candidate.path) {
+                count(CandidateOutcome::ClaimedElsewhere);
+                continue;
+            }
+            if held_for_seeding(config, torrents.as_ref(), &candidate.path) {
+                count(CandidateOutcome::Seeding);
+                continue;
+            }
+            let cached = probe_cache.get(&candidate).cloned();
+            let wait_secs = config.scan.stability_wait_secs;
+            let task_candidate = candidate.clone();
+            let handle = tokio::spawn(async move {
+                inspect_candidate(&task_candidate, wait_secs, cached).await
+            });
+            in_flight.push_back((candidate, handle));
+        }
+
+        let Some((candidate, handle)) = in_flight.pop_front() else {
+            break;
+        };
+        let outcome = match handle.await {
+            Ok(Inspection::Settled(outcome)) => outcome,
+            Ok(Inspection::Probed {
+                result,
+                probe_times,
+            }) => {
+                if let (Ok(probe), Some(_)) = (&result, probe_times) {
+                    probe_cache.insert(&candidate, probe.clone());
+                }
+                finish_candidate(
+                    ctx,
+                    &candidate,
+                    result,
+                    probe_times,
+                    JobOverrides::default(),
+                    batches.as_mut(),
+                    false,
+                )
+                .await
+            }
+            Err(e) => CandidateOutcome::StabilityError(e.to_string()),
+        };
+        count(outcome);
+    }
+
+    let deferred = candidates
+        .filter(|candidate| !has_job(config, &existing_jobs, &candidate.path))
+        .count() as u64;
+    if deferred > 0 {
+        log_info!(
+            "Queue is full ({} pending), leaving {} candidates for a later scan",
+            metrics.read().await.queue_len,
+            deferred
+        );
+        skips.insert("queue_full".to_string(), deferred);
+    }
+
+    probe_cache.retain_paths(&seen);
+    if let Err(e) = probe_cache.save(&config.paths.job_state_dir) {
+        log_warn!("Warning: Failed to save probe cache: {}", e);
+    }
+
+    {
+        let mut m = metrics.write().await;
+        m.scan.in_progress = false;
+        m.scan.last_scan_finished_unix_ms = timestamp_ms();
+        m.scan.last_scan_duration_ms = started.elapsed().as_millis() as u64;
+        m.scan.jobs_queued = jobs_queued as u64;
+        m.scan.skips_by_reason = skips;
+    }
+
+    // Coverage reads markers written during this cycle, so it runs last
+    let savings_ratio =
+        expected_savings_ratio(&metrics.read().await.jobs, config.gates.max_size_ratio);
+    let coverage = measure_coverage(
+        &config.scan.library_roots,
+        &config.scan.video_extensions,
+        savings_ratio,
+    );
+    metrics.write().await.coverage = coverage;
+
+    if terminal_skips > 0 {
+        if let Err(e) = persist_skip_stats(metrics, &config.paths.skip_stats_path).await {
+            log_warn!("Warning: Failed to save skip stats: {}", e);
+        }
+    }
+
+    jobs_queued
+}
+
+/// Writes the duplicates among `candidates` to the duplicates report
+async fn report_duplicates(config: &Config, candidates: &[ScanCandidate]) {
+    let candidates = candidates.to_vec();
+    // Signing reads sampled blocks of every file sharing a size
+    let groups = tokio::task::spawn_blocking(move || find_duplicates(&candidates))
+        .await
+        .unwrap_or_default();
+    let report = DuplicateReport {
+        generated_unix_ms: timestamp_ms(),
+        groups,
+    };
+    if !report.groups.is_empty() {
+        log_info!(
+            "Found {} files with duplicate content in {} groups ({} bytes redundant)",
+            report.groups.iter().map(|group| group.paths.len()).sum::<usize>(),
+            report.groups.len(),
+            report.redundant_bytes()
+        );
+    }
+    if let Err(e) = report.save(&config.paths.duplicates_report_path) {
+        log_warn!("Warning: Failed to save duplicates report: {}", e);
+    }
+}
+
+/// What [`reset_path`] cleared for a file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ResetReport {
+    /// Whether an `.av1skip` marker was removed
+    pub marker_removed: bool,
+    /// Number of why sidecars removed
+    pub sidecars_removed: usize,
+    /// Number of terminal job records removed
+    pub jobs_reset: usize,
+}
+
+/// Clears everything that keeps a previously rejected file from being retried.
+///
+/// Removes the `.av1skip` marker and why sidecars next to `path`, and deletes
+/// terminal (succeeded, failed, or skipped) job records for it. Active jobs
+/// are left alone.
+pub fn reset_path(path: &Path, config: &Config) -> io::Result<ResetReport> {
+    Ok(ResetReport {
+        marker_removed: remove_skip_marker(path)?,
+        sidecars_removed: remove_why_sidecars(path)?,
+        jobs_reset: remove_terminal_jobs_for_path(&config.paths.job_state_dir, path)?,
+    })
+}
+
+/// Resets a file and immediately runs it back through the pipeline.
+///
+/// The file must exist and lie inside a library root; nothing is cleared
+/// otherwise.
+///
+/// With `force`, the file is encoded even if it is already AV1 or fails the
+/// size gates. An `.av1force` marker is written first so that a failed probe
+/// is retried forced, except in read-only mode.
+///
+/// # Returns
+/// What was cleared, and the outcome of re-evaluating the file
+///
+/// # Errors
+/// `InvalidInput` if the path is outside every library root, or the error
+/// from reading the file's metadata.
+pub async fn requeue_path(
+    ctx: &PipelineContext,
+    path: &Path,
+    force: bool,
+) -> io::Result<(ResetReport, CandidateOutcome)> {
+    let roots = &ctx.config.scan.library_roots;
+    if !in_library_roots(path, roots) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not inside a library root", path.display()),
+        ));
+    }
+    let candidate = candidate_for_path(path, roots)?;
+    let report = reset_path(path, &ctx.config)?;
+    // In read-only mode nothing is written next to the original; the force
+    // only applies to this evaluation
+    if force && !ctx.config.read_only {
+        write_force_marker(path)?;
+    }
+
+    let existing_jobs = load_jobs(&ctx.config.paths.job_state_dir)?;
+    let outcome =
+        process_candidate_with(ctx, &candidate, &existing_jobs, JobOverrides::default(), force)
+            .await;
+
+    if matches!(outcome, CandidateOutcome::Skipped(_)) {
+        if let Err(e) = persist_skip_stats(&ctx.metrics, &ctx.config.paths.skip_stats_path).await {
+            log_warn!("Warning: Failed to save skip stats: {}", e);
+        }
+    }
+
+    Ok((report, outcome))
+}
+
+/// Result of importing one path
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportEntry {
+    /// Path as given in the import list
+    pub path: PathBuf,
+    /// Outcome label, or `error` if the file could not be read
+    pub outcome: String,
+    /// ID of the new job when the file was queued
+    pub job_id: Option<String>,
+    /// Human-readable detail for skips and errors
+    pub message: Option<String>,
+}
+
+/// Parses a newline-delimited path list.
+///
+/// Surrounding whitespace is trimmed, blank lines and `#` comments are
+/// ignored, and repeated paths are kept only once.
+pub fn parse_path_list(text: &str) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+/// Checks that a path named for import is a video file inside a library root.
+///
+/// Imports come from the API as well as the command line, so nothing is
+/// evaluated, marked, or replaced outside the libraries the daemon manages.
+///
+/// # Errors
+/// `InvalidInput` naming what is wrong with the path
+pub fn check_import_path(path: &Path, config: &Config) -> io::Result<()> {
+    if !in_library_roots(path, &config.scan.library_roots) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not inside a library root", path.display()),
+        ));
+    }
+    if !has_video_extension(path, &config.scan.video_extensions) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} does not have a video file extension", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Runs an explicit list of files through the pipeline without scanning.
+///
+/// Paths outside the library roots or without a video extension are
+/// reported as errors and left alone. Each other file still goes through
+/// stability, probe, gates, and classification. Files with an `.av1skip` marker are imported anyway;
+/// listing a file is taken as asking for it to be considered. Every job
+/// queued carries `overrides`.
+///
+/// # Returns
+/// One entry per path, in input order
+pub async fn import_paths(
+    ctx: &PipelineContext,
+    paths: &[PathBuf],
+    overrides: JobOverrides,
+) -> Vec<ImportEntry> {
+    let existing_jobs = load_jobs(&ctx.config.paths.job_state_dir).unwrap_or_else(|e| {
+        log_warn!("Warning: Failed to load existing jobs: {}", e);
+        Vec::new()
+    });
+
+    let mut entries = Vec::with_capacity(paths.len());
+    let mut any_skipped = false;
+
+    for path in paths {
+        let candidate = check_import_path(path, &ctx.config)
+            .and_then(|()| candidate_for_path(path, &ctx.config.scan.library_roots));
+        let entry = match candidate {
+            Ok(candidate) => {
+                let outcome =
+                    process_candidate_with(ctx, &candidate, &existing_jobs, overrides, false).await;
+                any_skipped |= matches!(outcome, CandidateOutcome::Skipped(_));
+                let (outcome, job_id, message) = outcome.into_parts();
+                ImportEntry {
+                    path: path.clone(),
+                    outcome,
+                    job_id,
+                    message,
+                }
+            }
+            Err(e) => ImportEntry {
+                path: path.clone(),
+                outcome: "error".to_string(),
+                job_id: None,
+                message: Some(e.to_string()),
+            },
+        };
+        entries.push(entry);
+    }
+
+    if any_skipped {
+        if let Err(e) = persist_skip_stats(&ctx.metrics, &ctx.config.paths.skip_stats_path).await {
+            log_warn!("Warning: Failed to save skip stats: {}", e);
+        }
+    }
+
+    entries
+}
+
+/// Get current timestamp in milliseconds
+fn timestamp_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::SourceType;
+    use crate::config::TorrentClientKind;
+    use crate::encode::SvtParams;
+    use crate::encode_settings::{write_settings_sidecar, EncodeSettings};
+    use crate::gates::{FormatInfo, ProbeResult};
+    use crate::jobs::JobStatus;
+    use crate::journal::{append_entry, journal_path, JournalEntry};
+    use crate::metrics::new_shared_metrics;
+    use crate::scan::{has_skip_marker, skip_marker_path};
+    use crate::skip_marker::{why_json_path, why_sidecar_path, write_skip_marker};
+    use std::fs::{self, File};
+    use tempfile::TempDir;
+
+    fn test_config(temp: &Path) -> Config {
+        let mut config = Config::default();
+        config.paths.job_state_dir = temp.join("jobs");
+        config.paths.temp_output_dir = temp.join("out");
+        config.paths.skip_stats_path = temp.join("skip_stats.json");
+        config.scan.library_roots = vec![temp.join("media")];
+        config.scan.stability_wait_secs = 0;
+        config
+    }
+
+    fn make_job(path: &Path, config: &Config) -> ManagedJob {
+        let candidate = ScanCandidate {
+            path: path.to_path_buf(),
+            size_bytes: 10,
+            modified_time: SystemTime::now(),
+            root: config.scan.library_roots[0].clone(),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 1.0,
+                size_bytes: 10,
+            },
+        };
+        create_job(&candidate, probe, SourceType::Unknown, &config.paths.temp_output_dir)
+    }
+
+    #[test]
+    fn test_season_batches_share_the_first_episodes_settings() {
+        let temp = TempDir::new().unwrap();
+        let config = test_config(temp.path());
+        let season = temp.path().join("media/Show/Season 01");
+        let mut first = make_job(&season.join("Show.S01E01.mkv"), &config);
+        first.source_type = SourceType::Animation;
+        first.overrides.crf = Some(20);
+        let movie = make_job(&temp.path().join("media/Film (2020).mkv"), &config);
+        let mut batches = SeasonBatches::from_jobs(&[first.clone(), movie]);
+        let key = first.batch_key.clone().unwrap();
+
+        // A later episode takes the season's class and CRF, keeping its own preset
+        let own = JobOverrides {
+            preset: Some(6),
+            ..JobOverrides::default()
+        };
+        let (source_type, overrides) = batches.settings_for(&key, SourceType::WebLike, own);
+        assert_eq!(source_type, SourceType::Animation);
+        assert_eq!(overrides.crf, Some(20));
+        assert_eq!(overrides.preset, Some(6));
+
+        // The first episode of a new season leads it
+        let (source_type, overrides) =
+            batches.settings_for("show/s02", SourceType::DiscLike, JobOverrides::default());
+        assert_eq!(source_type, SourceType::DiscLike);
+        assert!(overrides.is_empty());
+        let (source_type, _) =
+            batches.settings_for("show/s02", SourceType::WebLike, JobOverrides::default());
+        assert_eq!(source_type, SourceType::DiscLike);
+    }
+
+    #[test]
+    fn test_reset_path_clears_markers_and_terminal_jobs() {
+        let temp = TempDir::new().unwrap();
+        let config = test_config(temp.path());
+        let media = temp.path().join("media");
+        fs::create_dir_all(&media).unwrap();
+        let video = media.join("film.mkv");
+        File::create(&video).unwrap();
+
+        write_skip_marker(&video).unwrap();
+        write_why_sidecar(&video, "already AV1", true).unwrap();
+        write_why_json(&video, &SkipReason::new(SkipCode::AlreadyAv1, "already AV1"), true)
+            .unwrap();
+
+        let mut failed = make_job(&video, &config);
+        failed.set_status(JobStatus::Failed);
+        save_job(&failed, &config.paths.job_state_dir).unwrap();
+        let entry = JournalEntry::now(failed.stage, failed.status, None);
+        append_entry(&config.paths.job_state_dir, &failed.id, &entry).unwrap();
+        let other = make_job(&media.join("other.mkv"), &config);
+        save_job(&other, &config.paths.job_state_dir).unwrap();
+
+        let report = reset_path(&video, &config).unwrap();
+        assert_eq!(
+            report,
+            ResetReport {
+                marker_removed: true,
+                sidecars_removed: 2,
+                jobs_reset: 1,
+            }
+        );
+        assert!(!skip_marker_path(&video).exists());
+        assert!(!why_sidecar_path(&video).exists());
+        assert!(!why_json_path(&video).exists());
+        assert!(!journal_path(&config.paths.job_state_dir, &failed.id).exists());
+
+        let remaining = load_jobs(&config.paths.job_state_dir).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, other.id);
+
+        // Nothing left to clear the second time round
+        assert_eq!(reset_path(&video, &config).unwrap(), ResetReport::default());
+    }
+
+    #[tokio::test]
+    async fn test_process_candidate_existing_job() {
+        let temp = TempDir::new().unwrap();
+        let config = test_config(temp.path());
+        let video = temp.path().join("media/film.mkv");
+        let existing = vec![make_job(&video, &config)];
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+        let candidate = ScanCandidate {
+            path: video,
+            size_bytes: 10,
+            modified_time: SystemTime::now(),
+            root: temp.path().join("media"),
+        };
+
+        let outcome = process_candidate(&ctx, &candidate, &existing).await;
+        assert_eq!(outcome, CandidateOutcome::ExistingJob);
+        assert_eq!(outcome.label(), "existing_job");
+    }
+
+    #[tokio::test]
+    async fn test_requeue_unprobeable_file_is_skipped_again() {
+        let temp = TempDir::new().unwrap();
+        let config = test_config(temp.path());
+        let media = temp.path().join("media");
+        fs::create_dir_all(&media).unwrap();
+        let video = media.join("garbage.mkv");
+        fs::write(&video, b"not a video").unwrap();
+        write_skip_marker(&video).unwrap();
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+
+        let (report, outcome) = requeue_path(&ctx, &video, false).await.unwrap();
+        assert!(report.marker_removed);
+        // Either ffprobe is missing or it rejects the file; both are probe failures
+        match outcome {
+            CandidateOutcome::Skipped(reason) => assert_eq!(reason.code, SkipCode::ProbeFailed),
+            other => panic!("expected probe failure, got {:?}", other),
+        }
+        assert!(has_skip_marker(&video));
+        assert_eq!(ctx.metrics.read().await.skip_totals.get("probe_failed"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_requeue_outside_library_roots_clears_nothing() {
+        let temp = TempDir::new().unwrap();
+        let config = test_config(temp.path());
+        let elsewhere = temp.path().join("elsewhere");
+        fs::create_dir_all(&elsewhere).unwrap();
+        let video = elsewhere.join("film.mkv");
+        File::create(&video).unwrap();
+        write_skip_marker(&video).unwrap();
+        write_why_sidecar(&video, "already AV1", true).unwrap();
+        fs::create_dir_all(temp.path().join("media")).unwrap();
+        let missing = temp.path().join("media/missing.mkv");
+        write_skip_marker(&missing).unwrap();
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+
+        let err = requeue_path(&ctx, &video, false).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(has_skip_marker(&video));
+        assert!(why_sidecar_path(&video).exists());
+
+        // Climbing out of a root with `..` is outside it too
+        let climbing = temp.path().join("media/../elsewhere/film.mkv");
+        let err = requeue_path(&ctx, &climbing, false).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(has_skip_marker(&video));
+
+        // A missing file inside a root keeps its marker as well
+        let err = requeue_path(&ctx, &missing, false).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(skip_marker_path(&missing).exists());
+    }
+
+    #[test]
+    fn test_too_new_uses_min_age_days() {
+        let temp = TempDir::new().unwrap();
+        let mut config = test_config(temp.path());
+        let now = SystemTime::now();
+        let day = Duration::from_secs(86_400);
+        let candidate = |age: Duration| ScanCandidate {
+            path: temp.path().join("media/film.mkv"),
+            size_bytes: 10,
+            modified_time: now - age,
+            root: temp.path().join("media"),
+        };
+
+        assert!(!too_new(&config, &candidate(Duration::ZERO), now));
+        config.gates.min_age_days = 7;
+        assert!(too_new(&config, &candidate(day * 6), now));
+        assert!(!too_new(&config, &candidate(day * 7), now));
+        let future = ScanCandidate {
+            modified_time: now + day,
+            ..candidate(Duration::ZERO)
+        };
+        assert!(too_new(&config, &future, now));
+    }
+
+    #[tokio::test]
+    async fn test_forced_requeue_still_needs_a_probe() {
+        let temp = TempDir::new().unwrap();
+        let config = test_config(temp.path());
+        let media = temp.path().join("media");
+        fs::create_dir_all(&media).unwrap();
+        let video = media.join("garbage.mkv");
+        fs::write(&video, b"not a video").unwrap();
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+
+        let (_, outcome) = requeue_path(&ctx, &video, true).await.unwrap();
+        match outcome {
+            CandidateOutcome::Skipped(reason) => assert_eq!(reason.code, SkipCode::ProbeFailed),
+            other => panic!("expected probe failure, got {:?}", other),
+        }
+        // No job was queued, so the marker is left for the next attempt
+        assert!(has_force_marker(&video));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_forced_requeue_writes_no_marker() {
+        let temp = TempDir::new().unwrap();
+        let mut config = test_config(temp.path());
+        config.read_only = true;
+        let media = temp.path().join("media");
+        fs::create_dir_all(&media).unwrap();
+        let video = media.join("garbage.mkv");
+        fs::write(&video, b"not a video").unwrap();
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+
+        let (_, outcome) = requeue_path(&ctx, &video, true).await.unwrap();
+        assert!(matches!(outcome, CandidateOutcome::Skipped(_)));
+        assert!(!has_force_marker(&video));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_leaves_library_untouched() {
+        let temp = TempDir::new().unwrap();
+        let mut config = test_config(temp.path());
+        config.read_only = true;
+        config.scan.write_why_json = true;
+        let media = temp.path().join("media");
+        fs::create_dir_all(&media).unwrap();
+        let video = media.join("garbage.mkv");
+        fs::write(&video, b"not a video").unwrap();
+
+        let mut finished = make_job(&media.join("done.mkv"), &config);
+        finished.set_status(JobStatus::Success);
+        let existing = vec![finished];
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+        let candidate = |path: PathBuf| ScanCandidate {
+            path,
+            size_bytes: 11,
+            modified_time: SystemTime::now(),
+            root: media.clone(),
+        };
+
+        let outcome = process_candidate(&ctx, &candidate(video.clone()), &existing).await;
+        assert!(matches!(outcome, CandidateOutcome::Skipped(_)));
+        assert!(!has_skip_marker(&video));
+        assert!(!why_sidecar_path(&video).exists());
+        assert!(!why_json_path(&video).exists());
+
+        // Without markers, finished jobs are what keep files from coming back
+        let outcome = process_candidate(&ctx, &candidate(media.join("done.mkv")), &existing).await;
+        assert_eq!(outcome, CandidateOutcome::ExistingJob);
+    }
+
+    #[tokio::test]
+    async fn test_scan_stops_probing_while_queue_is_full() {
+        let temp = TempDir::new().unwrap();
+        let mut config = test_config(temp.path());
+        config.scan.max_queue_len = 2;
+        let media = temp.path().join("media");
+        fs::create_dir_all(&media).unwrap();
+        let video = media.join("garbage.mkv");
+        fs::write(&video, b"not a video").unwrap();
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+        ctx.metrics.write().await.queue_len = 2;
+
+        let queued = scan_and_queue(&ctx, &mut ScanCache::new()).await;
+        assert_eq!(queued, 0);
+        // Not probed, so not marked as unprobeable either
+        assert!(!has_skip_marker(&video));
+        assert_eq!(ctx.metrics.read().await.scan.skips_by_reason.get("queue_full"), Some(&1));
+
+        // Once the queue drains the file is probed as usual
+        ctx.metrics.write().await.queue_len = 1;
+        scan_and_queue(&ctx, &mut ScanCache::new()).await;
+        assert!(has_skip_marker(&video));
+        assert_eq!(ctx.metrics.read().await.scan.skips_by_reason.get("queue_full"), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hardlinked_file_is_skipped_when_configured() {
+        let temp = TempDir::new().unwrap();
+        let mut config = test_config(temp.path());
+        config.gates.hardlinks = HardlinkPolicy::Skip;
+        let media = temp.path().join("media");
+        fs::create_dir_all(&media).unwrap();
+        let video = media.join("film.mkv");
+        fs::write(&video, b"not a video").unwrap();
+        fs::hard_link(&video, temp.path().join("seed.mkv")).unwrap();
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+        let candidate = candidate_for_path(&video, &[media]).unwrap();
+
+        match process_candidate(&ctx, &candidate, &[]).await {
+            CandidateOutcome::Skipped(reason) => {
+                assert_eq!(reason.code, SkipCode::Hardlinked);
+                assert_eq!(reason.thresholds.get("hard_links"), Some(&2.0));
+            }
+            other => panic!("expected hardlink skip, got {:?}", other),
+        }
+        assert!(has_skip_marker(&video));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_torrent_client_holds_back_skip_libraries() {
+        let temp = TempDir::new().unwrap();
+        let mut config = test_config(temp.path());
+        config.torrent.client = TorrentClientKind::Qbittorrent;
+        config.torrent.url = Some("http://127.0.0.1:9".to_string());
+        let media = temp.path().join("media");
+        fs::create_dir_all(&media).unwrap();
+        let video = media.join("film.mkv");
+        fs::write(&video, b"not a video").unwrap();
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let mut ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+        let candidate = candidate_for_path(&video, std::slice::from_ref(&media)).unwrap();
+
+        let outcome = process_candidate(&ctx, &candidate, &[]).await;
+        assert!(matches!(outcome, CandidateOutcome::Seeding));
+        assert!(!has_skip_marker(&video));
+
+        // Libraries that do not skip seeding files never ask the client
+        ctx.config.torrent.libraries.insert(media.clone(), SeedAction::Pause);
+        let outcome = process_candidate(&ctx, &candidate, &[]).await;
+        assert!(!matches!(outcome, CandidateOutcome::Seeding));
+    }
+
+    #[test]
+    fn test_parse_path_list() {
+        let text = "/media/a.mkv\n\n  /media/b.mkv  \n# exported 2024-01-01\n/media/a.mkv\n";
+        assert_eq!(
+            parse_path_list(text),
+            vec![PathBuf::from("/media/a.mkv"), PathBuf::from("/media/b.mkv")]
+        );
+        assert!(parse_path_list("").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_paths_reports_each_path() {
+        let temp = TempDir::new().unwrap();
+        let config = test_config(temp.path());
+        let media = temp.path().join("media");
+        fs::create_dir_all(&media).unwrap();
+        let video = media.join("film.mkv");
+        File::create(&video).unwrap();
+        let existing = make_job(&video, &config);
+        save_job(&existing, &config.paths.job_state_dir).unwrap();
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+
+        let missing = media.join("missing.mkv");
+        let entries =
+            import_paths(&ctx, &[video.clone(), missing.clone()], JobOverrides::default()).await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, video);
+        assert_eq!(entries[0].outcome, "existing_job");
+        assert_eq!(entries[1].path, missing);
+        assert_eq!(entries[1].outcome, "error");
+        assert!(entries[1].message.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_import_paths_refuses_files_outside_libraries() {
+        let temp = TempDir::new().unwrap();
+        let config = test_config(temp.path());
+        fs::create_dir_all(temp.path().join("media")).unwrap();
+        let outside = temp.path().join("film.mkv");
+        File::create(&outside).unwrap();
+        let not_video = temp.path().join("media").join("notes.txt");
+        File::create(&not_video).unwrap();
+
+        let (job_tx, _job_rx) = mpsc::unbounded_channel();
+        let ctx = PipelineContext {
+            config,
+            metrics: new_shared_metrics(),
+            job_tx,
+        };
+
+        let paths = [outside.clone(), not_video.clone()];
+        let entries = import_paths(&ctx, &paths, JobOverrides::default()).await;
+        assert!(entries.iter().all(|entry| entry.outcome == "error"));
+        assert!(!has_skip_marker(&outside));
+        assert!(!has_skip_marker(&not_video));
+    }
+
+    #[test]
+    fn test_should_remux_only_legacy_av1_containers() {
+        let temp = TempDir::new().unwrap();
+        let mut config = test_config(temp.path());
+        let av1 = SkipReason::new(SkipCode::AlreadyAv1, "already AV1");
+        let mp4 = temp.path().join("clip.mp4");
+
+        assert!(!should_remux(&config, &mp4, &av1), "disabled by default");
+
+        config.gates.remux_av1 = true;
+        assert!(should_remux(&config, &mp4, &av1));
+        assert!(!should_remux(&config, &temp.path().join("clip.mkv"), &av1));
+        let too_small = SkipReason::new(SkipCode::BelowMinSize, "below minimum size");
+        assert!(!should_remux(&config, &mp4, &too_small));
+
+        // An existing sibling MKV would be overwritten by the remux
+        File::create(temp.path().join("clip.mkv")).unwrap();
+        assert!(!should_remux(&config, &mp4, &av1));
+    }
+
+    #[test]
+    fn test_settings_outdated_needs_policy_and_changed_settings() {
+        let temp = TempDir::new().unwrap();
+        let mut config = test_config(temp.path());
+        let av1 = SkipReason::new(SkipCode::AlreadyAv1, "already AV1");
+        let ours = temp.path().join("ours.mkv");
+        let settings = EncodeSettings::new(&SvtParams::film(), "yuv420p10le", SourceType::DiscLike, false);
+        write_settings_sidecar(&ours, &settings).unwrap();
+        let foreign = temp.path().join("foreign.mkv");
+
+        config.av1an.film_grain = Some(8);
+        assert!(!settings_outdated(&config, &ours, &av1), "disabled by default");
+
+        config.gates.reencode_on_settings_change = true;
+        assert!(settings_outdated(&config, &ours, &av1));
+        assert!(!settings_outdated(&config, &foreign, &av1));
+        assert!(!tracks_settings(&config, &foreign, &av1));
+        let too_small = SkipReason::new(SkipCode::BelowMinSize, "below minimum size");
+        assert!(!settings_outdated(&config, &ours, &too_small));
+
+        // Unchanged settings are skipped, but without a marker
+        config.av1an.film_grain = None;
+        assert!(!settings_outdated(&config, &ours, &av1));
+        assert!(tracks_settings(&config, &ours, &av1));
+    }
+}
@@ -0,0 +1,189 @@
+//! Pixel format handling for encodes.
+//!
+//! ffprobe reports each video stream's pixel format as a name such as
+//! `yuv422p10le` or `nv12`. From it the source's chroma subsampling and bit
+//! depth are read, which decide both whether the file is encoded at all and
+//! the `--pix-format` Av1an is given.
+//!
+//! SVT-AV1 only encodes 4:2:0 at 8 or 10 bits, so the output keeps the
+//! source's bit depth where the encoder allows it (with `keep_8bit`) and is
+//! 4:2:0 otherwise; 4:2:2 and 4:4:4 sources can be skipped instead of
+//! converted. Sources that are not YUV at all, such as RGB or palette
+//! formats, are skipped by the `skip_unsupported` gate. Streams without a
+//! reported pixel format are encoded with the defaults.
+
+use crate::config::{ChromaAction, PixelFormatConfig};
+use crate::skip_marker::{SkipCode, SkipReason};
+
+/// Pixel format encodes use unless the source asks for another.
+pub const DEFAULT_PIX_FORMAT: &str = "yuv420p10le";
+
+/// Pixel format of 8-bit encodes with `keep_8bit`.
+const PIX_FORMAT_8BIT: &str = "yuv420p";
+
+/// Chroma subsampling of a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chroma {
+    /// Monochrome; encoded as 4:2:0 without loss
+    Gray,
+    Yuv420,
+    Yuv422,
+    Yuv444,
+}
+
+/// Chroma subsampling and bit depth read from a pixel format name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelLayout {
+    pub chroma: Chroma,
+    pub bit_depth: u8,
+}
+
+impl PixelLayout {
+    /// Reads `pix_fmt` as ffmpeg names it.
+    ///
+    /// # Returns
+    /// `None` for formats that are not planar or semi-planar YUV or gray,
+    /// and for YUV layouts other than 4:2:0, 4:2:2 and 4:4:4
+    pub fn parse(pix_fmt: &str) -> Option<Self> {
+        let name = pix_fmt.trim().to_lowercase();
+        let name = name
+            .strip_suffix("le")
+            .or_else(|| name.strip_suffix("be"))
+            .unwrap_or(&name);
+
+        // Semi-planar formats name their layout and depth directly
+        let semi_planar = match name {
+            "nv12" | "nv21" => Some((Chroma::Yuv420, 8)),
+            "nv16" => Some((Chroma::Yuv422, 8)),
+            "nv24" | "nv42" => Some((Chroma::Yuv444, 8)),
+            "p010" => Some((Chroma::Yuv420, 10)),
+            "p012" => Some((Chroma::Yuv420, 12)),
+            "p016" => Some((Chroma::Yuv420, 16)),
+            "p210" => Some((Chroma::Yuv422, 10)),
+            "p216" => Some((Chroma::Yuv422, 16)),
+            "p410" => Some((Chroma::Yuv444, 10)),
+            "p416" => Some((Chroma::Yuv444, 16)),
+            _ => None,
+        };
+        if let Some((chroma, bit_depth)) = semi_planar {
+            return Some(Self { chroma, bit_depth });
+        }
+
+        if let Some(depth) = name.strip_prefix("gray") {
+            return Some(Self {
+                chroma: Chroma::Gray,
+                bit_depth: parse_depth(depth)?,
+            });
+        }
+
+        // yuv420p10, yuvj422p, yuva444p12, ...
+        let rest = name
+            .strip_prefix("yuvj")
+            .or_else(|| name.strip_prefix("yuva"))
+            .or_else(|| name.strip_prefix("yuv"))?;
+        let (layout, depth) = rest.split_once('p')?;
+        let chroma = match layout {
+            "420" => Chroma::Yuv420,
+            "422" => Chroma::Yuv422,
+            "444" => Chroma::Yuv444,
+            _ => return None,
+        };
+        Some(Self {
+            chroma,
+            bit_depth: parse_depth(depth)?,
+        })
+    }
+}
+
+/// Bit depth from the digits after a format's layout; none means 8 bits.
+fn parse_depth(digits: &str) -> Option<u8> {
+    match digits {
+        "" => Some(8),
+        digits => digits.parse().ok(),
+    }
+}
+
+/// Skip reason if the source's pixel format is not encoded under `config`.
+pub fn pixel_format_skip_reason(pix_fmt: Option<&str>, config: &PixelFormatConfig) -> Option<SkipReason> {
+    let pix_fmt = pix_fmt?;
+    let skip = |message: String| {
+        Some(SkipReason::new(SkipCode::UnsupportedPixelFormat, message))
+    };
+    match PixelLayout::parse(pix_fmt) {
+        None if config.skip_unsupported => skip(format!("unsupported pixel format {}", pix_fmt)),
+        Some(PixelLayout { chroma: Chroma::Yuv422, .. }) if config.chroma_422 == ChromaAction::Skip => {
+            skip(format!("4:2:2 source ({}) kept as it is", pix_fmt))
+        }
+        Some(PixelLayout { chroma: Chroma::Yuv444, .. }) if config.chroma_444 == ChromaAction::Skip => {
+            skip(format!("4:4:4 source ({}) kept as it is", pix_fmt))
+        }
+        _ => None,
+    }
+}
+
+/// Pixel format to encode a source with `pix_fmt` as.
+pub fn output_pix_format(pix_fmt: Option<&str>, config: &PixelFormatConfig) -> &'static str {
+    match pix_fmt.and_then(PixelLayout::parse) {
+        Some(layout) if config.keep_8bit && layout.bit_depth <= 8 => PIX_FORMAT_8BIT,
+        _ => DEFAULT_PIX_FORMAT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(chroma: Chroma, bit_depth: u8) -> Option<PixelLayout> {
+        Some(PixelLayout { chroma, bit_depth })
+    }
+
+    #[test]
+    fn test_parse_pixel_layouts() {
+        assert_eq!(PixelLayout::parse("yuv420p"), layout(Chroma::Yuv420, 8));
+        assert_eq!(PixelLayout::parse("yuv420p10le"), layout(Chroma::Yuv420, 10));
+        assert_eq!(PixelLayout::parse("yuvj422p"), layout(Chroma::Yuv422, 8));
+        assert_eq!(PixelLayout::parse("yuv444p12be"), layout(Chroma::Yuv444, 12));
+        assert_eq!(PixelLayout::parse("yuva420p"), layout(Chroma::Yuv420, 8));
+        assert_eq!(PixelLayout::parse("p010le"), layout(Chroma::Yuv420, 10));
+        assert_eq!(PixelLayout::parse("nv12"), layout(Chroma::Yuv420, 8));
+        assert_eq!(PixelLayout::parse("gray10le"), layout(Chroma::Gray, 10));
+        assert_eq!(PixelLayout::parse("gray"), layout(Chroma::Gray, 8));
+
+        for unsupported in ["rgb24", "bgr0", "gbrp10le", "pal8", "bayer_rggb8", "yuv411p", "yuv440p"] {
+            assert_eq!(PixelLayout::parse(unsupported), None, "{}", unsupported);
+        }
+    }
+
+    #[test]
+    fn test_pixel_format_gate() {
+        let mut config = PixelFormatConfig::default();
+        assert!(pixel_format_skip_reason(Some("yuv422p10le"), &config).is_none());
+        assert!(pixel_format_skip_reason(None, &config).is_none());
+
+        let reason = pixel_format_skip_reason(Some("rgb24"), &config).unwrap();
+        assert_eq!(reason.code, SkipCode::UnsupportedPixelFormat);
+        assert_eq!(reason.message, "unsupported pixel format rgb24");
+
+        config.chroma_422 = ChromaAction::Skip;
+        let reason = pixel_format_skip_reason(Some("yuv422p10le"), &config).unwrap();
+        assert_eq!(reason.message, "4:2:2 source (yuv422p10le) kept as it is");
+        assert!(pixel_format_skip_reason(Some("yuv444p"), &config).is_none());
+
+        config.skip_unsupported = false;
+        assert!(pixel_format_skip_reason(Some("rgb24"), &config).is_none());
+    }
+
+    #[test]
+    fn test_output_pix_format() {
+        let mut config = PixelFormatConfig::default();
+        assert_eq!(output_pix_format(Some("yuv420p"), &config), "yuv420p10le");
+        assert_eq!(output_pix_format(None, &config), "yuv420p10le");
+
+        config.keep_8bit = true;
+        assert_eq!(output_pix_format(Some("yuv420p"), &config), "yuv420p");
+        assert_eq!(output_pix_format(Some("yuvj422p"), &config), "yuv420p");
+        assert_eq!(output_pix_format(Some("yuv420p10le"), &config), "yuv420p10le");
+        assert_eq!(output_pix_format(Some("yuv420p12le"), &config), "yuv420p10le");
+        assert_eq!(output_pix_format(None, &config), "yuv420p10le");
+    }
+}
@@ -0,0 +1,336 @@
+//! Polite mode: yielding the host to interactive use.
+//!
+//! Every `polite.poll_secs` the daemon checks for signs that someone is
+//! using the machine: Plex transcoding a stream, or processes other than
+//! the daemon and its encoders keeping the CPU busy. While either holds,
+//! running encodes are suspended and no new job is dispatched. A
+//! [`PoliteGovernor`] adds hysteresis: encodes resume only once Plex is idle
+//! and other CPU use is at or below `resume_cpu_percent` for a full
+//! `resume_after_secs`, so a viewer pausing for a moment does not flap the
+//! encodes between states.
+
+use std::time::{Duration, Instant};
+
+use reqwest::Client;
+use serde::Deserialize;
+use sysinfo::{ProcessesToUpdate, System};
+
+use crate::config::PoliteConfig;
+use crate::encode::{can_suspend_groups, resume_all_groups, suspend_all_groups, SuspendReason};
+use crate::metrics::SharedMetrics;
+
+/// How long to wait for Plex before giving up on a check.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Parent links followed before giving up, in case of a loop.
+const MAX_TREE_DEPTH: usize = 64;
+
+/// What one check saw; `None` for a signal that is not watched or could not
+/// be read, which counts as neither busy nor quiet
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageSignals {
+    /// Active Plex transcodes
+    pub plex_transcodes: Option<u32>,
+    /// CPU percent used by processes other than the daemon and its encoders
+    pub other_cpu_percent: Option<f32>,
+}
+
+/// Hysteresis state machine deciding when encodes yield.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoliteGovernor {
+    suspend_cpu_percent: Option<f32>,
+    resume_cpu_percent: f32,
+    resume_after: Duration,
+    yielding: bool,
+    quiet_since: Option<Instant>,
+}
+
+impl PoliteGovernor {
+    pub fn from_config(config: &PoliteConfig) -> Self {
+        Self {
+            suspend_cpu_percent: config.suspend_cpu_percent,
+            resume_cpu_percent: config.resume_cpu_percent,
+            resume_after: Duration::from_secs(config.resume_after_secs),
+            yielding: false,
+            quiet_since: None,
+        }
+    }
+
+    /// Feeds the signals of a check made at `now` and returns whether
+    /// encodes should now yield.
+    pub fn update(&mut self, signals: &UsageSignals, now: Instant) -> bool {
+        let transcoding = signals.plex_transcodes.is_some_and(|n| n > 0);
+        let cpu = self.suspend_cpu_percent.zip(signals.other_cpu_percent);
+        let cpu_busy = cpu.is_some_and(|(limit, used)| used >= limit);
+        let cpu_quiet = cpu.is_none_or(|(_, used)| used <= self.resume_cpu_percent);
+
+        if transcoding || cpu_busy {
+            self.yielding = true;
+            self.quiet_since = None;
+        } else if self.yielding && cpu_quiet {
+            let since = *self.quiet_since.get_or_insert(now);
+            if now.duration_since(since) >= self.resume_after {
+                self.yielding = false;
+                self.quiet_since = None;
+            }
+        } else {
+            // Between the thresholds: not busy enough to yield, not quiet
+            // enough to count towards resuming
+            self.quiet_since = None;
+        }
+        self.yielding
+    }
+
+    pub fn is_yielding(&self) -> bool {
+        self.yielding
+    }
+}
+
+/// Response of Plex's `/transcode/sessions`.
+#[derive(Debug, Deserialize)]
+struct TranscodeSessions {
+    #[serde(rename = "MediaContainer")]
+    container: MediaContainer,
+}
+
+#[derive(Debug, Deserialize)]
+struct MediaContainer {
+    #[serde(default)]
+    size: u32,
+}
+
+/// Number of active transcodes in a `/transcode/sessions` response.
+pub fn parse_transcode_count(body: &str) -> Option<u32> {
+    serde_json::from_str::<TranscodeSessions>(body)
+        .ok()
+        .map(|sessions| sessions.container.size)
+}
+
+/// CPU usage of `root` and every process descending from it, summed from
+/// `(pid, parent, cpu)` triples
+pub fn tree_cpu_usage(processes: &[(u32, Option<u32>, f32)], root: u32) -> f32 {
+    let parent_of = |pid: u32| {
+        processes
+            .iter()
+            .find(|(p, _, _)| *p == pid)
+            .and_then(|(_, parent, _)| *parent)
+    };
+    processes
+        .iter()
+        .filter(|(pid, _, _)| {
+            let mut current = Some(*pid);
+            for _ in 0..MAX_TREE_DEPTH {
+                match current {
+                    Some(p) if p == root => return true,
+                    Some(p) => current = parent_of(p),
+                    None => return false,
+                }
+            }
+            false
+        })
+        .map(|(_, _, cpu)| cpu)
+        .sum()
+}
+
+/// Reads the usage signals `config` asks for.
+struct UsageMonitor {
+    config: PoliteConfig,
+    http: Option<Client>,
+    sys: System,
+    plex_failing: bool,
+}
+
+impl UsageMonitor {
+    fn new(config: PoliteConfig) -> Self {
+        let http = config
+            .plex_url
+            .as_ref()
+            .and_then(|_| Client::builder().timeout(REQUEST_TIMEOUT).build().ok());
+        Self {
+            config,
+            http,
+            sys: System::new(),
+            plex_failing: false,
+        }
+    }
+
+    async fn sample(&mut self) -> UsageSignals {
+        UsageSignals {
+            plex_transcodes: self.plex_transcodes().await,
+            other_cpu_percent: self
+                .config
+                .suspend_cpu_percent
+                .map(|_| self.other_cpu_percent()),
+        }
+    }
+
+    /// Active Plex transcodes, or `None` if Plex is not watched or did not
+    /// answer; a failure is logged once until Plex answers again
+    async fn plex_transcodes(&mut self) -> Option<u32> {
+        let (http, url) = (self.http.as_ref()?, self.config.plex_url.as_ref()?);
+        let mut request = http
+            .get(format!("{}/transcode/sessions", url.trim_end_matches('/')))
+            .header("Accept", "application/json");
+        if let Some(token) = &self.config.plex_token {
+            request = request.header("X-Plex-Token", token);
+        }
+        let result = match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(response) => response.text().await.map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+        .and_then(|body| {
+            parse_transcode_count(&body).ok_or_else(|| "unexpected response".to_string())
+        });
+
+        match result {
+            Ok(count) => {
+                self.plex_failing = false;
+                Some(count)
+            }
+            Err(e) => {
+                if !self.plex_failing {
+                    log_warn!("Warning: Cannot read Plex transcodes from {}: {}", url, e);
+                    self.plex_failing = true;
+                }
+                None
+            }
+        }
+    }
+
+    /// CPU percent of the whole machine used outside the daemon's process
+    /// tree, which holds every encoder it started
+    fn other_cpu_percent(&mut self) -> f32 {
+        self.sys.refresh_cpu_usage();
+        self.sys.refresh_processes(ProcessesToUpdate::All);
+        let processes: Vec<(u32, Option<u32>, f32)> = self
+            .sys
+            .processes()
+            .iter()
+            .map(|(pid, process)| {
+                (pid.as_u32(), process.parent().map(|p| p.as_u32()), process.cpu_usage())
+            })
+            .collect();
+        // Process usage is per core, the global figure across all of them
+        let cores = self.sys.cpus().len().max(1) as f32;
+        let own = tree_cpu_usage(&processes, std::process::id()) / cores;
+        (self.sys.global_cpu_usage() - own).max(0.0)
+    }
+}
+
+/// Runs polite mode until the task is dropped, suspending and resuming
+/// encodes and reporting its state in `metrics`.
+pub async fn run_polite_mode(config: PoliteConfig, metrics: SharedMetrics) {
+    if !can_suspend_groups() {
+        log_warn!(
+            "Warning: Running encodes cannot be suspended on this platform; \
+             polite mode only holds back new jobs"
+        );
+    }
+    let poll = Duration::from_secs(config.poll_secs.max(1));
+    let mut governor = PoliteGovernor::from_config(&config);
+    let mut monitor = UsageMonitor::new(config);
+    metrics.write().await.polite.enabled = true;
+
+    loop {
+        let signals = monitor.sample().await;
+        let was_yielding = governor.is_yielding();
+        let yielding = governor.update(&signals, Instant::now());
+
+        let mut snapshot = metrics.write().await;
+        snapshot.polite.yielding = yielding;
+        snapshot.polite.plex_transcodes = signals.plex_transcodes;
+        snapshot.polite.other_cpu_percent = signals.other_cpu_percent;
+        if yielding && !was_yielding {
+            snapshot.polite.yield_events += 1;
+            let suspended = suspend_all_groups(SuspendReason::Polite);
+            log_info!(
+                "Host in use (Plex transcodes: {:?}, other CPU: {:?}%), suspended {} encode(s)",
+                signals.plex_transcodes,
+                signals.other_cpu_percent.map(|cpu| cpu.round()),
+                suspended
+            );
+        } else if !yielding && was_yielding {
+            let resumed = resume_all_groups(SuspendReason::Polite);
+            log_info!("Host quiet, resuming work ({} encode(s) resumed)", resumed);
+        }
+        drop(snapshot);
+
+        tokio::time::sleep(poll).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn governor() -> PoliteGovernor {
+        PoliteGovernor::from_config(&PoliteConfig {
+            enabled: true,
+            suspend_cpu_percent: Some(50.0),
+            resume_cpu_percent: 20.0,
+            resume_after_secs: 60,
+            ..Default::default()
+        })
+    }
+
+    fn cpu(percent: f32) -> UsageSignals {
+        UsageSignals {
+            plex_transcodes: Some(0),
+            other_cpu_percent: Some(percent),
+        }
+    }
+
+    #[test]
+    fn test_governor_yields_and_resumes_with_hysteresis() {
+        let mut governor = governor();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(!governor.update(&cpu(40.0), at(0)), "below the suspend threshold");
+        assert!(governor.update(&cpu(55.0), at(10)));
+        // Between the thresholds the host is not quiet yet
+        assert!(governor.update(&cpu(30.0), at(20)));
+        assert!(governor.update(&cpu(10.0), at(30)));
+        assert!(governor.update(&cpu(10.0), at(60)), "quiet for only 30s");
+        assert!(governor.update(&cpu(25.0), at(70)), "quiet period restarts");
+        assert!(governor.update(&cpu(10.0), at(80)));
+        assert!(!governor.update(&cpu(10.0), at(140)));
+    }
+
+    #[test]
+    fn test_governor_yields_to_plex_transcodes() {
+        let mut governor = governor();
+        let now = Instant::now();
+        let transcoding = UsageSignals {
+            plex_transcodes: Some(1),
+            other_cpu_percent: Some(0.0),
+        };
+        assert!(governor.update(&transcoding, now));
+
+        // Plex not answering counts as neither busy nor quiet
+        let unknown = UsageSignals::default();
+        assert!(governor.update(&unknown, now + Duration::from_secs(30)));
+        assert!(!governor.update(&unknown, now + Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn test_parse_transcode_count() {
+        let body = r#"{"MediaContainer":{"size":2,"TranscodeSession":[{"key":"a"},{"key":"b"}]}}"#;
+        assert_eq!(parse_transcode_count(body), Some(2));
+        assert_eq!(parse_transcode_count(r#"{"MediaContainer":{}}"#), Some(0));
+        assert_eq!(parse_transcode_count("<html>"), None);
+    }
+
+    #[test]
+    fn test_tree_cpu_usage_counts_descendants() {
+        let processes = [
+            (1, None, 1.0),
+            (100, Some(1), 2.0),     // daemon
+            (200, Some(100), 300.0), // av1an
+            (201, Some(200), 400.0), // SvtAv1EncApp
+            (300, Some(1), 80.0),    // someone else
+        ];
+        assert_eq!(tree_cpu_usage(&processes, 100), 702.0);
+        assert_eq!(tree_cpu_usage(&processes, 999), 0.0);
+    }
+}
@@ -0,0 +1,249 @@
+//! Cached ffprobe results keyed by path, size, and modified time.
+//!
+//! Files that pass the probe but are not queued (unstable, or back after a
+//! failed job) come up again every scan cycle, and probing them again means
+//! reading container headers over the network each time. The cache lives next
+//! to the job files in `job_state_dir` as JSON Lines, one entry per file, and
+//! an entry is only reused while the file's size and mtime are unchanged.
+//!
+//! The file uses a `.jsonl` extension so [`crate::jobs::load_jobs`] never
+//! mistakes it for a job.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::gates::ProbeResult;
+use crate::scan::ScanCandidate;
+
+/// Name of the cache file inside `job_state_dir`.
+pub const PROBE_CACHE_FILE: &str = "probe_cache.jsonl";
+
+/// Version of what a probe captures. Entries from an older version lack
+/// fields the probe reads now, so they are dropped and the file is probed
+/// again.
+const PROBE_SCHEMA: u32 = 3;
+
+/// One cached probe and the file state it was taken from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedProbe {
+    #[serde(default)]
+    schema: u32,
+    path: PathBuf,
+    size_bytes: u64,
+    modified_unix_ms: i64,
+    probe: ProbeResult,
+}
+
+/// Probe results carried between scan cycles.
+#[derive(Debug, Default)]
+pub struct ProbeCache {
+    entries: HashMap<PathBuf, CachedProbe>,
+    dirty: bool,
+}
+
+impl ProbeCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the cache from `state_dir`, returning an empty cache if the file
+    /// does not exist.
+    ///
+    /// Lines that fail to parse or were written by an older version are
+    /// dropped; they are simply probed again.
+    pub fn load(state_dir: &Path) -> io::Result<Self> {
+        let content = match fs::read_to_string(state_dir.join(PROBE_CACHE_FILE)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let entries = content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<CachedProbe>(line).ok())
+            .filter(|entry| entry.schema == PROBE_SCHEMA)
+            .map(|entry| (entry.path.clone(), entry))
+            .collect();
+        Ok(Self {
+            entries,
+            dirty: false,
+        })
+    }
+
+    /// Writes the cache to `state_dir` if it changed since it was loaded.
+    ///
+    /// Goes through a temporary file so a crash never leaves a truncated
+    /// cache behind.
+    pub fn save(&mut self, state_dir: &Path) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        fs::create_dir_all(state_dir)?;
+
+        let mut entries: Vec<&CachedProbe> = self.entries.values().collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut content = String::new();
+        for entry in entries {
+            let line = serde_json::to_string(entry)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            content.push_str(&line);
+            content.push('\n');
+        }
+
+        let path = state_dir.join(PROBE_CACHE_FILE);
+        let tmp = path.with_extension("jsonl.tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, path)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Returns the cached probe for `candidate` if the file is unchanged.
+    pub fn get(&self, candidate: &ScanCandidate) -> Option<&ProbeResult> {
+        self.entries
+            .get(&candidate.path)
+            .filter(|entry| {
+                entry.size_bytes == candidate.size_bytes
+                    && entry.modified_unix_ms == unix_ms(candidate.modified_time)
+            })
+            .map(|entry| &entry.probe)
+    }
+
+    /// Stores the probe taken of `candidate`, replacing any older entry.
+    pub fn insert(&mut self, candidate: &ScanCandidate, probe: ProbeResult) {
+        let entry = CachedProbe {
+            schema: PROBE_SCHEMA,
+            path: candidate.path.clone(),
+            size_bytes: candidate.size_bytes,
+            modified_unix_ms: unix_ms(candidate.modified_time),
+            probe,
+        };
+        self.entries.insert(entry.path.clone(), entry);
+        self.dirty = true;
+    }
+
+    /// Drops entries for files that were not scanned this cycle.
+    ///
+    /// Files that were deleted, or marked skipped, no longer show up as
+    /// candidates, so this keeps the cache from growing without bound.
+    pub fn retain_paths(&mut self, seen: &HashSet<PathBuf>) {
+        let before = self.entries.len();
+        self.entries.retain(|path, _| seen.contains(path));
+        self.dirty |= self.entries.len() != before;
+    }
+
+    /// Number of cached probes.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn unix_ms(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::{FormatInfo, VideoStream};
+    use crate::jobs::load_jobs;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn candidate(path: &str, size_bytes: u64, mtime_secs: u64) -> ScanCandidate {
+        ScanCandidate {
+            path: PathBuf::from(path),
+            size_bytes,
+            modified_time: UNIX_EPOCH + Duration::from_secs(mtime_secs),
+            root: PathBuf::from("/media"),
+        }
+    }
+
+    fn probe(codec: &str) -> ProbeResult {
+        ProbeResult {
+            video_streams: vec![VideoStream {
+                codec_name: codec.to_string(),
+                width: 1920,
+                height: 1080,
+                bitrate_kbps: Some(8000.0),
+                pix_fmt: None,
+                duration_secs: None,
+                attached_pic: false,
+                ..Default::default()
+            }],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 60.0,
+                size_bytes: 1000,
+            },
+        }
+    }
+
+    #[test]
+    fn test_hit_requires_same_size_and_mtime() {
+        let mut cache = ProbeCache::new();
+        cache.insert(&candidate("/media/a.mkv", 1000, 10), probe("h264"));
+
+        assert_eq!(cache.get(&candidate("/media/a.mkv", 1000, 10)), Some(&probe("h264")));
+        assert!(cache.get(&candidate("/media/a.mkv", 1001, 10)).is_none());
+        assert!(cache.get(&candidate("/media/a.mkv", 1000, 11)).is_none());
+        assert!(cache.get(&candidate("/media/b.mkv", 1000, 10)).is_none());
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let mut cache = ProbeCache::new();
+        cache.insert(&candidate("/media/a.mkv", 1000, 10), probe("h264"));
+        cache.insert(&candidate("/media/b.mkv", 2000, 20), probe("hevc"));
+        cache.save(temp.path()).unwrap();
+
+        let loaded = ProbeCache::load(temp.path()).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get(&candidate("/media/b.mkv", 2000, 20)), Some(&probe("hevc")));
+
+        // The cache file sits in the job store without being read as a job
+        assert!(load_jobs(temp.path()).unwrap().is_empty());
+        assert!(ProbeCache::load(&temp.path().join("missing")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_entries_from_an_older_schema_are_dropped() {
+        let temp = TempDir::new().unwrap();
+        let mut cache = ProbeCache::new();
+        cache.insert(&candidate("/media/a.mkv", 1000, 10), probe("h264"));
+        cache.save(temp.path()).unwrap();
+
+        let path = temp.path().join(PROBE_CACHE_FILE);
+        let current = fs::read_to_string(&path).unwrap();
+        let old = current.replace(&format!("\"schema\":{},", PROBE_SCHEMA), "");
+        assert_ne!(old, current);
+        fs::write(&path, old).unwrap();
+        assert!(ProbeCache::load(temp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_retain_paths_prunes_unseen_files() {
+        let mut cache = ProbeCache::new();
+        cache.insert(&candidate("/media/a.mkv", 1000, 10), probe("h264"));
+        cache.insert(&candidate("/media/b.mkv", 1000, 10), probe("h264"));
+
+        let seen: HashSet<PathBuf> = [PathBuf::from("/media/b.mkv")].into_iter().collect();
+        cache.retain_paths(&seen);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(&candidate("/media/b.mkv", 1000, 10)).is_some());
+    }
+}
@@ -0,0 +1,184 @@
+//! PSNR and SSIM on sampled frames of an encode.
+//!
+//! A full VMAF pass over a film takes about as long as decoding it twice.
+//! Comparing a handful of frame pairs takes seconds and still catches encodes
+//! that went badly wrong: a broken chunk, a wrong pixel format, a CRF far
+//! too high for the source. Frames are taken at the same evenly spaced
+//! timestamps as the comparison stills and scored with ffmpeg's `psnr` and
+//! `ssim` filters. Scores below the configured thresholds flag the encode
+//! but do not fail it.
+
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::compare::still_timestamps;
+use crate::tools::Tool;
+
+/// Scores of the sampled frame pairs of one encode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityScores {
+    /// Mean PSNR in dB over all samples
+    pub psnr: f32,
+    /// Mean SSIM (0-1) over all samples
+    pub ssim: f32,
+    /// Lowest PSNR of any sample
+    pub worst_psnr: f32,
+    /// Lowest SSIM of any sample
+    pub worst_ssim: f32,
+    /// Number of frame pairs compared
+    pub samples: u32,
+}
+
+impl QualityScores {
+    /// Averages per-sample `(psnr, ssim)` scores; `None` if there are none.
+    pub fn from_samples(samples: &[(f32, f32)]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let n = samples.len() as f32;
+        Some(Self {
+            psnr: samples.iter().map(|s| s.0).sum::<f32>() / n,
+            ssim: samples.iter().map(|s| s.1).sum::<f32>() / n,
+            worst_psnr: samples.iter().map(|s| s.0).fold(f32::INFINITY, f32::min),
+            worst_ssim: samples.iter().map(|s| s.1).fold(f32::INFINITY, f32::min),
+            samples: samples.len() as u32,
+        })
+    }
+
+    /// Describes which thresholds the worst sample fell below, if any.
+    ///
+    /// The worst sample rather than the mean is compared, since a single
+    /// broken chunk barely moves the mean.
+    pub fn flag(&self, min_psnr: Option<f32>, min_ssim: Option<f32>) -> Option<String> {
+        let mut reasons = Vec::new();
+        if let Some(min) = min_psnr.filter(|&min| self.worst_psnr < min) {
+            reasons.push(format!("PSNR {:.2} dB below {:.2}", self.worst_psnr, min));
+        }
+        if let Some(min) = min_ssim.filter(|&min| self.worst_ssim < min) {
+            reasons.push(format!("SSIM {:.4} below {:.4}", self.worst_ssim, min));
+        }
+        if reasons.is_empty() {
+            None
+        } else {
+            Some(format!("sampled frame {}", reasons.join(", ")))
+        }
+    }
+}
+
+/// Build an ffmpeg command scoring the frame at `at_secs` of `encode`
+/// against the same frame of `original`.
+///
+/// Both scores are printed to stderr; see [`parse_psnr`] and [`parse_ssim`].
+pub fn build_quality_command(original: &Path, encode: &Path, at_secs: f64) -> Command {
+    let at = format!("{:.3}", at_secs);
+    let mut cmd = Tool::Ffmpeg.command();
+    cmd.arg("-hide_banner")
+        .arg("-nostdin")
+        .arg("-ss")
+        .arg(&at)
+        .arg("-i")
+        .arg(original)
+        .arg("-ss")
+        .arg(&at)
+        .arg("-i")
+        .arg(encode)
+        .arg("-filter_complex")
+        .arg("[1:v:0]split[d0][d1];[0:v:0]split[r0][r1];[d0][r0]psnr;[d1][r1]ssim")
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-f")
+        .arg("null")
+        .arg("-");
+    cmd
+}
+
+/// Value following `key` on the first line of `stderr` containing `marker`.
+fn summary_value(stderr: &str, marker: &str, key: &str) -> Option<f32> {
+    let line = stderr.lines().find(|line| line.contains(marker))?;
+    let rest = &line[line.find(key)? + key.len()..];
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// Average PSNR from the `psnr` filter summary, e.g. `PSNR y:.. average:41.2 ..`.
+///
+/// Identical frames report `inf`.
+pub fn parse_psnr(stderr: &str) -> Option<f32> {
+    summary_value(stderr, "PSNR y:", "average:")
+}
+
+/// Overall SSIM from the `ssim` filter summary, e.g. `SSIM Y:.. All:0.98 (17.1)`.
+pub fn parse_ssim(stderr: &str) -> Option<f32> {
+    summary_value(stderr, "SSIM Y:", "All:")
+}
+
+/// Score `samples` frame pairs of `encode` against `original`.
+///
+/// # Returns
+/// The scores, or `None` if the duration is unknown or no samples were asked for
+///
+/// # Errors
+/// Returns an error if ffmpeg cannot be run, fails, or prints no scores.
+pub fn sample_quality(
+    original: &Path,
+    encode: &Path,
+    duration_secs: f64,
+    samples: u32,
+) -> io::Result<Option<QualityScores>> {
+    let mut scores = Vec::new();
+    for at_secs in still_timestamps(duration_secs, samples) {
+        let output = build_quality_command(original, encode, at_secs)
+            .stdin(Stdio::null())
+            .output()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let parsed = parse_psnr(&stderr).zip(parse_ssim(&stderr));
+        match parsed {
+            Some(score) if output.status.success() => scores.push(score),
+            _ => {
+                return Err(io::Error::other(format!(
+                    "ffmpeg gave no scores at {:.1}s: {}",
+                    at_secs,
+                    stderr.lines().last().unwrap_or("").trim()
+                )))
+            }
+        }
+    }
+    Ok(QualityScores::from_samples(&scores))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDERR: &str = "\
+[Parsed_psnr_2 @ 0x55d0] PSNR y:42.118 u:45.901 v:46.220 average:43.071 min:43.071 max:43.071
+[Parsed_ssim_3 @ 0x55d1] SSIM Y:0.981203 (17.2548) U:0.990112 (20.0479) V:0.990544 (20.2410) All:0.984501 (18.0966)
+";
+
+    #[test]
+    fn test_parse_filter_summaries() {
+        assert_eq!(parse_psnr(STDERR), Some(43.071));
+        assert_eq!(parse_ssim(STDERR), Some(0.984501));
+        assert_eq!(
+            parse_psnr("PSNR y:inf u:inf v:inf average:inf min:inf max:inf"),
+            Some(f32::INFINITY)
+        );
+        assert_eq!(parse_psnr("Conversion failed!"), None);
+    }
+
+    #[test]
+    fn test_flag_uses_worst_sample() {
+        let scores = QualityScores::from_samples(&[(44.0, 0.99), (30.0, 0.97), (44.0, 0.99)]).unwrap();
+        assert!((scores.psnr - 39.333).abs() < 0.01);
+        assert_eq!(scores.worst_psnr, 30.0);
+        assert_eq!(scores.samples, 3);
+
+        assert_eq!(scores.flag(None, None), None);
+        assert_eq!(scores.flag(Some(29.0), Some(0.95)), None);
+        assert_eq!(
+            scores.flag(Some(35.0), Some(0.98)).as_deref(),
+            Some("sampled frame PSNR 30.00 dB below 35.00, SSIM 0.9700 below 0.9800")
+        );
+        assert!(QualityScores::from_samples(&[]).is_none());
+    }
+}
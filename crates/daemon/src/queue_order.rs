@@ -0,0 +1,209 @@
+//! Operator control over the order queued jobs start in.
+//!
+//! Every job handed to the executor waits for a slot, and the slots'
+//! semaphores hand them out first come, first served. To let `POST
+//! /queue/reorder` change that order, jobs first line up in a [`WaitLine`]:
+//! only the job at the front of its lane asks for a slot, so the slot goes
+//! to whichever job the order puts first.
+//!
+//! The order is by priority, highest first, then by arrival. Reordering
+//! raises the priority of the listed jobs above every other queued job and
+//! records it on the persisted job, so `GET /jobs` and the start estimates
+//! follow the same order.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+
+/// Slot pool a job waits for; jobs only queue behind others of the same lane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    /// The executor's single pool of slots or workers
+    Shared,
+    /// Long-file lane
+    Big,
+    /// Short-file lane
+    Small,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    id: String,
+    lane: Lane,
+    /// Arrival order
+    seq: u64,
+}
+
+#[derive(Debug, Default)]
+struct LineState {
+    waiting: Vec<Waiter>,
+    /// Priorities set by reordering, by job ID; jobs without one have 0
+    priorities: HashMap<String, i64>,
+    next_seq: u64,
+}
+
+impl LineState {
+    fn key(&self, waiter: &Waiter) -> (Reverse<i64>, u64) {
+        let priority = self.priorities.get(&waiter.id).copied().unwrap_or(0);
+        (Reverse(priority), waiter.seq)
+    }
+
+    fn is_first(&self, id: &str) -> bool {
+        let Some(me) = self.waiting.iter().find(|w| w.id == id) else {
+            return true;
+        };
+        let my_key = self.key(me);
+        self.waiting
+            .iter()
+            .filter(|w| w.lane == me.lane)
+            .all(|w| self.key(w) >= my_key)
+    }
+}
+
+/// Jobs waiting for a slot, in the order they get one.
+#[derive(Debug, Default)]
+pub struct WaitLine {
+    state: Mutex<LineState>,
+    changed: Notify,
+}
+
+/// A job's place in a [`WaitLine`]; leaving it drops the place.
+pub struct Place<'a> {
+    line: &'a WaitLine,
+    id: String,
+}
+
+impl Drop for Place<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.line.state.lock() {
+            state.waiting.retain(|w| w.id != self.id);
+            state.priorities.remove(&self.id);
+        }
+        self.line.changed.notify_waiters();
+    }
+}
+
+impl WaitLine {
+    /// Creates an empty line.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts job `id` at the back of `lane`, behind jobs of higher priority.
+    pub fn join(&self, id: &str, lane: Lane) -> Place<'_> {
+        if let Ok(mut state) = self.state.lock() {
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiting.push(Waiter {
+                id: id.to_string(),
+                lane,
+                seq,
+            });
+        }
+        Place {
+            line: self,
+            id: id.to_string(),
+        }
+    }
+
+    /// Waits until `place` is at the front of its lane.
+    pub async fn wait_turn(&self, place: &Place<'_>) {
+        loop {
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.is_first(&place.id) {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    fn is_first(&self, id: &str) -> bool {
+        self.state.lock().map_or(true, |state| state.is_first(id))
+    }
+
+    /// Sets the priorities of the jobs in `priorities`, including jobs that
+    /// have not joined the line yet.
+    pub fn set_priorities<'a>(&self, priorities: impl IntoIterator<Item = (&'a str, i64)>) {
+        if let Ok(mut state) = self.state.lock() {
+            for (id, priority) in priorities {
+                state.priorities.insert(id.to_string(), priority);
+            }
+        }
+        self.changed.notify_waiters();
+    }
+
+    /// IDs of the waiting jobs, in the order they get a slot.
+    pub fn order(&self) -> Vec<String> {
+        let Ok(state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let mut waiting: Vec<&Waiter> = state.waiting.iter().collect();
+        waiting.sort_by_key(|w| state.key(w));
+        waiting.into_iter().map(|w| w.id.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_priorities_come_before_arrival() {
+        let line = WaitLine::new();
+        let _a = line.join("a", Lane::Shared);
+        let _b = line.join("b", Lane::Shared);
+        let _c = line.join("c", Lane::Shared);
+        assert_eq!(line.order(), vec!["a", "b", "c"]);
+
+        line.set_priorities([("c", 2), ("b", 1)]);
+        assert_eq!(line.order(), vec!["c", "b", "a"]);
+
+        drop(_c);
+        assert_eq!(line.order(), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn test_lanes_queue_separately() {
+        let line = WaitLine::new();
+        let _big = line.join("big", Lane::Big);
+        let _small = line.join("small", Lane::Small);
+        let _later = line.join("later", Lane::Small);
+        assert!(line.is_first("big"));
+        assert!(line.is_first("small"));
+        assert!(!line.is_first("later"));
+
+        line.set_priorities([("later", 1)]);
+        assert!(line.is_first("later"));
+        assert!(!line.is_first("small"));
+    }
+
+    #[tokio::test]
+    async fn test_wait_turn_follows_reorder() {
+        let line = Arc::new(WaitLine::new());
+        let first = line.join("first", Lane::Shared);
+
+        let waiting = {
+            let line = line.clone();
+            tokio::spawn(async move {
+                let place = line.join("second", Lane::Shared);
+                line.wait_turn(&place).await;
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        // Bumped past the job in front of it
+        line.set_priorities([("second", 1)]);
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("bumped job should get its turn")
+            .unwrap();
+        line.wait_turn(&first).await;
+    }
+}
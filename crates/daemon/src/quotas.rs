@@ -0,0 +1,276 @@
+//! Per-library quotas enforced when jobs are dispatched.
+//!
+//! When several people share one box, one large library can keep every
+//! slot busy for days. A `[quotas.libraries]` entry caps what the jobs of a
+//! library root may take: how many run at once, how many hours they encode
+//! per day, and how many bytes of originals they process per day.
+//!
+//! A job whose library is over quota waits before it lines up for a slot,
+//! so jobs of other libraries pass it by. The bytes and the encode time of
+//! a job count against the day it starts, the encode time once the job
+//! finishes. A job is let through when its library has used nothing yet
+//! that day, so a single file larger than the daily byte quota still gets
+//! processed eventually.
+//!
+//! Usage is kept in memory only and starts over when the daemon restarts.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tokio::sync::Notify;
+
+use crate::config::{LibraryQuota, QuotaConfig};
+
+/// How often a job waiting for a daily quota checks whether the day ended
+const RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// What a library used on one day, and how many of its jobs are running
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryUsage {
+    /// Days since the Unix epoch, UTC
+    pub day: u64,
+    pub running: u32,
+    pub encode_secs: f64,
+    pub bytes: u64,
+}
+
+impl LibraryUsage {
+    fn roll_over(&mut self, day: u64) {
+        if self.day != day {
+            self.day = day;
+            self.encode_secs = 0.0;
+            self.bytes = 0;
+        }
+    }
+
+    /// Why a job of `bytes` may not start now, if it may not
+    fn blocked_by(&self, quota: &LibraryQuota, bytes: u64) -> Option<String> {
+        if quota.max_concurrent_jobs > 0 && self.running >= quota.max_concurrent_jobs {
+            return Some(format!("{} jobs running", self.running));
+        }
+        let max_secs = quota.max_encode_hours_per_day * 3600.0;
+        if max_secs > 0.0 && self.encode_secs >= max_secs {
+            return Some(format!(
+                "{:.1} of {} encode hours used today",
+                self.encode_secs / 3600.0,
+                quota.max_encode_hours_per_day
+            ));
+        }
+        let used_any = self.bytes > 0 || self.encode_secs > 0.0;
+        if quota.max_bytes_per_day > 0 && used_any && self.bytes + bytes > quota.max_bytes_per_day {
+            return Some(format!(
+                "{} of {} bytes used today",
+                self.bytes, quota.max_bytes_per_day
+            ));
+        }
+        None
+    }
+}
+
+/// Usage of every library with a quota.
+#[derive(Debug, Default)]
+pub struct LibraryQuotas {
+    config: QuotaConfig,
+    usage: Mutex<HashMap<PathBuf, LibraryUsage>>,
+    changed: Notify,
+}
+
+/// A job's admission under its library's quota; dropping it when the job
+/// ends frees its place and counts its encode time.
+pub struct QuotaPermit<'a> {
+    quotas: &'a LibraryQuotas,
+    root: PathBuf,
+    started: Instant,
+}
+
+impl Drop for QuotaPermit<'_> {
+    fn drop(&mut self) {
+        let secs = self.started.elapsed().as_secs_f64();
+        self.quotas.release(&self.root, secs);
+    }
+}
+
+impl LibraryQuotas {
+    /// Creates quotas with nothing used yet.
+    pub fn new(config: QuotaConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Waits until the library of `path` may start a job processing `bytes`.
+    ///
+    /// # Returns
+    /// `None` if the file is in no library with a quota
+    pub async fn admit(&self, job_id: &str, path: &Path, bytes: u64) -> Option<QuotaPermit<'_>> {
+        self.config.quota_for(path)?;
+        let mut waiting = false;
+        loop {
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            match self.try_admit(path, bytes, current_day()) {
+                Ok(permit) => return permit,
+                Err(reason) if !waiting => {
+                    log_info!("Job {} waits for the quota of its library: {}", job_id, reason);
+                    waiting = true;
+                }
+                Err(_) => {}
+            }
+            let _ = tokio::time::timeout(RECHECK_INTERVAL, notified).await;
+        }
+    }
+
+    /// Admits a job of `bytes` for `path` on `day` if its library's quota
+    /// allows it.
+    ///
+    /// # Errors
+    /// Why the library is over quota
+    pub fn try_admit(&self, path: &Path, bytes: u64, day: u64) -> Result<Option<QuotaPermit<'_>>, String> {
+        let Some((root, quota)) = self.config.quota_for(path) else {
+            return Ok(None);
+        };
+        let Ok(mut usage) = self.usage.lock() else {
+            return Ok(None);
+        };
+        let library = usage.entry(root.clone()).or_default();
+        library.roll_over(day);
+        if let Some(reason) = library.blocked_by(quota, bytes) {
+            return Err(reason);
+        }
+        library.running += 1;
+        library.bytes += bytes;
+        Ok(Some(QuotaPermit {
+            quotas: self,
+            root: root.clone(),
+            started: Instant::now(),
+        }))
+    }
+
+    /// Ends a job of the library at `root` that encoded for `encode_secs`;
+    /// the time counts against the day the job started.
+    fn release(&self, root: &Path, encode_secs: f64) {
+        if let Ok(mut usage) = self.usage.lock() {
+            if let Some(library) = usage.get_mut(root) {
+                library.running = library.running.saturating_sub(1);
+                library.encode_secs += encode_secs;
+            }
+        }
+        self.changed.notify_waiters();
+    }
+
+    /// What each library with a quota has used, by library root.
+    pub fn usage(&self) -> HashMap<PathBuf, LibraryUsage> {
+        let day = current_day();
+        let Ok(mut usage) = self.usage.lock() else {
+            return HashMap::new();
+        };
+        for library in usage.values_mut() {
+            library.roll_over(day);
+        }
+        usage.clone()
+    }
+}
+
+fn current_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / SECS_PER_DAY)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    fn quotas(quota: LibraryQuota) -> LibraryQuotas {
+        let mut libraries = BTreeMap::new();
+        libraries.insert(PathBuf::from("/media/anime"), quota);
+        LibraryQuotas::new(QuotaConfig { libraries })
+    }
+
+    #[test]
+    fn test_concurrent_jobs_are_capped_per_library() {
+        let quotas = quotas(LibraryQuota {
+            max_concurrent_jobs: 1,
+            ..LibraryQuota::default()
+        });
+        let anime = Path::new("/media/anime/show/e1.mkv");
+
+        let first = quotas.try_admit(anime, 10, 1).unwrap();
+        assert!(first.is_some());
+        assert_eq!(quotas.try_admit(anime, 10, 1).err().as_deref(), Some("1 jobs running"));
+        // Other libraries are not held back
+        assert!(quotas.try_admit(Path::new("/media/movies/a.mkv"), 10, 1).unwrap().is_none());
+
+        drop(first);
+        assert!(quotas.try_admit(anime, 10, 1).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_daily_bytes_reset_the_next_day() {
+        let quotas = quotas(LibraryQuota {
+            max_bytes_per_day: 100,
+            ..LibraryQuota::default()
+        });
+        let anime = Path::new("/media/anime/e1.mkv");
+
+        // The first job of the day goes through even when it is too large
+        drop(quotas.try_admit(anime, 150, 1).unwrap());
+        assert!(quotas.try_admit(anime, 1, 1).is_err());
+        assert!(quotas.try_admit(anime, 60, 2).unwrap().is_some());
+        assert!(quotas.try_admit(anime, 40, 2).unwrap().is_some());
+        assert_eq!(quotas.try_admit(anime, 1, 2).err().as_deref(), Some("100 of 100 bytes used today"));
+    }
+
+    #[test]
+    fn test_encode_hours_count_when_jobs_end() {
+        let quotas = quotas(LibraryQuota {
+            max_encode_hours_per_day: 1.0,
+            ..LibraryQuota::default()
+        });
+        let anime = Path::new("/media/anime/e1.mkv");
+        let day = current_day();
+
+        let permit = quotas.try_admit(anime, 0, day).unwrap();
+        assert!(quotas.try_admit(anime, 0, day).is_ok());
+        drop(permit);
+        quotas.release(Path::new("/media/anime"), 3600.0);
+        assert!(quotas.try_admit(anime, 0, day).is_err());
+        assert!(quotas.usage()[Path::new("/media/anime")].encode_secs >= 3600.0);
+    }
+
+    #[tokio::test]
+    async fn test_admit_waits_for_a_running_job() {
+        let quotas = Arc::new(quotas(LibraryQuota {
+            max_concurrent_jobs: 1,
+            ..LibraryQuota::default()
+        }));
+        let anime = Path::new("/media/anime/e1.mkv");
+        let first = quotas.admit("first", anime, 0).await;
+        assert!(first.is_some());
+
+        let waiting = {
+            let quotas = quotas.clone();
+            tokio::spawn(async move {
+                quotas.admit("second", Path::new("/media/anime/e2.mkv"), 0).await.is_some()
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        let admitted = tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("waiting job should be admitted")
+            .unwrap();
+        assert!(admitted);
+    }
+}
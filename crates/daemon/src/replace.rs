@@ -2,8 +2,23 @@
 //!
 //! This module provides functionality to safely replace original video files
 //! with encoded versions, creating backups and handling errors gracefully.
+//!
+//! Every replacement is bracketed by a `<name>.av1swap` intent file next to
+//! the original. It records the paths involved and how far the swap got, so
+//! [`repair_swap`] can restore the original or finish the swap after a crash
+//! instead of leaving the original gone and the copy partial. Files are
+//! copied to a `.av1swap.part` sibling and renamed into place, so a path only
+//! ever holds a complete file.
+//!
+//! On Windows a replacement in place goes through `ReplaceFileW`, which moves
+//! the original to its backup and the new file into place in one call and
+//! carries the original's ACL and attributes over to it.
 
-use std::fs;
+use crate::config::{BackupLocation, CollisionPolicy};
+use crate::staging::sha256_file;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
@@ -22,6 +37,219 @@ pub enum ReplaceError {
     /// Failed to delete backup file.
     #[error("Failed to delete backup: {0}")]
     DeleteBackupFailed(std::io::Error),
+
+    /// The destination path is already taken by another file.
+    #[error("Destination already exists: {0}")]
+    TargetExists(PathBuf),
+
+    /// The rename template produced an unusable file name.
+    #[error("Invalid output name: {0}")]
+    InvalidName(String),
+
+    /// Failed to read or write the `.av1swap` intent file.
+    #[error("Failed to update swap marker: {0}")]
+    MarkerFailed(std::io::Error),
+
+    /// The torrents seeding the original could not be paused.
+    #[error("Failed to pause seeding torrents: {0}")]
+    SeedingPauseFailed(String),
+
+    /// The original no longer matches the staged copy the encode read.
+    #[error("Original does not match its staged copy: {0}")]
+    SourceChanged(std::io::Error),
+}
+
+/// Suffix of the intent file written next to an original during replacement.
+pub const SWAP_MARKER_SUFFIX: &str = ".av1swap";
+
+/// Suffix of files being copied into place.
+const PART_SUFFIX: &str = ".av1swap.part";
+
+/// Folder next to the original that holds backups with
+/// [`BackupLocation::Hidden`].
+pub const BACKUP_DIR: &str = ".av1backup";
+
+/// How far a replacement got before its marker was last updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapPhase {
+    /// The original is being moved to its backup.
+    BackingUp,
+    /// The backup is complete and the encoded file is being copied in.
+    Copying,
+    /// The encoded file is in place; only the backup is left to delete.
+    Cleanup,
+}
+
+/// Contents of an `.av1swap` intent file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapIntent {
+    pub original: PathBuf,
+    pub encoded: PathBuf,
+    pub target: PathBuf,
+    pub backup: PathBuf,
+    pub keep_original: bool,
+    pub phase: SwapPhase,
+}
+
+/// What [`repair_swap`] did with an interrupted replacement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SwapRepair {
+    /// The original is back where it was and the encoded file was not used.
+    RolledBack,
+    /// The encoded file is in place, as if the replacement had not stopped.
+    /// Carries the marker's intent, since the steps that follow a
+    /// replacement still have to run for its target.
+    Finished(SwapIntent),
+}
+
+/// Path of the intent file for a replacement of `original`.
+pub fn swap_marker_path(original: &Path) -> PathBuf {
+    with_suffix(original, SWAP_MARKER_SUFFIX)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Writes the intent file through a temporary file so it is never torn.
+fn write_swap_marker(marker: &Path, intent: &SwapIntent) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(intent)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let tmp = with_suffix(marker, ".tmp");
+    fs::write(&tmp, json)?;
+    sync_file(&tmp)?;
+    fs::rename(&tmp, marker)
+}
+
+/// Flushes `path` to disk. Windows only flushes handles open for writing.
+fn sync_file(path: &Path) -> io::Result<()> {
+    OpenOptions::new().write(true).open(path)?.sync_all()
+}
+
+fn read_swap_marker(marker: &Path) -> io::Result<SwapIntent> {
+    let content = fs::read_to_string(marker)?;
+    serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Copies `from` to a `.av1swap.part` sibling of `to`, then renames it into
+/// place. With `verify`, the copy is only renamed if its checksum matches.
+fn copy_into_place(from: &Path, to: &Path, verify: bool) -> io::Result<()> {
+    let part = with_suffix(to, PART_SUFFIX);
+    let result = fs::copy(from, &part)
+        .and_then(|_| sync_file(&part))
+        .and_then(|_| if verify { verify_copy(from, &part) } else { Ok(()) })
+        .and_then(|_| fs::rename(&part, to));
+    if result.is_err() {
+        let _ = fs::remove_file(&part);
+    }
+    result
+}
+
+/// Fails with `InvalidData` unless `copy` holds the same data as `from`.
+fn verify_copy(from: &Path, copy: &Path) -> io::Result<()> {
+    if sha256_file(from)? == sha256_file(copy)? {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("copy of {:?} does not match it", from),
+        ))
+    }
+}
+
+/// Removes the [`BACKUP_DIR`] holding `backup` once no backups are left in it.
+pub(crate) fn remove_empty_backup_dir(backup: &Path) {
+    if let Some(dir) = backup.parent().filter(|dir| dir.ends_with(BACKUP_DIR)) {
+        // Fails, as intended, while other backups are still there
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+fn remove_if_present(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Highest number tried when suffixing a colliding name.
+const MAX_COLLISION_SUFFIX: u32 = 99;
+
+/// Renders the rename template for `original`.
+///
+/// Supported placeholders are `{stem}` (original file name without its
+/// extension), `{ext}` (the output container extension), and `{orig_ext}`
+/// (the original extension).
+///
+/// # Errors
+///
+/// Returns [`ReplaceError::InvalidName`] if the result is empty, `.`, `..`, or
+/// contains a path separator; the output always stays in the original's
+/// directory.
+pub fn render_output_name(
+    template: &str,
+    original: &Path,
+    ext: &str,
+) -> Result<String, ReplaceError> {
+    let stem = original
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let orig_ext = original
+        .extension()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+
+    let name = template
+        .replace("{stem}", &stem)
+        .replace("{orig_ext}", &orig_ext)
+        .replace("{ext}", ext);
+
+    if name.trim().is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(ReplaceError::InvalidName(name));
+    }
+    Ok(name)
+}
+
+/// Works out where the encoded file should end up once it replaces `original`.
+///
+/// The original itself never counts as a collision, since it is moved to its
+/// backup before the encoded file is copied in.
+///
+/// # Errors
+///
+/// Returns [`ReplaceError::InvalidName`] for a bad template, and
+/// [`ReplaceError::TargetExists`] when the name is taken and the policy is
+/// [`CollisionPolicy::Fail`] or every suffix is taken too.
+pub fn resolve_output_path(
+    original: &Path,
+    template: &str,
+    ext: &str,
+    on_collision: CollisionPolicy,
+) -> Result<PathBuf, ReplaceError> {
+    let name = render_output_name(template, original, ext)?;
+    let target = original.with_file_name(&name);
+    if target == original || !target.exists() {
+        return Ok(target);
+    }
+
+    if on_collision == CollisionPolicy::Fail {
+        return Err(ReplaceError::TargetExists(target));
+    }
+
+    let named = Path::new(&name);
+    let stem = named.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let suffix_ext = named
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (2..=MAX_COLLISION_SUFFIX)
+        .map(|n| original.with_file_name(format!("{} ({}){}", stem, n, suffix_ext)))
+        .find(|candidate| !candidate.exists())
+        .ok_or(ReplaceError::TargetExists(target))
 }
 
 /// Generates a backup path for the original file.
@@ -63,6 +291,43 @@ pub fn backup_path(original: &Path) -> PathBuf {
     PathBuf::from(backup)
 }
 
+/// Renders the backup template for `original` at Unix time `timestamp`.
+///
+/// Supported placeholders are `{name}` (the original file name) and `{ts}`
+/// (`timestamp` in seconds). With [`BackupLocation::Hidden`] the backup
+/// goes into a [`BACKUP_DIR`] folder next to the original.
+///
+/// # Errors
+///
+/// Returns [`ReplaceError::InvalidName`] if the name is empty, `.`, `..`,
+/// contains a path separator, or would be the original itself.
+pub fn render_backup_path(
+    original: &Path,
+    template: &str,
+    location: BackupLocation,
+    timestamp: u64,
+) -> Result<PathBuf, ReplaceError> {
+    let file_name = original
+        .file_name()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let name = template
+        .replace("{name}", &file_name)
+        .replace("{ts}", &timestamp.to_string());
+
+    if name.trim().is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(ReplaceError::InvalidName(name));
+    }
+    let backup = match location {
+        BackupLocation::Beside => original.with_file_name(&name),
+        BackupLocation::Hidden => original.with_file_name(BACKUP_DIR).join(&name),
+    };
+    if backup == original {
+        return Err(ReplaceError::InvalidName(name));
+    }
+    Ok(backup)
+}
+
 
 /// Atomically replaces the original file with the encoded file.
 ///
@@ -72,7 +337,8 @@ pub fn backup_path(original: &Path) -> PathBuf {
 /// 3. Delete the backup if `keep_original` is false
 ///
 /// If any step fails, the function preserves both the original and encoded
-/// files for manual inspection.
+/// files for manual inspection. A crash part way through leaves an
+/// `.av1swap` marker behind for [`repair_swap`].
 ///
 /// # Arguments
 ///
@@ -99,31 +365,249 @@ pub fn atomic_replace(
     encoded_path: &Path,
     keep_original: bool,
 ) -> Result<(), ReplaceError> {
-    // Step 1: Create backup of original file
+    atomic_replace_to(original_path, encoded_path, original_path, keep_original)
+}
+
+/// Replaces the original file with the encoded file written to `target_path`.
+///
+/// Works like [`atomic_replace`], except the encoded file lands at
+/// `target_path` instead of the original's location, for example when a
+/// remux changes `film.mp4` into `film.mkv`. The original is backed up and
+/// removed the same way.
+///
+/// # Errors
+///
+/// Returns [`ReplaceError::TargetExists`] without touching anything if
+/// `target_path` differs from `original_path` and is already present.
+pub fn atomic_replace_to(
+    original_path: &Path,
+    encoded_path: &Path,
+    target_path: &Path,
+    keep_original: bool,
+) -> Result<(), ReplaceError> {
     let backup = backup_path(original_path);
-    
+    replace_with_backup(original_path, encoded_path, target_path, &backup, keep_original, false)
+}
+
+/// Replaces the original like [`atomic_replace_to`], copying the original to
+/// its backup instead of renaming it.
+///
+/// Meant for files with other hard links: the original's data, which the
+/// other links share, is only ever read, and a kept backup is a separate
+/// file rather than one more link to it.
+pub fn atomic_copy_replace_to(
+    original_path: &Path,
+    encoded_path: &Path,
+    target_path: &Path,
+    keep_original: bool,
+) -> Result<(), ReplaceError> {
+    let backup = backup_path(original_path);
+    replace_with_backup(original_path, encoded_path, target_path, &backup, keep_original, true)
+}
+
+/// Replaces the original like [`atomic_replace_to`], backing it up to
+/// `backup`, for example one from [`render_backup_path`].
+///
+/// The backup's folder is created if it is missing. `copy_backup` copies
+/// the original to its backup instead of renaming it, as
+/// [`atomic_copy_replace_to`] does.
+///
+/// # Errors
+///
+/// Returns [`ReplaceError::InvalidName`] without touching anything if
+/// `backup` is the original or the target.
+pub fn replace_with_backup(
+    original_path: &Path,
+    encoded_path: &Path,
+    target_path: &Path,
+    backup: &Path,
+    keep_original: bool,
+    copy_backup: bool,
+) -> Result<(), ReplaceError> {
+    replace_with_backup_verified(
+        original_path,
+        encoded_path,
+        target_path,
+        backup,
+        keep_original,
+        copy_backup,
+        false,
+    )
+}
+
+/// Replaces the original like [`replace_with_backup`]; with `verify_copy`,
+/// the copy of the encoded file is checksummed against it before it takes
+/// the original's place, and a mismatch restores the original.
+pub fn replace_with_backup_verified(
+    original_path: &Path,
+    encoded_path: &Path,
+    target_path: &Path,
+    backup: &Path,
+    keep_original: bool,
+    copy_backup: bool,
+    verify_copy: bool,
+) -> Result<(), ReplaceError> {
+    if target_path != original_path && target_path.exists() {
+        return Err(ReplaceError::TargetExists(target_path.to_path_buf()));
+    }
+    if backup == original_path || backup == target_path {
+        return Err(ReplaceError::InvalidName(backup.display().to_string()));
+    }
+    if let Some(dir) = backup.parent() {
+        fs::create_dir_all(dir).map_err(ReplaceError::BackupFailed)?;
+    }
+
+    // Record the intent before touching anything
+    let backup = backup.to_path_buf();
+    let marker = swap_marker_path(original_path);
+    let mut intent = SwapIntent {
+        original: original_path.to_path_buf(),
+        encoded: encoded_path.to_path_buf(),
+        target: target_path.to_path_buf(),
+        backup: backup.clone(),
+        keep_original,
+        phase: SwapPhase::BackingUp,
+    };
+    write_swap_marker(&marker, &intent).map_err(ReplaceError::MarkerFailed)?;
+
+    #[cfg(windows)]
+    if target_path == original_path && !copy_backup {
+        // Until ReplaceFileW returns the original is either in place or,
+        // like after a failed step 1, at its backup
+        let part = with_suffix(target_path, PART_SUFFIX);
+        let swapped = fs::copy(encoded_path, &part)
+            .and_then(|_| sync_file(&part))
+            .and_then(|_| if verify_copy { self::verify_copy(encoded_path, &part) } else { Ok(()) })
+            .and_then(|_| replace_file(original_path, &part, &backup));
+        if let Err(e) = swapped {
+            if !original_path.exists() {
+                let _ = fs::rename(&backup, original_path);
+            }
+            let _ = fs::remove_file(&part);
+            let _ = fs::remove_file(&marker);
+            return Err(ReplaceError::CopyFailed(e));
+        }
+        return finish_swap(&marker, intent);
+    }
+
+    // Step 1: Create backup of original file
     // Try to rename first (faster, same filesystem)
     // Fall back to copy if rename fails (cross-filesystem or ZFS quirks)
-    if fs::rename(original_path, &backup).is_err() {
-        fs::copy(original_path, &backup)
-            .map_err(ReplaceError::BackupFailed)?;
-        fs::remove_file(original_path)
-            .map_err(ReplaceError::BackupFailed)?;
+    if copy_backup || fs::rename(original_path, &backup).is_err() {
+        let moved = copy_into_place(original_path, &backup, false).and_then(|_| fs::remove_file(original_path));
+        if let Err(e) = moved {
+            if original_path.exists() {
+                let _ = fs::remove_file(&backup);
+            }
+            let _ = fs::remove_file(&marker);
+            return Err(ReplaceError::BackupFailed(e));
+        }
     }
 
-    // Step 2: Copy encoded file to original location
-    if let Err(e) = fs::copy(encoded_path, original_path) {
+    // Step 2: Copy encoded file to its destination
+    intent.phase = SwapPhase::Copying;
+    let copied = write_swap_marker(&marker, &intent)
+        .map_err(ReplaceError::MarkerFailed)
+        .and_then(|_| copy_into_place(encoded_path, target_path, verify_copy).map_err(ReplaceError::CopyFailed));
+    if let Err(e) = copied {
         // Restore original from backup on failure
         let _ = fs::rename(&backup, original_path);
-        return Err(ReplaceError::CopyFailed(e));
+        let _ = fs::remove_file(&marker);
+        return Err(e);
     }
 
-    // Step 3: Delete backup if keep_original is false
-    if !keep_original {
-        fs::remove_file(&backup).map_err(ReplaceError::DeleteBackupFailed)?;
+    finish_swap(&marker, intent)
+}
+
+/// Step 3 of a swap: deletes the backup unless it is kept, then the marker.
+fn finish_swap(marker: &Path, mut intent: SwapIntent) -> Result<(), ReplaceError> {
+    intent.phase = SwapPhase::Cleanup;
+    let _ = write_swap_marker(marker, &intent);
+    let deleted = if intent.keep_original {
+        Ok(())
+    } else {
+        fs::remove_file(&intent.backup).map_err(ReplaceError::DeleteBackupFailed)
+    };
+    let _ = fs::remove_file(marker);
+    remove_empty_backup_dir(&intent.backup);
+    deleted
+}
+
+/// Puts `replacement` at `replaced`, moving the original to `backup`.
+#[cfg(windows)]
+fn replace_file(replaced: &Path, replacement: &Path, backup: &Path) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::{ReplaceFileW, REPLACEFILE_IGNORE_MERGE_ERRORS};
+
+    let wide = |path: &Path| -> Vec<u16> { path.as_os_str().encode_wide().chain(Some(0)).collect() };
+    let (replaced, replacement, backup) = (wide(replaced), wide(replacement), wide(backup));
+    // SAFETY: all three are NUL-terminated wide strings that outlive the call
+    let replaced = unsafe {
+        ReplaceFileW(
+            replaced.as_ptr(),
+            replacement.as_ptr(),
+            backup.as_ptr(),
+            REPLACEFILE_IGNORE_MERGE_ERRORS,
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    if replaced == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
     }
+}
 
-    Ok(())
+/// Repairs a replacement of `original` that was interrupted by a crash.
+///
+/// Reads the `.av1swap` marker left next to `original` and either finishes
+/// the swap, when the encoded file is in place or can still be copied in,
+/// or moves the original back from its backup. Running it again after it
+/// succeeds does nothing.
+///
+/// # Returns
+///
+/// What was done, or `None` if there was no marker
+pub fn repair_swap(original: &Path) -> Result<Option<SwapRepair>, ReplaceError> {
+    let marker = swap_marker_path(original);
+    let intent = match read_swap_marker(&marker) {
+        Ok(intent) => intent,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(ReplaceError::MarkerFailed(e)),
+    };
+    let _ = fs::remove_file(with_suffix(&intent.backup, PART_SUFFIX));
+    let _ = fs::remove_file(with_suffix(&intent.target, PART_SUFFIX));
+
+    let finished = match intent.phase {
+        // The original never left, so a backup is at most a spare copy
+        SwapPhase::BackingUp if intent.original.exists() => {
+            remove_if_present(&intent.backup).map_err(ReplaceError::BackupFailed)?;
+            false
+        }
+        SwapPhase::BackingUp | SwapPhase::Copying => {
+            if !intent.target.exists() && intent.encoded.exists() {
+                copy_into_place(&intent.encoded, &intent.target, false).map_err(ReplaceError::CopyFailed)?;
+            }
+            let placed = intent.target.exists();
+            if !placed {
+                fs::rename(&intent.backup, &intent.original).map_err(ReplaceError::BackupFailed)?;
+            }
+            placed
+        }
+        SwapPhase::Cleanup => true,
+    };
+
+    if finished && !intent.keep_original {
+        remove_if_present(&intent.backup).map_err(ReplaceError::DeleteBackupFailed)?;
+    }
+    remove_if_present(&marker).map_err(ReplaceError::MarkerFailed)?;
+    remove_empty_backup_dir(&intent.backup);
+    Ok(Some(if finished {
+        SwapRepair::Finished(intent)
+    } else {
+        SwapRepair::RolledBack
+    }))
 }
 
 #[cfg(test)]
@@ -271,4 +755,274 @@ mod tests {
         let result = atomic_replace(&original_path, &encoded_path, false);
         assert!(matches!(result, Err(ReplaceError::BackupFailed(_))));
     }
+
+    #[test]
+    fn test_atomic_replace_to_new_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let original_path = temp_dir.path().join("film.mp4");
+        let target_path = temp_dir.path().join("film.mkv");
+        let encoded_path = temp_dir.path().join("remuxed.mkv");
+        fs::write(&original_path, b"original content").unwrap();
+        fs::write(&encoded_path, b"remuxed content").unwrap();
+
+        atomic_replace_to(&original_path, &encoded_path, &target_path, false).unwrap();
+
+        assert!(!original_path.exists());
+        assert_eq!(fs::read_to_string(&target_path).unwrap(), "remuxed content");
+        let backups = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().to_string_lossy().contains(".orig."))
+            .count();
+        assert_eq!(backups, 0);
+    }
+
+    #[test]
+    fn test_atomic_replace_to_refuses_existing_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let original_path = temp_dir.path().join("film.mp4");
+        let target_path = temp_dir.path().join("film.mkv");
+        let encoded_path = temp_dir.path().join("remuxed.mkv");
+        fs::write(&original_path, b"original content").unwrap();
+        fs::write(&target_path, b"someone else's file").unwrap();
+        fs::write(&encoded_path, b"remuxed content").unwrap();
+
+        let result = atomic_replace_to(&original_path, &encoded_path, &target_path, false);
+        assert!(matches!(result, Err(ReplaceError::TargetExists(_))));
+        assert_eq!(fs::read_to_string(&original_path).unwrap(), "original content");
+        assert_eq!(fs::read_to_string(&target_path).unwrap(), "someone else's file");
+    }
+
+    #[test]
+    fn test_atomic_replace_clears_swap_marker() {
+        let temp_dir = TempDir::new().unwrap();
+        let original_path = temp_dir.path().join("film.mkv");
+        let encoded_path = temp_dir.path().join("encoded.mkv");
+        fs::write(&original_path, b"original content").unwrap();
+        fs::write(&encoded_path, b"encoded content").unwrap();
+
+        atomic_replace(&original_path, &encoded_path, false).unwrap();
+
+        let leftovers = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().to_string_lossy().contains(SWAP_MARKER_SUFFIX))
+            .count();
+        assert_eq!(leftovers, 0);
+        assert_eq!(repair_swap(&original_path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_verified_replace_checks_the_copy() {
+        let temp_dir = TempDir::new().unwrap();
+        let original_path = temp_dir.path().join("film.mkv");
+        let encoded_path = temp_dir.path().join("encoded.mkv");
+        let backup = temp_dir.path().join("film.mkv.bak");
+        fs::write(&original_path, b"original content").unwrap();
+        fs::write(&encoded_path, b"encoded content").unwrap();
+
+        let target = temp_dir.path().join("film AV1.mkv");
+        replace_with_backup_verified(&original_path, &encoded_path, &target, &backup, false, false, true)
+            .unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "encoded content");
+        assert!(!original_path.exists());
+        assert!(!backup.exists());
+
+        assert!(verify_copy(&encoded_path, &target).is_ok());
+        fs::write(&target, b"corrupted").unwrap();
+        let err = verify_copy(&encoded_path, &target).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_leaves_other_hard_links_intact() {
+        use crate::scan::hard_link_count;
+
+        let temp_dir = TempDir::new().unwrap();
+        for copy_backup in [false, true] {
+            let original_path = temp_dir.path().join(format!("film{}.mkv", copy_backup));
+            let seed_path = temp_dir.path().join(format!("seed{}.mkv", copy_backup));
+            let encoded_path = temp_dir.path().join("encoded.mkv");
+            fs::write(&original_path, b"original content").unwrap();
+            fs::hard_link(&original_path, &seed_path).unwrap();
+            fs::write(&encoded_path, b"encoded content").unwrap();
+
+            if copy_backup {
+                atomic_copy_replace_to(&original_path, &encoded_path, &original_path, true)
+                    .unwrap();
+            } else {
+                atomic_replace(&original_path, &encoded_path, true).unwrap();
+            }
+
+            assert_eq!(fs::read_to_string(&original_path).unwrap(), "encoded content");
+            assert_eq!(fs::read_to_string(&seed_path).unwrap(), "original content");
+            assert_eq!(hard_link_count(&original_path), 1);
+            // A renamed backup is still linked to the seed, a copied one is not
+            let expected_links = if copy_backup { 1 } else { 2 };
+            assert_eq!(hard_link_count(&seed_path), expected_links);
+        }
+    }
+
+    /// Leaves `dir` as a crash would: original moved to its backup, marker at
+    /// `phase`, and a half-copied part file at the target.
+    fn interrupted_swap(dir: &Path, phase: SwapPhase) -> SwapIntent {
+        let intent = SwapIntent {
+            original: dir.join("film.mkv"),
+            encoded: dir.join("encoded.mkv"),
+            target: dir.join("film.mkv"),
+            backup: dir.join("film.mkv.orig.1"),
+            keep_original: false,
+            phase,
+        };
+        fs::write(&intent.backup, b"original content").unwrap();
+        fs::write(&intent.encoded, b"encoded content").unwrap();
+        fs::write(with_suffix(&intent.target, PART_SUFFIX), b"enc").unwrap();
+        write_swap_marker(&swap_marker_path(&intent.original), &intent).unwrap();
+        intent
+    }
+
+    #[test]
+    fn test_repair_swap_finishes_copy() {
+        let temp_dir = TempDir::new().unwrap();
+        let intent = interrupted_swap(temp_dir.path(), SwapPhase::Copying);
+
+        assert_eq!(
+            repair_swap(&intent.original).unwrap(),
+            Some(SwapRepair::Finished(intent.clone()))
+        );
+        assert_eq!(fs::read_to_string(&intent.original).unwrap(), "encoded content");
+        assert!(!intent.backup.exists());
+        assert!(!with_suffix(&intent.target, PART_SUFFIX).exists());
+        assert!(!swap_marker_path(&intent.original).exists());
+
+        // A second run finds nothing to do
+        assert_eq!(repair_swap(&intent.original).unwrap(), None);
+    }
+
+    #[test]
+    fn test_repair_swap_restores_original_without_encode() {
+        let temp_dir = TempDir::new().unwrap();
+        let intent = interrupted_swap(temp_dir.path(), SwapPhase::Copying);
+        fs::remove_file(&intent.encoded).unwrap();
+
+        assert_eq!(repair_swap(&intent.original).unwrap(), Some(SwapRepair::RolledBack));
+        assert_eq!(fs::read_to_string(&intent.original).unwrap(), "original content");
+        assert!(!intent.backup.exists());
+        assert!(!swap_marker_path(&intent.original).exists());
+    }
+
+    #[test]
+    fn test_repair_swap_before_backup_keeps_original() {
+        let temp_dir = TempDir::new().unwrap();
+        let intent = interrupted_swap(temp_dir.path(), SwapPhase::BackingUp);
+        fs::write(&intent.original, b"original content").unwrap();
+
+        assert_eq!(repair_swap(&intent.original).unwrap(), Some(SwapRepair::RolledBack));
+        assert_eq!(fs::read_to_string(&intent.original).unwrap(), "original content");
+        assert!(!intent.backup.exists());
+        assert!(intent.encoded.exists(), "the encode is left for the job to clean up");
+    }
+
+    #[test]
+    fn test_render_backup_path_template_and_location() {
+        let original = Path::new("/tv/Show/ep.mkv");
+        assert_eq!(
+            render_backup_path(original, "{name}.orig.{ts}", BackupLocation::Beside, 17).unwrap(),
+            Path::new("/tv/Show/ep.mkv.orig.17")
+        );
+        assert_eq!(
+            render_backup_path(original, "{ts}-{name}", BackupLocation::Hidden, 17).unwrap(),
+            Path::new("/tv/Show/.av1backup/17-ep.mkv")
+        );
+        // Inside the hidden folder the plain name does not clash
+        assert_eq!(
+            render_backup_path(original, "{name}", BackupLocation::Hidden, 17).unwrap(),
+            Path::new("/tv/Show/.av1backup/ep.mkv")
+        );
+        for template in ["{name}", "", "../{name}", ".."] {
+            assert!(
+                matches!(
+                    render_backup_path(original, template, BackupLocation::Beside, 17),
+                    Err(ReplaceError::InvalidName(_))
+                ),
+                "template {:?} should be rejected",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn test_replace_with_backup_in_hidden_folder() {
+        let temp_dir = TempDir::new().unwrap();
+        let original_path = temp_dir.path().join("film.mkv");
+        let encoded_path = temp_dir.path().join("encoded.mkv");
+        fs::write(&original_path, b"original content").unwrap();
+        fs::write(&encoded_path, b"encoded content").unwrap();
+
+        let backup =
+            render_backup_path(&original_path, "{name}", BackupLocation::Hidden, 0).unwrap();
+        replace_with_backup(&original_path, &encoded_path, &original_path, &backup, true, false)
+            .unwrap();
+        assert_eq!(fs::read_to_string(&original_path).unwrap(), "encoded content");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "original content");
+
+        // Without keep_original the emptied folder goes too
+        fs::remove_file(&backup).unwrap();
+        fs::remove_dir(backup.parent().unwrap()).unwrap();
+        fs::write(&encoded_path, b"second encode").unwrap();
+        replace_with_backup(&original_path, &encoded_path, &original_path, &backup, false, false)
+            .unwrap();
+        assert_eq!(fs::read_to_string(&original_path).unwrap(), "second encode");
+        assert!(!temp_dir.path().join(BACKUP_DIR).exists());
+    }
+
+    #[test]
+    fn test_render_output_name_placeholders() {
+        let original = Path::new("/tv/Show/Show - S01E01.mp4");
+        assert_eq!(
+            render_output_name("{stem}.{ext}", original, "mkv").unwrap(),
+            "Show - S01E01.mkv"
+        );
+        assert_eq!(
+            render_output_name("{stem} AV1.{ext}", original, "mkv").unwrap(),
+            "Show - S01E01 AV1.mkv"
+        );
+        assert_eq!(
+            render_output_name("{stem}.{orig_ext}", original, "mkv").unwrap(),
+            "Show - S01E01.mp4"
+        );
+    }
+
+    #[test]
+    fn test_render_output_name_rejects_escaping_names() {
+        let original = Path::new("/movies/film.mkv");
+        for template in ["", "../{stem}.{ext}", "sub/{stem}.{ext}", " "] {
+            assert!(matches!(
+                render_output_name(template, original, "mkv"),
+                Err(ReplaceError::InvalidName(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_resolve_output_path_collisions() {
+        let temp_dir = TempDir::new().unwrap();
+        let original = temp_dir.path().join("film.mp4");
+        fs::write(&original, b"original").unwrap();
+
+        // Replacing in place is never a collision
+        let in_place = resolve_output_path(&original, "{stem}.{orig_ext}", "mkv", CollisionPolicy::Fail);
+        assert_eq!(in_place.unwrap(), original);
+
+        fs::write(temp_dir.path().join("film.mkv"), b"other").unwrap();
+        assert!(matches!(
+            resolve_output_path(&original, "{stem}.{ext}", "mkv", CollisionPolicy::Fail),
+            Err(ReplaceError::TargetExists(_))
+        ));
+        assert_eq!(
+            resolve_output_path(&original, "{stem}.{ext}", "mkv", CollisionPolicy::Suffix).unwrap(),
+            temp_dir.path().join("film (2).mkv")
+        );
+    }
 }
@@ -0,0 +1,72 @@
+//! Time-of-day window for replacements.
+//!
+//! Replacing an original copies the encode back over the network and swaps
+//! files under the media server, which is best done while nobody watches.
+//! With `gates.replace_window` set, encodes still run whenever a slot is
+//! free, but an encode that finishes outside the window is kept in temp as
+//! `ready_to_replace`. A background task checks the window every
+//! [`REPLACE_WINDOW_POLL_SECS`] and replaces the waiting jobs once it opens,
+//! oldest first.
+//!
+//! The window is in local time. Off Unix it is read as UTC.
+
+use crate::config::TimeWindow;
+
+/// Seconds between checks for an open window.
+pub const REPLACE_WINDOW_POLL_SECS: u64 = 60;
+
+/// Minutes after local midnight at `unix_ms`.
+pub fn local_minute_of_day(unix_ms: i64) -> u16 {
+    let secs = unix_ms.div_euclid(1000);
+    let local = secs + utc_offset_secs(secs);
+    (local.rem_euclid(86_400) / 60) as u16
+}
+
+/// Returns true if replacements may run at `unix_ms`; always true without
+/// a window.
+pub fn replace_window_open(window: Option<TimeWindow>, unix_ms: i64) -> bool {
+    window.is_none_or(|window| window.contains(local_minute_of_day(unix_ms)))
+}
+
+/// Offset of local time from UTC at `unix_secs`
+#[cfg(unix)]
+fn utc_offset_secs(unix_secs: i64) -> i64 {
+    let time = unix_secs as libc::time_t;
+    // SAFETY: localtime_r only writes to the tm it is given
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff as i64
+}
+
+/// Offset of local time from UTC; the window is read as UTC off Unix
+#[cfg(not(unix))]
+fn utc_offset_secs(_unix_secs: i64) -> i64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_window_open() {
+        let now = 1_700_000_000_000;
+        let minute = local_minute_of_day(now);
+        assert!(minute < 24 * 60);
+        assert!(replace_window_open(None, now));
+
+        let around = TimeWindow {
+            start_minute: minute,
+            end_minute: (minute + 1) % (24 * 60),
+        };
+        assert!(replace_window_open(Some(around), now));
+
+        let after = TimeWindow {
+            start_minute: (minute + 1) % (24 * 60),
+            end_minute: (minute + 2) % (24 * 60),
+        };
+        assert!(!replace_window_open(Some(after), now));
+    }
+}
@@ -0,0 +1,155 @@
+//! Daily cap on automatic replacements, for staged rollouts.
+//!
+//! After a change to the encode settings it is safer to let only a handful
+//! of originals be replaced until the results have been spot-checked. With
+//! `gates.max_replacements_per_day` set, encodes that pass every gate once
+//! the cap is reached are held as awaiting approval instead of replacing
+//! their original, and stay that way until approved or rejected through the
+//! API or the dashboard.
+//!
+//! The window is a rolling 24 hours. It is seeded from the job store on
+//! startup, so restarting the daemon does not reset it.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::jobs::{Job, JobStage, JobStatus};
+
+/// Length of the rolling window in milliseconds.
+pub const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Replacements made in the last 24 hours, against a limit.
+#[derive(Debug, Default)]
+pub struct ReplacementBudget {
+    /// Replacements allowed per window; 0 means unlimited
+    max_per_day: u32,
+    /// Times of the replacements still inside the window, oldest first
+    recent: Mutex<VecDeque<i64>>,
+}
+
+impl ReplacementBudget {
+    /// Creates a budget with nothing spent yet.
+    pub fn new(max_per_day: u32) -> Self {
+        Self {
+            max_per_day,
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Creates a budget charged with the jobs in `jobs` that completed
+    /// within the window ending at `now_ms`.
+    pub fn with_history(max_per_day: u32, jobs: &[Job], now_ms: i64) -> Self {
+        let mut times: Vec<i64> = jobs
+            .iter()
+            .filter(|job| job.stage == JobStage::Complete && job.status == JobStatus::Success)
+            .map(|job| job.updated_at)
+            .filter(|&at| at > now_ms - DAY_MS)
+            .collect();
+        times.sort_unstable();
+        Self {
+            max_per_day,
+            recent: Mutex::new(times.into()),
+        }
+    }
+
+    /// Returns true if a limit is configured.
+    pub fn is_limited(&self) -> bool {
+        self.max_per_day > 0
+    }
+
+    /// Returns true if a replacement at `now_ms` would stay within the
+    /// limit. Nothing is spent until it is [recorded](Self::record).
+    pub fn has_room(&self, now_ms: i64) -> bool {
+        self.max_per_day == 0 || self.lock(now_ms).len() < self.max_per_day as usize
+    }
+
+    /// Counts a replacement that was made, whether or not the limit allowed
+    /// it, as for an approved one.
+    pub fn record(&self, now_ms: i64) {
+        self.lock(now_ms).push_back(now_ms);
+    }
+
+    /// Replacements made within the window ending at `now_ms`.
+    pub fn used(&self, now_ms: i64) -> u32 {
+        self.lock(now_ms).len() as u32
+    }
+
+    /// Locks the history after dropping entries older than the window.
+    fn lock(&self, now_ms: i64) -> std::sync::MutexGuard<'_, VecDeque<i64>> {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        while recent.front().is_some_and(|&at| at <= now_ms - DAY_MS) {
+            recent.pop_front();
+        }
+        recent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::{FormatInfo, ProbeResult};
+    use crate::jobs::create_job;
+    use crate::scan::ScanCandidate;
+    use std::path::{Path, PathBuf};
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_limit_refills_after_a_day() {
+        let budget = ReplacementBudget::new(2);
+        assert!(budget.has_room(0));
+        budget.record(0);
+        // Checking spends nothing
+        assert!(budget.has_room(1_000));
+        assert!(budget.has_room(1_000));
+        budget.record(1_000);
+        assert!(!budget.has_room(2_000));
+        assert_eq!(budget.used(2_000), 2);
+
+        // The first replacement ages out of the window
+        assert!(budget.has_room(DAY_MS + 1));
+        budget.record(DAY_MS + 1);
+        assert!(!budget.has_room(DAY_MS + 2));
+
+        let unlimited = ReplacementBudget::new(0);
+        assert!(!unlimited.is_limited());
+        (0..10).for_each(|i| unlimited.record(i));
+        assert!(unlimited.has_room(10));
+    }
+
+    #[test]
+    fn test_history_counts_recent_completions() {
+        let now = 10 * DAY_MS;
+        let job = |stage: JobStage, status: JobStatus, updated_at: i64| {
+            let candidate = ScanCandidate {
+                path: PathBuf::from("/media/film.mkv"),
+                size_bytes: 1000,
+                modified_time: UNIX_EPOCH,
+                root: PathBuf::from("/media"),
+            };
+            let probe = ProbeResult {
+                video_streams: vec![],
+                audio_streams: vec![],
+                subtitle_streams: vec![],
+                font_attachments: 0,
+                format: FormatInfo {
+                    duration_secs: 60.0,
+                    size_bytes: 1000,
+                },
+            };
+            let mut job = create_job(&candidate, probe, Default::default(), Path::new("/tmp"));
+            job.stage = stage;
+            job.status = status;
+            job.updated_at = updated_at;
+            job
+        };
+        let jobs = vec![
+            job(JobStage::Complete, JobStatus::Success, now - 1_000),
+            job(JobStage::Complete, JobStatus::Success, now - DAY_MS - 1),
+            job(JobStage::Replacing, JobStatus::Failed, now - 1_000),
+        ];
+
+        let budget = ReplacementBudget::with_history(1, &jobs, now);
+        assert_eq!(budget.used(now), 1);
+        assert!(!budget.has_room(now));
+    }
+}
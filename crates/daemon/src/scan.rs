@@ -3,11 +3,22 @@
 //! This module provides functionality to recursively scan configured library roots
 //! for video files, filtering by extension and skip markers.
 
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
 use std::time::SystemTime;
 
-/// Video file extensions supported by the scanner (case-insensitive matching).
-pub const VIDEO_EXTENSIONS: &[&str] = &[".mkv", ".mp4", ".avi", ".mov", ".m4v", ".ts", ".m2ts"];
+use crate::classify::season_key;
+use crate::config::ScanOrder;
+
+/// Default video file extensions recognized by the scanner (case-insensitive matching).
+///
+/// Mirrors the default of `scan.video_extensions`, which overrides it at runtime.
+pub const VIDEO_EXTENSIONS: &[&str] = &[
+    ".mkv", ".mp4", ".avi", ".mov", ".m4v", ".ts", ".m2ts", ".webm", ".wmv", ".mpg", ".mpeg",
+    ".vob",
+];
 
 /// A candidate video file discovered during library scanning.
 #[derive(Debug, Clone)]
@@ -18,6 +29,8 @@ pub struct ScanCandidate {
     pub size_bytes: u64,
     /// Last modified time of the file.
     pub modified_time: SystemTime,
+    /// Library root the file was discovered under.
+    pub root: PathBuf,
 }
 
 /// Constructs the skip marker path for a given video file.
@@ -35,15 +48,137 @@ pub fn has_skip_marker(video_path: &Path) -> bool {
     skip_marker_path(video_path).exists()
 }
 
-/// Checks if a file has a video extension (case-insensitive).
+/// Constructs the force marker path for a given video file.
+///
+/// A file with an `.av1force` marker is encoded even if it is already AV1
+/// or fails the size gates. For example: `/media/movie.mkv` ->
+/// `/media/movie.mkv.av1force`
+pub fn force_marker_path(video_path: &Path) -> PathBuf {
+    let mut marker_path = video_path.as_os_str().to_owned();
+    marker_path.push(".av1force");
+    PathBuf::from(marker_path)
+}
+
+/// Checks if a force marker exists for the given video file.
+pub fn has_force_marker(video_path: &Path) -> bool {
+    force_marker_path(video_path).exists()
+}
+
+/// Returns true if the scanner leaves `video_path` alone: it has a skip
+/// marker and no force marker overriding it.
+pub fn is_marked_skipped(video_path: &Path) -> bool {
+    has_skip_marker(video_path) && !has_force_marker(video_path)
+}
+
+/// Checks if a file has one of the default video extensions (case-insensitive).
 pub fn is_video_file(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| {
-            let ext_lower = format!(".{}", ext.to_lowercase());
-            VIDEO_EXTENSIONS.contains(&ext_lower.as_str())
-        })
-        .unwrap_or(false)
+    has_video_extension(path, VIDEO_EXTENSIONS)
+}
+
+/// Checks if a file's extension is in `extensions` (case-insensitive).
+///
+/// Entries may be given with or without the leading dot.
+pub fn has_video_extension<S: AsRef<str>>(path: &Path, extensions: &[S]) -> bool {
+    let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+        return false;
+    };
+    extensions.iter().any(|candidate| {
+        let candidate = candidate.as_ref();
+        candidate
+            .strip_prefix('.')
+            .unwrap_or(candidate)
+            .eq_ignore_ascii_case(ext)
+    })
+}
+
+/// The physical file behind a path, the same for every path that reaches it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum FileIdentity {
+    /// Device and inode number; volume serial and file index on Windows
+    Inode(u64, u64),
+    /// Canonical path, where inodes are not available
+    Path(PathBuf),
+}
+
+/// Identifies the file at `path`, following symlinks.
+///
+/// Falls back to the path as given if the file cannot be read.
+fn file_identity(path: &Path) -> FileIdentity {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let Ok(metadata) = fs::metadata(path) {
+            return FileIdentity::Inode(metadata.dev(), metadata.ino());
+        }
+    }
+    #[cfg(windows)]
+    if let Some(info) = windows_file_info(path) {
+        let index = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+        return FileIdentity::Inode(u64::from(info.dwVolumeSerialNumber), index);
+    }
+    FileIdentity::Path(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+}
+
+/// Number of hard links to the file at `path`.
+///
+/// Reads as 1 where link counts are not available or the file cannot be read.
+pub fn hard_link_count(path: &Path) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let Ok(metadata) = fs::metadata(path) {
+            return metadata.nlink();
+        }
+    }
+    #[cfg(windows)]
+    if let Some(info) = windows_file_info(path) {
+        return u64::from(info.nNumberOfLinks);
+    }
+    #[cfg(not(any(unix, windows)))]
+    let _ = path;
+    1
+}
+
+/// Reads the volume, file index, and link count of the file at `path`.
+///
+/// The stable `MetadataExt` on Windows exposes none of them.
+#[cfg(windows)]
+fn windows_file_info(
+    path: &Path,
+) -> Option<windows_sys::Win32::Storage::FileSystem::BY_HANDLE_FILE_INFORMATION> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, FILE_FLAG_BACKUP_SEMANTICS,
+    };
+
+    // Reading file information needs no access rights, so this works on
+    // files other processes hold open
+    let file = fs::OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+        .ok()?;
+    // SAFETY: the handle is open for the duration of the call, and all
+    // zeroes is a valid value of the plain-data struct it fills in
+    unsafe {
+        let mut info = std::mem::zeroed();
+        let ok = GetFileInformationByHandle(file.as_raw_handle() as _, &mut info);
+        (ok != 0).then_some(info)
+    }
+}
+
+/// Drops candidates that are the same physical file as an earlier one.
+///
+/// Overlapping library roots, symlinked roots, bind mounts, and hardlinks
+/// all make one file show up under several paths. The first occurrence is
+/// kept, so the earlier library root wins.
+pub fn dedup_candidates(candidates: Vec<ScanCandidate>) -> Vec<ScanCandidate> {
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|candidate| seen.insert(file_identity(&candidate.path)))
+        .collect()
 }
 
 /// Scans the given library roots for video files.
@@ -53,11 +188,24 @@ pub fn is_video_file(path: &Path) -> bool {
 /// - Skips hidden directories (names starting with `.`)
 /// - Filters files by video extensions (case-insensitive)
 /// - Excludes files with existing `.av1skip` markers
+/// - Reports each physical file once, however many roots reach it
 /// - Captures file size and modified time for stability checking
 pub fn scan_libraries(roots: &[PathBuf]) -> Vec<ScanCandidate> {
+    scan_libraries_with_count(roots, VIDEO_EXTENSIONS).0
+}
+
+/// Same as [`scan_libraries`] with a custom extension list, also returning
+/// the number of files walked.
+///
+/// The count includes every regular file visited, not just video candidates.
+pub fn scan_libraries_with_count<S: AsRef<str>>(
+    roots: &[PathBuf],
+    extensions: &[S],
+) -> (Vec<ScanCandidate>, u64) {
     use walkdir::WalkDir;
 
     let mut candidates = Vec::new();
+    let mut files_walked = 0;
 
     for root in roots {
         if !root.exists() {
@@ -84,14 +232,15 @@ pub fn scan_libraries(roots: &[PathBuf]) -> Vec<ScanCandidate> {
             if !entry.file_type().is_file() {
                 continue;
             }
+            files_walked += 1;
 
             // Check if it's a video file
-            if !is_video_file(path) {
+            if !has_video_extension(path, extensions) {
                 continue;
             }
 
-            // Skip files with existing skip markers
-            if has_skip_marker(path) {
+            // Skip files with existing skip markers, unless forced
+            if is_marked_skipped(path) {
                 continue;
             }
 
@@ -104,12 +253,293 @@ pub fn scan_libraries(roots: &[PathBuf]) -> Vec<ScanCandidate> {
                     path: path.to_path_buf(),
                     size_bytes,
                     modified_time,
+                    root: root.clone(),
                 });
             }
         }
     }
 
-    candidates
+    (dedup_candidates(candidates), files_walked)
+}
+
+/// Same as [`scan_libraries_with_count`], walking directories on up to
+/// `threads` threads.
+///
+/// Walking is dominated by `stat` latency on network mounts, so several
+/// directories are read at once from a shared queue. A `threads` of 0 uses
+/// one thread per CPU and 1 falls back to the single-threaded walk.
+///
+/// Directories finish in no particular order, so candidates are returned
+/// sorted by root (in `roots` order) and then by path, keeping the output
+/// stable between runs.
+pub fn scan_libraries_parallel<S: AsRef<str> + Sync>(
+    roots: &[PathBuf],
+    extensions: &[S],
+    threads: usize,
+) -> (Vec<ScanCandidate>, u64) {
+    let threads = if threads == 0 { num_cpus::get() } else { threads };
+    if threads <= 1 {
+        return scan_libraries_with_count(roots, extensions);
+    }
+
+    let queue = WalkQueue::new(
+        roots
+            .iter()
+            .enumerate()
+            .filter(|(_, root)| root.is_dir())
+            .map(|(index, root)| (index, root.clone()))
+            .collect(),
+    );
+
+    let results: Vec<(Vec<(usize, ScanCandidate)>, u64)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| scope.spawn(|| walk_worker(&queue, roots, extensions)))
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap_or_default())
+            .collect()
+    });
+
+    let mut files_walked = 0;
+    let mut found = Vec::new();
+    for (candidates, files) in results {
+        files_walked += files;
+        found.extend(candidates);
+    }
+    found.sort_by(|(a_root, a), (b_root, b)| a_root.cmp(b_root).then_with(|| a.path.cmp(&b.path)));
+
+    (dedup_candidates(found.into_iter().map(|(_, c)| c).collect()), files_walked)
+}
+
+/// Directories waiting to be read, shared by the walk threads.
+///
+/// `active` counts directories being read; the walk is finished once the
+/// queue is empty and nothing is active, since only an active read can add
+/// more work.
+struct WalkQueue {
+    state: Mutex<WalkState>,
+    ready: Condvar,
+}
+
+struct WalkState {
+    pending: Vec<(usize, PathBuf)>,
+    active: usize,
+}
+
+impl WalkQueue {
+    fn new(pending: Vec<(usize, PathBuf)>) -> Self {
+        Self {
+            state: Mutex::new(WalkState { pending, active: 0 }),
+            ready: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a directory is available, or returns `None` when the
+    /// walk is done.
+    fn next(&self) -> Option<(usize, PathBuf)> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(dir) = state.pending.pop() {
+                state.active += 1;
+                return Some(dir);
+            }
+            if state.active == 0 {
+                return None;
+            }
+            state = self.ready.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Queues the subdirectories found in one directory and marks it done.
+    fn finish(&self, subdirs: Vec<(usize, PathBuf)>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.pending.extend(subdirs);
+        state.active -= 1;
+        self.ready.notify_all();
+    }
+}
+
+fn walk_worker<S: AsRef<str>>(
+    queue: &WalkQueue,
+    roots: &[PathBuf],
+    extensions: &[S],
+) -> (Vec<(usize, ScanCandidate)>, u64) {
+    let mut candidates = Vec::new();
+    let mut files_walked = 0;
+
+    while let Some((root_index, dir)) = queue.next() {
+        let mut subdirs = Vec::new();
+        let entries = fs::read_dir(&dir).into_iter().flatten().filter_map(|e| e.ok());
+
+        for entry in entries {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+
+            // Same rules as the single-threaded walk: hidden directories are
+            // pruned and symlinks are not followed
+            if file_type.is_dir() {
+                let hidden = entry.file_name().to_str().is_some_and(|n| n.starts_with('.'));
+                if !hidden {
+                    subdirs.push((root_index, path));
+                }
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+            files_walked += 1;
+
+            if !has_video_extension(&path, extensions) || is_marked_skipped(&path) {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata() {
+                candidates.push((
+                    root_index,
+                    ScanCandidate {
+                        size_bytes: metadata.len(),
+                        modified_time: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                        path,
+                        root: roots[root_index].clone(),
+                    },
+                ));
+            }
+        }
+
+        queue.finish(subdirs);
+    }
+
+    (candidates, files_walked)
+}
+
+/// Builds a candidate for a single file named explicitly rather than found by a walk.
+///
+/// The root is the first library root containing the file. Skip markers are
+/// not consulted; the caller decides whether a marked file should be retried.
+///
+/// # Errors
+/// Returns an error if the file is outside every library root, its metadata
+/// cannot be read, or it is not a regular file.
+pub fn candidate_for_path(path: &Path, roots: &[PathBuf]) -> std::io::Result<ScanCandidate> {
+    let root = roots
+        .iter()
+        .find(|root| path.starts_with(root))
+        .filter(|_| in_library_roots(path, roots))
+        .cloned()
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not inside a library root", path.display()),
+            )
+        })?;
+
+    let metadata = std::fs::metadata(path)?;
+    if !metadata.is_file() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is not a regular file", path.display()),
+        ));
+    }
+
+    Ok(ScanCandidate {
+        path: path.to_path_buf(),
+        size_bytes: metadata.len(),
+        modified_time: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        root,
+    })
+}
+
+/// Checks that `path` lies inside one of the library roots.
+///
+/// Paths with `..` components are never inside, so a path cannot climb out
+/// of a root it names.
+pub fn in_library_roots(path: &Path, roots: &[PathBuf]) -> bool {
+    !path
+        .components()
+        .any(|component| component == std::path::Component::ParentDir)
+        && roots.iter().any(|root| path.starts_with(root))
+}
+
+/// Orders scan candidates according to the configured [`ScanOrder`].
+///
+/// `scan_libraries` returns candidates root by root, so without reordering the
+/// first library is always drained before the next one gets any attention.
+/// All sorts are stable, keeping walk order among equal keys.
+pub fn order_candidates(mut candidates: Vec<ScanCandidate>, order: ScanOrder) -> Vec<ScanCandidate> {
+    match order {
+        ScanOrder::Walk => candidates,
+        ScanOrder::RoundRobin => round_robin_by_root(candidates),
+        ScanOrder::NewestFirst => {
+            candidates.sort_by_key(|c| std::cmp::Reverse(c.modified_time));
+            candidates
+        }
+        ScanOrder::LargestFirst => {
+            candidates.sort_by_key(|c| std::cmp::Reverse(c.size_bytes));
+            candidates
+        }
+        ScanOrder::SmallestFirst => {
+            candidates.sort_by_key(|c| c.size_bytes);
+            candidates
+        }
+    }
+}
+
+/// Interleaves candidates from each library root, taking one from each in turn.
+fn round_robin_by_root(candidates: Vec<ScanCandidate>) -> Vec<ScanCandidate> {
+    let total = candidates.len();
+    let mut queues: Vec<VecDeque<ScanCandidate>> = Vec::new();
+    let mut root_index: HashMap<PathBuf, usize> = HashMap::new();
+
+    for candidate in candidates {
+        let idx = *root_index.entry(candidate.root.clone()).or_insert_with(|| {
+            queues.push(Default::default());
+            queues.len() - 1
+        });
+        queues[idx].push_back(candidate);
+    }
+
+    let mut ordered = Vec::with_capacity(total);
+    while ordered.len() < total {
+        for queue in queues.iter_mut() {
+            if let Some(candidate) = queue.pop_front() {
+                ordered.push(candidate);
+            }
+        }
+    }
+    ordered
+}
+
+/// Reorders candidates so all episodes of a season are contiguous.
+///
+/// Each season is placed at the position of its first discovered episode,
+/// with episodes sorted by path so they queue in episode order. Movies keep
+/// their original relative order.
+pub fn group_by_season(candidates: Vec<ScanCandidate>) -> Vec<ScanCandidate> {
+    let mut groups: Vec<Vec<ScanCandidate>> = Vec::new();
+    let mut group_index: HashMap<String, usize> = HashMap::new();
+
+    for candidate in candidates {
+        match season_key(&candidate.path) {
+            Some(key) => {
+                let idx = *group_index.entry(key).or_insert_with(|| {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                });
+                groups[idx].push(candidate);
+            }
+            None => groups.push(vec![candidate]),
+        }
+    }
+
+    groups
+        .into_iter()
+        .flat_map(|mut group| {
+            group.sort_by(|a, b| a.path.cmp(&b.path));
+            group
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -128,7 +558,12 @@ mod tests {
         assert!(VIDEO_EXTENSIONS.contains(&".m4v"));
         assert!(VIDEO_EXTENSIONS.contains(&".ts"));
         assert!(VIDEO_EXTENSIONS.contains(&".m2ts"));
-        assert_eq!(VIDEO_EXTENSIONS.len(), 7);
+        assert!(VIDEO_EXTENSIONS.contains(&".webm"));
+        assert!(VIDEO_EXTENSIONS.contains(&".wmv"));
+        assert!(VIDEO_EXTENSIONS.contains(&".mpg"));
+        assert!(VIDEO_EXTENSIONS.contains(&".mpeg"));
+        assert!(VIDEO_EXTENSIONS.contains(&".vob"));
+        assert_eq!(VIDEO_EXTENSIONS.len(), 12);
     }
 
     #[test]
@@ -140,6 +575,236 @@ mod tests {
         assert!(!is_video_file(Path::new("/media/movie.txt")));
         assert!(!is_video_file(Path::new("/media/movie.jpg")));
         assert!(!is_video_file(Path::new("/media/movie"))); // no extension
+        assert!(is_video_file(Path::new("/media/movie.WebM")));
+        assert!(is_video_file(Path::new("/media/VIDEO_TS/VTS_01_1.VOB")));
+    }
+
+    #[test]
+    fn test_default_extensions_match_config_default() {
+        let config = crate::config::ScanConfig::default();
+        assert_eq!(config.video_extensions, VIDEO_EXTENSIONS);
+    }
+
+    #[test]
+    fn test_has_video_extension_custom_list() {
+        let extensions = vec!["mkv".to_string(), ".FLV".to_string()];
+        assert!(has_video_extension(Path::new("/media/a.MKV"), &extensions));
+        assert!(has_video_extension(Path::new("/media/a.flv"), &extensions));
+        assert!(!has_video_extension(Path::new("/media/a.mp4"), &extensions));
+        assert!(!has_video_extension(Path::new("/media/mkv"), &extensions));
+    }
+
+    #[test]
+    fn test_scan_respects_custom_extensions() {
+        let temp_dir = TempDir::new().unwrap();
+        File::create(temp_dir.path().join("a.mkv")).unwrap();
+        File::create(temp_dir.path().join("b.flv")).unwrap();
+
+        let roots = [temp_dir.path().to_path_buf()];
+        let (candidates, files_walked) = scan_libraries_with_count(&roots, &["flv"]);
+        assert_eq!(files_walked, 2);
+        assert_eq!(candidates.len(), 1);
+        assert!(candidates[0].path.ends_with("b.flv"));
+    }
+
+    fn make_candidate(root: &str, name: &str, size_bytes: u64, mtime_secs: u64) -> ScanCandidate {
+        ScanCandidate {
+            path: PathBuf::from(root).join(name),
+            size_bytes,
+            modified_time: SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(mtime_secs),
+            root: PathBuf::from(root),
+        }
+    }
+
+    fn names(candidates: &[ScanCandidate]) -> Vec<String> {
+        candidates
+            .iter()
+            .map(|c| c.path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_order_candidates_round_robin() {
+        let candidates = vec![
+            make_candidate("/a", "a1.mkv", 1, 0),
+            make_candidate("/a", "a2.mkv", 1, 0),
+            make_candidate("/a", "a3.mkv", 1, 0),
+            make_candidate("/b", "b1.mkv", 1, 0),
+        ];
+
+        let ordered = order_candidates(candidates, ScanOrder::RoundRobin);
+        assert_eq!(names(&ordered), vec!["a1.mkv", "b1.mkv", "a2.mkv", "a3.mkv"]);
+    }
+
+    #[test]
+    fn test_order_candidates_by_mtime_and_size() {
+        let candidates = vec![
+            make_candidate("/a", "old_big.mkv", 300, 10),
+            make_candidate("/a", "new_small.mkv", 100, 30),
+            make_candidate("/b", "mid.mkv", 200, 20),
+        ];
+
+        let newest = order_candidates(candidates.clone(), ScanOrder::NewestFirst);
+        assert_eq!(names(&newest), vec!["new_small.mkv", "mid.mkv", "old_big.mkv"]);
+
+        let largest = order_candidates(candidates.clone(), ScanOrder::LargestFirst);
+        assert_eq!(names(&largest), vec!["old_big.mkv", "mid.mkv", "new_small.mkv"]);
+
+        let smallest = order_candidates(candidates.clone(), ScanOrder::SmallestFirst);
+        assert_eq!(names(&smallest), vec!["new_small.mkv", "mid.mkv", "old_big.mkv"]);
+
+        let walk = order_candidates(candidates, ScanOrder::Walk);
+        assert_eq!(names(&walk), vec!["old_big.mkv", "new_small.mkv", "mid.mkv"]);
+    }
+
+    #[test]
+    fn test_group_by_season_makes_seasons_contiguous() {
+        let make = |p: &str| ScanCandidate {
+            path: PathBuf::from(p),
+            size_bytes: 1,
+            modified_time: SystemTime::UNIX_EPOCH,
+            root: PathBuf::from("/"),
+        };
+        let candidates = vec![
+            make("/tv/Show.S01E02.mkv"),
+            make("/movies/Movie.mkv"),
+            make("/tv/Other.S01E01.mkv"),
+            make("/tv/Show.S01E01.mkv"),
+        ];
+
+        let paths: Vec<PathBuf> = group_by_season(candidates)
+            .into_iter()
+            .map(|c| c.path)
+            .collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/tv/Show.S01E01.mkv"),
+                PathBuf::from("/tv/Show.S01E02.mkv"),
+                PathBuf::from("/movies/Movie.mkv"),
+                PathBuf::from("/tv/Other.S01E01.mkv"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_libraries_with_count_counts_all_files() {
+        use std::fs::File;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        File::create(temp_dir.path().join("film.mkv")).unwrap();
+        File::create(temp_dir.path().join("film.nfo")).unwrap();
+        File::create(temp_dir.path().join("poster.jpg")).unwrap();
+
+        let (candidates, files_walked) =
+            scan_libraries_with_count(&[temp_dir.path().to_path_buf()], VIDEO_EXTENSIONS);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(files_walked, 3);
+    }
+
+    #[test]
+    fn test_parallel_scan_matches_serial_scan() {
+        let temp = TempDir::new().unwrap();
+        let movies = temp.path().join("movies");
+        let tv = temp.path().join("tv");
+        for dir in ["a/b/c", "d", ".hidden/e"] {
+            fs::create_dir_all(movies.join(dir)).unwrap();
+        }
+        fs::create_dir_all(tv.join("Show/Season 1")).unwrap();
+        for file in ["a/1.mkv", "a/b/2.mp4", "a/b/c/3.mkv", "d/4.mkv", "d/notes.txt", ".hidden/e/5.mkv"] {
+            File::create(movies.join(file)).unwrap();
+        }
+        File::create(tv.join("Show/Season 1/e1.mkv")).unwrap();
+        File::create(tv.join("Show/Season 1/e2.mkv")).unwrap();
+        File::create(skip_marker_path(&tv.join("Show/Season 1/e2.mkv"))).unwrap();
+
+        let roots = vec![tv.clone(), movies.clone(), temp.path().join("missing")];
+        let (serial, serial_walked) = scan_libraries_with_count(&roots, VIDEO_EXTENSIONS);
+        let (parallel, parallel_walked) = scan_libraries_parallel(&roots, VIDEO_EXTENSIONS, 4);
+
+        let mut serial_paths: Vec<_> = serial.iter().map(|c| c.path.clone()).collect();
+        serial_paths.sort();
+        let mut parallel_paths: Vec<_> = parallel.iter().map(|c| c.path.clone()).collect();
+        parallel_paths.sort();
+        assert_eq!(parallel_paths, serial_paths);
+        assert_eq!(parallel_walked, serial_walked);
+        assert_eq!(parallel.len(), 5);
+
+        // Sorted by root order, then path
+        assert!(parallel[0].path.starts_with(&tv));
+        assert_eq!(parallel[0].root, tv);
+        assert!(parallel[1].path.ends_with("a/1.mkv"));
+        assert!(parallel[4].path.ends_with("d/4.mkv"));
+    }
+
+    #[test]
+    fn test_overlapping_roots_report_each_file_once() {
+        let temp = TempDir::new().unwrap();
+        let media = temp.path().join("media");
+        fs::create_dir_all(media.join("tv/Show")).unwrap();
+        File::create(media.join("film.mkv")).unwrap();
+        File::create(media.join("tv/Show/e1.mkv")).unwrap();
+        fs::hard_link(media.join("film.mkv"), media.join("tv/film-link.mkv")).unwrap();
+
+        let roots = vec![media.join("tv"), media.clone(), media.clone()];
+        for threads in [1, 4] {
+            let (candidates, _) = scan_libraries_parallel(&roots, VIDEO_EXTENSIONS, threads);
+            let mut names = names(&candidates);
+            names.sort();
+            assert_eq!(names, vec!["e1.mkv", "film-link.mkv"]);
+            // The first root to reach a file claims it
+            assert!(candidates.iter().all(|c| c.root == media.join("tv")));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_root_is_not_scanned_twice() {
+        let temp = TempDir::new().unwrap();
+        let media = temp.path().join("media");
+        fs::create_dir_all(&media).unwrap();
+        File::create(media.join("film.mkv")).unwrap();
+        std::os::unix::fs::symlink(&media, temp.path().join("alias")).unwrap();
+
+        let roots = vec![media.clone(), temp.path().join("alias")];
+        let (candidates, files_walked) = scan_libraries_with_count(&roots, VIDEO_EXTENSIONS);
+        assert_eq!(files_walked, 2);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].path, media.join("film.mkv"));
+    }
+
+    #[test]
+    fn test_in_library_roots() {
+        let roots = vec![PathBuf::from("/media/movies"), PathBuf::from("/media/tv")];
+        assert!(in_library_roots(Path::new("/media/tv/Show/ep.mkv"), &roots));
+        assert!(!in_library_roots(Path::new("/etc/passwd"), &roots));
+        assert!(!in_library_roots(Path::new("/media/movies-old/film.mkv"), &roots));
+        assert!(!in_library_roots(Path::new("/media/tv/../../etc/passwd"), &roots));
+        assert!(!in_library_roots(Path::new("/media/tv/ep.mkv"), &[]));
+    }
+
+    #[test]
+    fn test_candidate_for_path_picks_library_root() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("movies");
+        fs::create_dir_all(root.join("Film (2020)")).unwrap();
+        let video = root.join("Film (2020)/film.mkv");
+        fs::write(&video, b"data").unwrap();
+
+        let candidate = candidate_for_path(&video, &[temp.path().join("tv"), root.clone()]).unwrap();
+        assert_eq!(candidate.root, root);
+        assert_eq!(candidate.size_bytes, 4);
+
+        // Files outside every root are refused, not given a root of their own
+        assert!(candidate_for_path(&video, &[]).is_err());
+        assert!(candidate_for_path(&video, &[temp.path().join("tv")]).is_err());
+        let climbing = root.join("../movies/Film (2020)/film.mkv");
+        assert!(candidate_for_path(&climbing, std::slice::from_ref(&root)).is_err());
+
+        assert!(candidate_for_path(&root, std::slice::from_ref(&root)).is_err());
+        assert!(candidate_for_path(&root.join("missing.mkv"), &[root]).is_err());
     }
 
     #[test]
@@ -156,11 +821,33 @@ mod tests {
         assert_eq!(marker, PathBuf::from("/media/movies/film.2024.mkv.av1skip"));
     }
 
+    #[test]
+    fn test_force_marker_overrides_skip_marker() {
+        let temp = TempDir::new().unwrap();
+        let skipped = temp.path().join("skipped.mkv");
+        let forced = temp.path().join("forced.mkv");
+        for video in [&skipped, &forced] {
+            File::create(video).unwrap();
+            File::create(skip_marker_path(video)).unwrap();
+        }
+        File::create(force_marker_path(&forced)).unwrap();
+        assert_eq!(force_marker_path(&forced), temp.path().join("forced.mkv.av1force"));
+
+        let roots = vec![temp.path().to_path_buf()];
+        let (serial, _) = scan_libraries_with_count(&roots, VIDEO_EXTENSIONS);
+        let (parallel, _) = scan_libraries_parallel(&roots, VIDEO_EXTENSIONS, 2);
+        for candidates in [serial, parallel] {
+            let paths: Vec<_> = candidates.into_iter().map(|c| c.path).collect();
+            assert_eq!(paths, vec![forced.clone()]);
+        }
+    }
+
     // **Feature: av1-super-daemon, Property 9: Scanner Video Extension Filtering**
     // **Validates: Requirements 11.3**
     //
     // *For any* file path, the scanner SHALL include it as a candidate if and only if
-    // its extension (case-insensitive) is one of: `.mkv`, `.mp4`, `.avi`, `.mov`, `.m4v`, `.ts`, `.m2ts`.
+    // its extension (case-insensitive) is one of: `.mkv`, `.mp4`, `.avi`, `.mov`, `.m4v`, `.ts`, `.m2ts`,
+    // `.webm`, `.wmv`, `.mpg`, `.mpeg`, `.vob`.
     proptest! {
         #![proptest_config(ProptestConfig::with_cases(100))]
 
@@ -176,6 +863,9 @@ mod tests {
                 Just("m4v"), Just("M4V"), Just("M4v"),
                 Just("ts"), Just("TS"), Just("Ts"),
                 Just("m2ts"), Just("M2TS"), Just("M2Ts"),
+                Just("webm"), Just("WEBM"), Just("wmv"), Just("WMV"),
+                Just("mpg"), Just("MPG"), Just("mpeg"), Just("Mpeg"),
+                Just("vob"), Just("VOB"),
                 // Non-video extensions (should fail)
                 Just("txt"), Just("jpg"), Just("png"), Just("pdf"),
                 Just("doc"), Just("exe"), Just("zip"), Just("srt"),
@@ -189,6 +879,7 @@ mod tests {
             let expected_video = matches!(
                 ext_lower.as_str(),
                 "mkv" | "mp4" | "avi" | "mov" | "m4v" | "ts" | "m2ts"
+                    | "webm" | "wmv" | "mpg" | "mpeg" | "vob"
             );
 
             prop_assert_eq!(
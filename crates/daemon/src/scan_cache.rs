@@ -0,0 +1,293 @@
+//! Incremental library scanning backed by a directory mtime cache.
+//!
+//! A full walk stats every file in every library on every cycle, which gets
+//! expensive on libraries with hundreds of thousands of files. The cache keeps
+//! each directory's modified time together with the candidates found in it.
+//! When a directory's mtime is unchanged its cached candidates are reused
+//! without reading the directory, while subdirectories are still visited
+//! because their changes do not bump the parent's mtime.
+//!
+//! Editing a file in place does not change its directory's mtime, so a full
+//! re-walk is forced periodically as a safety net.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::scan::{dedup_candidates, has_video_extension, is_marked_skipped, ScanCandidate};
+
+/// Cached state of a single scanned directory.
+#[derive(Debug, Clone)]
+struct CachedDir {
+    /// Directory mtime observed when it was last read.
+    modified_time: SystemTime,
+    /// Video candidates found directly in this directory.
+    candidates: Vec<ScanCandidate>,
+    /// Non-hidden subdirectories found directly in this directory.
+    subdirs: Vec<PathBuf>,
+    /// Number of regular files in this directory, videos or not.
+    file_count: u64,
+}
+
+/// Directory cache carried between scan cycles.
+#[derive(Debug, Default)]
+pub struct ScanCache {
+    dirs: HashMap<PathBuf, CachedDir>,
+    last_full_scan: Option<SystemTime>,
+}
+
+/// Counters describing how much work an incremental scan did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IncrementalScanStats {
+    /// Whether this pass was a full re-walk.
+    pub full_rescan: bool,
+    /// Directories whose contents were read from disk.
+    pub dirs_read: usize,
+    /// Directories served from the cache.
+    pub dirs_cached: usize,
+    /// Regular files seen, including those in cached directories.
+    pub files_walked: u64,
+}
+
+impl ScanCache {
+    /// Creates an empty cache; the first scan is always a full walk.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of directories currently cached.
+    pub fn len(&self) -> usize {
+        self.dirs.len()
+    }
+
+    /// Returns true if nothing has been cached yet.
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty()
+    }
+
+    /// Returns true if a full re-walk is due.
+    ///
+    /// A `full_rescan_interval` of zero disables the cache entirely.
+    fn full_rescan_due(&self, now: SystemTime, full_rescan_interval: Duration) -> bool {
+        if full_rescan_interval.is_zero() {
+            return true;
+        }
+        match self.last_full_scan {
+            None => true,
+            Some(last) => now
+                .duration_since(last)
+                .map(|elapsed| elapsed >= full_rescan_interval)
+                .unwrap_or(true),
+        }
+    }
+}
+
+/// Scans the library roots, reusing cached results for unchanged directories.
+///
+/// Produces the same candidates as [`crate::scan::scan_libraries`]: hidden
+/// directories are skipped, only video extensions are considered, and files
+/// with `.av1skip` markers are excluded. Directories that disappeared since
+/// the last pass are dropped from the cache.
+///
+/// # Arguments
+/// * `roots` - Library root directories to scan
+/// * `extensions` - File extensions treated as video
+/// * `cache` - Cache carried over from the previous scan
+/// * `full_rescan_interval` - How often to ignore the cache and re-walk everything
+pub fn scan_libraries_incremental<S: AsRef<str>>(
+    roots: &[PathBuf],
+    extensions: &[S],
+    cache: &mut ScanCache,
+    full_rescan_interval: Duration,
+) -> (Vec<ScanCandidate>, IncrementalScanStats) {
+    let now = SystemTime::now();
+    let full = cache.full_rescan_due(now, full_rescan_interval);
+
+    let mut stats = IncrementalScanStats {
+        full_rescan: full,
+        ..Default::default()
+    };
+    let mut next_dirs = HashMap::new();
+    let mut candidates = Vec::new();
+
+    for root in roots {
+        if !root.is_dir() {
+            continue;
+        }
+        let mut pending = vec![root.clone()];
+        while let Some(dir) = pending.pop() {
+            let previous = if full { None } else { cache.dirs.get(&dir) };
+            let Some(entry) = visit_dir(&dir, root, extensions, previous, &mut stats) else {
+                continue;
+            };
+            stats.files_walked += entry.file_count;
+            candidates.extend(entry.candidates.iter().cloned());
+            // Reverse so subdirectories are visited in listing order
+            pending.extend(entry.subdirs.iter().rev().cloned());
+            next_dirs.insert(dir, entry);
+        }
+    }
+
+    cache.dirs = next_dirs;
+    if full {
+        cache.last_full_scan = Some(now);
+    }
+
+    (dedup_candidates(candidates), stats)
+}
+
+/// Returns the cached entry for `dir` if still fresh, otherwise reads it.
+fn visit_dir<S: AsRef<str>>(
+    dir: &Path,
+    root: &Path,
+    extensions: &[S],
+    previous: Option<&CachedDir>,
+    stats: &mut IncrementalScanStats,
+) -> Option<CachedDir> {
+    let modified_time = fs::metadata(dir).and_then(|m| m.modified()).ok()?;
+
+    if let Some(cached) = previous {
+        if cached.modified_time == modified_time {
+            stats.dirs_cached += 1;
+            return Some(cached.clone());
+        }
+    }
+
+    stats.dirs_read += 1;
+    let mut entries: Vec<_> = fs::read_dir(dir).ok()?.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    let mut candidates = Vec::new();
+    let mut subdirs = Vec::new();
+    let mut file_count = 0;
+
+    for entry in entries {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+
+        if file_type.is_dir() {
+            let hidden = entry.file_name().to_str().is_some_and(|n| n.starts_with('.'));
+            if !hidden {
+                subdirs.push(path);
+            }
+            continue;
+        }
+
+        if !file_type.is_file() {
+            continue;
+        }
+        file_count += 1;
+        if !has_video_extension(&path, extensions) || is_marked_skipped(&path) {
+            continue;
+        }
+
+        if let Ok(metadata) = entry.metadata() {
+            candidates.push(ScanCandidate {
+                size_bytes: metadata.len(),
+                modified_time: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                path,
+                root: root.to_path_buf(),
+            });
+        }
+    }
+
+    Some(CachedDir {
+        modified_time,
+        candidates,
+        subdirs,
+        file_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::VIDEO_EXTENSIONS;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn sorted_paths(candidates: &[ScanCandidate]) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = candidates.iter().map(|c| c.path.clone()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_incremental_matches_full_scan() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().to_path_buf();
+        fs::create_dir_all(root.join("movies/a")).unwrap();
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+        File::create(root.join("movies/a/film.mkv")).unwrap();
+        File::create(root.join("movies/notes.txt")).unwrap();
+        File::create(root.join(".hidden/secret.mkv")).unwrap();
+        File::create(root.join("top.mp4")).unwrap();
+
+        let roots = vec![root];
+        let mut cache = ScanCache::new();
+        let (candidates, stats) = scan_libraries_incremental(&roots, VIDEO_EXTENSIONS, &mut cache, HOUR);
+
+        assert!(stats.full_rescan);
+        assert_eq!(
+            sorted_paths(&candidates),
+            sorted_paths(&crate::scan::scan_libraries(&roots))
+        );
+    }
+
+    #[test]
+    fn test_unchanged_dirs_served_from_cache() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().to_path_buf();
+        fs::create_dir_all(root.join("show")).unwrap();
+        File::create(root.join("show/ep1.mkv")).unwrap();
+
+        let roots = vec![root.clone()];
+        let mut cache = ScanCache::new();
+        scan_libraries_incremental(&roots, VIDEO_EXTENSIONS, &mut cache, HOUR);
+
+        let (candidates, stats) = scan_libraries_incremental(&roots, VIDEO_EXTENSIONS, &mut cache, HOUR);
+        assert!(!stats.full_rescan);
+        assert_eq!(stats.dirs_read, 0);
+        assert_eq!(stats.dirs_cached, 2);
+        assert_eq!(stats.files_walked, 1);
+        assert_eq!(candidates.len(), 1);
+    }
+
+    #[test]
+    fn test_changed_dir_is_reread() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().to_path_buf();
+        fs::create_dir_all(root.join("show")).unwrap();
+        File::create(root.join("show/ep1.mkv")).unwrap();
+
+        let roots = vec![root.clone()];
+        let mut cache = ScanCache::new();
+        scan_libraries_incremental(&roots, VIDEO_EXTENSIONS, &mut cache, HOUR);
+
+        // Force a distinct mtime even on filesystems with coarse timestamps
+        File::create(root.join("show/ep2.mkv")).unwrap();
+        let dir = File::open(root.join("show")).unwrap();
+        dir.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+
+        let (candidates, stats) = scan_libraries_incremental(&roots, VIDEO_EXTENSIONS, &mut cache, HOUR);
+        assert_eq!(stats.dirs_read, 1);
+        assert_eq!(candidates.len(), 2);
+    }
+
+    #[test]
+    fn test_zero_interval_always_full() {
+        let temp = TempDir::new().unwrap();
+        let roots = vec![temp.path().to_path_buf()];
+        let mut cache = ScanCache::new();
+
+        scan_libraries_incremental(&roots, VIDEO_EXTENSIONS, &mut cache, Duration::ZERO);
+        let (_, stats) = scan_libraries_incremental(&roots, VIDEO_EXTENSIONS, &mut cache, Duration::ZERO);
+        assert!(stats.full_rescan);
+        assert_eq!(stats.dirs_cached, 0);
+    }
+}
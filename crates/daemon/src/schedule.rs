@@ -0,0 +1,224 @@
+//! Estimated start and finish times for queued jobs.
+//!
+//! How long a job takes is estimated from its media duration and how many
+//! wall-clock seconds finished jobs of the same kind needed per second of
+//! media (from their recorded stage times, see [`crate::timings`]). Running
+//! jobs keep what is left of their estimate; queued jobs are then handed,
+//! in queue order, to whichever slot frees up first.
+//!
+//! The estimates assume dispatch is never paused. Thermal or temp quota
+//! pauses push everything back, and the next refresh shows that.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::jobs::{queue_order, Job, JobKind, JobStatus};
+
+/// Seconds per second of media for full encodes until one has finished.
+pub const DEFAULT_ENCODE_SECS_PER_MEDIA_SEC: f64 = 2.0;
+
+/// Seconds per second of media for remuxes until one has finished.
+pub const DEFAULT_REMUX_SECS_PER_MEDIA_SEC: f64 = 0.02;
+
+/// Wall-clock seconds needed per second of media, by job kind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessingRates {
+    pub encode: f64,
+    pub remux: f64,
+}
+
+impl Default for ProcessingRates {
+    fn default() -> Self {
+        Self {
+            encode: DEFAULT_ENCODE_SECS_PER_MEDIA_SEC,
+            remux: DEFAULT_REMUX_SECS_PER_MEDIA_SEC,
+        }
+    }
+}
+
+/// Seconds `job` spent past the queue, in the encode and the stages after it.
+fn processing_secs(job: &Job) -> f64 {
+    job.stage_secs
+        .iter()
+        .filter(|(stage, _)| stage.as_str() != "queued")
+        .map(|(_, secs)| secs)
+        .sum()
+}
+
+impl ProcessingRates {
+    /// Median rates of the successful jobs in `jobs`, falling back to the
+    /// defaults for a kind with none.
+    pub fn from_history(jobs: &[Job]) -> Self {
+        let median = |kind: JobKind| {
+            let mut rates: Vec<f64> = jobs
+                .iter()
+                .filter(|job| job.kind == kind && job.status == JobStatus::Success)
+                .filter(|job| job.probe_result.format.duration_secs > 0.0)
+                .map(|job| processing_secs(job) / job.probe_result.format.duration_secs)
+                .filter(|rate| *rate > 0.0)
+                .collect();
+            rates.sort_by(f64::total_cmp);
+            rates.get(rates.len() / 2).copied()
+        };
+        let defaults = Self::default();
+        Self {
+            encode: median(JobKind::Encode).unwrap_or(defaults.encode),
+            remux: median(JobKind::Remux).unwrap_or(defaults.remux),
+        }
+    }
+
+    /// Estimated seconds from start to finish for `job`.
+    pub fn estimate_secs(&self, job: &Job) -> f64 {
+        let rate = match job.kind {
+            JobKind::Encode => self.encode,
+            JobKind::Remux => self.remux,
+        };
+        job.probe_result.format.duration_secs.max(0.0) * rate
+    }
+}
+
+/// When a job is expected to start and finish, in Unix milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobSchedule {
+    pub est_start_unix_ms: i64,
+    pub est_finish_unix_ms: i64,
+}
+
+/// Estimates start and finish times for the running and queued jobs in
+/// `jobs`, with `slots` jobs running at once.
+///
+/// Queued jobs start in the order they were created. Jobs that are done or
+/// held for approval get no estimate.
+///
+/// # Returns
+/// Estimates keyed by job ID
+pub fn schedule_jobs(jobs: &[Job], slots: usize, now_unix_ms: i64) -> HashMap<String, JobSchedule> {
+    let rates = ProcessingRates::from_history(jobs);
+    let estimate_ms = |job: &Job| (rates.estimate_secs(job) * 1000.0) as i64;
+    let mut schedule = HashMap::new();
+
+    // Slots are represented by the time they free up
+    let mut free_at = BinaryHeap::new();
+    for job in jobs.iter().filter(|job| job.status == JobStatus::Running) {
+        let elapsed_ms =
+            (processing_secs(job) * 1000.0) as i64 + (now_unix_ms - job.updated_at).max(0);
+        let start = now_unix_ms - elapsed_ms;
+        let finish = (start + estimate_ms(job)).max(now_unix_ms);
+        schedule.insert(
+            job.id.clone(),
+            JobSchedule {
+                est_start_unix_ms: start,
+                est_finish_unix_ms: finish,
+            },
+        );
+        free_at.push(Reverse(finish));
+    }
+    while free_at.len() < slots.max(1) {
+        free_at.push(Reverse(now_unix_ms));
+    }
+
+    let mut queued: Vec<&Job> = jobs.iter().filter(|job| job.is_queued()).collect();
+    queued.sort_by(|a, b| queue_order(a, b));
+    for job in queued {
+        let Some(Reverse(slot_free)) = free_at.pop() else {
+            break;
+        };
+        let start = slot_free.max(now_unix_ms);
+        let finish = start + estimate_ms(job);
+        schedule.insert(
+            job.id.clone(),
+            JobSchedule {
+                est_start_unix_ms: start,
+                est_finish_unix_ms: finish,
+            },
+        );
+        free_at.push(Reverse(finish));
+    }
+    schedule
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::{FormatInfo, ProbeResult};
+    use crate::jobs::{create_job, JobStage};
+    use crate::scan::ScanCandidate;
+    use std::path::{Path, PathBuf};
+    use std::time::UNIX_EPOCH;
+
+    fn job(name: &str, duration_secs: f64, created_at: i64) -> Job {
+        let candidate = ScanCandidate {
+            path: PathBuf::from(format!("/media/{}.mkv", name)),
+            size_bytes: 1000,
+            modified_time: UNIX_EPOCH,
+            root: PathBuf::from("/media"),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs,
+                size_bytes: 1000,
+            },
+        };
+        let mut job = create_job(&candidate, probe, Default::default(), Path::new("/tmp"));
+        job.id = name.to_string();
+        job.created_at = created_at;
+        job.updated_at = created_at;
+        job
+    }
+
+    #[test]
+    fn test_rates_learn_from_successful_jobs() {
+        assert_eq!(ProcessingRates::from_history(&[]), ProcessingRates::default());
+
+        let mut done = job("done", 100.0, 0);
+        done.status = JobStatus::Success;
+        done.stage_secs.insert("queued".to_string(), 5000.0);
+        done.stage_secs.insert("encoding".to_string(), 280.0);
+        done.stage_secs.insert("replacing".to_string(), 20.0);
+        let mut failed = job("failed", 100.0, 0);
+        failed.status = JobStatus::Failed;
+        failed.stage_secs.insert("encoding".to_string(), 5.0);
+
+        let rates = ProcessingRates::from_history(&[done, failed]);
+        assert_eq!(rates.encode, 3.0);
+        assert_eq!(rates.remux, DEFAULT_REMUX_SECS_PER_MEDIA_SEC);
+    }
+
+    #[test]
+    fn test_queued_jobs_fill_the_first_free_slot() {
+        let now = 1_000_000;
+        // Running for 100 s of an estimated 200 s (100 s of media at 2 s/s)
+        let mut running = job("running", 100.0, 0);
+        running.status = JobStatus::Running;
+        running.stage = JobStage::Encoding;
+        running.stage_secs.insert("encoding".to_string(), 60.0);
+        running.updated_at = now - 40_000;
+        let first = job("first", 50.0, 10);
+        let second = job("second", 10.0, 20);
+        let third = job("third", 10.0, 30);
+        let mut held = job("held", 10.0, 5);
+        held.stage = JobStage::AwaitingApproval;
+
+        let jobs = [third, running, held, second, first];
+        let schedule = schedule_jobs(&jobs, 2, now);
+        assert_eq!(schedule.len(), 4);
+        assert_eq!(
+            schedule["running"],
+            JobSchedule {
+                est_start_unix_ms: now - 100_000,
+                est_finish_unix_ms: now + 100_000,
+            }
+        );
+        // The idle slot takes the first queued job right away
+        assert_eq!(schedule["first"].est_start_unix_ms, now);
+        assert_eq!(schedule["first"].est_finish_unix_ms, now + 100_000);
+        // Both slots free up at the same time; ties go to either
+        assert_eq!(schedule["second"].est_start_unix_ms, now + 100_000);
+        assert_eq!(schedule["third"].est_start_unix_ms, now + 100_000);
+        assert_eq!(schedule["third"].est_finish_unix_ms, now + 120_000);
+    }
+}
@@ -2,12 +2,164 @@
 //!
 //! This module provides functionality to create `.av1skip` marker files
 //! and optional `.why.txt` sidecar files explaining why a file was skipped.
+//! A structured `.why.json` sidecar carrying a machine-readable reason code
+//! can be written alongside the human-readable one. An `.av1force` marker
+//! overrides the skip marker and the gates for one file.
 
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::scan::skip_marker_path;
+use crate::scan::{force_marker_path, skip_marker_path};
+
+/// Machine-readable code identifying why a file was skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipCode {
+    /// ffprobe could not read the file.
+    ProbeFailed,
+    /// The file has no video streams.
+    NoVideoStreams,
+    /// The file is smaller than `gates.min_bytes`.
+    BelowMinSize,
+    /// The first video stream is already AV1.
+    AlreadyAv1,
+    /// The encoded output was not small enough to keep.
+    SizeGateRejected,
+    /// The encode was held for approval and rejected.
+    RejectedOnReview,
+    /// The file has other hard links and `gates.hardlinks` is `skip`.
+    Hardlinked,
+    /// The source bitrate is already low for its codec and resolution.
+    LowBitrate,
+    /// The source's pixel format is not encoded under `[pixel_format]`.
+    UnsupportedPixelFormat,
+    /// The file has several video streams and `gates.multi_video` is
+    /// `skip`, or its main one is not the first.
+    MultipleVideoStreams,
+}
+
+impl SkipCode {
+    /// Stable string form of the code, as written to sidecars and metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipCode::ProbeFailed => "probe_failed",
+            SkipCode::NoVideoStreams => "no_video_streams",
+            SkipCode::BelowMinSize => "below_min_size",
+            SkipCode::AlreadyAv1 => "already_av1",
+            SkipCode::SizeGateRejected => "size_gate_rejected",
+            SkipCode::RejectedOnReview => "rejected_on_review",
+            SkipCode::Hardlinked => "hardlinked",
+            SkipCode::LowBitrate => "low_bitrate",
+            SkipCode::UnsupportedPixelFormat => "unsupported_pixel_format",
+            SkipCode::MultipleVideoStreams => "multiple_video_streams",
+        }
+    }
+
+    /// Parses the string form produced by [`SkipCode::as_str`].
+    pub fn from_code(code: &str) -> Option<Self> {
+        [
+            SkipCode::ProbeFailed,
+            SkipCode::NoVideoStreams,
+            SkipCode::BelowMinSize,
+            SkipCode::AlreadyAv1,
+            SkipCode::SizeGateRejected,
+            SkipCode::RejectedOnReview,
+            SkipCode::Hardlinked,
+            SkipCode::LowBitrate,
+            SkipCode::UnsupportedPixelFormat,
+            SkipCode::MultipleVideoStreams,
+        ]
+        .into_iter()
+        .find(|c| c.as_str() == code)
+    }
+
+    /// Name of the gate that produced this code.
+    pub fn gate(&self) -> &'static str {
+        match self {
+            SkipCode::ProbeFailed => "probe",
+            SkipCode::NoVideoStreams => "video_streams",
+            SkipCode::BelowMinSize => "min_bytes",
+            SkipCode::AlreadyAv1 => "codec",
+            SkipCode::SizeGateRejected => "size_gate",
+            SkipCode::RejectedOnReview => "approval",
+            SkipCode::Hardlinked => "hardlinks",
+            SkipCode::LowBitrate => "bitrate",
+            SkipCode::UnsupportedPixelFormat => "pixel_format",
+            SkipCode::MultipleVideoStreams => "video_streams",
+        }
+    }
+}
+
+impl std::fmt::Display for SkipCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Why a file was skipped: a reason code, a human-readable message, and the
+/// thresholds that were compared, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipReason {
+    pub code: SkipCode,
+    pub message: String,
+    pub thresholds: BTreeMap<String, f64>,
+}
+
+impl SkipReason {
+    /// Creates a skip reason with no thresholds attached.
+    pub fn new(code: SkipCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            thresholds: BTreeMap::new(),
+        }
+    }
+
+    /// Records a threshold or measured value that led to the skip.
+    pub fn with_threshold(mut self, name: &str, value: f64) -> Self {
+        self.thresholds.insert(name.to_string(), value);
+        self
+    }
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Contents of a `.why.json` sidecar.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WhySidecar {
+    pub code: SkipCode,
+    pub gate: String,
+    /// Same text as the `.why.txt` sidecar.
+    pub message: String,
+    pub thresholds: BTreeMap<String, f64>,
+    pub timestamp_unix_ms: i64,
+    pub daemon_version: String,
+}
+
+impl WhySidecar {
+    /// Builds the sidecar record for a skip reason, stamped with the current time.
+    pub fn from_reason(reason: &SkipReason) -> Self {
+        Self {
+            code: reason.code,
+            gate: reason.code.gate().to_string(),
+            message: reason.message.clone(),
+            thresholds: reason.thresholds.clone(),
+            timestamp_unix_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(0),
+            daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
 
 /// Constructs the why sidecar path for a given video file.
 ///
@@ -19,6 +171,15 @@ pub fn why_sidecar_path(video_path: &Path) -> std::path::PathBuf {
     std::path::PathBuf::from(sidecar_path)
 }
 
+/// Constructs the structured why sidecar path for a given video file.
+///
+/// For example: `/media/movie.mkv` -> `/media/movie.mkv.why.json`
+pub fn why_json_path(video_path: &Path) -> std::path::PathBuf {
+    let mut sidecar_path = video_path.as_os_str().to_owned();
+    sidecar_path.push(".why.json");
+    std::path::PathBuf::from(sidecar_path)
+}
+
 /// Creates an empty `.av1skip` marker file adjacent to the video file.
 ///
 /// This marker indicates that the video should not be processed by the daemon.
@@ -43,6 +204,25 @@ pub fn write_skip_marker(video_path: &Path) -> io::Result<()> {
     Ok(())
 }
 
+/// Creates a `.av1skip` marker holding the skip reason code.
+///
+/// The scanner only checks that the marker exists; the code lets coverage
+/// tracking tell files that are already AV1 from ones that were rejected.
+pub fn write_skip_marker_with_code(video_path: &Path, code: SkipCode) -> io::Result<()> {
+    let mut file = File::create(skip_marker_path(video_path))?;
+    writeln!(file, "{}", code.as_str())?;
+    Ok(())
+}
+
+/// Reads the reason code from a `.av1skip` marker.
+///
+/// Returns `None` if there is no marker, or for markers written without a
+/// code (older daemons, or created by hand).
+pub fn read_skip_marker_code(video_path: &Path) -> Option<SkipCode> {
+    let content = std::fs::read_to_string(skip_marker_path(video_path)).ok()?;
+    SkipCode::from_code(content.trim())
+}
+
 /// Creates a `.why.txt` sidecar file with the skip reason.
 ///
 /// This sidecar explains why a file was skipped, useful for debugging
@@ -74,6 +254,76 @@ pub fn write_why_sidecar(video_path: &Path, reason: &str, enabled: bool) -> io::
     Ok(())
 }
 
+/// Creates a `.why.json` sidecar describing the skip for external tooling.
+///
+/// The record carries the reason code, the gate that fired, any thresholds
+/// involved, a timestamp, and the daemon version. The human-readable message
+/// is included verbatim so the JSON can stand in for `.why.txt`.
+///
+/// # Arguments
+///
+/// * `video_path` - Path to the video file being skipped
+/// * `reason` - Structured skip reason
+/// * `enabled` - Whether to actually write the sidecar (from config)
+pub fn write_why_json(video_path: &Path, reason: &SkipReason, enabled: bool) -> io::Result<()> {
+    if !enabled {
+        return Ok(());
+    }
+
+    let record = WhySidecar::from_reason(reason);
+    let json = serde_json::to_string_pretty(&record)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    std::fs::write(why_json_path(video_path), json)
+}
+
+/// Removes the `.av1skip` marker for a video file so it will be scanned again.
+///
+/// # Returns
+///
+/// * `Ok(true)` if a marker was removed, `Ok(false)` if there was none
+pub fn remove_skip_marker(video_path: &Path) -> io::Result<bool> {
+    remove_if_exists(&skip_marker_path(video_path))
+}
+
+/// Creates an `.av1force` marker so the next evaluation of a video file
+/// encodes it whatever its codec and size.
+pub fn write_force_marker(video_path: &Path) -> io::Result<()> {
+    File::create(force_marker_path(video_path))?;
+    Ok(())
+}
+
+/// Removes the `.av1force` marker for a video file once a job was queued.
+///
+/// # Returns
+///
+/// * `Ok(true)` if a marker was removed, `Ok(false)` if there was none
+pub fn remove_force_marker(video_path: &Path) -> io::Result<bool> {
+    remove_if_exists(&force_marker_path(video_path))
+}
+
+/// Removes the `.why.txt` and `.why.json` sidecars for a video file.
+///
+/// # Returns
+///
+/// * `Ok(n)` with the number of sidecars that existed and were removed
+pub fn remove_why_sidecars(video_path: &Path) -> io::Result<usize> {
+    let mut removed = 0;
+    for sidecar in [why_sidecar_path(video_path), why_json_path(video_path)] {
+        if remove_if_exists(&sidecar)? {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<bool> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,6 +360,22 @@ mod tests {
         assert!(content.is_empty(), "Skip marker should be empty");
     }
 
+    #[test]
+    fn test_skip_marker_code_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let video_path = temp_dir.path().join("test_video.mkv");
+        File::create(&video_path).unwrap();
+
+        assert_eq!(read_skip_marker_code(&video_path), None);
+        write_skip_marker(&video_path).unwrap();
+        assert_eq!(read_skip_marker_code(&video_path), None);
+
+        write_skip_marker_with_code(&video_path, SkipCode::AlreadyAv1).unwrap();
+        assert_eq!(read_skip_marker_code(&video_path), Some(SkipCode::AlreadyAv1));
+        assert_eq!(SkipCode::from_code("size_gate_rejected"), Some(SkipCode::SizeGateRejected));
+        assert_eq!(SkipCode::from_code("bogus"), None);
+    }
+
     #[test]
     fn test_write_why_sidecar_when_enabled() {
         let temp_dir = TempDir::new().unwrap();
@@ -181,4 +447,55 @@ mod tests {
         let content = fs::read_to_string(&sidecar_path).unwrap();
         assert!(content.contains(reason));
     }
+
+    #[test]
+    fn test_write_why_json_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let video_path = temp_dir.path().join("test_video.mkv");
+        File::create(&video_path).unwrap();
+
+        let reason = SkipReason::new(SkipCode::BelowMinSize, "below minimum size (10 bytes < 20 bytes)")
+            .with_threshold("file_size", 10.0)
+            .with_threshold("min_bytes", 20.0);
+        write_why_json(&video_path, &reason, true).unwrap();
+
+        let content = fs::read_to_string(why_json_path(&video_path)).unwrap();
+        assert!(content.contains("\"below_min_size\""));
+
+        let record: WhySidecar = serde_json::from_str(&content).unwrap();
+        assert_eq!(record.code, SkipCode::BelowMinSize);
+        assert_eq!(record.gate, "min_bytes");
+        assert_eq!(record.message, reason.message);
+        assert_eq!(record.thresholds.get("min_bytes"), Some(&20.0));
+        assert_eq!(record.daemon_version, env!("CARGO_PKG_VERSION"));
+        assert!(record.timestamp_unix_ms > 0);
+    }
+
+    #[test]
+    fn test_write_why_json_when_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let video_path = temp_dir.path().join("test_video.mkv");
+
+        let reason = SkipReason::new(SkipCode::AlreadyAv1, "already AV1");
+        write_why_json(&video_path, &reason, false).unwrap();
+
+        assert!(!why_json_path(&video_path).exists());
+    }
+
+    #[test]
+    fn test_remove_skip_marker_and_sidecars() {
+        let temp_dir = TempDir::new().unwrap();
+        let video_path = temp_dir.path().join("test_video.mkv");
+
+        assert!(!remove_skip_marker(&video_path).unwrap());
+        assert_eq!(remove_why_sidecars(&video_path).unwrap(), 0);
+
+        write_skip_marker(&video_path).unwrap();
+        write_why_sidecar(&video_path, "already AV1", true).unwrap();
+
+        assert!(remove_skip_marker(&video_path).unwrap());
+        assert_eq!(remove_why_sidecars(&video_path).unwrap(), 1);
+        assert!(!skip_marker_path(&video_path).exists());
+        assert!(!why_sidecar_path(&video_path).exists());
+    }
 }
@@ -0,0 +1,97 @@
+//! Persisted skip-reason counters.
+//!
+//! Skipped files get an `.av1skip` marker and are never scanned again, so
+//! per-cycle scan statistics cannot show how much of a library was rejected
+//! over time. This module keeps lifetime counts per [`SkipCode`] in a small
+//! JSON file so they survive daemon restarts.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::metrics::SharedMetrics;
+use crate::skip_marker::SkipCode;
+
+/// Lifetime skip counts keyed by reason code.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SkipStats {
+    pub counts: BTreeMap<String, u64>,
+}
+
+impl SkipStats {
+    /// Loads counts from `path`, returning empty counts if the file does not exist.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes counts to `path` via a temporary file so a crash never leaves
+    /// a truncated store behind.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Total number of skips across all reasons.
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+}
+
+/// Increments the lifetime counter for `code` in the shared metrics.
+pub async fn record_skip(metrics: &SharedMetrics, code: SkipCode) {
+    let mut m = metrics.write().await;
+    *m.skip_totals.entry(code.as_str().to_string()).or_insert(0) += 1;
+}
+
+/// Writes the lifetime skip counters held in the shared metrics to `path`.
+pub async fn persist_skip_stats(metrics: &SharedMetrics, path: &Path) -> io::Result<()> {
+    let stats = SkipStats {
+        counts: metrics.read().await.skip_totals.clone(),
+    };
+    stats.save(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::new_shared_metrics;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_missing_file_is_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let stats = SkipStats::load(&temp_dir.path().join("missing.json")).unwrap();
+        assert_eq!(stats, SkipStats::default());
+        assert_eq!(stats.total(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_record_and_persist_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("state/skip_stats.json");
+        let metrics = new_shared_metrics();
+
+        record_skip(&metrics, SkipCode::AlreadyAv1).await;
+        record_skip(&metrics, SkipCode::AlreadyAv1).await;
+        record_skip(&metrics, SkipCode::SizeGateRejected).await;
+        persist_skip_stats(&metrics, &path).await.unwrap();
+
+        let stats = SkipStats::load(&path).unwrap();
+        assert_eq!(stats.counts.get("already_av1"), Some(&2));
+        assert_eq!(stats.counts.get("size_gate_rejected"), Some(&1));
+        assert_eq!(stats.total(), 3);
+    }
+}
@@ -0,0 +1,197 @@
+//! Local staging copies of sources on slow or remote storage.
+//!
+//! Av1an reads its input many times over: once per scene detection pass
+//! and again for every chunk. On an NFS or SMB share that is slow, and a
+//! share that is mounted read-only or drops out mid-encode fails the job.
+//! For libraries with `stage_locally`, the source is first copied to local
+//! scratch and every read of the encode goes to that copy.
+//!
+//! The copy is verified twice. The data is hashed as it is copied and the
+//! copy hashed again once written, which catches a bad write. The source is
+//! then read a second time and hashed on its own, which catches a bad read:
+//! a share returning different bytes for the same file fails the job
+//! instead of encoding a corrupted read. Before the result goes back to the
+//! library, the source is hashed once more, so an original that changed
+//! during the encode is not replaced; the replacement then also verifies
+//! its copy.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// Size of the reads while copying and hashing
+const CHUNK_BYTES: usize = 1 << 20;
+
+/// A verified local copy of a source, removed when dropped.
+#[derive(Debug)]
+pub struct StagedSource {
+    dir: PathBuf,
+    path: PathBuf,
+    digest: String,
+}
+
+impl StagedSource {
+    /// Where the copy is.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// SHA-256 of the copy, as hex, which the source matched when staged.
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+}
+
+impl Drop for StagedSource {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Copies `source` into its own folder `dir` and verifies the copy against
+/// a second read of `source`.
+///
+/// # Errors
+/// An IO error if the copy fails, or one of kind `InvalidData` if the copy
+/// does not match what was read from the source, or the source reads
+/// differently the second time
+pub fn stage_source(source: &Path, dir: &Path) -> io::Result<StagedSource> {
+    let name = source
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "source has no file name"))?;
+    fs::create_dir_all(dir)?;
+    let mut staged = StagedSource {
+        dir: dir.to_path_buf(),
+        path: dir.join(name),
+        digest: String::new(),
+    };
+    staged.digest = copy_verified(source, &staged.path)?;
+    verify_source(source, &staged.digest)?;
+    Ok(staged)
+}
+
+/// Hashes `source` in a pass of its own and compares it with `digest`.
+///
+/// # Errors
+/// An IO error if `source` cannot be read, or one of kind `InvalidData` if
+/// it does not match
+pub fn verify_source(source: &Path, digest: &str) -> io::Result<()> {
+    if sha256_file(source)? != digest {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{:?} no longer matches its staged copy", source),
+        ));
+    }
+    Ok(())
+}
+
+/// Copies `from` to `to`, hashing the data as it is copied, then hashes
+/// `to` and compares.
+///
+/// This proves the copy holds what was read, not that the read was right;
+/// [`verify_source`] checks that.
+///
+/// # Returns
+/// The SHA-256 of the copied data, as hex
+pub fn copy_verified(from: &Path, to: &Path) -> io::Result<String> {
+    let mut reader = File::open(from)?;
+    let mut writer = File::create(to)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK_BYTES];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        writer.write_all(&buf[..n])?;
+    }
+    writer.sync_all()?;
+    drop(writer);
+
+    let read = hex(hasher.finalize().as_slice());
+    let written = sha256_file(to)?;
+    if read != written {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("copy of {:?} does not match its source", from),
+        ));
+    }
+    Ok(written)
+}
+
+/// SHA-256 of the file at `path`, as hex.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK_BYTES];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex(hasher.finalize().as_slice()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_stage_source_copies_and_cleans_up() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("share").join("movie.mkv");
+        fs::create_dir_all(source.parent().unwrap()).unwrap();
+        fs::write(&source, vec![7u8; CHUNK_BYTES + 5]).unwrap();
+
+        let dir = temp.path().join("scratch").join("job-1");
+        let staged = stage_source(&source, &dir).unwrap();
+        assert_eq!(staged.path(), dir.join("movie.mkv"));
+        assert_eq!(fs::read(staged.path()).unwrap(), fs::read(&source).unwrap());
+
+        assert_eq!(staged.digest(), sha256_file(&source).unwrap());
+        drop(staged);
+        assert!(!dir.exists());
+        assert!(source.exists(), "the source is never touched");
+    }
+
+    #[test]
+    fn test_copy_verified_returns_digest() {
+        let temp = TempDir::new().unwrap();
+        let from = temp.path().join("a");
+        fs::write(&from, b"abc").unwrap();
+        let digest = copy_verified(&from, &temp.path().join("b")).unwrap();
+        assert_eq!(digest, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(sha256_file(&temp.path().join("b")).unwrap(), digest);
+    }
+
+    #[test]
+    fn test_verify_source_catches_changes() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("movie.mkv");
+        fs::write(&source, b"first read").unwrap();
+        let staged = stage_source(&source, &temp.path().join("job")).unwrap();
+        assert!(verify_source(&source, staged.digest()).is_ok());
+
+        fs::write(&source, b"other read").unwrap();
+        let err = verify_source(&source, staged.digest()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_missing_source_fails() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("job");
+        assert!(stage_source(&temp.path().join("missing.mkv"), &dir).is_err());
+        assert!(!dir.exists());
+    }
+}
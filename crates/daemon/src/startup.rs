@@ -4,11 +4,25 @@
 //! - Software-only encoding assertion (no hardware acceleration)
 //! - Av1an availability check
 //! - FFmpeg version check (requires 8.0+)
+//! - Read/write access to the library roots and the temp and state directories
+//! - Clock skew between this host and the filesystems it writes (warning only)
+//!
+//! [`run_startup_checks`] runs every check and reports all failures together
+//! in a [`StartupReport`], so a misconfigured host is fixed in one pass.
 
 use crate::config::Config;
-use std::process::Command;
+use crate::encode::{EncodeProfile, SvtOverrides};
+use crate::tools::Tool;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
 use thiserror::Error;
 
+/// Largest difference, in seconds, between this host's clock and a
+/// filesystem's timestamps that goes unreported
+pub const MAX_CLOCK_SKEW_SECS: i64 = 60;
+
 /// Forbidden hardware encoder flags that indicate hardware acceleration
 const FORBIDDEN_HW_FLAGS: &[&str] = &[
     "nvenc", "qsv", "vaapi", "cuda", "amf", "vce", "qsvenc",
@@ -26,10 +40,60 @@ pub enum StartupError {
     #[error("Hardware encoding detected: {0}")]
     HardwareEncodingDetected(String),
 
+    #[error("Permission check failed: {0}")]
+    Permission(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
 
+/// Every startup check that failed, in the order the checks ran
+#[derive(Debug, Default)]
+pub struct StartupReport {
+    pub failures: Vec<StartupError>,
+}
+
+impl StartupReport {
+    /// Records the failure of `result`, if it failed.
+    pub fn check(&mut self, result: Result<(), StartupError>) {
+        if let Err(e) = result {
+            self.failures.push(e);
+        }
+    }
+
+    /// Returns true if no check failed.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// `Ok` if no check failed, otherwise the report itself.
+    pub fn into_result(self) -> Result<(), StartupReport> {
+        if self.is_ok() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for StartupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of the startup checks failed:", self.failures.len())?;
+        for failure in &self.failures {
+            write!(f, "\n  - {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StartupReport {}
+
+impl From<StartupError> for StartupReport {
+    fn from(error: StartupError) -> Self {
+        Self { failures: vec![error] }
+    }
+}
+
 /// Check if a string contains any forbidden hardware encoder flags
 ///
 /// Returns the first detected forbidden flag, or None if clean.
@@ -41,26 +105,57 @@ pub fn detect_hardware_flag(s: &str) -> Option<&'static str> {
         .copied()
 }
 
+/// Every encoder and ffmpeg parameter string the configuration produces,
+/// with the setting it comes from
+///
+/// Covers the SVT-AV1 parameters of each encode profile with the `[av1an]`
+/// overrides applied, and the ffmpeg encoder of downmixed audio. Paths are
+/// left out; a library may well live under "/mnt/barracuda".
+pub fn configured_encoder_params(cfg: &Config) -> Vec<(&'static str, String)> {
+    let overrides = SvtOverrides::from_config(&cfg.av1an);
+    vec![
+        (
+            "av1an video params (film)",
+            overrides.apply(EncodeProfile::Film.svt_params()).to_string(),
+        ),
+        (
+            "av1an video params (animation)",
+            overrides.apply(EncodeProfile::Animation.svt_params()).to_string(),
+        ),
+        ("audio.downmix_codec", cfg.audio.downmix_codec.clone()),
+    ]
+}
+
 /// Assert that the configuration does not contain hardware encoding flags
 ///
-/// When `disallow_hardware_encoding` is enabled, this function checks for
-/// forbidden hardware flags in configuration values and returns an error
-/// if any are detected.
+/// When `disallow_hardware_encoding` is enabled, every string from
+/// [`configured_encoder_params`] is checked for forbidden hardware flags,
+/// and the configuration is rejected naming each setting that has one.
 ///
 /// # Requirements
 /// - 3.1: WHEN `disallow_hardware_encoding` is enabled and configuration contains
-///        hardware encoder flags THEN the Daemon SHALL reject the configuration
+///   hardware encoder flags THEN the Daemon SHALL reject the configuration
 /// - 3.2: WHEN the Daemon checks for forbidden hardware flags THEN the Daemon SHALL
-///        detect flags containing nvenc, qsv, vaapi, cuda, amf, vce, or qsvenc
+///   detect flags containing nvenc, qsv, vaapi, cuda, amf, vce, or qsvenc
 pub fn assert_software_only(cfg: &Config) -> Result<(), StartupError> {
     if !cfg.encoder_safety.disallow_hardware_encoding {
         return Ok(());
     }
 
-    // In a real implementation, we would check command-line arguments,
-    // config file paths, or other configuration values for hardware flags.
-    // For now, this function provides the interface and detection logic.
-    Ok(())
+    let found: Vec<String> = configured_encoder_params(cfg)
+        .into_iter()
+        .filter_map(|(setting, value)| {
+            detect_hardware_flag(&value)
+                .map(|flag| format!("'{}' in {} = {:?}", flag, setting, value))
+        })
+        .collect();
+    if found.is_empty() {
+        return Ok(());
+    }
+    Err(StartupError::HardwareEncodingDetected(format!(
+        "{}; hardware encoding is disabled by encoder_safety.disallow_hardware_encoding",
+        found.join(", ")
+    )))
 }
 
 
@@ -92,24 +187,28 @@ pub fn check_args_for_hardware_flags(
 ///
 /// # Requirements
 /// - 4.1: WHEN the daemon starts THEN the Daemon SHALL verify that `av1an --version`
-///        executes successfully
+///   executes successfully
 /// - 4.2: WHEN `av1an --version` fails THEN the Daemon SHALL abort startup with an
-///        error message indicating Av1an is unavailable
+///   error message indicating Av1an is unavailable
 pub fn check_av1an_available() -> Result<(), StartupError> {
-    let output = Command::new("av1an")
+    let program = Tool::Av1an.program();
+    let output = Tool::Av1an
+        .command()
         .arg("--version")
         .output()
         .map_err(|e| {
             StartupError::Av1anUnavailable(format!(
-                "av1an --version failed; is Av1an built and in PATH? Error: {}",
+                "{} --version failed; is Av1an built and in PATH, or tools.av1an set? Error: {}",
+                program.display(),
                 e
             ))
         })?;
 
     if !output.status.success() {
-        return Err(StartupError::Av1anUnavailable(
-            "av1an --version failed; is Av1an built and in PATH?".to_string(),
-        ));
+        return Err(StartupError::Av1anUnavailable(format!(
+            "{} --version failed; is Av1an built and in PATH, or tools.av1an set?",
+            program.display()
+        )));
     }
 
     Ok(())
@@ -123,7 +222,7 @@ pub fn check_av1an_available() -> Result<(), StartupError> {
 ///
 /// # Requirements
 /// - 4.5: WHEN parsing FFmpeg version THEN the Daemon SHALL handle version strings
-///        prefixed with `n` (e.g., `n8.0-...`)
+///   prefixed with `n` (e.g., `n8.0-...`)
 pub fn parse_ffmpeg_version(version_output: &str) -> Option<u32> {
     // Look for "ffmpeg version" followed by the version string
     let version_line = version_output
@@ -135,7 +234,6 @@ pub fn parse_ffmpeg_version(version_output: &str) -> Option<u32> {
         .to_lowercase()
         .split("ffmpeg version")
         .nth(1)?
-        .trim()
         .split_whitespace()
         .next()?
         .to_string();
@@ -145,7 +243,7 @@ pub fn parse_ffmpeg_version(version_output: &str) -> Option<u32> {
 
     // Extract major version (before first '.' or '-')
     let major_str = version_str
-        .split(|c| c == '.' || c == '-')
+        .split(['.', '-'])
         .next()?;
 
     major_str.parse().ok()
@@ -155,21 +253,28 @@ pub fn parse_ffmpeg_version(version_output: &str) -> Option<u32> {
 ///
 /// # Requirements
 /// - 4.3: WHEN the daemon starts THEN the Daemon SHALL verify that FFmpeg version
-///        is 8.0 or newer
+///   is 8.0 or newer
 /// - 4.4: WHEN FFmpeg version is below 8.0 THEN the Daemon SHALL abort startup with
-///        an error message indicating the required version
+///   an error message indicating the required version
 pub fn check_ffmpeg_version_8_or_newer() -> Result<(), StartupError> {
-    let output = Command::new("ffmpeg")
+    let program = Tool::Ffmpeg.program();
+    let output = Tool::Ffmpeg
+        .command()
         .arg("-version")
         .output()
         .map_err(|e| {
-            StartupError::FfmpegVersion(format!("Failed to run ffmpeg -version: {}", e))
+            StartupError::FfmpegVersion(format!(
+                "Failed to run {} -version: {}",
+                program.display(),
+                e
+            ))
         })?;
 
     if !output.status.success() {
-        return Err(StartupError::FfmpegVersion(
-            "ffmpeg -version failed".to_string(),
-        ));
+        return Err(StartupError::FfmpegVersion(format!(
+            "{} -version failed",
+            program.display()
+        )));
     }
 
     let version_output = String::from_utf8_lossy(&output.stdout);
@@ -182,25 +287,179 @@ pub fn check_ffmpeg_version_8_or_newer() -> Result<(), StartupError> {
 
     if major_version < 8 {
         return Err(StartupError::FfmpegVersion(format!(
-            "FFmpeg 8.x required, got: {}",
-            major_version
+            "FFmpeg 8.x required, got: {} from {}; set tools.ffmpeg to a newer build",
+            major_version,
+            program.display()
         )));
     }
 
     Ok(())
 }
 
+/// Checks that `dir` can be listed and, if `hint` is given, written:
+/// a probe file is created in it and deleted again.
+///
+/// `hint` says how to grant write access and is appended to the error.
+fn probe_directory(dir: &Path, what: &str, hint: Option<&str>) -> Result<(), StartupError> {
+    fs::read_dir(dir).map_err(|e| {
+        StartupError::Permission(format!(
+            "{} {} is not readable ({}); check that it is mounted and the daemon's user may list it",
+            what,
+            dir.display(),
+            e
+        ))
+    })?;
+
+    let Some(hint) = hint else {
+        return Ok(());
+    };
+    let probe = dir.join(format!(".av1sd-probe-{}", std::process::id()));
+    fs::write(&probe, b"").map_err(|e| {
+        StartupError::Permission(format!("{} {} is not writable ({}); {}", what, dir.display(), e, hint))
+    })?;
+    fs::remove_file(&probe).map_err(|e| {
+        StartupError::Permission(format!(
+            "{} {} allows creating files but not deleting them ({}); remove {} and {}",
+            what,
+            dir.display(),
+            e,
+            probe.display(),
+            hint
+        ))
+    })
+}
+
+/// Check that the daemon can use every directory it works in
+///
+/// Library roots must be readable, and writable too unless `read_only` is
+/// set, since replacing originals and writing skip markers happen there;
+/// a root that does not exist is only reported, as scans skip it until it
+/// appears. The temp output and job state directories are created if
+/// missing and must be writable. Every directory is checked, whatever
+/// failed before it.
+pub fn check_directory_permissions(cfg: &Config) -> Result<(), StartupReport> {
+    let mut report = StartupReport::default();
+    for root in &cfg.scan.library_roots {
+        if !root.exists() {
+            log_warn!(
+                "Warning: library root {} does not exist; it is skipped until it does",
+                root.display()
+            );
+            continue;
+        }
+        let hint = (!cfg.read_only).then_some(
+            "replacing originals needs write access: remount it read-write, grant the daemon's user write permission, or set read_only = true",
+        );
+        report.check(probe_directory(root, "Library root", hint));
+    }
+
+    let state_dirs = [
+        (&cfg.paths.temp_output_dir, "Temp directory", "paths.temp_output_dir"),
+        (&cfg.paths.job_state_dir, "Job state directory", "paths.job_state_dir"),
+    ];
+    for (dir, what, key) in state_dirs {
+        let hint = format!("grant the daemon's user write permission or point {} elsewhere", key);
+        report.check(
+            fs::create_dir_all(dir)
+                .map_err(|e| {
+                    StartupError::Permission(format!(
+                        "{} {} cannot be created ({}); {}",
+                        what,
+                        dir.display(),
+                        e,
+                        hint
+                    ))
+                })
+                .and_then(|()| probe_directory(dir, what, Some(&hint))),
+        );
+    }
+    report.into_result()
+}
+
+/// Measures how far the timestamps `dir`'s filesystem assigns are from this
+/// host's clock by creating a probe file and comparing its mtime with now.
+///
+/// # Returns
+/// Seconds the filesystem is ahead of this host (negative if behind)
+pub fn measure_clock_skew(dir: &Path) -> Result<i64, std::io::Error> {
+    let probe = dir.join(format!(".av1sd-clock-{}", std::process::id()));
+    let before = SystemTime::now();
+    fs::write(&probe, b"")?;
+    let modified = fs::metadata(&probe).and_then(|m| m.modified());
+    fs::remove_file(&probe)?;
+    let modified = modified?;
+
+    Ok(match modified.duration_since(before) {
+        Ok(ahead) => ahead.as_secs() as i64,
+        Err(behind) => -(behind.duration().as_secs() as i64),
+    })
+}
+
+/// Warning for a skew past [`MAX_CLOCK_SKEW_SECS`], if any
+fn clock_skew_warning(what: &str, dir: &Path, skew_secs: i64) -> Option<String> {
+    if skew_secs.abs() <= MAX_CLOCK_SKEW_SECS {
+        return None;
+    }
+    Some(format!(
+        "Warning: {} {} stamps files {}s {} this host's clock; stability checks, claim leases and backup ages will misbehave until both are synced (e.g. with NTP)",
+        what,
+        dir.display(),
+        skew_secs.abs(),
+        if skew_secs > 0 { "ahead of" } else { "behind" }
+    ))
+}
+
+/// Warn about filesystems whose timestamps disagree with this host's clock
+///
+/// Common on NAS mounts, where the server sets mtimes from its own clock.
+/// Checks every directory the daemon writes: the library roots (unless
+/// `read_only` is set) and the temp and job state directories. Never fails
+/// startup.
+pub fn check_clock_skew(cfg: &Config) {
+    let roots = cfg
+        .scan
+        .library_roots
+        .iter()
+        .filter(|root| !cfg.read_only && root.exists())
+        .map(|root| (root, "Library root"));
+    let dirs = roots.chain([
+        (&cfg.paths.temp_output_dir, "Temp directory"),
+        (&cfg.paths.job_state_dir, "Job state directory"),
+    ]);
+    for (dir, what) in dirs {
+        match measure_clock_skew(dir) {
+            Ok(skew) => {
+                if let Some(warning) = clock_skew_warning(what, dir, skew) {
+                    log_warn!("{}", warning);
+                }
+            }
+            Err(e) => log_warn!("Warning: could not check the clock of {} {}: {}", what, dir.display(), e),
+        }
+    }
+}
+
 /// Run all startup checks in order
 ///
-/// Checks are run in the following order:
+/// Checks are run in the following order, each whatever the earlier ones
+/// found:
 /// 1. Software-only assertion
-/// 2. Av1an availability
-/// 3. FFmpeg version
-pub fn run_startup_checks(cfg: &Config) -> Result<(), StartupError> {
-    assert_software_only(cfg)?;
-    check_av1an_available()?;
-    check_ffmpeg_version_8_or_newer()?;
-    Ok(())
+/// 2. Directory permissions
+/// 3. Clock skew, which only warns and is skipped if a directory is unusable
+/// 4. Av1an availability
+/// 5. FFmpeg version
+///
+/// # Returns
+/// * `Err(StartupReport)` listing every failed check
+pub fn run_startup_checks(cfg: &Config) -> Result<(), StartupReport> {
+    let mut report = StartupReport::default();
+    report.check(assert_software_only(cfg));
+    match check_directory_permissions(cfg) {
+        Ok(()) => check_clock_skew(cfg),
+        Err(dirs) => report.failures.extend(dirs.failures),
+    }
+    report.check(check_av1an_available());
+    report.check(check_ffmpeg_version_8_or_newer());
+    report.into_result()
 }
 
 
@@ -507,4 +766,78 @@ configuration: --enable-gpl"#;
         let args = vec!["-c:v", "h264_nvenc"];
         assert!(check_args_for_hardware_flags(&args, false).is_ok());
     }
+
+    #[test]
+    fn test_directory_permissions() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let library = temp_dir.path().join("library");
+        fs::create_dir(&library).unwrap();
+        let mut cfg = Config::default();
+        cfg.scan.library_roots = vec![library.clone(), temp_dir.path().join("unmounted")];
+        cfg.paths.temp_output_dir = temp_dir.path().join("temp");
+        cfg.paths.job_state_dir = temp_dir.path().join("jobs");
+
+        // Missing state dirs are created; a missing root is only reported
+        check_directory_permissions(&cfg).unwrap();
+        assert!(cfg.paths.job_state_dir.is_dir());
+        assert_eq!(fs::read_dir(&library).unwrap().count(), 0, "probe file left behind");
+
+        // A state dir that cannot be created names the setting to change
+        let blocker = temp_dir.path().join("file");
+        fs::write(&blocker, b"").unwrap();
+        cfg.paths.job_state_dir = blocker.join("jobs");
+        let report = check_directory_permissions(&cfg).unwrap_err();
+        assert_eq!(report.failures.len(), 1);
+        let err = report.to_string();
+        assert!(err.contains("Job state directory"), "{}", err);
+        assert!(err.contains("paths.job_state_dir"), "{}", err);
+    }
+
+    #[test]
+    fn test_assert_software_only_scans_configured_params() {
+        let mut cfg = Config::default();
+        assert!(assert_software_only(&cfg).is_ok());
+
+        cfg.audio.downmix_codec = "aac_qsv".to_string();
+        let err = assert_software_only(&cfg).unwrap_err().to_string();
+        assert!(err.contains("audio.downmix_codec"), "{}", err);
+        assert!(err.contains("qsv"), "{}", err);
+
+        cfg.encoder_safety.disallow_hardware_encoding = false;
+        assert!(assert_software_only(&cfg).is_ok());
+    }
+
+    #[test]
+    fn test_startup_report_lists_every_failure() {
+        let mut report = StartupReport::default();
+        report.check(Ok(()));
+        assert!(report.is_ok());
+
+        report.check(check_args_for_hardware_flags(&["h264_nvenc"], true));
+        report.check(Err(StartupError::Av1anUnavailable("not in PATH".to_string())));
+        report.check(Err(StartupError::FfmpegVersion("FFmpeg 8.x required, got: 7".to_string())));
+        assert_eq!(report.failures.len(), 3);
+
+        let text = report.into_result().unwrap_err().to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "3 of the startup checks failed:");
+        assert!(lines[1].contains("nvenc"), "{}", text);
+        assert!(lines[2].contains("not in PATH"), "{}", text);
+        assert!(lines[3].contains("got: 7"), "{}", text);
+    }
+
+    #[test]
+    fn test_clock_skew() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let skew = measure_clock_skew(temp_dir.path()).unwrap();
+        assert!(skew.abs() <= MAX_CLOCK_SKEW_SECS, "local skew {}", skew);
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+        let dir = Path::new("/mnt/nas");
+        assert_eq!(clock_skew_warning("Library root", dir, MAX_CLOCK_SKEW_SECS), None);
+        let warning = clock_skew_warning("Library root", dir, -300).unwrap();
+        assert!(warning.contains("300s behind"), "{}", warning);
+        let warning = clock_skew_warning("Library root", dir, 3600).unwrap();
+        assert!(warning.contains("3600s ahead of"), "{}", warning);
+    }
 }
@@ -0,0 +1,392 @@
+//! Subtitle tracks kept in encodes, and extraction of image-based
+//! subtitles to sidecar files.
+//!
+//! With `subtitles.keep_languages` set, tracks in other languages are left
+//! out of the encode. Tracks without a language tag are always kept, and so
+//! are forced tracks unless `keep_forced` is turned off, since they carry
+//! the translation of foreign dialogue in the kept languages.
+//!
+//! PGS and VobSub tracks are bitmaps: they add megabytes to the container,
+//! and some players only handle them as external files. Per library
+//! (`[subtitles]`), such tracks can be copied out to sidecars next to the
+//! original before the encode starts, and with `extract_and_drop` left out
+//! of the encode. PGS tracks are written with ffmpeg as `.sup`; VobSub
+//! tracks with `mkvextract` as an `.idx`/`.sub` pair, so only from Matroska
+//! sources.
+//!
+//! Sidecars are named after the original, `{stem}.{lang}.{ext}`, with a
+//! number after the language when a file has several tracks in it. A track
+//! is only dropped from the encode once its sidecar was written. When the
+//! replaced file gets a different name, the sidecars are renamed with it.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::SubtitleConfig;
+use crate::gates::SubtitleStream;
+use crate::tools::Tool;
+
+/// Language used in sidecar names for tracks without a language tag.
+const UNKNOWN_LANGUAGE: &str = "und";
+
+/// Positions of the subtitle tracks `config.keep_languages` leaves out of
+/// the encode.
+pub fn dropped_by_language(config: &SubtitleConfig, subtitles: &[SubtitleStream]) -> Vec<usize> {
+    if config.keep_languages.is_empty() {
+        return Vec::new();
+    }
+    let keep: Vec<String> = config
+        .keep_languages
+        .iter()
+        .map(|language| language.trim().to_lowercase())
+        .collect();
+    subtitles
+        .iter()
+        .enumerate()
+        .filter(|(_, stream)| !(stream.forced && config.keep_forced))
+        .filter(|(_, stream)| {
+            let language = stream.language.as_deref().unwrap_or("").trim().to_lowercase();
+            !language.is_empty() && language != UNKNOWN_LANGUAGE && !keep.contains(&language)
+        })
+        .map(|(track, _)| track)
+        .collect()
+}
+
+/// Sidecar format of an image-based subtitle codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidecarFormat {
+    /// Blu-ray PGS, written as a `.sup` file by ffmpeg
+    Sup,
+    /// DVD VobSub, written as `.idx` plus `.sub` by mkvextract
+    VobSub,
+}
+
+impl SidecarFormat {
+    /// Sidecar format for `codec_name`, or `None` for text subtitles
+    pub fn for_codec(codec_name: &str) -> Option<Self> {
+        match codec_name {
+            "hdmv_pgs_subtitle" | "pgssub" => Some(SidecarFormat::Sup),
+            "dvd_subtitle" | "dvdsub" => Some(SidecarFormat::VobSub),
+            _ => None,
+        }
+    }
+
+    /// Extension of the sidecar file named in the command
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SidecarFormat::Sup => "sup",
+            SidecarFormat::VobSub => "idx",
+        }
+    }
+
+    /// Extensions of every file the sidecar consists of
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            SidecarFormat::Sup => &["sup"],
+            SidecarFormat::VobSub => &["idx", "sub"],
+        }
+    }
+}
+
+/// An image-based subtitle track and the sidecar it goes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtitleSidecar {
+    /// Position among the source's subtitle streams
+    pub track: usize,
+    /// Index among all streams of the source, if ffprobe reported it
+    pub stream_index: Option<u32>,
+    pub format: SidecarFormat,
+    /// Sidecar path; for VobSub the `.idx`, with the `.sub` beside it
+    pub path: PathBuf,
+}
+
+impl SubtitleSidecar {
+    /// Every file the sidecar consists of
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.format
+            .extensions()
+            .iter()
+            .map(|ext| self.path.with_extension(ext))
+            .collect()
+    }
+}
+
+/// Sidecars for the image-based tracks in `subtitles`, named after `media`.
+pub fn plan_sidecars(media: &Path, subtitles: &[SubtitleStream]) -> Vec<SubtitleSidecar> {
+    let stem = media.file_stem().unwrap_or_default().to_string_lossy();
+    let dir = media.parent().unwrap_or(Path::new(""));
+    let language_of = |stream: &SubtitleStream| {
+        stream
+            .language
+            .as_deref()
+            .map(|language| language.trim().to_lowercase())
+            .filter(|language| !language.is_empty())
+            .unwrap_or_else(|| UNKNOWN_LANGUAGE.to_string())
+    };
+
+    let mut sidecars: Vec<SubtitleSidecar> = Vec::new();
+    for (track, stream) in subtitles.iter().enumerate() {
+        let Some(format) = SidecarFormat::for_codec(&stream.codec_name) else {
+            continue;
+        };
+        let language = language_of(stream);
+        let same_language = subtitles[..track]
+            .iter()
+            .filter(|s| SidecarFormat::for_codec(&s.codec_name) == Some(format))
+            .filter(|s| language_of(s) == language)
+            .count();
+        let name = match same_language {
+            0 => format!("{}.{}.{}", stem, language, format.extension()),
+            n => format!("{}.{}.{}.{}", stem, language, n + 1, format.extension()),
+        };
+        sidecars.push(SubtitleSidecar {
+            track,
+            stream_index: stream.stream_index,
+            format,
+            path: dir.join(name),
+        });
+    }
+    sidecars
+}
+
+/// Build the command writing `sidecar` from `input`.
+///
+/// # Returns
+/// `None` for VobSub tracks of non-Matroska sources, or without a stream
+/// index, which mkvextract cannot address
+pub fn build_extract_command(input: &Path, sidecar: &SubtitleSidecar) -> Option<Command> {
+    match sidecar.format {
+        SidecarFormat::Sup => {
+            let mut cmd = Tool::Ffmpeg.command();
+            cmd.arg("-hide_banner")
+                .arg("-nostdin")
+                .arg("-loglevel")
+                .arg("error")
+                .arg("-y")
+                .arg("-i")
+                .arg(input)
+                .arg("-map")
+                .arg(format!("0:s:{}", sidecar.track))
+                .arg("-c")
+                .arg("copy")
+                .arg("-f")
+                .arg("sup")
+                .arg(&sidecar.path);
+            Some(cmd)
+        }
+        SidecarFormat::VobSub => {
+            let is_mkv = input
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("mkv"));
+            let stream_index = sidecar.stream_index.filter(|_| is_mkv)?;
+            let mut cmd = Tool::Mkvextract.command();
+            cmd.arg(input)
+                .arg("tracks")
+                .arg(format!("{}:{}", stream_index, sidecar.path.display()));
+            Some(cmd)
+        }
+    }
+}
+
+/// Writes the sidecars of the image-based tracks in `subtitles`, except
+/// those at the positions in `skip`.
+///
+/// Failures are logged per track and leave no partial sidecar behind.
+///
+/// # Returns
+/// The sidecars written
+pub fn extract_image_subtitles(
+    input: &Path,
+    subtitles: &[SubtitleStream],
+    skip: &[usize],
+) -> Vec<SubtitleSidecar> {
+    let mut written = Vec::new();
+    let sidecars = plan_sidecars(input, subtitles);
+    for sidecar in sidecars.into_iter().filter(|sidecar| !skip.contains(&sidecar.track)) {
+        let Some(mut cmd) = build_extract_command(input, &sidecar) else {
+            log_warn!(
+                "Warning: Cannot extract VobSub track {} of {:?}; only Matroska sources are supported",
+                sidecar.track, input
+            );
+            continue;
+        };
+        let result = cmd.stdin(Stdio::null()).stdout(Stdio::null()).output();
+        match result {
+            Ok(output) if output.status.success() && sidecar.path.exists() => {
+                written.push(sidecar);
+            }
+            Ok(output) => {
+                log_warn!(
+                    "Warning: Failed to extract subtitle track {} of {:?}: {}",
+                    sidecar.track,
+                    input,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                remove_files(&sidecar);
+            }
+            Err(e) => {
+                log_warn!(
+                    "Warning: Failed to run subtitle extraction for {:?}: {}",
+                    input, e
+                );
+                remove_files(&sidecar);
+            }
+        }
+    }
+    written
+}
+
+fn remove_files(sidecar: &SubtitleSidecar) {
+    for file in sidecar.files() {
+        let _ = fs::remove_file(file);
+    }
+}
+
+/// ffmpeg arguments leaving the subtitle tracks at `tracks` out of the encode.
+pub fn drop_subtitle_args(tracks: &[usize]) -> String {
+    tracks
+        .iter()
+        .map(|track| format!("-map -0:s:{}", track))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Renames the sidecars written for `original` to go with `target`.
+///
+/// Does nothing when both have the same stem and folder, or for sidecars
+/// that were never written.
+///
+/// # Returns
+/// The sidecar files moved
+pub fn rename_sidecars(
+    original: &Path,
+    target: &Path,
+    subtitles: &[SubtitleStream],
+) -> io::Result<Vec<PathBuf>> {
+    let from = plan_sidecars(original, subtitles);
+    let to = plan_sidecars(target, subtitles);
+    let mut moved = Vec::new();
+    for (from, to) in from.iter().zip(&to) {
+        for (src, dst) in from.files().into_iter().zip(to.files()) {
+            if src == dst || !src.exists() {
+                continue;
+            }
+            fs::rename(&src, &dst)?;
+            moved.push(dst);
+        }
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subtitle(codec: &str, stream_index: u32, language: Option<&str>) -> SubtitleStream {
+        SubtitleStream {
+            codec_name: codec.to_string(),
+            stream_index: Some(stream_index),
+            language: language.map(String::from),
+            forced: false,
+        }
+    }
+
+    #[test]
+    fn test_dropped_by_language_keeps_listed_untagged_and_forced() {
+        let mut forced = subtitle("subrip", 6, Some("fre"));
+        forced.forced = true;
+        let subtitles = [
+            subtitle("subrip", 2, Some("eng")),
+            subtitle("ass", 3, Some("JPN")),
+            subtitle("hdmv_pgs_subtitle", 4, Some("ger")),
+            subtitle("subrip", 5, None),
+            forced,
+            subtitle("subrip", 7, Some("und")),
+        ];
+        let mut config = SubtitleConfig {
+            keep_languages: vec!["eng".to_string(), "jpn".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(dropped_by_language(&config, &subtitles), vec![2]);
+
+        config.keep_forced = false;
+        assert_eq!(dropped_by_language(&config, &subtitles), vec![2, 4]);
+
+        assert!(dropped_by_language(&SubtitleConfig::default(), &subtitles).is_empty());
+    }
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args().map(|a| a.to_string_lossy().to_string()).collect()
+    }
+
+    #[test]
+    fn test_plan_sidecars_names_image_tracks() {
+        let subtitles = [
+            subtitle("subrip", 2, Some("eng")),
+            subtitle("hdmv_pgs_subtitle", 3, Some("eng")),
+            subtitle("hdmv_pgs_subtitle", 4, Some("ENG")),
+            subtitle("dvd_subtitle", 5, None),
+        ];
+        let sidecars = plan_sidecars(Path::new("/media/Movie (2020).mkv"), &subtitles);
+        let paths: Vec<_> = sidecars.iter().map(|s| s.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/media/Movie (2020).eng.sup"),
+                PathBuf::from("/media/Movie (2020).eng.2.sup"),
+                PathBuf::from("/media/Movie (2020).und.idx"),
+            ]
+        );
+        assert_eq!(sidecars[0].track, 1);
+        assert_eq!(
+            sidecars[2].files(),
+            vec![
+                PathBuf::from("/media/Movie (2020).und.idx"),
+                PathBuf::from("/media/Movie (2020).und.sub"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_extract_command() {
+        let subtitles = [subtitle("hdmv_pgs_subtitle", 2, Some("eng")), subtitle("dvd_subtitle", 3, Some("fre"))];
+        let sidecars = plan_sidecars(Path::new("/media/a.mkv"), &subtitles);
+
+        let sup = build_extract_command(Path::new("/media/a.mkv"), &sidecars[0]).unwrap();
+        assert_eq!(sup.get_program(), "ffmpeg");
+        let sup_args = args(&sup);
+        assert!(sup_args.windows(2).any(|w| w == ["-map", "0:s:0"]));
+        assert_eq!(sup_args.last().unwrap(), "/media/a.eng.sup");
+
+        let vobsub = build_extract_command(Path::new("/media/a.mkv"), &sidecars[1]).unwrap();
+        assert_eq!(vobsub.get_program(), "mkvextract");
+        assert_eq!(args(&vobsub), vec!["/media/a.mkv", "tracks", "3:/media/a.fre.idx"]);
+
+        let from_vob = plan_sidecars(Path::new("/media/a.vob"), &subtitles);
+        assert!(build_extract_command(Path::new("/media/a.vob"), &from_vob[1]).is_none());
+    }
+
+    #[test]
+    fn test_drop_subtitle_args() {
+        assert_eq!(drop_subtitle_args(&[]), "");
+        assert_eq!(drop_subtitle_args(&[0, 2]), "-map -0:s:0 -map -0:s:2");
+    }
+
+    #[test]
+    fn test_rename_sidecars_follows_target() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let original = temp.path().join("a.mkv");
+        let target = temp.path().join("a AV1.mkv");
+        let subtitles = [subtitle("hdmv_pgs_subtitle", 2, Some("eng")), subtitle("dvd_subtitle", 3, None)];
+        fs::write(temp.path().join("a.eng.sup"), b"pgs").unwrap();
+
+        let moved = rename_sidecars(&original, &target, &subtitles).unwrap();
+        assert_eq!(moved, vec![temp.path().join("a AV1.eng.sup")]);
+        assert_eq!(fs::read(temp.path().join("a AV1.eng.sup")).unwrap(), b"pgs");
+        assert!(!temp.path().join("a.eng.sup").exists());
+
+        assert!(rename_sidecars(&target, &target, &subtitles).unwrap().is_empty());
+    }
+}
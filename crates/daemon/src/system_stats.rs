@@ -0,0 +1,411 @@
+//! Per-core CPU and per-filesystem IO sampling.
+//!
+//! CPU usage and disk throughput are both rates, so they only mean something
+//! between two samples. [`SystemSampler`] keeps the sysinfo state and the
+//! previous `/proc/diskstats` counters across calls; the metrics updater owns
+//! one and samples it every tick. macOS has no diskstats, so there the
+//! counters come from the IOKit block storage drivers via `ioreg`.
+//!
+//! Disks are reported per filesystem rather than per watched path: library
+//! roots on the same mount collapse into one entry, and a temp directory
+//! sharing a mount with the library shows up as a single entry with both
+//! roles.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use sysinfo::{Components, Disk, Disks, System};
+
+use crate::metrics::{DiskMetrics, SystemMetrics};
+use crate::thermal::cpu_temperature;
+
+/// Role reported for filesystems holding a library root.
+pub const ROLE_LIBRARY: &str = "library";
+
+/// Role reported for filesystems holding a temp directory.
+pub const ROLE_TEMP: &str = "temp";
+
+/// Linux reports diskstats sectors in 512-byte units regardless of the
+/// device's real sector size.
+const DISKSTATS_SECTOR_BYTES: u64 = 512;
+
+/// A directory whose filesystem should be reported, tagged with its role.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedPath {
+    pub role: &'static str,
+    pub path: PathBuf,
+}
+
+impl WatchedPath {
+    pub fn new(role: &'static str, path: impl Into<PathBuf>) -> Self {
+        Self {
+            role,
+            path: path.into(),
+        }
+    }
+}
+
+/// Cumulative bytes read and written by one block device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoCounters {
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+/// Stateful collector for [`SystemMetrics`].
+pub struct SystemSampler {
+    sys: System,
+    disks: Disks,
+    components: Components,
+    watched: Vec<WatchedPath>,
+    last_io: Option<(Instant, HashMap<String, IoCounters>)>,
+}
+
+impl SystemSampler {
+    /// Creates a sampler reporting the filesystems under `watched`.
+    pub fn new(watched: Vec<WatchedPath>) -> Self {
+        Self {
+            sys: System::new(),
+            disks: Disks::new_with_refreshed_list(),
+            components: Components::new_with_refreshed_list(),
+            watched,
+            last_io: None,
+        }
+    }
+
+    /// Takes a sample.
+    ///
+    /// Disk throughput is `None` on the first call and for devices without
+    /// IO counters, such as network and overlay filesystems.
+    pub fn sample(&mut self) -> SystemMetrics {
+        self.sys.refresh_cpu_usage();
+        self.sys.refresh_memory();
+
+        let total_memory = self.sys.total_memory();
+        let mem_usage = if total_memory > 0 {
+            (self.sys.used_memory() as f64 / total_memory as f64 * 100.0) as f32
+        } else {
+            0.0
+        };
+        let load_avg = System::load_average();
+
+        SystemMetrics {
+            cpu_usage_percent: self.sys.global_cpu_usage(),
+            mem_usage_percent: mem_usage,
+            load_avg_1: load_avg.one as f32,
+            load_avg_5: load_avg.five as f32,
+            load_avg_15: load_avg.fifteen as f32,
+            per_core_usage_percent: self.sys.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+            disks: self.sample_disks(),
+            cpu_temp_celsius: self.sample_cpu_temperature(),
+        }
+    }
+
+    fn sample_cpu_temperature(&mut self) -> Option<f32> {
+        self.components.refresh();
+        cpu_temperature(
+            self.components
+                .list()
+                .iter()
+                .map(|c| (c.label(), c.temperature())),
+        )
+    }
+
+    fn sample_disks(&mut self) -> Vec<DiskMetrics> {
+        if self.watched.is_empty() {
+            return Vec::new();
+        }
+        self.disks.refresh();
+
+        let now = Instant::now();
+        let counters = read_diskstats();
+        let previous = self.last_io.replace((now, counters.clone()));
+
+        let mount_points: Vec<PathBuf> = self
+            .disks
+            .list()
+            .iter()
+            .map(|d| d.mount_point().to_path_buf())
+            .collect();
+
+        let mut out: Vec<DiskMetrics> = Vec::new();
+        for watched in &self.watched {
+            let resolved = fs::canonicalize(&watched.path).unwrap_or_else(|_| watched.path.clone());
+            let Some(index) = mount_index_for(&resolved, &mount_points) else {
+                continue;
+            };
+            let disk = &self.disks.list()[index];
+            let mount_point = disk.mount_point().display().to_string();
+
+            if let Some(existing) = out.iter_mut().find(|d| d.mount_point == mount_point) {
+                if !existing.roles.iter().any(|r| r == watched.role) {
+                    existing.roles.push(watched.role.to_string());
+                }
+                continue;
+            }
+
+            let device = disk_device(disk);
+            let (read_rate, write_rate) = match (&previous, counters.get(&device)) {
+                (Some((then, old)), Some(current)) => match old.get(&device) {
+                    Some(old) => {
+                        let (read, write) = io_rates(*old, *current, now.duration_since(*then).as_secs_f64());
+                        (Some(read), Some(write))
+                    }
+                    None => (None, None),
+                },
+                _ => (None, None),
+            };
+
+            out.push(DiskMetrics {
+                mount_point,
+                roles: vec![watched.role.to_string()],
+                read_bytes_per_sec: read_rate,
+                write_bytes_per_sec: write_rate,
+                free_bytes: disk.available_space(),
+                total_bytes: disk.total_space(),
+            });
+        }
+        out
+    }
+}
+
+/// Index of the mount point that contains `path`, preferring the deepest.
+pub fn mount_index_for(path: &Path, mount_points: &[PathBuf]) -> Option<usize> {
+    mount_points
+        .iter()
+        .enumerate()
+        .filter(|(_, mount)| path.starts_with(mount))
+        .max_by_key(|(_, mount)| mount.components().count())
+        .map(|(index, _)| index)
+}
+
+/// Parses `/proc/diskstats` into cumulative counters keyed by device name.
+///
+/// Lines with too few fields are skipped.
+pub fn parse_diskstats(content: &str) -> HashMap<String, IoCounters> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let name = fields.get(2)?;
+            let sectors_read: u64 = fields.get(5)?.parse().ok()?;
+            let sectors_written: u64 = fields.get(9)?.parse().ok()?;
+            Some((
+                name.to_string(),
+                IoCounters {
+                    read_bytes: sectors_read * DISKSTATS_SECTOR_BYTES,
+                    write_bytes: sectors_written * DISKSTATS_SECTOR_BYTES,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Bytes per second read and written between two counter samples.
+///
+/// A counter that went backwards (device re-added) reads as zero.
+pub fn io_rates(old: IoCounters, new: IoCounters, elapsed_secs: f64) -> (u64, u64) {
+    if elapsed_secs <= 0.0 {
+        return (0, 0);
+    }
+    let rate = |old: u64, new: u64| (new.saturating_sub(old) as f64 / elapsed_secs) as u64;
+    (
+        rate(old.read_bytes, new.read_bytes),
+        rate(old.write_bytes, new.write_bytes),
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn read_diskstats() -> HashMap<String, IoCounters> {
+    fs::read_to_string("/proc/diskstats")
+        .map(|content| parse_diskstats(&content))
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "macos")]
+fn read_diskstats() -> HashMap<String, IoCounters> {
+    std::process::Command::new("ioreg")
+        .args(["-r", "-c", "IOBlockStorageDriver", "-w0", "-l"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| parse_ioreg_statistics(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read_diskstats() -> HashMap<String, IoCounters> {
+    HashMap::new()
+}
+
+/// Parses `ioreg -r -c IOBlockStorageDriver -l` into cumulative counters
+/// keyed by BSD device name.
+///
+/// Each driver lists its `Statistics` before the media below it, so every
+/// device in a driver's subtree, APFS volumes and snapshots included, gets
+/// the counters of the physical disk it lives on.
+pub fn parse_ioreg_statistics(content: &str) -> HashMap<String, IoCounters> {
+    fn number(text: &str, key: &str) -> Option<u64> {
+        let start = text.find(key)? + key.len();
+        let digits = &text[start..];
+        let end = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+        digits[..end].parse().ok()
+    }
+
+    let mut stats = HashMap::new();
+    let mut current = None;
+    for line in content.lines() {
+        if let Some((_, statistics)) = line.split_once("\"Statistics\" = {") {
+            current = Some(IoCounters {
+                read_bytes: number(statistics, "\"Bytes (Read)\"=").unwrap_or(0),
+                write_bytes: number(statistics, "\"Bytes (Write)\"=").unwrap_or(0),
+            });
+        } else if let Some((_, name)) = line.split_once("\"BSD Name\" = \"") {
+            if let (Some(counters), Some(name)) = (current, name.split('"').next()) {
+                stats.insert(name.to_string(), counters);
+            }
+        }
+    }
+    stats
+}
+
+/// Device name under which `disk`'s IO counters are reported.
+#[cfg(not(target_os = "macos"))]
+fn disk_device(disk: &Disk) -> String {
+    block_device_name(&disk.name().to_string_lossy())
+}
+
+/// Device name under which `disk`'s IO counters are reported.
+///
+/// sysinfo names macOS disks after their volume, so the device is read from
+/// the mount instead.
+#[cfg(target_os = "macos")]
+fn disk_device(disk: &Disk) -> String {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let mounted_from = || {
+        let path = CString::new(disk.mount_point().as_os_str().as_bytes()).ok()?;
+        // SAFETY: statfs fills in the zeroed struct, whose f_mntfromname is a
+        // NUL-terminated string on success
+        unsafe {
+            let mut stat: libc::statfs = std::mem::zeroed();
+            if libc::statfs(path.as_ptr(), &mut stat) != 0 {
+                return None;
+            }
+            Some(CStr::from_ptr(stat.f_mntfromname.as_ptr()).to_string_lossy().into_owned())
+        }
+    };
+    let name = mounted_from().unwrap_or_else(|| disk.name().to_string_lossy().into_owned());
+    block_device_name(&name)
+}
+
+/// Maps a sysinfo disk name such as `/dev/sda1` or `/dev/mapper/root` to
+/// its diskstats name (`sda1`, `dm-0`).
+fn block_device_name(name: &str) -> String {
+    let path = Path::new(name);
+    let resolved = if name.starts_with("/dev/") {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    } else {
+        path.to_path_buf()
+    };
+    resolved
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_diskstats() {
+        let content = "\
+   8       0 sda 1200 10 4096 300 800 20 2048 400 0 500 700
+ 253       0 dm-0 50 0 100 1 60 0 200 2 0 3 3 0 0 0 0
+   7       0 loop0 short
+";
+        let stats = parse_diskstats(content);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            stats["sda"],
+            IoCounters {
+                read_bytes: 4096 * 512,
+                write_bytes: 2048 * 512
+            }
+        );
+        assert_eq!(stats["dm-0"].write_bytes, 200 * 512);
+    }
+
+    #[test]
+    fn test_parse_ioreg_statistics() {
+        let content = r#"
++-o IOBlockStorageDriver  <class IOBlockStorageDriver, id 0x100000405>
+  | {
+  |   "Statistics" = {"Operations (Write)"=10,"Bytes (Read)"=4096,"Bytes (Write)"=2048}
+  | }
+  +-o APPLE SSD AP1024Z Media  <class IOMedia, id 0x100000406>
+    | {
+    |   "BSD Name" = "disk0"
+    | }
+    +-o Container  <class IOMedia, id 0x100000410>
+      | {
+      |   "BSD Name" = "disk3s1s1"
+      | }
++-o IOBlockStorageDriver  <class IOBlockStorageDriver, id 0x100000500>
+  | {
+  |   "Statistics" = {"Bytes (Write)"=7,"Bytes (Read)"=9}
+  | }
+  +-o External Media  <class IOMedia, id 0x100000501>
+    | {
+    |   "BSD Name" = "disk4"
+    | }
+"#;
+        let stats = parse_ioreg_statistics(content);
+        assert_eq!(stats.len(), 3);
+        let internal = IoCounters {
+            read_bytes: 4096,
+            write_bytes: 2048,
+        };
+        assert_eq!(stats["disk0"], internal);
+        assert_eq!(stats["disk3s1s1"], internal);
+        assert_eq!(
+            stats["disk4"],
+            IoCounters {
+                read_bytes: 9,
+                write_bytes: 7
+            }
+        );
+    }
+
+    #[test]
+    fn test_io_rates() {
+        let old = IoCounters {
+            read_bytes: 1_000,
+            write_bytes: 5_000,
+        };
+        let new = IoCounters {
+            read_bytes: 3_000,
+            write_bytes: 4_000,
+        };
+        assert_eq!(io_rates(old, new, 2.0), (1_000, 0));
+        assert_eq!(io_rates(old, new, 0.0), (0, 0));
+    }
+
+    #[test]
+    fn test_mount_index_prefers_deepest_mount() {
+        let mounts = vec![
+            PathBuf::from("/"),
+            PathBuf::from("/media"),
+            PathBuf::from("/media/tv"),
+            PathBuf::from("/media/tvshows"),
+        ];
+        assert_eq!(mount_index_for(Path::new("/media/tv/Show"), &mounts), Some(2));
+        assert_eq!(mount_index_for(Path::new("/media/movies"), &mounts), Some(1));
+        assert_eq!(mount_index_for(Path::new("/var/tmp"), &mounts), Some(0));
+        assert_eq!(mount_index_for(Path::new("relative"), &mounts), None);
+    }
+}
@@ -0,0 +1,485 @@
+//! OpenTelemetry export over OTLP/HTTP.
+//!
+//! Each job becomes one trace: a `job` root span with a child span for the
+//! probe, the wait in the queue, and every executor stage it went through
+//! (`encode` or `remux`, `validate`, `size_gate`, `replace`). The trace is
+//! sent once the job reaches an outcome. Daemon counters and gauges from
+//! the metrics snapshot are sent on a fixed interval.
+//!
+//! Both use the JSON encoding of OTLP, which every collector accepts on
+//! its HTTP port (4318), so no protobuf or gRPC stack is needed.
+
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+use crate::config::TelemetryConfig;
+use crate::job_executor::{Job, JobState};
+use crate::jobs::JobKind;
+use crate::metrics::MetricsSnapshot;
+
+/// Instrumentation scope reported with every span and metric.
+const SCOPE_NAME: &str = "av1-super-daemon";
+
+/// How long to wait for the collector before giving up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OTLP `SPAN_KIND_INTERNAL`.
+const SPAN_KIND_INTERNAL: u8 = 1;
+
+/// OTLP `STATUS_CODE_OK` and `STATUS_CODE_ERROR`.
+const STATUS_OK: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+
+/// OTLP `AGGREGATION_TEMPORALITY_CUMULATIVE`.
+const CUMULATIVE: u8 = 2;
+
+/// Start and end of something that happened, in nanoseconds since the epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanTimes {
+    pub start_unix_nanos: u64,
+    pub end_unix_nanos: u64,
+}
+
+impl SpanTimes {
+    /// From `start` until now.
+    pub fn since(start: SystemTime) -> Self {
+        Self {
+            start_unix_nanos: unix_nanos(start),
+            end_unix_nanos: unix_nanos(SystemTime::now()),
+        }
+    }
+}
+
+/// Nanoseconds since the epoch, 0 for times before it.
+pub fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// A finished span, ready to be encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    pub name: String,
+    pub times: SpanTimes,
+    pub attributes: Vec<(&'static str, String)>,
+    /// Error message for spans that ended in failure
+    pub error: Option<String>,
+}
+
+/// A job's trace while the job is still running.
+#[derive(Debug)]
+struct JobTrace {
+    trace_id: String,
+    root_span_id: String,
+    start_unix_nanos: u64,
+    /// Name, ID, and start of the stage span currently open
+    open_stage: Option<(&'static str, String, u64)>,
+    finished: Vec<Span>,
+}
+
+impl JobTrace {
+    /// Starts a trace for `job`, with its probe and queue wait if known.
+    fn start(job: &Job, now: u64) -> Self {
+        let mut trace = Self {
+            trace_id: Uuid::new_v4().simple().to_string(),
+            root_span_id: new_span_id(),
+            start_unix_nanos: now,
+            open_stage: None,
+            finished: Vec::new(),
+        };
+        if let Some(probe) = job.probe_times {
+            trace.start_unix_nanos = probe.start_unix_nanos.min(now);
+            trace.push_child("probe", probe, None);
+            let queued = SpanTimes {
+                start_unix_nanos: probe.end_unix_nanos.min(now),
+                end_unix_nanos: now,
+            };
+            trace.push_child("queue", queued, None);
+        }
+        trace
+    }
+
+    fn push_child(&mut self, name: &str, times: SpanTimes, error: Option<String>) {
+        self.finished.push(Span {
+            trace_id: self.trace_id.clone(),
+            span_id: new_span_id(),
+            parent_span_id: Some(self.root_span_id.clone()),
+            name: name.to_string(),
+            times,
+            attributes: Vec::new(),
+            error,
+        });
+    }
+
+    /// Ends the open stage span, if any, at `now`.
+    fn close_stage(&mut self, now: u64, error: Option<String>) {
+        if let Some((name, span_id, start)) = self.open_stage.take() {
+            self.finished.push(Span {
+                trace_id: self.trace_id.clone(),
+                span_id,
+                parent_span_id: Some(self.root_span_id.clone()),
+                name: name.to_string(),
+                times: SpanTimes {
+                    start_unix_nanos: start,
+                    end_unix_nanos: now,
+                },
+                attributes: Vec::new(),
+                error,
+            });
+        }
+    }
+}
+
+/// A random 64-bit span ID in hex.
+fn new_span_id() -> String {
+    Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+/// Name of the stage span for a running state, `None` for outcomes.
+fn stage_name(state: &JobState, kind: JobKind) -> Option<&'static str> {
+    match state {
+        JobState::Encoding if kind == JobKind::Remux => Some("remux"),
+        JobState::Encoding => Some("encode"),
+        JobState::Validating => Some("validate"),
+        JobState::SizeGating => Some("size_gate"),
+        JobState::Replacing => Some("replace"),
+        _ => None,
+    }
+}
+
+/// Exporter for traces and metrics.
+pub struct Telemetry {
+    client: Client,
+    endpoint: String,
+    service_name: String,
+    headers: Vec<(String, String)>,
+    /// Traces of jobs that have not reached an outcome, by job ID
+    traces: Mutex<HashMap<String, JobTrace>>,
+    /// Set while exports fail, so the failure is reported once
+    failing: AtomicBool,
+}
+
+impl Telemetry {
+    /// Creates the exporter, or `None` when no endpoint is configured.
+    pub fn from_config(config: &TelemetryConfig) -> Option<Self> {
+        let endpoint = config.endpoint.as_deref()?.trim_end_matches('/').to_string();
+        let client = Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Some(Self {
+            client,
+            endpoint,
+            service_name: config.service_name.clone(),
+            headers: config.headers.clone().into_iter().collect(),
+            traces: Mutex::new(HashMap::new()),
+            failing: AtomicBool::new(false),
+        })
+    }
+
+    /// Records that `job` entered its current state.
+    ///
+    /// Running states open a stage span, closing the previous one. An
+    /// outcome closes the trace.
+    ///
+    /// # Returns
+    /// The spans of the finished trace, to pass to [`Telemetry::export_spans`]
+    pub fn record(&self, job: &Job) -> Option<Vec<Span>> {
+        self.record_at(job, unix_nanos(SystemTime::now()))
+    }
+
+    fn record_at(&self, job: &Job, now: u64) -> Option<Vec<Span>> {
+        if job.state == JobState::Queued {
+            return None;
+        }
+        let mut traces = self.traces.lock().unwrap_or_else(|e| e.into_inner());
+        let trace = traces
+            .entry(job.id.clone())
+            .or_insert_with(|| JobTrace::start(job, now));
+
+        if let Some(name) = stage_name(&job.state, job.kind) {
+            trace.close_stage(now, None);
+            trace.open_stage = Some((name, new_span_id(), now));
+            return None;
+        }
+
+        let error = match &job.state {
+            JobState::Failed(reason) => Some(reason.clone()),
+            _ => None,
+        };
+        let mut trace = traces.remove(&job.id)?;
+        trace.close_stage(now, error.clone());
+        let mut attributes = vec![
+            ("job.id", job.id.clone()),
+            ("job.kind", format!("{:?}", job.kind).to_lowercase()),
+            ("job.outcome", job.state.as_str().to_string()),
+            ("file.path", job.input_path.to_string_lossy().into_owned()),
+        ];
+        if let Some(reason) = job.state.reason() {
+            attributes.push(("job.reason", reason.to_string()));
+        }
+        let mut spans = std::mem::take(&mut trace.finished);
+        spans.insert(
+            0,
+            Span {
+                trace_id: trace.trace_id,
+                span_id: trace.root_span_id,
+                parent_span_id: None,
+                name: "job".to_string(),
+                times: SpanTimes {
+                    start_unix_nanos: trace.start_unix_nanos,
+                    end_unix_nanos: now,
+                },
+                attributes,
+                error,
+            },
+        );
+        Some(spans)
+    }
+
+    /// Sends finished spans to `{endpoint}/v1/traces`.
+    pub async fn export_spans(&self, spans: Vec<Span>) {
+        let body = traces_payload(&self.service_name, &spans);
+        self.post("/v1/traces", &body).await;
+    }
+
+    /// Sends the counters and gauges of `snapshot` to `{endpoint}/v1/metrics`.
+    ///
+    /// `start` is when the counters started counting, normally daemon start.
+    pub async fn export_metrics(&self, snapshot: &MetricsSnapshot, start: SystemTime) {
+        let body = metrics_payload(
+            &self.service_name,
+            snapshot,
+            unix_nanos(start),
+            unix_nanos(SystemTime::now()),
+        );
+        self.post("/v1/metrics", &body).await;
+    }
+
+    async fn post(&self, path: &str, body: &Value) {
+        let mut request = self.client.post(format!("{}{}", self.endpoint, path)).json(body);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let result = match request.send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(format!("collector answered {}", response.status())),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(()) => {
+                if self.failing.swap(false, Ordering::Relaxed) {
+                    log_info!("Telemetry export to {} recovered", self.endpoint);
+                }
+            }
+            Err(e) => {
+                if !self.failing.swap(true, Ordering::Relaxed) {
+                    log_warn!("Warning: Telemetry export to {} failed: {}", self.endpoint, e);
+                }
+            }
+        }
+    }
+}
+
+/// OTLP resource carrying the service name.
+fn resource(service_name: &str) -> Value {
+    json!({ "attributes": [string_attribute("service.name", service_name)] })
+}
+
+fn string_attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// Encodes spans as an OTLP/JSON `ExportTraceServiceRequest`.
+pub fn traces_payload(service_name: &str, spans: &[Span]) -> Value {
+    let spans: Vec<Value> = spans
+        .iter()
+        .map(|span| {
+            let status = match &span.error {
+                Some(message) => json!({ "code": STATUS_ERROR, "message": message }),
+                None => json!({ "code": STATUS_OK }),
+            };
+            let attributes: Vec<Value> = span
+                .attributes
+                .iter()
+                .map(|(key, value)| string_attribute(key, value))
+                .collect();
+            json!({
+                "traceId": span.trace_id,
+                "spanId": span.span_id,
+                "parentSpanId": span.parent_span_id.as_deref().unwrap_or(""),
+                "name": span.name,
+                "kind": SPAN_KIND_INTERNAL,
+                "startTimeUnixNano": span.times.start_unix_nanos.to_string(),
+                "endTimeUnixNano": span.times.end_unix_nanos.to_string(),
+                "attributes": attributes,
+                "status": status,
+            })
+        })
+        .collect();
+    json!({
+        "resourceSpans": [{
+            "resource": resource(service_name),
+            "scopeSpans": [{ "scope": { "name": SCOPE_NAME }, "spans": spans }],
+        }]
+    })
+}
+
+/// Encodes the snapshot's counters and gauges as an OTLP/JSON
+/// `ExportMetricsServiceRequest`.
+pub fn metrics_payload(
+    service_name: &str,
+    snapshot: &MetricsSnapshot,
+    start_unix_nanos: u64,
+    now_unix_nanos: u64,
+) -> Value {
+    let start = start_unix_nanos.to_string();
+    let now = now_unix_nanos.to_string();
+    let counter = |name: &str, unit: &str, value: u64| {
+        json!({
+            "name": name,
+            "unit": unit,
+            "sum": {
+                "aggregationTemporality": CUMULATIVE,
+                "isMonotonic": true,
+                "dataPoints": [{
+                    "asInt": value.to_string(),
+                    "startTimeUnixNano": start,
+                    "timeUnixNano": now,
+                }],
+            },
+        })
+    };
+    let gauge = |name: &str, unit: &str, value: f64| {
+        json!({
+            "name": name,
+            "unit": unit,
+            "gauge": { "dataPoints": [{ "asDouble": value, "timeUnixNano": now }] },
+        })
+    };
+
+    let mut metrics = vec![
+        counter("av1sd.jobs.completed", "{job}", snapshot.completed_jobs),
+        counter("av1sd.jobs.failed", "{job}", snapshot.failed_jobs),
+        counter("av1sd.jobs.cancelled", "{job}", snapshot.cancelled_jobs),
+        counter("av1sd.encoded", "By", snapshot.total_bytes_encoded),
+        gauge("av1sd.jobs.running", "{job}", snapshot.running_jobs as f64),
+        gauge("av1sd.queue.length", "{job}", snapshot.queue_len as f64),
+        gauge("av1sd.temp.usage", "By", snapshot.temp.bytes_used as f64),
+        gauge("av1sd.cpu.utilization", "%", snapshot.system.cpu_usage_percent as f64),
+        gauge("av1sd.memory.utilization", "%", snapshot.system.mem_usage_percent as f64),
+    ];
+    if let Some(temp) = snapshot.system.cpu_temp_celsius {
+        metrics.push(gauge("av1sd.cpu.temperature", "Cel", temp as f64));
+    }
+    json!({
+        "resourceMetrics": [{
+            "resource": resource(service_name),
+            "scopeMetrics": [{ "scope": { "name": SCOPE_NAME }, "metrics": metrics }],
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn telemetry() -> Telemetry {
+        let config = TelemetryConfig {
+            endpoint: Some("http://localhost:4318/".to_string()),
+            ..TelemetryConfig::default()
+        };
+        Telemetry::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_job_trace_covers_probe_through_replace() {
+        let telemetry = telemetry();
+        assert_eq!(telemetry.endpoint, "http://localhost:4318");
+        let mut job = Job::new(
+            "job-1".to_string(),
+            PathBuf::from("/media/film.mkv"),
+            PathBuf::from("/tmp/film.mkv"),
+        );
+        job.probe_times = Some(SpanTimes {
+            start_unix_nanos: 100,
+            end_unix_nanos: 200,
+        });
+
+        for (state, at) in [
+            (JobState::Queued, 250),
+            (JobState::Encoding, 300),
+            (JobState::Validating, 400),
+            (JobState::SizeGating, 500),
+            (JobState::Replacing, 600),
+        ] {
+            job.state = state;
+            assert_eq!(telemetry.record_at(&job, at), None);
+        }
+        job.state = JobState::Failed("disk full".to_string());
+        let spans = telemetry.record_at(&job, 700).unwrap();
+
+        let names: Vec<&str> = spans.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["job", "probe", "queue", "encode", "validate", "size_gate", "replace"]
+        );
+        let root = &spans[0];
+        assert_eq!(root.times, SpanTimes { start_unix_nanos: 100, end_unix_nanos: 700 });
+        assert_eq!(root.error.as_deref(), Some("disk full"));
+        assert!(spans.iter().all(|s| s.trace_id == root.trace_id && s.trace_id.len() == 32));
+        assert!(spans[1..]
+            .iter()
+            .all(|s| s.parent_span_id.as_ref() == Some(&root.span_id)));
+        assert_eq!(spans[2].times, SpanTimes { start_unix_nanos: 200, end_unix_nanos: 300 });
+        assert_eq!(spans[6].error.as_deref(), Some("disk full"));
+        assert!(telemetry.traces.lock().unwrap().is_empty());
+
+        let payload = traces_payload("svc", &spans);
+        let encoded = &payload["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(encoded.as_array().unwrap().len(), 7);
+        assert_eq!(encoded[0]["parentSpanId"], "");
+        assert_eq!(encoded[0]["status"]["code"], STATUS_ERROR);
+        assert_eq!(encoded[3]["startTimeUnixNano"], "300");
+        assert_eq!(
+            payload["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"],
+            "svc"
+        );
+    }
+
+    #[test]
+    fn test_metrics_payload_reports_counters_and_gauges() {
+        let snapshot = MetricsSnapshot {
+            completed_jobs: 3,
+            queue_len: 5,
+            ..MetricsSnapshot::default()
+        };
+        let payload = metrics_payload("svc", &snapshot, 10, 20);
+        let metrics = payload["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+            .as_array()
+            .unwrap();
+        let find = |name: &str| metrics.iter().find(|m| m["name"] == name).unwrap();
+
+        let completed = &find("av1sd.jobs.completed")["sum"];
+        assert_eq!(completed["isMonotonic"], true);
+        assert_eq!(completed["dataPoints"][0]["asInt"], "3");
+        assert_eq!(completed["dataPoints"][0]["startTimeUnixNano"], "10");
+        assert_eq!(find("av1sd.queue.length")["gauge"]["dataPoints"][0]["asDouble"], 5.0);
+        assert!(metrics.iter().all(|m| m["name"] != "av1sd.cpu.temperature"));
+    }
+
+    #[test]
+    fn test_no_endpoint_means_no_exporter() {
+        assert!(Telemetry::from_config(&TelemetryConfig::default()).is_none());
+    }
+}
@@ -0,0 +1,277 @@
+//! Temp directory quota and garbage collection.
+//!
+//! Each job encodes into a `chunks_{id}` directory under the executor's temp
+//! base directory and writes its output under `temp_output_dir`. A crash or a
+//! killed daemon leaves those behind, and on a small scratch disk a few
+//! stale 4K encodes are enough to fill it. This module measures usage,
+//! removes chunk directories whose job is no longer active, and tells the
+//! job loop when to hold back new work.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::metrics::SharedMetrics;
+
+/// Prefix of per-job chunk directory names.
+const CHUNK_DIR_PREFIX: &str = "chunks_";
+
+/// Job stages that no longer need their chunk directory.
+const FINISHED_STAGES: &[&str] = &["completed", "skipped"];
+
+/// Outcome of a single garbage collection pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Chunk directories removed.
+    pub dirs_removed: u64,
+    /// Bytes freed by removing them.
+    pub bytes_freed: u64,
+}
+
+/// Extracts the job ID from a chunk directory name such as `chunks_<id>`.
+pub fn chunk_dir_job_id(name: &str) -> Option<&str> {
+    name.strip_prefix(CHUNK_DIR_PREFIX).filter(|id| !id.is_empty())
+}
+
+/// Total size in bytes of all regular files under `path`.
+///
+/// Missing paths count as zero; unreadable entries are ignored.
+pub fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Combined size of the given directories, counting each distinct path once.
+pub fn temp_usage(dirs: &[&Path]) -> u64 {
+    let mut seen = HashSet::new();
+    dirs.iter()
+        .filter(|dir| seen.insert(dir.to_path_buf()))
+        .map(|dir| dir_size(dir))
+        .sum()
+}
+
+/// Removes chunk directories under `temp_base_dir` not owned by an active job.
+///
+/// Only directories following the `chunks_{id}` naming are considered;
+/// anything else in the temp directory is left alone.
+pub fn collect_garbage(temp_base_dir: &Path, active_job_ids: &HashSet<String>) -> io::Result<GcReport> {
+    let mut report = GcReport::default();
+
+    let entries = match fs::read_dir(temp_base_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(report),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let name = entry.file_name();
+        let Some(job_id) = name.to_str().and_then(chunk_dir_job_id) else {
+            continue;
+        };
+        if active_job_ids.contains(job_id) {
+            continue;
+        }
+
+        let path = entry.path();
+        let size = dir_size(&path);
+        match fs::remove_dir_all(&path) {
+            Ok(()) => {
+                report.dirs_removed += 1;
+                report.bytes_freed += size;
+            }
+            Err(e) => log_warn!("Warning: Failed to remove orphaned chunk dir {:?}: {}", path, e),
+        }
+    }
+
+    Ok(report)
+}
+
+/// IDs of jobs in the metrics snapshot whose chunk directories are still in use.
+///
+/// Failed jobs count as active so their chunks stay available for inspection
+/// until the daemon restarts.
+pub async fn active_job_ids(metrics: &SharedMetrics) -> HashSet<String> {
+    metrics
+        .read()
+        .await
+        .jobs
+        .iter()
+        .filter(|job| !FINISHED_STAGES.contains(&job.stage.as_str()))
+        .map(|job| job.id.clone())
+        .collect()
+}
+
+/// Runs one garbage collection pass and refreshes the temp usage metrics.
+///
+/// # Returns
+/// Current usage in bytes after collection
+pub async fn run_temp_gc(
+    metrics: &SharedMetrics,
+    temp_base_dir: &Path,
+    temp_output_dir: &Path,
+    quota_bytes: u64,
+) -> u64 {
+    let active = active_job_ids(metrics).await;
+    let base = temp_base_dir.to_path_buf();
+    let output = temp_output_dir.to_path_buf();
+
+    let (report, usage) = tokio::task::spawn_blocking(move || {
+        let report = collect_garbage(&base, &active).unwrap_or_else(|e| {
+            log_warn!("Warning: Temp garbage collection failed: {}", e);
+            GcReport::default()
+        });
+        (report, temp_usage(&[&base, &output]))
+    })
+    .await
+    .unwrap_or_default();
+
+    if report.dirs_removed > 0 {
+        log_info!(
+            "Removed {} orphaned chunk directories ({} bytes)",
+            report.dirs_removed, report.bytes_freed
+        );
+    }
+
+    let mut m = metrics.write().await;
+    m.temp.bytes_used = usage;
+    m.temp.quota_bytes = quota_bytes;
+    m.temp.gc_dirs_removed += report.dirs_removed;
+    m.temp.gc_bytes_freed += report.bytes_freed;
+    usage
+}
+
+/// Returns true if `usage` exceeds a non-zero quota.
+pub fn over_quota(usage: u64, quota_bytes: u64) -> bool {
+    quota_bytes > 0 && usage > quota_bytes
+}
+
+/// Measures current temp usage off the async runtime.
+pub async fn measure_temp_usage(temp_base_dir: &Path, temp_output_dir: &Path) -> u64 {
+    let base = temp_base_dir.to_path_buf();
+    let output = temp_output_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || temp_usage(&[&base, &output]))
+        .await
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{new_shared_metrics, JobMetrics};
+    use tempfile::TempDir;
+
+    fn job_metrics(id: &str, stage: &str) -> JobMetrics {
+        JobMetrics {
+            id: id.to_string(),
+            input_path: String::new(),
+            stage: stage.to_string(),
+            progress: 0.0,
+            fps: 0.0,
+            bitrate_kbps: 0.0,
+            crf: 8,
+            encoder: "svt-av1".to_string(),
+            workers: 1,
+            est_remaining_secs: 0.0,
+            frames_encoded: 0,
+            total_frames: 0,
+            size_in_bytes_before: 0,
+            size_in_bytes_after: 0,
+            vmaf: None,
+            psnr: None,
+            ssim: None,
+            tags: vec![],
+            energy_joules: 0.0,
+            quality_flag: None,
+        }
+    }
+
+    #[test]
+    fn test_chunk_dir_job_id() {
+        assert_eq!(chunk_dir_job_id("chunks_abc-123"), Some("abc-123"));
+        assert_eq!(chunk_dir_job_id("chunks_"), None);
+        assert_eq!(chunk_dir_job_id("scratch"), None);
+    }
+
+    #[test]
+    fn test_collect_garbage_keeps_active_and_unrelated_dirs() {
+        let temp = TempDir::new().unwrap();
+        let base = temp.path();
+        fs::create_dir_all(base.join("chunks_active")).unwrap();
+        fs::create_dir_all(base.join("chunks_orphan/split")).unwrap();
+        fs::create_dir_all(base.join("other")).unwrap();
+        fs::write(base.join("chunks_orphan/split/0.ivf"), vec![0u8; 100]).unwrap();
+        fs::write(base.join("chunks_active/0.ivf"), vec![0u8; 50]).unwrap();
+
+        let active = HashSet::from(["active".to_string()]);
+        let report = collect_garbage(base, &active).unwrap();
+
+        assert_eq!(
+            report,
+            GcReport {
+                dirs_removed: 1,
+                bytes_freed: 100,
+            }
+        );
+        assert!(base.join("chunks_active").exists());
+        assert!(base.join("other").exists());
+        assert!(!base.join("chunks_orphan").exists());
+    }
+
+    #[test]
+    fn test_collect_garbage_missing_dir() {
+        let temp = TempDir::new().unwrap();
+        let report = collect_garbage(&temp.path().join("missing"), &HashSet::new()).unwrap();
+        assert_eq!(report, GcReport::default());
+    }
+
+    #[test]
+    fn test_temp_usage_counts_shared_dir_once() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("out.mkv"), vec![0u8; 64]).unwrap();
+        assert_eq!(temp_usage(&[temp.path(), temp.path()]), 64);
+    }
+
+    #[test]
+    fn test_over_quota() {
+        assert!(!over_quota(u64::MAX, 0));
+        assert!(!over_quota(100, 100));
+        assert!(over_quota(101, 100));
+    }
+
+    #[tokio::test]
+    async fn test_run_temp_gc_updates_metrics() {
+        let temp = TempDir::new().unwrap();
+        let base = temp.path().join("chunks");
+        let output = temp.path().join("out");
+        fs::create_dir_all(base.join("chunks_running")).unwrap();
+        fs::create_dir_all(base.join("chunks_done")).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        fs::write(base.join("chunks_running/0.ivf"), vec![0u8; 10]).unwrap();
+        fs::write(base.join("chunks_done/0.ivf"), vec![0u8; 20]).unwrap();
+        fs::write(output.join("running.mkv"), vec![0u8; 5]).unwrap();
+
+        let metrics = new_shared_metrics();
+        metrics.write().await.jobs = vec![
+            job_metrics("running", "encoding"),
+            job_metrics("done", "completed"),
+        ];
+
+        let usage = run_temp_gc(&metrics, &base, &output, 1000).await;
+        assert_eq!(usage, 15);
+
+        let m = metrics.read().await;
+        assert_eq!(m.temp.bytes_used, 15);
+        assert_eq!(m.temp.quota_bytes, 1000);
+        assert_eq!(m.temp.gc_dirs_removed, 1);
+        assert_eq!(m.temp.gc_bytes_freed, 20);
+    }
+}
@@ -0,0 +1,154 @@
+//! CPU temperature readings and the thermal throttle policy.
+//!
+//! The metrics updater reads the hottest CPU sensor every tick and feeds it
+//! to a [`ThermalGovernor`]. Crossing `max_cpu_temp_celsius` throttles the
+//! daemon; it stays throttled until the temperature drops
+//! `resume_margin_celsius` below the limit, so a sensor hovering at the limit
+//! does not flap between states. Throttling never touches running encodes:
+//! depending on [`ThermalAction`] it either holds back the next job or starts
+//! it with fewer Av1an workers.
+
+use crate::config::{ThermalAction, ThermalConfig};
+
+/// Substrings of hwmon sensor labels that belong to the CPU package or cores.
+///
+/// Covers Intel (`coretemp`), AMD (`k10temp`, `zenpower`), ARM boards
+/// (`cpu_thermal`), Intel Macs (`CPU Die`), and Apple silicon, whose CPU
+/// cluster dies read as `PMU tdie<n>`. Drive and chipset sensors are
+/// deliberately left out.
+const CPU_SENSOR_PATTERNS: &[&str] =
+    &["coretemp", "k10temp", "zenpower", "cpu", "package", "tctl", "tdie"];
+
+/// Hottest reading among CPU sensors, given `(label, celsius)` pairs.
+///
+/// Non-finite readings (sensors that failed to read) are ignored. Returns
+/// `None` when no CPU sensor is present.
+pub fn cpu_temperature<'a, I>(readings: I) -> Option<f32>
+where
+    I: IntoIterator<Item = (&'a str, f32)>,
+{
+    readings
+        .into_iter()
+        .filter(|(label, temp)| {
+            let label = label.to_lowercase();
+            temp.is_finite() && CPU_SENSOR_PATTERNS.iter().any(|p| label.contains(p))
+        })
+        .map(|(_, temp)| temp)
+        .reduce(f32::max)
+}
+
+/// Hysteresis state machine deciding when the daemon is throttled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThermalGovernor {
+    limit_celsius: f32,
+    resume_celsius: f32,
+    action: ThermalAction,
+    throttled_workers: u32,
+    throttled: bool,
+}
+
+impl ThermalGovernor {
+    /// Builds a governor from the config, or `None` when no limit is set.
+    pub fn from_config(config: &ThermalConfig) -> Option<Self> {
+        let limit = config.max_cpu_temp_celsius?;
+        Some(Self {
+            limit_celsius: limit,
+            resume_celsius: limit - config.resume_margin_celsius.max(0.0),
+            action: config.action,
+            throttled_workers: config.throttled_workers_per_job,
+            throttled: false,
+        })
+    }
+
+    /// Feeds a new reading and returns whether the daemon is now throttled.
+    ///
+    /// A missing reading keeps the current state.
+    pub fn update(&mut self, temp_celsius: Option<f32>) -> bool {
+        if let Some(temp) = temp_celsius {
+            if temp >= self.limit_celsius {
+                self.throttled = true;
+            } else if temp <= self.resume_celsius {
+                self.throttled = false;
+            }
+        }
+        self.throttled
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        self.throttled
+    }
+
+    pub fn action(&self) -> ThermalAction {
+        self.action
+    }
+
+    pub fn limit_celsius(&self) -> f32 {
+        self.limit_celsius
+    }
+
+    /// Workers per job to use while throttled with `reduce_workers`.
+    ///
+    /// Defaults to half of `planned`, never less than one.
+    pub fn reduced_workers(&self, planned: u32) -> u32 {
+        let workers = if self.throttled_workers > 0 {
+            self.throttled_workers.min(planned)
+        } else {
+            planned / 2
+        };
+        workers.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn governor(action: ThermalAction, workers: u32) -> ThermalGovernor {
+        ThermalGovernor::from_config(&ThermalConfig {
+            max_cpu_temp_celsius: Some(85.0),
+            resume_margin_celsius: 5.0,
+            action,
+            throttled_workers_per_job: workers,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_cpu_temperature_picks_hottest_cpu_sensor() {
+        let readings = [
+            ("coretemp Package id 0", 71.0),
+            ("coretemp Core 3", 74.5),
+            ("nvme Composite", 80.0),
+            ("k10temp Tctl", f32::NAN),
+        ];
+        assert_eq!(cpu_temperature(readings), Some(74.5));
+        assert_eq!(cpu_temperature([("nvme Composite", 50.0)]), None);
+        assert_eq!(cpu_temperature([("cpu_thermal temp1", 62.0)]), Some(62.0));
+        assert_eq!(cpu_temperature([("PMU tdie1", 58.0), ("NAND CH0 temp", 66.0)]), Some(58.0));
+    }
+
+    #[test]
+    fn test_governor_hysteresis() {
+        let mut gov = governor(ThermalAction::Pause, 0);
+        assert!(!gov.update(Some(84.9)));
+        assert!(gov.update(Some(85.0)));
+        // Between the resume point and the limit the state holds
+        assert!(gov.update(Some(82.0)));
+        assert!(gov.update(None));
+        assert!(!gov.update(Some(80.0)));
+        assert!(!gov.update(Some(82.0)));
+    }
+
+    #[test]
+    fn test_disabled_without_limit() {
+        assert!(ThermalGovernor::from_config(&ThermalConfig::default()).is_none());
+    }
+
+    #[test]
+    fn test_reduced_workers() {
+        assert_eq!(governor(ThermalAction::ReduceWorkers, 0).reduced_workers(8), 4);
+        assert_eq!(governor(ThermalAction::ReduceWorkers, 0).reduced_workers(1), 1);
+        assert_eq!(governor(ThermalAction::ReduceWorkers, 3).reduced_workers(8), 3);
+        assert_eq!(governor(ThermalAction::ReduceWorkers, 12).reduced_workers(8), 8);
+    }
+}
@@ -0,0 +1,159 @@
+//! Wall-clock time per pipeline stage.
+//!
+//! Every persisted job keeps the seconds it spent in each stage it passed
+//! through (`queued`, `encoding`, `validating`, `size_gating`, `replacing`)
+//! in `stage_secs`. Time is counted between persisted transitions, so a
+//! stage that is entered twice, such as `replacing` after an approval, adds
+//! up. Waiting for approval is not counted: that is the operator's time,
+//! not the pipeline's.
+//!
+//! [`stage_timing_stats`] turns the per-job numbers into the percentiles
+//! served at `/stats/timings`, which show whether the encode or the copy
+//! back to the library is the slow part.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::jobs::{Job, JobStage};
+
+/// Adds the time since the job's last transition to the stage it was in.
+///
+/// Called when the persisted job moves on, before its stage is updated.
+pub fn record_stage_time(job: &mut Job, now_unix_ms: i64) {
+    let timed = matches!(
+        job.stage,
+        JobStage::Queued
+            | JobStage::Encoding
+            | JobStage::Validating
+            | JobStage::SizeGating
+            | JobStage::Replacing
+    );
+    if !timed || !job.is_active() {
+        return;
+    }
+    let secs = (now_unix_ms - job.updated_at).max(0) as f64 / 1000.0;
+    *job.stage_secs.entry(job.stage.to_string()).or_default() += secs;
+}
+
+/// Aggregate timings of one stage across jobs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StageTimingStats {
+    /// Jobs that spent time in the stage
+    pub count: usize,
+    pub mean_secs: f64,
+    pub p50_secs: f64,
+    pub p95_secs: f64,
+    pub max_secs: f64,
+}
+
+/// Nearest-rank percentile of sorted `values`; 0 for no values.
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Per-stage percentiles over the recorded timings of `jobs`.
+pub fn stage_timing_stats(jobs: &[Job]) -> BTreeMap<String, StageTimingStats> {
+    let mut samples: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for job in jobs {
+        for (stage, secs) in &job.stage_secs {
+            samples.entry(stage.clone()).or_default().push(*secs);
+        }
+    }
+    samples
+        .into_iter()
+        .map(|(stage, mut secs)| {
+            secs.sort_by(f64::total_cmp);
+            let stats = StageTimingStats {
+                count: secs.len(),
+                mean_secs: secs.iter().sum::<f64>() / secs.len() as f64,
+                p50_secs: percentile(&secs, 50.0),
+                p95_secs: percentile(&secs, 95.0),
+                max_secs: secs.last().copied().unwrap_or(0.0),
+            };
+            (stage, stats)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::{FormatInfo, ProbeResult};
+    use crate::jobs::{create_job, JobStatus};
+    use crate::scan::ScanCandidate;
+    use std::path::{Path, PathBuf};
+    use std::time::UNIX_EPOCH;
+
+    fn job() -> Job {
+        let candidate = ScanCandidate {
+            path: PathBuf::from("/media/film.mkv"),
+            size_bytes: 1000,
+            modified_time: UNIX_EPOCH,
+            root: PathBuf::from("/media"),
+        };
+        let probe = ProbeResult {
+            video_streams: vec![],
+            audio_streams: vec![],
+            subtitle_streams: vec![],
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 60.0,
+                size_bytes: 1000,
+            },
+        };
+        create_job(&candidate, probe, Default::default(), Path::new("/tmp"))
+    }
+
+    #[test]
+    fn test_record_stage_time_accumulates_active_stages() {
+        let mut job = job();
+        job.updated_at = 1_000;
+        record_stage_time(&mut job, 4_000);
+        job.stage = JobStage::Encoding;
+        job.status = JobStatus::Running;
+        job.updated_at = 4_000;
+        record_stage_time(&mut job, 64_000);
+        job.updated_at = 64_000;
+        record_stage_time(&mut job, 65_500);
+        assert_eq!(job.stage_secs["queued"], 3.0);
+        assert_eq!(job.stage_secs["encoding"], 61.5);
+
+        // Held for approval, or already finished: not pipeline time
+        job.stage = JobStage::AwaitingApproval;
+        job.status = JobStatus::Pending;
+        record_stage_time(&mut job, 1_000_000);
+        job.stage = JobStage::Replacing;
+        job.status = JobStatus::Failed;
+        record_stage_time(&mut job, 1_000_000);
+        assert_eq!(job.stage_secs.len(), 2);
+    }
+
+    #[test]
+    fn test_stage_timing_stats_percentiles() {
+        let jobs: Vec<Job> = (1..=20)
+            .map(|i| {
+                let mut job = job();
+                job.stage_secs.insert("encoding".to_string(), i as f64 * 10.0);
+                if i <= 2 {
+                    job.stage_secs.insert("replacing".to_string(), i as f64);
+                }
+                job
+            })
+            .collect();
+
+        let stats = stage_timing_stats(&jobs);
+        let encoding = &stats["encoding"];
+        assert_eq!(encoding.count, 20);
+        assert_eq!(encoding.p50_secs, 100.0);
+        assert_eq!(encoding.p95_secs, 190.0);
+        assert_eq!(encoding.max_secs, 200.0);
+        assert_eq!(encoding.mean_secs, 105.0);
+        assert_eq!(stats["replacing"].count, 2);
+        assert_eq!(stats["replacing"].p95_secs, 2.0);
+        assert!(stage_timing_stats(&[]).is_empty());
+    }
+}
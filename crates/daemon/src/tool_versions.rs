@@ -0,0 +1,147 @@
+//! Versions of the tools an encode ran with.
+//!
+//! Two encodes of similar files can differ because Av1an, ffmpeg, or
+//! SVT-AV1 was upgraded in between. Each encode job records the versions
+//! alongside its Av1an command line, so `GET /jobs?encoder_version=` can
+//! pick out the jobs of one SVT-AV1 release.
+//!
+//! The tools are asked once per daemon run, at the first encode; a tool that
+//! cannot be run or whose output is not understood is recorded as unknown.
+
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tools::Tool;
+
+/// Version of this daemon.
+pub const DAEMON_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Versions of the daemon and the tools an encode ran with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolVersions {
+    pub daemon: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub av1an: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ffmpeg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub svt_av1: Option<String>,
+}
+
+impl ToolVersions {
+    /// Runs each tool's version command.
+    pub fn detect() -> Self {
+        Self {
+            daemon: DAEMON_VERSION.to_string(),
+            av1an: version_output(Tool::Av1an.command(), "--version")
+                .as_deref()
+                .and_then(parse_av1an_version),
+            ffmpeg: version_output(Tool::Ffmpeg.command(), "-version")
+                .as_deref()
+                .and_then(parse_ffmpeg_version_string),
+            svt_av1: version_output(Tool::SvtAv1EncApp.command(), "--version")
+                .as_deref()
+                .and_then(parse_svt_av1_version),
+        }
+    }
+
+    /// Check if the SVT-AV1 version starts with `version`, e.g. "2.1" for
+    /// 2.1.0 and 2.1.2. A leading `v` is ignored.
+    pub fn has_encoder_version(&self, version: &str) -> bool {
+        let version = version.trim().trim_start_matches('v');
+        self.svt_av1.as_deref().is_some_and(|svt| {
+            svt == version
+                || svt
+                    .strip_prefix(version)
+                    .is_some_and(|rest| rest.starts_with(['.', '-']))
+        })
+    }
+}
+
+/// Stdout of `cmd arg`, if it ran successfully.
+fn version_output(mut cmd: Command, arg: &str) -> Option<String> {
+    let output = cmd
+        .arg(arg)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Version from `av1an --version`, e.g. "0.4.4-unstable" from
+/// "av1an 0.4.4-unstable (rev 8a7b1e9) (Release)".
+pub fn parse_av1an_version(output: &str) -> Option<String> {
+    let line = output.lines().find(|line| !line.trim().is_empty())?;
+    let mut words = line.split_whitespace();
+    match words.next()? {
+        name if name.eq_ignore_ascii_case("av1an") => words.next().map(str::to_string),
+        version => Some(version.to_string()),
+    }
+}
+
+/// Full version from `ffmpeg -version`, e.g. "8.0" or "n8.0-12-g3a5b" from
+/// "ffmpeg version n8.0-12-g3a5b Copyright ...".
+pub fn parse_ffmpeg_version_string(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (_, rest) = line.split_once("ffmpeg version ")?;
+        rest.split_whitespace().next().map(str::to_string)
+    })
+}
+
+/// Version from `SvtAv1EncApp --version`, e.g. "2.1.0" from
+/// "SVT-AV1 v2.1.0 (release)". Forks keep their suffix, as in "2.3.0-A".
+pub fn parse_svt_av1_version(output: &str) -> Option<String> {
+    output.split_whitespace().find_map(|word| {
+        let version = word.strip_prefix('v')?;
+        version
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then(|| version.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!(
+            parse_av1an_version("av1an 0.4.4-unstable (rev 8a7b1e9) (Release)\n\n* Compiler\n").as_deref(),
+            Some("0.4.4-unstable")
+        );
+        assert_eq!(parse_av1an_version("").as_deref(), None);
+
+        assert_eq!(
+            parse_ffmpeg_version_string("ffmpeg version n8.0-12-g3a5b Copyright (c) 2000-2025\nbuilt with gcc").as_deref(),
+            Some("n8.0-12-g3a5b")
+        );
+        assert_eq!(parse_ffmpeg_version_string("not ffmpeg"), None);
+
+        assert_eq!(parse_svt_av1_version("SVT-AV1 v2.1.0 (release)").as_deref(), Some("2.1.0"));
+        assert_eq!(parse_svt_av1_version("SVT-AV1-PSY v2.3.0-A (release)").as_deref(), Some("2.3.0-A"));
+        assert_eq!(parse_svt_av1_version("SVT-AV1 version unknown"), None);
+    }
+
+    #[test]
+    fn test_has_encoder_version() {
+        let versions = ToolVersions {
+            daemon: DAEMON_VERSION.to_string(),
+            av1an: None,
+            ffmpeg: None,
+            svt_av1: Some("2.1.0".to_string()),
+        };
+        assert!(versions.has_encoder_version("2.1.0"));
+        assert!(versions.has_encoder_version("v2.1"));
+        assert!(versions.has_encoder_version("2"));
+        assert!(!versions.has_encoder_version("2.10"));
+        assert!(!versions.has_encoder_version("2.2"));
+
+        let unknown = ToolVersions { svt_av1: None, ..versions };
+        assert!(!unknown.has_encoder_version("2.1.0"));
+    }
+}
@@ -0,0 +1,299 @@
+//! External tools the daemon runs.
+//!
+//! Every ffmpeg, ffprobe, Av1an and MKVToolNix process is started from
+//! [`Tool::command`], which runs the binary set under `[tools]`, or the bare
+//! name looked up in PATH, with that tool's extra environment. The daemon
+//! installs its config with [`configure_tools`] before the startup checks,
+//! so the checks run the same binaries as the jobs.
+//!
+//! Av1an starts ffmpeg and ffprobe itself. When either has a path set, its
+//! directory is put first in Av1an's PATH so both use the same build.
+//! SVT-AV1 is only ever started by Av1an, so the daemon runs it (to ask its
+//! version) with Av1an's environment.
+//!
+//! With `[process.env]` `isolate` set, tools do not inherit the daemon's
+//! environment: they start from the configured PATH and locale, the
+//! variables it passes through, and its `vars`, so a daemon started from a
+//! login shell encodes the same as one started by systemd.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, RwLock};
+
+use crate::config::{Config, ProcessEnvConfig, ToolsConfig};
+
+/// Dynamic loader variables, which isolated tools never inherit
+pub const LOADER_VARS: &[&str] = &[
+    "LD_PRELOAD",
+    "LD_AUDIT",
+    "LD_LIBRARY_PATH",
+    "DYLD_INSERT_LIBRARIES",
+    "DYLD_LIBRARY_PATH",
+];
+
+/// Config in use by [`Tool::command`]; defaults until [`configure_tools`]
+static TOOLS: LazyLock<RwLock<(ToolsConfig, ProcessEnvConfig)>> = LazyLock::new(Default::default);
+
+/// A program the daemon runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Av1an,
+    Ffmpeg,
+    Ffprobe,
+    Mkvpropedit,
+    Mkvextract,
+    SvtAv1EncApp,
+}
+
+impl Tool {
+    /// Tools with their own `tools.env` entry; SVT-AV1 takes Av1an's
+    pub const ALL: [Tool; 5] = [
+        Tool::Av1an,
+        Tool::Ffmpeg,
+        Tool::Ffprobe,
+        Tool::Mkvpropedit,
+        Tool::Mkvextract,
+    ];
+
+    /// Name of the binary, also the tool's key in `tools.env`
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Av1an => "av1an",
+            Tool::Ffmpeg => "ffmpeg",
+            Tool::Ffprobe => "ffprobe",
+            Tool::Mkvpropedit => "mkvpropedit",
+            Tool::Mkvextract => "mkvextract",
+            Tool::SvtAv1EncApp => "SvtAv1EncApp",
+        }
+    }
+
+    /// Binary the daemon runs for this tool
+    pub fn program(self) -> PathBuf {
+        program_in(&current().0, self)
+    }
+
+    /// A command running this tool with its extra environment, isolated
+    /// from the daemon's if `[process.env]` says so
+    pub fn command(self) -> std::process::Command {
+        let (tools, process) = current();
+        let mut cmd = std::process::Command::new(program_in(&tools, self));
+        let path = if process.isolate {
+            let base = isolated_env(&process, |name| std::env::var_os(name));
+            let path = base.get(&OsString::from("PATH")).cloned();
+            cmd.env_clear().envs(base);
+            path
+        } else {
+            std::env::var_os("PATH")
+        };
+        cmd.envs(env_in(&tools, self, path));
+        cmd
+    }
+
+    /// Same as [`Tool::command`], for running the tool without blocking the
+    /// async runtime
+    pub fn async_command(self) -> tokio::process::Command {
+        self.command().into()
+    }
+}
+
+/// Makes every tool started from now on use the `[tools]` and
+/// `[process.env]` settings of `config`.
+///
+/// Env entries for a tool the daemon does not run are reported, since they
+/// are most likely a typo, as are loader variables listed to pass through.
+pub fn configure_tools(config: &Config) {
+    for name in config.tools.env.keys() {
+        if !Tool::ALL.iter().any(|tool| tool.name() == name) {
+            log_warn!(
+                "Warning: tools.env.{} is ignored; known tools are {}",
+                name,
+                Tool::ALL.map(Tool::name).join(", ")
+            );
+        }
+    }
+    for name in config.process.env.pass.iter().filter(|name| is_loader_var(name)) {
+        log_warn!(
+            "Warning: {} in process.env.pass is not passed to tools; set it in process.env.vars instead",
+            name
+        );
+    }
+    *TOOLS.write().unwrap_or_else(|e| e.into_inner()) =
+        (config.tools.clone(), config.process.env.clone());
+}
+
+fn current() -> (ToolsConfig, ProcessEnvConfig) {
+    TOOLS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn is_loader_var(name: &str) -> bool {
+    LOADER_VARS.contains(&name)
+}
+
+/// Environment an isolated tool starts from, before its own `tools.env`,
+/// with `inherited` reading the daemon's environment
+///
+/// Explicit settings win over passed-through ones: `vars` over `locale`
+/// and `path`, and those over `pass`.
+pub fn isolated_env(
+    config: &ProcessEnvConfig,
+    inherited: impl Fn(&str) -> Option<OsString>,
+) -> BTreeMap<OsString, OsString> {
+    let mut env: BTreeMap<OsString, OsString> = config
+        .pass
+        .iter()
+        .filter(|name| !is_loader_var(name))
+        .filter_map(|name| Some((name.into(), inherited(name)?)))
+        .collect();
+    env.insert("PATH".into(), config.path.clone().into());
+    env.insert("LANG".into(), config.locale.clone().into());
+    env.insert("LC_ALL".into(), config.locale.clone().into());
+    env.extend(config.vars.iter().map(|(key, value)| (key.into(), value.into())));
+    env
+}
+
+/// Binary `config` sets for `tool`, or its name to look up in PATH
+pub fn program_in(config: &ToolsConfig, tool: Tool) -> PathBuf {
+    let configured = match tool {
+        Tool::Av1an => config.av1an.as_ref(),
+        Tool::Ffmpeg => config.ffmpeg.as_ref(),
+        Tool::Ffprobe => config.ffprobe.as_ref(),
+        Tool::Mkvpropedit | Tool::Mkvextract | Tool::SvtAv1EncApp => None,
+    };
+    configured
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from(tool.name()))
+}
+
+/// Environment variables `tool` gets on top of the one it starts from,
+/// with `path` being the PATH it starts with
+///
+/// Av1an's PATH starts with the directories of a configured ffmpeg and
+/// ffprobe, unless `tools.env.av1an` sets PATH itself. SVT-AV1 gets Av1an's
+/// variables, as it does when Av1an starts it.
+pub fn env_in(config: &ToolsConfig, tool: Tool, path: Option<OsString>) -> BTreeMap<OsString, OsString> {
+    if tool == Tool::SvtAv1EncApp {
+        return env_in(config, Tool::Av1an, path);
+    }
+    let mut env: BTreeMap<OsString, OsString> = config
+        .env
+        .get(tool.name())
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| (key.into(), value.into()))
+        .collect();
+
+    if tool == Tool::Av1an && !env.contains_key(&OsString::from("PATH")) {
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        for binary in [&config.ffmpeg, &config.ffprobe].into_iter().flatten() {
+            let dir = binary.parent().filter(|dir| *dir != Path::new(""));
+            if let Some(dir) = dir.filter(|dir| !dirs.iter().any(|d| d == dir)) {
+                dirs.push(dir.to_path_buf());
+            }
+        }
+        if !dirs.is_empty() {
+            dirs.extend(path.iter().flat_map(std::env::split_paths));
+            match std::env::join_paths(dirs) {
+                Ok(joined) => {
+                    env.insert("PATH".into(), joined);
+                }
+                Err(e) => log_warn!("Warning: Cannot add the ffmpeg directory to Av1an's PATH: {}", e),
+            }
+        }
+    }
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ToolsConfig {
+        ToolsConfig {
+            ffmpeg: Some(PathBuf::from("/opt/ffmpeg-8/bin/ffmpeg")),
+            ffprobe: Some(PathBuf::from("/opt/ffmpeg-8/bin/ffprobe")),
+            env: BTreeMap::from([(
+                "ffmpeg".to_string(),
+                BTreeMap::from([("LD_LIBRARY_PATH".to_string(), "/opt/ffmpeg-8/lib".to_string())]),
+            )]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_configured_paths_replace_path_lookup() {
+        let config = config();
+        assert_eq!(program_in(&config, Tool::Ffmpeg), PathBuf::from("/opt/ffmpeg-8/bin/ffmpeg"));
+        assert_eq!(program_in(&config, Tool::Av1an), PathBuf::from("av1an"));
+        assert_eq!(program_in(&ToolsConfig::default(), Tool::Ffprobe), PathBuf::from("ffprobe"));
+
+        let env = env_in(&config, Tool::Ffmpeg, Some("/usr/bin".into()));
+        assert_eq!(env.get(&OsString::from("LD_LIBRARY_PATH")), Some(&OsString::from("/opt/ffmpeg-8/lib")));
+        assert!(env_in(&config, Tool::Ffprobe, Some("/usr/bin".into())).is_empty());
+    }
+
+    #[test]
+    fn test_isolated_env_drops_inherited_variables() {
+        let config = ProcessEnvConfig {
+            isolate: true,
+            pass: vec!["HOME".to_string(), "LD_PRELOAD".to_string(), "UNSET".to_string()],
+            vars: BTreeMap::from([("SVT_LOG".to_string(), "2".to_string())]),
+            ..Default::default()
+        };
+        let daemon_env = |name: &str| match name {
+            "HOME" => Some(OsString::from("/var/lib/av1sd")),
+            "LD_PRELOAD" => Some(OsString::from("/usr/lib/libfaketime.so")),
+            "PATH" => Some(OsString::from("/home/user/bin:/usr/bin")),
+            _ => None,
+        };
+
+        let env = isolated_env(&config, daemon_env);
+        let get = |name: &str| env.get(&OsString::from(name)).and_then(|v| v.to_str());
+        assert_eq!(get("HOME"), Some("/var/lib/av1sd"));
+        assert_eq!(get("PATH"), Some("/usr/local/bin:/usr/bin:/bin"));
+        assert_eq!(get("LANG"), Some("C.UTF-8"));
+        assert_eq!(get("LC_ALL"), Some("C.UTF-8"));
+        assert_eq!(get("SVT_LOG"), Some("2"));
+        assert_eq!(get("LD_PRELOAD"), None);
+        assert_eq!(env.len(), 5);
+    }
+
+    #[test]
+    fn test_svt_av1_runs_with_av1ans_environment() {
+        let mut config = config();
+        config.env.insert(
+            "av1an".to_string(),
+            BTreeMap::from([("PATH".to_string(), "/opt/svt/bin".to_string())]),
+        );
+        assert_eq!(program_in(&config, Tool::SvtAv1EncApp), PathBuf::from("SvtAv1EncApp"));
+        assert_eq!(
+            env_in(&config, Tool::SvtAv1EncApp, Some("/usr/bin".into())),
+            env_in(&config, Tool::Av1an, Some("/usr/bin".into()))
+        );
+        assert_eq!(
+            env_in(&config, Tool::SvtAv1EncApp, None).get(&OsString::from("PATH")),
+            Some(&OsString::from("/opt/svt/bin"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_av1an_path_starts_with_configured_ffmpeg() {
+        let env = env_in(&config(), Tool::Av1an, Some("/usr/local/bin:/usr/bin".into()));
+        assert_eq!(
+            env.get(&OsString::from("PATH")),
+            Some(&OsString::from("/opt/ffmpeg-8/bin:/usr/local/bin:/usr/bin"))
+        );
+
+        // An explicit PATH for Av1an is left alone
+        let mut config = config();
+        config.env.insert(
+            "av1an".to_string(),
+            BTreeMap::from([("PATH".to_string(), "/srv/bin".to_string())]),
+        );
+        let env = env_in(&config, Tool::Av1an, Some("/usr/bin".into()));
+        assert_eq!(env.get(&OsString::from("PATH")), Some(&OsString::from("/srv/bin")));
+
+        assert!(env_in(&ToolsConfig::default(), Tool::Av1an, Some("/usr/bin".into())).is_empty());
+    }
+}
@@ -0,0 +1,400 @@
+//! Torrent client integration for files that are being seeded.
+//!
+//! Replacing a file a torrent client is seeding pulls the data out from
+//! under it: the client keeps serving pieces that no longer match. When a
+//! client is configured, the scan asks it which torrents are seeding and
+//! leaves their files alone, or, for libraries set to `pause`, the executor
+//! pauses the torrents holding a file for the length of the replacement and
+//! has the client recheck them afterwards.
+//!
+//! qBittorrent is reached through its Web UI API (v2) and Transmission
+//! through its JSON RPC. Only the torrent list is read: each torrent is
+//! reduced to its content path, the single file or top directory it
+//! downloads to, and a file belongs to a torrent when it lies under it.
+
+use reqwest::header::{HeaderValue, COOKIE, SET_COOKIE};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use crate::config::{TorrentClientKind, TorrentConfig};
+
+/// Address used for qBittorrent when `torrent.url` is unset.
+pub const QBITTORRENT_DEFAULT_URL: &str = "http://localhost:8080";
+
+/// Address used for Transmission when `torrent.url` is unset.
+pub const TRANSMISSION_DEFAULT_URL: &str = "http://localhost:9091/transmission/rpc";
+
+/// Header carrying Transmission's CSRF token.
+const TRANSMISSION_SESSION_HEADER: &str = "X-Transmission-Session-Id";
+
+/// How long to wait for the client before giving up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors talking to a torrent client.
+#[derive(Debug, Error)]
+pub enum TorrentError {
+    /// The request could not be sent or the response not read.
+    #[error("torrent client request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The client refused the configured credentials.
+    #[error("torrent client rejected the login")]
+    Login,
+
+    /// The client answered with something other than what was asked for.
+    #[error("unexpected torrent client response: {0}")]
+    Response(String),
+}
+
+/// A torrent as far as seed protection cares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Torrent {
+    /// Info hash, used to address the torrent in later requests
+    pub hash: String,
+    /// Single file or top directory the torrent downloads to
+    pub content_path: PathBuf,
+    /// True while the torrent is complete and uploading or queued to
+    pub seeding: bool,
+}
+
+impl Torrent {
+    /// Whether `path` is part of this torrent's content.
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.content_path)
+    }
+}
+
+/// Hashes of the seeding torrents in `torrents` that contain `path`.
+pub fn seeding_hashes(torrents: &[Torrent], path: &Path) -> Vec<String> {
+    torrents
+        .iter()
+        .filter(|torrent| torrent.seeding && torrent.contains(path))
+        .map(|torrent| torrent.hash.clone())
+        .collect()
+}
+
+/// Subsets of the clients' JSON responses read here.
+mod api {
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    pub struct QbTorrent {
+        pub hash: String,
+        pub state: String,
+        pub content_path: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct TrResponse {
+        pub result: String,
+        #[serde(default)]
+        pub arguments: Option<TrArguments>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct TrArguments {
+        #[serde(default)]
+        pub torrents: Vec<TrTorrent>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TrTorrent {
+        pub hash_string: String,
+        pub status: i64,
+        pub download_dir: String,
+        pub name: String,
+    }
+}
+
+/// Parses the torrent list returned by qBittorrent's `/api/v2/torrents/info`.
+pub fn parse_qbittorrent_torrents(json: &str) -> Result<Vec<Torrent>, serde_json::Error> {
+    let torrents: Vec<api::QbTorrent> = serde_json::from_str(json)?;
+    Ok(torrents
+        .into_iter()
+        .map(|torrent| Torrent {
+            // Paused and stopped states end in "UP" too
+            seeding: matches!(
+                torrent.state.as_str(),
+                "uploading" | "stalledUP" | "forcedUP" | "queuedUP" | "checkingUP"
+            ),
+            hash: torrent.hash,
+            content_path: PathBuf::from(torrent.content_path),
+        })
+        .collect())
+}
+
+/// Parses the reply to a Transmission `torrent-get` request.
+pub fn parse_transmission_torrents(json: &str) -> Result<Vec<Torrent>, TorrentError> {
+    let response: api::TrResponse =
+        serde_json::from_str(json).map_err(|e| TorrentError::Response(e.to_string()))?;
+    if response.result != "success" {
+        return Err(TorrentError::Response(response.result));
+    }
+    Ok(response
+        .arguments
+        .map(|arguments| arguments.torrents)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|torrent| Torrent {
+            // 5 = queued to seed, 6 = seeding
+            seeding: matches!(torrent.status, 5 | 6),
+            content_path: Path::new(&torrent.download_dir).join(&torrent.name),
+            hash: torrent.hash_string,
+        })
+        .collect())
+}
+
+/// Connection to the configured torrent client.
+///
+/// Holds the session the client handed out (qBittorrent's `SID` cookie or
+/// Transmission's session ID) so it is only negotiated once.
+pub struct TorrentClient {
+    kind: TorrentClientKind,
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    http: Client,
+    session: Mutex<Option<HeaderValue>>,
+}
+
+impl TorrentClient {
+    /// Creates a client for `config`, or `None` if no client is configured.
+    pub fn from_config(config: &TorrentConfig) -> Option<Self> {
+        let default_url = match config.client {
+            TorrentClientKind::None => return None,
+            TorrentClientKind::Qbittorrent => QBITTORRENT_DEFAULT_URL,
+            TorrentClientKind::Transmission => TRANSMISSION_DEFAULT_URL,
+        };
+        let url = config.url.as_deref().unwrap_or(default_url);
+        Some(Self {
+            kind: config.client,
+            url: url.trim_end_matches('/').to_string(),
+            username: config.username.clone(),
+            password: config.password.clone(),
+            http: Client::builder().timeout(REQUEST_TIMEOUT).build().ok()?,
+            session: Mutex::new(None),
+        })
+    }
+
+    /// Lists every torrent the client knows about.
+    pub async fn torrents(&self) -> Result<Vec<Torrent>, TorrentError> {
+        match self.kind {
+            TorrentClientKind::Transmission => {
+                let body = self
+                    .transmission(
+                        "torrent-get",
+                        serde_json::json!({
+                            "fields": ["hashString", "status", "downloadDir", "name"]
+                        }),
+                    )
+                    .await?;
+                parse_transmission_torrents(&body)
+            }
+            _ => {
+                let body = self.qbittorrent_get("torrents/info").await?;
+                parse_qbittorrent_torrents(&body).map_err(|e| TorrentError::Response(e.to_string()))
+            }
+        }
+    }
+
+    /// Stops the torrents with the given hashes from uploading.
+    pub async fn pause(&self, hashes: &[String]) -> Result<(), TorrentError> {
+        match self.kind {
+            TorrentClientKind::Transmission => self.transmission_ids("torrent-stop", hashes).await,
+            // qBittorrent 5 renamed pause to stop
+            _ => self.qbittorrent_action(&["stop", "pause"], hashes).await,
+        }
+    }
+
+    /// Lets the torrents with the given hashes upload again.
+    pub async fn resume(&self, hashes: &[String]) -> Result<(), TorrentError> {
+        match self.kind {
+            TorrentClientKind::Transmission => self.transmission_ids("torrent-start", hashes).await,
+            _ => self.qbittorrent_action(&["start", "resume"], hashes).await,
+        }
+    }
+
+    /// Has the client verify the torrents' data against their pieces.
+    pub async fn recheck(&self, hashes: &[String]) -> Result<(), TorrentError> {
+        match self.kind {
+            TorrentClientKind::Transmission => self.transmission_ids("torrent-verify", hashes).await,
+            _ => self.qbittorrent_action(&["recheck"], hashes).await,
+        }
+    }
+
+    /// Sends `builder` with the session, logging in first if there is none.
+    ///
+    /// Without credentials qBittorrent is tried as is, which works when it
+    /// bypasses authentication for local clients.
+    async fn qbittorrent_send(&self, mut builder: RequestBuilder) -> Result<Response, TorrentError> {
+        let mut session = self.session.lock().await;
+        if session.is_none() && self.username.is_some() {
+            *session = Some(self.qbittorrent_login().await?);
+        }
+        if let Some(cookie) = session.as_ref() {
+            builder = builder.header(COOKIE, cookie.clone());
+        }
+        let response = builder.send().await?;
+        if response.status() == StatusCode::FORBIDDEN {
+            return Err(TorrentError::Login);
+        }
+        Ok(response)
+    }
+
+    /// Logs in to qBittorrent and returns the session cookie.
+    async fn qbittorrent_login(&self) -> Result<HeaderValue, TorrentError> {
+        let response = self
+            .http
+            .post(format!("{}/api/v2/auth/login", self.url))
+            .header("Referer", &self.url)
+            .form(&[
+                ("username", self.username.as_deref().unwrap_or_default()),
+                ("password", self.password.as_deref().unwrap_or_default()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+        let cookie = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(|value| value.split(';').next().filter(|c| c.starts_with("SID=")))
+            .and_then(|cookie| HeaderValue::from_str(cookie).ok());
+        cookie.ok_or(TorrentError::Login)
+    }
+
+    async fn qbittorrent_get(&self, endpoint: &str) -> Result<String, TorrentError> {
+        let url = format!("{}/api/v2/{}", self.url, endpoint);
+        let response = self.qbittorrent_send(self.http.get(url)).await?;
+        Ok(response.error_for_status()?.text().await?)
+    }
+
+    /// Posts `hashes` to the first of `actions` the client knows.
+    async fn qbittorrent_action(&self, actions: &[&str], hashes: &[String]) -> Result<(), TorrentError> {
+        let hashes = hashes.join("|");
+        for action in actions {
+            let url = format!("{}/api/v2/torrents/{}", self.url, action);
+            let response = self
+                .qbittorrent_send(self.http.post(url).form(&[("hashes", hashes.as_str())]))
+                .await?;
+            if response.status() != StatusCode::NOT_FOUND {
+                response.error_for_status()?;
+                return Ok(());
+            }
+        }
+        Err(TorrentError::Response(format!("no endpoint for {}", actions.join("/"))))
+    }
+
+    /// Calls a Transmission RPC method and returns the response body.
+    ///
+    /// Transmission answers the first request of a session with 409 and the
+    /// session ID to use, so that request is sent again with it.
+    async fn transmission(
+        &self,
+        method: &str,
+        arguments: serde_json::Value,
+    ) -> Result<String, TorrentError> {
+        let body = serde_json::json!({ "method": method, "arguments": arguments }).to_string();
+        let mut session = self.session.lock().await;
+        for _ in 0..2 {
+            let mut builder = self.http.post(&self.url).body(body.clone());
+            if let Some(username) = &self.username {
+                builder = builder.basic_auth(username, self.password.as_ref());
+            }
+            if let Some(id) = session.as_ref() {
+                builder = builder.header(TRANSMISSION_SESSION_HEADER, id.clone());
+            }
+            let response = builder.send().await?;
+            match response.status() {
+                StatusCode::CONFLICT => {
+                    *session = response.headers().get(TRANSMISSION_SESSION_HEADER).cloned();
+                }
+                StatusCode::UNAUTHORIZED => return Err(TorrentError::Login),
+                _ => return Ok(response.error_for_status()?.text().await?),
+            }
+        }
+        Err(TorrentError::Response("no session ID from Transmission".to_string()))
+    }
+
+    async fn transmission_ids(&self, method: &str, hashes: &[String]) -> Result<(), TorrentError> {
+        let body = self.transmission(method, serde_json::json!({ "ids": hashes })).await?;
+        #[derive(Deserialize)]
+        struct Reply {
+            result: String,
+        }
+        let reply: Reply =
+            serde_json::from_str(&body).map_err(|e| TorrentError::Response(e.to_string()))?;
+        if reply.result == "success" {
+            Ok(())
+        } else {
+            Err(TorrentError::Response(reply.result))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_qbittorrent_torrents() {
+        let json = r#"[
+            {"hash": "aaa", "state": "stalledUP", "content_path": "/seed/Film (2020)/film.mkv", "name": "film"},
+            {"hash": "bbb", "state": "pausedUP", "content_path": "/seed/Show S01"},
+            {"hash": "ccc", "state": "downloading", "content_path": "/seed/Other"}
+        ]"#;
+        let torrents = parse_qbittorrent_torrents(json).unwrap();
+        assert_eq!(torrents.len(), 3);
+        assert!(torrents[0].seeding);
+        assert!(!torrents[1].seeding);
+        assert!(!torrents[2].seeding);
+
+        assert!(torrents[0].contains(Path::new("/seed/Film (2020)/film.mkv")));
+        assert!(torrents[1].contains(Path::new("/seed/Show S01/e01.mkv")));
+        assert!(!torrents[1].contains(Path::new("/seed/Show S01 extras/e01.mkv")));
+
+        let film = Path::new("/seed/Film (2020)/film.mkv");
+        assert_eq!(seeding_hashes(&torrents, film), vec!["aaa".to_string()]);
+        // A paused torrent is not seeding, so nothing needs pausing
+        assert!(seeding_hashes(&torrents, Path::new("/seed/Show S01/e01.mkv")).is_empty());
+    }
+
+    #[test]
+    fn test_parse_transmission_torrents() {
+        let json = r#"{
+            "arguments": {"torrents": [
+                {"hashString": "aaa", "status": 6, "downloadDir": "/seed", "name": "film.mkv"},
+                {"hashString": "bbb", "status": 0, "downloadDir": "/seed", "name": "Show S01"}
+            ]},
+            "result": "success"
+        }"#;
+        let torrents = parse_transmission_torrents(json).unwrap();
+        assert_eq!(torrents[0].content_path, PathBuf::from("/seed/film.mkv"));
+        assert!(torrents[0].seeding);
+        assert!(!torrents[1].seeding);
+
+        let failed = parse_transmission_torrents(r#"{"result": "method name not recognized"}"#);
+        assert!(matches!(failed, Err(TorrentError::Response(r)) if r == "method name not recognized"));
+    }
+
+    #[test]
+    fn test_client_uses_default_address_per_kind() {
+        let mut config = TorrentConfig::default();
+        assert!(TorrentClient::from_config(&config).is_none());
+
+        config.client = TorrentClientKind::Transmission;
+        let client = TorrentClient::from_config(&config).unwrap();
+        assert_eq!(client.url, TRANSMISSION_DEFAULT_URL);
+
+        config.client = TorrentClientKind::Qbittorrent;
+        config.url = Some("http://nas:8081/".to_string());
+        let client = TorrentClient::from_config(&config).unwrap();
+        assert_eq!(client.url, "http://nas:8081");
+    }
+}
@@ -0,0 +1,200 @@
+//! Triage bundles for failed jobs.
+//!
+//! When a job fails, everything needed to look into it is collected into a
+//! `{id}.triage` directory next to the job's JSON in the job state
+//! directory, and the job record points at it:
+//! - `job.json`: the job record, with the error and stage timings
+//! - `probe.json`: the probe of the input
+//! - `command.txt`: the Av1an command line, if the encode got that far
+//! - `av1an.log`: the last lines Av1an printed, if it ran
+//! - `metrics.json`: the daemon's metrics at the time of the failure
+//! - `journal.jsonl`: the job's state transitions
+//!
+//! Attaching the directory to a bug report is enough to reproduce the
+//! failure. Only the newest `triage.max_bundles` bundles are kept.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::jobs::Job as ManagedJob;
+use crate::journal::journal_path;
+use crate::metrics::MetricsSnapshot;
+
+/// Suffix of the per-job directory holding a triage bundle.
+pub const TRIAGE_DIR_SUFFIX: &str = ".triage";
+
+/// Directory holding the triage bundle of job `id`.
+pub fn triage_dir(state_dir: &Path, id: &str) -> PathBuf {
+    state_dir.join(format!("{}{}", id, TRIAGE_DIR_SUFFIX))
+}
+
+/// Write the triage bundle of the failed job `job`, replacing any earlier
+/// bundle of the same job.
+///
+/// # Arguments
+/// * `output` - The last lines of Av1an's output, oldest first
+/// * `metrics` - The daemon's metrics at the time of the failure
+///
+/// # Returns
+/// The bundle's directory
+pub fn write_bundle(
+    state_dir: &Path,
+    job: &ManagedJob,
+    output: &[String],
+    metrics: &MetricsSnapshot,
+) -> io::Result<PathBuf> {
+    let dir = triage_dir(state_dir, &job.id);
+    match fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    fs::create_dir_all(&dir)?;
+
+    write_json(&dir.join("job.json"), job)?;
+    write_json(&dir.join("probe.json"), &job.probe_result)?;
+    if let Some(command) = &job.encode_command {
+        fs::write(dir.join("command.txt"), format!("{}\n", command))?;
+    }
+    if !output.is_empty() {
+        let mut log = output.join("\n");
+        log.push('\n');
+        fs::write(dir.join("av1an.log"), log)?;
+    }
+    write_json(&dir.join("metrics.json"), metrics)?;
+    match fs::copy(journal_path(state_dir, &job.id), dir.join("journal.jsonl")) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    Ok(dir)
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, json)
+}
+
+/// Remove the oldest triage bundles in `state_dir` until at most `keep`
+/// are left; 0 keeps them all.
+///
+/// # Returns
+/// The number of bundles removed
+pub fn prune_bundles(state_dir: &Path, keep: usize) -> io::Result<usize> {
+    if keep == 0 {
+        return Ok(0);
+    }
+    let mut bundles: Vec<(SystemTime, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(state_dir)? {
+        let entry = entry?;
+        let is_bundle = entry.file_name().to_string_lossy().ends_with(TRIAGE_DIR_SUFFIX);
+        if is_bundle && entry.file_type()?.is_dir() {
+            let modified = entry.metadata()?.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            bundles.push((modified, entry.path()));
+        }
+    }
+    if bundles.len() <= keep {
+        return Ok(0);
+    }
+    bundles.sort();
+    let excess = bundles.len() - keep;
+    for (_, dir) in &bundles[..excess] {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(excess)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::SourceType;
+    use crate::gates::{FormatInfo, ProbeResult};
+    use crate::jobs::{create_job, JobStage, JobStatus};
+    use crate::journal::{append_entry, JournalEntry};
+    use crate::scan::ScanCandidate;
+    use tempfile::TempDir;
+
+    fn failed_job(state_dir: &Path, name: &str) -> ManagedJob {
+        let candidate = ScanCandidate {
+            path: PathBuf::from(format!("/media/movies/{}.mkv", name)),
+            size_bytes: 4_000_000_000,
+            modified_time: SystemTime::now(),
+            root: PathBuf::from("/media/movies"),
+        };
+        let probe = ProbeResult {
+            video_streams: Vec::new(),
+            audio_streams: Vec::new(),
+            subtitle_streams: Vec::new(),
+            font_attachments: 0,
+            format: FormatInfo {
+                duration_secs: 5400.0,
+                size_bytes: 4_000_000_000,
+            },
+        };
+        let mut job = create_job(&candidate, probe, SourceType::WebLike, Path::new("/tmp/out"));
+        job.status = JobStatus::Failed;
+        job.error_reason = Some("Av1an failed with exit code 1".to_string());
+        job.encode_command = Some("av1an -i in.mkv -o out.mkv".to_string());
+        append_entry(
+            state_dir,
+            &job.id,
+            &JournalEntry::now(JobStage::Encoding, JobStatus::Failed, job.error_reason.clone()),
+        )
+        .unwrap();
+        job
+    }
+
+    #[test]
+    fn test_bundle_holds_everything_for_a_report() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_dir = temp_dir.path();
+        let job = failed_job(state_dir, "movie");
+        let output = vec!["Queue 12 chunks".to_string(), "Error: chunk 4 failed".to_string()];
+
+        let dir = write_bundle(state_dir, &job, &output, &MetricsSnapshot::default()).unwrap();
+        assert_eq!(dir, triage_dir(state_dir, &job.id));
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        let recorded: ManagedJob = serde_json::from_str(&read("job.json")).unwrap();
+        assert_eq!(recorded.error_reason, job.error_reason);
+        let probe: ProbeResult = serde_json::from_str(&read("probe.json")).unwrap();
+        assert_eq!(probe, job.probe_result);
+        assert_eq!(read("command.txt"), "av1an -i in.mkv -o out.mkv\n");
+        assert_eq!(read("av1an.log"), "Queue 12 chunks\nError: chunk 4 failed\n");
+        let _: MetricsSnapshot = serde_json::from_str(&read("metrics.json")).unwrap();
+        assert_eq!(read("journal.jsonl").lines().count(), 1);
+
+        // A failure before the encode has no command or output to keep
+        let mut early = job.clone();
+        early.encode_command = None;
+        let dir = write_bundle(state_dir, &early, &[], &MetricsSnapshot::default()).unwrap();
+        assert!(!dir.join("command.txt").exists());
+        assert!(!dir.join("av1an.log").exists());
+        assert!(dir.join("job.json").exists());
+    }
+
+    #[test]
+    fn test_prune_keeps_newest_bundles() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_dir = temp_dir.path();
+        let mut dirs = Vec::new();
+        let mut ids = Vec::new();
+        for name in ["a", "b", "c"] {
+            let job = failed_job(state_dir, name);
+            dirs.push(write_bundle(state_dir, &job, &[], &MetricsSnapshot::default()).unwrap());
+            ids.push(job.id);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+
+        assert_eq!(prune_bundles(state_dir, 0).unwrap(), 0);
+        assert_eq!(prune_bundles(state_dir, 2).unwrap(), 1);
+        assert!(!dirs[0].exists());
+        assert!(dirs[1].exists() && dirs[2].exists());
+        // Journals are not bundles
+        assert!(journal_path(state_dir, &ids[0]).exists());
+        assert_eq!(prune_bundles(state_dir, 2).unwrap(), 0);
+    }
+}
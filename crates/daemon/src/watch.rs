@@ -0,0 +1,210 @@
+//! Scans triggered by changes under the library roots.
+//!
+//! Libraries are found by the periodic scan. With `scan.watch` on macOS, an
+//! FSEvents stream on the library roots also asks for a scan as soon as a
+//! video file under them is created, renamed or modified, through the same
+//! [`DaemonControl::request_scan`] as `POST /control/scan`, so new files do
+//! not wait out `scan.scan_interval_secs`. The interval keeps running as a
+//! fallback, and a scan already requested absorbs later changes until it
+//! starts.
+//!
+//! FSEvents coalesces changes for [`WATCH_LATENCY_SECS`]. Changes the daemon
+//! makes itself, such as replacements and sidecars, are ignored. Other
+//! platforms have no watcher and rely on the interval.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::control::DaemonControl;
+use crate::scan::has_video_extension;
+
+/// Seconds FSEvents gathers changes before reporting them
+pub const WATCH_LATENCY_SECS: f64 = 5.0;
+
+/// Whether a change to `path` is worth a scan: one of the video files a
+/// scan would pick up
+pub fn triggers_scan<S: AsRef<str>>(path: &Path, video_extensions: &[S]) -> bool {
+    has_video_extension(path, video_extensions)
+}
+
+/// Request a scan on `control` whenever a video file under `roots` changes
+///
+/// The stream runs on a thread of its own for the life of the daemon.
+///
+/// # Errors
+/// `Unsupported` on platforms without a watcher, or an error if the stream
+/// cannot be started
+#[cfg(target_os = "macos")]
+pub fn watch_libraries(
+    roots: &[PathBuf],
+    video_extensions: &[String],
+    control: Arc<DaemonControl>,
+) -> io::Result<()> {
+    let watcher = fsevents::Watcher {
+        roots: roots.to_vec(),
+        video_extensions: video_extensions.to_vec(),
+        control,
+    };
+    let (started_tx, started_rx) = std::sync::mpsc::channel();
+    std::thread::Builder::new()
+        .name("fsevents".to_string())
+        .spawn(move || watcher.run(started_tx))?;
+    started_rx
+        .recv()
+        .unwrap_or_else(|_| Err(io::Error::other("FSEvents thread exited")))
+}
+
+/// Request a scan on `control` whenever a video file under `roots` changes
+///
+/// # Errors
+/// `Unsupported` on platforms without a watcher, or an error if the stream
+/// cannot be started
+#[cfg(not(target_os = "macos"))]
+pub fn watch_libraries(
+    _roots: &[PathBuf],
+    _video_extensions: &[String],
+    _control: Arc<DaemonControl>,
+) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "watching libraries needs FSEvents (macOS)",
+    ))
+}
+
+#[cfg(target_os = "macos")]
+mod fsevents {
+    use std::ffi::{CStr, CString, OsStr};
+    use std::io;
+    use std::os::raw::{c_char, c_void};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::Sender;
+    use std::sync::Arc;
+
+    use fsevent_sys as fs;
+    use fsevent_sys::core_foundation as cf;
+
+    use super::{triggers_scan, WATCH_LATENCY_SECS};
+    use crate::control::DaemonControl;
+
+    /// Changes that may have lost track of individual files
+    const RESCAN_FLAGS: fs::FSEventStreamEventFlags = fs::kFSEventStreamEventFlagMustScanSubDirs
+        | fs::kFSEventStreamEventFlagUserDropped
+        | fs::kFSEventStreamEventFlagKernelDropped
+        | fs::kFSEventStreamEventFlagRootChanged
+        | fs::kFSEventStreamEventFlagMount;
+
+    pub(super) struct Watcher {
+        pub roots: Vec<PathBuf>,
+        pub video_extensions: Vec<String>,
+        pub control: Arc<DaemonControl>,
+    }
+
+    impl Watcher {
+        /// Runs the stream on this thread's run loop, reporting on `started`
+        /// whether it came up
+        pub fn run(self, started: Sender<io::Result<()>>) {
+            let watcher = Box::into_raw(Box::new(self));
+            // SAFETY: `watcher` stays alive for as long as the stream, which
+            // is never released: the run loop serves it until the process
+            // exits. The paths array is only used by FSEventStreamCreate.
+            unsafe {
+                let paths = cf::CFArrayCreateMutable(cf::kCFAllocatorDefault, 0, &cf::kCFTypeArrayCallBacks);
+                for root in &(*watcher).roots {
+                    let Ok(root) = CString::new(root.as_os_str().as_bytes()) else {
+                        continue;
+                    };
+                    let root = cf::CFStringCreateWithCString(
+                        cf::kCFAllocatorDefault,
+                        root.as_ptr(),
+                        cf::kCFStringEncodingUTF8,
+                    );
+                    cf::CFArrayAppendValue(paths, root);
+                    cf::CFRelease(root);
+                }
+                let context = fs::FSEventStreamContext {
+                    version: 0,
+                    info: watcher as *mut c_void,
+                    retain: None,
+                    release: None,
+                    copy_description: None,
+                };
+                let stream = fs::FSEventStreamCreate(
+                    cf::kCFAllocatorDefault,
+                    on_events,
+                    &context,
+                    paths,
+                    fs::kFSEventStreamEventIdSinceNow,
+                    WATCH_LATENCY_SECS,
+                    fs::kFSEventStreamCreateFlagFileEvents | fs::kFSEventStreamCreateFlagIgnoreSelf,
+                );
+                cf::CFRelease(paths);
+                if stream.is_null() {
+                    let _ = started.send(Err(io::Error::other("FSEventStreamCreate failed")));
+                    return;
+                }
+                fs::FSEventStreamScheduleWithRunLoop(stream, cf::CFRunLoopGetCurrent(), cf::kCFRunLoopDefaultMode);
+                if fs::FSEventStreamStart(stream) == 0 {
+                    let _ = started.send(Err(io::Error::other("FSEventStreamStart failed")));
+                    return;
+                }
+                let _ = started.send(Ok(()));
+                cf::CFRunLoopRun();
+            }
+        }
+    }
+
+    /// Called on the run loop with a batch of changed paths, as C strings
+    extern "C" fn on_events(
+        _stream: fs::FSEventStreamRef,
+        info: *mut c_void,
+        count: usize,
+        paths: *mut c_void,
+        flags: *const fs::FSEventStreamEventFlags,
+        _ids: *const fs::FSEventStreamEventId,
+    ) {
+        // SAFETY: `info` is the watcher given to FSEventStreamCreate, and
+        // FSEvents passes `count` paths and flags
+        let (watcher, paths, flags) = unsafe {
+            (
+                &*(info as *const Watcher),
+                std::slice::from_raw_parts(paths as *const *const c_char, count),
+                std::slice::from_raw_parts(flags, count),
+            )
+        };
+        let wanted = paths.iter().zip(flags).any(|(&path, &flags)| {
+            // SAFETY: each path is a NUL-terminated string owned by FSEvents
+            let path = unsafe { CStr::from_ptr(path) };
+            let path = Path::new(OsStr::from_bytes(path.to_bytes()));
+            flags & RESCAN_FLAGS != 0 || triggers_scan(path, &watcher.video_extensions)
+        });
+        if wanted {
+            log_info!("Library change seen, requesting a scan");
+            watcher.control.request_scan();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_video_files_trigger_scans() {
+        let extensions = ["mkv", ".MP4"];
+        assert!(triggers_scan(Path::new("/media/new/film.mkv"), &extensions));
+        assert!(triggers_scan(Path::new("/media/new/clip.mp4"), &extensions));
+        // Sidecars, in-progress copies and folders do not
+        assert!(!triggers_scan(Path::new("/media/new/film.mkv.av1swap.part"), &extensions));
+        assert!(!triggers_scan(Path::new("/media/new/film.srt"), &extensions));
+        assert!(!triggers_scan(Path::new("/media/new"), &extensions));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_watching_is_unsupported_elsewhere() {
+        let err = watch_libraries(&[], &[], Arc::new(DaemonControl::new())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -1,7 +1,15 @@
 //! AV1 Dashboard TUI
 //!
 //! Terminal interface for real-time monitoring of encoding jobs and system metrics.
-//! Connects to the daemon metrics endpoint at http://127.0.0.1:7878/metrics
+//! Connects to the daemon API at http://127.0.0.1:7878, or at the address
+//! given with `--daemon HOST:PORT`; `--discover` finds a daemon advertising
+//! itself over mDNS on the LAN instead.
+//!
+//! `--theme default|high-contrast|monochrome` (or `ATOP_THEME`) picks the
+//! colors; terminals without 256 colors get the basic palette, and
+//! `NO_COLOR` or a dumb terminal gets no colors at all. Built with the
+//! `notifications` feature, `--notify` shows a desktop notification for
+//! every job that completes or fails while the dashboard runs.
 
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
@@ -15,20 +23,38 @@ use ratatui::{
     symbols,
     text::{Line, Span},
     widgets::{
-        Axis, Block, Borders, Cell, Chart, Dataset, Gauge, Paragraph, Row, Table, Wrap,
+        Axis, Bar, BarChart, BarGroup, Block, Borders, Cell, Chart, Clear, Dataset, Gauge, Paragraph, Row,
+        Table, Wrap,
     },
     Frame, Terminal,
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, HashMap, VecDeque},
     io::{self, Stdout},
     time::{Duration, Instant},
 };
 
-const METRICS_URL: &str = "http://127.0.0.1:7878/metrics";
+/// Daemon API address unless `--daemon` or `--discover` says otherwise
+const DEFAULT_DAEMON_URL: &str = "http://127.0.0.1:7878";
+const METRICS_PATH: &str = "/metrics";
+const HISTORY_PATH: &str = "/metrics/history";
+const APPROVALS_PATH: &str = "/jobs?stage=awaiting_approval";
+const QUEUED_PATH: &str = "/jobs?status=pending&stage=queued";
+const APPROVE_PATH: &str = "/jobs/approve";
+const REJECT_PATH: &str = "/jobs/reject";
+const NOTE_PATH: &str = "/jobs/note";
+const REORDER_PATH: &str = "/queue/reorder";
+const PAUSE_PATH: &str = "/control/pause";
+const RESUME_PATH: &str = "/control/resume";
+const SCAN_PATH: &str = "/control/scan";
+const CONFIG_PATH: &str = "/config";
+/// mDNS service type daemons advertise their API as
+const SERVICE_TYPE: &str = "_av1sd._tcp.local.";
+/// How long `--discover` browses for daemons
+const DISCOVER_TIMEOUT_SECS: u64 = 3;
 const POLL_INTERVAL_MS: u64 = 500;
-const MAX_THROUGHPUT_POINTS: usize = 60;
+const HISTORY_POLL_INTERVAL_SECS: u64 = 30;
 const MAX_EVENT_LOG_ENTRIES: usize = 100;
 
 // ============================================================================
@@ -55,20 +81,156 @@ pub struct JobMetrics {
     pub vmaf: Option<f32>,
     pub psnr: Option<f32>,
     pub ssim: Option<f32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub energy_joules: f64,
+    #[serde(default)]
+    pub quality_flag: Option<String>,
 }
 
 /// System-level metrics for resource monitoring
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct SystemMetrics {
     pub cpu_usage_percent: f32,
     pub mem_usage_percent: f32,
     pub load_avg_1: f32,
     pub load_avg_5: f32,
     pub load_avg_15: f32,
+    #[serde(default)]
+    pub per_core_usage_percent: Vec<f32>,
+    #[serde(default)]
+    pub disks: Vec<DiskMetrics>,
+    #[serde(default)]
+    pub cpu_temp_celsius: Option<f32>,
 }
 
-/// Complete metrics snapshot including jobs, system, and aggregate stats
+/// Throughput and space of a filesystem holding library roots or temp dirs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct DiskMetrics {
+    pub mount_point: String,
+    pub roles: Vec<String>,
+    pub read_bytes_per_sec: Option<u64>,
+    pub write_bytes_per_sec: Option<u64>,
+    pub free_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Library scanner progress and statistics for the most recent scan cycle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ScanMetrics {
+    pub in_progress: bool,
+    pub last_scan_started_unix_ms: i64,
+    pub last_scan_finished_unix_ms: i64,
+    pub last_scan_duration_ms: u64,
+    pub files_walked: u64,
+    pub candidates_found: u64,
+    pub jobs_queued: u64,
+    pub skips_by_reason: BTreeMap<String, u64>,
+}
+
+/// Thermal throttle state
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ThermalMetrics {
+    pub limit_celsius: Option<f32>,
+    pub throttled: bool,
+    pub action: String,
+    pub throttle_events: u64,
+}
+
+/// State of the kill switch sentinel file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct KillSwitchMetrics {
+    pub engaged: bool,
+    pub suspended_encodes: u64,
+}
+
+/// Global controls set through the daemon's control API
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ControlMetrics {
+    pub queue_paused: bool,
+    pub scan_requested: bool,
+}
+
+/// Polite mode state: whether encodes yield to interactive use
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct PoliteMetrics {
+    pub enabled: bool,
+    pub yielding: bool,
+    pub plex_transcodes: Option<u32>,
+    pub other_cpu_percent: Option<f32>,
+    pub yield_events: u64,
+}
+
+/// CPU package energy used by encodes since daemon startup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct EnergyMetrics {
+    pub available: bool,
+    pub encode_joules: f64,
+}
+
+/// Conversion progress of one library root
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct LibraryCoverage {
+    pub root: String,
+    pub converted_files: u64,
+    pub converted_bytes: u64,
+    pub skipped_files: u64,
+    pub remaining_files: u64,
+    pub remaining_bytes: u64,
+    pub percent_converted: f32,
+    pub est_remaining_savings_bytes: u64,
+}
+
+/// Encode totals at one point in time, from /metrics/history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct ThroughputSample {
+    pub timestamp_unix_ms: i64,
+    pub total_bytes_encoded: u64,
+    pub completed_jobs: u64,
+}
+
+/// A job whose encode waits for approval, from /jobs?stage=awaiting_approval
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HeldJob {
+    pub id: String,
+    pub input_path: String,
+    /// Operator notes, oldest first
+    #[serde(default)]
+    pub notes: Vec<JobNote>,
+}
+
+/// A free-text operator note on a job
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JobNote {
+    pub text: String,
+    pub added_at: i64,
+}
+
+/// A job waiting in the queue, from /jobs?status=pending&stage=queued
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueuedJob {
+    pub id: String,
+    pub input_path: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// When the daemon expects the job to start
+    #[serde(default)]
+    pub est_start_unix_ms: Option<i64>,
+    /// Set when the job was moved up the queue; higher starts first
+    #[serde(default)]
+    pub priority: i64,
+}
+
+/// Response body of /metrics/history
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct HistoryResponse {
+    pub interval_secs: u64,
+    pub samples: Vec<ThroughputSample>,
+}
+
+/// Complete metrics snapshot including jobs, system, and aggregate stats
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct MetricsSnapshot {
     pub timestamp_unix_ms: i64,
     pub jobs: Vec<JobMetrics>,
@@ -77,56 +239,545 @@ pub struct MetricsSnapshot {
     pub running_jobs: usize,
     pub completed_jobs: u64,
     pub failed_jobs: u64,
+    #[serde(default)]
+    pub cancelled_jobs: u64,
     pub total_bytes_encoded: u64,
+    #[serde(default)]
+    pub scan: ScanMetrics,
+    #[serde(default)]
+    pub skip_totals: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub thermal: ThermalMetrics,
+    #[serde(default)]
+    pub energy: EnergyMetrics,
+    #[serde(default)]
+    pub kill_switch: KillSwitchMetrics,
+    #[serde(default)]
+    pub control: ControlMetrics,
+    #[serde(default)]
+    pub polite: PoliteMetrics,
+    #[serde(default)]
+    pub coverage: Vec<LibraryCoverage>,
 }
 
-impl Default for SystemMetrics {
-    fn default() -> Self {
-        Self {
-            cpu_usage_percent: 0.0,
-            mem_usage_percent: 0.0,
-            load_avg_1: 0.0,
-            load_avg_5: 0.0,
-            load_avg_15: 0.0,
+/// What `e` saves: the dashboard's data at one moment
+#[derive(Debug, Clone, Serialize)]
+pub struct Export {
+    pub exported_unix_ms: i64,
+    /// `None` when the daemon could not be reached
+    pub metrics: Option<MetricsSnapshot>,
+    pub queued: Vec<QueuedJob>,
+    pub approvals: Vec<HeldJob>,
+    pub tag_filter: Vec<String>,
+}
+
+impl Export {
+    /// Plain-text summary to paste into a bug report or forum post
+    pub fn to_text(&self) -> String {
+        let mut out = format!("AV1 dashboard export, {}\n", format_file_time(self.exported_unix_ms));
+        let Some(ref metrics) = self.metrics else {
+            out.push_str("Not connected to the daemon\n");
+            return out;
+        };
+        out.push_str(&format!(
+            "Queue: {} | Running: {} | Completed: {} | Failed: {} | Cancelled: {} | Encoded: {}\n",
+            metrics.queue_len,
+            metrics.running_jobs,
+            metrics.completed_jobs,
+            metrics.failed_jobs,
+            metrics.cancelled_jobs,
+            format_bytes(metrics.total_bytes_encoded),
+        ));
+        let mut states = Vec::new();
+        if metrics.kill_switch.engaged {
+            states.push("kill switch engaged");
+        }
+        if metrics.control.queue_paused {
+            states.push("queue paused");
+        }
+        if metrics.thermal.throttled {
+            states.push("thermally throttled");
+        }
+        if metrics.scan.in_progress {
+            states.push("scanning");
+        }
+        if !states.is_empty() {
+            out.push_str(&format!("State: {}\n", states.join(", ")));
         }
+        let system = &metrics.system;
+        out.push_str(&format!(
+            "CPU: {:.1}% | Memory: {:.1}% | Load: {:.2} {:.2} {:.2}{}\n",
+            system.cpu_usage_percent,
+            system.mem_usage_percent,
+            system.load_avg_1,
+            system.load_avg_5,
+            system.load_avg_15,
+            system
+                .cpu_temp_celsius
+                .map(|t| format!(" | {:.0}°C", t))
+                .unwrap_or_default(),
+        ));
+        if !self.tag_filter.is_empty() {
+            out.push_str(&format!("Tag filter: {}\n", self.tag_filter.join(" ")));
+        }
+
+        out.push_str("\nJobs:\n");
+        for job in &metrics.jobs {
+            out.push_str(&format!(
+                "  {} {} {:.1}% {:.1} fps crf {} workers {} {}\n",
+                job.id,
+                job.stage,
+                job.progress * 100.0,
+                job.fps,
+                job.crf,
+                job.workers,
+                job.input_path,
+            ));
+        }
+        out.push_str("\nQueued:\n");
+        for job in &self.queued {
+            let starts = job
+                .est_start_unix_ms
+                .map(|ms| format!(" (starts ~{})", format_weekday_time(ms)))
+                .unwrap_or_default();
+            out.push_str(&format!("  {} {}{}\n", job.id, job.input_path, starts));
+        }
+        if !self.approvals.is_empty() {
+            out.push_str("\nAwaiting approval:\n");
+            for job in &self.approvals {
+                out.push_str(&format!("  {} {}\n", job.id, job.input_path));
+            }
+        }
+        out
     }
 }
 
-impl Default for MetricsSnapshot {
-    fn default() -> Self {
+// ============================================================================
+// Themes
+// ============================================================================
+
+/// Color theme, chosen with `--theme` or `ATOP_THEME`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThemeName {
+    #[default]
+    Default,
+    /// Bold, saturated colors and the terminal's own foreground, readable on
+    /// light and dark backgrounds alike
+    HighContrast,
+    /// No colors at all; emphasis through bold, dim, and reversed text
+    Monochrome,
+}
+
+impl ThemeName {
+    /// Parse a theme name as given on the command line
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "default" => Some(Self::Default),
+            "high-contrast" | "high_contrast" | "contrast" => Some(Self::HighContrast),
+            "monochrome" | "mono" | "none" => Some(Self::Monochrome),
+            _ => None,
+        }
+    }
+}
+
+/// Colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// `NO_COLOR` set or a dumb terminal
+    None,
+    /// The 16 ANSI colors
+    Basic,
+    /// 256 colors or more
+    Extended,
+}
+
+impl ColorDepth {
+    /// Detect the color support from `NO_COLOR`, `COLORTERM`, and `TERM`
+    pub fn detect() -> Self {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        Self::from_env(
+            std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+            &var("COLORTERM"),
+            &var("TERM"),
+        )
+    }
+
+    fn from_env(no_color: bool, colorterm: &str, term: &str) -> Self {
+        if no_color || term.is_empty() || term == "dumb" {
+            Self::None
+        } else if matches!(colorterm, "truecolor" | "24bit") || term.contains("256color") {
+            Self::Extended
+        } else {
+            Self::Basic
+        }
+    }
+}
+
+/// Styles for every colored element of the dashboard
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Table headers
+    pub header: Style,
+    /// CPU gauge, core bars, config keys
+    pub accent: Style,
+    /// Finished or active work: scanning, converted libraries, throughput
+    pub good: Style,
+    /// Busy cores
+    pub warn: Style,
+    /// Failures, throttling, low disk space
+    pub bad: Style,
+    /// Queued jobs, chart axes, redacted values
+    pub muted: Style,
+    /// Memory gauge
+    pub gauge: Style,
+    /// Libraries still being converted
+    pub emphasis: Style,
+    /// Bottom status bar
+    pub status_bar: Style,
+}
+
+impl Theme {
+    /// The theme `name`, degraded to what a terminal with `depth` can show
+    pub fn new(name: ThemeName, depth: ColorDepth) -> Self {
+        match (name, depth) {
+            (_, ColorDepth::None) | (ThemeName::Monochrome, _) => Self::monochrome(),
+            (ThemeName::HighContrast, _) => Self::high_contrast(),
+            (ThemeName::Default, ColorDepth::Basic) => Self::default_basic(),
+            (ThemeName::Default, ColorDepth::Extended) => Self {
+                // Grays from the 256-color ramp keep muted text readable
+                muted: Style::default().fg(Color::Indexed(245)),
+                status_bar: Style::default().fg(Color::Indexed(255)).bg(Color::Indexed(238)),
+                ..Self::default_basic()
+            },
+        }
+    }
+
+    fn default_basic() -> Self {
         Self {
-            timestamp_unix_ms: 0,
-            jobs: Vec::new(),
-            system: SystemMetrics::default(),
-            queue_len: 0,
-            running_jobs: 0,
-            completed_jobs: 0,
-            failed_jobs: 0,
-            total_bytes_encoded: 0,
+            header: Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            accent: Style::default().fg(Color::Cyan),
+            good: Style::default().fg(Color::Green),
+            warn: Style::default().fg(Color::Yellow),
+            bad: Style::default().fg(Color::Red),
+            muted: Style::default().fg(Color::DarkGray),
+            gauge: Style::default().fg(Color::Magenta),
+            emphasis: Style::default().fg(Color::White),
+            status_bar: Style::default().fg(Color::White).bg(Color::DarkGray),
+        }
+    }
+
+    fn high_contrast() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Self {
+            header: bold.add_modifier(Modifier::UNDERLINED),
+            accent: bold.fg(Color::Blue),
+            good: bold.fg(Color::Green),
+            warn: bold.fg(Color::Magenta),
+            bad: bold.fg(Color::Red),
+            muted: Style::default(),
+            gauge: bold.fg(Color::Blue),
+            emphasis: bold,
+            status_bar: Style::default().add_modifier(Modifier::REVERSED),
+        }
+    }
+
+    fn monochrome() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Self {
+            header: bold.add_modifier(Modifier::UNDERLINED),
+            accent: Style::default(),
+            good: Style::default(),
+            warn: bold,
+            bad: bold.add_modifier(Modifier::REVERSED),
+            muted: Style::default().add_modifier(Modifier::DIM),
+            gauge: Style::default(),
+            emphasis: bold,
+            status_bar: Style::default().add_modifier(Modifier::REVERSED),
+        }
+    }
+
+    /// A status-bar badge in the color of `tone`, or reversed without one
+    pub fn badge(&self, tone: Style) -> Style {
+        match tone.fg {
+            Some(color) => Style::default()
+                .fg(Color::Black)
+                .bg(color)
+                .add_modifier(Modifier::BOLD),
+            None => Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
         }
     }
 }
 
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new(ThemeName::Default, ColorDepth::Basic)
+    }
+}
+
+// ============================================================================
+// Keymap
+// ============================================================================
+
+/// Something a key does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    ToggleHelp,
+    ToggleConfig,
+    EditFilter,
+    ApprovalUp,
+    ApprovalDown,
+    Approve,
+    Reject,
+    AddNote,
+    QueueDown,
+    QueueUp,
+    MoveToTop,
+    MoveUp,
+    PauseQueue,
+    ResumeQueue,
+    ScanNow,
+    Export,
+    ScrollUp,
+    ScrollDown,
+    PageUp,
+    PageDown,
+}
+
+/// Keys bound to an action, and where
+pub struct KeyBinding {
+    pub keys: &'static [KeyCode],
+    /// How the keys are shown in the help overlay
+    pub label: &'static str,
+    /// Tab the binding applies on; `None` for every tab
+    pub tab: Option<Tab>,
+    pub action: Action,
+    pub description: &'static str,
+}
+
+/// Every key binding, in the order the help overlay lists them. Keys are
+/// looked up here, so the overlay always matches what the keys do.
+pub const KEYMAP: &[KeyBinding] = &[
+    KeyBinding {
+        keys: &[KeyCode::Char('?')],
+        label: "?",
+        tab: None,
+        action: Action::ToggleHelp,
+        description: "Show or hide this help",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Tab],
+        label: "Tab",
+        tab: None,
+        action: Action::ToggleConfig,
+        description: "Switch between the dashboard and the daemon's configuration",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('q'), KeyCode::Esc],
+        label: "q / Esc",
+        tab: None,
+        action: Action::Quit,
+        description: "Quit",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('/')],
+        label: "/",
+        tab: Some(Tab::Dashboard),
+        action: Action::EditFilter,
+        description: "Filter jobs by tags",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('j')],
+        label: "j",
+        tab: Some(Tab::Dashboard),
+        action: Action::QueueDown,
+        description: "Highlight the next queued job",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('k')],
+        label: "k",
+        tab: Some(Tab::Dashboard),
+        action: Action::QueueUp,
+        description: "Highlight the previous queued job",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('t')],
+        label: "t",
+        tab: Some(Tab::Dashboard),
+        action: Action::MoveToTop,
+        description: "Move the highlighted queued job to the front",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('+')],
+        label: "+",
+        tab: Some(Tab::Dashboard),
+        action: Action::MoveUp,
+        description: "Move the highlighted queued job up one place",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Up],
+        label: "Up",
+        tab: Some(Tab::Dashboard),
+        action: Action::ApprovalUp,
+        description: "Highlight the previous job awaiting approval",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Down],
+        label: "Down",
+        tab: Some(Tab::Dashboard),
+        action: Action::ApprovalDown,
+        description: "Highlight the next job awaiting approval",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('a')],
+        label: "a",
+        tab: Some(Tab::Dashboard),
+        action: Action::Approve,
+        description: "Approve the highlighted job",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('r')],
+        label: "r",
+        tab: Some(Tab::Dashboard),
+        action: Action::Reject,
+        description: "Reject the highlighted job",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('n')],
+        label: "n",
+        tab: Some(Tab::Dashboard),
+        action: Action::AddNote,
+        description: "Add a note to the highlighted job",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('p')],
+        label: "p",
+        tab: Some(Tab::Dashboard),
+        action: Action::PauseQueue,
+        description: "Pause the queue; running jobs carry on",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('P')],
+        label: "P",
+        tab: Some(Tab::Dashboard),
+        action: Action::ResumeQueue,
+        description: "Resume the queue",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('S')],
+        label: "S",
+        tab: Some(Tab::Dashboard),
+        action: Action::ScanNow,
+        description: "Start a scan cycle now",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Char('e')],
+        label: "e",
+        tab: Some(Tab::Dashboard),
+        action: Action::Export,
+        description: "Save the metrics and queue as JSON and text for bug reports",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Up, KeyCode::Char('k')],
+        label: "Up / k",
+        tab: Some(Tab::Config),
+        action: Action::ScrollUp,
+        description: "Scroll up",
+    },
+    KeyBinding {
+        keys: &[KeyCode::Down, KeyCode::Char('j')],
+        label: "Down / j",
+        tab: Some(Tab::Config),
+        action: Action::ScrollDown,
+        description: "Scroll down",
+    },
+    KeyBinding {
+        keys: &[KeyCode::PageUp],
+        label: "PgUp",
+        tab: Some(Tab::Config),
+        action: Action::PageUp,
+        description: "Scroll up a page",
+    },
+    KeyBinding {
+        keys: &[KeyCode::PageDown],
+        label: "PgDn",
+        tab: Some(Tab::Config),
+        action: Action::PageDown,
+        description: "Scroll down a page",
+    },
+];
+
+/// The action `code` is bound to on `tab`
+pub fn action_for(tab: Tab, code: KeyCode) -> Option<Action> {
+    KEYMAP
+        .iter()
+        .find(|binding| binding.tab.is_none_or(|t| t == tab) && binding.keys.contains(&code))
+        .map(|binding| binding.action)
+}
+
 // ============================================================================
 // App State
 // ============================================================================
 
+/// Screen shown above the status bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tab {
+    #[default]
+    Dashboard,
+    /// The daemon's effective configuration
+    Config,
+}
+
 /// Main application state for the TUI dashboard
 pub struct App {
     /// Current metrics snapshot from daemon
     pub metrics: Option<MetricsSnapshot>,
     /// Event log with recent job events
     pub event_log: VecDeque<String>,
-    /// Throughput history for chart (timestamp_secs, mb_encoded)
-    pub throughput_history: VecDeque<(f64, f64)>,
-    /// Last known total bytes for delta calculation
-    last_total_bytes: u64,
+    /// Throughput history kept by the daemon, oldest first
+    pub history: Vec<ThroughputSample>,
     /// Connection status
     pub connected: bool,
     /// HTTP client for metrics fetching
     client: reqwest::Client,
-    /// Start time for throughput chart x-axis
-    start_time: Instant,
+    /// Base URL of the daemon API, without a trailing slash
+    pub daemon_url: String,
+    /// Active tag filter for the queue table (jobs must carry every tag)
+    pub tag_filter: Vec<String>,
+    /// Tag filter being typed, if the filter prompt is open
+    pub filter_input: Option<String>,
+    /// Jobs waiting for approval, oldest first
+    pub approvals: Vec<HeldJob>,
+    /// Index of the highlighted entry in `approvals`
+    pub selected_approval: usize,
+    /// Note being typed for the highlighted held job, if the note prompt is open
+    pub note_input: Option<String>,
+    /// Jobs waiting to start, in queue order
+    pub queued: Vec<QueuedJob>,
+    /// Index of the highlighted entry among the visible queued jobs
+    pub selected_queued: usize,
+    /// Screen being shown
+    pub tab: Tab,
+    /// The daemon's configuration as `key = value` lines, fetched when the
+    /// config tab opens
+    pub config_lines: Vec<(String, String)>,
+    /// Lines scrolled past in the config tab
+    pub config_scroll: usize,
+    /// Styles for the dashboard
+    pub theme: Theme,
+    /// True while the keybinding overlay is shown
+    pub show_help: bool,
+    /// Send desktop notifications for finished jobs
+    pub notify: bool,
+    /// Last stage seen for each job, to spot jobs that just finished;
+    /// `None` until the first snapshot
+    job_stages: Option<HashMap<String, String>>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
@@ -135,14 +786,64 @@ impl App {
         Self {
             metrics: None,
             event_log: VecDeque::with_capacity(MAX_EVENT_LOG_ENTRIES),
-            throughput_history: VecDeque::with_capacity(MAX_THROUGHPUT_POINTS),
-            last_total_bytes: 0,
+            history: Vec::new(),
             connected: false,
             client: reqwest::Client::new(),
-            start_time: Instant::now(),
+            daemon_url: DEFAULT_DAEMON_URL.to_string(),
+            tag_filter: Vec::new(),
+            filter_input: None,
+            approvals: Vec::new(),
+            selected_approval: 0,
+            note_input: None,
+            queued: Vec::new(),
+            selected_queued: 0,
+            tab: Tab::Dashboard,
+            config_lines: Vec::new(),
+            config_scroll: 0,
+            theme: Theme::default(),
+            show_help: false,
+            notify: false,
+            job_stages: None,
+        }
+    }
+
+    /// Jobs matching the active tag filter
+    pub fn visible_jobs(&self) -> Vec<&JobMetrics> {
+        match self.metrics {
+            Some(ref metrics) => metrics
+                .jobs
+                .iter()
+                .filter(|job| self.tag_filter.iter().all(|tag| job.tags.contains(tag)))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Queued jobs matching the active tag filter
+    pub fn visible_queued(&self) -> Vec<&QueuedJob> {
+        self.queued
+            .iter()
+            .filter(|job| self.tag_filter.iter().all(|tag| job.tags.contains(tag)))
+            .collect()
+    }
+
+    /// Apply the typed filter prompt; an empty prompt clears the filter
+    pub fn apply_filter_input(&mut self) {
+        if let Some(input) = self.filter_input.take() {
+            self.tag_filter = input
+                .split([',', ' '])
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect();
+            self.move_queued_selection(0);
         }
     }
 
+    /// URL of the API endpoint `path`
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.daemon_url, path)
+    }
+
     /// Add an event to the log
     pub fn log_event(&mut self, event: String) {
         if self.event_log.len() >= MAX_EVENT_LOG_ENTRIES {
@@ -153,12 +854,17 @@ impl App {
 
     /// Fetch metrics from the daemon HTTP endpoint
     pub async fn fetch_metrics(&mut self) {
-        match self.client.get(METRICS_URL).send().await {
+        match self.client.get(self.url(METRICS_PATH)).send().await {
             Ok(response) => {
                 if response.status().is_success() {
                     match response.json::<MetricsSnapshot>().await {
                         Ok(snapshot) => {
-                            self.update_throughput(&snapshot);
+                            let finished = self.finished_jobs(&snapshot);
+                            if self.notify {
+                                for job in finished {
+                                    notify_finished(job);
+                                }
+                            }
                             self.metrics = Some(snapshot);
                             self.connected = true;
                         }
@@ -181,16 +887,319 @@ impl App {
         }
     }
 
-    /// Update throughput history with new data point
-    fn update_throughput(&mut self, snapshot: &MetricsSnapshot) {
-        let elapsed_secs = self.start_time.elapsed().as_secs_f64();
-        let total_mb = snapshot.total_bytes_encoded as f64 / (1024.0 * 1024.0);
+    /// Jobs in `snapshot` that completed or failed since the last snapshot;
+    /// jobs already finished when the dashboard started are not reported
+    fn finished_jobs(&mut self, snapshot: &MetricsSnapshot) -> Vec<JobMetrics> {
+        let stages: HashMap<String, String> = snapshot
+            .jobs
+            .iter()
+            .map(|job| (job.id.clone(), job.stage.clone()))
+            .collect();
+        let Some(previous) = self.job_stages.replace(stages) else {
+            return Vec::new();
+        };
+        snapshot
+            .jobs
+            .iter()
+            .filter(|job| matches!(job.stage.as_str(), "completed" | "failed"))
+            .filter(|job| previous.get(&job.id).is_none_or(|stage| *stage != job.stage))
+            .cloned()
+            .collect()
+    }
+
+    /// Fetch the throughput history from the daemon
+    ///
+    /// Failures keep the previous history; connection errors are already
+    /// reported by `fetch_metrics`.
+    pub async fn fetch_history(&mut self) {
+        let response = match self.client.get(self.url(HISTORY_PATH)).send().await {
+            Ok(response) if response.status().is_success() => response,
+            _ => return,
+        };
+        match response.json::<HistoryResponse>().await {
+            Ok(history) => self.history = history.samples,
+            Err(e) => self.log_event(format!("History parse error: {}", e)),
+        }
+    }
+
+    /// Carry out `action`
+    ///
+    /// # Returns
+    /// True if the dashboard should quit
+    pub async fn perform(&mut self, action: Action) -> bool {
+        match action {
+            Action::Quit => return true,
+            Action::ToggleHelp => self.show_help = !self.show_help,
+            Action::ToggleConfig => self.toggle_config_tab().await,
+            Action::EditFilter => self.filter_input = Some(self.tag_filter.join(" ")),
+            Action::ApprovalUp => self.move_approval_selection(-1),
+            Action::ApprovalDown => self.move_approval_selection(1),
+            Action::Approve => self.review_selected(true).await,
+            Action::Reject => self.review_selected(false).await,
+            Action::AddNote if !self.approvals.is_empty() => self.note_input = Some(String::new()),
+            Action::AddNote => {}
+            Action::QueueDown => self.move_queued_selection(1),
+            Action::QueueUp => self.move_queued_selection(-1),
+            Action::MoveToTop => self.bump_selected(true).await,
+            Action::MoveUp => self.bump_selected(false).await,
+            Action::PauseQueue => self.send_control(PAUSE_PATH, "Queue paused").await,
+            Action::ResumeQueue => self.send_control(RESUME_PATH, "Queue resumed").await,
+            Action::ScanNow => self.send_control(SCAN_PATH, "Scan requested").await,
+            Action::Export => self.export_snapshot(),
+            Action::ScrollUp => self.scroll_config(-1),
+            Action::ScrollDown => self.scroll_config(1),
+            Action::PageUp => self.scroll_config(-20),
+            Action::PageDown => self.scroll_config(20),
+        }
+        false
+    }
+
+    /// Write what the dashboard shows to `atop-<local time>.json` and
+    /// `.txt` in the current directory
+    pub fn export_snapshot(&mut self) {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+        let export = Export {
+            exported_unix_ms: now_ms,
+            metrics: self.metrics.clone(),
+            queued: self.queued.clone(),
+            approvals: self.approvals.clone(),
+            tag_filter: self.tag_filter.clone(),
+        };
+        let stem = format!("atop-{}", format_file_time(now_ms));
+        let json = match serde_json::to_string_pretty(&export) {
+            Ok(json) => json,
+            Err(e) => {
+                self.log_event(format!("Export failed: {}", e));
+                return;
+            }
+        };
+        let written = std::fs::write(format!("{}.json", stem), json)
+            .and_then(|_| std::fs::write(format!("{}.txt", stem), export.to_text()));
+        match written {
+            Ok(()) => self.log_event(format!("Exported {}.json and {}.txt", stem, stem)),
+            Err(e) => self.log_event(format!("Export failed: {}", e)),
+        }
+    }
+
+    /// Fetch the daemon's effective configuration, secrets redacted
+    pub async fn fetch_config(&mut self) {
+        match self.client.get(self.url(CONFIG_PATH)).send().await {
+            Ok(response) if response.status().is_success() => {
+                match response.json::<serde_json::Value>().await {
+                    Ok(config) => {
+                        self.config_lines.clear();
+                        flatten_config("", &config, &mut self.config_lines);
+                        let last = self.config_lines.len().saturating_sub(1);
+                        self.config_scroll = self.config_scroll.min(last);
+                    }
+                    Err(e) => self.log_event(format!("Config parse error: {}", e)),
+                }
+            }
+            Ok(response) => {
+                let status = response.status();
+                let message = response.text().await.unwrap_or_default();
+                self.log_event(format!("Config fetch failed ({}): {}", status, message));
+            }
+            Err(e) => self.log_event(format!("Config fetch failed: {}", e)),
+        }
+    }
+
+    /// Switch between the dashboard and the config tab, fetching the
+    /// configuration afresh each time the tab opens
+    pub async fn toggle_config_tab(&mut self) {
+        self.tab = match self.tab {
+            Tab::Dashboard => {
+                self.fetch_config().await;
+                Tab::Config
+            }
+            Tab::Config => Tab::Dashboard,
+        };
+    }
+
+    /// Scroll the config tab by `delta` lines, staying in range
+    pub fn scroll_config(&mut self, delta: isize) {
+        let last = self.config_lines.len().saturating_sub(1);
+        self.config_scroll = self.config_scroll.saturating_add_signed(delta).min(last);
+    }
+
+    /// Fetch the jobs waiting for approval from the daemon
+    pub async fn fetch_approvals(&mut self) {
+        let response = match self.client.get(self.url(APPROVALS_PATH)).send().await {
+            Ok(response) if response.status().is_success() => response,
+            _ => return,
+        };
+        match response.json::<Vec<HeldJob>>().await {
+            Ok(approvals) => {
+                self.approvals = approvals;
+                self.selected_approval = self
+                    .selected_approval
+                    .min(self.approvals.len().saturating_sub(1));
+            }
+            Err(e) => self.log_event(format!("Approvals parse error: {}", e)),
+        }
+    }
+
+    /// Fetch the queued jobs and their estimated start times from the daemon
+    pub async fn fetch_queued(&mut self) {
+        let response = match self.client.get(self.url(QUEUED_PATH)).send().await {
+            Ok(response) if response.status().is_success() => response,
+            _ => return,
+        };
+        match response.json::<Vec<QueuedJob>>().await {
+            Ok(mut queued) => {
+                // The list comes oldest first; the estimates carry the queue
+                // order, with moved-up jobs first among those starting together
+                queued.sort_by_key(|job| (job.est_start_unix_ms, std::cmp::Reverse(job.priority)));
+                self.queued = queued;
+                self.move_queued_selection(0);
+            }
+            Err(e) => self.log_event(format!("Queue parse error: {}", e)),
+        }
+    }
+
+    /// Move the queue highlight by `delta` entries, staying in range
+    pub fn move_queued_selection(&mut self, delta: isize) {
+        let last = self.visible_queued().len().saturating_sub(1);
+        self.selected_queued = self.selected_queued.saturating_add_signed(delta).min(last);
+    }
+
+    /// Move the highlighted queued job to the front of the queue, or one
+    /// place up
+    pub async fn bump_selected(&mut self, to_top: bool) {
+        let visible: Vec<String> = self.visible_queued().iter().map(|job| job.id.clone()).collect();
+        let selected = self.selected_queued;
+        let Some(id) = visible.get(selected).cloned() else {
+            return;
+        };
+        // Jobs listed go to the front in the order given, so moving up one
+        // place lists everything ahead of it with the two swapped
+        let (body, place) = if to_top || selected == 0 {
+            (serde_json::json!({ "top": id }), 0)
+        } else {
+            let mut ids = visible[..=selected].to_vec();
+            ids.swap(selected - 1, selected);
+            (serde_json::json!({ "ids": ids }), selected - 1)
+        };
+        match self.client.post(self.url(REORDER_PATH)).json(&body).send().await {
+            Ok(response) if response.status().is_success() => {
+                self.selected_queued = place;
+            }
+            Ok(response) => {
+                let status = response.status();
+                let message = response.text().await.unwrap_or_default();
+                self.log_event(format!("Reorder failed ({}): {}", status, message));
+            }
+            Err(e) => self.log_event(format!("Reorder failed: {}", e)),
+        }
+        self.fetch_queued().await;
+    }
+
+    /// True if the queue length in the metrics no longer matches `queued`
+    pub fn queued_stale(&self) -> bool {
+        self.metrics
+            .as_ref()
+            .is_some_and(|metrics| metrics.queue_len != self.queued.len())
+    }
+
+    /// True if the metrics show a held job missing from `approvals`
+    pub fn approvals_stale(&self) -> bool {
+        self.metrics.as_ref().is_some_and(|metrics| {
+            metrics.jobs.iter().any(|job| {
+                job.stage == "awaiting_approval" && !self.approvals.iter().any(|a| a.id == job.id)
+            })
+        })
+    }
+
+    /// Move the approval highlight by `delta` entries, staying in range
+    pub fn move_approval_selection(&mut self, delta: isize) {
+        let last = self.approvals.len().saturating_sub(1);
+        self.selected_approval = self.selected_approval.saturating_add_signed(delta).min(last);
+    }
+
+    /// Approve or reject the highlighted held job
+    pub async fn review_selected(&mut self, approve: bool) {
+        let Some(job) = self.approvals.get(self.selected_approval).cloned() else {
+            return;
+        };
+        let (path, verb) = if approve {
+            (APPROVE_PATH, "Approved")
+        } else {
+            (REJECT_PATH, "Rejected")
+        };
+        let body = serde_json::json!({ "id": job.id });
+        match self.client.post(self.url(path)).json(&body).send().await {
+            Ok(response) if response.status().is_success() => {
+                self.log_event(format!("{} {}", verb, job.input_path));
+            }
+            Ok(response) => {
+                let status = response.status();
+                let message = response.text().await.unwrap_or_default();
+                self.log_event(format!("{} failed ({}): {}", verb, status, message));
+            }
+            Err(e) => self.log_event(format!("{} failed: {}", verb, e)),
+        }
+        self.fetch_approvals().await;
+    }
+
+    /// Attach the typed note prompt to the highlighted held job; an empty
+    /// prompt adds nothing
+    pub async fn submit_note(&mut self) {
+        let Some(text) = self.note_input.take() else {
+            return;
+        };
+        let Some(job) = self.approvals.get(self.selected_approval).cloned() else {
+            return;
+        };
+        if text.trim().is_empty() {
+            return;
+        }
+        let body = serde_json::json!({ "id": job.id, "text": text });
+        match self.client.post(self.url(NOTE_PATH)).json(&body).send().await {
+            Ok(response) if response.status().is_success() => {
+                self.log_event(format!("Noted {}", job.input_path));
+            }
+            Ok(response) => {
+                let status = response.status();
+                let message = response.text().await.unwrap_or_default();
+                self.log_event(format!("Note failed ({}): {}", status, message));
+            }
+            Err(e) => self.log_event(format!("Note failed: {}", e)),
+        }
+        self.fetch_approvals().await;
+    }
 
-        if self.throughput_history.len() >= MAX_THROUGHPUT_POINTS {
-            self.throughput_history.pop_front();
+    /// Pause or resume the daemon's queue, or ask it for a scan now
+    pub async fn send_control(&mut self, path: &str, verb: &str) {
+        match self.client.post(self.url(path)).send().await {
+            Ok(response) if response.status().is_success() => {
+                self.log_event(verb.to_string());
+            }
+            Ok(response) => {
+                let status = response.status();
+                let message = response.text().await.unwrap_or_default();
+                self.log_event(format!("{} failed ({}): {}", verb, status, message));
+            }
+            Err(e) => self.log_event(format!("{} failed: {}", verb, e)),
         }
-        self.throughput_history.push_back((elapsed_secs, total_mb));
-        self.last_total_bytes = snapshot.total_bytes_encoded;
+        self.fetch_metrics().await;
+    }
+
+    /// Chart points as (minutes relative to the newest sample, MB encoded)
+    pub fn throughput_points(&self) -> Vec<(f64, f64)> {
+        let Some(newest) = self.history.last() else {
+            return Vec::new();
+        };
+        self.history
+            .iter()
+            .map(|s| {
+                (
+                    (s.timestamp_unix_ms - newest.timestamp_unix_ms) as f64 / 60_000.0,
+                    s.total_bytes_encoded as f64 / (1024.0 * 1024.0),
+                )
+            })
+            .collect()
     }
 }
 
@@ -226,36 +1235,62 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Re
 
 /// Render the queue table showing job status
 fn render_queue_table(f: &mut Frame, area: Rect, app: &App) {
-    let header_cells = ["ID", "Stage", "Progress %", "FPS", "Bitrate", "CRF", "Workers", "ETA"]
+    let header_cells = ["ID", "Stage", "Progress %", "FPS", "Bitrate", "CRF", "Workers", "ETA", "Tags"]
         .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        .map(|h| Cell::from(*h).style(app.theme.header));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let rows: Vec<Row> = if let Some(ref metrics) = app.metrics {
-        metrics
-            .jobs
-            .iter()
-            .map(|job| {
-                let eta = if job.est_remaining_secs > 0.0 {
-                    format_duration(job.est_remaining_secs)
-                } else {
-                    "-".to_string()
-                };
-                Row::new(vec![
-                    Cell::from(job.id.clone()),
-                    Cell::from(job.stage.clone()),
-                    Cell::from(format!("{:.1}%", job.progress * 100.0)),
-                    Cell::from(format!("{:.1}", job.fps)),
-                    Cell::from(format!("{:.0} kbps", job.bitrate_kbps)),
-                    Cell::from(format!("{}", job.crf)),
-                    Cell::from(format!("{}", job.workers)),
-                    Cell::from(eta),
-                ])
-            })
-            .collect()
-    } else {
-        vec![]
-    };
+    let mut rows: Vec<Row> = app
+        .visible_jobs()
+        .into_iter()
+        .map(|job| {
+            let eta = if job.est_remaining_secs > 0.0 {
+                format_duration(job.est_remaining_secs)
+            } else {
+                "-".to_string()
+            };
+            // Encodes flagged by the sampled quality checks stand out in red
+            let stage = match job.quality_flag {
+                Some(_) => Cell::from(format!("{} !", job.stage)).style(app.theme.bad),
+                None => Cell::from(job.stage.clone()),
+            };
+            Row::new(vec![
+                Cell::from(job.id.clone()),
+                stage,
+                Cell::from(format!("{:.1}%", job.progress * 100.0)),
+                Cell::from(format!("{:.1}", job.fps)),
+                Cell::from(format!("{:.0} kbps", job.bitrate_kbps)),
+                Cell::from(format!("{}", job.crf)),
+                Cell::from(format!("{}", job.workers)),
+                Cell::from(eta),
+                Cell::from(job.tags.join(" ")),
+            ])
+        })
+        .collect();
+    // Queued jobs follow, with the time the daemon expects them to start
+    rows.extend(app.visible_queued().into_iter().enumerate().map(|(i, job)| {
+        let starts = match job.est_start_unix_ms {
+            Some(ms) => format!("starts ~{}", format_weekday_time(ms)),
+            None => "-".to_string(),
+        };
+        let style = if i == app.selected_queued {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        Row::new(vec![
+            Cell::from(job.id.clone()),
+            Cell::from("queued").style(app.theme.muted),
+            Cell::from("-"),
+            Cell::from("-"),
+            Cell::from("-"),
+            Cell::from("-"),
+            Cell::from("-"),
+            Cell::from(starts),
+            Cell::from(job.tags.join(" ")),
+        ])
+        .style(style)
+    }));
 
     let widths = [
         Constraint::Length(12),
@@ -265,14 +1300,20 @@ fn render_queue_table(f: &mut Frame, area: Rect, app: &App) {
         Constraint::Length(12),
         Constraint::Length(6),
         Constraint::Length(8),
-        Constraint::Length(10),
+        Constraint::Length(17),
+        Constraint::Min(10),
     ];
 
-    let title = if app.connected {
-        " Queue "
+    let mut title = if app.connected {
+        " Queue | j/k select | 't' to top | '+' up ".to_string()
     } else {
-        " Queue (Disconnected) "
+        " Queue (Disconnected) ".to_string()
     };
+    if let Some(ref input) = app.filter_input {
+        title.push_str(&format!("[filter: {}_] ", input));
+    } else if !app.tag_filter.is_empty() {
+        title.push_str(&format!("[tags: {}] ", app.tag_filter.join(" ")));
+    }
 
     let table = Table::new(rows, widths)
         .header(header)
@@ -301,15 +1342,28 @@ fn render_system_gauges(f: &mut Frame, area: Rect, app: &App) {
         (0.0, 0.0)
     };
 
+    // Show the CPU temperature in the title, red while thermally throttled
+    let theme = &app.theme;
+    let (cpu_title, cpu_style) = match app.metrics {
+        Some(ref metrics) => match metrics.system.cpu_temp_celsius {
+            Some(temp) if metrics.thermal.throttled => {
+                (format!(" CPU {:.0}°C throttled ", temp), theme.bad)
+            }
+            Some(temp) => (format!(" CPU {:.0}°C ", temp), theme.accent),
+            None => (" CPU ".to_string(), theme.accent),
+        },
+        None => (" CPU ".to_string(), theme.accent),
+    };
+
     let cpu_gauge = Gauge::default()
-        .block(Block::default().borders(Borders::ALL).title(" CPU "))
-        .gauge_style(Style::default().fg(Color::Cyan))
+        .block(Block::default().borders(Borders::ALL).title(cpu_title))
+        .gauge_style(cpu_style)
         .ratio(cpu_percent.clamp(0.0, 1.0))
         .label(format!("{:.1}%", cpu_percent * 100.0));
 
     let mem_gauge = Gauge::default()
         .block(Block::default().borders(Borders::ALL).title(" Memory "))
-        .gauge_style(Style::default().fg(Color::Magenta))
+        .gauge_style(theme.gauge)
         .ratio(mem_percent.clamp(0.0, 1.0))
         .label(format!("{:.1}%", mem_percent * 100.0));
 
@@ -352,9 +1406,194 @@ fn render_load_averages(f: &mut Frame, area: Rect, app: &App) {
     f.render_widget(table, area);
 }
 
+/// Render per-core CPU usage as a bar chart
+fn render_core_usage(f: &mut Frame, area: Rect, app: &App) {
+    let cores: &[f32] = match app.metrics {
+        Some(ref metrics) => &metrics.system.per_core_usage_percent,
+        None => &[],
+    };
+    let block = Block::default().borders(Borders::ALL).title(" Cores ");
+    if cores.is_empty() {
+        f.render_widget(block, area);
+        return;
+    }
+
+    // Fit every core in the panel; drop the gap and values once bars get thin
+    let inner_width = area.width.saturating_sub(2) as usize;
+    let (bar_width, bar_gap) = match inner_width / cores.len() {
+        0 | 1 => (1, 0),
+        slot => (slot - 1, 1),
+    };
+    let bars: Vec<Bar> = cores
+        .iter()
+        .enumerate()
+        .map(|(i, usage)| {
+            let style = if *usage >= 90.0 {
+                app.theme.bad
+            } else if *usage >= 60.0 {
+                app.theme.warn
+            } else {
+                app.theme.accent
+            };
+            let bar = Bar::default()
+                .value(usage.round() as u64)
+                .style(style);
+            if bar_width >= 3 {
+                bar.label(Line::from(i.to_string()))
+            } else {
+                bar.text_value(String::new())
+            }
+        })
+        .collect();
+
+    let chart = BarChart::default()
+        .block(block)
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(bar_width as u16)
+        .bar_gap(bar_gap)
+        .max(100);
+
+    f.render_widget(chart, area);
+}
+
+/// Render read/write throughput and free space for library and temp filesystems
+fn render_disk_io(f: &mut Frame, area: Rect, app: &App) {
+    let header = Row::new(["Mount", "Role", "Read/s", "Write/s", "Free"].map(|h| {
+        Cell::from(h).style(app.theme.header)
+    }));
+
+    let rows: Vec<Row> = match app.metrics {
+        Some(ref metrics) => metrics
+            .system
+            .disks
+            .iter()
+            .map(|disk| {
+                let rate = |r: Option<u64>| r.map(format_bytes).unwrap_or_else(|| "-".to_string());
+                let free_ratio = if disk.total_bytes > 0 {
+                    disk.free_bytes as f64 / disk.total_bytes as f64
+                } else {
+                    1.0
+                };
+                let free_style = if free_ratio < 0.1 {
+                    app.theme.bad
+                } else {
+                    Style::default()
+                };
+                Row::new(vec![
+                    Cell::from(disk.mount_point.clone()),
+                    Cell::from(disk.roles.join("+")),
+                    Cell::from(rate(disk.read_bytes_per_sec)),
+                    Cell::from(rate(disk.write_bytes_per_sec)),
+                    Cell::from(format_bytes(disk.free_bytes)).style(free_style),
+                ])
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let widths = [
+        Constraint::Min(8),
+        Constraint::Length(12),
+        Constraint::Length(9),
+        Constraint::Length(9),
+        Constraint::Length(9),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(" Disk IO "));
+
+    f.render_widget(table, area);
+}
+
+/// Render scanner status: activity, last cycle timing, and skip reasons
+fn render_scan_panel(f: &mut Frame, area: Rect, app: &App) {
+    let lines: Vec<Line> = if let Some(ref metrics) = app.metrics {
+        let scan = &metrics.scan;
+        let state = if scan.in_progress {
+            Span::styled("scanning", app.theme.good)
+        } else if scan.last_scan_finished_unix_ms > 0 {
+            let ago_secs =
+                (metrics.timestamp_unix_ms - scan.last_scan_finished_unix_ms).max(0) as f32 / 1000.0;
+            Span::raw(format!("idle, last {} ago", format_duration(ago_secs)))
+        } else {
+            Span::styled("no scan yet", app.theme.muted)
+        };
+
+        let mut skips: Vec<(&String, &u64)> = scan.skips_by_reason.iter().collect();
+        skips.sort_by(|a, b| b.1.cmp(a.1));
+        let skips = skips
+            .iter()
+            .take(3)
+            .map(|(reason, count)| format!("{} {}", reason, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        vec![
+            Line::from(vec![Span::raw("State: "), state]),
+            Line::from(format!(
+                "Took {} | walked {} files",
+                format_duration(scan.last_scan_duration_ms as f32 / 1000.0),
+                scan.files_walked
+            )),
+            Line::from(format!(
+                "Candidates {} | queued {}",
+                scan.candidates_found, scan.jobs_queued
+            )),
+            Line::from(format!("Skips: {}", if skips.is_empty() { "-" } else { &skips })),
+        ]
+    } else {
+        vec![]
+    };
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(" Scanner "))
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(paragraph, area);
+}
+
+/// Render per-library conversion progress
+fn render_coverage_panel(f: &mut Frame, area: Rect, app: &App) {
+    let lines: Vec<Line> = match app.metrics {
+        Some(ref metrics) => metrics
+            .coverage
+            .iter()
+            .map(|library| {
+                let name = std::path::Path::new(&library.root)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| library.root.clone());
+                let style = if library.percent_converted >= 99.95 {
+                    app.theme.good
+                } else {
+                    app.theme.emphasis
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{}: {:.0}% converted", name, library.percent_converted),
+                        style,
+                    ),
+                    Span::raw(format!(
+                        ", {} left, est. {} saving",
+                        library.remaining_files,
+                        format_bytes(library.est_remaining_savings_bytes)
+                    )),
+                ])
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(" Libraries "))
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(paragraph, area);
+}
+
 /// Render throughput chart showing MB encoded over time
 fn render_throughput_chart(f: &mut Frame, area: Rect, app: &App) {
-    let data: Vec<(f64, f64)> = app.throughput_history.iter().cloned().collect();
+    let data = app.throughput_points();
 
     if data.is_empty() {
         let block = Block::default()
@@ -364,32 +1603,33 @@ fn render_throughput_chart(f: &mut Frame, area: Rect, app: &App) {
         return;
     }
 
-    let max_x = data.last().map(|(x, _)| *x).unwrap_or(60.0);
+    // Newest sample sits at 0; older ones are negative minutes
+    let min_x = data.first().map(|(x, _)| *x).unwrap_or(0.0).min(-1.0);
     let max_y = data.iter().map(|(_, y)| *y).fold(0.0f64, f64::max).max(1.0);
 
     let datasets = vec![Dataset::default()
         .name("MB encoded")
         .marker(symbols::Marker::Braille)
-        .style(Style::default().fg(Color::Green))
+        .style(app.theme.good)
         .data(&data)];
 
     let chart = Chart::new(datasets)
         .block(Block::default().borders(Borders::ALL).title(" Throughput (MB) "))
         .x_axis(
             Axis::default()
-                .title("Time (s)")
-                .style(Style::default().fg(Color::Gray))
-                .bounds([0.0, max_x])
+                .title("Minutes")
+                .style(app.theme.muted)
+                .bounds([min_x, 0.0])
                 .labels(vec![
-                    Span::raw("0"),
-                    Span::raw(format!("{:.0}", max_x / 2.0)),
-                    Span::raw(format!("{:.0}", max_x)),
+                    Span::raw(format!("{:.0}", min_x)),
+                    Span::raw(format!("{:.0}", min_x / 2.0)),
+                    Span::raw("now"),
                 ]),
         )
         .y_axis(
             Axis::default()
                 .title("MB")
-                .style(Style::default().fg(Color::Gray))
+                .style(app.theme.muted)
                 .bounds([0.0, max_y])
                 .labels(vec![
                     Span::raw("0"),
@@ -401,6 +1641,62 @@ fn render_throughput_chart(f: &mut Frame, area: Rect, app: &App) {
     f.render_widget(chart, area);
 }
 
+/// Render the jobs waiting for approval, with the highlighted one marked
+fn render_approvals(f: &mut Frame, area: Rect, app: &App) {
+    let rows: Vec<Row> = app
+        .approvals
+        .iter()
+        .enumerate()
+        .map(|(i, held)| {
+            // Sizes come from the metrics while the daemon still tracks the job
+            let sizes = app
+                .metrics
+                .as_ref()
+                .and_then(|m| m.jobs.iter().find(|job| job.id == held.id))
+                .filter(|job| job.size_in_bytes_after > 0)
+                .map(|job| {
+                    format!(
+                        "{} -> {}",
+                        format_bytes(job.size_in_bytes_before),
+                        format_bytes(job.size_in_bytes_after)
+                    )
+                })
+                .unwrap_or_else(|| "-".to_string());
+            let style = if i == app.selected_approval {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            // Only the latest note fits; the rest are in the job record
+            let note = held.notes.last().map_or("", |note| note.text.as_str());
+            Row::new(vec![
+                Cell::from(held.id.clone()),
+                Cell::from(sizes),
+                Cell::from(held.input_path.clone()),
+                Cell::from(note.to_string()),
+            ])
+            .style(style)
+        })
+        .collect();
+
+    let widths = [
+        Constraint::Length(12),
+        Constraint::Length(24),
+        Constraint::Min(10),
+        Constraint::Percentage(30),
+    ];
+    let mut title = format!(
+        " Awaiting Approval ({}) | 'a' approve | 'r' reject | 'n' note | up/down select ",
+        app.approvals.len()
+    );
+    if let Some(ref input) = app.note_input {
+        title.push_str(&format!("[note: {}_] ", input));
+    }
+    let table = Table::new(rows, widths).block(Block::default().borders(Borders::ALL).title(title));
+
+    f.render_widget(table, area);
+}
+
 /// Render event log showing recent job events
 fn render_event_log(f: &mut Frame, area: Rect, app: &App) {
     let events: Vec<Line> = app
@@ -418,27 +1714,79 @@ fn render_event_log(f: &mut Frame, area: Rect, app: &App) {
     f.render_widget(paragraph, area);
 }
 
-/// Render status bar with aggregate stats
+/// Colored badges for global daemon state, in order of severity
+fn status_badges(metrics: &MetricsSnapshot, theme: &Theme) -> Vec<Span<'static>> {
+    let badge = |text: &'static str, tone: Style| {
+        Span::styled(format!(" {} ", text), theme.badge(tone))
+    };
+    let mut badges = Vec::new();
+    if metrics.kill_switch.engaged {
+        badges.push(badge("KILL SWITCH", theme.bad));
+    }
+    if metrics.control.queue_paused {
+        badges.push(badge("PAUSED", theme.warn));
+    }
+    if metrics.thermal.throttled {
+        badges.push(badge("THERMAL", theme.gauge));
+    }
+    if metrics.polite.yielding {
+        badges.push(badge("YIELDING", theme.accent));
+    }
+    if metrics.scan.in_progress {
+        badges.push(badge("SCANNING", theme.good));
+    } else if metrics.control.scan_requested {
+        badges.push(badge("SCAN QUEUED", theme.accent));
+    }
+    badges
+}
+
+/// Render status bar with state badges and aggregate stats
 fn render_status_bar(f: &mut Frame, area: Rect, app: &App) {
-    let status = if let Some(ref metrics) = app.metrics {
-        format!(
-            " Queue: {} | Running: {} | Completed: {} | Failed: {} | Total: {:.2} GB | Press 'q' to quit ",
+    let line = if let Some(ref metrics) = app.metrics {
+        let energy = if metrics.energy.available {
+            format!(" | Energy: {:.2} kWh", metrics.energy.encode_joules / 3_600_000.0)
+        } else {
+            String::new()
+        };
+        let mut spans = status_badges(metrics, &app.theme);
+        spans.push(Span::raw(format!(
+            " Queue: {} | Running: {} | Completed: {} | Failed: {} | Cancelled: {} | Skipped: {} | Total: {:.2} GB{} | '?' keys | 'q' quit ",
             metrics.queue_len,
             metrics.running_jobs,
             metrics.completed_jobs,
             metrics.failed_jobs,
-            metrics.total_bytes_encoded as f64 / (1024.0 * 1024.0 * 1024.0)
-        )
+            metrics.cancelled_jobs,
+            metrics.skip_totals.values().sum::<u64>(),
+            metrics.total_bytes_encoded as f64 / (1024.0 * 1024.0 * 1024.0),
+            energy
+        )));
+        Line::from(spans)
     } else {
-        " Connecting to daemon... | Press 'q' to quit ".to_string()
+        Line::from(" Connecting to daemon... | '?' keys | 'q' quit ")
     };
 
-    let paragraph = Paragraph::new(status)
-        .style(Style::default().fg(Color::White).bg(Color::DarkGray));
+    let paragraph = Paragraph::new(line)
+        .style(app.theme.status_bar);
 
     f.render_widget(paragraph, area);
 }
 
+/// Format a byte count with a binary unit suffix
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
 /// Format duration in seconds to human-readable string
 fn format_duration(secs: f32) -> String {
     let total_secs = secs as u64;
@@ -455,6 +1803,180 @@ fn format_duration(secs: f32) -> String {
     }
 }
 
+/// Format a Unix time in milliseconds as local weekday and time, e.g. "Thu 02:00"
+fn format_weekday_time(unix_ms: i64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    let secs = unix_ms.div_euclid(1000);
+    let local = secs + utc_offset_secs(secs);
+    let days = local.div_euclid(86_400);
+    let of_day = local.rem_euclid(86_400);
+    // 1970-01-01 was a Thursday
+    let weekday = WEEKDAYS[(days + 4).rem_euclid(7) as usize];
+    format!("{} {:02}:{:02}", weekday, of_day / 3600, (of_day % 3600) / 60)
+}
+
+/// Format a Unix time in milliseconds as local date and time for file
+/// names, e.g. "20261016-142501"
+fn format_file_time(unix_ms: i64) -> String {
+    let secs = unix_ms.div_euclid(1000);
+    let local = secs + utc_offset_secs(secs);
+    let of_day = local.rem_euclid(86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = local.div_euclid(86_400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        (of_day % 3600) / 60,
+        of_day % 60
+    )
+}
+
+/// Offset of local time from UTC at `unix_secs`
+#[cfg(unix)]
+fn utc_offset_secs(unix_secs: i64) -> i64 {
+    let time = unix_secs as libc::time_t;
+    // SAFETY: localtime_r only writes to the tm it is given
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff as i64
+}
+
+/// Offset of local time from UTC; times are shown in UTC off Unix
+#[cfg(not(unix))]
+fn utc_offset_secs(_unix_secs: i64) -> i64 {
+    0
+}
+
+/// Appends the leaves of `value` to `lines` as dotted keys, one per setting;
+/// arrays stay on one line
+fn flatten_config(prefix: &str, value: &serde_json::Value, lines: &mut Vec<(String, String)>) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                // Keys like library roots are quoted as in the TOML file
+                let key = if key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                    key.clone()
+                } else {
+                    format!("{:?}", key)
+                };
+                let path = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_config(&path, value, lines);
+            }
+        }
+        serde_json::Value::Null => lines.push((prefix.to_string(), "(unset)".to_string())),
+        other => lines.push((prefix.to_string(), other.to_string())),
+    }
+}
+
+/// Area of `width` by `height` cells centered in `area`, shrunk to fit
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Render the keybinding overlay, listing [`KEYMAP`] by tab
+fn render_help(f: &mut Frame, area: Rect, app: &App) {
+    let sections = [
+        ("Everywhere", None),
+        ("Dashboard", Some(Tab::Dashboard)),
+        ("Configuration", Some(Tab::Config)),
+    ];
+    let label_width = KEYMAP.iter().map(|b| b.label.len()).max().unwrap_or(0);
+    let mut lines = Vec::new();
+    for (title, tab) in sections {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(title, app.theme.header)));
+        for binding in KEYMAP.iter().filter(|b| b.tab == tab) {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:width$}", binding.label, width = label_width),
+                    app.theme.accent,
+                ),
+                Span::raw(format!("  {}", binding.description)),
+            ]));
+        }
+    }
+
+    let width = lines.iter().map(|line| line.width()).max().unwrap_or(0) as u16 + 4;
+    let area = centered(area, width, lines.len() as u16 + 2);
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Keys (any key closes) "),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// Render the config tab: the daemon's effective configuration
+fn render_config_tab(f: &mut Frame, area: Rect, app: &App) {
+    let key_width = app
+        .config_lines
+        .iter()
+        .map(|(key, _)| key.len())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Line> = app
+        .config_lines
+        .iter()
+        .skip(app.config_scroll)
+        .map(|(key, value)| {
+            let redacted = value.contains("[redacted]");
+            Line::from(vec![
+                Span::styled(format!("{:width$}", key, width = key_width), app.theme.accent),
+                Span::raw(" = "),
+                Span::styled(
+                    value.clone(),
+                    if redacted {
+                        app.theme.muted
+                    } else {
+                        Style::default()
+                    },
+                ),
+            ])
+        })
+        .collect();
+    let title = format!(
+        " Configuration ({} settings, secrets redacted) | Tab: dashboard | j/k scroll ",
+        app.config_lines.len()
+    );
+    let paragraph = if lines.is_empty() {
+        Paragraph::new("Configuration not available from the daemon")
+    } else {
+        Paragraph::new(lines)
+    };
+
+    f.render_widget(
+        paragraph.block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
 
 // ============================================================================
 // Main UI Layout
@@ -470,49 +1992,240 @@ fn ui(f: &mut Frame, app: &App) {
         .constraints([Constraint::Min(0), Constraint::Length(1)])
         .split(size);
 
+    if app.tab == Tab::Config {
+        render_config_tab(f, main_chunks[0], app);
+        render_status_bar(f, main_chunks[1], app);
+        if app.show_help {
+            render_help(f, main_chunks[0], app);
+        }
+        return;
+    }
+
     // Content area: left panel (queue + events) and right panel (system + chart)
     let content_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
         .split(main_chunks[0]);
 
-    // Left panel: queue table on top, event log on bottom
+    // Left panel: queue table on top, cores and disk IO, event log on bottom
     let left_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .constraints([
+            Constraint::Percentage(50),
+            Constraint::Length(8),
+            Constraint::Min(0),
+        ])
         .split(content_chunks[0]);
 
-    // Right panel: gauges, load avg, and throughput chart
+    // Held jobs take up to five lines above the event log, only when present
+    let (approvals_area, event_log_area) = if app.approvals.is_empty() {
+        (None, left_chunks[2])
+    } else {
+        let approval_rows = app.approvals.len().min(5) as u16;
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(approval_rows + 2), Constraint::Min(0)])
+            .split(left_chunks[2]);
+        (Some(chunks[0]), chunks[1])
+    };
+
+    let resource_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+        .split(left_chunks[1]);
+
+    // One line per library root, up to four
+    let library_rows = app
+        .metrics
+        .as_ref()
+        .map_or(0, |m| m.coverage.len())
+        .clamp(1, 4) as u16;
+
+    // Right panel: gauges, load avg, scanner, libraries, and throughput chart
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(6),  // CPU + Memory gauges
             Constraint::Length(5),  // Load averages
+            Constraint::Length(6),  // Scanner status
+            Constraint::Length(library_rows + 2), // Library coverage
             Constraint::Min(0),     // Throughput chart
         ])
         .split(content_chunks[1]);
 
     // Render all widgets
     render_queue_table(f, left_chunks[0], app);
-    render_event_log(f, left_chunks[1], app);
+    render_core_usage(f, resource_chunks[0], app);
+    render_disk_io(f, resource_chunks[1], app);
+    if let Some(area) = approvals_area {
+        render_approvals(f, area, app);
+    }
+    render_event_log(f, event_log_area, app);
     render_system_gauges(f, right_chunks[0], app);
     render_load_averages(f, right_chunks[1], app);
-    render_throughput_chart(f, right_chunks[2], app);
+    render_scan_panel(f, right_chunks[2], app);
+    render_coverage_panel(f, right_chunks[3], app);
+    render_throughput_chart(f, right_chunks[4], app);
     render_status_bar(f, main_chunks[1], app);
+    if app.show_help {
+        render_help(f, main_chunks[0], app);
+    }
 }
 
 // ============================================================================
 // Main Entry Point
 // ============================================================================
 
+/// Show a desktop notification for a job that completed or failed
+#[cfg(feature = "notifications")]
+fn notify_finished(job: JobMetrics) {
+    let name = std::path::Path::new(&job.input_path)
+        .file_name()
+        .map_or_else(|| job.input_path.clone(), |n| n.to_string_lossy().into_owned());
+    let (summary, body) = if job.stage == "completed" {
+        let saved = job.size_in_bytes_before.saturating_sub(job.size_in_bytes_after);
+        (
+            format!("Encoded {}", name),
+            if job.size_in_bytes_after > 0 {
+                format!("Saved {}", format_bytes(saved))
+            } else {
+                String::new()
+            },
+        )
+    } else {
+        (format!("Failed {}", name), job.input_path.clone())
+    };
+    // Sending blocks on the session bus, so keep it off the UI loop
+    tokio::task::spawn_blocking(move || {
+        let _ = notify_rust::Notification::new()
+            .appname("atop")
+            .summary(&summary)
+            .body(&body)
+            .show();
+    });
+}
+
+/// Desktop notifications need the `notifications` feature; `--notify` is
+/// refused without it
+#[cfg(not(feature = "notifications"))]
+fn notify_finished(_job: JobMetrics) {}
+
+/// Base URL of the first daemon found advertising itself over mDNS within
+/// `timeout`, preferring its IPv4 addresses
+fn discover_daemon(timeout: Duration) -> Result<String, String> {
+    let mdns = mdns_sd::ServiceDaemon::new().map_err(|e| format!("mDNS unavailable: {}", e))?;
+    let events = mdns
+        .browse(SERVICE_TYPE)
+        .map_err(|e| format!("mDNS browse failed: {}", e))?;
+    let deadline = Instant::now() + timeout;
+    let mut found = None;
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        let Ok(event) = events.recv_timeout(left) else {
+            break;
+        };
+        if let mdns_sd::ServiceEvent::ServiceResolved(info) = event {
+            let mut addrs: Vec<_> = info.get_addresses().iter().copied().collect();
+            addrs.sort_by_key(|addr| !addr.is_ipv4());
+            if let Some(addr) = addrs.first() {
+                let host = std::net::SocketAddr::new(*addr, info.get_port());
+                found = Some(format!("http://{}", host));
+                break;
+            }
+        }
+    }
+    let _ = mdns.shutdown();
+    found.ok_or_else(|| format!("no daemon advertising {} found", SERVICE_TYPE))
+}
+
+/// Command-line options
+#[derive(Debug, Default)]
+struct Options {
+    theme: ThemeName,
+    notify: bool,
+    /// Daemon API base URL, if given
+    daemon_url: Option<String>,
+    discover: bool,
+}
+
+/// Parse `--theme NAME` (or `--theme=NAME`, default `ATOP_THEME`),
+/// `--notify`, `--daemon HOST:PORT`, and `--discover`
+fn parse_args() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut name = std::env::var("ATOP_THEME").ok();
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        if arg == "--theme" {
+            name = Some(args.next().ok_or("--theme needs a name")?);
+        } else if let Some(value) = arg.strip_prefix("--theme=") {
+            name = Some(value.to_string());
+        } else if arg == "--daemon" {
+            let addr = args.next().ok_or("--daemon needs HOST:PORT")?;
+            options.daemon_url = Some(daemon_url(&addr));
+        } else if let Some(addr) = arg.strip_prefix("--daemon=") {
+            options.daemon_url = Some(daemon_url(addr));
+        } else if arg == "--discover" {
+            options.discover = true;
+        } else if arg == "--notify" {
+            if !cfg!(feature = "notifications") {
+                return Err("--notify needs atop built with the notifications feature".to_string());
+            }
+            options.notify = true;
+        } else {
+            return Err(format!("unknown argument {}", arg));
+        }
+    }
+    if let Some(name) = name {
+        options.theme = ThemeName::parse(&name).ok_or_else(|| {
+            format!("unknown theme {} (default, high-contrast, monochrome)", name)
+        })?;
+    }
+    Ok(options)
+}
+
+/// Base URL for a `--daemon` address, which may include the scheme
+fn daemon_url(addr: &str) -> String {
+    let addr = addr.trim_end_matches('/');
+    if addr.contains("://") {
+        addr.to_string()
+    } else {
+        format!("http://{}", addr)
+    }
+}
+
 #[tokio::main]
 async fn main() -> io::Result<()> {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("atop: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let daemon_url = if options.discover {
+        let timeout = Duration::from_secs(DISCOVER_TIMEOUT_SECS);
+        match tokio::task::spawn_blocking(move || discover_daemon(timeout)).await {
+            Ok(Ok(url)) => Some(url),
+            Ok(Err(e)) => {
+                eprintln!("atop: {}", e);
+                std::process::exit(1);
+            }
+            Err(e) => return Err(io::Error::other(e)),
+        }
+    } else {
+        options.daemon_url
+    };
+
     // Initialize terminal
     let mut terminal = setup_terminal()?;
 
     // Create app state
     let mut app = App::new();
-    app.log_event("AV1 Dashboard started".to_string());
+    app.theme = Theme::new(options.theme, ColorDepth::detect());
+    app.notify = options.notify;
+    if let Some(url) = daemon_url {
+        app.daemon_url = url;
+    }
+    app.log_event(format!("AV1 Dashboard started, daemon at {}", app.daemon_url));
 
     // Run the main loop
     let result = run_app(&mut terminal, &mut app).await;
@@ -530,12 +2243,30 @@ async fn run_app(
 ) -> io::Result<()> {
     let poll_interval = Duration::from_millis(POLL_INTERVAL_MS);
     let mut last_fetch = Instant::now() - poll_interval; // Fetch immediately on start
+    let history_interval = Duration::from_secs(HISTORY_POLL_INTERVAL_SECS);
+    let mut last_history_fetch: Option<Instant> = None;
 
     loop {
         // Fetch metrics if poll interval has elapsed
         if last_fetch.elapsed() >= poll_interval {
             app.fetch_metrics().await;
             last_fetch = Instant::now();
+
+            // Refresh the history right after connecting, then periodically
+            let history_due = last_history_fetch.is_none_or(|t| t.elapsed() >= history_interval);
+            if app.connected && history_due {
+                app.fetch_history().await;
+                app.fetch_approvals().await;
+                app.fetch_queued().await;
+                last_history_fetch = Some(Instant::now());
+            } else {
+                if app.approvals_stale() {
+                    app.fetch_approvals().await;
+                }
+                if app.queued_stale() {
+                    app.fetch_queued().await;
+                }
+            }
         }
 
         // Draw UI
@@ -545,14 +2276,44 @@ async fn run_app(
         if event::poll(Duration::from_millis(50))? {
             if let Event::Key(key) = event::read()? {
                 if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Char('Q') => {
-                            return Ok(());
+                    // While the filter prompt is open, keys edit the filter
+                    if let Some(ref mut input) = app.filter_input {
+                        match key.code {
+                            KeyCode::Enter => app.apply_filter_input(),
+                            KeyCode::Esc => app.filter_input = None,
+                            KeyCode::Backspace => {
+                                input.pop();
+                            }
+                            KeyCode::Char(c) => input.push(c),
+                            _ => {}
                         }
-                        KeyCode::Esc => {
+                        continue;
+                    }
+
+                    // While the note prompt is open, keys edit the note
+                    if let Some(ref mut input) = app.note_input {
+                        match key.code {
+                            KeyCode::Enter => app.submit_note().await,
+                            KeyCode::Esc => app.note_input = None,
+                            KeyCode::Backspace => {
+                                input.pop();
+                            }
+                            KeyCode::Char(c) => input.push(c),
+                            _ => {}
+                        }
+                        continue;
+                    }
+
+                    // While the help overlay is open, any key closes it
+                    if app.show_help {
+                        app.show_help = false;
+                        continue;
+                    }
+
+                    if let Some(action) = action_for(app.tab, key.code) {
+                        if app.perform(action).await {
                             return Ok(());
                         }
-                        _ => {}
                     }
                 }
             }